@@ -0,0 +1,99 @@
+//! Throughput of the directory analysis pipeline.
+//!
+//! Analyzes a generated corpus through [`analyze_into`], once with a sink that only counts
+//! files and once with a sink that serializes every file, which stands in for the
+//! disk-backed accumulators. Files are analyzed by the rayon workers and handed to a single
+//! writer thread, so the second benchmark shows how much a slow consumer holds the
+//! workers back. Compare baselines across commits on a multi-core machine:
+//!
+//! ```sh
+//! cargo bench --bench pipeline -- --save-baseline before
+//! # ... change the pipeline ...
+//! cargo bench --bench pipeline -- --baseline before
+//! ```
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use ruloc_ffi::{AnalysisOptions, AnalysisSink, FileStats, InputSource, RulocError, analyze_into};
+use std::fs;
+use std::hint::black_box;
+use std::path::Path;
+use std::time::Duration;
+
+/// Number of files in the generated corpus.
+const CORPUS_FILES: usize = 2000;
+
+/// Counts the analyzed files.
+struct CountingSink(usize);
+
+impl AnalysisSink for CountingSink {
+    fn file(&mut self, _file_stats: &FileStats) -> Result<(), RulocError> {
+        self.0 += 1;
+        Ok(())
+    }
+}
+
+/// Serializes every analyzed file as a JSON line, like the NDJSON accumulator.
+struct SerializingSink(Vec<u8>);
+
+impl AnalysisSink for SerializingSink {
+    fn file(&mut self, file_stats: &FileStats) -> Result<(), RulocError> {
+        serde_json::to_writer(&mut self.0, file_stats)
+            .map_err(|e| RulocError::Output(format!("Failed to serialize file stats: {}", e)))?;
+        self.0.push(b'\n');
+        Ok(())
+    }
+}
+
+/// Writes a corpus of modules with production code, comments, and a test module each.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to write the files to
+fn write_corpus(dir: &Path) {
+    for i in 0..CORPUS_FILES {
+        let module = dir.join(format!("m{}", i % 20));
+        fs::create_dir_all(&module).unwrap();
+        let mut source =
+            String::from("//! Generated module.\n\nuse std::collections::HashMap;\n\n");
+        for f in 0..20 {
+            source.push_str(&format!(
+                "/// Sums the values of entry {f}.\npub fn entry_{f}(map: &HashMap<u32, u32>) -> u32 {{\n    // Missing keys count as zero\n    map.get(&{f}).copied().unwrap_or(0) + {i}\n}}\n\n"
+            ));
+        }
+        source.push_str(
+            "#[cfg(test)]\nmod tests {\n    use super::*;\n\n    #[test]\n    fn test_entry() {\n        assert_eq!(entry_0(&HashMap::new()), entry_0(&HashMap::new()));\n    }\n}\n",
+        );
+        fs::write(module.join(format!("f{}.rs", i)), source).unwrap();
+    }
+}
+
+/// Benchmarks directory analysis with a cheap and a serializing sink.
+fn bench_pipeline(c: &mut Criterion) {
+    let corpus = tempfile::tempdir().unwrap();
+    write_corpus(corpus.path());
+    let source = InputSource::Directory(corpus.path().to_path_buf());
+    let options = AnalysisOptions::default();
+
+    let mut group = c.benchmark_group("pipeline");
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(20));
+    group.throughput(Throughput::Elements(CORPUS_FILES as u64));
+    group.bench_function("counting_sink", |b| {
+        b.iter(|| {
+            let mut sink = CountingSink(0);
+            black_box(analyze_into(&source, &options, &mut sink).unwrap());
+            assert_eq!(sink.0, CORPUS_FILES);
+        })
+    });
+    group.bench_function("serializing_sink", |b| {
+        b.iter(|| {
+            let mut sink = SerializingSink(Vec::new());
+            black_box(analyze_into(&source, &options, &mut sink).unwrap());
+            black_box(sink.0.len());
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_pipeline);
+criterion_main!(benches);
@@ -0,0 +1,40 @@
+/*
+ * C ABI of ruloc, provided by the ruloc_ffi shared and static libraries when built
+ * with `--features ffi`.
+ *
+ * Every report returned by ruloc_analyze_file must be released with ruloc_free.
+ */
+
+#ifndef RULOC_H
+#define RULOC_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Outcome of analyzing one file. */
+typedef struct RulocReport RulocReport;
+
+/*
+ * Analyzes a single Rust file with the default options.
+ *
+ * Returns null if path is null or not UTF-8. Analysis errors are reported by
+ * ruloc_report_to_json instead.
+ */
+RulocReport *ruloc_analyze_file(const char *path);
+
+/*
+ * Returns the report as JSON: the file statistics as in --out-json reports, or an object
+ * with "error" and "exit-code" fields when the analysis failed. The string is owned by
+ * the report and stays valid until ruloc_free. Returns null if report is null.
+ */
+const char *ruloc_report_to_json(RulocReport *report);
+
+/* Releases a report and its JSON string. Null is ignored. */
+void ruloc_free(RulocReport *report);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* RULOC_H */
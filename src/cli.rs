@@ -0,0 +1,18372 @@
+//! Command-line interface of the `ruloc` binary.
+//!
+//! Parses the arguments, runs the requested analysis or subcommand with the analyzer in
+//! the crate root, and renders and delivers its reports.
+
+use super::*;
+use clap::{CommandFactory, Parser, Subcommand};
+use colored::Colorize;
+use std::io::{BufRead, BufReader, BufWriter};
+use std::ops::RangeInclusive;
+use std::process::ExitCode;
+use tracing::info;
+use tracing_subscriber::{EnvFilter, fmt::MakeWriter, util::SubscriberInitExt};
+
+/// Work-markers counted within comments unless overridden with `--todo-markers`.
+const DEFAULT_TODO_MARKERS: &str = "TODO,FIXME,HACK,XXX";
+
+/// Header markers identifying generated files unless overridden with `--generated-markers`.
+const DEFAULT_GENERATED_MARKERS: &str = "@generated,DO NOT EDIT";
+
+/// Path globs identifying generated files unless overridden with `--generated-globs`.
+const DEFAULT_GENERATED_GLOBS: &str = "*.pb.rs";
+
+/// Width above which `--line-length-stats` counts lines as too long unless overridden with
+/// `--line-width`.
+const DEFAULT_LINE_WIDTH: usize = 100;
+
+/// Buffer size for FileBackedAccumulator writer (8MB).
+const FILE_ACCUMULATOR_BUFFER_SIZE: usize = 8 * 1024 * 1024;
+
+/// Number of files `--accumulator auto` keeps in memory before spilling to disk.
+const AUTO_ACCUMULATOR_MEMORY_FILES: usize = 10_000;
+
+/// Number of spaces for base indentation level in text output formatting.
+const TEXT_OUTPUT_BASE_INDENT: usize = 4;
+
+/// Number of spaces for nested indentation level in text output formatting.
+const TEXT_OUTPUT_NESTED_INDENT: usize = 6;
+
+/// Debug mode marker for production blank lines (Production BLank).
+const DEBUG_MARKER_PRODUCTION_BLANK: &str = "PBL";
+
+/// Debug mode marker for production code lines (Production COde).
+const DEBUG_MARKER_PRODUCTION_CODE: &str = "PCO";
+
+/// Debug mode marker for production comment lines (Production CoMment).
+const DEBUG_MARKER_PRODUCTION_COMMENT: &str = "PCM";
+
+/// Debug mode marker for production rustdoc lines (Production DoC).
+const DEBUG_MARKER_PRODUCTION_RUSTDOC: &str = "PDC";
+
+/// Debug mode marker for test blank lines (Test BLank).
+const DEBUG_MARKER_TEST_BLANK: &str = "TBL";
+
+/// Debug mode marker for test code lines (Test COde).
+const DEBUG_MARKER_TEST_CODE: &str = "TCO";
+
+/// Debug mode marker for test comment lines (Test CoMment).
+const DEBUG_MARKER_TEST_COMMENT: &str = "TCM";
+
+/// Debug mode marker for test rustdoc lines (Test DoC).
+const DEBUG_MARKER_TEST_RUSTDOC: &str = "TDC";
+
+/// Debug mode marker for lines excluded by `// ruloc:ignore-start` (IGNored).
+const DEBUG_MARKER_IGNORED: &str = "IGN";
+
+/// Signed component-wise difference between two `LineStats` instances.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct LineStatsDelta {
+    /// Difference in total line counts.
+    #[serde(rename = "all-lines")]
+    pub all_lines: i64,
+
+    /// Difference in blank line counts.
+    #[serde(rename = "blank-lines")]
+    pub blank_lines: i64,
+
+    /// Difference in comment line counts.
+    #[serde(rename = "comment-lines")]
+    pub comment_lines: i64,
+
+    /// Difference in rustdoc line counts.
+    #[serde(rename = "rustdoc-lines")]
+    pub rustdoc_lines: i64,
+
+    /// Difference in code line counts.
+    #[serde(rename = "code-lines")]
+    pub code_lines: i64,
+}
+
+impl LineStatsDelta {
+    /// Computes `after - before` component-wise.
+    ///
+    /// # Arguments
+    ///
+    /// * `before` - Statistics of the baseline
+    /// * `after` - Statistics compared against the baseline
+    ///
+    /// # Returns
+    ///
+    /// The signed change of every component
+    pub fn between(before: &LineStats, after: &LineStats) -> Self {
+        let diff = |a: usize, b: usize| a as i64 - b as i64;
+        LineStatsDelta {
+            all_lines: diff(after.all_lines, before.all_lines),
+            blank_lines: diff(after.blank_lines, before.blank_lines),
+            comment_lines: diff(after.comment_lines, before.comment_lines),
+            rustdoc_lines: diff(after.rustdoc_lines, before.rustdoc_lines),
+            code_lines: diff(after.code_lines, before.code_lines),
+        }
+    }
+}
+
+/// Comprehensive analysis report encapsulating both aggregate and granular metrics.
+///
+/// Serves as the canonical output structure combining high-level summary statistics
+/// with detailed per-file breakdowns. Designed for serialization to JSON or rendering
+/// as human-readable text output.
+///
+/// # Structure
+///
+/// - **Summary**: Consolidated view of all files, providing immediate insights into
+///   overall codebase composition
+/// - **Files**: Exhaustive list of individual file analyses, preserving granularity
+///   for detailed examination and drill-down analysis
+///
+/// # Serialization
+///
+/// When serialized to JSON, produces a two-section structure ideal for programmatic
+/// consumption by CI/CD tools, static analyzers, or custom reporting pipelines.
+///
+/// The files are a `Vec` unless a writer serializes them straight from an iterator, so
+/// streamed and collected reports share this one definition.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct Report<F = Vec<FileStats>> {
+    /// Aggregate statistical summary spanning all analyzed files.
+    pub summary: Summary,
+
+    /// Ordered collection of per-file statistical analyses.
+    pub files: F,
+
+    /// Source revision that per-file links point to, present only when it was resolved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+
+    /// User-supplied key/value labels tagging this analysis (e.g., `team=payments`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+
+    /// Rust files that no crate root reaches through `mod` declarations, present with
+    /// `--from-entry` (relative to the entry point) or `--dead-files` (workspace-wide).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub orphans: Vec<String>,
+
+    /// Files excluded from the analysis, sorted by path, with the reason for each.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedFile>,
+
+    /// In-code and out-of-code documentation combined, present with `--include-docs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub documentation: Option<DocumentationRollup>,
+
+    /// Line counts of Rust and other common file types, present with `--all-languages`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub languages: Option<LanguageRollup>,
+
+    /// Context of the run that produced the report, present with `--with-metadata`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<RunMetadata>,
+
+    /// Totals extrapolated from the analyzed files, present with `--sample`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<SampleEstimate>,
+}
+
+/// Serializes an iterator as a sequence without collecting it.
+///
+/// The iterator is consumed by the first serialization; serializing again fails.
+struct SerializeIter<I>(std::cell::RefCell<Option<I>>);
+
+impl<I> SerializeIter<I> {
+    /// Wraps an iterator for serialization.
+    fn new(iter: I) -> Self {
+        Self(std::cell::RefCell::new(Some(iter)))
+    }
+}
+
+impl<I> Serialize for SerializeIter<I>
+where
+    I: Iterator,
+    I::Item: Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let iter = self
+            .0
+            .borrow_mut()
+            .take()
+            .ok_or_else(|| serde::ser::Error::custom("sequence was already serialized"))?;
+        serializer.collect_seq(iter)
+    }
+}
+
+/// Context of the run that produced a report, making archived reports self-describing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+struct RunMetadata {
+    /// Version of ruloc that produced the report.
+    pub tool_version: String,
+
+    /// Start of the run as an RFC 3339 UTC timestamp.
+    pub timestamp: String,
+
+    /// Canonical directory that reported paths are relative to.
+    pub analysis_root: String,
+
+    /// Commit checked out in the analysis root, when it is inside a git repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+
+    /// Branch checked out in the analysis root, absent for detached checkouts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_branch: Option<String>,
+
+    /// Wall time from the start of the run until the report was rendered.
+    pub duration_ms: u64,
+
+    /// Command-line arguments of the run, excluding the program name.
+    pub args: Vec<String>,
+}
+
+impl RunMetadata {
+    /// Captures the context of a run that is about to start.
+    ///
+    /// # Arguments
+    ///
+    /// * `analysis_root` - Directory that reported paths are relative to
+    ///
+    /// # Returns
+    ///
+    /// Metadata with a zero duration, to be filled in when the report is rendered
+    fn capture(analysis_root: &Path) -> Self {
+        let root = fs::canonicalize(analysis_root).unwrap_or_else(|_| analysis_root.to_path_buf());
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+
+        Self {
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            timestamp: format_utc_timestamp(now),
+            analysis_root: display_path(&root),
+            git_commit: run_git(&root, &["rev-parse", "HEAD"]).ok(),
+            git_branch: run_git(&root, &["rev-parse", "--abbrev-ref", "HEAD"])
+                .ok()
+                .filter(|branch| branch != "HEAD"),
+            duration_ms: 0,
+            args: std::env::args().skip(1).collect(),
+        }
+    }
+}
+
+/// Formats seconds since the Unix epoch as an RFC 3339 UTC timestamp.
+///
+/// # Arguments
+///
+/// * `seconds` - Seconds elapsed since 1970-01-01T00:00:00Z
+///
+/// # Returns
+///
+/// A timestamp such as `2024-03-01T12:00:00Z`
+fn format_utc_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let time = seconds % 86_400;
+
+    // Civil-from-days conversion over 400-year eras of the proleptic Gregorian calendar
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60
+    )
+}
+
+/// Documentation roll-up combining rustdoc lines with per-crate markdown documentation.
+///
+/// Gives documentation-health dashboards a single `lines` figure covering both in-code
+/// and out-of-code docs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct DocumentationRollup {
+    /// Rustdoc lines across all analyzed files.
+    #[serde(rename = "rustdoc-lines")]
+    pub rustdoc_lines: usize,
+
+    /// Markdown documentation summed over all crates.
+    pub markdown: MarkdownDocs,
+
+    /// Rustdoc lines plus non-blank markdown lines.
+    pub lines: usize,
+
+    /// Markdown documentation per crate directory.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub crates: BTreeMap<String, MarkdownDocs>,
+}
+
+impl DocumentationRollup {
+    /// Combines the analysis' rustdoc lines with per-crate markdown counts.
+    ///
+    /// # Arguments
+    ///
+    /// * `rustdoc_lines` - Rustdoc lines across all analyzed files
+    /// * `crates` - Markdown documentation per crate directory
+    ///
+    /// # Returns
+    ///
+    /// The roll-up with markdown totals and the combined line count
+    fn new(rustdoc_lines: usize, crates: BTreeMap<String, MarkdownDocs>) -> Self {
+        let mut markdown = MarkdownDocs::default();
+        for docs in crates.values() {
+            markdown.add(docs);
+        }
+        Self {
+            rustdoc_lines,
+            lines: rustdoc_lines + markdown.lines,
+            markdown,
+            crates,
+        }
+    }
+}
+
+/// Repository-wide line counts across Rust and other common file types, with
+/// `--all-languages`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct LanguageRollup {
+    /// Lines of every counted file, whatever its language.
+    pub total: LanguageLines,
+
+    /// Lines per language name; rustdoc lines count as Rust comments.
+    pub languages: BTreeMap<String, LanguageLines>,
+}
+
+impl LanguageRollup {
+    /// Combines the analysis' Rust totals with the counts of other languages.
+    ///
+    /// # Arguments
+    ///
+    /// * `summary` - Summary supplying the Rust line counts
+    /// * `others` - Line counts per language for the non-Rust files
+    ///
+    /// # Returns
+    ///
+    /// The roll-up with a Rust entry, when Rust files were analyzed, and the overall total
+    fn new(summary: &Summary, mut others: BTreeMap<String, LanguageLines>) -> Self {
+        if summary.files > 0 {
+            let rust = &summary.total;
+            others.insert(
+                "Rust".to_string(),
+                LanguageLines {
+                    files: summary.files,
+                    all_lines: rust.all_lines,
+                    blank_lines: rust.blank_lines,
+                    comment_lines: rust.comment_lines + rust.rustdoc_lines,
+                    code_lines: rust.code_lines,
+                },
+            );
+        }
+        let mut total = LanguageLines::default();
+        for lines in others.values() {
+            total.add(lines);
+        }
+        Self {
+            total,
+            languages: others,
+        }
+    }
+}
+
+/// High-performance in-memory statistics accumulator optimized for small to medium codebases.
+///
+/// Maintains all file statistics in a contiguous `Vec`, providing optimal iteration performance
+/// and zero I/O overhead. Suitable for projects with manageable file counts where memory
+/// consumption remains within acceptable bounds.
+///
+/// # Performance Characteristics
+///
+/// - **Time Complexity**: O(1) for `add_file`, O(1) for `get_summary`, O(n) for `iter_files`
+/// - **Space Complexity**: O(n) where n is the number of accumulated files
+/// - **Memory Footprint**: Proportional to total number of files × sizeof(`FileStats`)
+///
+/// # Recommended Usage
+///
+/// - Projects with fewer than 10,000 files
+/// - Environments with ample available RAM
+/// - Scenarios requiring high-speed iteration over file statistics
+///
+/// # Alternative
+///
+/// For large-scale analyses, consider [`FileBackedAccumulator`] which trades CPU/memory
+/// efficiency for unbounded scalability via disk-backed storage.
+struct InMemoryAccumulator {
+    /// Rolling summary statistics maintained incrementally.
+    summary: Summary,
+
+    /// Chronologically ordered collection of all accumulated file statistics.
+    files: Vec<FileStats>,
+}
+
+impl Default for InMemoryAccumulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InMemoryAccumulator {
+    /// Constructs a pristine accumulator with zero accumulated statistics.
+    ///
+    /// Initializes internal data structures with optimal default capacity,
+    /// ready to receive file statistics via `add_file()`.
+    ///
+    /// # Returns
+    ///
+    /// Fresh `InMemoryAccumulator` instance with empty summary and zero files
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut acc = InMemoryAccumulator::new();
+    /// assert_eq!(acc.get_summary().files, 0);
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            summary: Summary::default(),
+            files: Vec::new(),
+        }
+    }
+}
+
+impl StatsAccumulator for InMemoryAccumulator {
+    fn add_file(&mut self, file_stats: &FileStats) -> Result<(), RulocError> {
+        self.summary.add_file(file_stats);
+        self.files.push(file_stats.clone());
+        Ok(())
+    }
+
+    fn get_summary(&self) -> Summary {
+        self.summary.clone()
+    }
+
+    fn iter_files(&self) -> Result<Box<dyn Iterator<Item = FileStats>>, RulocError> {
+        Ok(Box::new(self.files.clone().into_iter()))
+    }
+}
+
+/// Scalable disk-backed statistics accumulator for unbounded codebase analysis.
+///
+/// Employs a streaming architecture that persists file statistics to a temporary file
+/// in [JSON Lines format](http://jsonlines.org/), retaining only aggregate summaries
+/// in memory. This design enables analysis of arbitrarily large codebases—including
+/// projects with millions of files—without risking memory exhaustion.
+///
+/// # Architecture
+///
+/// - **Streaming Writes**: File statistics serialized and appended immediately upon `add_file()`
+/// - **Buffered I/O**: 8MB write buffer minimizes syscall overhead
+/// - **Automatic Cleanup**: Temporary file deleted automatically via RAII when dropped
+/// - **JSON Lines Format**: One complete JSON object per line, facilitating line-oriented processing
+///
+/// # Performance Considerations
+///
+/// - **Memory**: O(1) - only summary statistics retained in RAM
+/// - **Disk I/O**: Sequential writes optimized for modern SSD/HDD characteristics
+/// - **Iteration**: Requires sequential read-through of temporary file
+///
+/// # Use Cases
+///
+/// - Analyzing monolithic monorepos with extensive file counts
+/// - CI/CD environments with constrained memory allocations
+/// - Historical analysis across thousands of revisions
+struct FileBackedAccumulator {
+    /// In-memory rolling summary, incrementally updated with each file.
+    summary: Summary,
+
+    /// Self-deleting temporary file handle for persistent statistics storage.
+    temp_file: NamedTempFile,
+
+    /// High-capacity buffered writer minimizing I/O syscalls.
+    writer: BufWriter<std::fs::File>,
+}
+
+impl FileBackedAccumulator {
+    /// Constructs a new disk-backed accumulator with ephemeral temporary storage.
+    ///
+    /// Allocates a system-managed temporary file for statistics persistence and initializes
+    /// an 8MB write buffer for optimal I/O performance. The temporary file is automatically
+    /// cleaned up upon object destruction, ensuring no disk space leakage.
+    ///
+    /// # Returns
+    ///
+    /// Initialized `FileBackedAccumulator` ready to receive statistics, or an error
+    /// if system resources are unavailable
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if:
+    /// - Temporary file creation fails (insufficient disk space, permission issues)
+    /// - File descriptor limits are exceeded
+    /// - Temporary directory is inaccessible
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let mut acc = FileBackedAccumulator::new()?;
+    /// // Accumulator ready for use with automatic cleanup on drop
+    /// ```
+    pub fn new() -> Result<Self, RulocError> {
+        let temp_file = NamedTempFile::new().map_err(RulocError::io(
+            "Failed to create temporary file for accumulator. Ensure adequate disk space and write permissions in temp directory",
+        ))?;
+
+        let file = temp_file.reopen().map_err(RulocError::io(format!(
+            "Failed to open temporary file '{}' for writing",
+            temp_file.path().display()
+        )))?;
+
+        let writer = BufWriter::with_capacity(FILE_ACCUMULATOR_BUFFER_SIZE, file);
+
+        Ok(Self {
+            summary: Summary::default(),
+            temp_file,
+            writer,
+        })
+    }
+
+    /// Flushes any buffered data to the temporary file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the flush operation fails
+    fn flush(&mut self) -> Result<(), RulocError> {
+        self.writer
+            .flush()
+            .map_err(RulocError::io("Failed to flush writer"))
+    }
+}
+
+impl StatsAccumulator for FileBackedAccumulator {
+    fn add_file(&mut self, file_stats: &FileStats) -> Result<(), RulocError> {
+        self.summary.add_file(file_stats);
+
+        // Serialize as JSON and write with newline (JSON Lines format)
+        let json = serde_json::to_string(file_stats)
+            .map_err(|e| RulocError::Output(format!("Failed to serialize file stats: {}", e)))?;
+
+        writeln!(self.writer, "{}", json)
+            .map_err(RulocError::io("Failed to write to temporary file"))?;
+
+        Ok(())
+    }
+
+    fn get_summary(&self) -> Summary {
+        self.summary.clone()
+    }
+
+    fn iter_files(&self) -> Result<Box<dyn Iterator<Item = FileStats>>, RulocError> {
+        // Flush any pending writes
+        // Note: We can't call self.flush() here because of borrowing rules,
+        // so we need to ensure flush is called before iter_files
+
+        // Open the temp file for reading
+        let file = std::fs::File::open(self.temp_file.path())
+            .map_err(RulocError::io("Failed to open temporary file for reading"))?;
+
+        let reader = BufReader::new(file);
+
+        // Create an iterator that reads JSON lines
+        let iter = reader.lines().filter_map(|line| match line {
+            Ok(line_str) => match serde_json::from_str::<FileStats>(&line_str) {
+                Ok(stats) => Some(stats),
+                Err(e) => {
+                    debug!("Failed to deserialize line: {}", e);
+                    None
+                }
+            },
+            Err(e) => {
+                debug!("Failed to read line: {}", e);
+                None
+            }
+        });
+
+        Ok(Box::new(iter))
+    }
+}
+
+/// Storage strategy for accumulated file statistics, selected with `--accumulator`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum AccumulatorKind {
+    /// Keep every file in memory ([`InMemoryAccumulator`]).
+    Memory,
+
+    /// Stream every file to a temporary file ([`FileBackedAccumulator`]).
+    Disk,
+
+    /// Start in memory and spill to disk beyond [`AUTO_ACCUMULATOR_MEMORY_FILES`] files.
+    #[default]
+    Auto,
+}
+
+/// Accumulator that starts in memory and moves to disk once it holds too many files.
+///
+/// Disk accumulation costs a temporary file and a serialization round trip per file,
+/// which dominates for small projects, so files stay in an [`InMemoryAccumulator`] until
+/// the spill threshold is exceeded. At that point everything accumulated so far is
+/// written to a [`FileBackedAccumulator`], which receives all further files.
+struct SpillingAccumulator {
+    /// Files accumulated before spilling.
+    memory: InMemoryAccumulator,
+
+    /// Disk storage, present once spilled.
+    disk: Option<FileBackedAccumulator>,
+
+    /// Number of files held in memory before spilling; `None` never spills.
+    threshold: Option<usize>,
+}
+
+impl SpillingAccumulator {
+    /// Creates an accumulator following a storage strategy.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Whether to stay in memory, go straight to disk, or spill automatically
+    ///
+    /// # Returns
+    ///
+    /// An accumulator with no files
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the disk strategy's temporary file cannot be created
+    fn new(kind: AccumulatorKind) -> Result<Self, RulocError> {
+        let (disk, threshold) = match kind {
+            AccumulatorKind::Memory => (None, None),
+            AccumulatorKind::Disk => (Some(FileBackedAccumulator::new()?), None),
+            AccumulatorKind::Auto => (None, Some(AUTO_ACCUMULATOR_MEMORY_FILES)),
+        };
+        Ok(Self {
+            memory: InMemoryAccumulator::new(),
+            disk,
+            threshold,
+        })
+    }
+
+    /// Flushes buffered disk writes so the files can be iterated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the flush operation fails
+    fn flush(&mut self) -> Result<(), RulocError> {
+        match &mut self.disk {
+            Some(disk) => disk.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl StatsAccumulator for SpillingAccumulator {
+    fn add_file(&mut self, file_stats: &FileStats) -> Result<(), RulocError> {
+        if let Some(disk) = &mut self.disk {
+            return disk.add_file(file_stats);
+        }
+
+        self.memory.add_file(file_stats)?;
+        if self
+            .threshold
+            .is_some_and(|threshold| self.memory.files.len() > threshold)
+        {
+            debug!(
+                "Spilling {} accumulated files to disk",
+                self.memory.files.len()
+            );
+            let mut disk = FileBackedAccumulator::new()?;
+            for file in std::mem::take(&mut self.memory).files {
+                disk.add_file(&file)?;
+            }
+            self.disk = Some(disk);
+        }
+        Ok(())
+    }
+
+    fn get_summary(&self) -> Summary {
+        match &self.disk {
+            Some(disk) => disk.get_summary(),
+            None => self.memory.get_summary(),
+        }
+    }
+
+    fn iter_files(&self) -> Result<Box<dyn Iterator<Item = FileStats>>, RulocError> {
+        match &self.disk {
+            Some(disk) => disk.iter_files(),
+            None => self.memory.iter_files(),
+        }
+    }
+}
+
+/// Streaming accumulator that writes each file's statistics as an NDJSON record.
+///
+/// Unlike the other accumulators, file statistics are not retained: each record is
+/// written and flushed to the underlying writer as soon as the file is added, and only
+/// the rolling summary is kept in memory. The stream is terminated with either a
+/// `summary` record ([`NdjsonAccumulator::finish`]) or an `incomplete` trailer
+/// ([`NdjsonAccumulator::abort`]).
+struct NdjsonAccumulator<W: Write + Send> {
+    /// In-memory rolling summary, incrementally updated with each file.
+    summary: Summary,
+
+    /// Destination of the NDJSON stream (typically stdout).
+    writer: W,
+
+    /// Presentation options applied to every record (links, labels).
+    options: OutputOptions,
+}
+
+impl<W: Write + Send> NdjsonAccumulator<W> {
+    /// Constructs a streaming accumulator writing to the given destination.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Destination of the NDJSON stream
+    /// * `options` - Presentation options applied to every record
+    fn new(writer: W, options: OutputOptions) -> Self {
+        Self {
+            summary: Summary::default(),
+            writer,
+            options,
+        }
+    }
+
+    /// Serializes a record as one line and flushes it so consumers see it immediately.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization or writing fails
+    fn write_record(&mut self, record: &NdjsonRecord) -> Result<(), RulocError> {
+        let json = serde_json::to_string(record)
+            .map_err(|e| RulocError::Output(format!("Failed to serialize NDJSON record: {}", e)))?;
+        writeln!(self.writer, "{}", json)
+            .and_then(|_| self.writer.flush())
+            .map_err(RulocError::io("Failed to write NDJSON record"))
+    }
+
+    /// Terminates a successful stream with the `summary` record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record cannot be written
+    fn finish(mut self) -> Result<(), RulocError> {
+        let mut summary = self.summary.clone();
+        self.options.normalize_summary(&mut summary);
+        let labels = self.options.labels.clone();
+        let orphans = self.options.orphans.clone();
+        let skipped = self.options.skipped.clone();
+        let metadata = self.options.metadata();
+        self.write_record(&NdjsonRecord::Summary {
+            summary: &summary,
+            revision: self.options.revision(),
+            labels: &labels,
+            orphans: &orphans,
+            skipped: &skipped,
+            documentation: self.options.documentation(&summary),
+            languages: self.options.languages(&summary),
+            metadata: metadata.as_ref(),
+        })
+    }
+
+    /// Terminates a failed stream with an `incomplete` trailer describing the error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the trailer cannot be written
+    fn abort(mut self, error: &str) -> Result<(), RulocError> {
+        let files = self.summary.files;
+        self.write_record(&NdjsonRecord::Incomplete { error, files })
+    }
+}
+
+impl<W: Write + Send> StatsAccumulator for NdjsonAccumulator<W> {
+    fn add_file(&mut self, file_stats: &FileStats) -> Result<(), RulocError> {
+        self.summary.add_file(file_stats);
+        let file = self.options.decorate(file_stats.clone());
+        self.write_record(&NdjsonRecord::File(&file))
+    }
+
+    fn get_summary(&self) -> Summary {
+        self.summary.clone()
+    }
+
+    fn iter_files(&self) -> Result<Box<dyn Iterator<Item = FileStats>>, RulocError> {
+        Err(RulocError::Output(
+            "NDJSON output streams file statistics without retaining them".to_string(),
+        ))
+    }
+}
+
+/// Column header of the `--stream-csv` output.
+const CSV_STREAM_HEADER: &str = "path,all-lines,blank-lines,comment-lines,rustdoc-lines,code-lines,\
+production-all-lines,production-code-lines,test-all-lines,test-code-lines";
+
+/// Accumulator decorator that writes a CSV row for each file as soon as it is added.
+///
+/// Rows are flushed immediately so that long runs can be monitored with `tail -f` and
+/// partially ingested if aborted. The stream ends with a `# complete` or `# incomplete`
+/// comment line; a stream without either was interrupted before it could be terminated.
+struct CsvStreamAccumulator<'a, A: StatsAccumulator> {
+    /// Accumulator receiving the statistics after they are streamed.
+    inner: &'a mut A,
+
+    /// Destination of the CSV stream.
+    writer: BufWriter<std::fs::File>,
+}
+
+impl<'a, A: StatsAccumulator> CsvStreamAccumulator<'a, A> {
+    /// Creates the CSV stream file, writes its header, and wraps an accumulator.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created or written
+    fn create(path: &Path, inner: &'a mut A) -> Result<Self, RulocError> {
+        let file = std::fs::File::create(path).map_err(RulocError::io(format!(
+            "Failed to create CSV stream '{}'",
+            path.display()
+        )))?;
+        let mut stream = Self {
+            inner,
+            writer: BufWriter::new(file),
+        };
+        stream.write_line(CSV_STREAM_HEADER)?;
+        Ok(stream)
+    }
+
+    /// Writes and flushes one line of the stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails
+    fn write_line(&mut self, line: &str) -> Result<(), RulocError> {
+        writeln!(self.writer, "{}", line)
+            .and_then(|_| self.writer.flush())
+            .map_err(RulocError::io("Failed to write CSV stream"))
+    }
+
+    /// Terminates the stream with a trailer recording whether the run completed.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error that ended the run, if it failed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the trailer cannot be written
+    fn finish(mut self, error: Option<&String>) -> Result<(), RulocError> {
+        let files = self.inner.get_summary().files;
+        let trailer = match error {
+            Some(error) => format!(
+                "# incomplete: {} files before error: {}",
+                files,
+                error.replace(['\n', '\r'], " ")
+            ),
+            None => format!("# complete: {} files", files),
+        };
+        self.write_line(&trailer)
+    }
+}
+
+impl<A: StatsAccumulator> StatsAccumulator for CsvStreamAccumulator<'_, A> {
+    fn add_file(&mut self, file_stats: &FileStats) -> Result<(), RulocError> {
+        self.write_line(&format_csv_row(file_stats))?;
+        self.inner.add_file(file_stats)
+    }
+
+    fn get_summary(&self) -> Summary {
+        self.inner.get_summary()
+    }
+
+    fn iter_files(&self) -> Result<Box<dyn Iterator<Item = FileStats>>, RulocError> {
+        self.inner.iter_files()
+    }
+}
+
+/// Quotes a CSV field when it contains separators, quotes, or line breaks.
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Formats a file's statistics as one `--stream-csv` row matching [`CSV_STREAM_HEADER`].
+fn format_csv_row(file: &FileStats) -> String {
+    format!(
+        "{},{},{},{},{},{},{},{},{},{}",
+        escape_csv_field(&file.path),
+        file.total.all_lines,
+        file.total.blank_lines,
+        file.total.comment_lines,
+        file.total.rustdoc_lines,
+        file.total.code_lines,
+        file.production.all_lines,
+        file.production.code_lines,
+        file.test.all_lines,
+        file.test.code_lines
+    )
+}
+
+/// Serialization format selector for statistical output.
+///
+/// Determines the encoding and structure of analysis results, enabling consumption
+/// by both human readers and automated tooling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum OutputFormat {
+    /// Human-readable hierarchical text format with indented structure (default).
+    ///
+    /// Optimized for terminal display and manual inspection, presenting statistics
+    /// in a tree-like layout with clear visual hierarchy.
+    Text,
+
+    /// Machine-parseable JSON format conforming to the [`Report`] schema.
+    ///
+    /// Suitable for integration with CI/CD pipelines, static analysis tools,
+    /// and custom reporting dashboards. Pretty-printed for readability.
+    Json,
+
+    /// Newline-delimited JSON streamed as files are analyzed (see [`NdjsonAccumulator`]).
+    ///
+    /// Emits one `file` record per analyzed file followed by a final `summary` record,
+    /// keeping memory flat for very large runs.
+    Ndjson,
+}
+
+/// A report written from the accumulated statistics once the analysis is complete.
+///
+/// Several formats can be written in one run (e.g. `--out-json report.json --out-html
+/// report.html --out-text`); each is fed by [`output_reports`] from a single pass over the
+/// accumulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ReportKind {
+    /// The aligned summary table of `--out-text`, the default.
+    Table,
+
+    /// The detailed indented report of `--out-text-verbose`.
+    Text,
+
+    /// The JSON report of `--out-json`, also delivered to `--sink`s.
+    Json,
+
+    /// The standalone HTML page of `--out-html`.
+    Html,
+}
+
+impl ReportKind {
+    /// Writes the report in this format.
+    ///
+    /// # Arguments
+    ///
+    /// * `summary` - Summary of the analysis
+    /// * `files` - File statistics in presentation order
+    /// * `options` - Presentation options, including the destination of the report
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the report cannot be rendered or written
+    fn write(
+        self,
+        summary: Summary,
+        files: impl Iterator<Item = FileStats>,
+        options: &OutputOptions,
+    ) -> Result<(), RulocError> {
+        match self {
+            ReportKind::Table => output_table(summary, files, options),
+            ReportKind::Text => output_text(summary, files, options),
+            ReportKind::Json => output_json(summary, files, options),
+            ReportKind::Html => output_html(summary, files, options),
+        }
+    }
+
+    /// Media type of the report, as announced to HTTP and object store sinks.
+    fn content_type(self) -> &'static str {
+        match self {
+            ReportKind::Table | ReportKind::Text => "text/plain; charset=utf-8",
+            ReportKind::Json => "application/json",
+            ReportKind::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+/// A requested report and where it is written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReportTarget {
+    /// Format of the report.
+    format: ReportKind,
+
+    /// File receiving the report, or `None` for stdout.
+    path: Option<PathBuf>,
+}
+
+/// A single line of `--out-ndjson` output, discriminated by its `type` field.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum NdjsonRecord<'a> {
+    /// Statistics for one analyzed file, emitted as soon as the file finishes.
+    File(&'a FileStats),
+
+    /// Aggregate statistics, emitted last when the run completes successfully.
+    Summary {
+        /// Aggregate statistics across all streamed files.
+        #[serde(flatten)]
+        summary: &'a Summary,
+
+        /// Revision the links point to, when `--link-base` is used.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        revision: Option<String>,
+
+        /// User-supplied `--label` tags.
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        labels: &'a BTreeMap<String, String>,
+
+        /// Files unreachable from the `--from-entry` module graph or found by `--dead-files`.
+        #[serde(skip_serializing_if = "<[String]>::is_empty")]
+        orphans: &'a [String],
+
+        /// Files excluded from the analysis, with the reason for each.
+        #[serde(skip_serializing_if = "<[SkippedFile]>::is_empty")]
+        skipped: &'a [SkippedFile],
+
+        /// Documentation roll-up, when `--include-docs` is used.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        documentation: Option<DocumentationRollup>,
+
+        /// Language roll-up, when `--all-languages` is used.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        languages: Option<LanguageRollup>,
+
+        /// Context of the run, when `--with-metadata` is used.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        metadata: Option<&'a RunMetadata>,
+    },
+
+    /// Trailer emitted in place of the summary when the run fails part-way, so that
+    /// consumers can tell a truncated stream from a complete one.
+    Incomplete {
+        /// Description of the failure that ended the run.
+        error: &'a str,
+
+        /// Number of file records emitted before the failure.
+        files: usize,
+    },
+}
+
+/// Placeholder within a `--link-base` template that is substituted with the resolved revision.
+const LINK_BASE_REVISION_PLACEHOLDER: &str = "<rev>";
+
+/// Resolved configuration for rendering file paths as revision-pinned hyperlinks.
+///
+/// Combines a base URL (typically a repository browser such as
+/// `https://github.com/org/repo/blob/<rev>/`) with the analysis root, so that every
+/// reported file can be localized to a workspace-relative URI. When the template carries
+/// the `<rev>` placeholder, it is substituted with the commit currently checked out in the
+/// analysis root, pinning links to the exact revision that was measured.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LinkBase {
+    /// Base URL with any revision placeholder already substituted, ending in `/`.
+    base: String,
+
+    /// Revision substituted into the template, if the template referenced one.
+    revision: Option<String>,
+
+    /// Analysis root against which file paths are relativized.
+    root: PathBuf,
+}
+
+impl LinkBase {
+    /// Resolves a link template against an analysis root using git metadata.
+    ///
+    /// Paths are relativized against the root of the enclosing git working tree so that
+    /// links match the repository layout regardless of which subdirectory was analyzed;
+    /// outside a repository, the analysis root itself is used.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - Base URL, optionally containing the `<rev>` placeholder
+    /// * `analysis_root` - Directory that was analyzed (or contains the analyzed file)
+    ///
+    /// # Returns
+    ///
+    /// A fully resolved `LinkBase`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template references `<rev>` but the revision cannot be
+    /// determined (e.g., git is unavailable or the root is not inside a repository)
+    fn resolve(template: &str, analysis_root: &Path) -> Result<Self, RulocError> {
+        let revision = if template.contains(LINK_BASE_REVISION_PLACEHOLDER) {
+            Some(run_git(analysis_root, &["rev-parse", "HEAD"])?)
+        } else {
+            None
+        };
+
+        let root = run_git(analysis_root, &["rev-parse", "--show-toplevel"])
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| analysis_root.to_path_buf());
+        let root = fs::canonicalize(&root).unwrap_or(root);
+
+        Ok(Self::with_revision(template, revision, &root))
+    }
+
+    /// Builds a `LinkBase` from a template and an already known revision.
+    ///
+    /// # Arguments
+    ///
+    /// * `template` - Base URL, optionally containing the `<rev>` placeholder
+    /// * `revision` - Revision to substitute for the placeholder, if any
+    /// * `root` - Directory against which file paths are relativized
+    ///
+    /// # Returns
+    ///
+    /// A `LinkBase` whose base URL is guaranteed to end with `/`
+    fn with_revision(template: &str, revision: Option<String>, root: &Path) -> Self {
+        let mut base = match &revision {
+            Some(rev) => template.replace(LINK_BASE_REVISION_PLACEHOLDER, rev),
+            None => template.to_string(),
+        };
+        if !base.ends_with('/') {
+            base.push('/');
+        }
+
+        Self {
+            base,
+            revision,
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// Computes the hyperlink for a reported file path.
+    ///
+    /// Resolves the path to its canonical form when it exists on disk, strips the root
+    /// prefix, and joins the remaining components with `/`, yielding URLs that are
+    /// identical across platforms.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path as recorded in [`FileStats::path`]
+    ///
+    /// # Returns
+    ///
+    /// The absolute URL for the file
+    fn url_for(&self, path: &str) -> String {
+        format!("{}{}", self.base, root_relative_path(&self.root, path))
+    }
+}
+
+/// How reported file paths are written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PathStyle {
+    /// Relative to the analysis root.
+    Relative,
+
+    /// Absolute, without resolving symbolic links.
+    Absolute,
+}
+
+/// Rewrites reported file paths into one style with `/` separators.
+///
+/// Paths are recorded exactly as they were reached during analysis, so the same file
+/// may otherwise appear as `./src/a.rs` on one machine and `/ci/build/src/a.rs` on
+/// another. Normalizing them makes reports diffable across machines and platforms.
+#[derive(Debug, Clone)]
+struct PathNormalizer {
+    /// Target style.
+    style: PathStyle,
+
+    /// Directory that relative paths are expressed against.
+    root: PathBuf,
+}
+
+impl PathNormalizer {
+    /// Normalizes a reported path.
+    ///
+    /// Relative paths are stripped of the root prefix as given, falling back to the
+    /// canonical forms of both when the path was reached another way. Paths outside the
+    /// root are left as they are, apart from their separators.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path as recorded in [`FileStats::path`]
+    ///
+    /// # Returns
+    ///
+    /// The path in the configured style with `/` separators
+    fn normalize(&self, path: &str) -> String {
+        let original = Path::new(path);
+        let normalized = match self.style {
+            PathStyle::Relative => original
+                .strip_prefix(&self.root)
+                .ok()
+                .map(Path::to_path_buf)
+                .or_else(|| {
+                    let canonical = fs::canonicalize(original).ok()?;
+                    let root = fs::canonicalize(&self.root).ok()?;
+                    canonical.strip_prefix(root).ok().map(Path::to_path_buf)
+                })
+                .filter(|relative| !relative.as_os_str().is_empty())
+                .unwrap_or_else(|| original.to_path_buf()),
+            PathStyle::Absolute => {
+                std::path::absolute(original).unwrap_or_else(|_| original.to_path_buf())
+            }
+        };
+
+        display_path(&normalized)
+    }
+}
+
+/// Age buckets reported by `--age-buckets`, from most to least recently modified.
+const AGE_BUCKETS: [&str; 4] = [
+    "under-3-months",
+    "3-to-12-months",
+    "over-1-year",
+    "untracked",
+];
+
+/// Streams blobs out of the object database through one `git cat-file --batch` process.
+///
+/// # Arguments
+///
+/// * `dir` - Directory inside the repository
+/// * `ids` - Object ids of the blobs to read
+/// * `visit` - Called with the index of each blob in `ids` and its content, with invalid
+///   UTF-8 replaced
+///
+/// # Errors
+///
+/// Returns an error if git cannot be executed, an object is missing, or its output cannot be
+/// read
+fn read_blobs<'a>(
+    dir: &Path,
+    ids: impl IntoIterator<Item = &'a str>,
+    mut visit: impl FnMut(usize, String),
+) -> Result<(), RulocError> {
+    use std::io::Read;
+
+    let requests: Vec<&str> = ids.into_iter().collect();
+    if requests.is_empty() {
+        return Ok(());
+    }
+    let input: String = requests.iter().map(|id| format!("{}\n", id)).collect();
+
+    let mut child = std::process::Command::new("git")
+        .args(["cat-file", "--batch"])
+        .current_dir(dir)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            RulocError::Git(format!(
+                "Failed to run git: {}. Ensure git is installed and on PATH.",
+                e
+            ))
+        })?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let read_error =
+        |e: std::io::Error| RulocError::Git(format!("Failed to read from git cat-file: {}", e));
+
+    // Requests are written from a second thread so git never blocks on a full pipe; dropping
+    // the reader early makes git exit, which in turn ends the writer.
+    let read = std::thread::scope(|scope| {
+        scope.spawn(move || stdin.write_all(input.as_bytes()));
+        let mut header = String::new();
+        for (index, id) in requests.iter().enumerate() {
+            header.clear();
+            stdout.read_line(&mut header).map_err(read_error)?;
+            let size = match header.split_whitespace().collect::<Vec<_>>()[..] {
+                [_, "blob", size] => size.parse::<usize>().ok(),
+                _ => None,
+            }
+            .ok_or_else(|| RulocError::Git(format!("git object {} is not a readable blob", id)))?;
+
+            let mut content = vec![0; size + 1];
+            stdout.read_exact(&mut content).map_err(read_error)?;
+            content.truncate(size);
+            visit(index, String::from_utf8_lossy(&content).into_owned());
+        }
+        drop(stdout);
+        Ok(())
+    });
+
+    let status = child.wait().map_err(read_error)?;
+    read?;
+    if !status.success() {
+        return Err(RulocError::Git(format!(
+            "git cat-file --batch failed in '{}'",
+            dir.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Rust sources of a directory at a git revision, read from the object database.
+///
+/// The tree is listed with `git ls-tree -z`, so unusual file names arrive unquoted, and all
+/// contents are streamed through [`read_blobs`] instead of one `git show` per file. The
+/// manifests at the same revision decide each file's edition, following the rules of
+/// [`EditionResolver`].
+struct RevisionSources {
+    /// Root of the git repository.
+    toplevel: PathBuf,
+
+    /// Object id and repository-relative path of each Rust source below the prefix, in tree
+    /// order.
+    sources: Vec<(String, String)>,
+
+    /// Repository-relative directories below the prefix that contain a `Cargo.toml`.
+    crate_dirs: Vec<String>,
+
+    /// Parsed manifests of the whole revision keyed by repository-relative directory, empty
+    /// for the root.
+    manifests: HashMap<String, toml::Table>,
+}
+
+impl RevisionSources {
+    /// Lists the sources of a directory at a revision and reads the revision's manifests.
+    ///
+    /// # Arguments
+    ///
+    /// * `toplevel` - Root of the git repository
+    /// * `prefix` - Repository-relative directory to restrict the sources to (may be empty)
+    /// * `revision` - Revision to read
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the revision cannot be listed or a manifest cannot be read
+    fn list(toplevel: &Path, prefix: &str, revision: &str) -> Result<Self, RulocError> {
+        let listing = run_git_raw(toplevel, &["ls-tree", "-r", "-z", revision])?;
+
+        let mut sources = Vec::new();
+        let mut manifest_ids = Vec::new();
+        for entry in listing.split('\0') {
+            // Entries read `<mode> SP <type> SP <object> TAB <path>`
+            let Some((meta, path)) = entry.split_once('\t') else {
+                continue;
+            };
+            let [_, "blob", id] = meta.split(' ').collect::<Vec<_>>()[..] else {
+                continue;
+            };
+            if let Some(dir) = path
+                .strip_suffix("Cargo.toml")
+                .filter(|dir| dir.is_empty() || dir.ends_with('/'))
+            {
+                manifest_ids.push((id.to_string(), dir.trim_end_matches('/').to_string()));
+            } else if path.ends_with(".rs") && path.starts_with(prefix) {
+                sources.push((id.to_string(), path.to_string()));
+            }
+        }
+
+        let mut manifests = HashMap::new();
+        read_blobs(
+            toplevel,
+            manifest_ids.iter().map(|(id, _)| id.as_str()),
+            |index, content| {
+                let dir = &manifest_ids[index].1;
+                match toml::from_str(&content) {
+                    Ok(manifest) => {
+                        manifests.insert(dir.clone(), manifest);
+                    }
+                    Err(e) => warn!("Failed to parse {}/Cargo.toml at {}: {}", dir, revision, e),
+                }
+            },
+        )?;
+
+        let crate_dirs = manifest_ids
+            .into_iter()
+            .map(|(_, dir)| dir)
+            .filter(|dir| format!("{}/", dir).starts_with(prefix))
+            .collect();
+
+        Ok(Self {
+            toplevel: toplevel.to_path_buf(),
+            sources,
+            crate_dirs,
+            manifests,
+        })
+    }
+
+    /// Determines the edition a source at this revision should be parsed with.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Repository-relative path of the source
+    ///
+    /// # Returns
+    ///
+    /// The edition of the owning package, or [`Edition::CURRENT`] if there is none
+    fn edition(&self, path: &str) -> Edition {
+        let manifest = |dir: &Path| self.manifests.get(dir.to_str()?);
+        Path::new(path)
+            .ancestors()
+            .skip(1)
+            .find_map(|dir| {
+                let package = manifest(dir)?.get("package")?;
+                Some(package_edition(dir, package, |ancestor| {
+                    manifest(ancestor).cloned()
+                }))
+            })
+            .unwrap_or(Edition::CURRENT)
+    }
+
+    /// Reads every source, in tree order.
+    ///
+    /// # Arguments
+    ///
+    /// * `forced` - Edition overriding the manifests, as given with `--edition`
+    /// * `visit` - Called with the repository-relative path, content, and edition of each
+    ///   source
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a source cannot be read
+    fn read(
+        &self,
+        forced: Option<Edition>,
+        mut visit: impl FnMut(&str, String, Edition),
+    ) -> Result<(), RulocError> {
+        read_blobs(
+            &self.toplevel,
+            self.sources.iter().map(|(id, _)| id.as_str()),
+            |index, content| {
+                let path = &self.sources[index].1;
+                visit(path, content, forced.unwrap_or_else(|| self.edition(path)));
+            },
+        )
+    }
+}
+
+/// Destination that receives a rendered report.
+///
+/// Implementations cover local delivery (stdout, files, SQLite databases) and remote
+/// delivery (HTTP), so one run can publish the same report to several places without
+/// wrapper scripts.
+trait ReportSink {
+    /// Delivers a rendered report.
+    ///
+    /// # Arguments
+    ///
+    /// * `report` - Report rendered in its final format
+    /// * `format` - Format the report is rendered in
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the destination rejects or cannot store the report
+    fn deliver(&mut self, report: &str, format: ReportKind) -> Result<(), RulocError>;
+}
+
+/// Writes reports to standard output.
+struct StdoutSink;
+
+impl ReportSink for StdoutSink {
+    fn deliver(&mut self, report: &str, _format: ReportKind) -> Result<(), RulocError> {
+        println!("{}", report);
+        Ok(())
+    }
+}
+
+/// Writes a file through a temporary sibling that is renamed over it once complete.
+///
+/// Readers of `path` never observe a partially written file, and a failed write leaves
+/// any previous content in place. A replaced file keeps its permissions; a new one gets
+/// the permissions of any other created file under the current umask.
+///
+/// # Arguments
+///
+/// * `path` - Destination file
+/// * `render` - Writes the content to the buffered temporary file
+///
+/// # Returns
+///
+/// The value returned by `render`
+///
+/// # Errors
+///
+/// Returns the error of `render`, or an error if the temporary file cannot be created,
+/// written, or renamed
+fn write_atomically<T>(
+    path: &Path,
+    render: impl FnOnce(&mut BufWriter<&NamedTempFile>) -> Result<T, RulocError>,
+) -> Result<T, RulocError> {
+    let dir = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut builder = tempfile::Builder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        // Masked by the umask like `File::create`, rather than the owner-only default
+        builder.permissions(fs::Permissions::from_mode(0o666));
+    }
+    let file = builder.tempfile_in(dir).map_err(RulocError::io(format!(
+        "Failed to create a temporary file in '{}'",
+        dir.display()
+    )))?;
+    if let Ok(existing) = fs::metadata(path) {
+        file.as_file()
+            .set_permissions(existing.permissions())
+            .map_err(RulocError::io(format!(
+                "Failed to copy the permissions of '{}'",
+                path.display()
+            )))?;
+    }
+
+    let mut writer = BufWriter::new(&file);
+    let value = render(&mut writer)?;
+    writer.flush().map_err(RulocError::io(format!(
+        "Failed to write '{}'",
+        path.display()
+    )))?;
+    drop(writer);
+
+    file.persist(path)
+        .map_err(|e| RulocError::io(format!("Failed to write '{}'", path.display()))(e.error))?;
+    Ok(value)
+}
+
+/// Writes reports to a file, atomically replacing any previous content.
+struct FileSink {
+    /// Destination file.
+    path: PathBuf,
+}
+
+impl ReportSink for FileSink {
+    fn deliver(&mut self, report: &str, _format: ReportKind) -> Result<(), RulocError> {
+        write_atomically(&self.path, |writer| {
+            writeln!(writer, "{}", report).map_err(RulocError::io(format!(
+                "Failed to write report to '{}'",
+                self.path.display()
+            )))
+        })
+    }
+}
+
+/// Runs an external uploader, feeding the report through its standard input.
+///
+/// # Arguments
+///
+/// * `program` - Executable to run (e.g., `curl`)
+/// * `args` - Arguments that make the program read the report from stdin
+/// * `report` - Rendered report
+/// * `destination` - Destination shown in error messages
+///
+/// # Errors
+///
+/// Returns an error if the program cannot be started or exits unsuccessfully
+fn pipe_report(
+    program: &str,
+    args: &[&str],
+    report: &str,
+    destination: &str,
+) -> Result<(), RulocError> {
+    let mut child = std::process::Command::new(program)
+        .args(args)
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| {
+            RulocError::Output(format!(
+                "Failed to run {}: {}. Ensure it is installed and on PATH.",
+                program, e
+            ))
+        })?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(report.as_bytes())
+            .map_err(RulocError::io(format!(
+                "Failed to send report to {}",
+                destination
+            )))?;
+    }
+
+    let output = child.wait_with_output().map_err(RulocError::io(format!(
+        "Failed to send report to {}",
+        destination
+    )))?;
+    if !output.status.success() {
+        return Err(RulocError::Output(format!(
+            "Failed to deliver report to {}: {}",
+            destination,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+/// POSTs reports to an HTTP endpoint using `curl`, labeled with their format's media type.
+struct HttpSink {
+    /// Endpoint URL.
+    url: String,
+}
+
+impl ReportSink for HttpSink {
+    fn deliver(&mut self, report: &str, format: ReportKind) -> Result<(), RulocError> {
+        let content_type = format!("Content-Type: {}", format.content_type());
+        pipe_report(
+            "curl",
+            &[
+                "--fail",
+                "--silent",
+                "--show-error",
+                "--request",
+                "POST",
+                "--header",
+                &content_type,
+                "--data-binary",
+                "@-",
+                &self.url,
+            ],
+            report,
+            &self.url,
+        )
+    }
+}
+
+/// Uploads reports to S3 or Google Cloud Storage through the provider's CLI.
+///
+/// Credentials are discovered by the CLI itself (environment variables, profiles, or
+/// instance metadata), so ruloc never handles secrets.
+#[cfg(feature = "cloud-sinks")]
+struct ObjectStoreSink {
+    /// Object URL such as `s3://bucket/path/report.json` or `gs://bucket/report.json`.
+    url: String,
+}
+
+#[cfg(feature = "cloud-sinks")]
+impl ReportSink for ObjectStoreSink {
+    fn deliver(&mut self, report: &str, format: ReportKind) -> Result<(), RulocError> {
+        let content_type = format.content_type();
+        if self.url.starts_with("s3://") {
+            pipe_report(
+                "aws",
+                &["s3", "cp", "-", &self.url, "--content-type", content_type],
+                report,
+                &self.url,
+            )
+        } else {
+            pipe_report(
+                "gcloud",
+                &[
+                    "storage",
+                    "cp",
+                    "-",
+                    &self.url,
+                    "--content-type",
+                    content_type,
+                ],
+                report,
+                &self.url,
+            )
+        }
+    }
+}
+
+/// Appends reports to a table of a SQLite database using the `sqlite3` CLI.
+///
+/// Each delivery inserts one row into `ruloc_reports`, which is created on first use, so a
+/// database accumulates a history of runs that can be queried with SQLite's JSON functions.
+struct SqliteSink {
+    /// Database file, created if missing.
+    path: PathBuf,
+}
+
+impl ReportSink for SqliteSink {
+    fn deliver(&mut self, report: &str, format: ReportKind) -> Result<(), RulocError> {
+        let database = self.path.display().to_string();
+        pipe_report(
+            "sqlite3",
+            &["-bail", &database],
+            &sqlite_insert_script(report, format.content_type()),
+            &database,
+        )
+    }
+}
+
+/// Builds the SQL script that stores one report in a [`SqliteSink`] database.
+///
+/// # Arguments
+///
+/// * `report` - Rendered report
+/// * `content_type` - Media type of the report
+///
+/// # Returns
+///
+/// Statements creating the `ruloc_reports` table if needed and inserting the report, with
+/// every value quoted as a SQL string literal
+fn sqlite_insert_script(report: &str, content_type: &str) -> String {
+    let quote = |value: &str| format!("'{}'", value.replace('\'', "''"));
+    format!(
+        "CREATE TABLE IF NOT EXISTS ruloc_reports (\n    \
+         id INTEGER PRIMARY KEY AUTOINCREMENT,\n    \
+         delivered_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,\n    \
+         content_type TEXT NOT NULL,\n    \
+         report TEXT NOT NULL\n);\n\
+         INSERT INTO ruloc_reports (content_type, report) VALUES ({}, {});\n",
+        quote(content_type),
+        quote(report)
+    )
+}
+
+/// Runs a read-only query against a SQLite database using the `sqlite3` CLI.
+///
+/// # Arguments
+///
+/// * `database` - Database file
+/// * `sql` - Query to run
+///
+/// # Returns
+///
+/// One JSON object per result row, keyed by column name
+///
+/// # Errors
+///
+/// Returns an error if `sqlite3` cannot be run, the query fails, or its output is not JSON
+fn query_sqlite(database: &Path, sql: &str) -> Result<Vec<serde_json::Value>, RulocError> {
+    let output = std::process::Command::new("sqlite3")
+        .args(["-readonly", "-json"])
+        .arg(database)
+        .arg(sql)
+        .output()
+        .map_err(|e| {
+            RulocError::Output(format!(
+                "Failed to run sqlite3: {}. Ensure it is installed and on PATH.",
+                e
+            ))
+        })?;
+    if !output.status.success() {
+        return Err(RulocError::Output(format!(
+            "Failed to query '{}': {}",
+            database.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    // An empty result prints nothing rather than an empty array
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(&stdout).map_err(|e| {
+        RulocError::Parse(format!(
+            "Unexpected sqlite3 output for '{}': {}",
+            database.display(),
+            e
+        ))
+    })
+}
+
+/// Number of stored runs drawn in the per-file sparklines of `--history-db`.
+const TREND_RUNS: usize = 12;
+
+/// Reads the per-file code lines of the most recent JSON reports stored by a [`SqliteSink`].
+///
+/// # Arguments
+///
+/// * `database` - Database written by `--sink sqlite:FILE`
+/// * `runs` - Number of most recent runs to read
+///
+/// # Returns
+///
+/// The code lines of every file path, one entry per run from oldest to newest, `None` for
+/// runs that did not contain the file
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be queried
+fn load_file_trends(
+    database: &Path,
+    runs: usize,
+) -> Result<BTreeMap<String, Vec<Option<usize>>>, RulocError> {
+    let rows = query_sqlite(
+        database,
+        &format!(
+            "SELECT r.id AS run, json_extract(f.value, '$.path') AS path, \
+             json_extract(f.value, '$.total.code-lines') AS code \
+             FROM (SELECT id, report FROM ruloc_reports WHERE content_type = 'application/json' \
+             ORDER BY id DESC LIMIT {}) AS r, json_each(r.report, '$.files') AS f \
+             ORDER BY r.id",
+            runs
+        ),
+    )?;
+
+    let mut run_ids: Vec<i64> = rows.iter().filter_map(|row| row["run"].as_i64()).collect();
+    run_ids.dedup();
+    let mut trends = BTreeMap::new();
+    for row in &rows {
+        let (Some(run), Some(path), Some(code)) = (
+            row["run"].as_i64(),
+            row["path"].as_str(),
+            row["code"].as_u64(),
+        ) else {
+            continue;
+        };
+        let index = run_ids.partition_point(|id| *id < run);
+        trends
+            .entry(path.to_string())
+            .or_insert_with(|| vec![None; run_ids.len()])[index] = Some(code as usize);
+    }
+    Ok(trends)
+}
+
+/// Estimate of when total code lines reach a budget, extrapolated from stored runs.
+#[derive(Debug, Clone, PartialEq)]
+struct BudgetProjection {
+    /// Code line budget.
+    budget: u64,
+
+    /// Total code lines of the most recent run.
+    current: usize,
+
+    /// Number of runs the growth rate was fitted to.
+    runs: usize,
+
+    /// Least-squares growth of total code lines per day.
+    lines_per_day: f64,
+
+    /// When the budget is reached at that rate, in seconds since the Unix epoch; `None` if
+    /// the code is not growing or already exceeds the budget.
+    reached_at: Option<u64>,
+
+    /// When the most recent run was stored, in seconds since the Unix epoch.
+    latest_at: u64,
+}
+
+/// Reads the total code lines of the most recent JSON reports stored by a [`SqliteSink`].
+///
+/// # Arguments
+///
+/// * `database` - Database written by `--sink sqlite:FILE`
+/// * `runs` - Number of most recent runs to read
+///
+/// # Returns
+///
+/// `(delivered at, code lines)` pairs from oldest to newest, with times in seconds since
+/// the Unix epoch
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be queried
+fn load_code_line_history(database: &Path, runs: usize) -> Result<Vec<(u64, usize)>, RulocError> {
+    let rows = query_sqlite(
+        database,
+        &format!(
+            "SELECT CAST(strftime('%s', delivered_at) AS INTEGER) AS delivered, \
+             json_extract(report, '$.summary.total.code-lines') AS code \
+             FROM (SELECT id, delivered_at, report FROM ruloc_reports \
+             WHERE content_type = 'application/json' ORDER BY id DESC LIMIT {}) \
+             ORDER BY id",
+            runs
+        ),
+    )?;
+    Ok(rows
+        .iter()
+        .filter_map(|row| Some((row["delivered"].as_u64()?, row["code"].as_u64()? as usize)))
+        .collect())
+}
+
+/// Fits a line through the code lines of stored runs and extrapolates it to a budget.
+///
+/// # Arguments
+///
+/// * `history` - `(delivered at, code lines)` pairs from oldest to newest
+/// * `budget` - Code line budget
+///
+/// # Returns
+///
+/// The projection, or `None` with fewer than two runs or when all runs were stored at the
+/// same second
+fn project_budget(history: &[(u64, usize)], budget: u64) -> Option<BudgetProjection> {
+    let [(first_at, _), .., (latest_at, current)] = *history else {
+        return None;
+    };
+    let days = |at: u64| at.saturating_sub(first_at) as f64 / SECONDS_PER_DAY as f64;
+    let count = history.len() as f64;
+    let mean_day = history.iter().map(|&(at, _)| days(at)).sum::<f64>() / count;
+    let mean_lines = history.iter().map(|&(_, lines)| lines as f64).sum::<f64>() / count;
+    let (covariance, variance) =
+        history
+            .iter()
+            .fold((0.0, 0.0), |(covariance, variance), &(at, lines)| {
+                let day = days(at) - mean_day;
+                (
+                    covariance + day * (lines as f64 - mean_lines),
+                    variance + day * day,
+                )
+            });
+    if variance == 0.0 {
+        return None;
+    }
+
+    let lines_per_day = covariance / variance;
+    let remaining = budget as f64 - current as f64;
+    let reached_at = (lines_per_day > 0.0 && remaining > 0.0)
+        .then(|| latest_at + (remaining / lines_per_day * SECONDS_PER_DAY as f64).ceil() as u64);
+    Some(BudgetProjection {
+        budget,
+        current,
+        runs: history.len(),
+        lines_per_day,
+        reached_at,
+        latest_at,
+    })
+}
+
+/// Formats a budget projection as plain text.
+///
+/// # Arguments
+///
+/// * `projection` - The projection to describe
+///
+/// # Returns
+///
+/// One line each for the budget use, the growth rate, and the outlook
+fn format_budget_projection(projection: &BudgetProjection) -> String {
+    let outlook = match projection.reached_at {
+        _ if projection.current as u64 >= projection.budget => {
+            "The budget is already exceeded".to_string()
+        }
+        Some(reached_at) => format!(
+            "Projected to reach the budget on {} (in {} days)",
+            &format_utc_timestamp(reached_at)[..10],
+            (reached_at - projection.latest_at).div_ceil(SECONDS_PER_DAY as u64)
+        ),
+        None => "Not growing; the budget is not projected to be reached".to_string(),
+    };
+    format!(
+        "Code lines: {} of {} budget ({:.1}%)\nGrowth: {:+.1} lines/day over the last {} runs\n{}",
+        projection.current,
+        projection.budget,
+        projection.current as f64 * 100.0 / projection.budget.max(1) as f64,
+        projection.lines_per_day,
+        projection.runs,
+        outlook
+    )
+}
+
+/// Prints when total code lines reach a budget at their recent growth rate.
+///
+/// # Arguments
+///
+/// * `database` - Database written by `--sink sqlite:FILE`
+/// * `budget` - Code line budget
+/// * `runs` - Number of most recent runs to fit the growth rate to
+///
+/// # Errors
+///
+/// Returns an error if the database cannot be queried or holds fewer than two runs at
+/// different times
+fn run_project(database: &Path, budget: u64, runs: u16) -> Result<(), RulocError> {
+    let history = load_code_line_history(database, usize::from(runs))?;
+    let projection = project_budget(&history, budget).ok_or_else(|| {
+        RulocError::Analysis(format!(
+            "'{}' holds {} JSON report(s); projecting growth needs at least two runs stored at different times",
+            database.display(),
+            history.len()
+        ))
+    })?;
+    println!("{}", format_budget_projection(&projection));
+    Ok(())
+}
+
+/// A report destination as written on the command line or in an analysis plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SinkSpec {
+    /// `stdout` or `-`.
+    Stdout,
+
+    /// Any other value, optionally prefixed with `file:`.
+    File(PathBuf),
+
+    /// An `http://` or `https://` URL.
+    Http(String),
+
+    /// A SQLite database file prefixed with `sqlite:`.
+    Sqlite(PathBuf),
+
+    /// An `s3://` or `gs://` object URL, with the `cloud-sinks` feature.
+    #[cfg(feature = "cloud-sinks")]
+    ObjectStore(String),
+}
+
+impl SinkSpec {
+    /// Parses a sink specification.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - `stdout`, `-`, an `http(s)://` URL, `sqlite:` and a database path, or a
+    ///   file path
+    ///
+    /// # Returns
+    ///
+    /// The parsed [`SinkSpec`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for empty specifications, `sqlite:` without a database path, and
+    /// `s3://` and `gs://` URLs without the `cloud-sinks` feature
+    fn parse(spec: &str) -> Result<Self, RulocError> {
+        let spec = spec.trim();
+        let is_object_store = spec.starts_with("s3://") || spec.starts_with("gs://");
+        match spec {
+            "" => Err(RulocError::Config("Sink must not be empty".to_string())),
+            "stdout" | "-" => Ok(SinkSpec::Stdout),
+            _ if spec.starts_with("http://") || spec.starts_with("https://") => {
+                Ok(SinkSpec::Http(spec.to_string()))
+            }
+            #[cfg(feature = "cloud-sinks")]
+            _ if is_object_store => Ok(SinkSpec::ObjectStore(spec.to_string())),
+            #[cfg(not(feature = "cloud-sinks"))]
+            _ if is_object_store => Err(RulocError::Config(format!(
+                "Unsupported sink '{}': cloud storage sinks require building ruloc with --features cloud-sinks",
+                spec
+            ))),
+            _ if spec.starts_with("sqlite:") => match spec["sqlite:".len()..].trim() {
+                "" => Err(RulocError::Config(format!(
+                    "Sink '{}' must name a database file, e.g. sqlite:metrics.db",
+                    spec
+                ))),
+                database => Ok(SinkSpec::Sqlite(PathBuf::from(database))),
+            },
+            _ => Ok(SinkSpec::File(PathBuf::from(
+                spec.strip_prefix("file:").unwrap_or(spec),
+            ))),
+        }
+    }
+
+    /// Creates the sink this specification describes.
+    fn open(&self) -> Box<dyn ReportSink> {
+        match self {
+            SinkSpec::Stdout => Box::new(StdoutSink),
+            SinkSpec::File(path) => Box::new(FileSink { path: path.clone() }),
+            SinkSpec::Http(url) => Box::new(HttpSink { url: url.clone() }),
+            SinkSpec::Sqlite(path) => Box::new(SqliteSink { path: path.clone() }),
+            #[cfg(feature = "cloud-sinks")]
+            SinkSpec::ObjectStore(url) => Box::new(ObjectStoreSink { url: url.clone() }),
+        }
+    }
+}
+
+/// Presentation options shared by all report renderers.
+///
+/// Captures settings that affect how accumulated statistics are rendered without
+/// influencing the analysis itself.
+#[derive(Debug, Clone, Default)]
+struct OutputOptions {
+    /// Hyperlink configuration used to attach revision-pinned URLs to files.
+    link_base: Option<LinkBase>,
+
+    /// Key/value labels embedded in the report so downstream consumers can slice metrics.
+    labels: BTreeMap<String, String>,
+
+    /// Whether to emit files in accumulator insertion order instead of sorting them by path.
+    preserve_insertion_order: bool,
+
+    /// Files left unreachable by `--from-entry` or `--dead-files` discovery.
+    orphans: Vec<String>,
+
+    /// Destinations for the rendered JSON report; empty means stdout.
+    sinks: Vec<SinkSpec>,
+
+    /// Files excluded from the analysis, with the reason for each.
+    skipped: Vec<SkippedFile>,
+
+    /// Markdown documentation per crate, collected with `--include-docs`.
+    markdown_docs: Option<BTreeMap<String, MarkdownDocs>>,
+
+    /// Line counts of non-Rust files per language, collected with `--all-languages`.
+    other_languages: Option<BTreeMap<String, LanguageLines>>,
+
+    /// Whether the text summary table may be colored, i.e. `--no-color` was not given;
+    /// see [`OutputOptions::colors_table`].
+    color: bool,
+
+    /// Rewrites reported paths into a consistent style; `None` keeps them as recorded.
+    paths: Option<PathNormalizer>,
+
+    /// Run context captured at startup with `--with-metadata`, and when the run started.
+    metadata: Option<(RunMetadata, std::time::Instant)>,
+
+    /// Module paths that files are regrouped by with `--group-by module`.
+    modules: Option<ModuleMap>,
+
+    /// File receiving the report instead of stdout, set with `--output`.
+    output: Option<PathBuf>,
+
+    /// Totals extrapolated from the analyzed files with `--sample`.
+    sample: Option<SampleEstimate>,
+
+    /// Code lines of each file over the runs stored in the `--history-db` database, oldest
+    /// first.
+    trends: Option<BTreeMap<String, Vec<Option<usize>>>>,
+
+    /// Growth projection towards the `--budget` of code lines, from the `--history-db` runs.
+    projection: Option<BudgetProjection>,
+}
+
+impl OutputOptions {
+    /// Stores the orphaned and skipped files, the markdown documentation, and the other
+    /// languages discovered while analyzing the input.
+    fn record(&mut self, mut outcome: InputOutcome) {
+        if let Some(paths) = &self.paths {
+            for orphan in &mut outcome.orphans {
+                *orphan = paths.normalize(orphan);
+            }
+            for skipped in &mut outcome.skipped {
+                skipped.path = paths.normalize(&skipped.path);
+            }
+        }
+        self.orphans = outcome.orphans;
+        self.skipped = outcome.skipped;
+        self.markdown_docs = outcome.markdown_docs;
+        self.other_languages = outcome.other_languages;
+    }
+
+    /// Normalizes the file paths that a summary refers to.
+    fn normalize_summary(&self, summary: &mut Summary) {
+        if let Some(paths) = &self.paths {
+            for file in summary
+                .duplicates
+                .iter_mut()
+                .flat_map(|group| &mut group.files)
+            {
+                file.path = paths.normalize(&file.path);
+            }
+        }
+    }
+
+    /// Completes the run metadata with the time elapsed since the run started.
+    ///
+    /// # Returns
+    ///
+    /// The metadata, or `None` without `--with-metadata`
+    fn metadata(&self) -> Option<RunMetadata> {
+        self.metadata
+            .as_ref()
+            .map(|(metadata, started)| RunMetadata {
+                duration_ms: started.elapsed().as_millis() as u64,
+                ..metadata.clone()
+            })
+    }
+
+    /// Builds the documentation roll-up when markdown documentation was collected.
+    ///
+    /// # Arguments
+    ///
+    /// * `summary` - Summary supplying the rustdoc line count
+    ///
+    /// # Returns
+    ///
+    /// The roll-up, or `None` without `--include-docs`
+    fn documentation(&self, summary: &Summary) -> Option<DocumentationRollup> {
+        self.markdown_docs
+            .as_ref()
+            .map(|crates| DocumentationRollup::new(summary.total.rustdoc_lines, crates.clone()))
+    }
+
+    /// Builds the language roll-up when other languages were counted.
+    ///
+    /// # Arguments
+    ///
+    /// * `summary` - Summary supplying the Rust line counts
+    ///
+    /// # Returns
+    ///
+    /// The roll-up, or `None` without `--all-languages`
+    fn languages(&self, summary: &Summary) -> Option<LanguageRollup> {
+        self.other_languages
+            .as_ref()
+            .map(|others| LanguageRollup::new(summary, others.clone()))
+    }
+
+    /// Applies presentation-level enrichments to a file's statistics.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - File statistics read back from the accumulator
+    ///
+    /// # Returns
+    ///
+    /// The statistics with their `url` populated when a link base is configured and
+    /// their path normalized when a path style is configured
+    fn decorate(&self, mut file: FileStats) -> FileStats {
+        if let Some(link_base) = &self.link_base {
+            file.url = Some(link_base.url_for(&file.path));
+        }
+        if let Some(paths) = &self.paths {
+            file.path = paths.normalize(&file.path);
+        }
+        file
+    }
+
+    /// Whether the text summary table is colored: only when coloring is allowed and the
+    /// report goes to stdout on a terminal, so files never receive ANSI escapes.
+    fn colors_table(&self) -> bool {
+        self.color && self.output.is_none() && std::io::stdout().is_terminal()
+    }
+
+    /// Writes a report to `--output`, or to stdout without it.
+    ///
+    /// # Arguments
+    ///
+    /// * `render` - Writes the report to the destination it is given
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of `render`, or an error if the destination cannot be written
+    fn write_output(
+        &self,
+        render: impl FnOnce(&mut dyn Write) -> Result<(), RulocError>,
+    ) -> Result<(), RulocError> {
+        match &self.output {
+            Some(path) => write_atomically(path, |writer| render(writer)),
+            None => {
+                let mut stdout = BufWriter::new(std::io::stdout().lock());
+                render(&mut stdout)?;
+                stdout
+                    .flush()
+                    .map_err(RulocError::io("Failed to write the report"))
+            }
+        }
+    }
+
+    /// Delivers a rendered report to every configured sink, or to `--output` (stdout by
+    /// default) if there are none.
+    ///
+    /// Every sink is attempted even if an earlier one fails, so one unreachable service
+    /// does not prevent the artifact file from being written.
+    ///
+    /// # Errors
+    ///
+    /// Returns the failures of all sinks that could not deliver the report
+    fn deliver(&self, report: &str, format: ReportKind) -> Result<(), RulocError> {
+        if self.sinks.is_empty() {
+            return self.write_output(|out| {
+                writeln!(out, "{}", report).map_err(RulocError::io("Failed to write the report"))
+            });
+        }
+
+        let errors: Vec<String> = self
+            .sinks
+            .iter()
+            .filter_map(|spec| spec.open().deliver(report, format).err())
+            .map(|error| error.to_string())
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(RulocError::Output(errors.join("; ")))
+        }
+    }
+
+    /// Reads the accumulated files back in presentation order, decorated for output.
+    ///
+    /// Files are streamed in the order they were accumulated, which the analysis keeps
+    /// sorted by path (see [`walk_dir`]) unless `--no-sort` asked for completion order, so
+    /// nothing is buffered here. Warnings and duplicate groups in the summary are sorted by
+    /// path too, unless `--no-sort` is given. With `--group-by module`, the files are first
+    /// regrouped into one entry per module, and the modules sorted the same way.
+    ///
+    /// # Arguments
+    ///
+    /// * `accumulator` - The stats accumulator to read from
+    ///
+    /// # Returns
+    ///
+    /// The summary and an iterator over the decorated file statistics
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the accumulator cannot provide file statistics
+    fn ordered_report<'a, A: StatsAccumulator>(
+        &'a self,
+        accumulator: &A,
+    ) -> Result<(Summary, Box<dyn Iterator<Item = FileStats> + 'a>), RulocError> {
+        let mut summary = accumulator.get_summary();
+        self.normalize_summary(&mut summary);
+        let files = accumulator.iter_files()?;
+        let files: Box<dyn Iterator<Item = FileStats> + 'a> = match &self.modules {
+            Some(modules) => {
+                let mut modules = modules.group(files, |file| self.decorate(file));
+                if !self.preserve_insertion_order {
+                    modules.sort_by(|a, b| a.path.cmp(&b.path));
+                }
+                Box::new(modules.into_iter())
+            }
+            None => Box::new(files.map(move |file| self.decorate(file))),
+        };
+
+        if self.preserve_insertion_order {
+            return Ok((summary, files));
+        }
+
+        for group in &mut summary.duplicates {
+            group.files.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        summary.duplicates.sort_by(|a, b| {
+            b.files[0]
+                .lines
+                .cmp(&a.files[0].lines)
+                .then_with(|| a.files[0].path.cmp(&b.files[0].path))
+        });
+        Ok((summary, files))
+    }
+
+    /// Returns the column headings of the summary table, whose rows are modules with
+    /// `--group-by module`.
+    fn table_headers(&self) -> [&'static str; 6] {
+        let mut headers = TABLE_HEADERS;
+        if self.modules.is_some() {
+            headers[0] = MODULE_TABLE_HEADER;
+        }
+        headers
+    }
+
+    /// Returns the revision that rendered links are pinned to, if any.
+    fn revision(&self) -> Option<String> {
+        self.link_base
+            .as_ref()
+            .and_then(|link_base| link_base.revision.clone())
+    }
+}
+
+/// Formats collected timings for stderr.
+///
+/// # Arguments
+///
+/// * `timings` - Time spent per phase and per file
+/// * `wall` - Wall time of the whole run
+/// * `slowest` - Number of slowest files to list
+///
+/// # Returns
+///
+/// The per-phase breakdown followed by the slowest files
+fn format_timings(timings: &Timings, wall: Duration, slowest: usize) -> String {
+    let mut output = format!(
+        "Timings (wall {:.3}s; walk, parse, and classify are summed across threads):\n",
+        wall.as_secs_f64()
+    );
+    for phase in TimingPhase::ALL {
+        output.push_str(&format!(
+            "  {:<10} {:>9.3}s\n",
+            phase.name(),
+            timings.phase(phase).as_secs_f64()
+        ));
+    }
+
+    let files = timings.slowest_files(slowest);
+    if !files.is_empty() {
+        output.push_str("Slowest files:\n");
+        for (path, elapsed) in files {
+            output.push_str(&format!("  {:>9.3}s  {}\n", elapsed.as_secs_f64(), path));
+        }
+    }
+    output
+}
+
+/// Command-line arguments for ruloc.
+#[derive(Debug, Clone, Default, Parser)]
+#[command(name = "ruloc", version, about = "Rust lines of code counter")]
+#[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
+#[command(group(
+    clap::ArgGroup::new("input")
+        .required(true)
+        .args(&["file", "dir", "from_entry", "git", "help_json"])
+))]
+struct Args {
+    /// Analyze a single Rust file.
+    #[arg(short, long, value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// Analyze all Rust files in a directory recursively.
+    #[arg(short, long, value_name = "DIR")]
+    dir: Option<PathBuf>,
+
+    /// Analyze the files reachable through `mod` declarations from an entry point
+    /// (e.g., src/lib.rs) and report unreachable files as orphans.
+    #[arg(long, value_name = "FILE")]
+    from_entry: Option<PathBuf>,
+
+    /// Analyze a remote git repository, shallow-cloned into a temporary directory.
+    #[arg(long, value_name = "URL")]
+    git: Option<String>,
+
+    /// Branch, tag, or commit of the --git repository to analyze (defaults to its HEAD).
+    #[arg(long, value_name = "REV", conflicts_with_all = ["file", "dir", "from_entry"])]
+    rev: Option<String>,
+
+    /// Print a JSON description of every flag and subcommand, then exit.
+    #[arg(long, exclusive = true)]
+    help_json: bool,
+
+    /// Output the plain text summary table (default), to FILE if given.
+    #[arg(long, value_name = "FILE", num_args = 0..=1, conflicts_with = "debug")]
+    out_text: Option<Option<PathBuf>>,
+
+    /// Output in JSON format, to FILE if given.
+    #[arg(long, value_name = "FILE", num_args = 0..=1)]
+    out_json: Option<Option<PathBuf>>,
+
+    /// Output a standalone HTML page with the summary table, to FILE if given.
+    #[arg(
+        long,
+        value_name = "FILE",
+        num_args = 0..=1,
+        conflicts_with_all = ["out_ndjson", "debug"]
+    )]
+    out_html: Option<Option<PathBuf>>,
+
+    /// Add a sparkline of each file's code lines over the last runs stored in a database
+    /// written by `--sink sqlite:FILE` to the HTML report.
+    #[arg(long, value_name = "FILE", requires = "out_html")]
+    history_db: Option<PathBuf>,
+
+    /// Project when total code lines reach LINES (e.g., 500k) from the `--history-db` runs,
+    /// shown in the HTML report.
+    #[arg(long, value_name = "LINES", value_parser = parse_line_count, requires = "history_db")]
+    budget: Option<u64>,
+
+    /// Additionally write one CSV row per file to FILE as soon as each file completes.
+    #[arg(long, value_name = "FILE", conflicts_with = "debug")]
+    stream_csv: Option<PathBuf>,
+
+    /// Stream newline-delimited JSON: one record per file as it finishes, then a summary.
+    #[arg(long, conflicts_with_all = ["out_text", "out_json", "debug"])]
+    out_ndjson: bool,
+
+    /// Enable debug mode: show each line with type prefix, or with --out-json one JSON
+    /// record per file listing the type of every line.
+    #[arg(long)]
+    debug: bool,
+
+    /// Prefix each line with its number in debug mode.
+    #[arg(long, requires = "debug")]
+    line_numbers: bool,
+
+    /// Only annotate lines START through END (1-based, inclusive) in debug mode, e.g.
+    /// `--lines 100-250`.
+    #[arg(long, value_name = "START-END", value_parser = parse_line_range, requires = "debug")]
+    lines: Option<RangeInclusive<usize>>,
+
+    /// Compare debug-mode classifications against FILE, saved earlier with
+    /// `--debug --out-json`, and print only the lines that differ.
+    #[arg(
+        long,
+        hide = true,
+        value_name = "FILE",
+        requires = "debug",
+        conflicts_with = "out_json"
+    )]
+    debug_compare: Option<PathBuf>,
+
+    /// Disable colored output in debug mode and in the text summary table.
+    #[arg(long)]
+    no_color: bool,
+
+    /// Print the detailed indented text report instead of the summary table, to FILE if
+    /// given. Combined with a bare --out-text, only the detailed report is printed.
+    #[arg(
+        long,
+        value_name = "FILE",
+        num_args = 0..=1,
+        conflicts_with_all = ["out_ndjson", "debug"]
+    )]
+    out_text_verbose: Option<Option<PathBuf>>,
+
+    /// Additionally append a Markdown summary to the file named by $GITHUB_STEP_SUMMARY
+    /// and emit ::warning:: annotations for the findings also reported by --out-sarif.
+    #[arg(long, conflicts_with_all = ["out_ndjson", "debug"])]
+    out_github_summary: bool,
+
+    /// Include a per-file table in the GitHub job summary.
+    #[arg(long, requires = "out_github_summary")]
+    github_summary_files: bool,
+
+    /// Additionally write code-quality findings (long functions, misnamed tests, low doc
+    /// coverage, oversized files) to FILE as a SARIF 2.1.0 log for code scanning.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["out_ndjson", "debug"]
+    )]
+    out_sarif: Option<PathBuf>,
+
+    /// Report files whose public items are documented below PERCENT as findings (implies
+    /// --doc-coverage).
+    #[arg(long, value_name = "PERCENT", value_parser = parse_percentage)]
+    min_doc_coverage: Option<f64>,
+
+    /// Metric shown by the shields.io endpoint badge written to --badge-out.
+    #[arg(long, value_enum, value_name = "METRIC", requires = "badge_out")]
+    badge: Option<BadgeKind>,
+
+    /// Write a shields.io endpoint badge JSON for the --badge metric to FILE.
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "badge",
+        conflicts_with = "debug"
+    )]
+    badge_out: Option<PathBuf>,
+
+    /// Write the report to this file instead of stdout, in every output format; `-`
+    /// means stdout. The file is replaced atomically once the report is complete.
+    #[arg(long, short = 'o', value_name = "PATH")]
+    output: Option<PathBuf>,
+
+    /// Enable verbose output for debugging.
+    #[arg(long)]
+    verbose: bool,
+
+    /// Format of the diagnostics written to stderr: human-readable text or one JSON
+    /// object per line, keeping them machine-readable apart from the report on stdout.
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "text")]
+    log_format: LogFormat,
+
+    /// Print only the report: no progress bar, warnings, or per-file errors.
+    #[arg(long, short = 'q', conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Hide the progress bar even when stdout is a terminal, keeping warnings.
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Maximum file size to analyze (supports units: KB, MB, GB; defaults to bytes).
+    /// Examples: 1000, 3.5KB, 10MB, 1.1GB
+    #[arg(long, value_name = "SIZE")]
+    max_file_size: Option<String>,
+
+    /// Parse every file with this edition (2015, 2018, 2021, or 2024) instead of the
+    /// edition declared in its crate's Cargo.toml.
+    #[arg(long, value_name = "EDITION", value_parser = parse_edition)]
+    edition: Option<Edition>,
+
+    /// Stop the run with an error once more than N files are discovered, e.g. when
+    /// pointed at a vendor tree or home directory by mistake.
+    #[arg(long, value_name = "N")]
+    max_files: Option<usize>,
+
+    /// Stop the run with an error once the discovered files exceed SIZE in total
+    /// (supports units: KB, MB, GB; defaults to bytes).
+    #[arg(long, value_name = "SIZE", value_parser = parse_file_size)]
+    max_total_size: Option<u64>,
+
+    /// Analyze a random subset of the discovered files, given as a percentage (10%) or a
+    /// number of files (500), and extrapolate the totals with 95% confidence intervals.
+    #[arg(
+        long,
+        value_name = "SIZE",
+        value_parser = parse_sample_size,
+        conflicts_with_all = ["file", "out_ndjson", "debug"]
+    )]
+    sample: Option<SampleSize>,
+
+    /// Seed selecting the files of `--sample`, to repeat the selection of an earlier run.
+    #[arg(long, value_name = "N", requires = "sample")]
+    sample_seed: Option<u64>,
+
+    /// Only discover files: print how many files and bytes would be analyzed, which
+    /// exclusions matched, and which files the size limits would skip, without parsing.
+    #[arg(
+        long,
+        conflicts_with_all = ["file", "from_entry", "debug", "out_ndjson", "out_text_verbose", "out_html"]
+    )]
+    dry_run: bool,
+
+    /// Attribute lines to the cargo features gating them via `#[cfg(feature = "...")]`.
+    #[arg(long)]
+    by_feature: bool,
+
+    /// Report the lines guarded by non-test #[cfg(...)] attributes, broken down by predicate
+    /// (unix, target_os = "...", feature = "...", ...).
+    #[arg(long)]
+    cfg_surface: bool,
+
+    /// Report the longest line, lines wider than --line-width, and p50/p95 line lengths
+    /// per file.
+    #[arg(long)]
+    line_length_stats: bool,
+
+    /// Count code lines dominated by string literal content (SQL, templates, embedded
+    /// assets) per file.
+    #[arg(long)]
+    string_lines: bool,
+
+    /// Report groups of identical files and of files identical once whitespace and
+    /// comments are ignored (copy-pasted modules).
+    #[arg(long)]
+    duplicates: bool,
+
+    /// Also measure size in tokens (all and non-trivia), syntax tree nodes, or logical
+    /// statements per file; accepts a comma-separated list.
+    #[arg(long, value_name = "MEASURE", value_delimiter = ',')]
+    count: Vec<SizeMeasure>,
+
+    /// Report entries per file, or per Rust module path (`crate::parser::lexer`) with
+    /// `module`, merging inline modules and `mod.rs`/`name.rs` layouts.
+    #[arg(
+        long,
+        value_name = "KEY",
+        default_value = "file",
+        conflicts_with_all = ["out_ndjson", "debug"]
+    )]
+    group_by: GroupBy,
+
+    /// Width in characters above which lines are counted by --line-length-stats.
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_LINE_WIDTH)]
+    line_width: usize,
+
+    /// Count files detected as generated as ordinary code instead of reporting them separately.
+    #[arg(long)]
+    count_generated: bool,
+
+    /// Comma-separated markers that identify a generated file when found in its header.
+    #[arg(
+        long,
+        value_name = "MARKERS",
+        value_delimiter = ',',
+        default_value = DEFAULT_GENERATED_MARKERS
+    )]
+    generated_markers: Vec<String>,
+
+    /// Comma-separated path globs that identify generated files (e.g., *.pb.rs).
+    #[arg(
+        long,
+        value_name = "GLOBS",
+        value_delimiter = ',',
+        default_value = DEFAULT_GENERATED_GLOBS
+    )]
+    generated_globs: Vec<String>,
+
+    /// TOML file of attribute rules mapping custom attributes to test, bench, or generated.
+    #[arg(long, value_name = "FILE")]
+    rules: Option<PathBuf>,
+
+    /// Comma-separated work-markers counted within comments.
+    #[arg(
+        long,
+        value_name = "MARKERS",
+        value_delimiter = ',',
+        default_value = DEFAULT_TODO_MARKERS
+    )]
+    todo_markers: Vec<String>,
+
+    /// List the location of every work-marker (TODO, FIXME, ...) found in comments.
+    #[arg(long)]
+    todos: bool,
+
+    /// Count the lints suppressed by #[allow(...)] and #![allow(...)] attributes per file.
+    #[arg(long)]
+    allows: bool,
+
+    /// Count unwrap(), expect(), panic!, todo!, and unimplemented! call sites per file,
+    /// split between production and test code.
+    #[arg(long)]
+    panics: bool,
+
+    /// List the location of every panic call site (implies --panics).
+    #[arg(long)]
+    list_panics: bool,
+
+    /// List the syntax errors of every file, whose line counts may be off near them.
+    #[arg(long)]
+    list_parse_errors: bool,
+
+    /// Count files named `*_test.rs` or `test_*.rs` as test code in their entirety.
+    #[arg(long)]
+    test_file_names: bool,
+
+    /// Exit with an error when production code contains more than N panic call sites
+    /// (implies --panics).
+    #[arg(long, value_name = "N")]
+    max_panics: Option<usize>,
+
+    /// Number of threads used to analyze files (default: one per CPU; 1 runs sequentially).
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<NonZeroUsize>,
+
+    /// Resolve include!/include_str!/include_bytes! targets; Rust sources pulled in with
+    /// include! that the directory walk would miss (non-.rs extensions) are counted as files.
+    #[arg(long)]
+    follow_includes: bool,
+
+    /// Count generic parameters, where-clauses, and higher-ranked trait bounds per file.
+    #[arg(long)]
+    type_complexity: bool,
+
+    /// Count structs, enums, traits, impls, functions, macros, consts, and statics per file.
+    #[arg(long)]
+    item_counts: bool,
+
+    /// Report the share of public items carrying rustdoc per file.
+    #[arg(long)]
+    doc_coverage: bool,
+
+    /// Count code lines inside macro definitions and multi-line macro invocations per file.
+    #[arg(long)]
+    macro_lines: bool,
+
+    /// Count public and private functions, types, traits, and consts per file.
+    #[arg(long)]
+    item_visibility: bool,
+
+    /// Count async functions, .await points, and code lines inside async bodies per file.
+    #[arg(long)]
+    async_metrics: bool,
+
+    /// Report the words and characters of rustdoc comments per file and per public item.
+    #[arg(long)]
+    doc_detail: bool,
+
+    /// List impl blocks per file and count trait implementations across the crate.
+    #[arg(long)]
+    impls: bool,
+
+    /// List .rs files that no crate root in the workspace reaches via `mod` declarations.
+    #[arg(long, conflicts_with_all = ["file", "from_entry"])]
+    dead_files: bool,
+
+    /// Count each crate's README.md and doc/ markdown alongside rustdoc lines.
+    #[arg(long, conflicts_with_all = ["file", "from_entry"])]
+    include_docs: bool,
+
+    /// Also count TOML, Markdown, YAML, and shell files for a repository-wide line total.
+    #[arg(long, conflicts_with_all = ["file", "from_entry"])]
+    all_languages: bool,
+
+    /// Follow symbolic links while walking directories (default).
+    #[arg(long, overrides_with = "no_follow_symlinks")]
+    follow_symlinks: bool,
+
+    /// Skip symbolic links while walking directories.
+    #[arg(long, overrides_with = "follow_symlinks")]
+    no_follow_symlinks: bool,
+
+    /// File name extensions analyzed as Rust sources while walking directories; accepts a
+    /// comma-separated list such as `rs,rs.in`.
+    #[arg(long, value_name = "EXT", value_delimiter = ',', default_value = "rs")]
+    ext: Vec<String>,
+
+    /// Report paths relative to the analyzed directory, or to the analyzed file's directory (default).
+    #[arg(long, overrides_with = "absolute_paths")]
+    relative_paths: bool,
+
+    /// Report absolute paths.
+    #[arg(long, overrides_with = "relative_paths")]
+    absolute_paths: bool,
+
+    /// Embed run metadata (version, timestamp, root, git commit and branch, duration,
+    /// arguments) in JSON and NDJSON reports.
+    #[arg(long)]
+    with_metadata: bool,
+
+    /// Print time spent per phase (walk, parse, classify, aggregate, output) and the
+    /// slowest files to stderr.
+    #[arg(long)]
+    timings: bool,
+
+    /// Number of slowest files listed by --timings.
+    #[arg(long, value_name = "N", default_value_t = 10, requires = "timings")]
+    timings_top: usize,
+
+    /// Allow followed symbolic links to resolve outside the analyzed directory.
+    #[arg(long)]
+    allow_external: bool,
+
+    /// Fail on files with invalid UTF-8 instead of replacing the offending bytes.
+    #[arg(long)]
+    strict_utf8: bool,
+
+    /// Where file statistics are accumulated before reporting: in memory, on disk, or in
+    /// memory until 10,000 files and on disk beyond that.
+    #[arg(long, value_enum, value_name = "KIND", default_value_t = AccumulatorKind::Auto)]
+    accumulator: AccumulatorKind,
+
+    /// Memory-map files of 64 KiB or more instead of reading them into memory; builds
+    /// without the `mmap` feature read them normally.
+    #[arg(long)]
+    mmap: bool,
+
+    /// Bucket files by time since their last git commit and report lines per bucket.
+    #[arg(long)]
+    age_buckets: bool,
+
+    /// Report test functions whose names do not match this regex (e.g., '^test_').
+    #[arg(long, value_name = "REGEX", value_parser = Regex::new)]
+    test_name_pattern: Option<Regex>,
+
+    /// Exit with an error when any test name violates --test-name-pattern.
+    #[arg(long, requires = "test_name_pattern")]
+    fail_on_test_names: bool,
+
+    /// Exit with an error if any file could not be analyzed (oversized files excepted).
+    #[arg(long, conflicts_with = "quiet_errors")]
+    strict: bool,
+
+    /// Do not print per-file errors; skipped files are still listed in the report.
+    #[arg(long)]
+    quiet_errors: bool,
+
+    /// Exit with an error when no file was analyzed or the analyzed files contain no code.
+    #[arg(long)]
+    fail_on_empty: bool,
+
+    /// Exit with an error when fewer than N files were analyzed, guarding CI against a
+    /// misconfigured path.
+    #[arg(long, value_name = "N")]
+    min_files: Option<usize>,
+
+    /// Exit with an error when the analyzed files contain fewer than N code lines.
+    #[arg(long, value_name = "N")]
+    min_code_lines: Option<usize>,
+
+    /// Report functions spanning more than N lines.
+    #[arg(long, value_name = "N")]
+    max_fn_lines: Option<usize>,
+
+    /// Exit with an error when any function exceeds --max-fn-lines.
+    #[arg(long, requires = "max_fn_lines")]
+    fail_on_long_fns: bool,
+
+    /// Estimate the cyclomatic complexity of every function and report the mean and maximum
+    /// per file.
+    #[arg(long)]
+    complexity: bool,
+
+    /// Report functions whose cyclomatic complexity exceeds N (implies --complexity).
+    #[arg(long, value_name = "N")]
+    max_complexity: Option<usize>,
+
+    /// Report the deepest block nesting per file.
+    #[arg(long)]
+    nesting: bool,
+
+    /// Exit with an error when any function nests control flow deeper than N (implies
+    /// --nesting).
+    #[arg(long, value_name = "N")]
+    max_nesting: Option<usize>,
+
+    /// Keep files in the order they finished analyzing instead of sorting them by path.
+    #[arg(long)]
+    no_sort: bool,
+
+    /// Attach a key/value label to the report; may be repeated (e.g., --label team=payments).
+    #[arg(long = "label", value_name = "KEY=VALUE", value_parser = parse_label)]
+    labels: Vec<(String, String)>,
+
+    /// Deliver the JSON report to a sink (stdout, a file path, a sqlite: database, or an
+    /// http(s), s3, or gs URL); may be repeated.
+    #[arg(
+        long = "sink",
+        visible_alias = "upload",
+        value_name = "SINK",
+        value_parser = SinkSpec::parse,
+        requires = "out_json"
+    )]
+    sinks: Vec<SinkSpec>,
+
+    /// Base URL used to render each file as a link (e.g.,
+    /// `https://github.com/org/repo/blob/<rev>/`). The `<rev>` placeholder is replaced
+    /// with the commit checked out in the analysis root.
+    #[arg(long, value_name = "URL")]
+    link_base: Option<String>,
+
+    /// Optional subcommand; when absent, a single analysis described by the flags above runs.
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// Subcommands extending ruloc beyond a single flag-driven analysis.
+#[derive(Debug, Clone, Subcommand)]
+enum Command {
+    /// Execute every analysis declared in a TOML plan file within one process.
+    Run {
+        /// Path to the analysis plan (e.g., plan.toml).
+        #[arg(value_name = "PLAN")]
+        plan: PathBuf,
+    },
+
+    /// Compare ruloc's counts against another line counter to validate a migration.
+    CrossCheck {
+        /// Directory to analyze with both tools.
+        #[arg(short, long, value_name = "DIR")]
+        dir: PathBuf,
+
+        /// Line counter to compare against; must be installed and on PATH.
+        #[arg(long, value_enum, default_value_t = CrossCheckTool::Tokei)]
+        tool: CrossCheckTool,
+    },
+
+    /// List public items added or removed between two git revisions, per crate.
+    ApiDiff {
+        /// Base revision (e.g., v1.0).
+        #[arg(long, value_name = "REV")]
+        from: String,
+
+        /// Target revision.
+        #[arg(long, value_name = "REV", default_value = "HEAD")]
+        to: String,
+
+        /// Directory within the repository to compare.
+        #[arg(short, long, value_name = "DIR", default_value = ".")]
+        dir: PathBuf,
+    },
+
+    /// Analyze past revisions of a git repository and print the summaries as a time series.
+    History {
+        /// Oldest revision to include (e.g., v0.1.0); defaults to the first commit.
+        #[arg(long, value_name = "REV")]
+        since: Option<String>,
+
+        /// Which revisions of the first-parent history of HEAD to analyze.
+        #[arg(long, value_enum, default_value_t = HistoryStep::Tag)]
+        step: HistoryStep,
+
+        /// Directory within the repository to analyze.
+        #[arg(short, long, value_name = "DIR", default_value = ".")]
+        dir: PathBuf,
+
+        /// Encoding of the time series printed to stdout.
+        #[arg(long, value_enum, default_value_t = HistoryFormat::Csv)]
+        format: HistoryFormat,
+    },
+
+    /// Compare two directories (e.g., a fork and its upstream) per category and per file.
+    Compare {
+        /// Baseline directory.
+        #[arg(value_name = "DIR_A")]
+        dir_a: PathBuf,
+
+        /// Directory compared against the baseline.
+        #[arg(value_name = "DIR_B")]
+        dir_b: PathBuf,
+
+        /// Encoding of the delta report printed to stdout.
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+    },
+
+    /// Rank files by churn × size (commit count × code lines) to target refactoring.
+    Hotspots {
+        /// Directory within a git repository to analyze.
+        #[arg(short, long, value_name = "DIR", default_value = ".")]
+        dir: PathBuf,
+
+        /// Number of files to list.
+        #[arg(long, value_name = "N", default_value_t = 20)]
+        top: usize,
+
+        /// Only count commits more recent than DATE (any date `git log --since` accepts,
+        /// e.g., "6 months ago").
+        #[arg(long, value_name = "DATE")]
+        since: Option<String>,
+
+        /// Encoding of the ranking printed to stdout.
+        #[arg(long, value_enum, default_value_t = ReportFormat::Text)]
+        format: ReportFormat,
+    },
+
+    /// Download a published crate from crates.io and analyze it (requires the `crates-io`
+    /// feature).
+    Crate {
+        /// Crate to analyze, optionally pinned to a version (e.g., serde@1.0.200); defaults
+        /// to the latest stable release.
+        #[arg(value_name = "NAME[@VERSION]", value_parser = CrateSpec::parse)]
+        spec: CrateSpec,
+
+        /// Analysis flags applied to the unpacked crate (e.g., --out-json).
+        #[arg(
+            value_name = "FLAGS",
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        flags: Vec<String>,
+    },
+
+    /// Attach a review note to a file entry of a saved JSON report.
+    Annotate {
+        /// JSON report produced by `--out-json`; rewritten in place.
+        #[arg(value_name = "REPORT")]
+        report: PathBuf,
+
+        /// Path of the file entry exactly as it appears in the report.
+        #[arg(long, value_name = "PATH")]
+        path: String,
+
+        /// Note text to append to the entry.
+        #[arg(long, value_name = "TEXT")]
+        note: String,
+    },
+
+    /// Project when total code lines reach a budget from the runs stored by a sqlite: sink.
+    Project {
+        /// Database written by `--sink sqlite:FILE`.
+        #[arg(long, value_name = "FILE")]
+        db: PathBuf,
+
+        /// Code line budget, with an optional k or m suffix (e.g., 500k).
+        #[arg(long, value_name = "LINES", value_parser = parse_line_count)]
+        budget: u64,
+
+        /// Number of most recent runs the growth rate is fitted to.
+        #[arg(long, value_name = "N", default_value_t = 10, value_parser = clap::value_parser!(u16).range(2..))]
+        runs: u16,
+    },
+}
+
+/// Revisions sampled by the `history` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum HistoryStep {
+    /// Every tagged commit.
+    Tag,
+
+    /// The last commit of every week, counted from the oldest included commit.
+    Weekly,
+}
+
+/// Encodings of the diagnostics written to stderr, selected with `--log-format`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum LogFormat {
+    /// Human-readable lines with a timestamp, level, and module.
+    #[default]
+    Text,
+
+    /// One JSON object per record with `timestamp`, `level`, `target`, and `message`.
+    Json,
+}
+
+/// Units that report entries are aggregated by, selected with `--group-by`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum GroupBy {
+    /// One entry per analyzed file.
+    #[default]
+    File,
+
+    /// One entry per Rust module, resolved from the crate roots' `mod` declarations.
+    Module,
+}
+
+/// Encodings of the time series printed by the `history` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum HistoryFormat {
+    /// One row per revision, with the headline counts and the test ratio.
+    Csv,
+
+    /// An array of revisions, each with its full [`Summary`].
+    Json,
+}
+
+/// Encodings of the reports printed by the `compare` and `hotspots` subcommands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ReportFormat {
+    /// Aligned plain-text tables.
+    Text,
+
+    /// The report as pretty-printed JSON.
+    Json,
+
+    /// Markdown tables, e.g., for pull request comments.
+    Markdown,
+}
+
+/// External line counters supported by the `cross-check` subcommand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum CrossCheckTool {
+    /// [tokei](https://github.com/XAMPPRocky/tokei), invoked with `--output json`.
+    Tokei,
+
+    /// [cloc](https://github.com/AlDanial/cloc), invoked with `--json`.
+    Cloc,
+}
+
+impl CrossCheckTool {
+    /// Returns the executable name of the tool.
+    fn program(self) -> &'static str {
+        match self {
+            CrossCheckTool::Tokei => "tokei",
+            CrossCheckTool::Cloc => "cloc",
+        }
+    }
+
+    /// Builds the arguments that make the tool emit Rust-only JSON for a directory.
+    fn arguments(self, dir: &Path) -> Vec<String> {
+        let dir = dir.display().to_string();
+        match self {
+            CrossCheckTool::Tokei => vec![
+                dir,
+                "--types".to_string(),
+                "Rust".to_string(),
+                "--output".to_string(),
+                "json".to_string(),
+            ],
+            CrossCheckTool::Cloc => vec![
+                "--json".to_string(),
+                "--quiet".to_string(),
+                "--include-lang=Rust".to_string(),
+                dir,
+            ],
+        }
+    }
+}
+
+impl Args {
+    /// Extracts the validated input source from command-line arguments.
+    ///
+    /// Converts the three `Option<PathBuf>` input fields into a single `InputSource` enum,
+    /// encoding the ArgGroup invariant that exactly one is `Some` at the type level.
+    ///
+    /// # Returns
+    ///
+    /// `InputSource` enum representing a file, directory, or entry point path
+    ///
+    /// # Panics
+    ///
+    /// Panics if the ArgGroup invariant is violated (should never happen with proper clap setup)
+    fn input_source(&self) -> InputSource {
+        match (&self.file, &self.dir, &self.from_entry) {
+            (Some(path), None, None) => InputSource::File(path.clone()),
+            (None, Some(path), None) => InputSource::Directory(path.clone()),
+            (None, None, Some(path)) => InputSource::Entry(path.clone()),
+            _ => unreachable!(
+                "ArgGroup ensures exactly one of file, dir, or from-entry is Some without a subcommand"
+            ),
+        }
+    }
+
+    /// Parses the max file size from the command-line argument.
+    ///
+    /// Supports units: KB, MB, GB. Without a unit, interprets as bytes.
+    ///
+    /// # Returns
+    ///
+    /// `Some(size_in_bytes)` if specified, `None` otherwise
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the size string cannot be parsed
+    fn parse_max_file_size(&self) -> Result<Option<u64>, RulocError> {
+        let Some(ref size_str) = self.max_file_size else {
+            return Ok(None);
+        };
+
+        parse_file_size(size_str).map(Some)
+    }
+
+    /// Resolves per-file analysis settings from command-line flags.
+    ///
+    /// # Returns
+    ///
+    /// `AnalysisOptions` reflecting the requested limits and opt-in analyses
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RulocError::Config`] if the maximum file size, generated-file globs, or
+    /// rules file are invalid, [`RulocError::Io`] if the rules file cannot be read, and
+    /// [`RulocError::Git`] if `--age-buckets` cannot read the git history
+    fn analysis_options(&self) -> Result<AnalysisOptions, RulocError> {
+        if self.mmap && !cfg!(feature = "mmap") {
+            warn!("--mmap requires building ruloc with --features mmap; reading files instead");
+        }
+
+        Ok(AnalysisOptions {
+            max_file_size: self.parse_max_file_size()?,
+            feature_breakdown: self.by_feature,
+            cfg_surface: self.cfg_surface,
+            line_width: self.line_length_stats.then_some(self.line_width),
+            string_lines: self.string_lines,
+            duplicates: self.duplicates,
+            size_measures: self.count.clone(),
+            inline_modules: self.group_by == GroupBy::Module,
+            markers: self
+                .todo_markers
+                .iter()
+                .map(|marker| marker.trim().to_string())
+                .filter(|marker| !marker.is_empty())
+                .collect(),
+            list_markers: self.todos,
+            allows: self.allows,
+            panics: self.panics || self.list_panics || self.max_panics.is_some(),
+            list_panics: self.list_panics,
+            list_parse_errors: self.list_parse_errors,
+            test_file_names: self.test_file_names,
+            max_fn_lines: self.max_fn_lines,
+            complexity: self.complexity || self.max_complexity.is_some(),
+            max_complexity: self.max_complexity,
+            nesting: self.nesting || self.max_nesting.is_some(),
+            max_nesting: self.max_nesting,
+            list_impls: self.impls,
+            type_complexity: self.type_complexity,
+            item_counts: self.item_counts,
+            doc_coverage: self.doc_coverage || self.min_doc_coverage.is_some(),
+            macro_lines: self.macro_lines,
+            item_visibility: self.item_visibility,
+            async_metrics: self.async_metrics,
+            doc_detail: self.doc_detail,
+            jobs: self.jobs,
+            completion_order: self.no_sort,
+            follow_includes: self.follow_includes,
+            test_name_pattern: self.test_name_pattern.clone(),
+            dead_files: self.dead_files,
+            include_docs: self.include_docs,
+            all_languages: self.all_languages,
+            symlinks: SymlinkPolicy {
+                follow: self.follow_symlinks || !self.no_follow_symlinks,
+                allow_external: self.allow_external,
+            },
+            extensions: SourceExtensions::new(&self.ext),
+            limits: DiscoveryLimits {
+                max_files: self.max_files,
+                max_total_size: self.max_total_size,
+            },
+            sampling: self.sample.map(|size| {
+                Arc::new(Sampling::new(
+                    size,
+                    self.sample_seed.unwrap_or_else(random_seed),
+                ))
+            }),
+            strict_utf8: self.strict_utf8,
+            mmap: self.mmap,
+            quiet_errors: self.quiet_errors || self.quiet,
+            progress: !self.quiet && !self.no_progress,
+            generated: if self.count_generated {
+                None
+            } else {
+                Some(GeneratedRules::new(
+                    &self.generated_markers,
+                    &self.generated_globs,
+                )?)
+            },
+            rules: match &self.rules {
+                Some(path) => Arc::new(ClassificationRules::load(path)?),
+                None => Arc::default(),
+            },
+            file_ages: if self.age_buckets {
+                Some(Arc::new(FileAges::load(&self.analysis_root())?))
+            } else {
+                None
+            },
+            editions: Arc::new(EditionResolver::new(self.edition)),
+            tooling: Arc::default(),
+            timings: self.timings.then(Arc::default),
+        })
+    }
+
+    /// Builds the path normalizer selected by `--relative-paths` and `--absolute-paths`.
+    ///
+    /// # Returns
+    ///
+    /// A normalizer expressing paths against the analysis root, relative unless
+    /// `--absolute-paths` is given alone
+    fn path_normalizer(&self) -> PathNormalizer {
+        PathNormalizer {
+            style: if self.absolute_paths && !self.relative_paths {
+                PathStyle::Absolute
+            } else {
+                PathStyle::Relative
+            },
+            root: self.analysis_root(),
+        }
+    }
+
+    /// Resolves presentation options from command-line flags.
+    ///
+    /// # Returns
+    ///
+    /// `OutputOptions` reflecting the requested presentation settings
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `--link-base` revision cannot be resolved or the
+    /// `--history-db` database cannot be read
+    fn output_options(&self) -> Result<OutputOptions, RulocError> {
+        let link_base = match &self.link_base {
+            Some(template) => Some(LinkBase::resolve(template, &self.analysis_root())?),
+            None => None,
+        };
+
+        Ok(OutputOptions {
+            link_base,
+            labels: self.labels.iter().cloned().collect(),
+            preserve_insertion_order: self.no_sort,
+            orphans: Vec::new(),
+            sinks: self.sinks.clone(),
+            skipped: Vec::new(),
+            markdown_docs: None,
+            other_languages: None,
+            color: !self.no_color,
+            paths: Some(self.path_normalizer()),
+            metadata: self.with_metadata.then(|| {
+                (
+                    RunMetadata::capture(&self.analysis_root()),
+                    std::time::Instant::now(),
+                )
+            }),
+            modules: None,
+            output: self.output.clone().filter(|path| path.as_os_str() != "-"),
+            sample: None,
+            trends: match &self.history_db {
+                Some(database) => Some(load_file_trends(database, TREND_RUNS)?),
+                None => None,
+            },
+            projection: match (&self.history_db, self.budget) {
+                (Some(database), Some(budget)) => {
+                    project_budget(&load_code_line_history(database, TREND_RUNS)?, budget)
+                }
+                _ => None,
+            },
+        })
+    }
+
+    /// Determines the directory that reported paths are considered relative to.
+    ///
+    /// # Returns
+    ///
+    /// The analyzed directory, or the parent directory of the analyzed file
+    fn analysis_root(&self) -> PathBuf {
+        match self.input_source() {
+            InputSource::Directory(path) => path,
+            InputSource::File(path) | InputSource::Entry(path) => path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .map(Path::to_path_buf)
+                .unwrap_or_else(|| PathBuf::from(".")),
+        }
+    }
+
+    /// Determines the output format based on command-line flags.
+    ///
+    /// # Returns
+    ///
+    /// `OutputFormat::Ndjson` if `--out-ndjson` is specified, `OutputFormat::Json` if `--out-json`
+    /// is specified, otherwise `OutputFormat::Text`
+    fn output_format(&self) -> OutputFormat {
+        if self.out_ndjson {
+            OutputFormat::Ndjson
+        } else if self.out_json.is_some() {
+            OutputFormat::Json
+        } else {
+            OutputFormat::Text
+        }
+    }
+
+    /// Lists the reports to write after the analysis, each to its own destination.
+    ///
+    /// A format given without a file writes to `--output`, or to stdout without it. Without
+    /// any format flag, the summary table is written.
+    ///
+    /// # Returns
+    ///
+    /// One target per requested format, in the order table, detailed text, JSON, HTML
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RulocError::Config`] if more than one format would write to the same
+    /// destination
+    fn report_targets(&self) -> Result<Vec<ReportTarget>, RulocError> {
+        // A bare --out-text only selects text output, which --out-text-verbose refines
+        let out_text = match (&self.out_text, &self.out_text_verbose) {
+            (Some(None), Some(_)) => None,
+            (out_text, _) => out_text.clone(),
+        };
+
+        let mut targets: Vec<ReportTarget> = [
+            (ReportKind::Table, out_text),
+            (ReportKind::Text, self.out_text_verbose.clone()),
+            (ReportKind::Json, self.out_json.clone()),
+            (ReportKind::Html, self.out_html.clone()),
+        ]
+        .into_iter()
+        .filter_map(|(format, path)| {
+            path.map(|path| ReportTarget {
+                format,
+                path: path.or_else(|| self.output.clone()),
+            })
+        })
+        .collect();
+        if targets.is_empty() {
+            targets.push(ReportTarget {
+                format: ReportKind::Table,
+                path: self.output.clone(),
+            });
+        }
+
+        let mut destinations = HashSet::new();
+        for target in &mut targets {
+            target.path = target.path.take().filter(|path| path.as_os_str() != "-");
+            if !destinations.insert(target.path.clone()) {
+                return Err(RulocError::Config(format!(
+                    "Several output formats would be written to {}; give each format its own file",
+                    target
+                        .path
+                        .as_ref()
+                        .map_or("stdout".to_string(), |path| format!("'{}'", path.display()))
+                )));
+            }
+        }
+        Ok(targets)
+    }
+}
+
+/// Declarative description of several analyses executed by `ruloc run`.
+///
+/// Replaces brittle shell scripts that invoke ruloc repeatedly with a single plan file,
+/// executed sequentially within one process. Each `[[analysis]]` table mirrors the
+/// command-line flags of a standalone invocation.
+///
+/// # Example
+///
+/// ```toml
+/// [[analysis]]
+/// name = "core"
+/// dir = "crates/core"
+/// format = "json"
+/// max-file-size = "1MB"
+///
+/// [[analysis]]
+/// name = "entrypoint"
+/// file = "src/main.rs"
+/// ```
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+struct AnalysisPlan {
+    /// Analyses to execute, in declaration order.
+    #[serde(rename = "analysis", default)]
+    analyses: Vec<PlannedAnalysis>,
+}
+
+/// A single analysis declared within an [`AnalysisPlan`].
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+struct PlannedAnalysis {
+    /// Human-readable identifier used in progress messages and diagnostics.
+    name: Option<String>,
+
+    /// Single Rust file to analyze (mutually exclusive with `dir`).
+    file: Option<PathBuf>,
+
+    /// Directory to analyze recursively (mutually exclusive with `file`).
+    dir: Option<PathBuf>,
+
+    /// Output format for this analysis; defaults to text.
+    #[serde(default = "PlannedAnalysis::default_format")]
+    format: OutputFormat,
+
+    /// Maximum file size threshold, using the same syntax as `--max-file-size`.
+    max_file_size: Option<String>,
+
+    /// Base URL for revision-pinned file links, as accepted by `--link-base`.
+    link_base: Option<String>,
+
+    /// Key/value labels embedded in the report, as accepted by `--label`.
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+
+    /// Work-markers counted within comments, as accepted by `--todo-markers`.
+    todo_markers: Option<Vec<String>>,
+
+    /// Whether to list work-marker locations, as accepted by `--todos`.
+    #[serde(default)]
+    todos: bool,
+
+    /// Report destinations, as accepted by `--sink`; requires the `json` format.
+    #[serde(default)]
+    sinks: Vec<String>,
+
+    /// Attribute classification rules file, as accepted by `--rules`.
+    rules: Option<PathBuf>,
+}
+
+impl PlannedAnalysis {
+    /// Supplies the default output format for planned analyses.
+    fn default_format() -> OutputFormat {
+        OutputFormat::Text
+    }
+
+    /// Returns the display label for this analysis.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Zero-based position of the analysis within the plan
+    ///
+    /// # Returns
+    ///
+    /// The declared name, or a positional label such as `#2` when unnamed
+    fn label(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("#{}", index + 1))
+    }
+
+    /// Translates this planned analysis into equivalent command-line arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - Zero-based position of the analysis within the plan, used in errors
+    ///
+    /// # Returns
+    ///
+    /// `Args` describing a standalone invocation of the analysis
+    ///
+    /// # Errors
+    ///
+    /// Returns an error unless exactly one of `file` or `dir` is declared
+    fn to_args(&self, index: usize) -> Result<Args, RulocError> {
+        if self.file.is_some() == self.dir.is_some() {
+            return Err(RulocError::Config(format!(
+                "Analysis '{}' must declare exactly one of 'file' or 'dir'",
+                self.label(index)
+            )));
+        }
+        if !self.sinks.is_empty() && self.format != OutputFormat::Json {
+            return Err(RulocError::Config(format!(
+                "Analysis '{}' declares sinks, which require format = \"json\"",
+                self.label(index)
+            )));
+        }
+        let sinks = self
+            .sinks
+            .iter()
+            .map(|spec| SinkSpec::parse(spec))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.context(format!("Analysis '{}'", self.label(index))))?;
+
+        Ok(Args {
+            file: self.file.clone(),
+            dir: self.dir.clone(),
+            out_json: (self.format == OutputFormat::Json).then_some(None),
+            out_ndjson: self.format == OutputFormat::Ndjson,
+            max_file_size: self.max_file_size.clone(),
+            link_base: self.link_base.clone(),
+            labels: self.labels.clone().into_iter().collect(),
+            todo_markers: self.todo_markers.clone().unwrap_or_else(|| {
+                DEFAULT_TODO_MARKERS
+                    .split(',')
+                    .map(str::to_string)
+                    .collect()
+            }),
+            todos: self.todos,
+            sinks,
+            rules: self.rules.clone(),
+            generated_markers: DEFAULT_GENERATED_MARKERS
+                .split(',')
+                .map(str::to_string)
+                .collect(),
+            generated_globs: DEFAULT_GENERATED_GLOBS
+                .split(',')
+                .map(str::to_string)
+                .collect(),
+            ..Default::default()
+        })
+    }
+}
+
+/// Parses an analysis plan from TOML source.
+///
+/// # Arguments
+///
+/// * `content` - TOML document describing the plan
+///
+/// # Returns
+///
+/// The parsed [`AnalysisPlan`]
+///
+/// # Errors
+///
+/// Returns an error if the document is malformed, contains unknown keys, or declares
+/// no analyses
+fn parse_analysis_plan(content: &str) -> Result<AnalysisPlan, RulocError> {
+    let plan: AnalysisPlan = toml::from_str(content)
+        .map_err(|e| RulocError::Config(format!("Invalid analysis plan: {}", e)))?;
+
+    if plan.analyses.is_empty() {
+        return Err(RulocError::Config(
+            "Analysis plan declares no analyses. Add at least one [[analysis]] table.".to_string(),
+        ));
+    }
+
+    Ok(plan)
+}
+
+/// Executes every analysis declared in a plan file.
+///
+/// Validates the complete plan before running anything, so that a typo in the last
+/// analysis does not surface only after earlier analyses have already produced output.
+/// Each analysis announces itself on stderr, keeping stdout reserved for reports.
+///
+/// # Arguments
+///
+/// * `plan_path` - Path to the TOML plan file
+///
+/// # Returns
+///
+/// `Ok(())` once all analyses have completed
+///
+/// # Errors
+///
+/// Returns an error if the plan cannot be read or parsed, or if any analysis fails
+fn run_plan(plan_path: &Path) -> Result<(), RulocError> {
+    let content = fs::read_to_string(plan_path).map_err(RulocError::io(format!(
+        "Failed to read analysis plan '{}'",
+        plan_path.display()
+    )))?;
+    let plan = parse_analysis_plan(&content)?;
+
+    let invocations = plan
+        .analyses
+        .iter()
+        .enumerate()
+        .map(|(index, analysis)| {
+            analysis
+                .to_args(index)
+                .map(|args| (analysis.label(index), args))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (label, args) in &invocations {
+        info!("Running analysis '{}'", label);
+        run_analysis(args).map_err(|e| e.context(format!("Analysis '{}' failed", label)))?;
+    }
+
+    Ok(())
+}
+
+/// Rust line counts reported by an external counter, mapped onto ruloc's categories.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct ExternalCounts {
+    /// Blank lines.
+    blank: usize,
+
+    /// Comment lines, including doc comments.
+    comment: usize,
+
+    /// Code lines.
+    code: usize,
+}
+
+/// Reads a non-negative integer field from a JSON object, defaulting to zero.
+fn json_count(value: &serde_json::Value, field: &str) -> usize {
+    value
+        .get(field)
+        .and_then(serde_json::Value::as_u64)
+        .unwrap_or(0) as usize
+}
+
+/// Parses the Rust totals from the output of `tokei --output json`.
+///
+/// # Errors
+///
+/// Returns an error if the output is not valid JSON
+fn parse_tokei_json(output: &str) -> Result<ExternalCounts, RulocError> {
+    let value: serde_json::Value = serde_json::from_str(output)
+        .map_err(|e| RulocError::Parse(format!("Failed to parse tokei output: {}", e)))?;
+    let rust = &value["Rust"];
+
+    Ok(ExternalCounts {
+        blank: json_count(rust, "blanks"),
+        comment: json_count(rust, "comments"),
+        code: json_count(rust, "code"),
+    })
+}
+
+/// Parses the Rust totals from the output of `cloc --json`.
+///
+/// # Errors
+///
+/// Returns an error if the output is not valid JSON
+fn parse_cloc_json(output: &str) -> Result<ExternalCounts, RulocError> {
+    let value: serde_json::Value = serde_json::from_str(output)
+        .map_err(|e| RulocError::Parse(format!("Failed to parse cloc output: {}", e)))?;
+    let rust = &value["Rust"];
+
+    Ok(ExternalCounts {
+        blank: json_count(rust, "blank"),
+        comment: json_count(rust, "comment"),
+        code: json_count(rust, "code"),
+    })
+}
+
+/// Runs an external line counter over a directory and parses its Rust totals.
+///
+/// # Errors
+///
+/// Returns an error if the tool is not installed, exits unsuccessfully, or emits
+/// output that cannot be parsed
+fn run_external_counter(tool: CrossCheckTool, dir: &Path) -> Result<ExternalCounts, RulocError> {
+    let output = std::process::Command::new(tool.program())
+        .args(tool.arguments(dir))
+        .output()
+        .map_err(RulocError::io(format!(
+            "Failed to run {}. Ensure it is installed and on PATH",
+            tool.program()
+        )))?;
+
+    if !output.status.success() {
+        return Err(RulocError::Analysis(format!(
+            "{} failed: {}",
+            tool.program(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    match tool {
+        CrossCheckTool::Tokei => parse_tokei_json(&stdout),
+        CrossCheckTool::Cloc => parse_cloc_json(&stdout),
+    }
+}
+
+/// One row of the cross-check reconciliation table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ReconciliationRow {
+    /// Category being compared (Blank, Comment, Code, Total).
+    category: &'static str,
+
+    /// Count reported by ruloc.
+    ruloc: usize,
+
+    /// Count reported by the external tool.
+    other: usize,
+
+    /// Likely cause of the difference; empty when the counts agree.
+    reason: &'static str,
+}
+
+impl ReconciliationRow {
+    /// Signed difference between ruloc's count and the external tool's count.
+    fn delta(&self) -> i64 {
+        self.ruloc as i64 - self.other as i64
+    }
+}
+
+/// Lines in files under `tests/` or `benches/` directories.
+///
+/// ruloc attributes lines to tests only through `#[cfg(test)]` and `#[test]`, so
+/// integration tests and benchmarks count as production lines; other tools commonly
+/// treat these directories as tests.
+fn is_test_directory_file(path: &str) -> bool {
+    Path::new(path).components().any(|component| {
+        matches!(
+            component,
+            std::path::Component::Normal(name) if name == "tests" || name == "benches"
+        )
+    })
+}
+
+/// Builds the reconciliation table between ruloc's totals and an external tool's.
+///
+/// Comment counts include rustdoc lines because external counters do not separate
+/// documentation from ordinary comments.
+///
+/// # Arguments
+///
+/// * `ruloc` - Aggregate totals computed by ruloc
+/// * `other` - Totals reported by the external tool
+///
+/// # Returns
+///
+/// Rows for Blank, Comment, Code, and Total, each with a likely cause when they differ
+fn reconcile_counts(ruloc: &LineStats, other: &ExternalCounts) -> Vec<ReconciliationRow> {
+    let rows = [
+        (
+            "Blank",
+            ruloc.blank_lines,
+            other.blank,
+            "blank lines inside block comments or string literals are classified differently",
+        ),
+        (
+            "Comment",
+            ruloc.comment_lines + ruloc.rustdoc_lines,
+            other.comment,
+            "lines mixing code and comments, or comment markers inside strings, are attributed differently",
+        ),
+        (
+            "Code",
+            ruloc.code_lines,
+            other.code,
+            "lines mixing code and comments count as code in ruloc; multi-line strings may differ",
+        ),
+        (
+            "Total",
+            ruloc.all_lines,
+            other.blank + other.comment + other.code,
+            "the tools scanned different file sets (ignore rules, size limits, or unreadable files)",
+        ),
+    ];
+
+    rows.into_iter()
+        .map(|(category, ruloc, other, reason)| ReconciliationRow {
+            category,
+            ruloc,
+            other,
+            reason: if ruloc == other { "" } else { reason },
+        })
+        .collect()
+}
+
+/// Sink counting production lines in files under `tests/` or `benches/` directories.
+#[derive(Debug, Default)]
+struct TestDirectoryLines(usize);
+
+impl AnalysisSink for TestDirectoryLines {
+    fn file(&mut self, file_stats: &FileStats) -> Result<(), RulocError> {
+        if is_test_directory_file(&file_stats.path) {
+            self.0 += file_stats.production.all_lines;
+        }
+        Ok(())
+    }
+}
+
+/// Compares ruloc's counts for a directory with those of an external line counter.
+///
+/// Prints a reconciliation table of per-category counts, their differences, and the
+/// likely cause of each difference, followed by the number of lines under `tests/`
+/// and `benches/` that ruloc counts as production.
+///
+/// # Errors
+///
+/// Returns an error if either analysis fails
+fn run_cross_check(dir: &Path, tool: CrossCheckTool) -> Result<(), RulocError> {
+    let other = run_external_counter(tool, dir)?;
+
+    let mut test_directory_lines = TestDirectoryLines::default();
+    let summary = analyze_into(
+        &InputSource::Directory(dir.to_path_buf()),
+        &AnalysisOptions::default(),
+        &mut test_directory_lines,
+    )?;
+    let test_directory_lines = test_directory_lines.0;
+
+    let rows = reconcile_counts(&summary.total, &other);
+    println!(
+        "{:<10}{:>10}{:>10}{:>10}  Likely cause",
+        "Category",
+        "ruloc",
+        tool.program(),
+        "Delta"
+    );
+    for row in &rows {
+        println!(
+            "{:<10}{:>10}{:>10}{:>+10}  {}",
+            row.category,
+            row.ruloc,
+            row.other,
+            row.delta(),
+            row.reason
+        );
+    }
+
+    if test_directory_lines > 0 {
+        println!();
+        println!(
+            "Note: {} production lines live under tests/ or benches/; ruloc only counts #[cfg(test)] and #[test] code as test lines.",
+            test_directory_lines
+        );
+    }
+
+    Ok(())
+}
+
+/// Public items of one revision, keyed by crate directory and then by item identity.
+type ApiSnapshot = BTreeMap<String, BTreeMap<String, PublicItem>>;
+
+/// Public items added and removed within one crate between two revisions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ApiChanges {
+    /// Items present only in the target revision, keyed by identity.
+    added: Vec<(String, PublicItem)>,
+
+    /// Items present only in the base revision, keyed by identity.
+    removed: Vec<(String, PublicItem)>,
+}
+
+/// Determines the crate a file belongs to from the manifests present in the same tree.
+///
+/// # Arguments
+///
+/// * `file` - Repository-relative path of a Rust source file
+/// * `crate_dirs` - Repository-relative directories containing a `Cargo.toml`
+///
+/// # Returns
+///
+/// The deepest crate directory containing the file, or `.` for the repository root
+fn owning_crate<'a>(file: &str, crate_dirs: &'a [String]) -> &'a str {
+    crate_dirs
+        .iter()
+        .filter(|dir| dir.is_empty() || Path::new(file).starts_with(dir.as_str()))
+        .max_by_key(|dir| dir.len())
+        .map(|dir| if dir.is_empty() { "." } else { dir.as_str() })
+        .unwrap_or(".")
+}
+
+/// Builds the public API snapshot of a directory at a git revision.
+///
+/// Reads files straight from the object database, so the working tree is never touched.
+///
+/// # Arguments
+///
+/// * `toplevel` - Root of the git repository
+/// * `prefix` - Repository-relative directory to restrict the snapshot to (may be empty)
+/// * `revision` - Revision to read
+///
+/// # Errors
+///
+/// Returns an error if the revision cannot be listed or a file cannot be read
+fn api_snapshot(toplevel: &Path, prefix: &str, revision: &str) -> Result<ApiSnapshot, RulocError> {
+    let revision_sources = RevisionSources::list(toplevel, prefix, revision)?;
+    let crate_dirs = &revision_sources.crate_dirs;
+
+    let mut snapshot = ApiSnapshot::new();
+    revision_sources.read(None, |path, content, edition| {
+        let root = SourceFile::parse(&content, edition).syntax_node();
+        let crate_dir = owning_crate(path, crate_dirs);
+        let relative = Path::new(path)
+            .strip_prefix(crate_dir)
+            .unwrap_or(Path::new(path))
+            .display()
+            .to_string();
+
+        let crate_items = snapshot.entry(crate_dir.to_string()).or_default();
+        for item in collect_public_items(&root, &LineIndex::new(&content)) {
+            crate_items.insert(format!("{}: {} {}", relative, item.kind, item.name), item);
+        }
+    })?;
+
+    Ok(snapshot)
+}
+
+/// Compares two API snapshots crate by crate.
+///
+/// # Arguments
+///
+/// * `from` - Snapshot of the base revision
+/// * `to` - Snapshot of the target revision
+///
+/// # Returns
+///
+/// Changes for every crate in which at least one public item was added or removed
+fn diff_api(from: &ApiSnapshot, to: &ApiSnapshot) -> BTreeMap<String, ApiChanges> {
+    let empty = BTreeMap::new();
+    let crates: std::collections::BTreeSet<&String> = from.keys().chain(to.keys()).collect();
+
+    crates
+        .into_iter()
+        .filter_map(|crate_dir| {
+            let before = from.get(crate_dir).unwrap_or(&empty);
+            let after = to.get(crate_dir).unwrap_or(&empty);
+            let only_in = |a: &BTreeMap<String, PublicItem>, b: &BTreeMap<String, PublicItem>| {
+                a.iter()
+                    .filter(|(key, _)| !b.contains_key(*key))
+                    .map(|(key, item)| (key.clone(), item.clone()))
+                    .collect::<Vec<_>>()
+            };
+
+            let changes = ApiChanges {
+                added: only_in(after, before),
+                removed: only_in(before, after),
+            };
+            (!changes.added.is_empty() || !changes.removed.is_empty())
+                .then(|| (crate_dir.clone(), changes))
+        })
+        .collect()
+}
+
+/// Lists public items added or removed between two revisions of a repository.
+///
+/// # Arguments
+///
+/// * `dir` - Directory within the repository to compare
+/// * `from` - Base revision
+/// * `to` - Target revision
+///
+/// # Errors
+///
+/// Returns an error if `dir` is not inside a git repository or a revision cannot be read
+fn run_api_diff(dir: &Path, from: &str, to: &str) -> Result<(), RulocError> {
+    let toplevel = PathBuf::from(run_git(dir, &["rev-parse", "--show-toplevel"])?);
+    let prefix = run_git(dir, &["rev-parse", "--show-prefix"])?;
+
+    let changes = diff_api(
+        &api_snapshot(&toplevel, &prefix, from)?,
+        &api_snapshot(&toplevel, &prefix, to)?,
+    );
+
+    if changes.is_empty() {
+        println!("No public API changes between {} and {}", from, to);
+        return Ok(());
+    }
+
+    for (crate_dir, crate_changes) in &changes {
+        println!("Crate {}:", crate_dir);
+        for (sign, entries) in [("+", &crate_changes.added), ("-", &crate_changes.removed)] {
+            for (identity, item) in entries {
+                println!("  {} {} ({} lines)", sign, identity, item.lines);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Seconds in a week, the sampling interval of `history --step weekly`.
+const SECONDS_PER_WEEK: u64 = 7 * 24 * 60 * 60;
+
+/// Header of the CSV time series printed by the `history` subcommand.
+const HISTORY_CSV_HEADER: &str = "revision,commit,date,files,all,blank,comment,rustdoc,code,production-code,test-code,test-ratio";
+
+/// A commit on the first-parent history of HEAD.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HistoryCommit {
+    /// Full commit hash.
+    hash: String,
+
+    /// Committer time, in seconds since the Unix epoch.
+    timestamp: u64,
+}
+
+/// Statistics of one revision analyzed by the `history` subcommand.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct HistoryPoint {
+    /// Tag names, or the abbreviated commit hash for untagged revisions.
+    revision: String,
+
+    /// Full commit hash.
+    commit: String,
+
+    /// Committer date as an RFC 3339 UTC timestamp.
+    date: String,
+
+    /// Aggregate statistics of the revision.
+    summary: Summary,
+}
+
+/// Lists the first-parent history of HEAD, oldest commit first.
+///
+/// # Arguments
+///
+/// * `toplevel` - Root of the git repository
+/// * `since` - Oldest revision to include; the whole history when `None`
+///
+/// # Errors
+///
+/// Returns an error if the history cannot be read or `since` is not on the first-parent
+/// history of HEAD
+fn first_parent_commits(
+    toplevel: &Path,
+    since: Option<&str>,
+) -> Result<Vec<HistoryCommit>, RulocError> {
+    let log = run_git(
+        toplevel,
+        &[
+            "log",
+            "--first-parent",
+            "--reverse",
+            "--format=%H %ct",
+            "HEAD",
+        ],
+    )?;
+    let mut commits = log
+        .lines()
+        .filter_map(|line| {
+            let (hash, timestamp) = line.split_once(' ')?;
+            Some(HistoryCommit {
+                hash: hash.to_string(),
+                timestamp: timestamp.parse().ok()?,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(since) = since {
+        let hash = run_git(
+            toplevel,
+            &["rev-parse", "--verify", &format!("{}^{{commit}}", since)],
+        )?;
+        let start = commits
+            .iter()
+            .position(|commit| commit.hash == hash)
+            .ok_or_else(|| {
+                RulocError::Git(format!(
+                    "'{}' is not on the first-parent history of HEAD",
+                    since
+                ))
+            })?;
+        commits.drain(..start);
+    }
+
+    Ok(commits)
+}
+
+/// Maps commits to the tags pointing at them, peeling annotated tags.
+///
+/// # Arguments
+///
+/// * `toplevel` - Root of the git repository
+///
+/// # Returns
+///
+/// Tag names per commit hash, oldest tag first
+///
+/// # Errors
+///
+/// Returns an error if the tags cannot be listed
+fn tags_by_commit(toplevel: &Path) -> Result<HashMap<String, Vec<String>>, RulocError> {
+    let listing = run_git(
+        toplevel,
+        &[
+            "for-each-ref",
+            "--sort=creatordate",
+            "--format=%(objectname)%09%(*objectname)%09%(refname:short)",
+            "refs/tags",
+        ],
+    )?;
+
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    for line in listing.lines() {
+        let mut fields = line.split('\t');
+        let (Some(object), Some(peeled), Some(name)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let commit = if peeled.is_empty() { object } else { peeled };
+        tags.entry(commit.to_string())
+            .or_default()
+            .push(name.to_string());
+    }
+    Ok(tags)
+}
+
+/// Picks the revisions analyzed by the `history` subcommand.
+///
+/// # Arguments
+///
+/// * `commits` - First-parent history, oldest commit first
+/// * `tags` - Tag names per commit hash
+/// * `step` - Sampling strategy
+///
+/// # Returns
+///
+/// The label and commit of every sampled revision, oldest first. Tagged revisions are
+/// labeled with their tag names, others with the abbreviated commit hash
+fn select_history_revisions<'a>(
+    commits: &'a [HistoryCommit],
+    tags: &HashMap<String, Vec<String>>,
+    step: HistoryStep,
+) -> Vec<(String, &'a HistoryCommit)> {
+    let label = |commit: &HistoryCommit| match tags.get(&commit.hash) {
+        Some(names) => names.join(","),
+        None => commit.hash.chars().take(12).collect(),
+    };
+
+    match step {
+        HistoryStep::Tag => commits
+            .iter()
+            .filter(|commit| tags.contains_key(&commit.hash))
+            .map(|commit| (label(commit), commit))
+            .collect(),
+        HistoryStep::Weekly => {
+            let Some(first) = commits.first() else {
+                return Vec::new();
+            };
+            let week = |commit: &HistoryCommit| {
+                commit.timestamp.saturating_sub(first.timestamp) / SECONDS_PER_WEEK
+            };
+            commits
+                .iter()
+                .enumerate()
+                .filter(|(index, commit)| {
+                    commits
+                        .get(index + 1)
+                        .is_none_or(|next| week(next) != week(commit))
+                })
+                .map(|(_, commit)| (label(commit), commit))
+                .collect()
+        }
+    }
+}
+
+/// Analyzes the Rust sources of a directory at a git revision.
+///
+/// Reads files straight from the object database, so the working tree is never touched.
+///
+/// # Arguments
+///
+/// * `toplevel` - Root of the git repository
+/// * `prefix` - Repository-relative directory to analyze (may be empty)
+/// * `revision` - Revision to read
+/// * `options` - Analysis options applied to every file
+///
+/// # Errors
+///
+/// Returns an error if the revision cannot be listed or a file cannot be read
+fn analyze_revision(
+    toplevel: &Path,
+    prefix: &str,
+    revision: &str,
+    options: &AnalysisOptions,
+) -> Result<Summary, RulocError> {
+    let mut summary = Summary::default();
+    RevisionSources::list(toplevel, prefix, revision)?.read(
+        options.editions.forced,
+        |path, content, edition| {
+            let relative = Path::new(path)
+                .strip_prefix(prefix)
+                .unwrap_or(Path::new(path));
+            summary.add_file(&analyze_content(
+                relative,
+                Cow::Owned(content),
+                0,
+                edition,
+                options,
+            ));
+        },
+    )?;
+    Ok(summary)
+}
+
+/// Formats the time series of the `history` subcommand as CSV.
+///
+/// # Arguments
+///
+/// * `points` - Analyzed revisions, oldest first
+///
+/// # Returns
+///
+/// The rows under [`HISTORY_CSV_HEADER`]; the test ratio is the percentage of code lines
+/// that are test code, left empty for revisions without code
+fn format_history_csv(points: &[HistoryPoint]) -> String {
+    let mut lines = vec![HISTORY_CSV_HEADER.to_string()];
+    lines.extend(points.iter().map(|point| {
+        let summary = &point.summary;
+        let test_ratio = if summary.total.code_lines == 0 {
+            String::new()
+        } else {
+            format!(
+                "{:.1}",
+                summary.test.code_lines as f64 * 100.0 / summary.total.code_lines as f64
+            )
+        };
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{}",
+            escape_csv_field(&point.revision),
+            point.commit,
+            point.date,
+            summary.files,
+            summary.total.all_lines,
+            summary.total.blank_lines,
+            summary.total.comment_lines,
+            summary.total.rustdoc_lines,
+            summary.total.code_lines,
+            summary.production.code_lines,
+            summary.test.code_lines,
+            test_ratio
+        )
+    }));
+    lines.join("\n")
+}
+
+/// Analyzes sampled revisions of a repository and prints their summaries as a time series.
+///
+/// # Arguments
+///
+/// * `dir` - Directory within the repository to analyze
+/// * `since` - Oldest revision to include
+/// * `step` - Sampling strategy
+/// * `format` - Encoding of the time series
+///
+/// # Errors
+///
+/// Returns an error if `dir` is not inside a git repository or a revision cannot be read
+fn run_history(
+    dir: &Path,
+    since: Option<&str>,
+    step: HistoryStep,
+    format: HistoryFormat,
+) -> Result<(), RulocError> {
+    let toplevel = PathBuf::from(run_git(dir, &["rev-parse", "--show-toplevel"])?);
+    let prefix = run_git(dir, &["rev-parse", "--show-prefix"])?;
+
+    let commits = first_parent_commits(&toplevel, since)?;
+    let tags = tags_by_commit(&toplevel)?;
+    let revisions = select_history_revisions(&commits, &tags, step);
+    if revisions.is_empty() {
+        warn!("No revisions to analyze; try --step weekly or an earlier --since");
+    }
+
+    let options = AnalysisOptions::default();
+    let mut points = Vec::with_capacity(revisions.len());
+    for (revision, commit) in revisions {
+        debug!("Analyzing {} ({})", revision, commit.hash);
+        points.push(HistoryPoint {
+            summary: analyze_revision(&toplevel, &prefix, &commit.hash, &options)?,
+            revision,
+            commit: commit.hash.clone(),
+            date: format_utc_timestamp(commit.timestamp),
+        });
+    }
+
+    match format {
+        HistoryFormat::Csv => println!("{}", format_history_csv(&points)),
+        HistoryFormat::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&points)
+                .map_err(|e| RulocError::Output(format!("Failed to serialize history: {}", e)))?
+        ),
+    }
+    Ok(())
+}
+
+/// Change of the total, production, and test statistics between two analyses.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+struct CategoryDelta {
+    /// Change of all lines.
+    total: LineStatsDelta,
+
+    /// Change of production lines.
+    production: LineStatsDelta,
+
+    /// Change of test lines.
+    test: LineStatsDelta,
+}
+
+impl CategoryDelta {
+    /// Computes the change between two sets of total, production, and test statistics.
+    ///
+    /// # Arguments
+    ///
+    /// * `before` - Total, production, and test statistics of the baseline
+    /// * `after` - Total, production, and test statistics compared against the baseline
+    fn between(before: [&LineStats; 3], after: [&LineStats; 3]) -> Self {
+        CategoryDelta {
+            total: LineStatsDelta::between(before[0], after[0]),
+            production: LineStatsDelta::between(before[1], after[1]),
+            test: LineStatsDelta::between(before[2], after[2]),
+        }
+    }
+}
+
+/// How a file differs between the two directories of a `compare` run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+enum FileChange {
+    /// Present only in the second directory.
+    Added,
+
+    /// Present only in the baseline directory.
+    Removed,
+
+    /// Present in both directories with different statistics.
+    Modified,
+}
+
+impl FileChange {
+    /// Returns the marker printed before the file in text and Markdown reports.
+    fn marker(self) -> &'static str {
+        match self {
+            FileChange::Added => "+",
+            FileChange::Removed => "-",
+            FileChange::Modified => "~",
+        }
+    }
+}
+
+/// Per-file entry of a [`DirectoryComparison`].
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+struct FileComparison {
+    /// Path relative to the compared directories.
+    path: String,
+
+    /// Whether the file was added, removed, or modified.
+    change: FileChange,
+
+    /// Change of the file's statistics; missing files count as empty.
+    #[serde(flatten)]
+    delta: CategoryDelta,
+}
+
+/// Delta report of the `compare` subcommand.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+struct DirectoryComparison {
+    /// Baseline directory.
+    dir_a: String,
+
+    /// Directory compared against the baseline.
+    dir_b: String,
+
+    /// Number of files analyzed in the baseline.
+    files_a: usize,
+
+    /// Number of files analyzed in the compared directory.
+    files_b: usize,
+
+    /// Change of the aggregate statistics.
+    summary: CategoryDelta,
+
+    /// Files that differ, sorted by path; identical files are omitted.
+    files: Vec<FileComparison>,
+}
+
+/// Sink collecting the statistics of every analyzed file by its path below a root.
+#[derive(Debug)]
+struct FilesByPath {
+    /// Directory being analyzed; stripped from the collected paths.
+    root: PathBuf,
+
+    /// Statistics of the analyzed files, by path relative to `root`.
+    files: BTreeMap<String, FileStats>,
+}
+
+impl FilesByPath {
+    /// Creates an empty collection of the files below `root`.
+    fn new(root: &Path) -> Self {
+        FilesByPath {
+            root: root.to_path_buf(),
+            files: BTreeMap::new(),
+        }
+    }
+}
+
+impl AnalysisSink for FilesByPath {
+    fn file(&mut self, file_stats: &FileStats) -> Result<(), RulocError> {
+        let path = Path::new(&file_stats.path);
+        let relative = display_path(path.strip_prefix(&self.root).unwrap_or(path));
+        self.files.insert(
+            relative.clone(),
+            FileStats {
+                path: relative,
+                ..file_stats.clone()
+            },
+        );
+        Ok(())
+    }
+}
+
+/// Compares the analyses of two directories.
+///
+/// # Arguments
+///
+/// * `dir_a` - Baseline directory
+/// * `dir_b` - Directory compared against the baseline
+/// * `files_a` - Statistics of the baseline files, by path relative to `dir_a`
+/// * `files_b` - Statistics of the compared files, by path relative to `dir_b`
+///
+/// # Returns
+///
+/// The comparison, listing every file that was added, removed, or changed
+fn compare_directories(
+    dir_a: &Path,
+    dir_b: &Path,
+    files_a: &BTreeMap<String, FileStats>,
+    files_b: &BTreeMap<String, FileStats>,
+) -> DirectoryComparison {
+    let mut summary_a = Summary::default();
+    files_a.values().for_each(|file| summary_a.add_file(file));
+    let mut summary_b = Summary::default();
+    files_b.values().for_each(|file| summary_b.add_file(file));
+
+    let empty = FileStats::default();
+    let paths: std::collections::BTreeSet<&String> = files_a.keys().chain(files_b.keys()).collect();
+    let files = paths
+        .into_iter()
+        .filter_map(|path| {
+            let (before, after) = (files_a.get(path), files_b.get(path));
+            let change = match (before, after) {
+                (None, _) => FileChange::Added,
+                (_, None) => FileChange::Removed,
+                _ => FileChange::Modified,
+            };
+            let before = before.unwrap_or(&empty);
+            let after = after.unwrap_or(&empty);
+            let delta = CategoryDelta::between(
+                [&before.total, &before.production, &before.test],
+                [&after.total, &after.production, &after.test],
+            );
+            (change != FileChange::Modified || delta != CategoryDelta::default()).then(|| {
+                FileComparison {
+                    path: path.clone(),
+                    change,
+                    delta,
+                }
+            })
+        })
+        .collect();
+
+    DirectoryComparison {
+        dir_a: display_path(dir_a),
+        dir_b: display_path(dir_b),
+        files_a: summary_a.files,
+        files_b: summary_b.files,
+        summary: CategoryDelta::between(
+            [&summary_a.total, &summary_a.production, &summary_a.test],
+            [&summary_b.total, &summary_b.production, &summary_b.test],
+        ),
+        files,
+    }
+}
+
+/// Renders a comparison as aligned plain-text tables.
+///
+/// # Arguments
+///
+/// * `comparison` - Comparison to render
+///
+/// # Returns
+///
+/// A category table followed by one line per changed file
+fn format_comparison_text(comparison: &DirectoryComparison) -> String {
+    let mut lines = vec![
+        format!("Comparing {} -> {}", comparison.dir_a, comparison.dir_b),
+        format!(
+            "Files: {} -> {} ({:+})",
+            comparison.files_a,
+            comparison.files_b,
+            comparison.files_b as i64 - comparison.files_a as i64
+        ),
+        String::new(),
+        format!(
+            "{:<12}{:>10}{:>10}{:>10}{:>10}{:>10}",
+            "Category", "All", "Blank", "Comment", "Rustdoc", "Code"
+        ),
+    ];
+    for (category, delta) in comparison_categories(&comparison.summary) {
+        lines.push(format!(
+            "{:<12}{:>+10}{:>+10}{:>+10}{:>+10}{:>+10}",
+            category,
+            delta.all_lines,
+            delta.blank_lines,
+            delta.comment_lines,
+            delta.rustdoc_lines,
+            delta.code_lines
+        ));
+    }
+
+    lines.push(String::new());
+    if comparison.files.is_empty() {
+        lines.push("No file differences".to_string());
+    } else {
+        lines.push(format!("Changed files ({}):", comparison.files.len()));
+        lines.extend(comparison.files.iter().map(|file| {
+            format!(
+                "  {} {}: {}",
+                file.change.marker(),
+                file.path,
+                format_line_stats_delta(&file.delta.total)
+            )
+        }));
+    }
+    lines.join("\n")
+}
+
+/// Renders a comparison as Markdown tables.
+///
+/// # Arguments
+///
+/// * `comparison` - Comparison to render
+///
+/// # Returns
+///
+/// A category table followed by a table of changed files with their code line changes
+fn format_comparison_markdown(comparison: &DirectoryComparison) -> String {
+    let mut lines = vec![
+        format!(
+            "## `{}` → `{}`",
+            comparison.dir_a.replace('`', "'"),
+            comparison.dir_b.replace('`', "'")
+        ),
+        String::new(),
+        format!(
+            "**Files:** {} → {} ({:+})",
+            comparison.files_a,
+            comparison.files_b,
+            comparison.files_b as i64 - comparison.files_a as i64
+        ),
+        String::new(),
+        "| Category | All | Blank | Comment | Rustdoc | Code |".to_string(),
+        "|:--|--:|--:|--:|--:|--:|".to_string(),
+    ];
+    for (category, delta) in comparison_categories(&comparison.summary) {
+        lines.push(format!(
+            "| {} | {:+} | {:+} | {:+} | {:+} | {:+} |",
+            category,
+            delta.all_lines,
+            delta.blank_lines,
+            delta.comment_lines,
+            delta.rustdoc_lines,
+            delta.code_lines
+        ));
+    }
+
+    if !comparison.files.is_empty() {
+        lines.push(String::new());
+        lines.push("| | File | All | Code | Production code | Test code |".to_string());
+        lines.push("|:-:|:--|--:|--:|--:|--:|".to_string());
+        lines.extend(comparison.files.iter().map(|file| {
+            format!(
+                "| {} | `{}` | {:+} | {:+} | {:+} | {:+} |",
+                file.change.marker(),
+                file.path.replace('|', "\\|"),
+                file.delta.total.all_lines,
+                file.delta.total.code_lines,
+                file.delta.production.code_lines,
+                file.delta.test.code_lines
+            )
+        }));
+    }
+    lines.join("\n")
+}
+
+/// Lists the categories of a [`CategoryDelta`] with their display names.
+fn comparison_categories(delta: &CategoryDelta) -> [(&'static str, &LineStatsDelta); 3] {
+    [
+        ("Total", &delta.total),
+        ("Production", &delta.production),
+        ("Test", &delta.test),
+    ]
+}
+
+/// Analyzes two directories and prints their differences.
+///
+/// # Arguments
+///
+/// * `dir_a` - Baseline directory
+/// * `dir_b` - Directory compared against the baseline
+/// * `format` - Encoding of the delta report
+///
+/// # Errors
+///
+/// Returns an error if either analysis fails or the report cannot be serialized
+fn run_compare(dir_a: &Path, dir_b: &Path, format: ReportFormat) -> Result<(), RulocError> {
+    let options = AnalysisOptions::default();
+    let mut files_a = FilesByPath::new(dir_a);
+    analyze_into(
+        &InputSource::Directory(dir_a.to_path_buf()),
+        &options,
+        &mut files_a,
+    )
+    .map_err(|e| e.context(format!("Failed to analyze '{}'", dir_a.display())))?;
+    let mut files_b = FilesByPath::new(dir_b);
+    analyze_into(
+        &InputSource::Directory(dir_b.to_path_buf()),
+        &options,
+        &mut files_b,
+    )
+    .map_err(|e| e.context(format!("Failed to analyze '{}'", dir_b.display())))?;
+
+    let comparison = compare_directories(dir_a, dir_b, &files_a.files, &files_b.files);
+    let report = match format {
+        ReportFormat::Text => format_comparison_text(&comparison),
+        ReportFormat::Json => serde_json::to_string_pretty(&comparison)
+            .map_err(|e| RulocError::Output(format!("Failed to serialize comparison: {}", e)))?,
+        ReportFormat::Markdown => format_comparison_markdown(&comparison),
+    };
+    println!("{}", report);
+    Ok(())
+}
+
+/// A file ranked by the `hotspots` subcommand.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+struct Hotspot {
+    /// Path relative to the analyzed directory.
+    path: String,
+
+    /// Number of commits that touched the file.
+    commits: usize,
+
+    /// Code lines of the file.
+    code_lines: usize,
+
+    /// Ranking score, `commits × code_lines`.
+    score: usize,
+}
+
+/// Counts the commits touching each path in `git log --format= --name-only` output.
+///
+/// # Returns
+///
+/// The number of commits per repository-relative path
+fn count_commits_per_path(log: &str) -> HashMap<String, usize> {
+    let mut commits = HashMap::new();
+    for path in log.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        *commits.entry(path.to_string()).or_insert(0) += 1;
+    }
+    commits
+}
+
+/// Ranks files by churn × size.
+///
+/// # Arguments
+///
+/// * `files` - Statistics of the analyzed files, by path relative to the analyzed directory
+/// * `commits` - Commit counts by repository-relative path
+/// * `prefix` - Repository-relative path of the analyzed directory, ending in `/` (may be empty)
+/// * `top` - Maximum number of files to return
+///
+/// # Returns
+///
+/// Files with a non-zero score, highest first; ties are ordered by path
+fn rank_hotspots(
+    files: &BTreeMap<String, FileStats>,
+    commits: &HashMap<String, usize>,
+    prefix: &str,
+    top: usize,
+) -> Vec<Hotspot> {
+    let mut hotspots: Vec<Hotspot> = files
+        .iter()
+        .filter_map(|(path, file)| {
+            let commits = *commits.get(&format!("{}{}", prefix, path))?;
+            let code_lines = file.total.code_lines;
+            let score = commits * code_lines;
+            (score > 0).then(|| Hotspot {
+                path: path.clone(),
+                commits,
+                code_lines,
+                score,
+            })
+        })
+        .collect();
+    hotspots.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    hotspots.truncate(top);
+    hotspots
+}
+
+/// Renders a hotspot ranking as an aligned plain-text table.
+fn format_hotspots_text(hotspots: &[Hotspot]) -> String {
+    if hotspots.is_empty() {
+        return "No hotspots: no analyzed file has both commits and code lines".to_string();
+    }
+
+    let mut lines = vec![format!(
+        "{:>4}  {:>10}  {:>7}  {:>6}  File",
+        "Rank", "Score", "Commits", "Code"
+    )];
+    lines.extend(hotspots.iter().enumerate().map(|(index, hotspot)| {
+        format!(
+            "{:>4}  {:>10}  {:>7}  {:>6}  {}",
+            index + 1,
+            hotspot.score,
+            hotspot.commits,
+            hotspot.code_lines,
+            hotspot.path
+        )
+    }));
+    lines.join("\n")
+}
+
+/// Renders a hotspot ranking as a Markdown table.
+fn format_hotspots_markdown(hotspots: &[Hotspot]) -> String {
+    let mut lines = vec![
+        "| Rank | File | Score | Commits | Code |".to_string(),
+        "|--:|:--|--:|--:|--:|".to_string(),
+    ];
+    lines.extend(hotspots.iter().enumerate().map(|(index, hotspot)| {
+        format!(
+            "| {} | `{}` | {} | {} | {} |",
+            index + 1,
+            hotspot.path.replace('|', "\\|"),
+            hotspot.score,
+            hotspot.commits,
+            hotspot.code_lines
+        )
+    }));
+    lines.join("\n")
+}
+
+/// Ranks the files of a directory by churn × size and prints the ranking.
+///
+/// # Arguments
+///
+/// * `dir` - Directory within a git repository to analyze
+/// * `top` - Number of files to list
+/// * `since` - Only count commits more recent than this date
+/// * `format` - Encoding of the ranking
+///
+/// # Errors
+///
+/// Returns an error if `dir` is not inside a git repository, the history cannot be read,
+/// or the analysis fails
+fn run_hotspots(
+    dir: &Path,
+    top: usize,
+    since: Option<&str>,
+    format: ReportFormat,
+) -> Result<(), RulocError> {
+    let toplevel = PathBuf::from(run_git(dir, &["rev-parse", "--show-toplevel"])?);
+    let prefix = run_git(dir, &["rev-parse", "--show-prefix"])?;
+
+    let since = since.map(|since| format!("--since={}", since));
+    let mut log_args = vec!["log", "--format=", "--name-only", "--no-renames"];
+    log_args.extend(since.as_deref());
+    let commits = count_commits_per_path(&run_git(&toplevel, &log_args)?);
+
+    let mut files = FilesByPath::new(dir);
+    analyze_into(
+        &InputSource::Directory(dir.to_path_buf()),
+        &AnalysisOptions::default(),
+        &mut files,
+    )?;
+
+    let hotspots = rank_hotspots(&files.files, &commits, &prefix, top);
+    let report = match format {
+        ReportFormat::Text => format_hotspots_text(&hotspots),
+        ReportFormat::Json => serde_json::to_string_pretty(&hotspots)
+            .map_err(|e| RulocError::Output(format!("Failed to serialize hotspots: {}", e)))?,
+        ReportFormat::Markdown => format_hotspots_markdown(&hotspots),
+    };
+    println!("{}", report);
+    Ok(())
+}
+
+/// Base URL of the crates.io registry API.
+#[cfg(feature = "crates-io")]
+const CRATES_IO_API: &str = "https://crates.io/api/v1/crates";
+
+/// Base URL from which published `.crate` archives are downloaded.
+#[cfg(any(feature = "crates-io", test))]
+const CRATES_IO_DOWNLOADS: &str = "https://static.crates.io/crates";
+
+/// A published crate named on the command line, as `NAME` or `NAME@VERSION`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CrateSpec {
+    /// Crate name.
+    name: String,
+
+    /// Requested version; the latest stable release when `None`.
+    version: Option<String>,
+}
+
+impl CrateSpec {
+    /// Parses a crate specification.
+    ///
+    /// # Arguments
+    ///
+    /// * `spec` - `NAME` or `NAME@VERSION` (e.g., `serde@1.0.200`)
+    ///
+    /// # Returns
+    ///
+    /// The parsed [`CrateSpec`]
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name or version is empty or contains characters crates.io
+    /// does not allow
+    fn parse(spec: &str) -> Result<Self, RulocError> {
+        let (name, version) = match spec.trim().split_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (spec.trim(), None),
+        };
+
+        let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if name.is_empty() || !name.chars().all(is_name_char) {
+            return Err(RulocError::Config(format!(
+                "Invalid crate name in '{}'",
+                spec
+            )));
+        }
+        let is_version_char = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+');
+        if let Some(version) = version
+            && (version.is_empty() || !version.chars().all(is_version_char))
+        {
+            return Err(RulocError::Config(format!(
+                "Invalid crate version in '{}'",
+                spec
+            )));
+        }
+
+        Ok(CrateSpec {
+            name: name.to_string(),
+            version: version.map(str::to_string),
+        })
+    }
+
+    /// Returns the download URL of the `.crate` archive of a version of this crate.
+    #[cfg(any(feature = "crates-io", test))]
+    fn archive_url(&self, version: &str) -> String {
+        format!(
+            "{}/{}/{}-{}.crate",
+            CRATES_IO_DOWNLOADS, self.name, self.name, version
+        )
+    }
+}
+
+/// Extracts the latest stable version from a crates.io crate metadata response.
+///
+/// # Arguments
+///
+/// * `metadata` - Response of `GET /api/v1/crates/<name>`
+///
+/// # Returns
+///
+/// The `max_stable_version`, or `max_version` for crates without stable releases
+///
+/// # Errors
+///
+/// Returns an error if the response is not JSON or names no version
+#[cfg(any(feature = "crates-io", test))]
+fn parse_latest_version(metadata: &str) -> Result<String, RulocError> {
+    let value: serde_json::Value = serde_json::from_str(metadata)
+        .map_err(|e| RulocError::Parse(format!("Failed to parse crates.io response: {}", e)))?;
+    ["max_stable_version", "max_version"]
+        .iter()
+        .find_map(|field| value["crate"][field].as_str())
+        .map(str::to_string)
+        .ok_or_else(|| RulocError::Parse("crates.io response names no version".to_string()))
+}
+
+/// Runs an external program for the `crate` subcommand and captures its standard output.
+///
+/// # Arguments
+///
+/// * `program` - Program to run, such as `curl` or `tar`
+/// * `args` - Arguments passed to the program
+///
+/// # Errors
+///
+/// Returns an error if the program is not installed or exits unsuccessfully
+#[cfg(feature = "crates-io")]
+fn run_fetch_tool(program: &str, args: &[&str]) -> Result<Vec<u8>, RulocError> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .output()
+        .map_err(RulocError::io(format!(
+            "Failed to run {}. Ensure it is installed and on PATH",
+            program
+        )))?;
+
+    if !output.status.success() {
+        return Err(RulocError::Io {
+            context: format!("{} {} failed", program, args.join(" ")),
+            source: std::io::Error::other(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ),
+        });
+    }
+    Ok(output.stdout)
+}
+
+/// Downloads a crate from crates.io and unpacks it, using `curl` and `tar`.
+///
+/// # Arguments
+///
+/// * `spec` - Crate to download
+/// * `dest` - Directory to download and unpack the archive into
+///
+/// # Returns
+///
+/// The directory of the unpacked crate
+///
+/// # Errors
+///
+/// Returns an error if the version cannot be resolved or the archive cannot be downloaded
+/// or unpacked
+#[cfg(feature = "crates-io")]
+fn fetch_crate(spec: &CrateSpec, dest: &Path) -> Result<PathBuf, RulocError> {
+    let user_agent = format!(
+        "ruloc/{} ({})",
+        env!("CARGO_PKG_VERSION"),
+        env!("CARGO_PKG_REPOSITORY")
+    );
+    let curl = |url: &str, output: Option<&Path>| {
+        let mut args = vec![
+            "--fail",
+            "--silent",
+            "--show-error",
+            "--location",
+            "--user-agent",
+            &user_agent,
+        ];
+        let output = output.map(|path| path.to_string_lossy().into_owned());
+        if let Some(output) = &output {
+            args.extend(["--output", output]);
+        }
+        args.push(url);
+        run_fetch_tool("curl", &args)
+    };
+
+    let version = match &spec.version {
+        Some(version) => version.clone(),
+        None => {
+            let metadata = curl(&format!("{}/{}", CRATES_IO_API, spec.name), None)?;
+            parse_latest_version(&String::from_utf8_lossy(&metadata))?
+        }
+    };
+
+    let archive = dest.join(format!("{}-{}.crate", spec.name, version));
+    info!("Downloading {} {}", spec.name, version);
+    curl(&spec.archive_url(&version), Some(&archive))?;
+    run_fetch_tool(
+        "tar",
+        &[
+            "-xzf",
+            &archive.to_string_lossy(),
+            "-C",
+            &dest.to_string_lossy(),
+        ],
+    )?;
+
+    Ok(dest.join(format!("{}-{}", spec.name, version)))
+}
+
+/// Reports that this build cannot download crates.
+///
+/// # Errors
+///
+/// Always returns a configuration error naming the missing `crates-io` feature
+#[cfg(not(feature = "crates-io"))]
+fn fetch_crate(spec: &CrateSpec, _dest: &Path) -> Result<PathBuf, RulocError> {
+    Err(RulocError::Config(format!(
+        "Cannot analyze '{}': downloading crates requires building ruloc with --features crates-io",
+        spec.name
+    )))
+}
+
+/// Downloads a published crate into a temporary directory and analyzes it.
+///
+/// # Arguments
+///
+/// * `spec` - Crate to analyze
+/// * `flags` - Analysis flags applied to the unpacked crate
+///
+/// # Errors
+///
+/// Returns an error if the flags are invalid, the crate cannot be fetched, or the analysis
+/// fails
+fn run_crate(spec: &CrateSpec, flags: &[String]) -> Result<(), RulocError> {
+    let temp_dir =
+        tempfile::tempdir().map_err(RulocError::io("Failed to create a temporary directory"))?;
+    let crate_dir = fetch_crate(spec, temp_dir.path())?;
+
+    let args = Args::try_parse_from(
+        [
+            "ruloc".to_string(),
+            "--dir".to_string(),
+            display_path(&crate_dir),
+        ]
+        .into_iter()
+        .chain(flags.iter().cloned()),
+    )
+    .map_err(|e| RulocError::Config(e.to_string().trim().to_string()))?;
+    run_analysis(&args)
+}
+
+/// Appends a review note to the entry of a report for one file.
+///
+/// # Arguments
+///
+/// * `report` - Report to modify
+/// * `path` - Path of the file entry, as recorded in the report
+/// * `note` - Note text
+///
+/// # Errors
+///
+/// Returns an error if the note is blank or the report has no entry for `path`
+fn annotate_report(report: &mut Report, path: &str, note: &str) -> Result<(), RulocError> {
+    let note = note.trim();
+    if note.is_empty() {
+        return Err(RulocError::Config("Note must not be empty".to_string()));
+    }
+
+    let file = report
+        .files
+        .iter_mut()
+        .find(|file| file.path == path)
+        .ok_or_else(|| {
+            RulocError::Config(format!(
+                "Report has no entry for '{}'. Use the path exactly as it appears in the report.",
+                path
+            ))
+        })?;
+    file.notes.push(note.to_string());
+    Ok(())
+}
+
+/// Attaches a review note to a saved JSON report, rewriting the report in place.
+///
+/// The report is written to a temporary file beside the original and then renamed over
+/// it, so an interrupted run never leaves a truncated report behind.
+///
+/// # Arguments
+///
+/// * `report_path` - JSON report produced by `--out-json`
+/// * `path` - Path of the file entry to annotate
+/// * `note` - Note text
+///
+/// # Errors
+///
+/// Returns an error if the report cannot be read, parsed, annotated, or written back
+fn run_annotate(report_path: &Path, path: &str, note: &str) -> Result<(), RulocError> {
+    let content = fs::read_to_string(report_path).map_err(RulocError::io(format!(
+        "Failed to read report '{}'",
+        report_path.display()
+    )))?;
+    let mut report: Report = serde_json::from_str(&content).map_err(|e| {
+        RulocError::Parse(format!(
+            "Failed to parse report '{}': {}",
+            report_path.display(),
+            e
+        ))
+    })?;
+
+    annotate_report(&mut report, path, note)?;
+
+    let json = serde_json::to_string_pretty(&report)
+        .map_err(|e| RulocError::Output(format!("Failed to serialize JSON: {}", e)))?;
+    let directory = report_path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut temp_file = NamedTempFile::new_in(directory)
+        .map_err(RulocError::io("Failed to create temporary report file"))?;
+    writeln!(temp_file, "{}", json)
+        .map_err(RulocError::io("Failed to write temporary report file"))?;
+    temp_file
+        .persist(report_path)
+        .map_err(|e| e.error)
+        .map_err(RulocError::io(format!(
+            "Failed to replace '{}'",
+            report_path.display()
+        )))?;
+
+    Ok(())
+}
+
+/// Parses a file size string with optional unit suffix.
+///
+/// Supports units: KB, MB, GB (case-insensitive). Without a unit, interprets as bytes.
+/// Allows decimal numbers (e.g., "3.5KB").
+///
+/// # Arguments
+///
+/// * `size_str` - The size string to parse (e.g., "1000", "3.5KB", "10MB")
+///
+/// # Returns
+///
+/// The size in bytes as `u64`
+///
+/// # Errors
+///
+/// Returns an error if the string cannot be parsed as a valid size
+fn parse_file_size(size_str: &str) -> Result<u64, RulocError> {
+    let size_str = size_str.trim();
+
+    // Try to match unit suffix
+    let (number_str, multiplier) =
+        if let Some(pos) = size_str.to_uppercase().find(|c: char| c.is_alphabetic()) {
+            let (num, unit) = size_str.split_at(pos);
+            let mult = match unit.to_uppercase().as_str() {
+                "KB" => 1024u64,
+                "MB" => 1024u64 * 1024,
+                "GB" => 1024u64 * 1024 * 1024,
+                _ => {
+                    return Err(RulocError::Config(format!(
+                        "Invalid size unit: '{}'. Supported units: KB, MB, GB",
+                        unit
+                    )));
+                }
+            };
+            (num, mult)
+        } else {
+            (size_str, 1u64)
+        };
+
+    // Parse the numeric part
+    let number: f64 = number_str
+        .trim()
+        .parse()
+        .map_err(|_| RulocError::Config(format!("Invalid size number: '{}'", number_str)))?;
+
+    if number < 0.0 {
+        return Err(RulocError::Config(
+            "File size cannot be negative".to_string(),
+        ));
+    }
+
+    let bytes = (number * multiplier as f64) as u64;
+    Ok(bytes)
+}
+
+/// Parses a line count with an optional `k` (thousand) or `m` (million) suffix.
+///
+/// # Arguments
+///
+/// * `count_str` - The count to parse (e.g., "800", "500k", "1.5m")
+///
+/// # Returns
+///
+/// The number of lines
+///
+/// # Errors
+///
+/// Returns an error if the string is not a non-negative number with a known suffix
+fn parse_line_count(count_str: &str) -> Result<u64, RulocError> {
+    let count_str = count_str.trim();
+    let (number_str, multiplier) = match count_str.char_indices().last() {
+        Some((pos, 'k' | 'K')) => (&count_str[..pos], 1_000.0),
+        Some((pos, 'm' | 'M')) => (&count_str[..pos], 1_000_000.0),
+        _ => (count_str, 1.0),
+    };
+    let number: f64 = number_str
+        .trim()
+        .parse()
+        .ok()
+        .filter(|number: &f64| number.is_finite() && *number >= 0.0)
+        .ok_or_else(|| {
+            RulocError::Config(format!(
+                "Invalid line count: '{}'. Use a number with an optional k or m suffix, e.g. 500k",
+                count_str
+            ))
+        })?;
+    Ok((number * multiplier).round() as u64)
+}
+
+/// Parses a `KEY=VALUE` report label.
+///
+/// # Arguments
+///
+/// * `label` - The raw label argument (e.g., `team=payments`)
+///
+/// # Returns
+///
+/// The trimmed key and value
+///
+/// # Errors
+///
+/// Returns an error if the separator is missing or the key is empty
+fn parse_label(label: &str) -> Result<(String, String), RulocError> {
+    let (key, value) = label.split_once('=').ok_or_else(|| {
+        RulocError::Config(format!("Invalid label '{}': expected KEY=VALUE", label))
+    })?;
+
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(RulocError::Config(format!(
+            "Invalid label '{}': key must not be empty",
+            label
+        )));
+    }
+
+    Ok((key.to_string(), value.trim().to_string()))
+}
+
+/// Parses a percentage threshold such as `80` or `62.5`.
+///
+/// # Arguments
+///
+/// * `value` - The raw argument, optionally suffixed with `%`
+///
+/// # Returns
+///
+/// The percentage in the range `0.0..=100.0`
+///
+/// # Errors
+///
+/// Returns an error if the value is not a number or lies outside the range
+fn parse_percentage(value: &str) -> Result<f64, RulocError> {
+    let percentage: f64 = value
+        .trim()
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| RulocError::Config(format!("Invalid percentage '{}'", value)))?;
+    if !(0.0..=100.0).contains(&percentage) {
+        return Err(RulocError::Config(format!(
+            "Invalid percentage '{}': must be between 0 and 100",
+            value
+        )));
+    }
+    Ok(percentage)
+}
+
+/// Parses a 1-based, inclusive line range such as `100-250`, or a single line such as `42`.
+///
+/// # Arguments
+///
+/// * `value` - The raw argument
+///
+/// # Returns
+///
+/// The range of line numbers
+///
+/// # Errors
+///
+/// Returns an error if a bound is not a positive integer or the range is empty
+fn parse_line_range(value: &str) -> Result<RangeInclusive<usize>, RulocError> {
+    let (start, end) = value.split_once('-').unwrap_or((value, value));
+    let bound = |bound: &str| {
+        bound
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|line| *line > 0)
+            .ok_or_else(|| {
+                RulocError::Config(format!(
+                    "Invalid line range '{}': expected START-END",
+                    value
+                ))
+            })
+    };
+    let (start, end) = (bound(start)?, bound(end)?);
+    if start > end {
+        return Err(RulocError::Config(format!(
+            "Invalid line range '{}': START must not exceed END",
+            value
+        )));
+    }
+    Ok(start..=end)
+}
+
+/// Parses the Rust edition given with `--edition`.
+///
+/// # Arguments
+///
+/// * `value` - The edition year (e.g., "2018")
+///
+/// # Returns
+///
+/// The parsed edition
+///
+/// # Errors
+///
+/// Returns an error if the value is not a known edition
+fn parse_edition(value: &str) -> Result<Edition, RulocError> {
+    value.trim().parse().map_err(|_| {
+        RulocError::Config(format!(
+            "Invalid edition '{}': expected 2015, 2018, 2021, or 2024",
+            value
+        ))
+    })
+}
+
+/// Parses the size of a `--sample`, either a percentage (`10%`) or a number of files.
+///
+/// # Arguments
+///
+/// * `value` - The size string to parse (e.g., "10%", "2.5%", "500")
+///
+/// # Returns
+///
+/// The parsed sample size
+///
+/// # Errors
+///
+/// Returns an error if the percentage is not within (0, 100] or the count is zero
+fn parse_sample_size(value: &str) -> Result<SampleSize, RulocError> {
+    let value = value.trim();
+    if let Some(percent) = value.strip_suffix('%') {
+        return percent
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|percent| *percent > 0.0 && *percent <= 100.0)
+            .map(SampleSize::Percent)
+            .ok_or_else(|| {
+                RulocError::Config(format!(
+                    "Invalid sample '{}': expected a percentage between 0% and 100%",
+                    value
+                ))
+            });
+    }
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|files| *files > 0)
+        .map(SampleSize::Files)
+        .ok_or_else(|| {
+            RulocError::Config(format!(
+                "Invalid sample '{}': expected a percentage (10%) or a number of files",
+                value
+            ))
+        })
+}
+
+/// Machine-readable description of a command, emitted by `--help-json`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+struct CommandDescription {
+    /// Command name as typed on the command line.
+    name: String,
+
+    /// Program version, present on the top-level command only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+
+    /// One-line description of the command.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    about: Option<String>,
+
+    /// Flags and positional arguments in declaration order.
+    args: Vec<ArgDescription>,
+
+    /// Groups of arguments of which exactly one must be given.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    required_groups: Vec<Vec<String>>,
+
+    /// Nested subcommands.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    subcommands: Vec<CommandDescription>,
+}
+
+/// Machine-readable description of a single flag or positional argument.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+struct ArgDescription {
+    /// Long flag name without dashes, or the argument id for positionals.
+    name: String,
+
+    /// Single-character short flag, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    short: Option<char>,
+
+    /// Help text shown by `--help`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help: Option<String>,
+
+    /// Value type: `boolean`, `integer`, `number`, `path`, `enum`, or `string`.
+    value_type: &'static str,
+
+    /// Placeholder shown for the value in usage strings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value_name: Option<String>,
+
+    /// Whether the argument is positional rather than a flag.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    positional: bool,
+
+    /// Whether the argument must always be given.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    required: bool,
+
+    /// Whether the argument must be given on its own.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    exclusive: bool,
+
+    /// Whether the argument accepts several values, by repetition or delimiter.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    multiple: bool,
+
+    /// Values used when the argument is absent.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    default: Vec<String>,
+
+    /// Accepted values for enumerated arguments.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    possible_values: Vec<String>,
+
+    /// Alternative long names.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
+
+    /// Arguments that cannot be combined with this one.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    conflicts_with: Vec<String>,
+}
+
+/// Returns the name under which an argument appears in `--help-json` output.
+fn arg_display_name(arg: &clap::Arg) -> String {
+    arg.get_long()
+        .map(str::to_string)
+        .unwrap_or_else(|| arg.get_id().to_string())
+}
+
+/// Classifies the type of value an argument accepts.
+///
+/// # Arguments
+///
+/// * `arg` - The argument to classify
+///
+/// # Returns
+///
+/// One of `boolean`, `integer`, `number`, `path`, `enum`, or `string`; arguments with
+/// custom parsers report `string`, the form in which their values are written
+fn arg_value_type(arg: &clap::Arg) -> &'static str {
+    use std::any::TypeId;
+
+    if !arg.get_action().takes_values() {
+        return "boolean";
+    }
+    if !arg.get_possible_values().is_empty() {
+        return "enum";
+    }
+
+    let type_id = arg.get_value_parser().type_id();
+    if type_id == TypeId::of::<PathBuf>() {
+        "path"
+    } else if [
+        TypeId::of::<usize>(),
+        TypeId::of::<u64>(),
+        TypeId::of::<u32>(),
+        TypeId::of::<NonZeroUsize>(),
+    ]
+    .iter()
+    .any(|integer| type_id == *integer)
+    {
+        "integer"
+    } else if type_id == TypeId::of::<f64>() {
+        "number"
+    } else {
+        "string"
+    }
+}
+
+/// Describes a command, its arguments, and its subcommands for `--help-json`.
+///
+/// # Arguments
+///
+/// * `command` - A built clap command
+///
+/// # Returns
+///
+/// The description of the command tree rooted at `command`
+fn describe_command(command: &clap::Command) -> CommandDescription {
+    let args = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set())
+        .map(|arg| ArgDescription {
+            name: arg_display_name(arg),
+            short: arg.get_short(),
+            help: arg.get_help().map(|help| help.to_string()),
+            value_type: arg_value_type(arg),
+            value_name: arg
+                .get_value_names()
+                .and_then(|names| names.first())
+                .map(|name| name.to_string()),
+            positional: arg.is_positional(),
+            required: arg.is_required_set(),
+            exclusive: arg.is_exclusive_set(),
+            multiple: matches!(arg.get_action(), clap::ArgAction::Append)
+                || arg.get_value_delimiter().is_some(),
+            default: arg
+                .get_default_values()
+                .iter()
+                .map(|value| value.to_string_lossy().to_string())
+                .collect(),
+            possible_values: arg
+                .get_possible_values()
+                .iter()
+                .filter(|value| !value.is_hide_set())
+                .map(|value| value.get_name().to_string())
+                .collect(),
+            aliases: arg
+                .get_visible_aliases()
+                .unwrap_or_default()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            conflicts_with: command
+                .get_arg_conflicts_with(arg)
+                .into_iter()
+                .map(arg_display_name)
+                .collect(),
+        })
+        .collect();
+
+    let required_groups = command
+        .get_groups()
+        .filter(|group| group.is_required_set())
+        .map(|group| {
+            group
+                .get_args()
+                .filter_map(|id| command.get_arguments().find(|arg| arg.get_id() == id))
+                .map(arg_display_name)
+                .collect()
+        })
+        .collect();
+
+    CommandDescription {
+        name: command.get_name().to_string(),
+        version: command.get_version().map(str::to_string),
+        about: command.get_about().map(|about| about.to_string()),
+        args,
+        required_groups,
+        subcommands: command
+            .get_subcommands()
+            .filter(|subcommand| !subcommand.is_hide_set() && subcommand.get_name() != "help")
+            .map(describe_command)
+            .collect(),
+    }
+}
+
+/// Prints the `--help-json` description of the whole command-line interface.
+///
+/// # Errors
+///
+/// Returns an error if JSON serialization fails
+fn print_help_json() -> Result<(), RulocError> {
+    let mut command = Args::command();
+    command.build();
+    let json = serde_json::to_string_pretty(&describe_command(&command))
+        .map_err(|e| RulocError::Output(format!("Failed to serialize JSON: {}", e)))?;
+    println!("{}", json);
+    Ok(())
+}
+
+/// Entry point for the ruloc CLI application.
+///
+/// Parses command-line arguments, initializes logging, and either executes a
+/// subcommand or analyzes the specified file or directory, outputting the results
+/// in the requested format.
+///
+/// # Returns
+///
+/// Success, or the exit code of the [`RulocError`] that aborted the run after printing it
+/// to stderr
+pub fn run() -> ExitCode {
+    let args = Args::parse();
+    init_logging(&args);
+
+    let result = match &args.command {
+        _ if args.help_json => print_help_json(),
+        Some(Command::Run { plan }) => run_plan(plan),
+        Some(Command::CrossCheck { dir, tool }) => run_cross_check(dir, *tool),
+        Some(Command::ApiDiff { from, to, dir }) => run_api_diff(dir, from, to),
+        Some(Command::History {
+            since,
+            step,
+            dir,
+            format,
+        }) => run_history(dir, since.as_deref(), *step, *format),
+        Some(Command::Compare {
+            dir_a,
+            dir_b,
+            format,
+        }) => run_compare(dir_a, dir_b, *format),
+        Some(Command::Hotspots {
+            dir,
+            top,
+            since,
+            format,
+        }) => run_hotspots(dir, *top, since.as_deref(), *format),
+        Some(Command::Crate { spec, flags }) => run_crate(spec, flags),
+        Some(Command::Annotate { report, path, note }) => run_annotate(report, path, note),
+        Some(Command::Project { db, budget, runs }) => run_project(db, *budget, *runs),
+        None => run_analysis(&args),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            match args.log_format {
+                LogFormat::Text => eprintln!("Error: {}", e),
+                LogFormat::Json => error!("{}", e),
+            }
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+/// Installs the subscriber that carries ruloc's diagnostics on stderr.
+///
+/// Warnings and errors are shown by default, along with ruloc's own progress messages
+/// (fetching, downloading, running plan analyses); `--verbose` shows everything and
+/// `--quiet` errors only. `RUST_LOG` refines the filter as usual.
+///
+/// # Arguments
+///
+/// * `args` - Arguments carrying the verbosity and `--log-format`
+fn init_logging(args: &Args) {
+    log_subscriber(args, || ProgressAwareStderr).init();
+}
+
+/// Builds the `tracing` subscriber installed by [`init_logging`].
+///
+/// Text records are colored only on a terminal; `--log-format json` uses the formatter's
+/// own JSON encoding with the event fields flattened, one object per line.
+///
+/// # Arguments
+///
+/// * `args` - Arguments carrying the verbosity and `--log-format`
+/// * `writer` - Destination of the formatted records
+///
+/// # Returns
+///
+/// A subscriber filtering records by verbosity and `RUST_LOG` and writing them to `writer`
+///
+/// # Example
+///
+/// ```ignore
+/// {"timestamp":"2025-01-01T00:00:00.000000Z","level":"WARN","message":"Skipping broken symlink src/x.rs","target":"ruloc"}
+/// ```
+fn log_subscriber<W>(args: &Args, writer: W) -> Box<dyn tracing::Subscriber + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let level = if args.verbose {
+        "trace".to_string()
+    } else if args.quiet {
+        "error".to_string()
+    } else {
+        format!("warn,{}=info", module_path!())
+    };
+    let directives = match std::env::var("RUST_LOG") {
+        Ok(env) if !env.is_empty() => format!("{},{}", level, env),
+        _ => level,
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::builder().parse_lossy(directives))
+        .with_writer(writer);
+    match args.log_format {
+        LogFormat::Text => Box::new(builder.with_ansi(std::io::stderr().is_terminal()).finish()),
+        LogFormat::Json => Box::new(builder.json().flatten_event(true).finish()),
+    }
+}
+
+/// Runs a single analysis described by command-line arguments and prints its report.
+///
+/// Accumulates files in memory or on disk as selected by `--accumulator`; the default
+/// spills to disk only for large codebases, avoiding excessive memory consumption.
+///
+/// # Arguments
+///
+/// * `args` - Arguments identifying the input and the presentation settings
+///
+/// # Returns
+///
+/// `Ok(())` on success, or the [`RulocError`] that aborted the analysis
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The maximum file size cannot be parsed
+/// - File reading fails
+/// - Directory contains no Rust files
+/// - JSON serialization fails
+/// - Temporary file operations fail
+/// - A `--fail-on-*` or `--strict` gate fails
+fn run_analysis(args: &Args) -> Result<(), RulocError> {
+    if let Some(url) = &args.git {
+        return run_remote_analysis(args, url);
+    }
+
+    // Parse max file size and opt-in analyses
+    let options = args.analysis_options()?;
+    let targets = args.report_targets()?;
+
+    // Handle debug mode separately
+    if args.debug {
+        return handle_debug_mode(args, &options);
+    }
+    if args.dry_run {
+        return handle_dry_run(args, &options);
+    }
+
+    let started = Instant::now();
+    if args.output_format() == OutputFormat::Ndjson {
+        let (summary, skipped) = match args
+            .output
+            .as_deref()
+            .filter(|path| path.as_os_str() != "-")
+        {
+            Some(path) => write_atomically(path, |writer| stream_ndjson(args, &options, writer))?,
+            None => stream_ndjson(args, &options, std::io::stdout())?,
+        };
+        write_badge(args, &summary)?;
+        print_timings(args, &options, started);
+        return check_gates(args, &summary, &skipped);
+    }
+
+    // Keep small analyses in memory and spill large ones to disk (see --accumulator)
+    let mut accumulator = SpillingAccumulator::new(args.accumulator)?;
+
+    // Determine what to analyze and collect stats into accumulator
+    let mut output_options = args.output_options()?;
+    output_options.record(analyze_input(
+        &args.input_source(),
+        &options,
+        &mut accumulator,
+        args.stream_csv.as_deref(),
+    )?);
+
+    // Flush accumulator to ensure all data is written
+    options.timed(TimingPhase::Aggregate, || accumulator.flush())?;
+
+    if let Some(sampling) = &options.sampling {
+        let files = accumulator.iter_files()?;
+        output_options.sample = Some(sampling.estimate(files));
+    }
+    if args.group_by == GroupBy::Module {
+        output_options.modules = Some(ModuleMap::resolve(&args.input_source(), options.symlinks));
+    }
+
+    // Output results using the accumulator
+    options.timed(TimingPhase::Output, || {
+        output_reports(&accumulator, &targets, &output_options)
+    })?;
+
+    // Annotations and SARIF results point at source files, so they stay per file
+    output_options.modules = None;
+    if args.out_github_summary {
+        options.timed(TimingPhase::Output, || {
+            write_github_summary(args, &accumulator, &output_options)
+        })?;
+    }
+    if let Some(path) = &args.out_sarif {
+        options.timed(TimingPhase::Output, || {
+            write_sarif(args, &accumulator, &output_options, path)
+        })?;
+    }
+    write_badge(args, &accumulator.get_summary())?;
+    print_timings(args, &options, started);
+
+    check_gates(args, &accumulator.get_summary(), &output_options.skipped)
+}
+
+/// Runs an analysis of a remote repository given by `--git`.
+///
+/// Fetches only the requested revision into a temporary directory, analyzes it as if it
+/// were passed to `--dir`, and removes the checkout afterwards.
+///
+/// # Arguments
+///
+/// * `args` - Arguments of the analysis, including `--rev`
+/// * `url` - URL of the repository to clone
+///
+/// # Errors
+///
+/// Returns an error if the repository cannot be fetched or the analysis fails
+fn run_remote_analysis(args: &Args, url: &str) -> Result<(), RulocError> {
+    let checkout =
+        tempfile::tempdir().map_err(RulocError::io("Failed to create a temporary directory"))?;
+    let rev = args.rev.as_deref().unwrap_or("HEAD");
+    info!("Fetching {} ({})", url, rev);
+    shallow_clone(url, rev, checkout.path())?;
+
+    run_analysis(&Args {
+        dir: Some(checkout.path().to_path_buf()),
+        git: None,
+        rev: None,
+        ..args.clone()
+    })
+}
+
+/// Checks out a single revision of a repository without its history.
+///
+/// # Arguments
+///
+/// * `url` - URL or path of the repository
+/// * `rev` - Branch, tag, or commit to check out
+/// * `dest` - Empty directory that receives the working tree
+///
+/// # Errors
+///
+/// Returns an error if the URL looks like an option or git cannot fetch the revision
+fn shallow_clone(url: &str, rev: &str, dest: &Path) -> Result<(), RulocError> {
+    if url.starts_with('-') || rev.starts_with('-') {
+        return Err(RulocError::Config(format!(
+            "Invalid repository '{}' or revision '{}'",
+            url, rev
+        )));
+    }
+
+    run_git(dest, &["init", "--quiet"])?;
+    run_git(dest, &["fetch", "--quiet", "--depth", "1", url, rev])?;
+    run_git(dest, &["checkout", "--quiet", "FETCH_HEAD"])?;
+    Ok(())
+}
+
+/// Prints the timings collected during a run to stderr, when `--timings` is used.
+///
+/// # Arguments
+///
+/// * `args` - Arguments carrying the number of slowest files to list
+/// * `options` - Analysis options holding the collected timings
+/// * `started` - When the run started
+fn print_timings(args: &Args, options: &AnalysisOptions, started: Instant) {
+    if let Some(timings) = &options.timings {
+        eprint!(
+            "{}",
+            format_timings(timings, started.elapsed(), args.timings_top)
+        );
+    }
+}
+
+/// Analyzes an input source into an accumulator.
+///
+/// For `--from-entry` inputs, the module graph is discovered first so that files left
+/// unreachable can be reported as orphans. With `--stream-csv`, every file is also
+/// written to the CSV stream as soon as it completes.
+///
+/// # Arguments
+///
+/// * `source` - The file, directory, or entry point to analyze
+/// * `options` - Per-file analysis settings
+/// * `accumulator` - Accumulator to collect file statistics
+/// * `csv_stream` - Path of the live CSV stream, if requested
+///
+/// # Returns
+///
+/// The orphaned files discovered for entry-point inputs (empty otherwise)
+///
+/// # Errors
+///
+/// Returns an error if discovery, analysis, accumulation, or CSV streaming fails
+fn analyze_input<A: StatsAccumulator>(
+    source: &InputSource,
+    options: &AnalysisOptions,
+    accumulator: &mut A,
+    csv_stream: Option<&Path>,
+) -> Result<InputOutcome, RulocError> {
+    if let Some(csv_path) = csv_stream {
+        let mut stream = CsvStreamAccumulator::create(csv_path, accumulator)?;
+        let result = analyze_source(source, options, &mut stream);
+        let error = result.as_ref().err().map(ToString::to_string);
+        stream.finish(error.as_ref())?;
+        return result;
+    }
+
+    analyze_source(source, options, accumulator)
+}
+
+/// Runs an analysis while streaming NDJSON records to a writer.
+///
+/// File records are emitted as files are analyzed, in path order unless `--no-sort` is
+/// given. On success the stream ends with a
+/// `summary` record; if the analysis fails part-way, an `incomplete` trailer carrying
+/// the error is written instead before the error is returned.
+///
+/// # Arguments
+///
+/// * `args` - Arguments identifying the input and the presentation settings
+/// * `options` - Per-file analysis settings
+/// * `writer` - Destination of the NDJSON stream
+///
+/// # Returns
+///
+/// The summary of the completed analysis and the files it skipped
+///
+/// # Errors
+///
+/// Returns an error if presentation options cannot be resolved, the analysis fails,
+/// or the stream cannot be written
+fn stream_ndjson<W: Write + Send>(
+    args: &Args,
+    options: &AnalysisOptions,
+    writer: W,
+) -> Result<(Summary, Vec<SkippedFile>), RulocError> {
+    let mut accumulator = NdjsonAccumulator::new(writer, args.output_options()?);
+
+    let result = analyze_input(
+        &args.input_source(),
+        options,
+        &mut accumulator,
+        args.stream_csv.as_deref(),
+    );
+
+    match result {
+        Ok(outcome) => {
+            let skipped = outcome.skipped.clone();
+            accumulator.options.record(outcome);
+            let summary = accumulator.get_summary();
+            options.timed(TimingPhase::Output, || accumulator.finish())?;
+            Ok((summary, skipped))
+        }
+        Err(e) => {
+            accumulator.abort(&e.to_string())?;
+            Err(e)
+        }
+    }
+}
+
+/// Enforces the `--fail-on-*` and `--strict` gates after the report has been written.
+///
+/// # Arguments
+///
+/// * `args` - Arguments carrying the thresholds and gate settings
+/// * `summary` - Summary of the completed analysis
+/// * `skipped` - Files excluded from the analysis
+///
+/// # Returns
+///
+/// `Ok(())` when every enabled gate passes
+///
+/// # Errors
+///
+/// Returns an error describing the first failing gate
+fn check_gates(args: &Args, summary: &Summary, skipped: &[SkippedFile]) -> Result<(), RulocError> {
+    let failed = skipped
+        .iter()
+        .filter(|file| file.category != SkipCategory::Oversized)
+        .count();
+    if args.strict && failed > 0 {
+        return Err(RulocError::Gate(format!(
+            "{} file(s) could not be analyzed (--strict)",
+            failed
+        )));
+    }
+
+    if args.fail_on_empty && summary.total.code_lines == 0 {
+        return Err(RulocError::Gate(format!(
+            "{} file(s) with no code were analyzed (--fail-on-empty)",
+            summary.files
+        )));
+    }
+
+    if let Some(min_files) = args.min_files
+        && summary.files < min_files
+    {
+        return Err(RulocError::Gate(format!(
+            "only {} file(s) were analyzed, fewer than the minimum of {}",
+            summary.files, min_files
+        )));
+    }
+
+    if let Some(min_code_lines) = args.min_code_lines
+        && summary.total.code_lines < min_code_lines
+    {
+        return Err(RulocError::Gate(format!(
+            "only {} code line(s) were analyzed, fewer than the minimum of {}",
+            summary.total.code_lines, min_code_lines
+        )));
+    }
+
+    if args.fail_on_long_fns && summary.long_functions > 0 {
+        return Err(RulocError::Gate(format!(
+            "{} function(s) exceed the maximum of {} lines",
+            summary.long_functions,
+            args.max_fn_lines.unwrap_or_default()
+        )));
+    }
+
+    if let Some(max_nesting) = args.max_nesting
+        && summary.nested_functions > 0
+    {
+        return Err(RulocError::Gate(format!(
+            "{} function(s) exceed the maximum nesting depth of {}",
+            summary.nested_functions, max_nesting
+        )));
+    }
+
+    if let (Some(max_panics), Some(panics)) = (args.max_panics, &summary.panics)
+        && panics.production.total() > max_panics
+    {
+        return Err(RulocError::Gate(format!(
+            "production code contains {} panic call site(s), exceeding the maximum of {}",
+            panics.production.total(),
+            max_panics
+        )));
+    }
+
+    if args.fail_on_test_names && summary.test_name_violations > 0 {
+        return Err(RulocError::Gate(format!(
+            "{} test function(s) do not match the pattern '{}'",
+            summary.test_name_violations,
+            args.test_name_pattern
+                .as_ref()
+                .map(Regex::as_str)
+                .unwrap_or_default()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Reads a source file, replacing invalid UTF-8 unless strict decoding is requested.
+///
+/// # Arguments
+///
+/// * `path` - File to read
+/// * `strict_utf8` - Whether invalid UTF-8 is an error rather than being replaced
+///
+/// # Returns
+///
+/// The decoded, LF-normalized content and the number of invalid bytes that were replaced
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, or if it is not valid UTF-8 and
+/// `strict_utf8` is set
+fn read_source(path: &Path, strict_utf8: bool) -> Result<(String, usize), RulocError> {
+    let bytes = SourceBytes::load(path, false)?;
+    let (content, replaced_bytes) = decode_source(path, &bytes, strict_utf8)?;
+    Ok((content.into_owned(), replaced_bytes))
+}
+
+/// Rust module paths of analyzed files, used to regroup reports with `--group-by module`.
+#[derive(Debug, Clone, Default)]
+struct ModuleMap {
+    /// Module path of each file reachable from a crate root, keyed by canonical path.
+    by_file: HashMap<PathBuf, String>,
+}
+
+impl ModuleMap {
+    /// Resolves the module tree of every crate in an input.
+    ///
+    /// Single files and entry points are the root of a crate named `crate`. Directories
+    /// contribute the crate roots of every `Cargo.toml` below them: the library (or, without
+    /// one, the main binary) of a lone package is `crate`, and every other target is named
+    /// the way Cargo names its crate. A directory without manifests is treated as a
+    /// `src` directory holding `lib.rs` or `main.rs`.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The analyzed input
+    /// * `symlinks` - Traversal policy used while searching for manifests
+    ///
+    /// # Returns
+    ///
+    /// The module paths of all files reachable from the crate roots
+    fn resolve(source: &InputSource, symlinks: SymlinkPolicy) -> Self {
+        let roots = match source {
+            InputSource::File(path) | InputSource::Entry(path) => {
+                vec![(path.clone(), "crate".to_string())]
+            }
+            InputSource::Directory(dir) => Self::directory_roots(dir, symlinks),
+        };
+
+        let mut map = ModuleMap::default();
+        let mut seen = HashSet::new();
+        for (root, crate_name) in roots {
+            let files = match walk_module_tree(&root, &mut seen) {
+                Ok(files) => files,
+                Err(e) => {
+                    warn!("{}", e);
+                    continue;
+                }
+            };
+            for (file, segments) in files {
+                let module = [std::slice::from_ref(&crate_name), &segments]
+                    .concat()
+                    .join("::");
+                map.by_file
+                    .insert(fs::canonicalize(&file).unwrap_or(file), module);
+            }
+        }
+        map
+    }
+
+    /// Lists the crate roots below a directory with the name of each crate.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - Analyzed directory
+    /// * `symlinks` - Traversal policy used while searching for manifests
+    ///
+    /// # Returns
+    ///
+    /// `(root, crate name)` pairs, primary targets first
+    fn directory_roots(dir: &Path, symlinks: SymlinkPolicy) -> Vec<(PathBuf, String)> {
+        let mut packages = Vec::new();
+        let manifests = walk_workspace(dir, symlinks)
+            .filter_map(walk_entry_ok)
+            .filter(|e| e.file_name() == "Cargo.toml" && e.path().is_file());
+        for manifest in manifests {
+            let Ok(content) = fs::read_to_string(manifest.path()) else {
+                continue;
+            };
+            let Some(name) = toml::from_str::<toml::Table>(&content)
+                .ok()
+                .and_then(|table| {
+                    table
+                        .get("package")?
+                        .get("name")?
+                        .as_str()
+                        .map(|name| name.replace('-', "_"))
+                })
+            else {
+                continue;
+            };
+            let manifest_dir = manifest.path().parent().unwrap_or(Path::new("."));
+            packages.push((name, manifest_crate_roots(manifest_dir, &content)));
+        }
+
+        if packages.is_empty() {
+            return ["lib.rs", "main.rs"]
+                .into_iter()
+                .map(|name| dir.join(name))
+                .find(|root| root.is_file())
+                .map(|root| (root, "crate".to_string()))
+                .into_iter()
+                .collect();
+        }
+
+        let single = packages.len() == 1;
+        let mut roots = Vec::new();
+        for (package, targets) in packages {
+            // manifest_crate_roots lists src/lib.rs before src/main.rs
+            let primary = targets.iter().position(|root| {
+                root.ends_with(Path::new("src").join("lib.rs"))
+                    || root.ends_with(Path::new("src").join("main.rs"))
+            });
+            for (index, root) in targets.into_iter().enumerate() {
+                let name = if Some(index) == primary {
+                    if single {
+                        "crate".to_string()
+                    } else {
+                        package.clone()
+                    }
+                } else if root.file_name().is_some_and(|name| name == "build.rs") {
+                    "build_script_build".to_string()
+                } else if root.ends_with(Path::new("src").join("main.rs")) {
+                    package.clone()
+                } else {
+                    let stem = root.file_stem().unwrap_or_default();
+                    let target = if stem == "main" {
+                        root.parent().and_then(Path::file_name).unwrap_or(stem)
+                    } else {
+                        stem
+                    };
+                    target.to_string_lossy().replace('-', "_")
+                };
+                roots.push((root, name));
+            }
+        }
+        roots
+    }
+
+    /// Regroups file statistics into one entry per module.
+    ///
+    /// Each file becomes the entry of its module, less the lines of its inline modules,
+    /// which become entries of their own carrying line statistics only. Entries that
+    /// resolve to the same module are merged. Files outside every module tree are kept
+    /// as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - File statistics in presentation order
+    /// * `decorate` - Presentation enrichments applied to files that are kept as they are
+    ///
+    /// # Returns
+    ///
+    /// Module entries and unresolved files, in order of first appearance
+    fn group(
+        &self,
+        files: impl Iterator<Item = FileStats>,
+        decorate: impl Fn(FileStats) -> FileStats,
+    ) -> Vec<FileStats> {
+        let mut entries: Vec<FileStats> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut push = |entry: FileStats| match index.get(&entry.path) {
+            Some(&existing) => {
+                let merged = &mut entries[existing];
+                merged.total.add(&entry.total);
+                merged.production.add(&entry.production);
+                merged.test.add(&entry.test);
+                merged.bench.add(&entry.bench);
+                merged.build_script.add(&entry.build_script);
+                merged.proc_macro.add(&entry.proc_macro);
+            }
+            None => {
+                index.insert(entry.path.clone(), entries.len());
+                entries.push(entry);
+            }
+        };
+
+        for mut file in files {
+            let canonical =
+                fs::canonicalize(&file.path).unwrap_or_else(|_| PathBuf::from(&file.path));
+            let Some(module) = self.by_file.get(&canonical) else {
+                file.inline_modules.clear();
+                push(decorate(file));
+                continue;
+            };
+
+            let inline_modules = std::mem::take(&mut file.inline_modules);
+            for inline in &inline_modules {
+                file.total.subtract(&inline.total);
+                file.production.subtract(&inline.production);
+                file.test.subtract(&inline.test);
+                file.bench.subtract(&inline.bench);
+                file.build_script.subtract(&inline.build_script);
+                file.proc_macro.subtract(&inline.proc_macro);
+            }
+            file.path = module.clone();
+            push(file);
+            for inline in inline_modules {
+                push(FileStats {
+                    path: format!("{}::{}", module, inline.path),
+                    total: inline.total,
+                    production: inline.production,
+                    test: inline.test,
+                    bench: inline.bench,
+                    build_script: inline.build_script,
+                    proc_macro: inline.proc_macro,
+                    ..Default::default()
+                });
+            }
+        }
+        entries
+    }
+}
+
+/// What an analysis of a directory would cover, reported by `--dry-run`.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+struct DryRun {
+    /// Files that would be analyzed, sorted by path.
+    files: Vec<DiscoveredFile>,
+
+    /// Total size of the files that would be analyzed, in bytes.
+    total_bytes: u64,
+
+    /// Paths left out or set aside by a discovery rule, sorted by path.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    excluded: Vec<ExcludedPath>,
+
+    /// Files that `--max-file-size` would skip, sorted by path.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    oversized: Vec<DiscoveredFile>,
+
+    /// Number of files that `--sample` would analyze.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sampled_files: Option<usize>,
+
+    /// Message of the `--max-files` or `--max-total-size` limit that would stop the run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    limit_exceeded: Option<String>,
+}
+
+impl DryRun {
+    /// Rewrites the listed paths into the style of the analysis reports.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - Normalizer selected by `--relative-paths` or `--absolute-paths`
+    fn normalize_paths(&mut self, paths: &PathNormalizer) {
+        for file in self.files.iter_mut().chain(&mut self.oversized) {
+            file.path = paths.normalize(&file.path);
+        }
+        for excluded in &mut self.excluded {
+            excluded.path = paths.normalize(&excluded.path);
+        }
+    }
+}
+
+/// A file found by `--dry-run` discovery.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+struct DiscoveredFile {
+    /// Path of the file.
+    path: String,
+
+    /// Size of the file in bytes.
+    size: u64,
+}
+
+/// A path matched by an exclusion rule during `--dry-run` discovery.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+struct ExcludedPath {
+    /// Path of the file or symlink.
+    path: String,
+
+    /// The rule that matched.
+    reason: String,
+}
+
+/// Discovers the files of a directory the way [`analyze_directory`] would, without
+/// reading or parsing any of them.
+///
+/// # Arguments
+///
+/// * `dir` - Path to the directory to scan
+/// * `options` - Symlink policy, extensions, size limits, and sampling of the analysis
+///
+/// # Returns
+///
+/// The files that would be analyzed, skipped, or excluded
+fn dry_run_directory(dir: &Path, options: &AnalysisOptions) -> DryRun {
+    let root = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let mut excluded = Vec::new();
+    let paths: Vec<PathBuf> = WalkDir::new(dir)
+        .follow_links(options.symlinks.follow)
+        .into_iter()
+        .filter_entry(|entry| match options.symlinks.refusal(entry, &root) {
+            Some(refusal) => {
+                excluded.push(ExcludedPath {
+                    path: display_path(entry.path()),
+                    reason: refusal.describe(),
+                });
+                false
+            }
+            None => true,
+        })
+        .filter_map(walk_entry_ok)
+        .filter(|e| e.path().is_file())
+        .filter(|e| options.extensions.matches(e.path()))
+        .map(walkdir::DirEntry::into_path)
+        .collect();
+
+    let mut dry_run = DryRun::default();
+    let mut discovered_bytes = 0;
+    for path in &paths {
+        let file = DiscoveredFile {
+            path: display_path(path),
+            size: fs::metadata(path).map_or(0, |metadata| metadata.len()),
+        };
+        discovered_bytes += file.size;
+
+        // Generated files are still analyzed, but left out of the totals
+        if options
+            .generated
+            .as_ref()
+            .is_some_and(|rules| rules.matches(path, ""))
+        {
+            excluded.push(ExcludedPath {
+                path: file.path.clone(),
+                reason: "generated file (--generated-globs), excluded from totals".to_string(),
+            });
+        }
+        if options.max_file_size.is_some_and(|limit| file.size > limit) {
+            dry_run.oversized.push(file);
+        } else {
+            dry_run.total_bytes += file.size;
+            dry_run.files.push(file);
+        }
+    }
+
+    dry_run.files.sort_by(|a, b| a.path.cmp(&b.path));
+    dry_run.oversized.sort_by(|a, b| a.path.cmp(&b.path));
+    excluded.sort_by(|a, b| a.path.cmp(&b.path));
+    dry_run.excluded = excluded;
+    dry_run.sampled_files = options
+        .sampling
+        .as_ref()
+        .map(|sampling| sampling.size.files(paths.len()));
+    dry_run.limit_exceeded = options
+        .limits
+        .check(paths.len(), discovered_bytes, dir)
+        .err()
+        .map(|error| error.to_string());
+    dry_run
+}
+
+/// Log target writing to stderr around the active progress bar, so that warnings emitted
+/// during analysis are not overdrawn by the bar or torn apart by its redraws.
+struct ProgressAwareStderr;
+
+impl Write for ProgressAwareStderr {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let progress = ACTIVE_PROGRESS.lock().unwrap().clone();
+        match progress {
+            Some(progress) => progress.suspend(|| std::io::stderr().write(buf)),
+            None => std::io::stderr().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stderr().flush()
+    }
+}
+
+/// Derives a seed for `--sample` from the current time, when `--sample-seed` is not given.
+fn random_seed() -> u64 {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    split_mix64(&mut (now.as_nanos() as u64 ^ u64::from(std::process::id())))
+}
+
+/// Formats line statistics for plain text output with proper indentation.
+///
+/// # Arguments
+///
+/// * `stats` - The line statistics to format
+/// * `indent` - Number of spaces to indent each line
+///
+/// # Returns
+///
+/// A formatted string with all line counts displayed on separate lines
+fn format_line_stats(stats: &LineStats, indent: usize) -> String {
+    let prefix = " ".repeat(indent);
+    format!(
+        "{}All lines: {}\n\
+         {}Blank lines: {}\n\
+         {}Comment lines: {}\n\
+         {}Rustdoc lines: {}\n\
+         {}Code lines: {}",
+        prefix,
+        stats.all_lines,
+        prefix,
+        stats.blank_lines,
+        prefix,
+        stats.comment_lines,
+        prefix,
+        stats.rustdoc_lines,
+        prefix,
+        stats.code_lines
+    )
+}
+
+/// Formats documentation coverage for plain text output.
+///
+/// # Arguments
+///
+/// * `coverage` - The documentation coverage counters to format
+///
+/// # Returns
+///
+/// A single-line description such as `12/15 public items documented (80.0%)`, or a
+/// placeholder when the scope contains no public items
+fn format_doc_coverage(coverage: &DocCoverage) -> String {
+    match coverage.percentage() {
+        Some(percentage) => format!(
+            "{}/{} public items documented ({:.1}%)",
+            coverage.documented_items, coverage.public_items, percentage
+        ),
+        None => "n/a (no public items)".to_string(),
+    }
+}
+
+/// Formats cyclomatic complexity counters for plain text output.
+///
+/// # Arguments
+///
+/// * `complexity` - The counters to format
+///
+/// # Returns
+///
+/// A single-line description such as `12 functions, mean 2.4, max 9`
+fn format_function_complexity(complexity: &FunctionComplexity) -> String {
+    match complexity.mean() {
+        Some(mean) => format!(
+            "{} functions, mean {:.1}, max {}",
+            complexity.functions, mean, complexity.max
+        ),
+        None => "n/a (no functions)".to_string(),
+    }
+}
+
+/// Formats a rustdoc volume for plain text output.
+///
+/// # Arguments
+///
+/// * `volume` - The volume to format
+///
+/// # Returns
+///
+/// A single-line description such as `120 words, 734 characters, 12 example lines (3 hidden)`
+fn format_doc_volume(volume: &DocVolume) -> String {
+    let mut text = format!("{} words, {} characters", volume.words, volume.characters);
+    if volume.example_lines + volume.hidden_example_lines > 0 {
+        text.push_str(&format!(
+            ", {} example lines ({} hidden)",
+            volume.example_lines, volume.hidden_example_lines
+        ));
+    }
+    text
+}
+
+/// Formats macro line counts for plain text output.
+///
+/// # Arguments
+///
+/// * `macro_lines` - The macro line counts to format
+///
+/// # Returns
+///
+/// A single-line description such as `42 in definitions, 17 in invocations`
+fn format_macro_lines(macro_lines: &MacroLines) -> String {
+    format!(
+        "{} in definitions, {} in invocations",
+        macro_lines.definition_lines, macro_lines.invocation_lines
+    )
+}
+
+/// Formats public and private item counts for plain text output.
+///
+/// # Arguments
+///
+/// * `items` - The item counts to format
+///
+/// # Returns
+///
+/// A single-line description such as `12 public, 30 private (fns 8/21, types 3/6, traits
+/// 1/0, consts 0/3)`
+fn format_item_visibility(items: &ItemVisibility) -> String {
+    let (public, private) = (&items.public, &items.private);
+    format!(
+        "{} public, {} private (fns {}/{}, types {}/{}, traits {}/{}, consts {}/{})",
+        public.total(),
+        private.total(),
+        public.functions,
+        private.functions,
+        public.types,
+        private.types,
+        public.traits,
+        private.traits,
+        public.consts,
+        private.consts
+    )
+}
+
+/// Formats type complexity counters for plain text output.
+///
+/// # Arguments
+///
+/// * `complexity` - The type complexity counters to format
+///
+/// # Returns
+///
+/// A single-line description such as `12 generic params, 3 where clauses, 1 higher-ranked bounds`
+fn format_type_complexity(complexity: &TypeComplexity) -> String {
+    format!(
+        "{} generic params, {} where clauses, {} higher-ranked bounds",
+        complexity.generic_params, complexity.where_clauses, complexity.higher_ranked_bounds
+    )
+}
+
+/// Formats an item kind census for plain text output.
+///
+/// # Arguments
+///
+/// * `counts` - The item kind counts to format
+///
+/// # Returns
+///
+/// A single-line description such as `4 structs, 2 enums, 1 traits, 6 impls, 40 fns, 0
+/// macros, 3 consts, 0 statics`
+fn format_item_kind_counts(counts: &ItemKindCounts) -> String {
+    format!(
+        "{} structs, {} enums, {} traits, {} impls, {} fns, {} macros, {} consts, {} statics",
+        counts.structs,
+        counts.enums,
+        counts.traits,
+        counts.impls,
+        counts.functions,
+        counts.macros,
+        counts.consts,
+        counts.statics
+    )
+}
+
+/// Formats async metrics for plain text output.
+///
+/// # Arguments
+///
+/// * `metrics` - The async metrics to format
+///
+/// # Returns
+///
+/// A single-line description such as `6 async fns, 14 await points, 120 lines in async
+/// bodies`
+fn format_async_metrics(metrics: &AsyncMetrics) -> String {
+    format!(
+        "{} async fns, {} await points, {} lines in async bodies",
+        metrics.async_functions, metrics.await_points, metrics.async_lines
+    )
+}
+
+/// Formats a line length distribution for plain text output.
+///
+/// # Arguments
+///
+/// * `lengths` - The line lengths to format
+///
+/// # Returns
+///
+/// A single-line description such as `max 132, 4 over 100, p50 38, p95 91`
+fn format_line_lengths(lengths: &LineLengths) -> String {
+    format!(
+        "max {}, {} over {}, p50 {}, p95 {}",
+        lengths.max, lengths.over_width, lengths.width, lengths.p50, lengths.p95
+    )
+}
+
+/// Formats a per-feature line breakdown for plain text output.
+///
+/// # Arguments
+///
+/// * `features` - Line statistics keyed by feature name
+/// * `indent` - Number of spaces to indent each line
+///
+/// # Returns
+///
+/// One line per feature, such as `serde: 120 code lines (150 lines)`, in name order
+fn format_feature_breakdown(features: &BTreeMap<String, LineStats>, indent: usize) -> String {
+    let prefix = " ".repeat(indent);
+    features
+        .iter()
+        .map(|(feature, stats)| {
+            format!(
+                "{}{}: {} code lines ({} lines)",
+                prefix, feature, stats.code_lines, stats.all_lines
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats a documentation roll-up as indented text lines.
+///
+/// # Arguments
+///
+/// * `documentation` - Roll-up to format
+/// * `indent` - Number of spaces to prefix each line with
+///
+/// # Returns
+///
+/// The combined totals followed by one line per crate
+fn format_documentation(documentation: &DocumentationRollup, indent: usize) -> String {
+    let pad = " ".repeat(indent);
+    let mut lines = vec![format!(
+        "{}Lines: {} (rustdoc: {}, markdown: {} in {} files, {} words)",
+        pad,
+        documentation.lines,
+        documentation.rustdoc_lines,
+        documentation.markdown.lines,
+        documentation.markdown.files,
+        documentation.markdown.words
+    )];
+    for (crate_dir, docs) in &documentation.crates {
+        lines.push(format!(
+            "{}{}: {} lines in {} files, {} words",
+            pad, crate_dir, docs.lines, docs.files, docs.words
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Formats the line counts of one language for plain text output.
+///
+/// # Arguments
+///
+/// * `lines` - Line counts to format
+///
+/// # Returns
+///
+/// A single-line description such as `3 files, 120 lines (80 code, 25 comment, 15 blank)`
+fn format_language_lines(lines: &LanguageLines) -> String {
+    format!(
+        "{} files, {} lines ({} code, {} comment, {} blank)",
+        lines.files, lines.all_lines, lines.code_lines, lines.comment_lines, lines.blank_lines
+    )
+}
+
+/// Formats report labels for plain text output.
+///
+/// # Arguments
+///
+/// * `labels` - Key/value labels in key order
+///
+/// # Returns
+///
+/// A comma-separated list such as `team=payments, tier=critical`
+fn format_labels(labels: &BTreeMap<String, String>) -> String {
+    labels
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Formats work-marker counts for plain text output.
+///
+/// # Arguments
+///
+/// * `markers` - Occurrence counts keyed by marker
+///
+/// # Returns
+///
+/// A comma-separated list such as `FIXME: 2, TODO: 5`
+fn format_marker_counts(markers: &BTreeMap<String, usize>) -> String {
+    markers
+        .iter()
+        .map(|(marker, count)| format!("{}: {}", marker, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Formats panic call site counts for plain text output.
+///
+/// # Arguments
+///
+/// * `counts` - The counts to format
+///
+/// # Returns
+///
+/// A single-line description such as `5 (unwrap 3, expect 1, panic! 1, todo! 0,
+/// unimplemented! 0)`
+fn format_panic_counts(counts: &PanicCounts) -> String {
+    format!(
+        "{} (unwrap {}, expect {}, panic! {}, todo! {}, unimplemented! {})",
+        counts.total(),
+        counts.unwrap,
+        counts.expect,
+        counts.panic,
+        counts.todo,
+        counts.unimplemented
+    )
+}
+
+/// Orders allowed lints from most to least allowed, breaking ties alphabetically.
+///
+/// # Arguments
+///
+/// * `lints` - Occurrence counts keyed by lint name
+///
+/// # Returns
+///
+/// `(lint, count)` pairs, most-allowed first
+fn most_allowed_lints(lints: &BTreeMap<String, usize>) -> Vec<(&str, usize)> {
+    let mut ranked: Vec<(&str, usize)> = lints
+        .iter()
+        .map(|(lint, &count)| (lint.as_str(), count))
+        .collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    ranked
+}
+
+/// Formats a line statistics delta as a compact list of its non-zero components.
+///
+/// # Arguments
+///
+/// * `delta` - The signed change of every component
+///
+/// # Returns
+///
+/// A string such as `all +1, code +1`, or `none` when every component is zero
+fn format_line_stats_delta(delta: &LineStatsDelta) -> String {
+    let components = [
+        ("all", delta.all_lines),
+        ("blank", delta.blank_lines),
+        ("comment", delta.comment_lines),
+        ("rustdoc", delta.rustdoc_lines),
+        ("code", delta.code_lines),
+    ];
+
+    let parts: Vec<String> = components
+        .iter()
+        .filter(|(_, value)| *value != 0)
+        .map(|(name, value)| format!("{} {:+}", name, value))
+        .collect();
+
+    if parts.is_empty() {
+        "none".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+/// Formats an include target for plain text output.
+///
+/// # Arguments
+///
+/// * `include` - The included file
+///
+/// # Returns
+///
+/// A single-line description such as `include!("gen.in") -> src/gen.in`
+fn format_include_target(include: &IncludeTarget) -> String {
+    format!(
+        "{}!(\"{}\") -> {}",
+        include.macro_name,
+        include.target,
+        include.resolved.as_deref().unwrap_or("(unresolved)")
+    )
+}
+
+/// Formats an `impl` block entry for plain text output.
+///
+/// # Arguments
+///
+/// * `block` - The `impl` block to describe
+///
+/// # Returns
+///
+/// A single-line description such as `Display for Parser (line 12, 8 lines)`
+fn format_impl_block(block: &ImplBlock) -> String {
+    let header = match &block.trait_name {
+        Some(trait_name) => format!("{} for {}", trait_name, block.self_type),
+        None => block.self_type.clone(),
+    };
+
+    format!(
+        "{} (line {}, {} lines)",
+        header, block.start_line, block.lines
+    )
+}
+
+/// Formats the crate-level trait inventory, most frequently implemented traits first.
+///
+/// # Arguments
+///
+/// * `trait_impls` - Number of implementations per trait
+/// * `indent` - Number of spaces to prefix each line with
+///
+/// # Returns
+///
+/// One `Trait: count` line per trait, ordered by descending count and then by name
+fn format_trait_inventory(trait_impls: &BTreeMap<String, usize>, indent: usize) -> String {
+    let mut entries: Vec<_> = trait_impls.iter().collect();
+    entries.sort_by(|(a_name, a_count), (b_name, b_count)| {
+        b_count.cmp(a_count).then_with(|| a_name.cmp(b_name))
+    });
+
+    let indent_str = " ".repeat(indent);
+    entries
+        .into_iter()
+        .map(|(name, count)| format!("{}{}: {}", indent_str, name, count))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats a long function entry for plain text output.
+///
+/// # Arguments
+///
+/// * `long_function` - The function exceeding the length threshold
+///
+/// # Returns
+///
+/// A single-line description such as `Parser::parse (lines 10-95, 86 lines)`
+fn format_long_function(long_function: &LongFunction) -> String {
+    format!(
+        "{} (lines {}-{}, {} lines)",
+        long_function.name, long_function.start_line, long_function.end_line, long_function.lines
+    )
+}
+
+/// Formats a function exceeding `--max-complexity` for plain text output.
+///
+/// # Arguments
+///
+/// * `function` - The function exceeding the complexity threshold
+///
+/// # Returns
+///
+/// A single-line description such as `parse (line 10, complexity 14)`
+fn format_complex_function(function: &ComplexFunction) -> String {
+    format!(
+        "{} (line {}, complexity {})",
+        function.name, function.start_line, function.complexity
+    )
+}
+
+/// Formats a function exceeding `--max-nesting` for plain text output.
+///
+/// # Arguments
+///
+/// * `function` - The function exceeding the nesting threshold
+///
+/// # Returns
+///
+/// A single-line description such as `parse (line 10, depth 6)`
+fn format_nested_function(function: &NestedFunction) -> String {
+    format!(
+        "{} (line {}, depth {})",
+        function.name, function.start_line, function.depth
+    )
+}
+
+/// Formats a call site listed with `--list-panics` for plain text output.
+///
+/// # Arguments
+///
+/// * `site` - The call site that may panic
+///
+/// # Returns
+///
+/// A single-line description such as `12: let value = parse(text).unwrap(); (test)`
+fn format_panic_site(site: &PanicSite) -> String {
+    format!(
+        "{}: {}{}",
+        site.line,
+        site.text,
+        if site.test { " (test)" } else { "" }
+    )
+}
+
+/// Returns the debug-mode marker of a line, such as `PCO` for production code.
+///
+/// # Arguments
+///
+/// * `line_type` - The type of line (Blank, Comment, Rustdoc, Code)
+/// * `is_test` - Whether this line is in test code
+///
+/// # Returns
+///
+/// One of the `DEBUG_MARKER_*` constants
+fn debug_marker(line_type: LineType, is_test: bool) -> &'static str {
+    match (is_test, line_type) {
+        (false, LineType::Blank) => DEBUG_MARKER_PRODUCTION_BLANK,
+        (false, LineType::Comment) => DEBUG_MARKER_PRODUCTION_COMMENT,
+        (false, LineType::Rustdoc) => DEBUG_MARKER_PRODUCTION_RUSTDOC,
+        (false, LineType::Code) => DEBUG_MARKER_PRODUCTION_CODE,
+        (true, LineType::Blank) => DEBUG_MARKER_TEST_BLANK,
+        (true, LineType::Comment) => DEBUG_MARKER_TEST_COMMENT,
+        (true, LineType::Rustdoc) => DEBUG_MARKER_TEST_RUSTDOC,
+        (true, LineType::Code) => DEBUG_MARKER_TEST_CODE,
+    }
+}
+
+/// Formats a single line for debug output with type prefix and optional coloring.
+///
+/// # Arguments
+///
+/// * `line` - The line content to display
+/// * `line_type` - The type of line (Blank, Comment, Rustdoc, Code)
+/// * `is_test` - Whether this line is in test code
+/// * `use_color` - Whether to apply color to the prefix
+///
+/// # Returns
+///
+/// A formatted string with prefix and line content
+fn format_debug_line(line: &str, line_type: LineType, is_test: bool, use_color: bool) -> String {
+    let prefix = debug_marker(line_type, is_test);
+    let colored_prefix = match (is_test, line_type) {
+        (false, LineType::Blank) => prefix.bright_black(),
+        (false, LineType::Comment) => prefix.green(),
+        (false, LineType::Rustdoc) => prefix.bright_green(),
+        (false, LineType::Code) => prefix.blue(),
+        (true, LineType::Blank) => prefix.bright_black(),
+        (true, LineType::Comment) => prefix.yellow(),
+        (true, LineType::Rustdoc) => prefix.bright_yellow(),
+        (true, LineType::Code) => prefix.magenta(),
+    };
+
+    if use_color {
+        format!("{}  {}", colored_prefix, line)
+    } else {
+        format!("{}  {}", prefix, line)
+    }
+}
+
+/// Per-line classification of one file, as reported by `--debug`.
+#[derive(Debug, Serialize, Deserialize)]
+struct DebugFile {
+    /// Path of the file, with `/` separators.
+    path: String,
+
+    /// Classification of every line, in order.
+    lines: Vec<DebugLine>,
+}
+
+/// Classification of one source line in `--debug` output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DebugLine {
+    /// 1-based line number.
+    line: usize,
+
+    /// Kind of content on the line.
+    #[serde(rename = "type")]
+    line_type: LineType,
+
+    /// Whether the line belongs to test code.
+    is_test: bool,
+
+    /// Whether the line is excluded from line counts by a `// ruloc:ignore-start` directive.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    ignored: bool,
+
+    /// Content of the line, printed by the plain text format only.
+    #[serde(skip)]
+    text: String,
+}
+
+/// Presentation settings of debug mode.
+#[derive(Debug, Clone, Default)]
+struct DebugOptions {
+    /// Whether to write JSON records instead of annotated lines (`--out-json`).
+    json: bool,
+
+    /// Whether to apply color to the prefixes.
+    use_color: bool,
+
+    /// Whether to prefix each line with its number (`--line-numbers`).
+    line_numbers: bool,
+
+    /// Lines to report, or `None` for the whole file (`--lines`).
+    lines: Option<RangeInclusive<usize>>,
+}
+
+impl DebugLine {
+    /// Returns the debug-mode marker of the line, such as `PCO` or `IGN`.
+    fn marker(&self) -> &'static str {
+        if self.ignored {
+            DEBUG_MARKER_IGNORED
+        } else {
+            debug_marker(self.line_type, self.is_test)
+        }
+    }
+}
+
+/// Classifies every line of a file for debug mode.
+///
+/// # Arguments
+///
+/// * `path` - Path to the file to analyze
+/// * `max_file_size` - Optional maximum file size limit
+/// * `editions` - Resolver of the edition the file is parsed with
+///
+/// # Returns
+///
+/// The type and test membership of each line
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or exceeds the size limit
+fn classify_debug_file(
+    path: &Path,
+    max_file_size: Option<u64>,
+    editions: &EditionResolver,
+) -> Result<DebugFile, RulocError> {
+    // Check file size if limit is specified
+    if let Some(max_size) = max_file_size {
+        let metadata = fs::metadata(path).map_err(RulocError::io(format!(
+            "Failed to read metadata for {}",
+            path.display()
+        )))?;
+        let file_size = metadata.len();
+
+        if file_size > max_size {
+            return Err(RulocError::FileTooLarge {
+                path: path.to_path_buf(),
+                size: file_size,
+                limit: max_size,
+            });
+        }
+    }
+
+    let (content, _) = read_source(path, false)?;
+    let mut file = DebugFile {
+        path: display_path(path),
+        lines: Vec::new(),
+    };
+    if content.is_empty() {
+        return Ok(file);
+    }
+
+    let edition = editions.resolve(path);
+    let ParsedSource {
+        line_types,
+        is_test_line,
+        is_ignored_line,
+        ..
+    } = ParsedSource::parse(&content, edition);
+
+    file.lines = content
+        .lines()
+        .zip(
+            line_types
+                .into_iter()
+                .zip(is_test_line)
+                .zip(is_ignored_line),
+        )
+        .enumerate()
+        .map(|(i, (text, ((line_type, is_test), ignored)))| DebugLine {
+            line: i + 1,
+            line_type,
+            is_test,
+            ignored,
+            text: text.to_string(),
+        })
+        .collect();
+    Ok(file)
+}
+
+/// Writes a classified file in debug mode.
+///
+/// The plain text format prints the path followed by each line with its type prefix, and
+/// nothing for an empty file. The JSON format prints the file as a single line, so that
+/// a directory yields one record per file. With `--lines`, only lines in the range are
+/// written in either format.
+///
+/// # Arguments
+///
+/// * `out` - Destination of the output
+/// * `file` - The classified file
+/// * `options` - Format, coloring, line numbers, and line range
+///
+/// # Errors
+///
+/// Returns an error if writing fails
+fn write_debug_file(
+    out: &mut dyn Write,
+    file: &DebugFile,
+    options: &DebugOptions,
+) -> std::io::Result<()> {
+    let selected;
+    let file = match &options.lines {
+        Some(range) => {
+            selected = DebugFile {
+                path: file.path.clone(),
+                lines: file
+                    .lines
+                    .iter()
+                    .filter(|line| range.contains(&line.line))
+                    .cloned()
+                    .collect(),
+            };
+            &selected
+        }
+        None => file,
+    };
+
+    if options.json {
+        serde_json::to_writer(&mut *out, file)?;
+        return writeln!(out);
+    }
+    let Some(last) = file.lines.last() else {
+        return Ok(());
+    };
+
+    writeln!(out, "{}:", file.path)?;
+    let width = last.line.to_string().len();
+    for line in &file.lines {
+        let formatted = if line.ignored {
+            let marker = if options.use_color {
+                DEBUG_MARKER_IGNORED.bright_black().to_string()
+            } else {
+                DEBUG_MARKER_IGNORED.to_string()
+            };
+            format!("{}  {}", marker, line.text)
+        } else {
+            format_debug_line(&line.text, line.line_type, line.is_test, options.use_color)
+        };
+        if options.line_numbers {
+            writeln!(out, "{:>width$}  {}", line.line, formatted)?;
+        } else {
+            writeln!(out, "{}", formatted)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads classifications saved with `--debug --out-json` for `--debug-compare`.
+///
+/// # Arguments
+///
+/// * `path` - File holding one JSON record per analyzed file
+///
+/// # Returns
+///
+/// The saved classifications, keyed by file path
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or a record is malformed
+fn load_debug_baseline(path: &Path) -> Result<HashMap<String, DebugFile>, RulocError> {
+    let content = fs::read_to_string(path).map_err(RulocError::io(format!(
+        "Failed to read '{}'",
+        path.display()
+    )))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str::<DebugFile>(line)
+                .map(|file| (file.path.clone(), file))
+                .map_err(|e| {
+                    RulocError::Parse(format!(
+                        "Invalid debug record on line {} of '{}': {}",
+                        index + 1,
+                        path.display(),
+                        e
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Writes the lines whose classification differs from a saved baseline.
+///
+/// Each mismatch shows the line number, the baseline marker, the current marker, and the
+/// line's content; `---` stands for a line missing on one side. Nothing is written for a
+/// file that matches its baseline.
+///
+/// # Arguments
+///
+/// * `out` - Destination of the output
+/// * `current` - Classifications of the current build
+/// * `baseline` - Saved classifications of the same file, if it was saved
+/// * `options` - Line range to compare
+///
+/// # Returns
+///
+/// The number of mismatched lines
+///
+/// # Errors
+///
+/// Returns an error if writing fails
+fn write_debug_mismatches(
+    out: &mut dyn Write,
+    current: &DebugFile,
+    baseline: Option<&DebugFile>,
+    options: &DebugOptions,
+) -> std::io::Result<usize> {
+    let by_number = |file: &DebugFile| -> BTreeMap<usize, &'static str> {
+        file.lines
+            .iter()
+            .filter(|line| {
+                options
+                    .lines
+                    .as_ref()
+                    .is_none_or(|range| range.contains(&line.line))
+            })
+            .map(|line| (line.line, line.marker()))
+            .collect()
+    };
+    let before = baseline.map(by_number).unwrap_or_default();
+    let after = by_number(current);
+    let texts: HashMap<usize, &str> = current
+        .lines
+        .iter()
+        .map(|line| (line.line, line.text.as_str()))
+        .collect();
+
+    let mut numbers: Vec<usize> = before.keys().chain(after.keys()).copied().collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+    let width = numbers.last().map_or(1, |line| line.to_string().len());
+    let marker = |marker: Option<&&'static str>| marker.copied().unwrap_or("---");
+
+    let mut mismatches = 0;
+    for number in numbers {
+        let (old, new) = (before.get(&number), after.get(&number));
+        if old == new {
+            continue;
+        }
+        if mismatches == 0 {
+            writeln!(out, "{}:", current.path)?;
+        }
+        writeln!(
+            out,
+            "{:>width$}  {} -> {}  {}",
+            number,
+            marker(old),
+            marker(new),
+            texts.get(&number).copied().unwrap_or_default()
+        )?;
+        mismatches += 1;
+    }
+    Ok(mismatches)
+}
+
+/// Handles `--dry-run`: discovers the files of the analyzed directory and reports what
+/// an analysis would cover, as text or, with `--out-json`, as JSON.
+///
+/// # Arguments
+///
+/// * `args` - Arguments selecting the directory and the output
+/// * `options` - Discovery settings of the analysis
+///
+/// # Errors
+///
+/// Returns an error if the input is not a directory, the report cannot be written, or
+/// the files exceed `--max-files` or `--max-total-size`, as the real run would
+fn handle_dry_run(args: &Args, options: &AnalysisOptions) -> Result<(), RulocError> {
+    let InputSource::Directory(dir) = args.input_source() else {
+        return Err(RulocError::Config(
+            "--dry-run requires a directory input".to_string(),
+        ));
+    };
+    let mut dry_run = dry_run_directory(&dir, options);
+    dry_run.normalize_paths(&args.path_normalizer());
+
+    let destination = OutputOptions {
+        output: args
+            .out_json
+            .clone()
+            .flatten()
+            .or_else(|| args.output.clone())
+            .filter(|path| path.as_os_str() != "-"),
+        ..Default::default()
+    };
+    destination.write_output(|out| {
+        if args.out_json.is_some() {
+            write_json_report(out, &dry_run)
+        } else {
+            write_dry_run(out, &dir, &dry_run).map_err(RulocError::io("Failed to write the report"))
+        }
+    })?;
+
+    match dry_run.limit_exceeded {
+        Some(message) => Err(RulocError::Analysis(message)),
+        None => Ok(()),
+    }
+}
+
+/// Writes the plain text report of `--dry-run`.
+///
+/// # Arguments
+///
+/// * `out` - Destination of the report
+/// * `dir` - The scanned directory
+/// * `dry_run` - What the analysis would cover
+///
+/// # Errors
+///
+/// Returns an error if writing fails
+fn write_dry_run(out: &mut dyn Write, dir: &Path, dry_run: &DryRun) -> std::io::Result<()> {
+    let indent = " ".repeat(TEXT_OUTPUT_BASE_INDENT);
+    writeln!(out, "Dry run of {}:", dir.display())?;
+    writeln!(
+        out,
+        "  Files to analyze: {} ({} bytes)",
+        dry_run.files.len(),
+        dry_run.total_bytes
+    )?;
+    if let Some(sampled) = dry_run.sampled_files {
+        writeln!(
+            out,
+            "  Sample: {} of {} files",
+            sampled,
+            dry_run.files.len() + dry_run.oversized.len()
+        )?;
+    }
+    if !dry_run.excluded.is_empty() {
+        writeln!(out, "  Excluded:")?;
+        for excluded in &dry_run.excluded {
+            writeln!(out, "{}{}: {}", indent, excluded.path, excluded.reason)?;
+        }
+    }
+    if !dry_run.oversized.is_empty() {
+        writeln!(out, "  Skipped by --max-file-size:")?;
+        for file in &dry_run.oversized {
+            writeln!(out, "{}{} ({} bytes)", indent, file.path, file.size)?;
+        }
+    }
+    if let Some(message) = &dry_run.limit_exceeded {
+        writeln!(out, "  Limit exceeded: {}", message)?;
+    }
+    Ok(())
+}
+
+/// Handles debug mode output for files or directories.
+///
+/// Processes either a single file or all Rust files in a directory, outputting
+/// line-by-line debug information with type annotations, or one JSON record per file
+/// with `--out-json`, optionally numbered and limited to `--lines`. With
+/// `--debug-compare`, only lines classified differently than in the saved records are
+/// printed. The output goes to the `--out-json` file or `--output` if given,
+/// uncolored, and to stdout otherwise.
+///
+/// # Arguments
+///
+/// * `args` - Command-line arguments containing file/dir paths and color settings
+/// * `analysis` - Analysis options supplying the size limit and editions
+///
+/// # Returns
+///
+/// `Ok(())` on success, or an error message if analysis fails
+///
+/// # Errors
+///
+/// Returns an error if a single input file cannot be analyzed, the output cannot be
+/// written, or `--debug-compare` finds differences; files of a directory or module tree
+/// that fail are logged and skipped
+fn handle_debug_mode(args: &Args, analysis: &AnalysisOptions) -> Result<(), RulocError> {
+    let destination = OutputOptions {
+        output: args
+            .out_json
+            .clone()
+            .flatten()
+            .or_else(|| args.output.clone())
+            .filter(|path| path.as_os_str() != "-"),
+        ..Default::default()
+    };
+    let options = DebugOptions {
+        json: args.out_json.is_some(),
+        use_color: !args.no_color && destination.output.is_none(),
+        line_numbers: args.line_numbers,
+        lines: args.lines.clone(),
+    };
+    let write_error = || RulocError::io("Failed to write the report");
+    let mut baseline = args
+        .debug_compare
+        .as_deref()
+        .map(load_debug_baseline)
+        .transpose()?;
+
+    let single_file = matches!(args.input_source(), InputSource::File(_));
+    let paths: Vec<PathBuf> = match args.input_source() {
+        InputSource::File(file_path) => vec![file_path],
+        InputSource::Entry(entry) => discover_module_graph(&entry, SymlinkPolicy::default())?.files,
+        InputSource::Directory(dir_path) => {
+            let extensions = SourceExtensions::new(&args.ext);
+            WalkDir::new(&dir_path)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| extensions.matches(e.path()))
+                .map(walkdir::DirEntry::into_path)
+                .collect()
+        }
+    };
+
+    let mut mismatches = 0;
+    destination.write_output(|out| {
+        for path in paths {
+            let file = match classify_debug_file(&path, analysis.max_file_size, &analysis.editions)
+            {
+                Ok(file) => file,
+                Err(e) if single_file => return Err(e),
+                Err(e) => {
+                    warn!("{}", e);
+                    continue;
+                }
+            };
+
+            if let Some(baseline) = &mut baseline {
+                let saved = baseline.remove(&file.path);
+                mismatches += write_debug_mismatches(out, &file, saved.as_ref(), &options)
+                    .map_err(write_error())?;
+                continue;
+            }
+            write_debug_file(out, &file, &options)
+                // Files are separated by a blank line in the plain text format
+                .and_then(|()| {
+                    if options.json || single_file {
+                        Ok(())
+                    } else {
+                        writeln!(out)
+                    }
+                })
+                .map_err(write_error())?;
+        }
+
+        // Files that were saved but no longer analyzed lose every line
+        let mut missing: Vec<DebugFile> = baseline.unwrap_or_default().into_values().collect();
+        missing.sort_by(|a, b| a.path.cmp(&b.path));
+        for saved in missing {
+            let current = DebugFile {
+                path: saved.path.clone(),
+                lines: Vec::new(),
+            };
+            mismatches += write_debug_mismatches(out, &current, Some(&saved), &options)
+                .map_err(write_error())?;
+        }
+        Ok(())
+    })?;
+
+    match &args.debug_compare {
+        Some(path) if mismatches > 0 => Err(RulocError::Gate(format!(
+            "{} line(s) are classified differently than in '{}'",
+            mismatches,
+            path.display()
+        ))),
+        Some(path) => {
+            info!("All lines are classified as in '{}'", path.display());
+            Ok(())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Column headings of the text summary table.
+const TABLE_HEADERS: [&str; 6] = ["File", "Code", "Comments", "Docs", "Blanks", "Test %"];
+
+/// Heading of the first table column when rows are modules with `--group-by module`.
+const MODULE_TABLE_HEADER: &str = "Module";
+
+/// Builds one row of the text summary table.
+///
+/// # Arguments
+///
+/// * `label` - Text of the first column
+/// * `total` - Line counts for the row
+/// * `test` - Test line counts, used for the share of code that is test code
+///
+/// # Returns
+///
+/// Cells in [`TABLE_HEADERS`] order
+fn table_row(label: String, total: &LineStats, test: &LineStats) -> [String; 6] {
+    let test_share = if total.code_lines == 0 {
+        "-".to_string()
+    } else {
+        format!(
+            "{:.1}%",
+            test.code_lines as f64 * 100.0 / total.code_lines as f64
+        )
+    };
+    [
+        label,
+        total.code_lines.to_string(),
+        total.comment_lines.to_string(),
+        total.rustdoc_lines.to_string(),
+        total.blank_lines.to_string(),
+        test_share,
+    ]
+}
+
+/// Renders rows as an aligned table with a heading and a closing totals row.
+///
+/// The first column is left-aligned and the numeric columns right-aligned. With color,
+/// the heading and totals are bold.
+///
+/// # Arguments
+///
+/// * `headers` - Column headings, such as [`TABLE_HEADERS`]
+/// * `rows` - Per-file rows in display order
+/// * `totals` - Totals row printed below a rule
+/// * `color` - Whether to emit ANSI styling
+///
+/// # Returns
+///
+/// The table, one line per row, without a trailing newline
+fn format_table(
+    headers: &[&str; 6],
+    rows: &[[String; 6]],
+    totals: &[String; 6],
+    color: bool,
+) -> String {
+    let mut widths = headers.map(str::len);
+    for row in rows.iter().chain(std::iter::once(totals)) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let render = |cells: &[&str], bold: bool| {
+        let line = cells
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(column, (cell, width))| {
+                if column == 0 {
+                    format!("{:<width$}", cell)
+                } else {
+                    format!("{:>width$}", cell)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ");
+        if color && bold {
+            line.bold().to_string()
+        } else {
+            line
+        }
+    };
+
+    let mut lines = vec![render(headers, true)];
+    lines.extend(
+        rows.iter()
+            .map(|row| render(&row.each_ref().map(String::as_str), false)),
+    );
+    lines.push("-".repeat(widths.iter().sum::<usize>() + 2 * (widths.len() - 1)));
+    lines.push(render(&totals.each_ref().map(String::as_str), true));
+    lines.join("\n")
+}
+
+/// Outputs statistics as an aligned summary table.
+///
+/// Prints one row per file and a totals row, which is the default text layout; the
+/// detailed report is available through `--out-text-verbose`. Generated files are listed
+/// but excluded from the totals, and skipped files are counted below the table. The summaries and per-file listings of requested opt-in analyses,
+/// such as `--todos` or `--max-fn-lines`, follow.
+///
+/// # Arguments
+///
+/// * `summary` - Summary of the analysis
+/// * `files` - File statistics in presentation order
+/// * `options` - Presentation options such as coloring
+///
+/// # Errors
+///
+/// Returns an error if the table cannot be written
+fn output_table(
+    summary: Summary,
+    files: impl Iterator<Item = FileStats>,
+    options: &OutputOptions,
+) -> Result<(), RulocError> {
+    let mut listings = Vec::new();
+    let mut rows = Vec::new();
+    for file in files {
+        write_file_listings(&mut listings, &file)
+            .map_err(RulocError::io("Failed to render file listings"))?;
+        let label = if file.generated {
+            format!("{} (generated)", file.path)
+        } else {
+            file.path
+        };
+        rows.push(table_row(label, &file.total, &file.test));
+    }
+    let totals = table_row(
+        format!("Total ({} files)", summary.files),
+        &summary.total,
+        &summary.test,
+    );
+    options.write_output(|out| {
+        write_table_report(out, &rows, &totals, &listings, &summary, options)
+            .map_err(RulocError::io("Failed to write the report"))
+    })
+}
+
+/// Writes the summary table followed by hints about skipped and inconsistent files and the
+/// sections of requested opt-in analyses.
+///
+/// # Arguments
+///
+/// * `out` - Destination of the report
+/// * `rows` - One row per file
+/// * `totals` - The totals row
+/// * `listings` - Per-file listings rendered by [`write_file_listings`]
+/// * `summary` - Summary of the analysis
+/// * `options` - Presentation options such as coloring
+///
+/// # Errors
+///
+/// Returns an error if writing fails
+fn write_table_report(
+    out: &mut dyn Write,
+    rows: &[[String; 6]],
+    totals: &[String; 6],
+    listings: &[u8],
+    summary: &Summary,
+    options: &OutputOptions,
+) -> std::io::Result<()> {
+    writeln!(
+        out,
+        "{}",
+        format_table(
+            &options.table_headers(),
+            rows,
+            totals,
+            options.colors_table()
+        )
+    )?;
+
+    if !options.skipped.is_empty() {
+        writeln!(
+            out,
+            "\n{} file(s) skipped; rerun with --out-text-verbose for details",
+            options.skipped.len()
+        )?;
+    }
+    if summary.parse_error_files > 0 {
+        writeln!(
+            out,
+            "\n{} file(s) have syntax errors and may be miscounted (see --list-parse-errors)",
+            summary.parse_error_files
+        )?;
+    }
+    if let Some(sample) = &options.sample {
+        writeln!(out, "\n{}", format_sample_estimate(sample))?;
+    }
+
+    let mut sections = Vec::new();
+    write_summary_sections(&mut sections, summary, options)?;
+    if !sections.is_empty() {
+        writeln!(out, "\nSummary:")?;
+        out.write_all(&sections)?;
+    }
+    if !listings.is_empty() {
+        writeln!(out, "\nFiles:")?;
+        out.write_all(listings)?;
+    }
+
+    Ok(())
+}
+
+/// Formats the totals extrapolated with `--sample` as a single line.
+///
+/// # Arguments
+///
+/// * `sample` - The estimated totals
+///
+/// # Returns
+///
+/// A line naming the sample size and listing every estimate with its margin
+fn format_sample_estimate(sample: &SampleEstimate) -> String {
+    let estimates: Vec<String> = sample
+        .estimates
+        .iter()
+        .map(|estimate| {
+            format!(
+                "{} {}",
+                estimate.metric.replace('-', " "),
+                format_estimated_total(estimate)
+            )
+        })
+        .collect();
+    format!(
+        "Sampled {} of {} files (seed {}); estimated totals at {}% confidence: {}",
+        sample.sampled_files,
+        sample.population_files,
+        sample.seed,
+        sample.confidence,
+        estimates.join(", ")
+    )
+}
+
+/// Formats an estimated total as `VALUE ± MARGIN`, with `?` for an unknown margin.
+fn format_estimated_total(estimate: &EstimatedTotal) -> String {
+    match estimate.margin {
+        Some(margin) => format!("{} ± {}", estimate.value, margin),
+        None => format!("{} ± ?", estimate.value),
+    }
+}
+
+/// Outputs statistics in the detailed plain text format.
+///
+/// Used with `--out-text-verbose`. Displays a summary section with aggregated statistics,
+/// followed by detailed statistics for each analyzed file as it is read back from the
+/// accumulator, without loading everything into memory.
+///
+/// # Arguments
+///
+/// * `summary` - Summary of the analysis
+/// * `files` - File statistics in presentation order
+/// * `options` - Presentation options such as revision-pinned file links
+///
+/// # Errors
+///
+/// Returns an error if the report cannot be written
+fn output_text(
+    summary: Summary,
+    files: impl Iterator<Item = FileStats>,
+    options: &OutputOptions,
+) -> Result<(), RulocError> {
+    options.write_output(|out| {
+        write_text_report(out, &summary, files, options)
+            .map_err(RulocError::io("Failed to write the report"))
+    })
+}
+
+/// Writes the detailed plain text report of `--out-text-verbose`.
+///
+/// # Arguments
+///
+/// * `out` - Destination of the report
+/// * `summary` - Summary of the analysis
+/// * `files` - File statistics in presentation order
+/// * `options` - Presentation options such as revision-pinned file links
+///
+/// # Errors
+///
+/// Returns an error if writing fails
+fn write_text_report(
+    out: &mut dyn Write,
+    summary: &Summary,
+    files: impl Iterator<Item = FileStats>,
+    options: &OutputOptions,
+) -> std::io::Result<()> {
+    writeln!(out, "Summary:")?;
+    writeln!(out, "  Files: {}", summary.files)?;
+    if let Some(doc_coverage) = &summary.doc_coverage {
+        writeln!(out, "  Doc coverage: {}", format_doc_coverage(doc_coverage))?;
+    }
+    if let Some(macro_lines) = &summary.macro_lines {
+        writeln!(out, "  Macro lines: {}", format_macro_lines(macro_lines))?;
+    }
+    if let Some(items) = &summary.items {
+        writeln!(out, "  Items: {}", format_item_visibility(items))?;
+    }
+    writeln!(out, "  Total:")?;
+    writeln!(
+        out,
+        "{}",
+        format_line_stats(&summary.total, TEXT_OUTPUT_BASE_INDENT)
+    )?;
+    writeln!(out, "  Production:")?;
+    writeln!(
+        out,
+        "{}",
+        format_line_stats(&summary.production, TEXT_OUTPUT_BASE_INDENT)
+    )?;
+    writeln!(out, "  Test:")?;
+    writeln!(
+        out,
+        "{}",
+        format_line_stats(&summary.test, TEXT_OUTPUT_BASE_INDENT)
+    )?;
+    if !is_default(&summary.bench) {
+        writeln!(out, "  Bench:")?;
+        writeln!(
+            out,
+            "{}",
+            format_line_stats(&summary.bench, TEXT_OUTPUT_BASE_INDENT)
+        )?;
+    }
+    if !is_default(&summary.build_script) {
+        writeln!(out, "  Build script:")?;
+        writeln!(
+            out,
+            "{}",
+            format_line_stats(&summary.build_script, TEXT_OUTPUT_BASE_INDENT)
+        )?;
+    }
+    if !is_default(&summary.proc_macro) {
+        writeln!(out, "  Proc macro:")?;
+        writeln!(
+            out,
+            "{}",
+            format_line_stats(&summary.proc_macro, TEXT_OUTPUT_BASE_INDENT)
+        )?;
+    }
+
+    if let Some(sample) = &options.sample {
+        writeln!(
+            out,
+            "  Sample ({} of {} files, seed {}, {}% confidence):",
+            sample.sampled_files, sample.population_files, sample.seed, sample.confidence
+        )?;
+        for estimate in &sample.estimates {
+            writeln!(
+                out,
+                "{}Estimated {}: {}",
+                " ".repeat(TEXT_OUTPUT_BASE_INDENT),
+                estimate.metric.replace('-', " "),
+                format_estimated_total(estimate)
+            )?;
+        }
+    }
+
+    write_summary_sections(out, summary, options)?;
+
+    if !options.skipped.is_empty() {
+        writeln!(out, "  Skipped files:")?;
+        for skipped in &options.skipped {
+            writeln!(
+                out,
+                "{}{} [{}]: {}",
+                " ".repeat(TEXT_OUTPUT_BASE_INDENT),
+                skipped.path,
+                skipped.category.as_str(),
+                skipped.reason
+            )?;
+        }
+    }
+
+    writeln!(out, "\nFiles:")?;
+    for file in files {
+        writeln!(out, "  {}:", file.path)?;
+        if let Some(url) = &file.url {
+            writeln!(out, "    Link: {}", url)?;
+        }
+        if file.classification == Classification::Approximate {
+            writeln!(out, "    Classification: approximate")?;
+        }
+        if file.parse_errors > 0 {
+            writeln!(out, "    Syntax errors: {}", file.parse_errors)?;
+        }
+        for site in &file.parse_error_sites {
+            writeln!(
+                out,
+                "{}{}: {}",
+                " ".repeat(TEXT_OUTPUT_NESTED_INDENT),
+                site.line,
+                site.message
+            )?;
+        }
+        if file.generated {
+            writeln!(out, "    Generated: excluded from totals")?;
+        }
+        if file.ignored_lines > 0 {
+            writeln!(
+                out,
+                "    Ignored lines: {} (ruloc:ignore directives)",
+                file.ignored_lines
+            )?;
+        }
+        if let Some(doc_coverage) = &file.doc_coverage {
+            writeln!(
+                out,
+                "    Doc coverage: {}",
+                format_doc_coverage(doc_coverage)
+            )?;
+        }
+        if let Some(macro_lines) = &file.macro_lines {
+            writeln!(out, "    Macro lines: {}", format_macro_lines(macro_lines))?;
+        }
+        if let Some(items) = &file.items {
+            writeln!(out, "    Items: {}", format_item_visibility(items))?;
+        }
+        writeln!(out, "    Total:")?;
+        writeln!(
+            out,
+            "{}",
+            format_line_stats(&file.total, TEXT_OUTPUT_NESTED_INDENT)
+        )?;
+        writeln!(out, "    Production:")?;
+        writeln!(
+            out,
+            "{}",
+            format_line_stats(&file.production, TEXT_OUTPUT_NESTED_INDENT)
+        )?;
+        writeln!(out, "    Test:")?;
+        writeln!(
+            out,
+            "{}",
+            format_line_stats(&file.test, TEXT_OUTPUT_NESTED_INDENT)
+        )?;
+        if !is_default(&file.bench) {
+            writeln!(out, "    Bench:")?;
+            writeln!(
+                out,
+                "{}",
+                format_line_stats(&file.bench, TEXT_OUTPUT_NESTED_INDENT)
+            )?;
+        }
+        if !is_default(&file.build_script) {
+            writeln!(out, "    Build script:")?;
+            writeln!(
+                out,
+                "{}",
+                format_line_stats(&file.build_script, TEXT_OUTPUT_NESTED_INDENT)
+            )?;
+        }
+        if !is_default(&file.proc_macro) {
+            writeln!(out, "    Proc macro:")?;
+            writeln!(
+                out,
+                "{}",
+                format_line_stats(&file.proc_macro, TEXT_OUTPUT_NESTED_INDENT)
+            )?;
+        }
+        if !file.features.is_empty() {
+            writeln!(out, "    Features:")?;
+            writeln!(
+                out,
+                "{}",
+                format_feature_breakdown(&file.features, TEXT_OUTPUT_NESTED_INDENT)
+            )?;
+        }
+        if let Some(string_lines) = file.string_lines {
+            writeln!(out, "    String literal lines: {}", string_lines)?;
+        }
+        if let Some(tokens) = &file.tokens {
+            writeln!(
+                out,
+                "    Tokens: {} ({} non-trivia)",
+                tokens.all, tokens.non_trivia
+            )?;
+        }
+        if let Some(nodes) = file.nodes {
+            writeln!(out, "    Syntax nodes: {}", nodes)?;
+        }
+        if let Some(statements) = file.statements {
+            writeln!(out, "    Statements: {}", statements)?;
+        }
+        if let Some(line_lengths) = &file.line_lengths {
+            writeln!(
+                out,
+                "    Line lengths: {}",
+                format_line_lengths(line_lengths)
+            )?;
+        }
+        if let Some(cfg_surface) = &file.cfg_surface {
+            writeln!(
+                out,
+                "    Cfg-guarded: {} code lines ({} lines)",
+                cfg_surface.guarded.code_lines, cfg_surface.guarded.all_lines
+            )?;
+            if !cfg_surface.predicates.is_empty() {
+                writeln!(
+                    out,
+                    "{}",
+                    format_feature_breakdown(
+                        &cfg_surface.predicates,
+                        TEXT_OUTPUT_NESTED_INDENT + 2
+                    )
+                )?;
+            }
+        }
+        if !file.long_functions.is_empty() {
+            writeln!(out, "    Long functions:")?;
+            for long_function in &file.long_functions {
+                writeln!(
+                    out,
+                    "{}{}",
+                    " ".repeat(TEXT_OUTPUT_NESTED_INDENT),
+                    format_long_function(long_function)
+                )?;
+            }
+        }
+        if let Some(complexity) = &file.complexity {
+            writeln!(
+                out,
+                "    Complexity: {}",
+                format_function_complexity(complexity)
+            )?;
+        }
+        if !file.complex_functions.is_empty() {
+            writeln!(out, "    Complex functions:")?;
+            for function in &file.complex_functions {
+                writeln!(
+                    out,
+                    "{}{}",
+                    " ".repeat(TEXT_OUTPUT_NESTED_INDENT),
+                    format_complex_function(function)
+                )?;
+            }
+        }
+        if let Some(depth) = file.max_nesting {
+            writeln!(out, "    Max nesting: {}", depth)?;
+        }
+        if !file.nested_functions.is_empty() {
+            writeln!(out, "    Deeply nested functions:")?;
+            for function in &file.nested_functions {
+                writeln!(
+                    out,
+                    "{}{}",
+                    " ".repeat(TEXT_OUTPUT_NESTED_INDENT),
+                    format_nested_function(function)
+                )?;
+            }
+        }
+        if let Some(item_counts) = &file.item_counts {
+            writeln!(
+                out,
+                "    Item counts: {}",
+                format_item_kind_counts(item_counts)
+            )?;
+        }
+        if let Some(async_metrics) = &file.async_metrics {
+            writeln!(out, "    Async: {}", format_async_metrics(async_metrics))?;
+        }
+        if let Some(type_complexity) = &file.type_complexity {
+            writeln!(
+                out,
+                "    Type complexity: {}",
+                format_type_complexity(type_complexity)
+            )?;
+        }
+        if let Some(doc_detail) = &file.doc_detail {
+            writeln!(
+                out,
+                "    Doc volume: {}",
+                format_doc_volume(&doc_detail.volume)
+            )?;
+            for item in &doc_detail.items {
+                writeln!(
+                    out,
+                    "{}{} {} (line {}): {}",
+                    " ".repeat(TEXT_OUTPUT_NESTED_INDENT),
+                    item.kind,
+                    item.name,
+                    item.line,
+                    format_doc_volume(&item.volume)
+                )?;
+            }
+        }
+        if let Some(bucket) = &file.age_bucket {
+            writeln!(out, "    Age: {}", bucket)?;
+        }
+        if !file.test_name_violations.is_empty() {
+            writeln!(out, "    Test name violations:")?;
+            for violation in &file.test_name_violations {
+                writeln!(
+                    out,
+                    "{}{}: {}",
+                    " ".repeat(TEXT_OUTPUT_NESTED_INDENT),
+                    violation.line,
+                    violation.name
+                )?;
+            }
+        }
+        if !file.includes.is_empty() {
+            writeln!(out, "    Includes:")?;
+            for include in &file.includes {
+                writeln!(
+                    out,
+                    "{}{}",
+                    " ".repeat(TEXT_OUTPUT_NESTED_INDENT),
+                    format_include_target(include)
+                )?;
+            }
+        }
+        if !file.impls.is_empty() {
+            writeln!(out, "    Impl blocks:")?;
+            for block in &file.impls {
+                writeln!(
+                    out,
+                    "{}{}",
+                    " ".repeat(TEXT_OUTPUT_NESTED_INDENT),
+                    format_impl_block(block)
+                )?;
+            }
+        }
+        if !file.markers.is_empty() {
+            writeln!(out, "    Markers: {}", format_marker_counts(&file.markers))?;
+        }
+        for location in &file.marker_locations {
+            writeln!(
+                out,
+                "{}{}: {}",
+                " ".repeat(TEXT_OUTPUT_NESTED_INDENT),
+                location.line,
+                location.text
+            )?;
+        }
+        if !file.allowed_lints.is_empty() {
+            writeln!(
+                out,
+                "    Allowed lints: {}",
+                format_marker_counts(&file.allowed_lints)
+            )?;
+        }
+        if let Some(panics) = &file.panics {
+            writeln!(
+                out,
+                "    Panic sites: production {}, test {}",
+                panics.production.total(),
+                panics.test.total()
+            )?;
+        }
+        for site in &file.panic_sites {
+            writeln!(
+                out,
+                "{}{}",
+                " ".repeat(TEXT_OUTPUT_NESTED_INDENT),
+                format_panic_site(site)
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Outputs statistics in JSON format.
+///
+/// Serializes the summary and file statistics to pretty-printed JSON. Files are written
+/// one at a time as they are read back from the accumulator, so the report never holds
+/// every `FileStats` at once. Sinks receive the rendered report as a whole.
+///
+/// # Arguments
+///
+/// * `summary` - Summary of the analysis
+/// * `files` - File statistics in presentation order
+/// * `options` - Presentation options such as revision-pinned file links
+///
+/// # Errors
+///
+/// Returns an error if serialization fails or the report cannot be delivered
+fn output_json(
+    summary: Summary,
+    files: impl Iterator<Item = FileStats>,
+    options: &OutputOptions,
+) -> Result<(), RulocError> {
+    let documentation = options.documentation(&summary);
+    let languages = options.languages(&summary);
+    let report = Report {
+        summary,
+        files: SerializeIter::new(files),
+        revision: options.revision(),
+        labels: options.labels.clone(),
+        orphans: options.orphans.clone(),
+        skipped: options.skipped.clone(),
+        documentation,
+        languages,
+        metadata: options.metadata(),
+        sample: options.sample.clone(),
+    };
+
+    if options.sinks.is_empty() {
+        return options.write_output(|out| write_json_report(out, &report));
+    }
+
+    let json = serde_json::to_string_pretty(&report)
+        .map_err(|e| RulocError::Output(format!("Failed to serialize JSON: {}", e)))?;
+    options.deliver(&json, ReportKind::Json)
+}
+
+/// Writes the summary sections of the opt-in analyses, such as marker counts, duplicate
+/// groups, or the language roll-up, indented as in the detailed text report.
+///
+/// Nothing is written for analyses that were not requested, so both the detailed report
+/// and the summary table can append these sections.
+///
+/// # Arguments
+///
+/// * `out` - Destination of the report
+/// * `summary` - Summary of the analysis
+/// * `options` - Presentation options holding revision, labels, and discovered files
+///
+/// # Errors
+///
+/// Returns an error if writing fails
+fn write_summary_sections(
+    out: &mut dyn Write,
+    summary: &Summary,
+    options: &OutputOptions,
+) -> std::io::Result<()> {
+    if !summary.markers.is_empty() {
+        writeln!(out, "  Markers: {}", format_marker_counts(&summary.markers))?;
+    }
+
+    if !summary.allowed_lints.is_empty() {
+        writeln!(out, "  Most-allowed lints:")?;
+        for (lint, count) in most_allowed_lints(&summary.allowed_lints) {
+            writeln!(
+                out,
+                "{}{:>6}  {}",
+                " ".repeat(TEXT_OUTPUT_NESTED_INDENT),
+                count,
+                lint
+            )?;
+        }
+    }
+
+    if let Some(panics) = &summary.panics {
+        writeln!(
+            out,
+            "  Panic sites: production {}",
+            format_panic_counts(&panics.production)
+        )?;
+        writeln!(
+            out,
+            "  Panic sites: test {}",
+            format_panic_counts(&panics.test)
+        )?;
+    }
+
+    if let Some(complexity) = &summary.complexity {
+        writeln!(
+            out,
+            "  Complexity: {}",
+            format_function_complexity(complexity)
+        )?;
+    }
+
+    if summary.complex_functions > 0 {
+        writeln!(out, "  Complex functions: {}", summary.complex_functions)?;
+    }
+
+    if let Some(depth) = summary.max_nesting {
+        writeln!(out, "  Max nesting: {}", depth)?;
+    }
+
+    if summary.nested_functions > 0 {
+        writeln!(
+            out,
+            "  Deeply nested functions: {}",
+            summary.nested_functions
+        )?;
+    }
+
+    if summary.long_functions > 0 {
+        writeln!(out, "  Long functions: {}", summary.long_functions)?;
+    }
+
+    if summary.approximate_files > 0 {
+        writeln!(out, "  Approximate files: {}", summary.approximate_files)?;
+    }
+
+    if summary.parse_error_files > 0 {
+        writeln!(
+            out,
+            "  Files with syntax errors: {}",
+            summary.parse_error_files
+        )?;
+    }
+
+    if !is_default(&summary.generated) {
+        writeln!(out, "  Generated (excluded):")?;
+        writeln!(
+            out,
+            "{}",
+            format_line_stats(&summary.generated, TEXT_OUTPUT_BASE_INDENT)
+        )?;
+    }
+
+    if !summary.age_buckets.is_empty() {
+        writeln!(out, "  Age buckets:")?;
+        for bucket in AGE_BUCKETS {
+            if let Some(stats) = summary.age_buckets.get(bucket) {
+                writeln!(
+                    out,
+                    "{}{}: {} lines ({} code)",
+                    " ".repeat(TEXT_OUTPUT_BASE_INDENT),
+                    bucket,
+                    stats.all_lines,
+                    stats.code_lines
+                )?;
+            }
+        }
+    }
+
+    if summary.test_name_violations > 0 {
+        writeln!(
+            out,
+            "  Test name violations: {}",
+            summary.test_name_violations
+        )?;
+    }
+
+    if let Some(item_counts) = &summary.item_counts {
+        writeln!(
+            out,
+            "  Item counts: {}",
+            format_item_kind_counts(item_counts)
+        )?;
+    }
+
+    if let Some(async_metrics) = &summary.async_metrics {
+        writeln!(out, "  Async: {}", format_async_metrics(async_metrics))?;
+    }
+
+    if let Some(type_complexity) = &summary.type_complexity {
+        writeln!(
+            out,
+            "  Type complexity: {}",
+            format_type_complexity(type_complexity)
+        )?;
+    }
+
+    if let Some(doc_volume) = &summary.doc_volume {
+        writeln!(out, "  Doc volume: {}", format_doc_volume(doc_volume))?;
+    }
+
+    if !summary.trait_impls.is_empty() {
+        writeln!(out, "  Trait implementations:")?;
+        writeln!(
+            out,
+            "{}",
+            format_trait_inventory(&summary.trait_impls, TEXT_OUTPUT_BASE_INDENT)
+        )?;
+    }
+
+    if !summary.duplicates.is_empty() {
+        writeln!(out, "  Duplicates:")?;
+        for group in &summary.duplicates {
+            writeln!(
+                out,
+                "{}{} files, {}:",
+                " ".repeat(TEXT_OUTPUT_BASE_INDENT),
+                group.files.len(),
+                if group.identical {
+                    "identical"
+                } else {
+                    "identical ignoring whitespace and comments"
+                }
+            )?;
+            for file in &group.files {
+                writeln!(
+                    out,
+                    "{}{} ({} lines)",
+                    " ".repeat(TEXT_OUTPUT_NESTED_INDENT),
+                    file.path,
+                    file.lines
+                )?;
+            }
+        }
+    }
+
+    if !summary.features.is_empty() {
+        writeln!(out, "  Features:")?;
+        writeln!(
+            out,
+            "{}",
+            format_feature_breakdown(&summary.features, TEXT_OUTPUT_BASE_INDENT)
+        )?;
+    }
+
+    if let Some(string_lines) = summary.string_lines {
+        writeln!(out, "  String literal lines: {}", string_lines)?;
+    }
+
+    if let Some(tokens) = &summary.tokens {
+        writeln!(
+            out,
+            "  Tokens: {} ({} non-trivia)",
+            tokens.all, tokens.non_trivia
+        )?;
+    }
+
+    if let Some(nodes) = summary.nodes {
+        writeln!(out, "  Syntax nodes: {}", nodes)?;
+    }
+
+    if let Some(statements) = summary.statements {
+        writeln!(out, "  Statements: {}", statements)?;
+    }
+
+    if let Some(line_lengths) = &summary.line_lengths {
+        writeln!(out, "  Line lengths: {}", format_line_lengths(line_lengths))?;
+    }
+
+    if let Some(cfg_surface) = &summary.cfg_surface {
+        writeln!(
+            out,
+            "  Cfg-guarded: {} code lines ({} lines)",
+            cfg_surface.guarded.code_lines, cfg_surface.guarded.all_lines
+        )?;
+        if !cfg_surface.predicates.is_empty() {
+            writeln!(
+                out,
+                "{}",
+                format_feature_breakdown(&cfg_surface.predicates, TEXT_OUTPUT_NESTED_INDENT)
+            )?;
+        }
+    }
+
+    if let Some(revision) = options.revision() {
+        writeln!(out, "  Revision: {}", revision)?;
+    }
+
+    if !options.labels.is_empty() {
+        writeln!(out, "  Labels: {}", format_labels(&options.labels))?;
+    }
+
+    if !options.orphans.is_empty() {
+        writeln!(out, "  Orphaned files:")?;
+        for orphan in &options.orphans {
+            writeln!(out, "{}{}", " ".repeat(TEXT_OUTPUT_BASE_INDENT), orphan)?;
+        }
+    }
+
+    if let Some(documentation) = options.documentation(summary) {
+        writeln!(out, "  Documentation:")?;
+        writeln!(
+            out,
+            "{}",
+            format_documentation(&documentation, TEXT_OUTPUT_BASE_INDENT)
+        )?;
+    }
+
+    if let Some(languages) = options.languages(summary) {
+        writeln!(out, "  Languages:")?;
+        for (name, lines) in &languages.languages {
+            writeln!(
+                out,
+                "{}{}: {}",
+                " ".repeat(TEXT_OUTPUT_BASE_INDENT),
+                name,
+                format_language_lines(lines)
+            )?;
+        }
+        writeln!(
+            out,
+            "{}Total: {}",
+            " ".repeat(TEXT_OUTPUT_BASE_INDENT),
+            format_language_lines(&languages.total)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Writes the per-file listings of the opt-in analyses below a file heading.
+///
+/// Used by the summary table, whose rows cannot hold listings such as long functions,
+/// work-marker locations, or syntax errors. Files without any listing are left out.
+///
+/// # Arguments
+///
+/// * `out` - Destination of the report
+/// * `file` - Statistics of one file or module
+///
+/// # Errors
+///
+/// Returns an error if writing fails
+fn write_file_listings(out: &mut dyn Write, file: &FileStats) -> std::io::Result<()> {
+    let indent = " ".repeat(TEXT_OUTPUT_NESTED_INDENT);
+    let mut sections: Vec<(&str, Vec<String>)> = vec![
+        (
+            "Syntax errors",
+            file.parse_error_sites
+                .iter()
+                .map(|site| format!("{}: {}", site.line, site.message))
+                .collect(),
+        ),
+        (
+            "Long functions",
+            file.long_functions
+                .iter()
+                .map(format_long_function)
+                .collect(),
+        ),
+        (
+            "Complex functions",
+            file.complex_functions
+                .iter()
+                .map(format_complex_function)
+                .collect(),
+        ),
+        (
+            "Deeply nested functions",
+            file.nested_functions
+                .iter()
+                .map(format_nested_function)
+                .collect(),
+        ),
+        (
+            "Test name violations",
+            file.test_name_violations
+                .iter()
+                .map(|violation| format!("{}: {}", violation.line, violation.name))
+                .collect(),
+        ),
+        (
+            "Includes",
+            file.includes.iter().map(format_include_target).collect(),
+        ),
+        (
+            "Impl blocks",
+            file.impls.iter().map(format_impl_block).collect(),
+        ),
+        (
+            "Panic sites",
+            file.panic_sites.iter().map(format_panic_site).collect(),
+        ),
+        (
+            "Markers",
+            file.marker_locations
+                .iter()
+                .map(|location| format!("{}: {}", location.line, location.text))
+                .collect(),
+        ),
+    ];
+    sections.retain(|(_, entries)| !entries.is_empty());
+    if sections.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(out, "  {}:", file.path)?;
+    for (heading, entries) in sections {
+        writeln!(out, "    {}:", heading)?;
+        for entry in entries {
+            writeln!(out, "{}{}", indent, entry)?;
+        }
+    }
+    Ok(())
+}
+
+/// Outputs statistics as a standalone HTML page.
+///
+/// The page holds the same columns as the summary table, one row per file followed by the
+/// totals, with file paths linked when `--link-base` is set. It has no external
+/// resources, so it can be archived as a CI artifact and opened anywhere.
+///
+/// # Arguments
+///
+/// * `summary` - Summary of the analysis
+/// * `files` - File statistics in presentation order
+/// * `options` - Presentation options such as revision-pinned file links
+///
+/// # Errors
+///
+/// Returns an error if the page cannot be written
+fn output_html(
+    summary: Summary,
+    files: impl Iterator<Item = FileStats>,
+    options: &OutputOptions,
+) -> Result<(), RulocError> {
+    options.write_output(|out| {
+        write_html_report(out, &summary, files, options)
+            .map_err(RulocError::io("Failed to write the report"))
+    })
+}
+
+/// Styles embedded in the HTML report.
+const HTML_REPORT_STYLE: &str = "body{font-family:sans-serif;margin:2em}\
+table{border-collapse:collapse}th,td{padding:.25em .75em;border-bottom:1px solid #ddd}\
+td:not(:first-child),th:not(:first-child){text-align:right}tfoot{font-weight:bold}";
+
+/// Writes the HTML page of `--out-html`.
+///
+/// # Arguments
+///
+/// * `out` - Destination of the page
+/// * `summary` - Summary of the analysis
+/// * `files` - File statistics in presentation order
+/// * `options` - Presentation options such as revision-pinned file links
+///
+/// # Errors
+///
+/// Returns an error if writing fails
+fn write_html_report(
+    out: &mut dyn Write,
+    summary: &Summary,
+    files: impl Iterator<Item = FileStats>,
+    options: &OutputOptions,
+) -> std::io::Result<()> {
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html lang=\"en\">")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>ruloc report</title>")?;
+    writeln!(out, "<style>{}</style>", HTML_REPORT_STYLE)?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>ruloc report</h1>")?;
+    if let Some(revision) = options.revision() {
+        writeln!(
+            out,
+            "<p>Revision: <code>{}</code></p>",
+            escape_html(&revision)
+        )?;
+    }
+    for (key, value) in &options.labels {
+        writeln!(out, "<p>{}: {}</p>", escape_html(key), escape_html(value))?;
+    }
+
+    writeln!(out, "<table>")?;
+    writeln!(out, "<thead><tr>")?;
+    for header in options.table_headers() {
+        writeln!(out, "<th>{}</th>", header)?;
+    }
+    if options.trends.is_some() {
+        writeln!(out, "<th>Trend</th>")?;
+    }
+    writeln!(out, "</tr></thead>")?;
+    writeln!(out, "<tbody>")?;
+    for file in files {
+        let mut label = match &file.url {
+            Some(url) => format!(
+                "<a href=\"{}\">{}</a>",
+                escape_html(url),
+                escape_html(&file.path)
+            ),
+            None => escape_html(&file.path),
+        };
+        if file.generated {
+            label.push_str(" (generated)");
+        }
+        let [label, cells @ ..] = table_row(label, &file.total, &file.test);
+        write!(out, "<tr><td>{}</td>", label)?;
+        for cell in cells {
+            write!(out, "<td>{}</td>", cell)?;
+        }
+        if let Some(trends) = &options.trends {
+            let mut points = trends.get(&file.path).cloned().unwrap_or_default();
+            points.push(Some(file.total.code_lines));
+            write!(out, "<td>{}</td>", render_sparkline(&points))?;
+        }
+        writeln!(out, "</tr>")?;
+    }
+    writeln!(out, "</tbody>")?;
+    let totals = table_row(
+        format!("Total ({} files)", summary.files),
+        &summary.total,
+        &summary.test,
+    );
+    write!(out, "<tfoot><tr>")?;
+    for cell in totals {
+        write!(out, "<td>{}</td>", cell)?;
+    }
+    if options.trends.is_some() {
+        write!(out, "<td></td>")?;
+    }
+    writeln!(out, "</tr></tfoot>")?;
+    writeln!(out, "</table>")?;
+
+    if !options.skipped.is_empty() {
+        writeln!(out, "<p>{} file(s) skipped.</p>", options.skipped.len())?;
+    }
+    if let Some(sample) = &options.sample {
+        writeln!(
+            out,
+            "<p>{}</p>",
+            escape_html(&format_sample_estimate(sample))
+        )?;
+    }
+    if let Some(projection) = &options.projection {
+        writeln!(out, "<h2>Budget</h2>")?;
+        for line in format_budget_projection(projection).lines() {
+            writeln!(out, "<p>{}</p>", escape_html(line))?;
+        }
+    }
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")
+}
+
+/// Draws a file's code lines over several runs as an inline SVG sparkline.
+///
+/// # Arguments
+///
+/// * `points` - Code lines per run, oldest first; `None` for runs without the file
+///
+/// # Returns
+///
+/// An `<svg>` element scaled between the smallest and largest count, listing the counts in
+/// its tooltip
+fn render_sparkline(points: &[Option<usize>]) -> String {
+    const WIDTH: f64 = 80.0;
+    const HEIGHT: f64 = 16.0;
+    let values = points.iter().flatten();
+    let (min, max) = (
+        values.clone().min().copied().unwrap_or(0),
+        values.max().copied().unwrap_or(0),
+    );
+    let step = WIDTH / (points.len().max(2) - 1) as f64;
+    let coordinates: Vec<String> = points
+        .iter()
+        .enumerate()
+        .filter_map(|(run, lines)| {
+            let lines = (*lines)?;
+            let y = if max == min {
+                HEIGHT / 2.0
+            } else {
+                1.0 + (max - lines) as f64 * (HEIGHT - 2.0) / (max - min) as f64
+            };
+            Some(format!("{:.1},{:.1}", run as f64 * step, y))
+        })
+        .collect();
+    let title: Vec<String> = points
+        .iter()
+        .map(|lines| lines.map_or_else(|| "-".to_string(), |lines| lines.to_string()))
+        .collect();
+
+    format!(
+        "<svg class=\"trend\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\
+         <title>Code lines: {}</title>\
+         <polyline fill=\"none\" stroke=\"currentColor\" points=\"{}\"/></svg>",
+        title.join(", "),
+        coordinates.join(" "),
+        w = WIDTH,
+        h = HEIGHT
+    )
+}
+
+/// Escapes text for inclusion in HTML content and quoted attribute values.
+///
+/// # Arguments
+///
+/// * `text` - Text to escape
+///
+/// # Returns
+///
+/// The text with `&`, `<`, `>`, and `"` replaced by character references
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Number of files buffered for each report writer when several formats are written.
+const REPORT_QUEUE_DEPTH: usize = 256;
+
+/// Writes every requested report from a single pass over the accumulator.
+///
+/// With several targets, each format runs on its own thread and receives the files
+/// through a bounded channel, so the accumulator is read once and streaming formats such
+/// as JSON still never hold every file at once.
+///
+/// # Arguments
+///
+/// * `accumulator` - The stats accumulator to read from
+/// * `targets` - Reports to write and their destinations
+/// * `options` - Presentation options shared by all reports
+///
+/// # Errors
+///
+/// Returns the first error of the reports, in target order
+fn output_reports<A: StatsAccumulator>(
+    accumulator: &A,
+    targets: &[ReportTarget],
+    options: &OutputOptions,
+) -> Result<(), RulocError> {
+    let (summary, files) = options.ordered_report(accumulator)?;
+    let options: Vec<OutputOptions> = targets
+        .iter()
+        .map(|target| OutputOptions {
+            output: target.path.clone(),
+            ..options.clone()
+        })
+        .collect();
+
+    if let ([target], [options]) = (targets, options.as_slice()) {
+        return target.format.write(summary, files, options);
+    }
+
+    std::thread::scope(|threads| {
+        let (senders, writers): (Vec<_>, Vec<_>) = targets
+            .iter()
+            .zip(&options)
+            .map(|(target, options)| {
+                let (sender, receiver) = mpsc::sync_channel::<FileStats>(REPORT_QUEUE_DEPTH);
+                let summary = summary.clone();
+                let writer = threads
+                    .spawn(move || target.format.write(summary, receiver.into_iter(), options));
+                (sender, writer)
+            })
+            .collect();
+
+        for file in files {
+            for sender in &senders {
+                // Sending only fails once that writer has stopped, which it reports below
+                let _ = sender.send(file.clone());
+            }
+        }
+        drop(senders);
+
+        writers
+            .into_iter()
+            .map(|writer| writer.join().expect("report writer panicked"))
+            .collect::<Result<Vec<()>, _>>()
+            .map(|_| ())
+    })
+}
+
+/// Writes a pretty-printed JSON report followed by a newline, streaming its files.
+///
+/// # Arguments
+///
+/// * `writer` - Destination of the report
+/// * `report` - Report to write; files streamed from an iterator are consumed while
+///   writing
+///
+/// # Returns
+///
+/// `Ok(())` once the whole report has been written and flushed
+///
+/// # Errors
+///
+/// Returns an error if serialization or writing fails
+fn write_json_report<W: Write>(mut writer: W, report: &impl Serialize) -> Result<(), RulocError> {
+    serde_json::to_writer_pretty(&mut writer, report)
+        .map_err(|e| RulocError::Output(format!("Failed to serialize JSON: {}", e)))?;
+    writeln!(writer)
+        .and_then(|()| writer.flush())
+        .map_err(RulocError::io("Failed to write JSON report"))
+}
+
+/// Environment variable naming the file that GitHub Actions renders as the job summary.
+const GITHUB_STEP_SUMMARY_ENV: &str = "GITHUB_STEP_SUMMARY";
+
+/// Writes the GitHub Actions job summary and threshold annotations for `--out-github-summary`.
+///
+/// The Markdown summary is appended to the file named by `$GITHUB_STEP_SUMMARY`, as other
+/// steps may have written to it already; outside of GitHub Actions, where the variable is
+/// unset, only a warning is logged. Annotations are printed to stderr, so they never mix
+/// with a JSON report on stdout.
+///
+/// # Arguments
+///
+/// * `args` - Arguments carrying the thresholds and summary settings
+/// * `accumulator` - The stats accumulator to read from
+/// * `options` - Presentation options such as path normalization
+///
+/// # Errors
+///
+/// Returns an error if the accumulator cannot provide file statistics or the summary
+/// file cannot be written
+fn write_github_summary<A: StatsAccumulator>(
+    args: &Args,
+    accumulator: &A,
+    options: &OutputOptions,
+) -> Result<(), RulocError> {
+    let (summary, files) = options.ordered_report(accumulator)?;
+
+    let mut rows = Vec::new();
+    for file in files {
+        for finding in file_findings(args, &file) {
+            eprintln!("{}", github_warning(&finding));
+        }
+        if args.github_summary_files {
+            rows.push(table_row(file.path, &file.total, &file.test));
+        }
+    }
+    for finding in skipped_findings(&options.skipped) {
+        eprintln!("{}", github_warning(&finding));
+    }
+
+    let Some(summary_path) = std::env::var_os(GITHUB_STEP_SUMMARY_ENV) else {
+        warn!(
+            "{} is not set; skipping the job summary",
+            GITHUB_STEP_SUMMARY_ENV
+        );
+        return Ok(());
+    };
+    let markdown = format_github_summary(
+        &summary,
+        &options.table_headers(),
+        args.github_summary_files.then_some(rows.as_slice()),
+        options.skipped.len(),
+    );
+    let summary_path = PathBuf::from(summary_path);
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&summary_path)
+        .and_then(|mut file| file.write_all(markdown.as_bytes()))
+        .map_err(RulocError::io(format!(
+            "Failed to write job summary '{}'",
+            summary_path.display()
+        )))
+}
+
+/// Renders the Markdown job summary for `--out-github-summary`.
+///
+/// # Arguments
+///
+/// * `summary` - Aggregate statistics of the analysis
+/// * `headers` - Column headings of the file table, such as [`TABLE_HEADERS`]
+/// * `files` - Per-file rows in `headers` order, when the file table is requested
+/// * `skipped` - Number of files excluded from the analysis
+///
+/// # Returns
+///
+/// The summary, ending with a blank line so later steps can append their own sections
+fn format_github_summary(
+    summary: &Summary,
+    headers: &[&str; 6],
+    files: Option<&[[String; 6]]>,
+    skipped: usize,
+) -> String {
+    let mut scopes = vec![
+        ("Total", &summary.total),
+        ("Production", &summary.production),
+        ("Test", &summary.test),
+    ];
+    if !is_default(&summary.bench) {
+        scopes.push(("Bench", &summary.bench));
+    }
+    if !is_default(&summary.build_script) {
+        scopes.push(("Build script", &summary.build_script));
+    }
+    if !is_default(&summary.proc_macro) {
+        scopes.push(("Proc macro", &summary.proc_macro));
+    }
+
+    let mut lines = vec![
+        "## ruloc".to_string(),
+        String::new(),
+        "| Scope | Code | Comments | Docs | Blanks | All |".to_string(),
+        "|:--|--:|--:|--:|--:|--:|".to_string(),
+    ];
+    lines.extend(scopes.into_iter().map(|(scope, stats)| {
+        format!(
+            "| {} | {} | {} | {} | {} | {} |",
+            scope,
+            stats.code_lines,
+            stats.comment_lines,
+            stats.rustdoc_lines,
+            stats.blank_lines,
+            stats.all_lines
+        )
+    }));
+    lines.push(String::new());
+
+    let mut facts = vec![format!("**Files:** {}", summary.files)];
+    if summary.long_functions > 0 {
+        facts.push(format!("**Long functions:** {}", summary.long_functions));
+    }
+    if summary.test_name_violations > 0 {
+        facts.push(format!(
+            "**Test name violations:** {}",
+            summary.test_name_violations
+        ));
+    }
+    if skipped > 0 {
+        facts.push(format!("**Skipped files:** {}", skipped));
+    }
+    lines.push(facts.join(" · "));
+    lines.push(String::new());
+
+    if let Some(files) = files {
+        lines.push(format!(
+            "<details><summary>Files ({})</summary>",
+            files.len()
+        ));
+        lines.push(String::new());
+        lines.push(format!("| {} |", headers.join(" | ")));
+        lines.push("|:--|--:|--:|--:|--:|--:|".to_string());
+        lines.extend(files.iter().map(|row| {
+            let mut cells = row.clone();
+            cells[0] = format!("`{}`", cells[0].replace('|', "\\|"));
+            format!("| {} |", cells.join(" | "))
+        }));
+        lines.push(String::new());
+        lines.push("</details>".to_string());
+        lines.push(String::new());
+    }
+
+    lines.join("\n") + "\n"
+}
+
+/// Formats a GitHub Actions `::warning` workflow command for a finding.
+///
+/// # Arguments
+///
+/// * `finding` - Finding to annotate, with its path relative to the repository root
+///
+/// # Returns
+///
+/// The command, with properties and message escaped as GitHub Actions requires
+fn github_warning(finding: &Finding) -> String {
+    let escape_data = |value: &str| {
+        value
+            .replace('%', "%25")
+            .replace('\r', "%0D")
+            .replace('\n', "%0A")
+    };
+    let escape_property = |value: &str| escape_data(value).replace(':', "%3A").replace(',', "%2C");
+    let line = finding
+        .line
+        .map(|line| format!(",line={}", line))
+        .unwrap_or_default();
+    format!(
+        "::warning file={}{},title={}::{}",
+        escape_property(&finding.path),
+        line,
+        escape_property(finding.rule.title()),
+        escape_data(&finding.message)
+    )
+}
+
+/// Code-quality rule whose violations are reported as findings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FindingRule {
+    /// A function longer than `--max-fn-lines`.
+    LongFunction,
+
+    /// A test function not matching `--test-name-pattern`.
+    TestName,
+
+    /// A function more complex than `--max-complexity`.
+    ComplexFunction,
+
+    /// A function nested deeper than `--max-nesting`.
+    DeepNesting,
+
+    /// A file whose public items are documented below `--min-doc-coverage`.
+    LowDocCoverage,
+
+    /// A file skipped for exceeding `--max-file-size`.
+    OversizedFile,
+}
+
+impl FindingRule {
+    /// Every rule, in the order listed in SARIF tool metadata.
+    const ALL: [FindingRule; 6] = [
+        FindingRule::LongFunction,
+        FindingRule::TestName,
+        FindingRule::ComplexFunction,
+        FindingRule::DeepNesting,
+        FindingRule::LowDocCoverage,
+        FindingRule::OversizedFile,
+    ];
+
+    /// Returns the stable identifier of the rule, used as the SARIF `ruleId`.
+    fn id(self) -> &'static str {
+        match self {
+            FindingRule::LongFunction => "long-function",
+            FindingRule::TestName => "test-name",
+            FindingRule::ComplexFunction => "complex-function",
+            FindingRule::DeepNesting => "deep-nesting",
+            FindingRule::LowDocCoverage => "low-doc-coverage",
+            FindingRule::OversizedFile => "oversized-file",
+        }
+    }
+
+    /// Returns the short human-readable title of the rule.
+    fn title(self) -> &'static str {
+        match self {
+            FindingRule::LongFunction => "Long function",
+            FindingRule::TestName => "Test name",
+            FindingRule::ComplexFunction => "Complex function",
+            FindingRule::DeepNesting => "Deep nesting",
+            FindingRule::LowDocCoverage => "Low doc coverage",
+            FindingRule::OversizedFile => "Oversized file",
+        }
+    }
+
+    /// Returns a one-sentence description of what the rule checks.
+    fn description(self) -> &'static str {
+        match self {
+            FindingRule::LongFunction => "Function spans more lines than --max-fn-lines allows.",
+            FindingRule::TestName => "Test function name does not match --test-name-pattern.",
+            FindingRule::ComplexFunction => {
+                "Function's cyclomatic complexity exceeds --max-complexity."
+            }
+            FindingRule::DeepNesting => "Function nests control flow deeper than --max-nesting.",
+            FindingRule::LowDocCoverage => {
+                "Share of documented public items is below --min-doc-coverage."
+            }
+            FindingRule::OversizedFile => {
+                "File exceeds --max-file-size and was excluded from the analysis."
+            }
+        }
+    }
+}
+
+/// A single violation of a [`FindingRule`], located in the analyzed sources.
+///
+/// Findings are derived from the per-file statistics and skipped files after the analysis,
+/// so that annotation and SARIF consumers share one model independent of the raw counts.
+#[derive(Debug, Clone, PartialEq)]
+struct Finding {
+    /// Rule that was violated.
+    rule: FindingRule,
+
+    /// Path of the offending file, relative to the repository root.
+    path: String,
+
+    /// One-based line of the violation, when it concerns a specific item.
+    line: Option<usize>,
+
+    /// Description of the violation.
+    message: String,
+}
+
+/// Resolves a reported file path against the analysis root.
+///
+/// # Arguments
+///
+/// * `args` - Arguments determining the analysis root
+/// * `path` - Path as reported, relative to the analysis root unless absolute
+///
+/// # Returns
+///
+/// The path relative to the working directory, without a leading `./`
+fn repository_path(args: &Args, path: &str) -> String {
+    let path = args.analysis_root().join(path);
+    display_path(path.strip_prefix(".").unwrap_or(&path))
+}
+
+/// Derives the findings for an analyzed file.
+///
+/// # Arguments
+///
+/// * `args` - Arguments carrying the thresholds that were checked
+/// * `file` - Statistics of the analyzed file
+///
+/// # Returns
+///
+/// Long-function and test-name findings in line order, followed by a doc-coverage finding
+/// when `--min-doc-coverage` is set and not met
+fn file_findings(args: &Args, file: &FileStats) -> Vec<Finding> {
+    let path = repository_path(args, &file.path);
+    let mut findings: Vec<Finding> = file
+        .long_functions
+        .iter()
+        .map(|function| Finding {
+            rule: FindingRule::LongFunction,
+            path: path.clone(),
+            line: Some(function.start_line),
+            message: format!(
+                "{} spans {} lines (maximum {})",
+                function.name,
+                function.lines,
+                args.max_fn_lines.unwrap_or_default()
+            ),
+        })
+        .chain(file.test_name_violations.iter().map(|violation| Finding {
+            rule: FindingRule::TestName,
+            path: path.clone(),
+            line: Some(violation.line),
+            message: format!(
+                "{} does not match the pattern '{}'",
+                violation.name,
+                args.test_name_pattern
+                    .as_ref()
+                    .map(Regex::as_str)
+                    .unwrap_or_default()
+            ),
+        }))
+        .chain(file.complex_functions.iter().map(|function| Finding {
+            rule: FindingRule::ComplexFunction,
+            path: path.clone(),
+            line: Some(function.start_line),
+            message: format!(
+                "{} has a cyclomatic complexity of {} (maximum {})",
+                function.name,
+                function.complexity,
+                args.max_complexity.unwrap_or_default()
+            ),
+        }))
+        .chain(file.nested_functions.iter().map(|function| Finding {
+            rule: FindingRule::DeepNesting,
+            path: path.clone(),
+            line: Some(function.start_line),
+            message: format!(
+                "{} nests {} levels deep (maximum {})",
+                function.name,
+                function.depth,
+                args.max_nesting.unwrap_or_default()
+            ),
+        }))
+        .collect();
+    findings.sort_by_key(|finding| finding.line);
+
+    if let (Some(minimum), Some(doc_coverage)) = (args.min_doc_coverage, file.doc_coverage)
+        && let Some(coverage) = doc_coverage.percentage()
+        && coverage < minimum
+    {
+        findings.push(Finding {
+            rule: FindingRule::LowDocCoverage,
+            path,
+            line: None,
+            message: format!(
+                "{} of {} public items documented ({:.1}%, minimum {}%)",
+                doc_coverage.documented_items, doc_coverage.public_items, coverage, minimum
+            ),
+        });
+    }
+    findings
+}
+
+/// Derives the findings for files excluded from the analysis.
+///
+/// # Arguments
+///
+/// * `skipped` - Files skipped during the analysis
+///
+/// # Returns
+///
+/// An oversized-file finding for every file skipped for its size
+fn skipped_findings(skipped: &[SkippedFile]) -> impl Iterator<Item = Finding> + '_ {
+    skipped
+        .iter()
+        .filter(|file| file.category == SkipCategory::Oversized)
+        .map(|file| Finding {
+            rule: FindingRule::OversizedFile,
+            path: display_path(
+                Path::new(&file.path)
+                    .strip_prefix(".")
+                    .unwrap_or(Path::new(&file.path)),
+            ),
+            line: None,
+            message: file.reason.clone(),
+        })
+}
+
+/// Writes the findings of a run to `--out-sarif` as a SARIF 2.1.0 log.
+///
+/// # Arguments
+///
+/// * `args` - Arguments carrying the checked thresholds
+/// * `accumulator` - Accumulator holding the per-file statistics
+/// * `options` - Output options holding the skipped files and the file order
+/// * `path` - File to write the log to
+///
+/// # Errors
+///
+/// Returns an error if the statistics cannot be read back or the file cannot be written
+fn write_sarif<A: StatsAccumulator>(
+    args: &Args,
+    accumulator: &A,
+    options: &OutputOptions,
+    path: &Path,
+) -> Result<(), RulocError> {
+    let (_, files) = options.ordered_report(accumulator)?;
+    let findings: Vec<Finding> = files
+        .flat_map(|file| file_findings(args, &file))
+        .chain(skipped_findings(&options.skipped))
+        .collect();
+    let json = serde_json::to_string_pretty(&sarif_log(&findings))
+        .map_err(|e| RulocError::Output(format!("Failed to serialize SARIF log: {}", e)))?;
+    fs::write(path, json + "\n").map_err(RulocError::io(format!(
+        "Failed to write SARIF log '{}'",
+        path.display()
+    )))
+}
+
+/// Builds a SARIF 2.1.0 log reporting findings as warnings of a single ruloc run.
+///
+/// # Arguments
+///
+/// * `findings` - Findings to report
+///
+/// # Returns
+///
+/// The log, describing every [`FindingRule`] in the tool metadata
+fn sarif_log(findings: &[Finding]) -> serde_json::Value {
+    let rules: Vec<serde_json::Value> = FindingRule::ALL
+        .iter()
+        .map(|rule| {
+            serde_json::json!({
+                "id": rule.id(),
+                "name": rule.title(),
+                "shortDescription": { "text": rule.description() },
+                "defaultConfiguration": { "level": "warning" },
+            })
+        })
+        .collect();
+    let results: Vec<serde_json::Value> = findings
+        .iter()
+        .map(|finding| {
+            let mut location = serde_json::json!({
+                "artifactLocation": { "uri": finding.path },
+            });
+            if let Some(line) = finding.line {
+                location["region"] = serde_json::json!({ "startLine": line });
+            }
+            serde_json::json!({
+                "ruleId": finding.rule.id(),
+                "ruleIndex": FindingRule::ALL.iter().position(|rule| *rule == finding.rule),
+                "level": "warning",
+                "message": { "text": finding.message },
+                "locations": [{ "physicalLocation": location }],
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "ruloc",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+/// Metric rendered by `--badge`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum BadgeKind {
+    /// Total code lines, abbreviated (e.g., `12.3k`).
+    CodeLines,
+
+    /// Share of code lines that are test code.
+    TestRatio,
+
+    /// Production rustdoc lines relative to production code lines.
+    DocRatio,
+}
+
+/// A shields.io endpoint badge (<https://shields.io/badges/endpoint-badge>).
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct ShieldsBadge {
+    /// Endpoint schema version; always 1.
+    schema_version: u8,
+
+    /// Left-hand text of the badge.
+    label: &'static str,
+
+    /// Right-hand text of the badge.
+    message: String,
+
+    /// Color of the right-hand side.
+    color: &'static str,
+}
+
+impl BadgeKind {
+    /// Builds the badge for this metric.
+    ///
+    /// Ratios are colored from orange to bright green as they grow; the line count is
+    /// always blue.
+    ///
+    /// # Arguments
+    ///
+    /// * `summary` - Summary of the analysis
+    ///
+    /// # Returns
+    ///
+    /// The badge, with `n/a` as message when a ratio has no code lines to relate to
+    fn badge(self, summary: &Summary) -> ShieldsBadge {
+        let percentage =
+            |part: usize, whole: usize| (whole > 0).then(|| part as f64 * 100.0 / whole as f64);
+        let (label, ratio) = match self {
+            BadgeKind::CodeLines => {
+                return ShieldsBadge {
+                    schema_version: 1,
+                    label: "lines of code",
+                    message: format_compact_count(summary.total.code_lines),
+                    color: "blue",
+                };
+            }
+            BadgeKind::TestRatio => (
+                "test code",
+                percentage(summary.test.code_lines, summary.total.code_lines),
+            ),
+            BadgeKind::DocRatio => (
+                "docs",
+                percentage(
+                    summary.production.rustdoc_lines,
+                    summary.production.code_lines,
+                ),
+            ),
+        };
+
+        ShieldsBadge {
+            schema_version: 1,
+            label,
+            message: ratio.map_or_else(|| "n/a".to_string(), |ratio| format!("{:.1}%", ratio)),
+            color: match ratio {
+                Some(ratio) if ratio >= 40.0 => "brightgreen",
+                Some(ratio) if ratio >= 25.0 => "green",
+                Some(ratio) if ratio >= 10.0 => "yellow",
+                Some(_) => "orange",
+                None => "lightgrey",
+            },
+        }
+    }
+}
+
+/// Abbreviates a count with a `k` or `M` suffix, as badges have little room.
+///
+/// # Arguments
+///
+/// * `count` - The count to abbreviate
+///
+/// # Returns
+///
+/// The count as is below 1000, otherwise with one decimal and a suffix (e.g., `12.3k`)
+fn format_compact_count(count: usize) -> String {
+    match count {
+        0..1_000 => count.to_string(),
+        1_000..1_000_000 => format!("{:.1}k", count as f64 / 1e3),
+        _ => format!("{:.1}M", count as f64 / 1e6),
+    }
+}
+
+/// Writes the shields.io endpoint badge requested with `--badge` and `--badge-out`.
+///
+/// # Arguments
+///
+/// * `args` - Arguments carrying the badge metric and destination
+/// * `summary` - Summary of the analysis
+///
+/// # Errors
+///
+/// Returns an error if the badge cannot be serialized or written
+fn write_badge(args: &Args, summary: &Summary) -> Result<(), RulocError> {
+    let (Some(kind), Some(path)) = (args.badge, &args.badge_out) else {
+        return Ok(());
+    };
+    let json = serde_json::to_string_pretty(&kind.badge(summary))
+        .map_err(|e| RulocError::Output(format!("Failed to serialize badge: {}", e)))?;
+    fs::write(path, json + "\n").map_err(RulocError::io(format!(
+        "Failed to write badge '{}'",
+        path.display()
+    )))
+}
+
+/// Unit tests for the ruloc line counting and analysis functionality.
+///
+/// Tests cover:
+/// - Line statistics operations (default, add)
+/// - Line classification (blank, comments, code)
+/// - Block comment handling
+/// - Production vs test code classification
+/// - Summary aggregation
+/// - Output formatting
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ==================== Test Helpers ====================
+
+    /// Creates a `LineStats` instance with the given values.
+    ///
+    /// # Arguments
+    ///
+    /// * `all_lines` - Total number of lines
+    /// * `blank_lines` - Number of blank lines
+    /// * `comment_lines` - Number of comment lines (excluding rustdocs)
+    /// * `rustdoc_lines` - Number of rustdoc lines
+    /// * `code_lines` - Number of code lines
+    fn make_line_stats(
+        all_lines: usize,
+        blank_lines: usize,
+        comment_lines: usize,
+        rustdoc_lines: usize,
+        code_lines: usize,
+    ) -> LineStats {
+        LineStats {
+            all_lines,
+            blank_lines,
+            comment_lines,
+            rustdoc_lines,
+            code_lines,
+        }
+    }
+
+    /// Creates a simple `FileStats` instance for testing.
+    ///
+    /// Creates a file with the given stats for all code (no distinction between production and test).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path
+    /// * `all_lines` - Total number of lines
+    /// * `blank_lines` - Number of blank lines
+    /// * `comment_lines` - Number of comment lines (excluding rustdocs)
+    /// * `rustdoc_lines` - Number of rustdoc lines
+    /// * `code_lines` - Number of code lines
+    fn make_simple_file_stats(
+        path: &str,
+        all_lines: usize,
+        blank_lines: usize,
+        comment_lines: usize,
+        rustdoc_lines: usize,
+        code_lines: usize,
+    ) -> FileStats {
+        let stats = make_line_stats(
+            all_lines,
+            blank_lines,
+            comment_lines,
+            rustdoc_lines,
+            code_lines,
+        );
+        FileStats {
+            path: path.to_string(),
+            total: stats.clone(),
+            production: stats,
+            test: LineStats::default(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a `FileStats` instance with separate production and test stats.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path
+    /// * `prod_stats` - Production code statistics
+    /// * `test_stats` - Test code statistics
+    fn make_file_stats_with_tests(
+        path: &str,
+        prod_stats: LineStats,
+        test_stats: LineStats,
+    ) -> FileStats {
+        let mut total = prod_stats.clone();
+        total.add(&test_stats);
+
+        FileStats {
+            path: path.to_string(),
+            total,
+            production: prod_stats,
+            test: test_stats,
+            ..Default::default()
+        }
+    }
+
+    /// Creates a standard test `FileStats` for basic testing scenarios.
+    ///
+    /// Contains 10 total lines: 7 production (4 code) and 3 test (1 code).
+    fn make_standard_test_file_stats() -> FileStats {
+        make_file_stats_with_tests(
+            "test.rs",
+            make_line_stats(7, 1, 2, 0, 4),
+            make_line_stats(3, 1, 1, 0, 1),
+        )
+    }
+
+    /// Creates a minimal test `FileStats` for simple scenarios.
+    ///
+    /// Contains 5 lines of production code only.
+    fn make_minimal_test_file_stats() -> FileStats {
+        make_simple_file_stats("test.rs", 5, 1, 1, 0, 3)
+    }
+
+    /// Creates a detailed test `FileStats` for complex testing scenarios.
+    ///
+    /// Contains 15 total lines: 10 production (5 code) and 5 test (3 code).
+    fn make_detailed_test_file_stats() -> FileStats {
+        make_file_stats_with_tests(
+            "test.rs",
+            make_line_stats(10, 2, 3, 0, 5),
+            make_line_stats(5, 1, 1, 0, 3),
+        )
+    }
+
+    // ==================== Tests ====================
+
+    /// Tests that `LineStats::default()` creates a zero-initialized instance.
+    #[test]
+    fn test_line_stats_default() {
+        let stats = LineStats::default();
+        assert_eq!(stats.all_lines, 0);
+        assert_eq!(stats.blank_lines, 0);
+        assert_eq!(stats.comment_lines, 0);
+        assert_eq!(stats.rustdoc_lines, 0);
+        assert_eq!(stats.code_lines, 0);
+    }
+
+    /// Tests that `LineStats::add()` correctly accumulates statistics.
+    #[test]
+    fn test_line_stats_add() {
+        let mut stats1 = make_line_stats(10, 2, 3, 0, 5);
+        let stats2 = make_line_stats(20, 4, 6, 0, 10);
+        stats1.add(&stats2);
+        assert_eq!(stats1.all_lines, 30);
+        assert_eq!(stats1.blank_lines, 6);
+        assert_eq!(stats1.comment_lines, 9);
+        assert_eq!(stats1.rustdoc_lines, 0);
+        assert_eq!(stats1.code_lines, 15);
+    }
+
+    /// Tests that blank lines (empty or whitespace-only) are correctly identified.
+    #[test]
+    fn test_analyze_lines_blank() {
+        let content = "\n\n  \n\t\n";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 4);
+        assert!(line_types.iter().all(|&t| t == LineType::Blank));
+    }
+
+    /// Tests that line comments (`//`) and doc comments (`///`) are correctly identified.
+    #[test]
+    fn test_analyze_lines_line_comments() {
+        let content = "// comment 1\n// comment 2\n/// doc comment";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 3);
+        assert_eq!(line_types[0], LineType::Comment);
+        assert_eq!(line_types[1], LineType::Comment);
+        assert_eq!(line_types[2], LineType::Rustdoc);
+    }
+
+    /// Tests that multiline block comments (`/* ... */`) are correctly identified.
+    #[test]
+    fn test_analyze_lines_block_comment() {
+        let content = "/* start\nmiddle\nend */";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 3);
+        assert!(line_types.iter().all(|&t| t == LineType::Comment));
+    }
+
+    /// Tests that code lines are correctly identified.
+    #[test]
+    fn test_analyze_lines_code() {
+        let content = "fn main() {\n    println!(\"hello\");\n}";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 3);
+        assert!(line_types.iter().all(|&t| t == LineType::Code));
+    }
+
+    /// Tests classification of mixed content (comments, blanks, and code).
+    #[test]
+    fn test_analyze_lines_mixed() {
+        let content = "// comment\n\nfn main() {}";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 3);
+        assert_eq!(line_types[0], LineType::Comment);
+        assert_eq!(line_types[1], LineType::Blank);
+        assert_eq!(line_types[2], LineType::Code);
+    }
+
+    /// Tests that line statistics are correctly computed from line type classifications.
+    #[test]
+    fn test_compute_line_stats() {
+        let line_types = vec![
+            LineType::Comment,
+            LineType::Blank,
+            LineType::Code,
+            LineType::Code,
+            LineType::Blank,
+        ];
+        let stats = compute_line_stats(&line_types, 5);
+        assert_eq!(stats.all_lines, 5);
+        assert_eq!(stats.blank_lines, 2);
+        assert_eq!(stats.comment_lines, 1);
+        assert_eq!(stats.code_lines, 2);
+    }
+
+    /// Tests that production code without tests is classified as non-test.
+    #[test]
+    fn test_classify_lines_no_tests() {
+        let content = "fn main() {\n    println!(\"hello\");\n}";
+        let is_test = classify_lines(content, Edition::CURRENT);
+        assert_eq!(is_test.len(), 3);
+        assert!(is_test.iter().all(|&x| !x));
+    }
+
+    /// Tests that functions marked with `#[test]` are correctly identified as test code.
+    #[test]
+    fn test_classify_lines_with_test_function() {
+        let content = r#"
+fn production() {}
+
+#[test]
+fn test_something() {
+    assert!(true);
+}
+"#;
+        let is_test = classify_lines(content, Edition::CURRENT);
+        // Lines: "", "fn production() {}", "", "#[test]", "fn test_something() {", "    assert!(true);", "}"
+        assert!(!is_test.is_empty());
+        // The test function lines should be marked as test
+        assert!(is_test.iter().any(|&x| x));
+    }
+
+    /// Tests that modules marked with `#[cfg(test)]` are correctly identified as test code.
+    #[test]
+    fn test_classify_lines_with_test_module() {
+        let content = r#"
+fn production() {}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_it() {}
+}
+"#;
+        let is_test = classify_lines(content, Edition::CURRENT);
+        assert!(!is_test.is_empty());
+        // The module and its contents should be marked as test
+        assert!(is_test.iter().any(|&x| x));
+    }
+
+    /// Tests that `Summary::add_file()` correctly aggregates file statistics.
+    #[test]
+    fn test_summary_add_file() {
+        let mut summary = Summary::default();
+        let file_stats = make_standard_test_file_stats();
+        summary.add_file(&file_stats);
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.total.all_lines, 10);
+        assert_eq!(summary.production.all_lines, 7);
+        assert_eq!(summary.test.all_lines, 3);
+    }
+
+    /// Tests that line statistics are correctly formatted for text output.
+    #[test]
+    fn test_format_line_stats() {
+        let stats = make_line_stats(100, 20, 30, 0, 50);
+        let formatted = format_line_stats(&stats, 2);
+        assert!(formatted.contains("All lines: 100"));
+        assert!(formatted.contains("Blank lines: 20"));
+        assert!(formatted.contains("Comment lines: 30"));
+        assert!(formatted.contains("Code lines: 50"));
+    }
+
+    /// Tests that empty files (with no content) are handled correctly.
+    #[test]
+    fn test_empty_file_analysis() {
+        let content = "";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 0);
+    }
+
+    /// Tests that multiline block comments spanning multiple lines are correctly handled.
+    #[test]
+    fn test_analyze_lines_multiline_block_comment() {
+        let content = "code line\n/* comment start\ncomment middle\ncomment end */\nmore code";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 5);
+        assert_eq!(line_types[0], LineType::Code);
+        assert_eq!(line_types[1], LineType::Comment);
+        assert_eq!(line_types[2], LineType::Comment);
+        assert_eq!(line_types[3], LineType::Comment);
+        assert_eq!(line_types[4], LineType::Code);
+    }
+
+    /// Tests parsing file size with no unit (bytes).
+    #[test]
+    fn test_parse_file_size_bytes() {
+        assert_eq!(parse_file_size("1000").unwrap(), 1000);
+        assert_eq!(parse_file_size("500").unwrap(), 500);
+        assert_eq!(parse_file_size("1").unwrap(), 1);
+    }
+
+    /// Tests parsing file size with KB unit.
+    #[test]
+    fn test_parse_file_size_kb() {
+        assert_eq!(parse_file_size("1KB").unwrap(), 1024);
+        assert_eq!(parse_file_size("1kb").unwrap(), 1024);
+        assert_eq!(parse_file_size("3.5KB").unwrap(), 3584);
+        assert_eq!(parse_file_size("10KB").unwrap(), 10240);
+    }
+
+    /// Tests parsing file size with MB unit.
+    #[test]
+    fn test_parse_file_size_mb() {
+        assert_eq!(parse_file_size("1MB").unwrap(), 1048576);
+        assert_eq!(parse_file_size("1mb").unwrap(), 1048576);
+        assert_eq!(parse_file_size("2.5MB").unwrap(), 2621440);
+    }
+
+    /// Tests parsing file size with GB unit.
+    #[test]
+    fn test_parse_file_size_gb() {
+        assert_eq!(parse_file_size("1GB").unwrap(), 1073741824);
+        assert_eq!(parse_file_size("1gb").unwrap(), 1073741824);
+        assert_eq!(parse_file_size("1.1GB").unwrap(), 1181116006);
+    }
+
+    /// Tests parsing file size with whitespace.
+    #[test]
+    fn test_parse_file_size_with_whitespace() {
+        assert_eq!(parse_file_size("  1000  ").unwrap(), 1000);
+        assert_eq!(parse_file_size("  3.5KB  ").unwrap(), 3584);
+    }
+
+    /// Tests parsing invalid file size returns error.
+    #[test]
+    fn test_parse_file_size_invalid() {
+        assert!(parse_file_size("invalid").is_err());
+        assert!(parse_file_size("").is_err());
+        assert!(parse_file_size("KB").is_err());
+        assert!(parse_file_size("1TB").is_err()); // Unsupported unit
+    }
+
+    /// Tests parsing negative file size returns error.
+    #[test]
+    fn test_parse_file_size_negative() {
+        assert!(parse_file_size("-100").is_err());
+        assert!(parse_file_size("-1KB").is_err());
+    }
+
+    /// Tests Args::parse_max_file_size with valid input.
+    #[test]
+    fn test_args_parse_max_file_size_some() {
+        let args = Args {
+            file: None,
+            dir: None,
+            out_text: None,
+            out_json: None,
+            debug: false,
+            no_color: false,
+            verbose: false,
+            max_file_size: Some("10MB".to_string()),
+            ..Default::default()
+        };
+        let result = args.parse_max_file_size().unwrap();
+        assert_eq!(result, Some(10 * 1024 * 1024));
+    }
+
+    /// Tests Args::parse_max_file_size with None.
+    #[test]
+    fn test_args_parse_max_file_size_none() {
+        let args = Args {
+            file: None,
+            dir: None,
+            out_text: None,
+            out_json: None,
+            debug: false,
+            no_color: false,
+            verbose: false,
+            max_file_size: None,
+            ..Default::default()
+        };
+        let result = args.parse_max_file_size().unwrap();
+        assert_eq!(result, None);
+    }
+
+    /// Tests Args::output_format returns Json when flag is set.
+    #[test]
+    fn test_args_output_format_json() {
+        let args = Args {
+            file: None,
+            dir: None,
+            out_text: None,
+            out_json: Some(None),
+            debug: false,
+            no_color: false,
+            verbose: false,
+            max_file_size: None,
+            ..Default::default()
+        };
+        assert_eq!(args.output_format(), OutputFormat::Json);
+    }
+
+    /// Tests Args::output_format returns Text by default.
+    #[test]
+    fn test_args_output_format_text() {
+        let args = Args {
+            file: None,
+            dir: None,
+            out_text: None,
+            out_json: None,
+            debug: false,
+            no_color: false,
+            verbose: false,
+            max_file_size: None,
+            ..Default::default()
+        };
+        assert_eq!(args.output_format(), OutputFormat::Text);
+    }
+
+    /// Tests analyze_file with a real Rust file.
+    #[test]
+    fn test_analyze_file_integration() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_ruloc.rs");
+
+        let test_code = r#"
+// Production code
+fn hello() {
+    println!("hello");
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_hello() {
+        assert!(true);
+    }
+}
+"#;
+
+        std::fs::write(&temp_file, test_code).unwrap();
+
+        let result = analyze_file(&temp_file, &AnalysisOptions::default());
+        assert!(result.is_ok());
+
+        let stats = result.unwrap();
+        assert!(stats.total.all_lines > 0);
+        assert!(stats.production.code_lines > 0);
+        assert!(stats.test.code_lines > 0);
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests analyze_file respects max_file_size limit.
+    #[test]
+    fn test_analyze_file_size_limit() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_ruloc_large.rs");
+
+        let test_code = "// A large file\n".repeat(100);
+        std::fs::write(&temp_file, &test_code).unwrap();
+
+        // File is ~1600 bytes, set limit to 100 bytes
+        let result = analyze_file(
+            &temp_file,
+            &AnalysisOptions {
+                max_file_size: Some(100),
+                ..Default::default()
+            },
+        );
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("exceeds maximum size")
+        );
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests output_text formatting with accumulator.
+    #[test]
+    fn test_output_text() {
+        let mut acc = InMemoryAccumulator::new();
+        let stats = make_standard_test_file_stats();
+        acc.add_file(&stats).unwrap();
+
+        let result = output_to_stdout(ReportKind::Text, &acc);
+        assert!(result.is_ok());
+    }
+
+    /// Tests output_json formatting with accumulator.
+    #[test]
+    fn test_output_json() {
+        let mut acc = InMemoryAccumulator::new();
+        let stats = make_standard_test_file_stats();
+        acc.add_file(&stats).unwrap();
+
+        let result = output_to_stdout(ReportKind::Json, &acc);
+        assert!(result.is_ok());
+    }
+
+    /// Tests analyze_directory with a temporary directory structure.
+    #[test]
+    fn test_analyze_directory_integration() {
+        use std::fs;
+
+        let temp_dir = std::env::temp_dir().join("test_ruloc_dir");
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create test files
+        let file1 = temp_dir.join("file1.rs");
+        fs::write(&file1, "fn main() {}\n").unwrap();
+
+        let file2 = temp_dir.join("file2.rs");
+        fs::write(&file2, "#[test]\nfn test() {}\n").unwrap();
+
+        let mut accumulator = InMemoryAccumulator::new();
+        let result = analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut accumulator);
+        assert!(result.is_ok());
+
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.files, 2);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests analyze_directory with max_file_size filtering.
+    #[test]
+    fn test_analyze_directory_with_size_filter() {
+        use std::fs;
+
+        let temp_dir = std::env::temp_dir().join("test_ruloc_dir_filter");
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create a small file
+        let small_file = temp_dir.join("small.rs");
+        fs::write(&small_file, "fn f() {}\n").unwrap();
+
+        // Create a large file
+        let large_file = temp_dir.join("large.rs");
+        fs::write(&large_file, "// Large\n".repeat(100)).unwrap();
+
+        // Set size limit to 100 bytes - should skip the large file
+        let mut accumulator = InMemoryAccumulator::new();
+        let result = analyze_directory(
+            &temp_dir,
+            &AnalysisOptions {
+                max_file_size: Some(100),
+                ..Default::default()
+            },
+            &mut accumulator,
+        );
+        assert!(result.is_ok());
+
+        let summary = accumulator.get_summary();
+        // Only the small file should be analyzed
+        assert!(summary.files <= 1);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests analyze_file with invalid Rust code.
+    #[test]
+    fn test_analyze_file_invalid_rust() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_invalid.rs");
+
+        // This is syntactically invalid but should still count lines
+        let invalid_code = "fn broken( {}\nthis is not rust\n";
+        std::fs::write(&temp_file, invalid_code).unwrap();
+
+        let result = analyze_file(&temp_file, &AnalysisOptions::default());
+        // Should succeed even with invalid syntax, just counts lines
+        assert!(result.is_ok());
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests analyze_file with a file that has complex nested test modules.
+    #[test]
+    fn test_analyze_file_nested_tests() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_nested.rs");
+
+        let test_code = r#"
+fn prod() {}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(test)]
+    mod nested {
+        #[test]
+        fn inner_test() {}
+    }
+}
+"#;
+
+        std::fs::write(&temp_file, test_code).unwrap();
+
+        let result = analyze_file(&temp_file, &AnalysisOptions::default());
+        assert!(result.is_ok());
+
+        let stats = result.unwrap();
+        assert!(stats.test.code_lines > 0);
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests output_text with detailed file statistics.
+    #[test]
+    fn test_output_text_with_files() {
+        let mut acc = InMemoryAccumulator::new();
+        let file_stats = make_detailed_test_file_stats();
+        acc.add_file(&file_stats).unwrap();
+
+        let result = output_to_stdout(ReportKind::Text, &acc);
+        assert!(result.is_ok());
+    }
+
+    /// Tests analyze_file with an empty file.
+    #[test]
+    fn test_analyze_file_empty() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_empty.rs");
+
+        std::fs::write(&temp_file, "").unwrap();
+
+        let result = analyze_file(&temp_file, &AnalysisOptions::default());
+        assert!(result.is_ok());
+
+        let stats = result.unwrap();
+        assert_eq!(stats.total.all_lines, 0);
+        assert_eq!(stats.total.code_lines, 0);
+        assert_eq!(stats.production.all_lines, 0);
+        assert_eq!(stats.test.all_lines, 0);
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests analyze_directory with a directory containing no Rust files.
+    #[test]
+    fn test_analyze_directory_no_rust_files() {
+        use std::fs;
+
+        let temp_dir = std::env::temp_dir().join("test_ruloc_no_rs");
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create non-.rs files
+        let txt_file = temp_dir.join("readme.txt");
+        fs::write(&txt_file, "Not a Rust file").unwrap();
+
+        let mut accumulator = InMemoryAccumulator::new();
+        let result = analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut accumulator);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("No Rust files found")
+        );
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests analyze_directory where all files are too large.
+    #[test]
+    fn test_analyze_directory_all_files_too_large() {
+        use std::fs;
+
+        let temp_dir = std::env::temp_dir().join("test_ruloc_all_large");
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create large files that exceed size limit
+        let large_file1 = temp_dir.join("large1.rs");
+        fs::write(&large_file1, "// Large\n".repeat(100)).unwrap();
+
+        let large_file2 = temp_dir.join("large2.rs");
+        fs::write(&large_file2, "// Large\n".repeat(100)).unwrap();
+
+        // Set size limit to 50 bytes - all files will be skipped
+        let mut accumulator = InMemoryAccumulator::new();
+        let result = analyze_directory(
+            &temp_dir,
+            &AnalysisOptions {
+                max_file_size: Some(50),
+                ..Default::default()
+            },
+            &mut accumulator,
+        );
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("No Rust files could be analyzed")
+        );
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests analyze_file with a module using standalone #[cfg(test)] attribute.
+    #[test]
+    fn test_analyze_file_cfg_test_module() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_cfg_module.rs");
+
+        let test_code = r#"
+fn production_code() {}
+
+#[cfg(test)]
+mod test_module {
+    #[test]
+    fn test_helper() {}
+}
+"#;
+
+        std::fs::write(&temp_file, test_code).unwrap();
+
+        let result = analyze_file(&temp_file, &AnalysisOptions::default());
+        assert!(result.is_ok());
+
+        let stats = result.unwrap();
+        assert!(stats.total.all_lines > 0);
+        assert!(stats.production.code_lines > 0);
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests analyze_file with a very large file that exceeds size limit.
+    #[test]
+    fn test_analyze_file_exceeds_size_with_debug() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_large_debug.rs");
+
+        // Create a file larger than 500 bytes
+        let large_content = "// This is a large file\n".repeat(50);
+        std::fs::write(&temp_file, &large_content).unwrap();
+
+        // Set limit to 500 bytes - file should be rejected
+        let result = analyze_file(
+            &temp_file,
+            &AnalysisOptions {
+                max_file_size: Some(500),
+                ..Default::default()
+            },
+        );
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("exceeds maximum size")
+        );
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests parse_file_size with various edge cases for error handling.
+    #[test]
+    fn test_parse_file_size_edge_cases() {
+        // Test with decimal values
+        assert_eq!(parse_file_size("1.5KB").unwrap(), 1536);
+        assert_eq!(parse_file_size("0.5MB").unwrap(), 524288);
+
+        // Test case insensitivity
+        assert_eq!(parse_file_size("1kb").unwrap(), 1024);
+        assert_eq!(parse_file_size("1Kb").unwrap(), 1024);
+    }
+
+    /// Tests analyze_file with line at the boundary of file size limit.
+    #[test]
+    fn test_analyze_file_at_size_boundary() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_boundary.rs");
+
+        // Create a file exactly at the boundary
+        let content = "x".repeat(1000);
+        std::fs::write(&temp_file, &content).unwrap();
+
+        // Test with size exactly at the limit - should pass
+        let result = analyze_file(
+            &temp_file,
+            &AnalysisOptions {
+                max_file_size: Some(1000),
+                ..Default::default()
+            },
+        );
+        assert!(result.is_ok());
+
+        // Test with size one byte under - should fail
+        let result = analyze_file(
+            &temp_file,
+            &AnalysisOptions {
+                max_file_size: Some(999),
+                ..Default::default()
+            },
+        );
+        assert!(result.is_err());
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests file with only whitespace lines after empty check.
+    #[test]
+    fn test_analyze_file_only_whitespace() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_whitespace.rs");
+
+        let content = "   \n\t\n  \t  \n";
+        std::fs::write(&temp_file, content).unwrap();
+
+        let result = analyze_file(&temp_file, &AnalysisOptions::default());
+        assert!(result.is_ok());
+
+        let stats = result.unwrap();
+        assert_eq!(stats.total.blank_lines, 3);
+        assert_eq!(stats.total.code_lines, 0);
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests classify_lines with a mix of production and test code.
+    #[test]
+    fn test_classify_lines_mixed() {
+        let code = r#"
+fn production() {}
+
+#[cfg(test)]
+mod tests {
+    fn helper() {}
+
+    #[test]
+    fn test_fn() {}
+}
+"#;
+        let result = classify_lines(code, Edition::CURRENT);
+
+        // Should identify test lines correctly
+        assert!(result.iter().any(|&is_test| is_test));
+        assert!(result.iter().any(|&is_test| !is_test));
+    }
+
+    /// Tests analyze_directory with mixed valid and invalid files.
+    #[test]
+    fn test_analyze_directory_mixed_files() {
+        use std::fs;
+
+        let temp_dir = std::env::temp_dir().join("test_ruloc_mixed");
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create valid files
+        let good1 = temp_dir.join("good1.rs");
+        fs::write(&good1, "fn a() {}").unwrap();
+
+        let good2 = temp_dir.join("good2.rs");
+        fs::write(&good2, "fn b() {}\n#[test]\nfn t() {}").unwrap();
+
+        // Create a subdirectory with more files
+        let subdir = temp_dir.join("subdir");
+        fs::create_dir_all(&subdir).unwrap();
+        let sub_file = subdir.join("sub.rs");
+        fs::write(&sub_file, "fn sub() {}").unwrap();
+
+        let mut accumulator = InMemoryAccumulator::new();
+        let result = analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut accumulator);
+        assert!(result.is_ok());
+
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.files, 3); // Should find all 3 .rs files
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests Args structure with parse_max_file_size error handling.
+    #[test]
+    fn test_args_parse_max_file_size_error() {
+        let args = Args {
+            file: None,
+            dir: None,
+            out_text: None,
+            out_json: None,
+            debug: false,
+            no_color: false,
+            verbose: false,
+            max_file_size: Some("invalid".to_string()),
+            ..Default::default()
+        };
+        let result = args.parse_max_file_size();
+        assert!(result.is_err());
+    }
+
+    /// Tests InMemoryAccumulator basic operations.
+    #[test]
+    fn test_in_memory_accumulator() {
+        let mut acc = InMemoryAccumulator::new();
+
+        let stats1 = make_file_stats_with_tests(
+            "test1.rs",
+            make_line_stats(7, 1, 2, 0, 4),
+            make_line_stats(3, 1, 1, 0, 1),
+        );
+        let stats2 = make_simple_file_stats("test2.rs", 5, 1, 1, 0, 3);
+
+        acc.add_file(&stats1).unwrap();
+        acc.add_file(&stats2).unwrap();
+
+        let summary = acc.get_summary();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.total.all_lines, 15);
+        assert_eq!(summary.production.code_lines, 7);
+        assert_eq!(summary.test.code_lines, 1);
+
+        let files: Vec<_> = acc.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "test1.rs");
+        assert_eq!(files[1].path, "test2.rs");
+    }
+
+    /// Tests FileBackedAccumulator basic operations.
+    #[test]
+    fn test_file_backed_accumulator() {
+        let mut acc = FileBackedAccumulator::new().unwrap();
+
+        let stats1 = make_file_stats_with_tests(
+            "test1.rs",
+            make_line_stats(7, 1, 2, 0, 4),
+            make_line_stats(3, 1, 1, 0, 1),
+        );
+        let stats2 = make_simple_file_stats("test2.rs", 5, 1, 1, 0, 3);
+
+        acc.add_file(&stats1).unwrap();
+        acc.add_file(&stats2).unwrap();
+        acc.flush().unwrap();
+
+        let summary = acc.get_summary();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.total.all_lines, 15);
+        assert_eq!(summary.production.code_lines, 7);
+        assert_eq!(summary.test.code_lines, 1);
+
+        let files: Vec<_> = acc.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "test1.rs");
+        assert_eq!(files[1].path, "test2.rs");
+    }
+
+    /// Tests FileBackedAccumulator with large number of files.
+    #[test]
+    fn test_file_backed_accumulator_many_files() {
+        let mut acc = FileBackedAccumulator::new().unwrap();
+
+        // Add 1000 files to test buffering
+        for i in 0..1000 {
+            let stats = make_simple_file_stats(&format!("test{}.rs", i), 10, 2, 3, 0, 5);
+            acc.add_file(&stats).unwrap();
+        }
+
+        acc.flush().unwrap();
+
+        let summary = acc.get_summary();
+        assert_eq!(summary.files, 1000);
+        assert_eq!(summary.total.all_lines, 10000);
+
+        let files: Vec<_> = acc.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 1000);
+    }
+
+    /// Tests the detailed text report with InMemoryAccumulator.
+    #[test]
+    fn test_output_text_from_accumulator() {
+        let mut acc = InMemoryAccumulator::new();
+        let stats = make_standard_test_file_stats();
+        acc.add_file(&stats).unwrap();
+
+        // Just ensure it doesn't panic
+        let result = output_to_stdout(ReportKind::Text, &acc);
+        assert!(result.is_ok());
+    }
+
+    /// Tests the JSON report with InMemoryAccumulator.
+    #[test]
+    fn test_output_json_from_accumulator() {
+        let mut acc = InMemoryAccumulator::new();
+        let stats = make_standard_test_file_stats();
+        acc.add_file(&stats).unwrap();
+
+        let result = output_to_stdout(ReportKind::Json, &acc);
+        assert!(result.is_ok());
+    }
+
+    /// Tests that FileBackedAccumulator properly handles file I/O errors.
+    #[test]
+    fn test_file_backed_accumulator_iteration() {
+        let mut acc = FileBackedAccumulator::new().unwrap();
+        let stats = make_minimal_test_file_stats();
+        acc.add_file(&stats).unwrap();
+        acc.flush().unwrap();
+
+        // Test multiple iterations
+        let files1: Vec<_> = acc.iter_files().unwrap().collect();
+        let files2: Vec<_> = acc.iter_files().unwrap().collect();
+
+        assert_eq!(files1.len(), 1);
+        assert_eq!(files2.len(), 1);
+        assert_eq!(files1[0].path, files2[0].path);
+    }
+
+    /// Tests InMemoryAccumulator with empty data.
+    #[test]
+    fn test_in_memory_accumulator_empty() {
+        let acc = InMemoryAccumulator::new();
+
+        let summary = acc.get_summary();
+        assert_eq!(summary.files, 0);
+        assert_eq!(summary.total.all_lines, 0);
+
+        let files: Vec<_> = acc.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 0);
+    }
+
+    /// Tests FileBackedAccumulator with empty data.
+    #[test]
+    fn test_file_backed_accumulator_empty() {
+        let acc = FileBackedAccumulator::new().unwrap();
+
+        let summary = acc.get_summary();
+        assert_eq!(summary.files, 0);
+        assert_eq!(summary.total.all_lines, 0);
+
+        let files: Vec<_> = acc.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 0);
+    }
+
+    /// Tests FileBackedAccumulator flush method.
+    #[test]
+    fn test_file_backed_accumulator_flush() {
+        let mut acc = FileBackedAccumulator::new().unwrap();
+        let stats = make_minimal_test_file_stats();
+        acc.add_file(&stats).unwrap();
+
+        // Multiple flushes should succeed
+        assert!(acc.flush().is_ok());
+        assert!(acc.flush().is_ok());
+
+        let files: Vec<_> = acc.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 1);
+    }
+
+    /// Tests output functions with FileBackedAccumulator.
+    #[test]
+    fn test_output_functions_with_file_backed_accumulator() {
+        let mut acc = FileBackedAccumulator::new().unwrap();
+        let stats = make_standard_test_file_stats();
+        acc.add_file(&stats).unwrap();
+        acc.flush().unwrap();
+
+        // Test text output
+        let result = output_to_stdout(ReportKind::Text, &acc);
+        assert!(result.is_ok());
+
+        // Test JSON output
+        let result = output_to_stdout(ReportKind::Json, &acc);
+        assert!(result.is_ok());
+    }
+
+    /// Tests analyze_directory error handling with accumulator errors.
+    #[test]
+    fn test_analyze_directory_with_file_backed_accumulator() {
+        use std::fs;
+
+        let temp_dir = std::env::temp_dir().join("test_ruloc_file_backed");
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create test files
+        let file1 = temp_dir.join("file1.rs");
+        fs::write(&file1, "fn main() {}\n").unwrap();
+
+        let file2 = temp_dir.join("file2.rs");
+        fs::write(&file2, "#[test]\nfn test() {}\n").unwrap();
+
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+        let result = analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut accumulator);
+        assert!(result.is_ok());
+
+        accumulator.flush().unwrap();
+
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.files, 2);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that analyze_file handles nonexistent files correctly.
+    #[test]
+    fn test_analyze_file_nonexistent() {
+        let result = analyze_file(
+            std::path::Path::new("/nonexistent/file.rs"),
+            &AnalysisOptions::default(),
+        );
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("Failed to"));
+    }
+
+    /// Tests Summary default initialization.
+    #[test]
+    fn test_summary_default() {
+        let summary = Summary::default();
+        assert_eq!(summary.files, 0);
+        assert_eq!(summary.total.all_lines, 0);
+        assert_eq!(summary.production.all_lines, 0);
+        assert_eq!(summary.test.all_lines, 0);
+    }
+
+    /// Tests that line stats accurately track all components.
+    #[test]
+    fn test_line_stats_comprehensive() {
+        let mut stats = make_line_stats(100, 20, 30, 0, 50);
+        let other = make_line_stats(50, 10, 15, 0, 25);
+
+        stats.add(&other);
+
+        assert_eq!(stats.all_lines, 150);
+        assert_eq!(stats.blank_lines, 30);
+        assert_eq!(stats.comment_lines, 45);
+        assert_eq!(stats.rustdoc_lines, 0);
+        assert_eq!(stats.code_lines, 75);
+    }
+
+    /// Tests InMemoryAccumulator iterator returns owned data.
+    #[test]
+    fn test_in_memory_accumulator_iterator_ownership() {
+        let mut acc = InMemoryAccumulator::new();
+        let stats = make_minimal_test_file_stats();
+        acc.add_file(&stats).unwrap();
+
+        // Consume iterator multiple times
+        let files1: Vec<_> = acc.iter_files().unwrap().collect();
+        let files2: Vec<_> = acc.iter_files().unwrap().collect();
+
+        assert_eq!(files1.len(), 1);
+        assert_eq!(files2.len(), 1);
+    }
+
+    /// Tests analyze_file with a file containing only comments.
+    #[test]
+    fn test_analyze_file_only_comments() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_only_comments.rs");
+
+        let content = "// Comment 1\n// Comment 2\n/* Block comment */\n";
+        std::fs::write(&temp_file, content).unwrap();
+
+        let result = analyze_file(&temp_file, &AnalysisOptions::default());
+        assert!(result.is_ok());
+
+        let stats = result.unwrap();
+        assert_eq!(stats.total.comment_lines, 3);
+        assert_eq!(stats.total.code_lines, 0);
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests analyze_lines with single-line block comment.
+    #[test]
+    fn test_analyze_lines_single_line_block_comment() {
+        let content = "/* single line block comment */\ncode();\n";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+
+        assert_eq!(line_types.len(), 2);
+        assert_eq!(line_types[0], LineType::Comment);
+        assert_eq!(line_types[1], LineType::Code);
+    }
+
+    /// Tests classify_lines with mixed production and test code.
+    #[test]
+    fn test_classify_lines_production_and_test_mixed() {
+        let code = r#"
+fn production() {}
+
+#[test]
+fn test_func() {
+    assert!(true);
+}
+
+fn more_production() {}
+"#;
+        let result = classify_lines(code, Edition::CURRENT);
+
+        // Should have both test and production lines
+        assert!(result.iter().any(|&is_test| is_test));
+        assert!(result.iter().any(|&is_test| !is_test));
+    }
+
+    /// Tests FileStats equality.
+    #[test]
+    fn test_file_stats_equality() {
+        let stats1 = make_standard_test_file_stats();
+        let stats2 = stats1.clone();
+
+        assert_eq!(stats1, stats2);
+    }
+
+    /// Tests Report equality.
+    #[test]
+    fn test_report_equality() {
+        let report1: Report = Report {
+            summary: Summary::default(),
+            files: vec![],
+            ..Default::default()
+        };
+
+        let report2 = report1.clone();
+
+        assert_eq!(report1, report2);
+    }
+
+    /// Tests Args output_format method with text flag.
+    #[test]
+    fn test_args_output_format_with_text_flag() {
+        let args = Args {
+            file: None,
+            dir: None,
+            out_text: Some(None),
+            out_json: None,
+            debug: false,
+            no_color: false,
+            verbose: false,
+            max_file_size: None,
+            ..Default::default()
+        };
+        assert_eq!(args.output_format(), OutputFormat::Text);
+    }
+
+    /// Tests analyze_file with metadata errors for size checking.
+    #[test]
+    fn test_analyze_file_metadata_error() {
+        // Try to analyze a file that doesn't exist with max_file_size set
+        let result = analyze_file(
+            std::path::Path::new("/nonexistent/path/file.rs"),
+            &AnalysisOptions {
+                max_file_size: Some(1000),
+                ..Default::default()
+            },
+        );
+        assert!(result.is_err());
+    }
+
+    /// Tests analyze_lines with empty content.
+    #[test]
+    fn test_analyze_lines_empty_content() {
+        let content = "";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 0);
+    }
+
+    /// Tests compute_line_stats with empty input.
+    #[test]
+    fn test_compute_line_stats_empty() {
+        let line_types: Vec<LineType> = vec![];
+        let stats = compute_line_stats(&line_types, 0);
+        assert_eq!(stats.all_lines, 0);
+        assert_eq!(stats.blank_lines, 0);
+        assert_eq!(stats.comment_lines, 0);
+        assert_eq!(stats.code_lines, 0);
+    }
+
+    /// Tests compute_line_stats with only blank lines.
+    #[test]
+    fn test_compute_line_stats_only_blanks() {
+        let line_types = vec![LineType::Blank, LineType::Blank, LineType::Blank];
+        let stats = compute_line_stats(&line_types, 3);
+        assert_eq!(stats.all_lines, 3);
+        assert_eq!(stats.blank_lines, 3);
+        assert_eq!(stats.comment_lines, 0);
+        assert_eq!(stats.code_lines, 0);
+    }
+
+    /// Tests is_test_node with non-test nodes.
+    #[test]
+    fn test_is_test_node_regular_function() {
+        let content = "fn regular_function() {}";
+        let parse = SourceFile::parse(content, Edition::CURRENT);
+        let root = parse.syntax_node();
+
+        // The root itself should not be a test node
+        assert!(!is_test_node(&root));
+    }
+
+    /// Tests analyze_file with file at exact size limit.
+    #[test]
+    fn test_analyze_file_exact_size_limit() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_exact_size.rs");
+
+        // Create a file with known size
+        let content = "fn test() {}"; // 12 bytes
+        std::fs::write(&temp_file, content).unwrap();
+
+        // Set limit to exact size - should succeed
+        let result = analyze_file(
+            &temp_file,
+            &AnalysisOptions {
+                max_file_size: Some(12),
+                ..Default::default()
+            },
+        );
+        assert!(result.is_ok());
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests FileBackedAccumulator serialization error handling.
+    #[test]
+    fn test_file_backed_accumulator_add_multiple() {
+        let mut acc = FileBackedAccumulator::new().unwrap();
+
+        // Add multiple files sequentially
+        for i in 0..10 {
+            let mut stats = make_minimal_test_file_stats();
+            stats.path = format!("test{}.rs", i);
+            assert!(acc.add_file(&stats).is_ok());
+        }
+
+        assert!(acc.flush().is_ok());
+
+        let summary = acc.get_summary();
+        assert_eq!(summary.files, 10);
+    }
+
+    /// Tests analyze_directory with progress tracking.
+    #[test]
+    fn test_analyze_directory_progress() {
+        use std::fs;
+
+        let temp_dir = std::env::temp_dir().join("test_ruloc_progress");
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create multiple files to test progress bar
+        for i in 0..5 {
+            let file = temp_dir.join(format!("file{}.rs", i));
+            fs::write(&file, "fn main() {}\n").unwrap();
+        }
+
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+        let result = analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut accumulator);
+        assert!(result.is_ok());
+
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.files, 5);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests analyze_file with production and test code.
+    #[test]
+    fn test_analyze_file_production_and_test() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_prod_test.rs");
+
+        let content = r#"
+fn production() {
+    println!("production");
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_it() {
+        assert!(true);
+    }
+}
+"#;
+        std::fs::write(&temp_file, content).unwrap();
+
+        let result = analyze_file(&temp_file, &AnalysisOptions::default());
+        assert!(result.is_ok());
+
+        let stats = result.unwrap();
+        assert!(stats.production.code_lines > 0);
+        assert!(stats.test.code_lines > 0);
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests LineStats serialization.
+    #[test]
+    fn test_line_stats_serialization() {
+        let stats = make_line_stats(100, 20, 30, 0, 50);
+        let json = serde_json::to_string(&stats).unwrap();
+        let deserialized: LineStats = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(stats, deserialized);
+    }
+
+    /// Tests FileStats serialization.
+    #[test]
+    fn test_file_stats_serialization() {
+        let stats = make_standard_test_file_stats();
+        let json = serde_json::to_string(&stats).unwrap();
+        let deserialized: FileStats = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(stats, deserialized);
+    }
+
+    /// Tests Summary serialization.
+    #[test]
+    fn test_summary_serialization() {
+        let summary = Summary {
+            files: 5,
+            total: make_line_stats(100, 20, 30, 0, 50),
+            production: make_line_stats(70, 10, 20, 0, 40),
+            test: make_line_stats(30, 10, 10, 0, 10),
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&summary).unwrap();
+        let deserialized: Summary = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(summary, deserialized);
+    }
+
+    /// Tests Report serialization.
+    #[test]
+    fn test_report_serialization() {
+        let report = Report {
+            summary: Summary::default(),
+            files: vec![],
+            ..Default::default()
+        };
+
+        let json = serde_json::to_string(&report).unwrap();
+        let deserialized: Report = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(report, deserialized);
+    }
+
+    /// Tests FileBackedAccumulator with corrupted data.
+    #[test]
+    fn test_file_backed_accumulator_corrupted_data() {
+        use std::io::Write;
+
+        let mut acc = FileBackedAccumulator::new().unwrap();
+
+        // Add valid data first
+        let stats = make_minimal_test_file_stats();
+        acc.add_file(&stats).unwrap();
+
+        // Write corrupted data directly to the writer
+        writeln!(acc.writer, "corrupted json data").unwrap();
+        acc.flush().unwrap();
+
+        // Should skip corrupted lines and only return valid ones
+        let files: Vec<_> = acc.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 1); // Only the valid entry
+    }
+
+    /// Tests parse_file_size with zero.
+    #[test]
+    fn test_parse_file_size_zero() {
+        assert_eq!(parse_file_size("0").unwrap(), 0);
+        assert_eq!(parse_file_size("0KB").unwrap(), 0);
+    }
+
+    /// Tests analyze_lines with code after block comment end.
+    #[test]
+    fn test_analyze_lines_code_after_block_comment() {
+        let content = "/* comment */ code();";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+
+        assert_eq!(line_types.len(), 1);
+        // The whole line is treated as a comment since it starts with /*
+        assert_eq!(line_types[0], LineType::Comment);
+    }
+
+    /// Tests classify_lines with nested test modules.
+    #[test]
+    fn test_classify_lines_nested_test_modules() {
+        let code = r#"
+fn production() {}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(test)]
+    mod nested {
+        #[test]
+        fn inner() {}
+    }
+}
+"#;
+        let result = classify_lines(code, Edition::CURRENT);
+
+        // Should have both test and production lines
+        assert!(result.iter().any(|&is_test| is_test));
+        assert!(result.iter().any(|&is_test| !is_test));
+    }
+
+    /// Tests find_test_sections directly.
+    #[test]
+    fn test_find_test_sections_function() {
+        let content = r#"
+fn production() {}
+
+#[test]
+fn test_one() {}
+
+#[test]
+fn test_two() {}
+"#;
+        let parse = SourceFile::parse(content, Edition::CURRENT);
+        let root = parse.syntax_node();
+
+        let mut sections = Vec::new();
+        find_test_sections(&root, &mut sections, content);
+
+        // Should find two test sections
+        assert_eq!(sections.len(), 2);
+    }
+
+    /// Tests compute_line_stats with only comments.
+    #[test]
+    fn test_compute_line_stats_only_comments() {
+        let line_types = vec![LineType::Comment, LineType::Comment, LineType::Comment];
+        let stats = compute_line_stats(&line_types, 3);
+        assert_eq!(stats.all_lines, 3);
+        assert_eq!(stats.blank_lines, 0);
+        assert_eq!(stats.comment_lines, 3);
+        assert_eq!(stats.code_lines, 0);
+    }
+
+    /// Tests compute_line_stats with only code.
+    #[test]
+    fn test_compute_line_stats_only_code() {
+        let line_types = vec![LineType::Code, LineType::Code, LineType::Code];
+        let stats = compute_line_stats(&line_types, 3);
+        assert_eq!(stats.all_lines, 3);
+        assert_eq!(stats.blank_lines, 0);
+        assert_eq!(stats.comment_lines, 0);
+        assert_eq!(stats.code_lines, 3);
+    }
+
+    /// Tests InMemoryAccumulator default behavior.
+    #[test]
+    fn test_in_memory_accumulator_new() {
+        let acc = InMemoryAccumulator::new();
+        let summary = acc.get_summary();
+
+        assert_eq!(summary.files, 0);
+        assert_eq!(summary.total.all_lines, 0);
+    }
+
+    /// Tests analyze_file with very large line count.
+    #[test]
+    fn test_analyze_file_large_file() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_large_file.rs");
+
+        // Create a file with many lines
+        let mut content = String::new();
+        for i in 0..1000 {
+            content.push_str(&format!("// Line {}\n", i));
+        }
+
+        std::fs::write(&temp_file, &content).unwrap();
+
+        let result = analyze_file(&temp_file, &AnalysisOptions::default());
+        assert!(result.is_ok());
+
+        let stats = result.unwrap();
+        assert_eq!(stats.total.all_lines, 1000);
+        assert_eq!(stats.total.comment_lines, 1000);
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests is_test_node with a test function.
+    #[test]
+    fn test_is_test_node_test_function() {
+        let content = "#[test]\nfn test_something() {}";
+        let parse = SourceFile::parse(content, Edition::CURRENT);
+        let root = parse.syntax_node();
+
+        // Find the function node
+        for child in root.descendants() {
+            if ast::Fn::cast(child.clone()).is_some() && is_test_node(&child) {
+                return; // Test passes
+            }
+        }
+
+        panic!("Should have found a test node");
+    }
+
+    /// Tests is_test_node returns false for normal code.
+    #[test]
+    fn test_is_test_node_normal_code() {
+        let content = "fn regular_function() {}";
+        let parse = SourceFile::parse(content, Edition::CURRENT);
+        let root = parse.syntax_node();
+
+        // Regular functions should not be test nodes
+        for child in root.descendants() {
+            if ast::Fn::cast(child.clone()).is_some() {
+                // This should return false for regular functions
+                let _ = is_test_node(&child);
+            }
+        }
+        // Test completes successfully
+    }
+
+    /// Tests find_test_sections with multiple test annotations.
+    #[test]
+    fn test_find_test_sections_multiple_annotations() {
+        let content = r#"
+fn production() {}
+
+#[test]
+fn test_alpha() {
+    assert!(true);
+}
+
+#[test]
+fn test_beta() {
+    assert!(false || true);
+}
+"#;
+        let parse = SourceFile::parse(content, Edition::CURRENT);
+        let root = parse.syntax_node();
+
+        let mut sections = Vec::new();
+        find_test_sections(&root, &mut sections, content);
+
+        // Should find both test functions
+        assert!(sections.len() >= 2);
+    }
+
+    /// Tests analyze_file with mixed blank, comment, and code lines.
+    #[test]
+    fn test_analyze_file_mixed_content() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_mixed_content.rs");
+
+        let content = r#"
+// Header comment
+
+fn production() {
+    // Inner comment
+    println!("hello");
+}
+
+#[test]
+fn test() {
+
+    assert!(true);
+}
+"#;
+        std::fs::write(&temp_file, content).unwrap();
+
+        let result = analyze_file(&temp_file, &AnalysisOptions::default());
+        assert!(result.is_ok());
+
+        let stats = result.unwrap();
+        assert!(stats.total.blank_lines > 0);
+        assert!(stats.total.comment_lines > 0);
+        assert!(stats.total.code_lines > 0);
+        assert!(stats.production.code_lines > 0);
+        assert!(stats.test.code_lines > 0);
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests CodeSection structure usage.
+    #[test]
+    fn test_code_section_creation() {
+        let section = CodeSection {
+            start_line: 10,
+            end_line: 20,
+        };
+
+        assert_eq!(section.start_line, 10);
+        assert_eq!(section.end_line, 20);
+    }
+
+    /// Tests analyze_lines with mixed block and line comments.
+    #[test]
+    fn test_analyze_lines_mixed_comments() {
+        let content = r#"// Line comment
+/* Block start
+Still in block
+Block end */
+// Another line comment
+code();"#;
+        let line_types = analyze_lines(content, Edition::CURRENT);
+
+        assert_eq!(line_types.len(), 6);
+        assert_eq!(line_types[0], LineType::Comment);
+        assert_eq!(line_types[1], LineType::Comment);
+        assert_eq!(line_types[2], LineType::Comment);
+        assert_eq!(line_types[3], LineType::Comment);
+        assert_eq!(line_types[4], LineType::Comment);
+        assert_eq!(line_types[5], LineType::Code);
+    }
+
+    /// Tests analyze_lines with tab characters.
+    #[test]
+    fn test_analyze_lines_with_tabs() {
+        let content = "\t\t// Indented comment\n\t\tfn code() {}\n";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+
+        assert_eq!(line_types.len(), 2);
+        assert_eq!(line_types[0], LineType::Comment);
+        assert_eq!(line_types[1], LineType::Code);
+    }
+
+    /// Tests that Summary accumulates correctly across multiple files.
+    #[test]
+    fn test_summary_accumulation() {
+        let mut summary = Summary::default();
+
+        for _ in 0..5 {
+            let stats = make_standard_test_file_stats();
+            summary.add_file(&stats);
+        }
+
+        assert_eq!(summary.files, 5);
+        assert_eq!(summary.total.all_lines, 50);
+        assert_eq!(summary.production.code_lines, 20);
+        assert_eq!(summary.test.code_lines, 5);
+    }
+
+    /// Tests FileBackedAccumulator without any flush calls.
+    #[test]
+    fn test_file_backed_accumulator_no_explicit_flush() {
+        let mut acc = FileBackedAccumulator::new().unwrap();
+        let stats = make_minimal_test_file_stats();
+        acc.add_file(&stats).unwrap();
+
+        // Try to read without explicit flush
+        let summary = acc.get_summary();
+        assert_eq!(summary.files, 1);
+    }
+
+    /// Tests LineType enum completeness.
+    #[test]
+    fn test_line_type_variants() {
+        let blank = LineType::Blank;
+        let comment = LineType::Comment;
+        let code = LineType::Code;
+
+        assert_eq!(blank, LineType::Blank);
+        assert_eq!(comment, LineType::Comment);
+        assert_eq!(code, LineType::Code);
+        assert_ne!(blank, comment);
+        assert_ne!(blank, code);
+        assert_ne!(comment, code);
+    }
+
+    /// Tests format_debug_line for all line type combinations.
+    #[test]
+    fn test_format_debug_line() {
+        // Test production code prefixes
+        let line = "fn main() {}";
+        assert!(
+            format_debug_line(line, LineType::Code, false, false)
+                .starts_with(&format!("{}  ", DEBUG_MARKER_PRODUCTION_CODE))
+        );
+        assert!(
+            format_debug_line(line, LineType::Comment, false, false)
+                .starts_with(&format!("{}  ", DEBUG_MARKER_PRODUCTION_COMMENT))
+        );
+        assert!(
+            format_debug_line(line, LineType::Rustdoc, false, false)
+                .starts_with(&format!("{}  ", DEBUG_MARKER_PRODUCTION_RUSTDOC))
+        );
+        assert!(
+            format_debug_line("", LineType::Blank, false, false)
+                .starts_with(&format!("{}  ", DEBUG_MARKER_PRODUCTION_BLANK))
+        );
+
+        // Test test code prefixes
+        assert!(
+            format_debug_line(line, LineType::Code, true, false)
+                .starts_with(&format!("{}  ", DEBUG_MARKER_TEST_CODE))
+        );
+        assert!(
+            format_debug_line(line, LineType::Comment, true, false)
+                .starts_with(&format!("{}  ", DEBUG_MARKER_TEST_COMMENT))
+        );
+        assert!(
+            format_debug_line(line, LineType::Rustdoc, true, false)
+                .starts_with(&format!("{}  ", DEBUG_MARKER_TEST_RUSTDOC))
+        );
+        assert!(
+            format_debug_line("", LineType::Blank, true, false)
+                .starts_with(&format!("{}  ", DEBUG_MARKER_TEST_BLANK))
+        );
+
+        // Verify line content is preserved
+        assert!(format_debug_line(line, LineType::Code, false, false).contains(line));
+
+        // Test with colors enabled
+        let colored_output = format_debug_line(line, LineType::Code, false, true);
+        assert!(colored_output.contains(line));
+    }
+
+    /// Tests InMemoryAccumulator::default() implementation.
+    #[test]
+    fn test_in_memory_accumulator_default() {
+        let acc = InMemoryAccumulator::default();
+        let summary = acc.get_summary();
+        assert_eq!(summary.files, 0);
+        assert_eq!(summary.total.all_lines, 0);
+    }
+
+    /// Tests classify_debug_file function with a test file.
+    #[test]
+    fn test_classify_debug_file() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_debug_output.rs");
+
+        let content = r#"/// Doc comment
+fn main() {
+    // Comment
+    println!("test");
+}
+
+#[test]
+fn test() {
+    assert!(true);
+}"#;
+
+        std::fs::write(&temp_file, content).unwrap();
+
+        let file = classify_debug_file(&temp_file, None, &EditionResolver::default()).unwrap();
+        assert_eq!(file.lines.len(), 10);
+        assert_eq!(file.lines[0].line_type, LineType::Rustdoc);
+        assert!(!file.lines[2].is_test);
+        assert!(file.lines[8].is_test);
+
+        // Test without and with colors
+        let mut out = Vec::new();
+        assert!(write_debug_file(&mut out, &file, &DebugOptions::default()).is_ok());
+        let colored = DebugOptions {
+            use_color: true,
+            ..Default::default()
+        };
+        assert!(write_debug_file(&mut out, &file, &colored).is_ok());
+
+        // Test with size limit that allows file
+        let result = classify_debug_file(&temp_file, Some(10000), &EditionResolver::default());
+        assert!(result.is_ok());
+
+        // Test with size limit that rejects file
+        let result = classify_debug_file(&temp_file, Some(10), &EditionResolver::default());
+        assert!(result.is_err());
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests that debug mode numbers lines and limits output to a line range.
+    #[test]
+    fn test_write_debug_file_line_range() {
+        let file = DebugFile {
+            path: "src/lib.rs".to_string(),
+            lines: (1..=12)
+                .map(|line| DebugLine {
+                    line,
+                    line_type: LineType::Code,
+                    is_test: false,
+                    ignored: false,
+                    text: format!("let x{} = 0;", line),
+                })
+                .collect(),
+        };
+        let options = DebugOptions {
+            line_numbers: true,
+            lines: Some(9..=10),
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write_debug_file(&mut out, &file, &options).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "src/lib.rs:\n 9  PCO  let x9 = 0;\n10  PCO  let x10 = 0;\n"
+        );
+    }
+
+    /// Tests that --debug-compare reports only changed, added, and removed lines.
+    #[test]
+    fn test_write_debug_mismatches() {
+        let line = |line, line_type, is_test| DebugLine {
+            line,
+            line_type,
+            is_test,
+            ignored: false,
+            text: format!("line {}", line),
+        };
+        let baseline = DebugFile {
+            path: "src/lib.rs".to_string(),
+            lines: vec![
+                line(1, LineType::Code, false),
+                line(2, LineType::Comment, false),
+                line(3, LineType::Code, false),
+            ],
+        };
+        let current = DebugFile {
+            path: "src/lib.rs".to_string(),
+            lines: vec![
+                line(1, LineType::Code, false),
+                line(2, LineType::Code, false),
+            ],
+        };
+
+        let mut out = Vec::new();
+        let mismatches = write_debug_mismatches(
+            &mut out,
+            &current,
+            Some(&baseline),
+            &DebugOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(mismatches, 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "src/lib.rs:\n2  PCM -> PCO  line 2\n3  PCO -> ---  \n"
+        );
+
+        let mut out = Vec::new();
+        let first_line = DebugOptions {
+            lines: Some(1..=1),
+            ..Default::default()
+        };
+        assert_eq!(
+            write_debug_mismatches(&mut out, &current, Some(&baseline), &first_line).unwrap(),
+            0
+        );
+        assert!(out.is_empty());
+
+        // Records written by --debug --out-json are read back as the baseline
+        let mut saved = NamedTempFile::new().unwrap();
+        let json = DebugOptions {
+            json: true,
+            ..Default::default()
+        };
+        write_debug_file(saved.as_file_mut(), &baseline, &json).unwrap();
+        let loaded = load_debug_baseline(saved.path()).unwrap();
+        assert_eq!(loaded["src/lib.rs"].lines.len(), 3);
+        assert_eq!(loaded["src/lib.rs"].lines[1].line_type, LineType::Comment);
+    }
+
+    /// Tests parsing of --lines ranges.
+    #[test]
+    fn test_parse_line_range() {
+        assert_eq!(parse_line_range("100-250").unwrap(), 100..=250);
+        assert_eq!(parse_line_range("42").unwrap(), 42..=42);
+        assert!(parse_line_range("250-100").is_err());
+        assert!(parse_line_range("0-10").is_err());
+        assert!(parse_line_range("10-").is_err());
+        assert!(parse_line_range("a-b").is_err());
+    }
+
+    /// Tests classify_debug_file with empty file.
+    #[test]
+    fn test_classify_debug_file_empty() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_debug_empty.rs");
+
+        std::fs::write(&temp_file, "").unwrap();
+
+        let file = classify_debug_file(&temp_file, None, &EditionResolver::default()).unwrap();
+        assert!(file.lines.is_empty());
+
+        let mut out = Vec::new();
+        write_debug_file(&mut out, &file, &DebugOptions::default()).unwrap();
+        assert!(out.is_empty());
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests classify_debug_file with nonexistent file.
+    #[test]
+    fn test_classify_debug_file_nonexistent() {
+        let path = std::path::Path::new("/nonexistent/file.rs");
+        let result = classify_debug_file(path, None, &EditionResolver::default());
+        assert!(result.is_err());
+    }
+
+    /// Tests that the JSON debug format writes one record per file.
+    #[test]
+    fn test_write_debug_file_json() {
+        let file = DebugFile {
+            path: "src/lib.rs".to_string(),
+            lines: vec![
+                DebugLine {
+                    line: 1,
+                    line_type: LineType::Code,
+                    is_test: false,
+                    ignored: false,
+                    text: "fn f() {}".to_string(),
+                },
+                DebugLine {
+                    line: 2,
+                    line_type: LineType::Blank,
+                    is_test: true,
+                    ignored: false,
+                    text: String::new(),
+                },
+            ],
+        };
+        let mut out = Vec::new();
+        let json = DebugOptions {
+            json: true,
+            use_color: true,
+            ..Default::default()
+        };
+        write_debug_file(&mut out, &file, &json).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                r#"{"path":"src/lib.rs","lines":["#,
+                r#"{"line":1,"type":"code","is-test":false},"#,
+                r#"{"line":2,"type":"blank","is-test":true}]}"#,
+                "\n"
+            )
+        );
+    }
+
+    /// Tests analyze_lines with rustdoc comments.
+    #[test]
+    fn test_analyze_lines_rustdoc() {
+        let content = "/// This is a rustdoc comment\n//! Module doc\n/** Block rustdoc */\n/*! Block module doc */";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 4);
+        assert_eq!(line_types[0], LineType::Rustdoc);
+        assert_eq!(line_types[1], LineType::Rustdoc);
+        assert_eq!(line_types[2], LineType::Rustdoc);
+        assert_eq!(line_types[3], LineType::Rustdoc);
+    }
+
+    /// Tests analyze_lines with mixed rustdoc and regular comments.
+    #[test]
+    fn test_analyze_lines_mixed_rustdoc_comments() {
+        let content = "/// Rustdoc\n// Regular\n//! Module doc\n/* Block */\n/** Block rustdoc */";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 5);
+        assert_eq!(line_types[0], LineType::Rustdoc);
+        assert_eq!(line_types[1], LineType::Comment);
+        assert_eq!(line_types[2], LineType::Rustdoc);
+        assert_eq!(line_types[3], LineType::Comment);
+        assert_eq!(line_types[4], LineType::Rustdoc);
+    }
+
+    /// Tests compute_line_stats with rustdoc lines.
+    #[test]
+    fn test_compute_line_stats_with_rustdoc() {
+        let line_types = vec![
+            LineType::Rustdoc,
+            LineType::Rustdoc,
+            LineType::Code,
+            LineType::Blank,
+        ];
+        let stats = compute_line_stats(&line_types, 4);
+        assert_eq!(stats.all_lines, 4);
+        assert_eq!(stats.rustdoc_lines, 2);
+        assert_eq!(stats.code_lines, 1);
+        assert_eq!(stats.blank_lines, 1);
+        assert_eq!(stats.comment_lines, 0);
+    }
+
+    /// Tests LineStats with rustdoc lines included.
+    #[test]
+    fn test_line_stats_with_rustdoc() {
+        let stats = make_line_stats(100, 20, 15, 10, 55);
+        assert_eq!(stats.all_lines, 100);
+        assert_eq!(stats.rustdoc_lines, 10);
+        // Verify sum equals total
+        let sum = stats.blank_lines + stats.comment_lines + stats.rustdoc_lines + stats.code_lines;
+        assert_eq!(sum, stats.all_lines);
+    }
+
+    /// Tests LineStats::add with rustdoc lines.
+    #[test]
+    fn test_line_stats_add_with_rustdoc() {
+        let mut stats1 = make_line_stats(50, 10, 10, 5, 25);
+        let stats2 = make_line_stats(50, 5, 5, 10, 30);
+        stats1.add(&stats2);
+        assert_eq!(stats1.all_lines, 100);
+        assert_eq!(stats1.rustdoc_lines, 15);
+        assert_eq!(stats1.comment_lines, 15);
+        assert_eq!(stats1.blank_lines, 15);
+        assert_eq!(stats1.code_lines, 55);
+    }
+
+    /// Tests format_line_stats with rustdoc.
+    #[test]
+    fn test_format_line_stats_with_rustdoc() {
+        let stats = make_line_stats(100, 20, 15, 12, 53);
+        let formatted = format_line_stats(&stats, 2);
+        assert!(formatted.contains("Rustdoc lines: 12"));
+    }
+
+    /// Tests Summary with rustdoc lines.
+    #[test]
+    fn test_summary_with_rustdoc() {
+        let mut summary = Summary::default();
+        let file_stats = make_file_stats_with_tests(
+            "test.rs",
+            make_line_stats(50, 5, 5, 8, 32),
+            make_line_stats(20, 2, 2, 4, 12),
+        );
+        summary.add_file(&file_stats);
+        assert_eq!(summary.total.rustdoc_lines, 12); // 8 + 4
+        assert_eq!(summary.production.rustdoc_lines, 8);
+        assert_eq!(summary.test.rustdoc_lines, 4);
+    }
+
+    /// Tests analyze_file with rustdoc comments.
+    #[test]
+    fn test_analyze_file_with_rustdoc() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_rustdoc.rs");
+
+        let content = r#"/// Module documentation
+//! Crate documentation
+/// Function documentation
+fn documented_function() {
+    // Regular comment
+}
+
+/** Block rustdoc */
+fn another_function() {}
+
+#[cfg(test)]
+mod tests {
+    /// Test function doc
+    #[test]
+    fn test_it() {}
+}"#;
+
+        std::fs::write(&temp_file, content).unwrap();
+
+        let result = analyze_file(&temp_file, &AnalysisOptions::default());
+        assert!(result.is_ok());
+
+        let stats = result.unwrap();
+        assert!(stats.total.rustdoc_lines > 0);
+        assert!(stats.production.rustdoc_lines > 0);
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests FileBackedAccumulator deserialization error handling.
+    #[test]
+    fn test_file_backed_accumulator_invalid_json() {
+        use std::io::Write;
+
+        let mut acc = FileBackedAccumulator::new().unwrap();
+
+        // Write some invalid JSON directly
+        writeln!(acc.writer, "{{invalid json}}").unwrap();
+        writeln!(acc.writer, "not even json").unwrap();
+
+        // Add valid data
+        let stats = make_minimal_test_file_stats();
+        acc.add_file(&stats).unwrap();
+        acc.flush().unwrap();
+
+        // Should only return the valid entry
+        let files: Vec<_> = acc.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 1);
+    }
+
+    /// Tests format_debug_line with rustdoc type.
+    #[test]
+    fn test_format_debug_line_rustdoc() {
+        let line = "/// Documentation";
+
+        // Test production rustdoc
+        let prod = format_debug_line(line, LineType::Rustdoc, false, false);
+        assert!(prod.starts_with(&format!("{}  ", DEBUG_MARKER_PRODUCTION_RUSTDOC)));
+        assert!(prod.contains(line));
+
+        // Test test rustdoc
+        let test = format_debug_line(line, LineType::Rustdoc, true, false);
+        assert!(test.starts_with(&format!("{}  ", DEBUG_MARKER_TEST_RUSTDOC)));
+        assert!(test.contains(line));
+
+        // Test with colors
+        let colored = format_debug_line(line, LineType::Rustdoc, false, true);
+        assert!(colored.contains(line));
+    }
+
+    /// Tests analyze_lines with multiline rustdoc block comment.
+    #[test]
+    fn test_analyze_lines_multiline_rustdoc_block() {
+        let content = "/** Start rustdoc\nContinued rustdoc\nEnd rustdoc */\ncode();";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 4);
+        assert_eq!(line_types[0], LineType::Rustdoc);
+        assert_eq!(line_types[1], LineType::Rustdoc);
+        assert_eq!(line_types[2], LineType::Rustdoc);
+        assert_eq!(line_types[3], LineType::Code);
+    }
+
+    /// Tests analyze_lines with module-level rustdoc.
+    #[test]
+    fn test_analyze_lines_module_rustdoc() {
+        let content = "//! Module level documentation\n//! Continued\n\nfn main() {}";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 4);
+        assert_eq!(line_types[0], LineType::Rustdoc);
+        assert_eq!(line_types[1], LineType::Rustdoc);
+        assert_eq!(line_types[2], LineType::Blank);
+        assert_eq!(line_types[3], LineType::Code);
+    }
+
+    /// Tests FileBackedAccumulator with IO error during flush.
+    #[test]
+    fn test_file_backed_accumulator_flush_error() {
+        // This test verifies error handling but actual IO error simulation is complex
+        let mut acc = FileBackedAccumulator::new().unwrap();
+
+        // Multiple flushes should work
+        for _ in 0..5 {
+            assert!(acc.flush().is_ok());
+        }
+    }
+
+    /// Tests InMemoryAccumulator with large number of files.
+    #[test]
+    fn test_in_memory_accumulator_many_files() {
+        let mut acc = InMemoryAccumulator::new();
+
+        // Add many files to test memory accumulation
+        for i in 0..100 {
+            let stats = make_simple_file_stats(&format!("file{}.rs", i), 10, 2, 2, 1, 5);
+            acc.add_file(&stats).unwrap();
+        }
+
+        let summary = acc.get_summary();
+        assert_eq!(summary.files, 100);
+        assert_eq!(summary.total.all_lines, 1000);
+        assert_eq!(summary.total.rustdoc_lines, 100);
+
+        let files: Vec<_> = acc.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 100);
+    }
+
+    /// Tests FileBackedAccumulator iter_files with corrupted line.
+    #[test]
+    fn test_file_backed_accumulator_iter_files_error_handling() {
+        use std::io::Write;
+
+        let mut acc = FileBackedAccumulator::new().unwrap();
+        let stats1 = make_minimal_test_file_stats();
+        acc.add_file(&stats1).unwrap();
+
+        // Write corrupted line
+        writeln!(acc.writer, "{{corrupted}}").unwrap();
+
+        let stats2 = make_simple_file_stats("test2.rs", 5, 1, 1, 0, 3);
+        acc.add_file(&stats2).unwrap();
+        acc.flush().unwrap();
+
+        // Should skip corrupted line
+        let files: Vec<_> = acc.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 2); // Two valid files
+    }
+
+    /// Tests is_test_node with module having cfg(test) attribute.
+    #[test]
+    fn test_is_test_node_with_cfg_test_module() {
+        let content = "#[cfg(test)]\nmod tests {}";
+        let parse = SourceFile::parse(content, Edition::CURRENT);
+        let root = parse.syntax_node();
+
+        let mut found_test_module = false;
+        for child in root.descendants() {
+            if let Some(module) = ast::Module::cast(child.clone()) {
+                // Check attributes directly for debugging
+                for attr in module.attrs() {
+                    if let Some(path) = attr.path() {
+                        let attr_text = path.to_string();
+                        // The attribute path might be just "cfg" not "cfg(test)"
+                        if attr_text == "cfg" || attr_text.contains("cfg") {
+                            found_test_module = true;
+                            break;
+                        }
+                    }
+                }
+                if found_test_module {
+                    break;
+                }
+            }
+        }
+
+        assert!(found_test_module);
+    }
+
+    /// Tests find_test_sections with nested test modules.
+    #[test]
+    fn test_find_test_sections_nested_modules() {
+        let content = r#"
+#[cfg(test)]
+mod tests {
+    mod inner {
+        #[test]
+        fn test() {}
+    }
+}"#;
+        let parse = SourceFile::parse(content, Edition::CURRENT);
+        let root = parse.syntax_node();
+
+        let mut sections = Vec::new();
+        find_test_sections(&root, &mut sections, content);
+
+        // Should find the test module
+        assert!(!sections.is_empty());
+    }
+
+    /// Tests analyze_lines edge cases with complex combinations.
+    #[test]
+    fn test_analyze_lines_edge_cases() {
+        // Empty string content
+        let content = "";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 0);
+
+        // Only newlines
+        let content = "\n\n\n";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 3);
+        assert!(line_types.iter().all(|&t| t == LineType::Blank));
+
+        // Mixed code and comment on same line
+        let content = "fn test() {} // comment";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 1);
+        assert_eq!(line_types[0], LineType::Comment); // Comment overrides code when both present
+    }
+
+    /// Tests CodeSection usage in find_test_sections.
+    #[test]
+    fn test_code_section_ranges() {
+        let content = r#"
+fn prod() {}
+
+#[test]
+fn test1() {}
+
+#[test]
+fn test2() {}
+"#;
+        let parse = SourceFile::parse(content, Edition::CURRENT);
+        let root = parse.syntax_node();
+
+        let mut sections = Vec::new();
+        find_test_sections(&root, &mut sections, content);
+
+        // Verify sections were found
+        assert!(sections.len() >= 2);
+        for section in &sections {
+            assert!(section.end_line >= section.start_line);
+        }
+    }
+
+    /// Tests offset_to_line mapping in analyze_lines.
+    #[test]
+    fn test_analyze_lines_offset_mapping() {
+        let content = "line1\nline2\nline3";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 3);
+
+        // All should be code lines
+        assert!(line_types.iter().all(|&t| t == LineType::Code));
+    }
+
+    /// Tests analyze_lines with very long lines.
+    #[test]
+    fn test_analyze_lines_long_lines() {
+        let long_comment = format!("// {}", "x".repeat(5000));
+        let long_code = format!("fn test() {{ {} }}", "x".repeat(5000));
+        let content = format!("{}\n{}", long_comment, long_code);
+
+        let line_types = analyze_lines(&content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 2);
+        assert_eq!(line_types[0], LineType::Comment);
+        assert_eq!(line_types[1], LineType::Code);
+    }
+
+    /// Tests FileBackedAccumulator with empty iterator.
+    #[test]
+    fn test_file_backed_accumulator_empty_iterator() {
+        let acc = FileBackedAccumulator::new().unwrap();
+        let files: Vec<_> = acc.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 0);
+    }
+
+    /// Tests analyze_lines with code containing comment-like strings.
+    #[test]
+    fn test_analyze_lines_comment_in_string() {
+        let content = r#"let s = "// not a comment";"#;
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 1);
+        assert_eq!(line_types[0], LineType::Code); // Should be code, not comment
+    }
+
+    /// Tests analyze_lines with rustdoc in block comment.
+    #[test]
+    fn test_analyze_lines_rustdoc_block_multiline() {
+        let content = "/*!\n * Module doc\n * More doc\n */";
+        let line_types = analyze_lines(content, Edition::CURRENT);
+        assert_eq!(line_types.len(), 4);
+        assert!(line_types.iter().all(|&t| t == LineType::Rustdoc));
+    }
+
+    /// Tests classify_lines with empty input.
+    #[test]
+    fn test_classify_lines_empty() {
+        let result = classify_lines("", Edition::CURRENT);
+        assert_eq!(result.len(), 0);
+    }
+
+    /// Tests classify_lines with no test code.
+    #[test]
+    fn test_classify_lines_all_production() {
+        let content = "fn prod1() {}\nfn prod2() {}\nfn prod3() {}";
+        let result = classify_lines(content, Edition::CURRENT);
+        assert!(result.iter().all(|&is_test| !is_test));
+    }
+
+    /// Tests FileBackedAccumulator error path coverage.
+    #[test]
+    fn test_file_backed_accumulator_write_error_simulation() {
+        // We can't easily simulate real write errors, but we can test the error handling path
+        let mut acc = FileBackedAccumulator::new().unwrap();
+
+        // Add a large number of files to exercise buffering
+        for i in 0..10000 {
+            let mut stats = make_minimal_test_file_stats();
+            stats.path = format!("file{}.rs", i);
+            let result = acc.add_file(&stats);
+            assert!(result.is_ok());
+        }
+
+        acc.flush().unwrap();
+
+        // Verify all files were written correctly
+        let files: Vec<_> = acc.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 10000);
+    }
+
+    /// Tests FileStats with all line types.
+    #[test]
+    fn test_file_stats_all_line_types() {
+        let prod_stats = make_line_stats(100, 10, 15, 20, 55);
+        let test_stats = make_line_stats(50, 5, 10, 10, 25);
+        let file_stats = make_file_stats_with_tests("test.rs", prod_stats, test_stats);
+
+        assert_eq!(file_stats.total.all_lines, 150);
+        assert_eq!(file_stats.total.blank_lines, 15);
+        assert_eq!(file_stats.total.comment_lines, 25);
+        assert_eq!(file_stats.total.rustdoc_lines, 30);
+        assert_eq!(file_stats.total.code_lines, 80);
+    }
+
+    /// Tests Summary accumulation with rustdoc.
+    #[test]
+    fn test_summary_accumulation_with_rustdoc() {
+        let mut summary = Summary::default();
+
+        for i in 0..10 {
+            let file_stats = make_file_stats_with_tests(
+                &format!("file{}.rs", i),
+                make_line_stats(50, 5, 5, 10, 30),
+                make_line_stats(25, 3, 2, 5, 15),
+            );
+            summary.add_file(&file_stats);
+        }
+
+        assert_eq!(summary.files, 10);
+        assert_eq!(summary.total.rustdoc_lines, 150); // (10 + 5) * 10
+        assert_eq!(summary.production.rustdoc_lines, 100); // 10 * 10
+        assert_eq!(summary.test.rustdoc_lines, 50); // 5 * 10
+    }
+
+    /// Tests parse_file_size with fractional values.
+    #[test]
+    fn test_parse_file_size_fractional() {
+        assert_eq!(parse_file_size("0.25KB").unwrap(), 256);
+        assert_eq!(parse_file_size("2.75MB").unwrap(), 2883584);
+        assert_eq!(parse_file_size("0.001GB").unwrap(), 1073741);
+    }
+
+    /// Tests Args with all flags set.
+    #[test]
+    fn test_args_all_flags() {
+        let args = Args {
+            file: Some(std::path::PathBuf::from("test.rs")),
+            dir: None,
+            out_text: Some(None),
+            out_json: None,
+            debug: true,
+            no_color: true,
+            verbose: true,
+            max_file_size: Some("100KB".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(args.output_format(), OutputFormat::Text);
+        let size = args.parse_max_file_size().unwrap();
+        assert_eq!(size, Some(102400));
+    }
+
+    /// Tests is_test_node with #[cfg(test)] attribute detection.
+    #[test]
+    fn test_is_test_node_cfg_test_detection() {
+        let code = r#"
+#[cfg(test)]
+mod tests {
+    fn helper() {}
+}
+"#;
+        let parse = SourceFile::parse(code, Edition::CURRENT);
+        let root = parse.syntax_node();
+
+        // Find nodes that are detected as test nodes
+        let mut found_test_node = false;
+        for node in root.descendants() {
+            if is_test_node(&node) {
+                found_test_node = true;
+                break;
+            }
+        }
+        assert!(
+            found_test_node,
+            "Should detect #[cfg(test)] module as test node"
+        );
+    }
+
+    /// Tests the detailed text report with file stats.
+    #[test]
+    fn test_output_text_accumulator_with_data() {
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+
+        let stats = FileStats {
+            path: "test.rs".to_string(),
+            total: LineStats {
+                all_lines: 10,
+                blank_lines: 2,
+                comment_lines: 3,
+                code_lines: 5,
+                rustdoc_lines: 1,
+            },
+            production: LineStats {
+                all_lines: 6,
+                blank_lines: 1,
+                comment_lines: 2,
+                code_lines: 3,
+                rustdoc_lines: 1,
+            },
+            test: LineStats {
+                all_lines: 4,
+                blank_lines: 1,
+                comment_lines: 1,
+                code_lines: 2,
+                rustdoc_lines: 0,
+            },
+            ..Default::default()
+        };
+
+        accumulator.add_file(&stats).unwrap();
+        accumulator.flush().unwrap();
+
+        // Ensure function doesn't panic
+        let result = output_to_stdout(ReportKind::Text, &accumulator);
+        assert!(result.is_ok());
+    }
+
+    /// Tests the JSON report with multiple files.
+    #[test]
+    fn test_output_json_accumulator_multi_file() {
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+
+        let stats1 = FileStats {
+            path: "test1.rs".to_string(),
+            total: LineStats {
+                all_lines: 10,
+                blank_lines: 2,
+                comment_lines: 3,
+                code_lines: 5,
+                rustdoc_lines: 1,
+            },
+            production: LineStats {
+                all_lines: 6,
+                blank_lines: 1,
+                comment_lines: 2,
+                code_lines: 3,
+                rustdoc_lines: 1,
+            },
+            test: LineStats {
+                all_lines: 4,
+                blank_lines: 1,
+                comment_lines: 1,
+                code_lines: 2,
+                rustdoc_lines: 0,
+            },
+            ..Default::default()
+        };
+
+        let stats2 = FileStats {
+            path: "test2.rs".to_string(),
+            total: LineStats {
+                all_lines: 8,
+                blank_lines: 1,
+                comment_lines: 2,
+                code_lines: 5,
+                rustdoc_lines: 0,
+            },
+            production: LineStats {
+                all_lines: 8,
+                blank_lines: 1,
+                comment_lines: 2,
+                code_lines: 5,
+                rustdoc_lines: 0,
+            },
+            test: LineStats::default(),
+            ..Default::default()
+        };
+
+        accumulator.add_file(&stats1).unwrap();
+        accumulator.add_file(&stats2).unwrap();
+        accumulator.flush().unwrap();
+
+        let result = output_to_stdout(ReportKind::Json, &accumulator);
+        assert!(result.is_ok());
+    }
+
+    /// Tests analyze_file respecting file size limits.
+    #[test]
+    fn test_analyze_file_with_size_limit() {
+        let mut temp_file = std::env::temp_dir();
+        temp_file.push("test_size_limited.rs");
+
+        // Create a file larger than the limit
+        let large_content = "// Large file\n".repeat(50);
+        std::fs::write(&temp_file, large_content).unwrap();
+
+        let result = analyze_file(
+            &temp_file,
+            &AnalysisOptions {
+                max_file_size: Some(100),
+                ..Default::default()
+            },
+        );
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("exceeds maximum size")
+        );
+
+        std::fs::remove_file(&temp_file).unwrap();
+    }
+
+    /// Tests is_test_node detects #[cfg(test)] on functions.
+    #[test]
+    fn test_is_test_node_cfg_test_on_function() {
+        let code = r#"
+#[cfg(test)]
+fn helper_function() {
+    println!("test helper");
+}
+"#;
+        let parse = SourceFile::parse(code, Edition::CURRENT);
+        let root = parse.syntax_node();
+
+        let mut found_cfg_test_fn = false;
+        for node in root.descendants() {
+            if is_test_node(&node) && ast::Fn::cast(node.clone()).is_some() {
+                found_cfg_test_fn = true;
+                break;
+            }
+        }
+        assert!(found_cfg_test_fn, "Should detect #[cfg(test)] on function");
+    }
+
+    /// Tests classify_lines with complex mixed production and test code.
+    #[test]
+    fn test_classify_lines_complex_cfg_test() {
+        let content = r#"
+// Production code
+fn prod() {}
+
+#[cfg(test)]
+fn test_helper() {}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test1() {}
+}
+"#;
+        let is_test = classify_lines(content, Edition::CURRENT);
+
+        // Should have some production and some test lines
+        let test_count = is_test.iter().filter(|&&x| x).count();
+        let prod_count = is_test.iter().filter(|&&x| !x).count();
+
+        assert!(test_count > 0, "Should detect test lines");
+        assert!(prod_count > 0, "Should detect production lines");
+    }
+
+    /// Tests accumulator get_summary with FileBackedAccumulator.
+    #[test]
+    fn test_file_backed_accumulator_get_summary() {
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+
+        let stats1 = FileStats {
+            path: "file1.rs".to_string(),
+            total: LineStats {
+                all_lines: 100,
+                blank_lines: 10,
+                comment_lines: 20,
+                code_lines: 70,
+                rustdoc_lines: 5,
+            },
+            production: LineStats {
+                all_lines: 60,
+                blank_lines: 5,
+                comment_lines: 10,
+                code_lines: 45,
+                rustdoc_lines: 5,
+            },
+            test: LineStats {
+                all_lines: 40,
+                blank_lines: 5,
+                comment_lines: 10,
+                code_lines: 25,
+                rustdoc_lines: 0,
+            },
+            ..Default::default()
+        };
+
+        let stats2 = FileStats {
+            path: "file2.rs".to_string(),
+            total: LineStats {
+                all_lines: 50,
+                blank_lines: 5,
+                comment_lines: 10,
+                code_lines: 35,
+                rustdoc_lines: 2,
+            },
+            production: LineStats {
+                all_lines: 50,
+                blank_lines: 5,
+                comment_lines: 10,
+                code_lines: 35,
+                rustdoc_lines: 2,
+            },
+            test: LineStats::default(),
+            ..Default::default()
+        };
+
+        accumulator.add_file(&stats1).unwrap();
+        accumulator.add_file(&stats2).unwrap();
+        accumulator.flush().unwrap();
+
+        let summary = accumulator.get_summary();
+
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.total.all_lines, 150);
+        assert_eq!(summary.production.all_lines, 110);
+        assert_eq!(summary.test.all_lines, 40);
+    }
+
+    /// Tests analyze_directory with FileBackedAccumulator and no valid files.
+    #[test]
+    fn test_analyze_directory_file_backed_no_files() {
+        let temp_dir = std::env::temp_dir().join("test_no_files_fb");
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create only a non-Rust file
+        std::fs::write(temp_dir.join("readme.md"), "Not Rust").unwrap();
+
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+        let result = analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut accumulator);
+
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("No Rust files"));
+
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Tests analyze_directory with all files exceeding size limit using FileBackedAccumulator.
+    #[test]
+    fn test_analyze_directory_file_backed_all_too_large() {
+        let temp_dir = std::env::temp_dir().join("test_all_large_fb");
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create large files
+        std::fs::write(temp_dir.join("big.rs"), "// ".repeat(200)).unwrap();
+
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+        let result = analyze_directory(
+            &temp_dir,
+            &AnalysisOptions {
+                max_file_size: Some(50),
+                ..Default::default()
+            },
+            &mut accumulator,
+        );
+
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("No Rust files could be analyzed")
+        );
+
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Tests analyze_file and analyze_directory integration with real directory.
+    #[test]
+    fn test_integration_analyze_real_directory() {
+        let temp_dir = std::env::temp_dir().join("test_real_analysis");
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create realistic Rust files
+        std::fs::write(
+            temp_dir.join("lib.rs"),
+            r#"
+//! Library documentation
+
+/// A function
+pub fn func() {}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_func() {}
+}
+"#,
+        )
+        .unwrap();
+
+        std::fs::write(
+            temp_dir.join("main.rs"),
+            r#"
+fn main() {
+    println!("Hello");
+}
+"#,
+        )
+        .unwrap();
+
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+        let result = analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut accumulator);
+
+        assert!(result.is_ok());
+
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.files, 2);
+        assert!(summary.total.all_lines > 0);
+
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Tests the detailed text report with both accumulators.
+    #[test]
+    fn test_output_text_integration() {
+        let mut accumulator = InMemoryAccumulator::new();
+
+        let stats = FileStats {
+            path: "sample.rs".to_string(),
+            total: LineStats {
+                all_lines: 20,
+                blank_lines: 3,
+                comment_lines: 5,
+                code_lines: 12,
+                rustdoc_lines: 2,
+            },
+            production: LineStats {
+                all_lines: 15,
+                blank_lines: 2,
+                comment_lines: 3,
+                code_lines: 10,
+                rustdoc_lines: 2,
+            },
+            test: LineStats {
+                all_lines: 5,
+                blank_lines: 1,
+                comment_lines: 2,
+                code_lines: 2,
+                rustdoc_lines: 0,
+            },
+            ..Default::default()
+        };
+
+        accumulator.add_file(&stats).unwrap();
+
+        // Call output function - it prints to stdout
+        let result = output_to_stdout(ReportKind::Text, &accumulator);
+        assert!(result.is_ok());
+
+        // Also test with FileBackedAccumulator
+        let mut fb_acc = FileBackedAccumulator::new().unwrap();
+        fb_acc.add_file(&stats).unwrap();
+        fb_acc.flush().unwrap();
+        let result2 = output_to_stdout(ReportKind::Text, &fb_acc);
+        assert!(result2.is_ok());
+    }
+
+    /// Tests the JSON report with both accumulators.
+    #[test]
+    fn test_output_json_integration() {
+        let mut accumulator = InMemoryAccumulator::new();
+
+        let stats = FileStats {
+            path: "sample.rs".to_string(),
+            total: LineStats {
+                all_lines: 20,
+                blank_lines: 3,
+                comment_lines: 5,
+                code_lines: 12,
+                rustdoc_lines: 2,
+            },
+            production: LineStats {
+                all_lines: 15,
+                blank_lines: 2,
+                comment_lines: 3,
+                code_lines: 10,
+                rustdoc_lines: 2,
+            },
+            test: LineStats {
+                all_lines: 5,
+                blank_lines: 1,
+                comment_lines: 2,
+                code_lines: 2,
+                rustdoc_lines: 0,
+            },
+            ..Default::default()
+        };
+
+        accumulator.add_file(&stats).unwrap();
+
+        // Call output function - it prints to stdout
+        let result = output_to_stdout(ReportKind::Json, &accumulator);
+        assert!(result.is_ok());
+
+        // Also test with FileBackedAccumulator
+        let mut fb_acc = FileBackedAccumulator::new().unwrap();
+        fb_acc.add_file(&stats).unwrap();
+        fb_acc.flush().unwrap();
+        let result2 = output_to_stdout(ReportKind::Json, &fb_acc);
+        assert!(result2.is_ok());
+    }
+
+    /// Tests Args::parse_max_file_size with completely invalid input.
+    #[test]
+    fn test_args_parse_max_file_size_invalid_format() {
+        let args = Args {
+            file: None,
+            dir: None,
+            out_text: None,
+            out_json: None,
+            debug: false,
+            no_color: false,
+            verbose: false,
+            max_file_size: Some("not-a-number".to_string()),
+            ..Default::default()
+        };
+        let result = args.parse_max_file_size();
+        assert!(result.is_err());
+    }
+
+    /// Tests analyze_directory with subdirectories containing Rust files.
+    #[test]
+    fn test_analyze_directory_with_subdirs() {
+        let temp_dir = std::env::temp_dir().join("test_subdirs");
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        std::fs::create_dir_all(temp_dir.join("subdir")).unwrap();
+
+        // Create files in root and subdirectory
+        std::fs::write(temp_dir.join("root.rs"), "fn root() {}").unwrap();
+        std::fs::write(temp_dir.join("subdir/nested.rs"), "fn nested() {}").unwrap();
+
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+        let result = analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut accumulator);
+
+        assert!(result.is_ok());
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.files, 2);
+
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Tests analyze_file with complex code containing all line types.
+    #[test]
+    fn test_analyze_file_comprehensive_content() {
+        let temp_file = std::env::temp_dir().join("comprehensive.rs");
+
+        let content = r#"
+//! Module docs
+
+// Regular comment
+
+/// Rustdoc for function
+fn production_fn() {
+    // Implementation
+    println!("prod");
+}
+
+#[cfg(test)]
+mod tests {
+    /// Test docs
+    #[test]
+    fn test_something() {
+        assert!(true);
+    }
+
+    #[cfg(test)]
+    fn helper() {}
+}
+"#;
+
+        std::fs::write(&temp_file, content).unwrap();
+
+        let result = analyze_file(&temp_file, &AnalysisOptions::default());
+        assert!(result.is_ok());
+
+        let stats = result.unwrap();
+        assert!(stats.total.all_lines > 0);
+        assert!(stats.production.all_lines > 0);
+        assert!(stats.test.all_lines > 0);
+        assert!(stats.total.rustdoc_lines > 0);
+        assert!(stats.total.comment_lines > 0);
+        assert!(stats.total.blank_lines > 0);
+
+        std::fs::remove_file(&temp_file).unwrap();
+    }
+
+    /// Tests end-to-end workflow: create files, analyze, output.
+    #[test]
+    fn test_end_to_end_workflow() {
+        let temp_dir = std::env::temp_dir().join("test_e2e");
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create multiple Rust files
+        std::fs::write(
+            temp_dir.join("file1.rs"),
+            "fn main() {\n    println!(\"test\");\n}\n",
+        )
+        .unwrap();
+
+        std::fs::write(
+            temp_dir.join("file2.rs"),
+            r#"
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test() {}
+}
+"#,
+        )
+        .unwrap();
+
+        // Analyze with FileBackedAccumulator
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+        let result = analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut accumulator);
+        assert!(result.is_ok());
+
+        accumulator.flush().unwrap();
+
+        // Output both formats
+        let text_result = output_to_stdout(ReportKind::Text, &accumulator);
+        assert!(text_result.is_ok());
+
+        let json_result = output_to_stdout(ReportKind::Json, &accumulator);
+        assert!(json_result.is_ok());
+
+        // Verify summary
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.files, 2);
+
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Tests analyze_directory handles file iteration and accumulation correctly.
+    #[test]
+    fn test_analyze_directory_accumulation() {
+        let temp_dir = std::env::temp_dir().join("test_accumulation");
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create several Rust files with varying content
+        for i in 1..=5 {
+            let content = format!(
+                "// File {}\nfn func{}() {{}}\n\n#[cfg(test)]\nmod tests{} {{}}\n",
+                i, i, i
+            );
+            std::fs::write(temp_dir.join(format!("file{}.rs", i)), content).unwrap();
+        }
+
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+        let result = analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut accumulator);
+
+        assert!(result.is_ok());
+        accumulator.flush().unwrap();
+
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.files, 5);
+        assert!(summary.total.all_lines > 0);
+
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Tests multiple consecutive analyze operations with same accumulator.
+    #[test]
+    fn test_multiple_analyze_operations() {
+        let temp_dir1 = std::env::temp_dir().join("test_multi_1");
+        let temp_dir2 = std::env::temp_dir().join("test_multi_2");
+        std::fs::create_dir_all(&temp_dir1).unwrap();
+        std::fs::create_dir_all(&temp_dir2).unwrap();
+
+        std::fs::write(temp_dir1.join("a.rs"), "fn a() {}").unwrap();
+        std::fs::write(temp_dir2.join("b.rs"), "fn b() {}").unwrap();
+
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+
+        // Analyze first directory
+        analyze_directory(&temp_dir1, &AnalysisOptions::default(), &mut accumulator).unwrap();
+        // Analyze second directory
+        analyze_directory(&temp_dir2, &AnalysisOptions::default(), &mut accumulator).unwrap();
+
+        accumulator.flush().unwrap();
+
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.files, 2);
+
+        std::fs::remove_dir_all(&temp_dir1).unwrap();
+        std::fs::remove_dir_all(&temp_dir2).unwrap();
+    }
+
+    /// Tests FileBackedAccumulator iteration works correctly after flush.
+    #[test]
+    fn test_file_backed_accumulator_iteration_after_flush() {
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+
+        for i in 1..=3 {
+            let stats = FileStats {
+                path: format!("file{}.rs", i),
+                total: LineStats {
+                    all_lines: i * 10,
+                    blank_lines: i,
+                    comment_lines: i * 2,
+                    code_lines: i * 7,
+                    rustdoc_lines: 0,
+                },
+                production: LineStats {
+                    all_lines: i * 10,
+                    blank_lines: i,
+                    comment_lines: i * 2,
+                    code_lines: i * 7,
+                    rustdoc_lines: 0,
+                },
+                test: LineStats::default(),
+                ..Default::default()
+            };
+            accumulator.add_file(&stats).unwrap();
+        }
+
+        accumulator.flush().unwrap();
+
+        // Iterate and verify
+        let files: Vec<_> = accumulator.iter_files().unwrap().collect();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0].path, "file1.rs");
+        assert_eq!(files[1].path, "file2.rs");
+        assert_eq!(files[2].path, "file3.rs");
+    }
+
+    /// Tests analyze_directory with very large directory (many files).
+    #[test]
+    fn test_analyze_directory_many_files() {
+        let temp_dir = std::env::temp_dir().join("test_many_files");
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        // Create 10 files to test bulk operations
+        for i in 1..=10 {
+            let content = format!("fn func{}() {{}}\n", i);
+            std::fs::write(temp_dir.join(format!("file{}.rs", i)), content).unwrap();
+        }
+
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+        let result = analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut accumulator);
+
+        assert!(result.is_ok());
+        accumulator.flush().unwrap();
+
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.files, 10);
+
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Tests analyze_file followed by analyze_directory with same accumulator.
+    #[test]
+    fn test_mixed_file_and_directory_analysis() {
+        let temp_file = std::env::temp_dir().join("single.rs");
+        let temp_dir = std::env::temp_dir().join("test_mixed");
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        std::fs::write(&temp_file, "fn single() {}").unwrap();
+        std::fs::write(temp_dir.join("dir.rs"), "fn dir() {}").unwrap();
+
+        let mut accumulator = FileBackedAccumulator::new().unwrap();
+
+        // Analyze single file
+        let file_stats = analyze_file(&temp_file, &AnalysisOptions::default()).unwrap();
+        accumulator.add_file(&file_stats).unwrap();
+
+        // Analyze directory
+        analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut accumulator).unwrap();
+
+        accumulator.flush().unwrap();
+
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.files, 2);
+
+        std::fs::remove_file(&temp_file).unwrap();
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    /// Tests output functions handle empty accumulator gracefully.
+    #[test]
+    fn test_output_with_empty_accumulator() {
+        let accumulator = InMemoryAccumulator::new();
+
+        // Both should work even with no files
+        let text_result = output_to_stdout(ReportKind::Text, &accumulator);
+        assert!(text_result.is_ok());
+
+        let json_result = output_to_stdout(ReportKind::Json, &accumulator);
+        assert!(json_result.is_ok());
+    }
+
+    /// Tests Report serialization and deserialization.
+    #[test]
+    fn test_report_json_roundtrip() {
+        let report = Report {
+            summary: Summary {
+                files: 1,
+                total: LineStats {
+                    all_lines: 10,
+                    blank_lines: 2,
+                    comment_lines: 3,
+                    code_lines: 5,
+                    rustdoc_lines: 1,
+                },
+                production: LineStats {
+                    all_lines: 7,
+                    blank_lines: 1,
+                    comment_lines: 2,
+                    code_lines: 4,
+                    rustdoc_lines: 1,
+                },
+                test: LineStats {
+                    all_lines: 3,
+                    blank_lines: 1,
+                    comment_lines: 1,
+                    code_lines: 1,
+                    rustdoc_lines: 0,
+                },
+                ..Default::default()
+            },
+            files: vec![FileStats {
+                path: "test.rs".to_string(),
+                total: LineStats {
+                    all_lines: 10,
+                    blank_lines: 2,
+                    comment_lines: 3,
+                    code_lines: 5,
+                    rustdoc_lines: 1,
+                },
+                production: LineStats {
+                    all_lines: 7,
+                    blank_lines: 1,
+                    comment_lines: 2,
+                    code_lines: 4,
+                    rustdoc_lines: 1,
+                },
+                test: LineStats {
+                    all_lines: 3,
+                    blank_lines: 1,
+                    comment_lines: 1,
+                    code_lines: 1,
+                    rustdoc_lines: 0,
+                },
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        // Serialize
+        let json = serde_json::to_string_pretty(&report).unwrap();
+        assert!(json.contains("test.rs"));
+
+        // Deserialize
+        let deserialized: Report = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.summary.files, 1);
+        assert_eq!(deserialized.files.len(), 1);
+    }
+
+    /// Tests that compute_doc_coverage counts documented and undocumented public items.
+    #[test]
+    fn test_compute_doc_coverage_public_items() {
+        let code = r#"
+/// Documented function.
+pub fn documented() {}
+
+pub fn undocumented() {}
+
+#[doc = "Attribute documentation."]
+pub struct Attributed;
+
+pub enum Bare { A }
+
+/// Documented trait.
+pub trait Shape {}
+
+fn private_fn() {}
+pub(crate) fn crate_visible() {}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let coverage = compute_doc_coverage(&root);
+
+        assert_eq!(coverage.public_items, 5);
+        assert_eq!(coverage.documented_items, 3);
+        assert_eq!(coverage.percentage(), Some(60.0));
+    }
+
+    /// Tests that compute_doc_coverage includes public methods and excludes test code.
+    #[test]
+    fn test_compute_doc_coverage_methods_and_tests() {
+        let code = r#"
+pub struct Widget;
+
+impl Widget {
+    /// Creates a widget.
+    pub fn new() -> Self { Widget }
+}
+
+#[cfg(test)]
+mod tests {
+    pub fn helper() {}
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let coverage = compute_doc_coverage(&root);
+
+        assert_eq!(coverage.public_items, 2);
+        assert_eq!(coverage.documented_items, 1);
+    }
+
+    /// Tests DocCoverage percentage, accumulation, and serialization.
+    #[test]
+    fn test_doc_coverage_percentage_add_and_serialization() {
+        let mut coverage = DocCoverage::default();
+        assert_eq!(coverage.percentage(), None);
+
+        coverage.add(&DocCoverage {
+            public_items: 4,
+            documented_items: 1,
+        });
+        assert_eq!(coverage.percentage(), Some(25.0));
+
+        let json = serde_json::to_value(coverage).unwrap();
+        assert_eq!(json["public-items"], 4);
+        assert_eq!(json["documented-items"], 1);
+        assert_eq!(json["coverage-percent"], 25.0);
+
+        let deserialized: DocCoverage = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, coverage);
+
+        let empty = serde_json::to_value(DocCoverage::default()).unwrap();
+        assert!(empty["coverage-percent"].is_null());
+    }
+
+    /// Tests that Summary::add_file aggregates documentation coverage.
+    #[test]
+    fn test_summary_add_file_doc_coverage() {
+        let mut summary = Summary::default();
+        summary.add_file(&make_minimal_test_file_stats());
+        assert_eq!(summary.doc_coverage, None);
+
+        let stats = FileStats {
+            doc_coverage: Some(DocCoverage {
+                public_items: 3,
+                documented_items: 2,
+            }),
+            ..make_minimal_test_file_stats()
+        };
+        summary.add_file(&stats);
+        summary.add_file(&stats);
+
+        let doc_coverage = summary.doc_coverage.unwrap();
+        assert_eq!(doc_coverage.public_items, 6);
+        assert_eq!(doc_coverage.documented_items, 4);
+    }
+
+    /// Tests format_doc_coverage output with and without public items.
+    #[test]
+    fn test_format_doc_coverage() {
+        let coverage = DocCoverage {
+            public_items: 15,
+            documented_items: 12,
+        };
+        assert_eq!(
+            format_doc_coverage(&coverage),
+            "12/15 public items documented (80.0%)"
+        );
+        assert_eq!(
+            format_doc_coverage(&DocCoverage::default()),
+            "n/a (no public items)"
+        );
+    }
+
+    /// Tests that analyze_file reports documentation coverage only with --doc-coverage.
+    #[test]
+    fn test_analyze_file_doc_coverage() {
+        let temp_file = std::env::temp_dir().join("test_ruloc_doc_coverage.rs");
+        std::fs::write(
+            &temp_file,
+            "/// Documented.\npub fn a() {}\npub fn b() {}\n",
+        )
+        .unwrap();
+
+        let stats = analyze_file(&temp_file, &AnalysisOptions::default()).unwrap();
+        assert_eq!(stats.doc_coverage, None);
+
+        let options = AnalysisOptions {
+            doc_coverage: true,
+            ..Default::default()
+        };
+        let doc_coverage = analyze_file(&temp_file, &options)
+            .unwrap()
+            .doc_coverage
+            .unwrap();
+        assert_eq!(doc_coverage.public_items, 2);
+        assert_eq!(doc_coverage.documented_items, 1);
+
+        let args = Args::try_parse_from(["ruloc", "--dir", "src", "--min-doc-coverage", "80"]);
+        assert!(args.unwrap().analysis_options().unwrap().doc_coverage);
+
+        std::fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests LinkBase revision substitution and trailing slash normalization.
+    #[test]
+    fn test_link_base_with_revision() {
+        let link_base = LinkBase::with_revision(
+            "https://github.com/org/repo/blob/<rev>",
+            Some("abc123".to_string()),
+            Path::new("/work/repo"),
+        );
+        assert_eq!(link_base.base, "https://github.com/org/repo/blob/abc123/");
+        assert_eq!(link_base.revision.as_deref(), Some("abc123"));
+
+        let unpinned =
+            LinkBase::with_revision("https://example.com/src/", None, Path::new("/work/repo"));
+        assert_eq!(unpinned.base, "https://example.com/src/");
+        assert_eq!(unpinned.revision, None);
+    }
+
+    /// Tests that LinkBase::url_for relativizes paths against the root.
+    #[test]
+    fn test_link_base_url_for() {
+        let link_base = LinkBase::with_revision(
+            "https://github.com/org/repo/blob/main/",
+            None,
+            Path::new("/nonexistent/repo"),
+        );
+
+        assert_eq!(
+            link_base.url_for("/nonexistent/repo/src/lib.rs"),
+            "https://github.com/org/repo/blob/main/src/lib.rs"
+        );
+        assert_eq!(
+            link_base.url_for("other/./main.rs"),
+            "https://github.com/org/repo/blob/main/other/main.rs"
+        );
+    }
+
+    /// Tests LinkBase::resolve outside a git repository.
+    #[test]
+    fn test_link_base_resolve_outside_git() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        let link_base = LinkBase::resolve("https://example.com/", temp_dir.path()).unwrap();
+        assert_eq!(link_base.revision, None);
+        assert_eq!(link_base.root, fs::canonicalize(temp_dir.path()).unwrap());
+
+        if run_git(temp_dir.path(), &["rev-parse", "HEAD"]).is_err() {
+            assert!(LinkBase::resolve("https://example.com/<rev>/", temp_dir.path()).is_err());
+        }
+    }
+
+    /// Tests that OutputOptions::decorate attaches URLs only when configured.
+    #[test]
+    fn test_output_options_decorate() {
+        let stats = make_minimal_test_file_stats();
+        let plain = OutputOptions::default();
+        assert_eq!(plain.decorate(stats.clone()).url, None);
+        assert_eq!(plain.revision(), None);
+
+        let linked = OutputOptions {
+            link_base: Some(LinkBase::with_revision(
+                "https://example.com/blob/<rev>/",
+                Some("deadbeef".to_string()),
+                Path::new("/nonexistent"),
+            )),
+            ..Default::default()
+        };
+        assert_eq!(
+            linked.decorate(stats).url.as_deref(),
+            Some("https://example.com/blob/deadbeef/test.rs")
+        );
+        assert_eq!(linked.revision().as_deref(), Some("deadbeef"));
+    }
+
+    /// Tests Args::analysis_root for file and directory inputs.
+    #[test]
+    fn test_args_analysis_root() {
+        let dir_args = Args {
+            dir: Some(PathBuf::from("src")),
+            ..Default::default()
+        };
+        assert_eq!(dir_args.analysis_root(), PathBuf::from("src"));
+
+        let nested_file_args = Args {
+            file: Some(PathBuf::from("src/main.rs")),
+            ..Default::default()
+        };
+        assert_eq!(nested_file_args.analysis_root(), PathBuf::from("src"));
+
+        let bare_file_args = Args {
+            file: Some(PathBuf::from("main.rs")),
+            ..Default::default()
+        };
+        assert_eq!(bare_file_args.analysis_root(), PathBuf::from("."));
+        assert!(bare_file_args.output_options().unwrap().link_base.is_none());
+    }
+
+    /// Tests that link URLs and the revision are serialized only when present.
+    #[test]
+    fn test_report_link_serialization() {
+        let report = Report {
+            files: vec![make_minimal_test_file_stats()],
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json.get("revision").is_none());
+        assert!(json["files"][0].get("url").is_none());
+
+        let mut linked = report.clone();
+        linked.revision = Some("abc".to_string());
+        linked.files[0].url = Some("https://example.com/test.rs".to_string());
+        let json = serde_json::to_value(&linked).unwrap();
+        assert_eq!(json["revision"], "abc");
+        assert_eq!(json["files"][0]["url"], "https://example.com/test.rs");
+    }
+
+    /// Tests parsing of a valid analysis plan with multiple analyses.
+    #[test]
+    fn test_parse_analysis_plan_valid() {
+        let plan = parse_analysis_plan(
+            r#"
+[[analysis]]
+name = "core"
+dir = "crates/core"
+format = "json"
+max-file-size = "1MB"
+
+[[analysis]]
+file = "src/main.rs"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(plan.analyses.len(), 2);
+        assert_eq!(plan.analyses[0].label(0), "core");
+        assert_eq!(plan.analyses[0].format, OutputFormat::Json);
+        assert_eq!(plan.analyses[1].label(1), "#2");
+        assert_eq!(plan.analyses[1].format, OutputFormat::Text);
+
+        let args = plan.analyses[0].to_args(0).unwrap();
+        assert_eq!(args.dir, Some(PathBuf::from("crates/core")));
+        assert_eq!(args.output_format(), OutputFormat::Json);
+        assert_eq!(args.parse_max_file_size().unwrap(), Some(1024 * 1024));
+    }
+
+    /// Tests that malformed, unknown-key, and empty plans are rejected.
+    #[test]
+    fn test_parse_analysis_plan_invalid() {
+        assert!(parse_analysis_plan("[[analysis]\n").is_err());
+        assert!(parse_analysis_plan("[[analysis]]\ndirr = \"src\"\n").is_err());
+        assert!(parse_analysis_plan("[[analysis]]\nformat = \"yaml\"\n").is_err());
+
+        let err = parse_analysis_plan("").unwrap_err();
+        assert!(err.to_string().contains("no analyses"));
+    }
+
+    /// Tests that planned analyses require exactly one input.
+    #[test]
+    fn test_planned_analysis_to_args_requires_single_input() {
+        let plan = parse_analysis_plan(
+            "[[analysis]]\nname = \"both\"\nfile = \"a.rs\"\ndir = \"src\"\n\n[[analysis]]\n",
+        )
+        .unwrap();
+
+        let err = plan.analyses[0].to_args(0).unwrap_err();
+        assert!(err.to_string().contains("'both'"));
+        assert!(plan.analyses[1].to_args(1).is_err());
+    }
+
+    /// Tests run_plan end to end and with a missing plan file.
+    #[test]
+    fn test_run_plan() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = temp_dir.path().join("lib.rs");
+        fs::write(&source, "fn main() {}\n").unwrap();
+
+        let plan_path = temp_dir.path().join("plan.toml");
+        fs::write(
+            &plan_path,
+            format!(
+                "[[analysis]]\ndir = {:?}\n\n[[analysis]]\nfile = {:?}\nformat = \"json\"\n",
+                temp_dir.path(),
+                source
+            ),
+        )
+        .unwrap();
+        assert!(run_plan(&plan_path).is_ok());
+
+        let missing = temp_dir.path().join("missing.toml");
+        assert!(
+            run_plan(&missing)
+                .unwrap_err()
+                .to_string()
+                .contains("Failed to read")
+        );
+    }
+
+    /// Tests that the run subcommand is parsed without requiring --file or --dir.
+    #[test]
+    fn test_args_parse_run_subcommand() {
+        let args = Args::try_parse_from(["ruloc", "run", "plan.toml"]).unwrap();
+        match args.command {
+            Some(Command::Run { plan }) => assert_eq!(plan, PathBuf::from("plan.toml")),
+            other => panic!("expected run subcommand, got {:?}", other),
+        }
+
+        assert!(Args::try_parse_from(["ruloc"]).is_err());
+    }
+
+    /// Tests LineIndex offset and span mapping.
+    #[test]
+    fn test_line_index_mapping() {
+        let content = "ab\ncd\n\nef";
+        let index = LineIndex::new(content);
+
+        assert_eq!(index.line_of(0), 0);
+        assert_eq!(index.line_of(2), 0);
+        assert_eq!(index.line_of(3), 1);
+        assert_eq!(index.line_of(6), 2);
+        assert_eq!(index.line_of(7), 3);
+        assert_eq!(index.line_of(100), 3);
+
+        let range = ra_ap_syntax::TextRange::new(1.into(), 7.into());
+        assert_eq!(index.line_span(range), (0, 2));
+    }
+
+    /// Tests that compute_macro_lines counts definition and multi-line invocation lines.
+    #[test]
+    fn test_compute_macro_lines() {
+        let code = r#"macro_rules! square {
+    ($x:expr) => {
+        $x * $x
+    };
+}
+
+fn main() {
+    println!("one line");
+    let v = vec![
+        1,
+        // comment inside invocation
+        2,
+    ];
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let line_types = analyze_lines(code, Edition::CURRENT);
+        let macro_lines = compute_macro_lines(&root, &LineIndex::new(code), &line_types);
+
+        assert_eq!(macro_lines.definition_lines, 5);
+        assert_eq!(macro_lines.invocation_lines, 3);
+    }
+
+    /// Tests MacroLines accumulation, Summary aggregation, and text formatting.
+    #[test]
+    fn test_macro_lines_add_and_format() {
+        let stats = FileStats {
+            macro_lines: Some(MacroLines {
+                definition_lines: 4,
+                invocation_lines: 2,
+            }),
+            ..make_minimal_test_file_stats()
+        };
+
+        let mut summary = Summary::default();
+        summary.add_file(&stats);
+        summary.add_file(&stats);
+
+        let macro_lines = summary.macro_lines.unwrap();
+        assert_eq!(macro_lines.definition_lines, 8);
+        assert_eq!(macro_lines.invocation_lines, 4);
+        assert_eq!(
+            format_macro_lines(&macro_lines),
+            "8 in definitions, 4 in invocations"
+        );
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["macro-lines"]["definition-lines"], 8);
+        assert_eq!(json["macro-lines"]["invocation-lines"], 4);
+
+        let args = Args::try_parse_from(["ruloc", "--dir", "src", "--macro-lines"]).unwrap();
+        assert!(args.analysis_options().unwrap().macro_lines);
+    }
+
+    /// Tests that cfg_features finds features at any nesting depth.
+    #[test]
+    fn test_cfg_features_nested_predicates() {
+        let code = r#"#[cfg(all(feature = "serde", any(unix, not(feature = "slim"))))]
+fn gated() {}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let func = root.descendants().find_map(ast::Fn::cast).unwrap();
+
+        assert_eq!(
+            feature_gates(func.syntax()),
+            vec!["serde".to_string(), "slim".to_string()]
+        );
+    }
+
+    /// Tests that feature_gates ignores non-cfg attributes and ungated nodes.
+    #[test]
+    fn test_feature_gates_ignores_other_attributes() {
+        let code = "#[cfg(test)]\n#[doc = \"feature = x\"]\nfn plain() {}\n";
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let func = root.descendants().find_map(ast::Fn::cast).unwrap();
+
+        assert!(feature_gates(func.syntax()).is_empty());
+    }
+
+    /// Tests per-feature line attribution including nested gates.
+    #[test]
+    fn test_compute_feature_breakdown() {
+        let code = r#"fn always() {}
+
+#[cfg(feature = "net")]
+mod net {
+    // Connects.
+    pub fn connect() {}
+
+    #[cfg(feature = "tls")]
+    pub fn secure() {}
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let line_types = analyze_lines(code, Edition::CURRENT);
+        let features = compute_feature_breakdown(&root, &LineIndex::new(code), &line_types);
+
+        assert_eq!(features.len(), 2);
+        assert_eq!(features["net"], make_line_stats(8, 1, 1, 0, 6));
+        assert_eq!(features["tls"], make_line_stats(2, 0, 0, 0, 2));
+    }
+
+    /// Tests that analyze_file only computes the feature breakdown when requested.
+    #[test]
+    fn test_analyze_file_feature_breakdown_opt_in() {
+        let temp_file = std::env::temp_dir().join("test_ruloc_feature_breakdown.rs");
+        fs::write(&temp_file, "#[cfg(feature = \"extra\")]\nfn extra() {}\n").unwrap();
+
+        let plain = analyze_file(&temp_file, &AnalysisOptions::default()).unwrap();
+        assert!(plain.features.is_empty());
+
+        let options = AnalysisOptions {
+            feature_breakdown: true,
+            ..Default::default()
+        };
+        let stats = analyze_file(&temp_file, &options).unwrap();
+        assert_eq!(stats.features["extra"].code_lines, 2);
+
+        let mut summary = Summary::default();
+        summary.add_file(&stats);
+        summary.add_file(&stats);
+        assert_eq!(summary.features["extra"].code_lines, 4);
+
+        fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests format_feature_breakdown output.
+    #[test]
+    fn test_format_feature_breakdown() {
+        let mut features = BTreeMap::new();
+        features.insert("b".to_string(), make_line_stats(5, 1, 0, 0, 4));
+        features.insert("a".to_string(), make_line_stats(2, 0, 0, 0, 2));
+
+        assert_eq!(
+            format_feature_breakdown(&features, 2),
+            "  a: 2 code lines (2 lines)\n  b: 4 code lines (5 lines)"
+        );
+    }
+
+    /// Tests parse_label with valid and invalid input.
+    #[test]
+    fn test_parse_label() {
+        assert_eq!(
+            parse_label("team=payments").unwrap(),
+            ("team".to_string(), "payments".to_string())
+        );
+        assert_eq!(
+            parse_label(" url = a=b ").unwrap(),
+            ("url".to_string(), "a=b".to_string())
+        );
+        assert_eq!(
+            parse_label("empty=").unwrap(),
+            ("empty".to_string(), String::new())
+        );
+        assert!(parse_label("novalue").is_err());
+        assert!(parse_label("=value").is_err());
+    }
+
+    /// Tests that repeated --label flags populate the output options and report.
+    #[test]
+    fn test_args_labels_in_output_options() {
+        let args = Args::try_parse_from([
+            "ruloc",
+            "--dir",
+            "src",
+            "--label",
+            "tier=critical",
+            "--label",
+            "team=payments",
+        ])
+        .unwrap();
+        let options = args.output_options().unwrap();
+
+        assert_eq!(
+            format_labels(&options.labels),
+            "team=payments, tier=critical"
+        );
+
+        let report: Report = Report {
+            labels: options.labels.clone(),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["labels"]["team"], "payments");
+        assert!(
+            serde_json::to_value(Report::<Vec<FileStats>>::default())
+                .unwrap()
+                .get("labels")
+                .is_none()
+        );
+
+        assert!(Args::try_parse_from(["ruloc", "--dir", "src", "--label", "bad"]).is_err());
+    }
+
+    /// Tests that plan analyses propagate labels to their arguments.
+    #[test]
+    fn test_planned_analysis_labels() {
+        let plan = parse_analysis_plan(
+            "[[analysis]]\ndir = \"src\"\n[analysis.labels]\nteam = \"core\"\n",
+        )
+        .unwrap();
+        let args = plan.analyses[0].to_args(0).unwrap();
+
+        assert_eq!(args.labels, vec![("team".to_string(), "core".to_string())]);
+    }
+
+    /// Tests marker_occurrences whole-word matching.
+    #[test]
+    fn test_marker_occurrences_whole_words() {
+        assert_eq!(marker_occurrences("// TODO: fix", "TODO"), vec![3]);
+        assert_eq!(
+            marker_occurrences("TODO(alice) and TODO", "TODO"),
+            vec![0, 16]
+        );
+        assert!(marker_occurrences("// TODOS MY_TODO TODO2", "TODO").is_empty());
+    }
+
+    /// Tests that the marker census only scans comments and records locations.
+    #[test]
+    fn test_compute_marker_census() {
+        let code = r#"// TODO: split this module
+fn todo_helper() {
+    let s = "FIXME in a string";
+    /* HACK: temporary
+       FIXME later */
+}
+/// XXX documented caveat
+fn other() {}
+"#;
+        let parsed = ParsedSource::parse(code, Edition::CURRENT);
+        let markers: Vec<String> = ["TODO", "FIXME", "HACK", "XXX"]
+            .iter()
+            .map(|marker| marker.to_string())
+            .collect();
+
+        let (counts, locations) =
+            compute_marker_census(&parsed.comments, code, &parsed.line_index, &markers, true);
+        assert_eq!(counts.get("TODO"), Some(&1));
+        assert_eq!(counts.get("FIXME"), Some(&1));
+        assert_eq!(counts.get("HACK"), Some(&1));
+        assert_eq!(counts.get("XXX"), Some(&1));
+
+        let lines: Vec<usize> = locations.iter().map(|location| location.line).collect();
+        assert_eq!(lines, vec![1, 4, 5, 7]);
+        assert_eq!(locations[0].text, "// TODO: split this module");
+        assert_eq!(locations[2].marker, "FIXME");
+
+        let (_, unlisted) =
+            compute_marker_census(&parsed.comments, code, &parsed.line_index, &markers, false);
+        assert!(unlisted.is_empty());
+    }
+
+    /// Tests marker options derived from arguments and summary aggregation.
+    #[test]
+    fn test_marker_options_and_summary() {
+        let args = Args::try_parse_from(["ruloc", "--dir", "src"]).unwrap();
+        let options = args.analysis_options().unwrap();
+        assert_eq!(options.markers, vec!["TODO", "FIXME", "HACK", "XXX"]);
+        assert!(!options.list_markers);
+
+        let custom = Args::try_parse_from([
+            "ruloc",
+            "--dir",
+            "src",
+            "--todo-markers",
+            "NOTE, BUG,",
+            "--todos",
+        ])
+        .unwrap()
+        .analysis_options()
+        .unwrap();
+        assert_eq!(custom.markers, vec!["NOTE", "BUG"]);
+        assert!(custom.list_markers);
+
+        let mut stats = make_minimal_test_file_stats();
+        stats.markers.insert("TODO".to_string(), 2);
+        let mut summary = Summary::default();
+        summary.add_file(&stats);
+        summary.add_file(&stats);
+        assert_eq!(summary.markers["TODO"], 4);
+        assert_eq!(format_marker_counts(&summary.markers), "TODO: 4");
+    }
+
+    /// Tests that find_long_functions reports functions above the threshold.
+    #[test]
+    fn test_find_long_functions() {
+        let code = r#"/// Short and documented.
+/// More docs that must not count.
+#[inline]
+fn short() {
+    let _ = 1;
+}
+
+struct Parser;
+
+impl Parser {
+    fn parse(&self) {
+        let a = 1;
+        let b = 2;
+        let _ = a + b;
+    }
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let line_index = LineIndex::new(code);
+
+        let long = find_long_functions(&root, &line_index, 3);
+        assert_eq!(long.len(), 1);
+        assert_eq!(long[0].name, "Parser::parse");
+        assert_eq!(long[0].start_line, 11);
+        assert_eq!(long[0].end_line, 15);
+        assert_eq!(long[0].lines, 5);
+
+        let all = find_long_functions(&root, &line_index, 2);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].name, "short");
+        assert_eq!(all[0].start_line, 4);
+        assert_eq!(all[0].lines, 3);
+
+        assert!(find_long_functions(&root, &line_index, 5).is_empty());
+    }
+
+    /// Tests long function reporting through analyze_file, the summary, and the gate.
+    #[test]
+    fn test_long_functions_summary_and_gate() {
+        let temp_file = std::env::temp_dir().join("test_ruloc_long_functions.rs");
+        fs::write(&temp_file, "fn long() {\n    let _ = 1;\n}\n").unwrap();
+
+        let options = AnalysisOptions {
+            max_fn_lines: Some(2),
+            ..Default::default()
+        };
+        let stats = analyze_file(&temp_file, &options).unwrap();
+        assert_eq!(stats.long_functions.len(), 1);
+        assert_eq!(
+            format_long_function(&stats.long_functions[0]),
+            "long (lines 1-3, 3 lines)"
+        );
+        assert!(
+            analyze_file(&temp_file, &AnalysisOptions::default())
+                .unwrap()
+                .long_functions
+                .is_empty()
+        );
+
+        let mut summary = Summary::default();
+        summary.add_file(&stats);
+        assert_eq!(summary.long_functions, 1);
+        assert_eq!(serde_json::to_value(&summary).unwrap()["long-functions"], 1);
+        let json = serde_json::to_value(Summary::default()).unwrap();
+        assert!(json.get("long-functions").is_none());
+
+        let report_only = Args {
+            max_fn_lines: Some(2),
+            ..Default::default()
+        };
+        assert!(check_gates(&report_only, &summary, &[]).is_ok());
+
+        let gated = Args {
+            max_fn_lines: Some(2),
+            fail_on_long_fns: true,
+            ..Default::default()
+        };
+        let err = check_gates(&gated, &summary, &[]).unwrap_err().to_string();
+        assert!(err.contains("1 function(s) exceed the maximum of 2 lines"));
+        assert!(check_gates(&gated, &Summary::default(), &[]).is_ok());
+
+        fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests that --fail-on-long-fns requires --max-fn-lines.
+    #[test]
+    fn test_args_fail_on_long_fns_requires_threshold() {
+        assert!(Args::try_parse_from(["ruloc", "--dir", "src", "--fail-on-long-fns"]).is_err());
+        assert!(
+            Args::try_parse_from([
+                "ruloc",
+                "--dir",
+                "src",
+                "--max-fn-lines",
+                "50",
+                "--fail-on-long-fns"
+            ])
+            .is_ok()
+        );
+    }
+
+    /// Tests parsing of tokei and cloc JSON output into external counts.
+    #[test]
+    fn test_parse_external_counter_json() {
+        let tokei = r#"{"Rust":{"blanks":10,"code":80,"comments":15,"inaccurate":false,"reports":[],"children":{}},"Total":{"blanks":10,"code":80,"comments":15}}"#;
+        assert_eq!(
+            parse_tokei_json(tokei).unwrap(),
+            ExternalCounts {
+                blank: 10,
+                comment: 15,
+                code: 80,
+            }
+        );
+
+        let cloc = r#"{"header":{"cloc_version":"2.00"},"Rust":{"nFiles":3,"blank":7,"comment":4,"code":50},"SUM":{"blank":7,"comment":4,"code":50,"nFiles":3}}"#;
+        assert_eq!(
+            parse_cloc_json(cloc).unwrap(),
+            ExternalCounts {
+                blank: 7,
+                comment: 4,
+                code: 50,
+            }
+        );
+
+        assert_eq!(parse_cloc_json("{}").unwrap(), ExternalCounts::default());
+        assert!(parse_tokei_json("not json").is_err());
+    }
+
+    /// Tests reconciliation rows, deltas, and cause attribution.
+    #[test]
+    fn test_reconcile_counts() {
+        let ruloc = make_line_stats(100, 10, 12, 3, 75);
+        let other = ExternalCounts {
+            blank: 10,
+            comment: 14,
+            code: 76,
+        };
+
+        let rows = reconcile_counts(&ruloc, &other);
+        assert_eq!(rows.len(), 4);
+
+        assert_eq!(rows[0].category, "Blank");
+        assert_eq!(rows[0].delta(), 0);
+        assert!(rows[0].reason.is_empty());
+
+        assert_eq!(rows[1].category, "Comment");
+        assert_eq!(rows[1].ruloc, 15);
+        assert_eq!(rows[1].delta(), 1);
+        assert!(!rows[1].reason.is_empty());
+
+        assert_eq!(rows[2].delta(), -1);
+        assert_eq!(rows[3].category, "Total");
+        assert_eq!(rows[3].delta(), 0);
+    }
+
+    /// Tests detection of files under tests/ and benches/ directories.
+    #[test]
+    fn test_is_test_directory_file() {
+        assert!(is_test_directory_file("tests/integration.rs"));
+        assert!(is_test_directory_file("crates/core/benches/parse.rs"));
+        assert!(!is_test_directory_file("src/tests.rs"));
+        assert!(!is_test_directory_file("src/main.rs"));
+    }
+
+    /// Tests parsing of the cross-check subcommand.
+    #[test]
+    fn test_args_cross_check_subcommand() {
+        let args = Args::try_parse_from(["ruloc", "cross-check", "--dir", "src", "--tool", "cloc"])
+            .unwrap();
+        match args.command {
+            Some(Command::CrossCheck { dir, tool }) => {
+                assert_eq!(dir, PathBuf::from("src"));
+                assert_eq!(tool, CrossCheckTool::Cloc);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+
+        let args = Args::try_parse_from(["ruloc", "cross-check", "--dir", "src"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::CrossCheck {
+                tool: CrossCheckTool::Tokei,
+                ..
+            })
+        ));
+    }
+
+    /// Tests formatting of line statistics deltas.
+    #[test]
+    fn test_format_line_stats_delta() {
+        let delta = LineStatsDelta::between(
+            &make_line_stats(10, 2, 3, 1, 4),
+            &make_line_stats(9, 2, 3, 1, 3),
+        );
+        assert_eq!(format_line_stats_delta(&delta), "all -1, code -1");
+        assert_eq!(format_line_stats_delta(&LineStatsDelta::default()), "none");
+    }
+
+    /// Tests impl block inventory extraction and trait name normalization.
+    #[test]
+    fn test_collect_impl_blocks() {
+        let code = r#"struct Parser;
+
+/// Inherent methods.
+impl Parser {
+    fn new() -> Self {
+        Parser
+    }
+}
+
+impl std::fmt::Display for Parser {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "parser")
+    }
+}
+
+impl From<u8> for Parser {
+    fn from(_: u8) -> Self { Parser }
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let impls = collect_impl_blocks(&root, &LineIndex::new(code));
+
+        assert_eq!(impls.len(), 3);
+        assert_eq!(impls[0].self_type, "Parser");
+        assert_eq!(impls[0].trait_name, None);
+        assert_eq!(impls[0].start_line, 4);
+        assert_eq!(impls[0].lines, 5);
+        assert_eq!(impls[1].trait_name.as_deref(), Some("Display"));
+        assert_eq!(impls[2].trait_name.as_deref(), Some("From"));
+        assert_eq!(impls[2].lines, 3);
+
+        assert_eq!(format_impl_block(&impls[0]), "Parser (line 4, 5 lines)");
+        assert_eq!(
+            format_impl_block(&impls[1]),
+            "Display for Parser (line 10, 5 lines)"
+        );
+    }
+
+    /// Tests crate-level trait inventory aggregation and ordering.
+    #[test]
+    fn test_trait_inventory() {
+        let block = |trait_name: Option<&str>| ImplBlock {
+            self_type: "T".to_string(),
+            trait_name: trait_name.map(str::to_string),
+            start_line: 1,
+            lines: 1,
+        };
+        let first = FileStats {
+            impls: vec![block(Some("Display")), block(None), block(Some("From"))],
+            ..Default::default()
+        };
+        let second = FileStats {
+            impls: vec![block(Some("From"))],
+            ..Default::default()
+        };
+
+        let mut summary = Summary::default();
+        summary.add_file(&first);
+        summary.add_file(&second);
+
+        assert_eq!(summary.trait_impls.len(), 2);
+        assert_eq!(summary.trait_impls["From"], 2);
+        assert_eq!(
+            format_trait_inventory(&summary.trait_impls, 2),
+            "  From: 2\n  Display: 1"
+        );
+
+        let json = serde_json::to_value(&first).unwrap();
+        assert_eq!(json["impls"][0]["trait"], "Display");
+        assert!(json["impls"][1].get("trait").is_none());
+    }
+
+    /// Tests that impl blocks are only inventoried with --impls.
+    #[test]
+    fn test_impls_opt_in() {
+        let temp_file = std::env::temp_dir().join("test_ruloc_impls_opt_in.rs");
+        fs::write(
+            &temp_file,
+            "struct S;\nimpl Clone for S {\n    fn clone(&self) -> Self { S }\n}\n",
+        )
+        .unwrap();
+
+        assert!(
+            analyze_file(&temp_file, &AnalysisOptions::default())
+                .unwrap()
+                .impls
+                .is_empty()
+        );
+
+        let args = Args::try_parse_from(["ruloc", "--file", "x.rs", "--impls"]).unwrap();
+        let stats = analyze_file(&temp_file, &args.analysis_options().unwrap()).unwrap();
+        assert_eq!(stats.impls.len(), 1);
+        assert_eq!(stats.impls[0].trait_name.as_deref(), Some("Clone"));
+
+        fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests that NDJSON output streams file records followed by a summary record.
+    #[test]
+    fn test_stream_ndjson_success() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_ndjson_success");
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(temp_dir.join("b.rs"), "// b\nfn b() {}\n").unwrap();
+
+        let args = Args {
+            dir: Some(temp_dir.clone()),
+            out_ndjson: true,
+            labels: vec![("team".to_string(), "core".to_string())],
+            ..Default::default()
+        };
+        assert_eq!(args.output_format(), OutputFormat::Ndjson);
+
+        let mut output = Vec::new();
+        let (summary, _) = stream_ndjson(&args, &AnalysisOptions::default(), &mut output).unwrap();
+        assert_eq!(summary.files, 2);
+
+        let records: Vec<serde_json::Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["type"], "file");
+        assert_eq!(records[1]["type"], "file");
+        assert!(records[0]["total"]["all-lines"].is_number());
+        assert_eq!(records[2]["type"], "summary");
+        assert_eq!(records[2]["files"], 2);
+        assert_eq!(records[2]["total"]["all-lines"], 3);
+        assert_eq!(records[2]["labels"]["team"], "core");
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that a failed NDJSON run ends with an incomplete trailer instead of a summary.
+    #[test]
+    fn test_stream_ndjson_failure_trailer() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_ndjson_failure");
+        fs::create_dir_all(&temp_dir).unwrap();
+
+        let args = Args {
+            dir: Some(temp_dir.clone()),
+            out_ndjson: true,
+            ..Default::default()
+        };
+
+        let mut output = Vec::new();
+        let err = stream_ndjson(&args, &AnalysisOptions::default(), &mut output)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("No Rust files found"));
+
+        let output = String::from_utf8(output).unwrap();
+        let records: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["type"], "incomplete");
+        assert_eq!(records[0]["files"], 0);
+        assert!(
+            records[0]["error"]
+                .as_str()
+                .unwrap()
+                .contains("No Rust files")
+        );
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that the NDJSON accumulator does not retain file statistics.
+    #[test]
+    fn test_ndjson_accumulator_does_not_retain_files() {
+        let mut accumulator = NdjsonAccumulator::new(Vec::new(), OutputOptions::default());
+        accumulator
+            .add_file(&make_minimal_test_file_stats())
+            .unwrap();
+
+        assert_eq!(accumulator.get_summary().files, 1);
+        assert!(accumulator.iter_files().is_err());
+        assert_eq!(
+            String::from_utf8_lossy(&accumulator.writer).lines().count(),
+            1
+        );
+    }
+
+    /// Tests that --out-ndjson conflicts with the other output formats.
+    #[test]
+    fn test_args_out_ndjson_conflicts() {
+        assert!(Args::try_parse_from(["ruloc", "--dir", "src", "--out-ndjson"]).is_ok());
+        assert!(
+            Args::try_parse_from(["ruloc", "--dir", "src", "--out-ndjson", "--out-json"]).is_err()
+        );
+        assert!(
+            Args::try_parse_from(["ruloc", "--dir", "src", "--out-ndjson", "--debug"]).is_err()
+        );
+    }
+
+    /// Tests counting of generic parameters, where clauses, and higher-ranked bounds.
+    #[test]
+    fn test_compute_type_complexity() {
+        let code = r#"struct Wrapper<'a, T, const N: usize> {
+    items: &'a [T; N],
+}
+
+impl<T: Clone> Clone for Wrapper<'_, T, 1> {
+    fn clone(&self) -> Self {
+        for item in self.items.iter() {
+            let _ = item;
+        }
+        todo!()
+    }
+}
+
+fn apply<F>(f: F)
+where
+    F: for<'x> Fn(&'x str) -> &'x str,
+{
+    let _ = f;
+}
+
+type Callback = Box<dyn for<'a> Fn(&'a u8)>;
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let complexity = compute_type_complexity(&root);
+
+        assert_eq!(complexity.generic_params, 5);
+        assert_eq!(complexity.where_clauses, 1);
+        assert_eq!(complexity.higher_ranked_bounds, 2);
+        assert_eq!(
+            format_type_complexity(&complexity),
+            "5 generic params, 1 where clauses, 2 higher-ranked bounds"
+        );
+    }
+
+    /// Tests that type complexity is opt-in and aggregated into the summary.
+    #[test]
+    fn test_type_complexity_opt_in_and_summary() {
+        let temp_file = std::env::temp_dir().join("test_ruloc_type_complexity.rs");
+        fs::write(&temp_file, "fn id<T>(value: T) -> T {\n    value\n}\n").unwrap();
+
+        let plain = analyze_file(&temp_file, &AnalysisOptions::default()).unwrap();
+        assert_eq!(plain.type_complexity, None);
+
+        let options = AnalysisOptions {
+            type_complexity: true,
+            ..Default::default()
+        };
+        let stats = analyze_file(&temp_file, &options).unwrap();
+        assert_eq!(stats.type_complexity.unwrap().generic_params, 1);
+
+        let mut summary = Summary::default();
+        summary.add_file(&plain);
+        assert_eq!(summary.type_complexity, None);
+        summary.add_file(&stats);
+        summary.add_file(&stats);
+        assert_eq!(summary.type_complexity.unwrap().generic_params, 2);
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["type-complexity"]["generic-params"], 2);
+
+        fs::remove_file(&temp_file).ok();
+    }
+
+    /// Tests that --jobs caps the thread pool and rejects zero.
+    #[test]
+    fn test_jobs_option() {
+        let args = Args::try_parse_from(["ruloc", "--dir", "src", "--jobs", "2"]).unwrap();
+        assert_eq!(args.analysis_options().unwrap().jobs, NonZeroUsize::new(2));
+        assert!(Args::try_parse_from(["ruloc", "--dir", "src", "--jobs", "0"]).is_err());
+
+        let temp_dir = std::env::temp_dir().join("test_ruloc_jobs");
+        fs::create_dir_all(&temp_dir).unwrap();
+        for index in 0..4 {
+            fs::write(temp_dir.join(format!("file{}.rs", index)), "fn main() {}\n").unwrap();
+        }
+
+        let options = AnalysisOptions {
+            jobs: NonZeroUsize::new(1),
+            ..Default::default()
+        };
+        let mut accumulator = InMemoryAccumulator::new();
+        analyze_directory(&temp_dir, &options, &mut accumulator).unwrap();
+        assert_eq!(accumulator.get_summary().files, 4);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that files are streamed in accumulation order, with or without --no-sort.
+    #[test]
+    fn test_ordered_report_sorting() {
+        let mut accumulator = InMemoryAccumulator::new();
+        for path in ["src/z.rs", "src/a.rs", "src/m.rs"] {
+            accumulator
+                .add_file(&FileStats {
+                    path: path.to_string(),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+
+        let sorted = OutputOptions::default();
+        let (_, files) = sorted.ordered_report(&accumulator).unwrap();
+        let paths: Vec<String> = files.map(|file| file.path).collect();
+        assert_eq!(paths, ["src/z.rs", "src/a.rs", "src/m.rs"]);
+
+        let unsorted = OutputOptions {
+            preserve_insertion_order: true,
+            ..Default::default()
+        };
+        let (_, files) = unsorted.ordered_report(&accumulator).unwrap();
+        let paths: Vec<String> = files.map(|file| file.path).collect();
+        assert_eq!(paths, ["src/z.rs", "src/a.rs", "src/m.rs"]);
+
+        let args = Args::try_parse_from(["ruloc", "--dir", "src", "--no-sort"]).unwrap();
+        assert!(args.output_options().unwrap().preserve_insertion_order);
+    }
+
+    /// Tests public item collection with qualification and visibility filtering.
+    #[test]
+    fn test_collect_public_items() {
+        let code = r#"pub fn top() {}
+
+fn private() {}
+
+pub(crate) struct Internal;
+
+pub struct Parser;
+
+impl Parser {
+    pub fn new() -> Self {
+        Parser
+    }
+
+    fn helper(&self) {}
+}
+
+pub mod nested {
+    pub const LIMIT: usize = 3;
+}
+
+pub trait Visitor {
+    fn visit(&self);
+}
+
+#[cfg(test)]
+mod tests {
+    pub fn hidden() {}
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let items = collect_public_items(&root, &LineIndex::new(code));
+        let names: Vec<(&str, &str)> = items
+            .iter()
+            .map(|item| (item.kind, item.name.as_str()))
+            .collect();
+
+        assert_eq!(
+            names,
+            [
+                ("fn", "top"),
+                ("struct", "Parser"),
+                ("fn", "Parser::new"),
+                ("const", "nested::LIMIT"),
+                ("trait", "Visitor"),
+            ]
+        );
+        assert_eq!(items[2].lines, 3);
+    }
+
+    /// Tests crate attribution of files from the manifests in a tree.
+    #[test]
+    fn test_owning_crate() {
+        let crate_dirs = vec![String::new(), "crates/core".to_string()];
+        assert_eq!(
+            owning_crate("crates/core/src/lib.rs", &crate_dirs),
+            "crates/core"
+        );
+        assert_eq!(owning_crate("src/main.rs", &crate_dirs), ".");
+        assert_eq!(owning_crate("crates/core2/src/lib.rs", &crate_dirs), ".");
+        assert_eq!(owning_crate("src/main.rs", &[]), ".");
+    }
+
+    /// Tests snapshot comparison of public items per crate.
+    #[test]
+    fn test_diff_api() {
+        let item = |name: &str| PublicItem {
+            name: name.to_string(),
+            kind: "fn",
+            lines: 2,
+            line: 1,
+            docs: DocVolume::default(),
+        };
+        let snapshot = |entries: &[(&str, &str)]| {
+            let mut snapshot = ApiSnapshot::new();
+            for (crate_dir, name) in entries {
+                snapshot
+                    .entry(crate_dir.to_string())
+                    .or_default()
+                    .insert(format!("src/lib.rs: fn {}", name), item(name));
+            }
+            snapshot
+        };
+
+        let from = snapshot(&[(".", "kept"), (".", "dropped"), ("crates/a", "same")]);
+        let to = snapshot(&[(".", "kept"), (".", "introduced"), ("crates/a", "same")]);
+
+        let changes = diff_api(&from, &to);
+        assert_eq!(changes.len(), 1);
+        let root_changes = &changes["."];
+        assert_eq!(root_changes.added.len(), 1);
+        assert_eq!(root_changes.added[0].0, "src/lib.rs: fn introduced");
+        assert_eq!(root_changes.removed[0].1.name, "dropped");
+
+        assert!(diff_api(&from, &from).is_empty());
+    }
+
+    /// Tests parsing of the api-diff subcommand and its defaults.
+    #[test]
+    fn test_args_api_diff_subcommand() {
+        let args = Args::try_parse_from(["ruloc", "api-diff", "--from", "v1.0"]).unwrap();
+        match args.command {
+            Some(Command::ApiDiff { from, to, dir }) => {
+                assert_eq!(from, "v1.0");
+                assert_eq!(to, "HEAD");
+                assert_eq!(dir, PathBuf::from("."));
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    /// Tests detection and resolution of include!-family targets.
+    #[test]
+    fn test_find_include_targets() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_include_targets");
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("generated.in"), "pub fn generated() {}\n").unwrap();
+
+        let code = r#"include!("generated.in");
+const README: &str = include_str!("missing.md");
+const BLOB: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/blob"));
+fn main() {
+    println!("not an include");
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let includes = find_include_targets(&root, &temp_dir.join("lib.rs"));
+
+        assert_eq!(includes.len(), 2);
+        assert_eq!(includes[0].macro_name, "include");
+        assert_eq!(includes[0].target, "generated.in");
+        assert!(includes[0].resolved.is_some());
+        assert_eq!(includes[1].macro_name, "include_str");
+        assert_eq!(includes[1].resolved, None);
+        assert_eq!(
+            format_include_target(&includes[1]),
+            "include_str!(\"missing.md\") -> (unresolved)"
+        );
+
+        let stats = FileStats {
+            includes,
+            ..Default::default()
+        };
+        let uncounted = uncounted_rust_includes(&stats, &SourceExtensions::default());
+        assert_eq!(uncounted, [temp_dir.join("generated.in")]);
+        let in_templates = SourceExtensions::new(&["rs".to_string(), "in".to_string()]);
+        assert!(uncounted_rust_includes(&stats, &in_templates).is_empty());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that --follow-includes counts included Rust sources exactly once.
+    #[test]
+    fn test_follow_includes_in_directory() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_follow_includes");
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("generated.in"), "pub fn generated() {}\n").unwrap();
+        fs::write(temp_dir.join("a.rs"), "include!(\"generated.in\");\n").unwrap();
+        fs::write(temp_dir.join("b.rs"), "include!(\"generated.in\");\n").unwrap();
+
+        let mut plain = InMemoryAccumulator::new();
+        analyze_directory(&temp_dir, &AnalysisOptions::default(), &mut plain).unwrap();
+        assert_eq!(plain.get_summary().files, 2);
+
+        let options = AnalysisOptions {
+            follow_includes: true,
+            ..Default::default()
+        };
+        let mut following = InMemoryAccumulator::new();
+        analyze_directory(&temp_dir, &options, &mut following).unwrap();
+        assert_eq!(following.get_summary().files, 3);
+        let names: Vec<String> = following
+            .iter_files()
+            .unwrap()
+            .map(|file| file.path.rsplit('/').next().unwrap().to_string())
+            .collect();
+        assert_eq!(names, ["a.rs", "generated.in", "b.rs"]);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that directory analysis accumulates files in path order, or as they finish
+    /// with --no-sort.
+    #[test]
+    fn test_analyze_directory_path_order() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_path_order");
+        for file in ["a.rs", "a/b.rs", "a-b/c.rs", "ab.rs", "Z.rs"] {
+            let path = temp_dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "fn f() {}\n").unwrap();
+        }
+
+        let relative_paths = |options: &AnalysisOptions| {
+            let mut accumulator = InMemoryAccumulator::new();
+            analyze_directory(&temp_dir, options, &mut accumulator).unwrap();
+            let prefix = format!("{}/", display_path(&temp_dir));
+            accumulator
+                .iter_files()
+                .unwrap()
+                .map(|file| file.path.strip_prefix(&prefix).unwrap().to_string())
+                .collect::<Vec<_>>()
+        };
+        let options = AnalysisOptions {
+            jobs: NonZeroUsize::new(4),
+            ..Default::default()
+        };
+        assert_eq!(
+            relative_paths(&options),
+            ["Z.rs", "a-b/c.rs", "a.rs", "a/b.rs", "ab.rs"]
+        );
+
+        let options = AnalysisOptions {
+            completion_order: true,
+            ..options
+        };
+        assert_eq!(relative_paths(&options).len(), 5);
+        let args = Args::try_parse_from(["ruloc", "--dir", "src", "--no-sort"]).unwrap();
+        assert!(args.analysis_options().unwrap().completion_order);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests the test naming audit against a configurable pattern.
+    #[test]
+    fn test_audit_test_names() {
+        let code = r#"fn helper() {}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parses() {}
+
+    #[test]
+    fn parses_empty_input() {}
+
+    #[tokio::test]
+    async fn fetches() {}
+
+    fn not_a_test() {}
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let pattern = Regex::new("^test_").unwrap();
+        let violations = audit_test_names(&root, &LineIndex::new(code), &pattern);
+
+        assert_eq!(
+            violations,
+            [
+                TestNameViolation {
+                    name: "parses_empty_input".to_string(),
+                    line: 9,
+                },
+                TestNameViolation {
+                    name: "fetches".to_string(),
+                    line: 12,
+                },
+            ]
+        );
+
+        let lenient = Regex::new("^(test_|parses|fetches)").unwrap();
+        assert!(audit_test_names(&root, &LineIndex::new(code), &lenient).is_empty());
+    }
+
+    /// Tests test naming flags, summary aggregation, and the failure gate.
+    #[test]
+    fn test_test_name_pattern_gate() {
+        assert!(
+            Args::try_parse_from(["ruloc", "--dir", "src", "--test-name-pattern", "("]).is_err()
+        );
+        assert!(Args::try_parse_from(["ruloc", "--dir", "src", "--fail-on-test-names"]).is_err());
+
+        let args = Args::try_parse_from([
+            "ruloc",
+            "--dir",
+            "src",
+            "--test-name-pattern",
+            "^test_",
+            "--fail-on-test-names",
+        ])
+        .unwrap();
+        assert!(args.analysis_options().unwrap().test_name_pattern.is_some());
+
+        let stats = FileStats {
+            test_name_violations: vec![TestNameViolation {
+                name: "bad".to_string(),
+                line: 3,
+            }],
+            ..Default::default()
+        };
+        let mut summary = Summary::default();
+        assert!(
+            serde_json::to_value(&summary)
+                .unwrap()
+                .get("test-name-violations")
+                .is_none()
+        );
+        summary.add_file(&stats);
+        assert_eq!(summary.test_name_violations, 1);
+
+        let err = check_gates(&args, &summary, &[]).unwrap_err().to_string();
+        assert!(err.contains("1 test function(s) do not match the pattern '^test_'"));
+        assert!(check_gates(&args, &Summary::default(), &[]).is_ok());
+    }
+
+    /// Tests parsing of git log output into newest per-path commit times.
+    #[test]
+    fn test_file_ages_parse_log() {
+        let log = "@300\nsrc/a.rs\n\n@200\nsrc/a.rs\nsrc/b.rs\n@100\nsrc/c.rs\n";
+        let last_modified = FileAges::parse_log(log);
+
+        assert_eq!(last_modified["src/a.rs"], 300);
+        assert_eq!(last_modified["src/b.rs"], 200);
+        assert_eq!(last_modified["src/c.rs"], 100);
+        assert_eq!(last_modified.len(), 3);
+    }
+
+    /// Tests age bucket classification and per-bucket summary aggregation.
+    #[test]
+    fn test_file_ages_buckets() {
+        let root = PathBuf::from("/nonexistent-ruloc-root");
+        let now = 1_000 * SECONDS_PER_DAY;
+        let ages = FileAges {
+            root: root.clone(),
+            last_modified: [
+                ("src/new.rs".to_string(), now - 10 * SECONDS_PER_DAY),
+                ("src/mid.rs".to_string(), now - 200 * SECONDS_PER_DAY),
+                ("src/old.rs".to_string(), now - 400 * SECONDS_PER_DAY),
+            ]
+            .into_iter()
+            .collect(),
+            now,
+        };
+
+        let path = |name: &str| root.join(name).to_string_lossy().to_string();
+        assert_eq!(ages.bucket_for(&path("src/new.rs")), "under-3-months");
+        assert_eq!(ages.bucket_for(&path("src/mid.rs")), "3-to-12-months");
+        assert_eq!(ages.bucket_for(&path("src/old.rs")), "over-1-year");
+        assert_eq!(ages.bucket_for(&path("src/uncommitted.rs")), "untracked");
+
+        let mut summary = Summary::default();
+        for bucket in ["over-1-year", "over-1-year", "untracked"] {
+            summary.add_file(&FileStats {
+                total: make_line_stats(10, 1, 1, 1, 7),
+                age_bucket: Some(bucket.to_string()),
+                ..Default::default()
+            });
+        }
+        assert_eq!(summary.age_buckets["over-1-year"].all_lines, 20);
+        assert_eq!(summary.age_buckets["untracked"].code_lines, 7);
+        assert!(!summary.age_buckets.contains_key("under-3-months"));
+    }
+
+    /// Tests module-graph discovery, including #[path], inline modules, and orphans.
+    #[test]
+    fn test_discover_module_graph() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_module_graph");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let src = temp_dir.join("src");
+        fs::create_dir_all(src.join("net")).unwrap();
+        fs::create_dir_all(src.join("outer")).unwrap();
+        fs::create_dir_all(src.join("platform")).unwrap();
+
+        fs::write(
+            src.join("lib.rs"),
+            "mod net;\nmod util;\nmod outer {\n    mod inner;\n}\n#[path = \"platform/unix.rs\"]\nmod sys;\n",
+        )
+        .unwrap();
+        fs::write(src.join("net").join("mod.rs"), "mod tcp;\n").unwrap();
+        fs::write(src.join("net").join("tcp.rs"), "pub fn connect() {}\n").unwrap();
+        fs::write(src.join("util.rs"), "mod helpers;\n").unwrap();
+        fs::create_dir_all(src.join("util")).unwrap();
+        fs::write(src.join("util").join("helpers.rs"), "fn h() {}\n").unwrap();
+        fs::write(src.join("outer").join("inner.rs"), "fn i() {}\n").unwrap();
+        fs::write(src.join("platform").join("unix.rs"), "fn u() {}\n").unwrap();
+        fs::write(src.join("stale.rs"), "fn unused() {}\n").unwrap();
+
+        let graph = discover_module_graph(&src.join("lib.rs"), SymlinkPolicy::default()).unwrap();
+        let reachable: HashSet<PathBuf> = graph.files.iter().cloned().collect();
+
+        assert_eq!(graph.files[0], src.join("lib.rs"));
+        assert_eq!(graph.files.len(), 7);
+        assert!(reachable.contains(&src.join("net").join("tcp.rs")));
+        assert!(reachable.contains(&src.join("util").join("helpers.rs")));
+        assert!(reachable.contains(&src.join("outer").join("inner.rs")));
+        assert!(reachable.contains(&src.join("platform").join("unix.rs")));
+        assert_eq!(graph.orphans, [src.join("stale.rs")]);
+        assert_eq!(
+            graph.orphan_paths(),
+            [src.join("stale.rs").to_string_lossy().to_string()]
+        );
+
+        assert!(discover_module_graph(&src.join("missing.rs"), SymlinkPolicy::default()).is_err());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests analysis through --from-entry and orphan reporting in the JSON report.
+    #[test]
+    fn test_from_entry_analysis() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_from_entry");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("main.rs"), "mod used;\nfn main() {}\n").unwrap();
+        fs::write(temp_dir.join("used.rs"), "pub fn used() {}\n").unwrap();
+        fs::write(temp_dir.join("orphan.rs"), "pub fn orphan() {}\n").unwrap();
+
+        let args = Args::try_parse_from([
+            "ruloc",
+            "--from-entry",
+            temp_dir.join("main.rs").to_str().unwrap(),
+        ])
+        .unwrap();
+        assert!(matches!(args.input_source(), InputSource::Entry(_)));
+        assert_eq!(args.analysis_root(), temp_dir);
+        assert!(
+            Args::try_parse_from(["ruloc", "--from-entry", "src/lib.rs", "--dir", "src"]).is_err()
+        );
+
+        let mut accumulator = InMemoryAccumulator::new();
+        let orphans = analyze_input(
+            &args.input_source(),
+            &AnalysisOptions::default(),
+            &mut accumulator,
+            None,
+        )
+        .unwrap()
+        .orphans;
+
+        assert_eq!(accumulator.get_summary().files, 2);
+        assert_eq!(orphans.len(), 1);
+        assert!(orphans[0].ends_with("orphan.rs"));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests CSV field escaping and row formatting.
+    #[test]
+    fn test_format_csv_row() {
+        assert_eq!(escape_csv_field("src/main.rs"), "src/main.rs");
+        assert_eq!(escape_csv_field("a,b.rs"), "\"a,b.rs\"");
+        assert_eq!(escape_csv_field("say \"hi\".rs"), "\"say \"\"hi\"\".rs\"");
+
+        let row = format_csv_row(&make_standard_test_file_stats());
+        assert_eq!(row.split(',').count(), CSV_STREAM_HEADER.split(',').count());
+    }
+
+    /// Tests that --stream-csv writes rows plus a completion trailer.
+    #[test]
+    fn test_stream_csv_complete() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_stream_csv_complete");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(temp_dir.join("b.rs"), "fn b() {}\n\n").unwrap();
+        let csv_path = std::env::temp_dir().join("test_ruloc_stream_csv_complete.csv");
+
+        let mut accumulator = InMemoryAccumulator::new();
+        analyze_input(
+            &InputSource::Directory(temp_dir.clone()),
+            &AnalysisOptions::default(),
+            &mut accumulator,
+            Some(&csv_path),
+        )
+        .unwrap();
+        assert_eq!(accumulator.get_summary().files, 2);
+
+        let csv = fs::read_to_string(&csv_path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], CSV_STREAM_HEADER);
+        assert!(lines[1..3].iter().all(|line| line.contains(".rs,")));
+        assert_eq!(lines[3], "# complete: 2 files");
+
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::remove_file(&csv_path).ok();
+    }
+
+    /// Tests that a failed run terminates the CSV stream with an incomplete trailer.
+    #[test]
+    fn test_stream_csv_incomplete() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_stream_csv_incomplete");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let csv_path = std::env::temp_dir().join("test_ruloc_stream_csv_incomplete.csv");
+
+        let mut accumulator = InMemoryAccumulator::new();
+        let result = analyze_input(
+            &InputSource::Directory(temp_dir.clone()),
+            &AnalysisOptions::default(),
+            &mut accumulator,
+            Some(&csv_path),
+        );
+        assert!(result.is_err());
+
+        let csv = fs::read_to_string(&csv_path).unwrap();
+        let last = csv.lines().last().unwrap();
+        assert!(last.starts_with("# incomplete: 0 files before error: No Rust files found"));
+
+        fs::remove_dir_all(&temp_dir).ok();
+        fs::remove_file(&csv_path).ok();
+    }
+
+    /// Tests crate root discovery from manifests and conventional layouts.
+    #[test]
+    fn test_manifest_crate_roots() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_manifest_roots");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("src").join("bin").join("tool")).unwrap();
+        fs::create_dir_all(temp_dir.join("tests")).unwrap();
+        fs::create_dir_all(temp_dir.join("custom")).unwrap();
+        for file in [
+            "src/main.rs",
+            "src/bin/extra.rs",
+            "src/bin/tool/main.rs",
+            "tests/it.rs",
+            "custom/gen.rs",
+            "custom/entry.rs",
+        ] {
+            fs::write(temp_dir.join(file), "fn main() {}\n").unwrap();
+        }
+
+        let manifest = r#"[package]
+name = "demo"
+build = "custom/gen.rs"
+
+[[bin]]
+name = "entry"
+path = "custom/entry.rs"
+"#;
+        let roots: HashSet<PathBuf> = manifest_crate_roots(&temp_dir, manifest)
+            .into_iter()
+            .collect();
+
+        assert_eq!(roots.len(), 6);
+        assert!(roots.contains(&temp_dir.join("src").join("main.rs")));
+        assert!(
+            roots.contains(
+                &temp_dir
+                    .join("src")
+                    .join("bin")
+                    .join("tool")
+                    .join("main.rs")
+            )
+        );
+        assert!(roots.contains(&temp_dir.join("custom").join("gen.rs")));
+        assert!(roots.contains(&temp_dir.join("custom/entry.rs")));
+        assert!(!roots.contains(&temp_dir.join("src").join("lib.rs")));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests workspace-wide dead file detection across member crates.
+    #[test]
+    fn test_find_dead_files() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_dead_files");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let member = temp_dir.join("crates").join("core");
+        fs::create_dir_all(member.join("src")).unwrap();
+        fs::create_dir_all(temp_dir.join("target").join("debug")).unwrap();
+
+        fs::write(
+            temp_dir.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/core\"]\n",
+        )
+        .unwrap();
+        fs::write(member.join("Cargo.toml"), "[package]\nname = \"core\"\n").unwrap();
+        fs::write(member.join("src").join("lib.rs"), "mod live;\n").unwrap();
+        fs::write(member.join("src").join("live.rs"), "pub fn live() {}\n").unwrap();
+        fs::write(member.join("src").join("abandoned.rs"), "fn gone() {}\n").unwrap();
+        fs::write(temp_dir.join("target").join("debug").join("out.rs"), "\n").unwrap();
+
+        let dead = find_dead_files(&temp_dir, SymlinkPolicy::default());
+        assert_eq!(dead.len(), 1);
+        assert!(dead[0].ends_with("abandoned.rs"));
+
+        let args =
+            Args::try_parse_from(["ruloc", "--dir", temp_dir.to_str().unwrap(), "--dead-files"])
+                .unwrap();
+        let mut accumulator = InMemoryAccumulator::new();
+        let orphans = analyze_input(
+            &args.input_source(),
+            &args.analysis_options().unwrap(),
+            &mut accumulator,
+            None,
+        )
+        .unwrap()
+        .orphans;
+        assert_eq!(orphans, dead);
+        assert!(Args::try_parse_from(["ruloc", "--file", "x.rs", "--dead-files"]).is_err());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests edition resolution from package, workspace, and default manifests.
+    #[test]
+    fn test_edition_resolver() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_edition_resolver");
+        let _ = fs::remove_dir_all(&temp_dir);
+        for dir in ["explicit/src", "inherited/src", "legacy/src"] {
+            fs::create_dir_all(temp_dir.join(dir)).unwrap();
+        }
+        fs::write(
+            temp_dir.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"*\"]\n\n[workspace.package]\nedition = \"2018\"\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.join("explicit").join("Cargo.toml"),
+            "[package]\nname = \"explicit\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.join("inherited").join("Cargo.toml"),
+            "[package]\nname = \"inherited\"\nedition.workspace = true\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.join("legacy").join("Cargo.toml"),
+            "[package]\nname = \"legacy\"\n",
+        )
+        .unwrap();
+
+        let resolver = EditionResolver::default();
+        let file = |dir: &str| temp_dir.join(dir).join("src").join("lib.rs");
+        assert_eq!(resolver.resolve(&file("explicit")), Edition::Edition2021);
+        assert_eq!(resolver.resolve(&file("inherited")), Edition::Edition2018);
+        assert_eq!(resolver.resolve(&file("legacy")), Edition::Edition2015);
+        assert_eq!(resolver.resolve(&temp_dir.join("lib.rs")), Edition::CURRENT);
+
+        fs::write(file("explicit"), "fn main() {}\n").unwrap();
+        let stats = analyze_file(&file("explicit"), &AnalysisOptions::default()).unwrap();
+        assert_eq!(stats.edition.as_deref(), Some("2021"));
+
+        // `--edition` overrides the manifests, e.g. for `async` used as an identifier
+        let forced = EditionResolver::new(Some(Edition::Edition2015));
+        assert_eq!(forced.resolve(&file("explicit")), Edition::Edition2015);
+        fs::write(file("explicit"), "fn async() {}\n").unwrap();
+        let with_edition = |edition| AnalysisOptions {
+            editions: Arc::new(EditionResolver::new(Some(edition))),
+            ..Default::default()
+        };
+        let stats = analyze_file(&file("explicit"), &with_edition(Edition::Edition2015)).unwrap();
+        assert_eq!(
+            (stats.edition.as_deref(), stats.parse_errors),
+            (Some("2015"), 0)
+        );
+        let stats = analyze_file(&file("explicit"), &with_edition(Edition::Edition2021)).unwrap();
+        assert!(stats.parse_errors > 0);
+
+        assert_eq!(parse_edition("2018").unwrap(), Edition::Edition2018);
+        assert!(
+            parse_edition("2019")
+                .unwrap_err()
+                .to_string()
+                .contains("expected 2015")
+        );
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that syntax errors mark a file, and the summary, as approximate.
+    #[test]
+    fn test_classification_marks_parse_errors_approximate() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_classification");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let clean = temp_dir.join("clean.rs");
+        let broken = temp_dir.join("broken.rs");
+        fs::write(&clean, "fn main() {}\n").unwrap();
+        fs::write(&broken, "fn main( {\n    let x = ;\n").unwrap();
+
+        let options = AnalysisOptions::default();
+        let clean_stats = analyze_file(&clean, &options).unwrap();
+        let broken_stats = analyze_file(&broken, &options).unwrap();
+        assert_eq!(clean_stats.classification, Classification::Exact);
+        assert_eq!(broken_stats.classification, Classification::Approximate);
+
+        let mut summary = Summary::default();
+        summary.add_file(&clean_stats);
+        summary.add_file(&broken_stats);
+        assert_eq!(summary.approximate_files, 1);
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap()["approximate-files"],
+            1
+        );
+        let json = serde_json::to_value(Summary::default()).unwrap();
+        assert!(json.get("approximate-files").is_none());
+        assert_eq!(summary.parse_error_files, 1);
+
+        let json = serde_json::to_value(&broken_stats).unwrap();
+        assert_eq!(json["classification"], "approximate");
+        assert!(broken_stats.parse_errors > 0);
+        assert_eq!(json["parse-errors"], broken_stats.parse_errors);
+        assert!(json.get("parse-error-sites").is_none());
+        assert!(
+            serde_json::to_value(&clean_stats)
+                .unwrap()
+                .get("parse-errors")
+                .is_none()
+        );
+
+        let listed = AnalysisOptions {
+            list_parse_errors: true,
+            ..Default::default()
+        };
+        let broken_stats = analyze_file(&broken, &listed).unwrap();
+        assert_eq!(
+            broken_stats.parse_error_sites.len(),
+            broken_stats.parse_errors
+        );
+        assert_eq!(broken_stats.parse_error_sites[0].line, 1);
+        assert!(!broken_stats.parse_error_sites[0].message.is_empty());
+        assert!(
+            analyze_file(&clean, &listed)
+                .unwrap()
+                .parse_error_sites
+                .is_empty()
+        );
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests symlink cycle protection and the external-link guard.
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_policy() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_symlink_policy");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let root = temp_dir.join("project");
+        let outside = temp_dir.join("outside");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(root.join("src").join("lib.rs"), "fn lib() {}\n").unwrap();
+        fs::write(outside.join("vendored.rs"), "fn vendored() {}\n").unwrap();
+        std::os::unix::fs::symlink(&root, root.join("src").join("loop")).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("external")).unwrap();
+        std::os::unix::fs::symlink(root.join("src").join("lib.rs"), root.join("alias.rs")).unwrap();
+
+        let walk = |policy: SymlinkPolicy| -> Vec<String> {
+            let mut names: Vec<String> = walk_dir(&root, policy)
+                .filter_map(walk_entry_ok)
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "rs"))
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(walk(SymlinkPolicy::default()), vec!["alias.rs", "lib.rs"]);
+        assert_eq!(
+            walk(SymlinkPolicy {
+                follow: true,
+                allow_external: true,
+            }),
+            vec!["alias.rs", "lib.rs", "vendored.rs"]
+        );
+        assert_eq!(
+            walk(SymlinkPolicy {
+                follow: false,
+                allow_external: true,
+            }),
+            vec!["lib.rs"]
+        );
+
+        let args = Args::try_parse_from([
+            "ruloc",
+            "--dir",
+            "src",
+            "--no-follow-symlinks",
+            "--follow-symlinks",
+        ])
+        .unwrap();
+        assert!(args.analysis_options().unwrap().symlinks.follow);
+        let args = Args::try_parse_from(["ruloc", "--dir", "src", "--no-follow-symlinks"]).unwrap();
+        assert!(!args.analysis_options().unwrap().symlinks.follow);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that annotating a saved report appends notes to the matching entry.
+    #[test]
+    fn test_run_annotate() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_annotate");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let report_path = temp_dir.join("report.json");
+        let report = Report {
+            files: vec![FileStats {
+                path: "src/big.rs".to_string(),
+                ..make_minimal_test_file_stats()
+            }],
+            ..Default::default()
+        };
+        fs::write(&report_path, serde_json::to_string(&report).unwrap()).unwrap();
+
+        run_annotate(&report_path, "src/big.rs", "split planned Q3").unwrap();
+        run_annotate(&report_path, "src/big.rs", "  owner: payments  ").unwrap();
+        assert!(run_annotate(&report_path, "src/missing.rs", "x").is_err());
+        assert!(run_annotate(&report_path, "src/big.rs", "   ").is_err());
+
+        let annotated: Report =
+            serde_json::from_str(&fs::read_to_string(&report_path).unwrap()).unwrap();
+        assert_eq!(
+            annotated.files[0].notes,
+            vec!["split planned Q3", "owner: payments"]
+        );
+
+        let args = Args::try_parse_from([
+            "ruloc",
+            "annotate",
+            "report.json",
+            "--path",
+            "src/big.rs",
+            "--note",
+            "split planned Q3",
+        ])
+        .unwrap();
+        assert!(matches!(args.command, Some(Command::Annotate { .. })));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests lossy decoding of invalid UTF-8 and the strict alternative.
+    #[test]
+    fn test_read_source_invalid_utf8() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_invalid_utf8");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let path = temp_dir.join("latin1.rs");
+        fs::write(&path, b"// caf\xe9 \xff\n fn main() {}\n").unwrap();
+
+        let (content, replaced) = read_source(&path, false).unwrap();
+        assert_eq!(replaced, 2);
+        assert!(content.contains('\u{FFFD}'));
+        assert!(read_source(&path, true).is_err());
+
+        let stats = analyze_file(&path, &AnalysisOptions::default()).unwrap();
+        assert_eq!(stats.replaced_bytes, 2);
+        assert_eq!(stats.classification, Classification::Approximate);
+        assert_eq!(stats.total.comment_lines, 1);
+        assert_eq!(stats.total.code_lines, 1);
+
+        let strict = AnalysisOptions {
+            strict_utf8: true,
+            ..Default::default()
+        };
+        assert!(analyze_file(&path, &strict).is_err());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests sink specification parsing.
+    #[test]
+    fn test_sink_spec_parse() {
+        assert_eq!(SinkSpec::parse("stdout").unwrap(), SinkSpec::Stdout);
+        assert_eq!(SinkSpec::parse("-").unwrap(), SinkSpec::Stdout);
+        assert_eq!(
+            SinkSpec::parse("https://metrics.example.com/loc").unwrap(),
+            SinkSpec::Http("https://metrics.example.com/loc".to_string())
+        );
+        assert_eq!(
+            SinkSpec::parse("file:out/report.json").unwrap(),
+            SinkSpec::File(PathBuf::from("out/report.json"))
+        );
+        assert_eq!(
+            SinkSpec::parse("report.json").unwrap(),
+            SinkSpec::File(PathBuf::from("report.json"))
+        );
+        assert!(SinkSpec::parse("").is_err());
+        assert_eq!(
+            SinkSpec::parse("sqlite:metrics.db").unwrap(),
+            SinkSpec::Sqlite(PathBuf::from("metrics.db"))
+        );
+        assert!(SinkSpec::parse("sqlite:").is_err());
+        assert_eq!(
+            SinkSpec::parse("s3://bucket/key").is_ok(),
+            cfg!(feature = "cloud-sinks")
+        );
+        assert_eq!(
+            SinkSpec::parse("gs://bucket/key").is_ok(),
+            cfg!(feature = "cloud-sinks")
+        );
+    }
+
+    /// Tests delivering one report to several file sinks and sink validation on the CLI.
+    #[test]
+    fn test_output_options_deliver_to_sinks() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_report_sinks");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let first = temp_dir.join("first.json");
+        let second = temp_dir.join("second.json");
+
+        let options = OutputOptions {
+            sinks: vec![
+                SinkSpec::File(first.clone()),
+                SinkSpec::File(temp_dir.join("missing").join("x.json")),
+                SinkSpec::File(second.clone()),
+            ],
+            ..Default::default()
+        };
+        let error = options
+            .deliver("{}", ReportKind::Json)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("missing"));
+        assert_eq!(fs::read_to_string(&first).unwrap(), "{}\n");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "{}\n");
+
+        assert!(Args::try_parse_from(["ruloc", "--dir", "src", "--sink", "a.json"]).is_err());
+        let args = Args::try_parse_from([
+            "ruloc",
+            "--dir",
+            "src",
+            "--out-json",
+            "--sink",
+            "a.json",
+            "--sink",
+            "-",
+        ])
+        .unwrap();
+        assert_eq!(args.output_options().unwrap().sinks.len(), 2);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests the SQL written for SQLite sinks and, with `sqlite3` installed, delivery to one.
+    #[test]
+    fn test_sqlite_sink() {
+        let script = sqlite_insert_script("{\"name\": \"it's\"}", "application/json");
+        assert!(script.starts_with("CREATE TABLE IF NOT EXISTS ruloc_reports ("));
+        assert!(script.ends_with(
+            "INSERT INTO ruloc_reports (content_type, report) VALUES ('application/json', '{\"name\": \"it''s\"}');\n"
+        ));
+
+        if std::process::Command::new("sqlite3")
+            .arg("-version")
+            .output()
+            .is_err()
+        {
+            return;
+        }
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = temp_dir.path().join("metrics.db");
+        let mut sink = SqliteSink {
+            path: database.clone(),
+        };
+        sink.deliver("{\"files\": 1}", ReportKind::Json).unwrap();
+        sink.deliver("{\"files\": 2}", ReportKind::Json).unwrap();
+        let output = std::process::Command::new("sqlite3")
+            .arg(&database)
+            .arg("SELECT content_type, json_extract(report, '$.files') FROM ruloc_reports ORDER BY id")
+            .output()
+            .unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "application/json|1\napplication/json|2\n"
+        );
+    }
+
+    /// Tests that planned sinks are parsed and require the JSON format.
+    #[test]
+    fn test_planned_analysis_sinks() {
+        let plan = parse_analysis_plan(
+            r#"
+[[analysis]]
+dir = "src"
+format = "json"
+sinks = ["target/loc.json", "https://metrics.example.com/loc"]
+
+[[analysis]]
+dir = "src"
+sinks = ["target/loc.txt"]
+"#,
+        )
+        .unwrap();
+
+        let args = plan.analyses[0].to_args(0).unwrap();
+        assert_eq!(
+            args.sinks,
+            vec![
+                SinkSpec::File(PathBuf::from("target/loc.json")),
+                SinkSpec::Http("https://metrics.example.com/loc".to_string()),
+            ]
+        );
+        let err = plan.analyses[1].to_args(1).unwrap_err();
+        assert!(err.to_string().contains("json"));
+    }
+
+    /// Tests that skipped files are reported with their reason and category.
+    #[test]
+    fn test_analyze_directory_reports_skipped_files() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_skipped_files");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("small.rs"), "fn a() {}\n").unwrap();
+        fs::write(temp_dir.join("large.rs"), "fn b() {}\n".repeat(50)).unwrap();
+        fs::write(temp_dir.join("latin1.rs"), b"// caf\xe9\n").unwrap();
+
+        let mut accumulator = InMemoryAccumulator::new();
+        let options = AnalysisOptions {
+            max_file_size: Some(100),
+            strict_utf8: true,
+            ..Default::default()
+        };
+        let skipped = analyze_directory(&temp_dir, &options, &mut accumulator).unwrap();
+
+        assert_eq!(accumulator.get_summary().files, 1);
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped[0].path.ends_with("large.rs"));
+        assert_eq!(skipped[0].category, SkipCategory::Oversized);
+        assert!(skipped[0].reason.contains("exceeds maximum size"));
+        assert!(skipped[1].path.ends_with("latin1.rs"));
+        assert_eq!(skipped[1].category, SkipCategory::InvalidUtf8);
+
+        let report: Report = Report {
+            skipped,
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["skipped"][0]["category"], "oversized");
+        assert_eq!(json["skipped"][1]["category"], "invalid-utf8");
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests the --strict gate and its interaction with --quiet-errors.
+    #[test]
+    fn test_check_gates_strict() {
+        let skipped = |category| SkippedFile {
+            path: "a.rs".to_string(),
+            reason: "boom".to_string(),
+            category,
+        };
+        let summary = Summary::default();
+
+        let strict = Args::try_parse_from(["ruloc", "--dir", "src", "--strict"]).unwrap();
+        let lenient = Args::try_parse_from(["ruloc", "--dir", "src"]).unwrap();
+        let err = check_gates(&strict, &summary, &[skipped(SkipCategory::Unreadable)])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("1 file(s)"));
+        assert!(check_gates(&strict, &summary, &[skipped(SkipCategory::Oversized)]).is_ok());
+        assert!(check_gates(&lenient, &summary, &[skipped(SkipCategory::Unreadable)]).is_ok());
+
+        let quiet = Args::try_parse_from(["ruloc", "--dir", "src", "--quiet-errors"]).unwrap();
+        assert!(quiet.analysis_options().unwrap().quiet_errors);
+        assert!(
+            Args::try_parse_from(["ruloc", "--dir", "src", "--strict", "--quiet-errors"]).is_err()
+        );
+    }
+
+    /// Tests generated-file heuristics and their separate summary category.
+    #[test]
+    fn test_generated_file_detection() {
+        let rules = GeneratedRules::new(
+            &["@generated".to_string(), "DO NOT EDIT".to_string()],
+            &["*.pb.rs".to_string(), "gen/**".to_string(), " ".to_string()],
+        )
+        .unwrap();
+        assert!(rules.matches(Path::new("src/proto/a.pb.rs"), ""));
+        assert!(rules.matches(Path::new("gen/deep/x.rs"), ""));
+        assert!(!rules.matches(Path::new("src/pb.rs"), ""));
+        assert!(rules.matches(Path::new("a.rs"), "// @generated by build.rs\nfn a() {}\n"));
+        assert!(!rules.matches(
+            Path::new("a.rs"),
+            &format!("{}// DO NOT EDIT\n", "\n".repeat(GENERATED_HEADER_LINES))
+        ));
+
+        let temp_dir = std::env::temp_dir().join("test_ruloc_generated_files");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("lib.rs"), "fn lib() {}\n").unwrap();
+        fs::write(temp_dir.join("api.pb.rs"), "fn a() {}\nfn b() {}\n").unwrap();
+
+        let args = Args::try_parse_from(["ruloc", "--dir", temp_dir.to_str().unwrap()]).unwrap();
+        let mut accumulator = InMemoryAccumulator::new();
+        analyze_directory(
+            &temp_dir,
+            &args.analysis_options().unwrap(),
+            &mut accumulator,
+        )
+        .unwrap();
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.total.code_lines, 1);
+        assert_eq!(summary.generated.code_lines, 2);
+
+        let args = Args::try_parse_from([
+            "ruloc",
+            "--dir",
+            temp_dir.to_str().unwrap(),
+            "--count-generated",
+        ])
+        .unwrap();
+        let mut accumulator = InMemoryAccumulator::new();
+        analyze_directory(
+            &temp_dir,
+            &args.analysis_options().unwrap(),
+            &mut accumulator,
+        )
+        .unwrap();
+        let summary = accumulator.get_summary();
+        assert_eq!(summary.production.code_lines, 3);
+        assert_eq!(summary.generated, LineStats::default());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that benchmark code is split from production and test code.
+    #[test]
+    fn test_bench_classification() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_bench_classification");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("benches")).unwrap();
+        let lib = temp_dir.join("lib.rs");
+        fs::write(
+            &lib,
+            "fn work() {}\n\n#[bench]\nfn bench_work(b: &mut Bencher) {\n    b.iter(work);\n}\n\n#[cfg(test)]\nmod tests {\n    #[bench]\n    fn inner(b: &mut Bencher) {}\n\n    #[test]\n    fn t() {}\n}\n",
+        )
+        .unwrap();
+        let harness = temp_dir.join("benches").join("speed.rs");
+        fs::write(
+            &harness,
+            "fn speed(c: &mut Criterion) {}\ncriterion_group!(benches, speed);\ncriterion_main!(benches);\n",
+        )
+        .unwrap();
+
+        let stats = analyze_file(&lib, &AnalysisOptions::default()).unwrap();
+        assert_eq!(stats.bench.code_lines, 6);
+        assert_eq!(stats.production.code_lines, 1);
+        assert_eq!(stats.test.code_lines, 5);
+
+        let stats = analyze_file(&harness, &AnalysisOptions::default()).unwrap();
+        assert_eq!(stats.bench.all_lines, 3);
+        assert_eq!(stats.production, LineStats::default());
+
+        let root = SourceFile::parse(
+            "criterion_main!(benches);\nfn main() {}\n",
+            Edition::CURRENT,
+        )
+        .syntax_node();
+        assert_eq!(
+            classify_bench_lines(
+                Path::new("src/main.rs"),
+                &root,
+                "criterion_main!(benches);\nfn main() {}\n"
+            ),
+            vec![true, false]
+        );
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests the --include-docs roll-up of README and doc/ markdown per crate.
+    #[test]
+    fn test_include_docs_rollup() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_include_docs");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(temp_dir.join("src")).unwrap();
+        fs::create_dir_all(temp_dir.join("docs").join("guide")).unwrap();
+        fs::create_dir_all(temp_dir.join("target")).unwrap();
+
+        fs::write(temp_dir.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        fs::write(temp_dir.join("README.md"), "# Demo\n\nA small crate.\n").unwrap();
+        fs::write(
+            temp_dir.join("docs").join("guide").join("intro.md"),
+            "Getting started\n",
+        )
+        .unwrap();
+        fs::write(temp_dir.join("docs").join("notes.txt"), "ignored\n").unwrap();
+        fs::write(temp_dir.join("target").join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(
+            temp_dir.join("src").join("lib.rs"),
+            "/// Adds.\n/// Really.\npub fn add() {}\n",
+        )
+        .unwrap();
+
+        let crates = count_markdown_docs(&temp_dir, SymlinkPolicy::default());
+        assert_eq!(crates.len(), 1);
+        let docs = &crates[&temp_dir.display().to_string()];
+        assert_eq!(
+            docs,
+            &MarkdownDocs {
+                files: 2,
+                lines: 3,
+                words: 7,
+            }
+        );
+
+        let args = Args::try_parse_from([
+            "ruloc",
+            "--dir",
+            temp_dir.to_str().unwrap(),
+            "--include-docs",
+        ])
+        .unwrap();
+        let mut accumulator = InMemoryAccumulator::new();
+        let outcome = analyze_input(
+            &args.input_source(),
+            &args.analysis_options().unwrap(),
+            &mut accumulator,
+            None,
+        )
+        .unwrap();
+        let mut options = OutputOptions::default();
+        options.record(outcome);
+        let documentation = options.documentation(&accumulator.get_summary()).unwrap();
+        assert_eq!(documentation.rustdoc_lines, 2);
+        assert_eq!(documentation.lines, 5);
+
+        let json = serde_json::to_value(&documentation).unwrap();
+        assert_eq!(json["rustdoc-lines"], 2);
+        assert_eq!(json["markdown"]["words"], 7);
+        assert!(
+            OutputOptions::default()
+                .documentation(&Summary::default())
+                .is_none()
+        );
+        assert!(Args::try_parse_from(["ruloc", "--file", "x.rs", "--include-docs"]).is_err());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests user-supplied attribute rules classifying test, bench, and generated code.
+    #[test]
+    fn test_attribute_classification_rules() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_classification_rules");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let rules_path = temp_dir.join("rules.toml");
+        fs::write(
+            &rules_path,
+            "[[rule]]\nattribute = \"wasm_bindgen_test\"\ncategory = \"test\"\n\n\
+             [[rule]]\nattribute = \"divan::bench\"\ncategory = \"bench\"\n\n\
+             [[rule]]\nattribute = \"builder\"\ncategory = \"generated\"\n",
+        )
+        .unwrap();
+        let source = temp_dir.join("lib.rs");
+        fs::write(
+            &source,
+            "fn prod() {}\n\n#[wasm_bindgen_test]\nfn browser() {\n    prod();\n}\n\n\
+             #[divan::bench]\nfn fast() {}\n",
+        )
+        .unwrap();
+
+        let args = Args::try_parse_from([
+            "ruloc",
+            "--file",
+            source.to_str().unwrap(),
+            "--rules",
+            rules_path.to_str().unwrap(),
+        ])
+        .unwrap();
+        let options = args.analysis_options().unwrap();
+        let stats = analyze_file(&source, &options).unwrap();
+        assert_eq!(stats.production.code_lines, 1);
+        assert_eq!(stats.test.code_lines, 4);
+        assert_eq!(stats.bench.code_lines, 2);
+        assert!(!stats.generated);
+
+        let builder = temp_dir.join("builder.rs");
+        fs::write(&builder, "#[builder]\nstruct Config;\n").unwrap();
+        assert!(analyze_file(&builder, &options).unwrap().generated);
+
+        let without_rules =
+            Args::try_parse_from(["ruloc", "--file", source.to_str().unwrap()]).unwrap();
+        let without_rules = without_rules.analysis_options().unwrap();
+        assert!(options.rules.assigns(RuleCategory::Bench));
+        assert!(!without_rules.rules.assigns(RuleCategory::Bench));
+        let stats = analyze_file(&source, &without_rules).unwrap();
+        assert_eq!(stats.test.code_lines, 0);
+
+        fs::write(
+            &rules_path,
+            "[[rule]]\nattribute = \"x\"\ncategory = \"docs\"\n",
+        )
+        .unwrap();
+        assert!(ClassificationRules::load(&rules_path).is_err());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests column alignment and the totals rule of the summary table.
+    #[test]
+    fn test_format_table() {
+        let rows = vec![
+            table_row(
+                "src/lib.rs".to_string(),
+                &make_line_stats(20, 2, 1, 3, 14),
+                &make_line_stats(8, 1, 0, 0, 7),
+            ),
+            table_row(
+                "src/empty.rs".to_string(),
+                &make_line_stats(1, 1, 0, 0, 0),
+                &LineStats::default(),
+            ),
+        ];
+        assert_eq!(rows[0][5], "50.0%");
+        assert_eq!(rows[1][5], "-");
+
+        let totals = table_row(
+            "Total (2 files)".to_string(),
+            &make_line_stats(21, 3, 1, 3, 14),
+            &make_line_stats(8, 1, 0, 0, 7),
+        );
+        let table = format_table(&TABLE_HEADERS, &rows, &totals, false);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "File             Code  Comments  Docs  Blanks  Test %",
+                "src/lib.rs         14         1     3       2   50.0%",
+                "src/empty.rs        0         0     0       1       -",
+                "-----------------------------------------------------",
+                "Total (2 files)    14         1     3       3   50.0%",
+            ]
+        );
+    }
+
+    /// Tests that --help-json describes value types, defaults, and conflicts.
+    #[test]
+    fn test_help_json_describes_cli() {
+        let mut command = Args::command();
+        command.build();
+        let description = describe_command(&command);
+        assert_eq!(description.name, "ruloc");
+        assert_eq!(
+            description.version.as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+
+        let arg = |name: &str| {
+            description
+                .args
+                .iter()
+                .find(|arg| arg.name == name)
+                .unwrap()
+                .clone()
+        };
+        let dir = arg("dir");
+        assert_eq!(dir.short, Some('d'));
+        assert_eq!(dir.value_type, "path");
+        assert_eq!(arg("max-fn-lines").value_type, "integer");
+        assert_eq!(arg("verbose").value_type, "boolean");
+        assert_eq!(arg("todo-markers").default, [DEFAULT_TODO_MARKERS]);
+        assert!(arg("todo-markers").multiple);
+        assert!(arg("help-json").exclusive);
+        assert!(
+            arg("out-html")
+                .conflicts_with
+                .contains(&"debug".to_string())
+        );
+        assert_eq!(
+            description.required_groups,
+            [["file", "dir", "from-entry", "git", "help-json"]]
+        );
+
+        let run = description
+            .subcommands
+            .iter()
+            .find(|subcommand| subcommand.name == "run")
+            .unwrap();
+        assert!(
+            run.args
+                .iter()
+                .any(|arg| arg.name == "plan" && arg.positional)
+        );
+        let cross_check = description
+            .subcommands
+            .iter()
+            .find(|subcommand| subcommand.name == "cross-check")
+            .unwrap();
+        let tool = cross_check
+            .args
+            .iter()
+            .find(|arg| arg.name == "tool")
+            .unwrap();
+        assert_eq!(tool.value_type, "enum");
+        assert!(tool.possible_values.contains(&"tokei".to_string()));
+
+        assert!(
+            Args::try_parse_from(["ruloc", "--help-json"])
+                .unwrap()
+                .help_json
+        );
+        assert!(Args::try_parse_from(["ruloc", "--help-json", "--verbose"]).is_err());
+    }
+
+    /// Tests relative and absolute rendering of reported paths.
+    #[test]
+    fn test_path_normalizer() {
+        let relative = PathNormalizer {
+            style: PathStyle::Relative,
+            root: PathBuf::from("crates/core"),
+        };
+        assert_eq!(relative.normalize("crates/core/src/lib.rs"), "src/lib.rs");
+        assert_eq!(relative.normalize("other/src/lib.rs"), "other/src/lib.rs");
+        assert_eq!(relative.normalize("crates/core"), "crates/core");
+
+        let current = PathNormalizer {
+            style: PathStyle::Relative,
+            root: PathBuf::from("."),
+        };
+        assert_eq!(current.normalize("./src/main.rs"), "src/main.rs");
+
+        let absolute = PathNormalizer {
+            style: PathStyle::Absolute,
+            root: PathBuf::from("."),
+        };
+        let normalized = absolute.normalize("src/main.rs");
+        assert!(Path::new(&normalized).is_absolute());
+        assert!(normalized.ends_with("/src/main.rs"));
+
+        let args = Args::try_parse_from(["ruloc", "--dir", "src", "--absolute-paths"]).unwrap();
+        let paths = args.output_options().unwrap().paths.unwrap();
+        assert_eq!(paths.style, PathStyle::Absolute);
+        let args = Args::try_parse_from([
+            "ruloc",
+            "--dir",
+            "src",
+            "--absolute-paths",
+            "--relative-paths",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.output_options().unwrap().paths.unwrap().style,
+            PathStyle::Relative
+        );
+
+        let mut options = OutputOptions {
+            paths: Some(relative),
+            ..Default::default()
+        };
+        options.record(InputOutcome {
+            orphans: vec!["crates/core/src/dead.rs".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(options.orphans, ["src/dead.rs"]);
+    }
+
+    /// Tests that CRLF and LF checkouts of a file produce identical statistics.
+    #[test]
+    fn test_crlf_matches_lf() {
+        let lf = "//! Crate docs.\n\n/// Adds.\npub fn add(a: i32) -> i32 {\n    // TODO: overflow\n    \
+                  a /* inline */ + 1\n}\n\n/*\n * Block\n */\nconst S: &str = r\"one\n\ntwo\";\n\n\
+                  #[cfg(test)]\nmod tests {\n    #[test]\n    fn t() {}\n}\n";
+        let crlf = lf.replace('\n', "\r\n");
+
+        assert_eq!(
+            analyze_lines(&crlf, Edition::CURRENT),
+            analyze_lines(lf, Edition::CURRENT)
+        );
+        assert_eq!(
+            classify_lines(&crlf, Edition::CURRENT),
+            classify_lines(lf, Edition::CURRENT)
+        );
+
+        let temp_dir = std::env::temp_dir().join("test_ruloc_crlf");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("lf.rs"), lf).unwrap();
+        fs::write(temp_dir.join("crlf.rs"), &crlf).unwrap();
+
+        let options = AnalysisOptions {
+            markers: vec!["TODO".to_string()],
+            list_markers: true,
+            ..Default::default()
+        };
+        let lf_stats = analyze_file(&temp_dir.join("lf.rs"), &options).unwrap();
+        let crlf_stats = analyze_file(&temp_dir.join("crlf.rs"), &options).unwrap();
+        assert_eq!(crlf_stats.classification, Classification::Exact);
+        assert_eq!(
+            FileStats {
+                path: lf_stats.path.clone(),
+                ..crlf_stats
+            },
+            lf_stats
+        );
+        assert_eq!(lf_stats.total.all_lines, 20);
+        assert_eq!(lf_stats.marker_locations[0].text, "// TODO: overflow");
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that displayed paths use forward slashes.
+    #[test]
+    fn test_display_path_uses_forward_slashes() {
+        let path = Path::new("crates").join("core").join("lib.rs");
+        assert_eq!(display_path(&path), "crates/core/lib.rs");
+        assert_eq!(display_path(Path::new("a.rs")), "a.rs");
+    }
+
+    /// Tests RFC 3339 formatting of epoch seconds, including a leap day.
+    #[test]
+    fn test_format_utc_timestamp() {
+        assert_eq!(format_utc_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_utc_timestamp(1_709_294_400), "2024-03-01T12:00:00Z");
+        assert_eq!(format_utc_timestamp(4_102_444_799), "2099-12-31T23:59:59Z");
+    }
+
+    /// Tests that the metadata block is omitted unless present.
+    #[test]
+    fn test_report_metadata_is_optional() {
+        let report: Report = Report::default();
+        let json = serde_json::to_value(&report).unwrap();
+        assert!(json.get("metadata").is_none());
+
+        let report: Report = Report {
+            metadata: Some(RunMetadata {
+                tool_version: "1.0.0".to_string(),
+                git_branch: Some("main".to_string()),
+                duration_ms: 42,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["metadata"]["tool-version"], "1.0.0");
+        assert_eq!(json["metadata"]["git-branch"], "main");
+        assert_eq!(json["metadata"]["duration-ms"], 42);
+        assert!(json["metadata"].get("git-commit").is_none());
+    }
+
+    /// Tests collection and formatting of --timings phases and slowest files.
+    #[test]
+    fn test_timings_collected_per_phase_and_file() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_timings");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(temp_dir.join("b.rs"), "fn b() {}\n").unwrap();
+
+        let args = Args::try_parse_from([
+            "ruloc",
+            "--dir",
+            temp_dir.to_str().unwrap(),
+            "--timings",
+            "--timings-top",
+            "1",
+        ])
+        .unwrap();
+        let options = args.analysis_options().unwrap();
+        let mut acc = InMemoryAccumulator::new();
+        analyze_input(&args.input_source(), &options, &mut acc, None).unwrap();
+
+        let timings = options.timings.as_ref().unwrap();
+        assert_eq!(timings.files.lock().unwrap().len(), 2);
+        assert!(timings.phase(TimingPhase::Parse) > Duration::ZERO);
+
+        let output = format_timings(timings, Duration::from_millis(1500), args.timings_top);
+        assert!(output.starts_with("Timings (wall 1.500s;"));
+        for phase in TimingPhase::ALL {
+            assert!(output.contains(&format!("\n  {:<10} ", phase.name())));
+        }
+        let slowest = output.split("Slowest files:\n").nth(1).unwrap();
+        assert_eq!(slowest.lines().count(), 1);
+
+        assert!(Args::try_parse_from(["ruloc", "--file", "a.rs", "--timings-top", "3"]).is_err());
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that memory-mapped and read sources decode to identical statistics.
+    #[test]
+    fn test_mmap_matches_read() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_mmap");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let path = temp_dir.join("generated.rs");
+        let content = "/// Generated.\npub const VALUE: u32 = 1;\n\n".repeat(2_000);
+        assert!(content.len() as u64 >= MMAP_MIN_BYTES);
+        fs::write(&path, &content).unwrap();
+
+        let bytes = SourceBytes::load(&path, true).unwrap();
+        assert_eq!(
+            matches!(bytes, SourceBytes::Read(_)),
+            !cfg!(feature = "mmap")
+        );
+        let (decoded, replaced) = decode_source(&path, &bytes, true).unwrap();
+        assert!(matches!(decoded, Cow::Borrowed(_)));
+        assert_eq!((decoded.as_ref(), replaced), (content.as_str(), 0));
+
+        let mapped = AnalysisOptions {
+            mmap: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            analyze_file(&path, &mapped).unwrap(),
+            analyze_file(&path, &AnalysisOptions::default()).unwrap()
+        );
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that one parse yields the same line types and test lines as the wrappers.
+    #[test]
+    fn test_parsed_source_single_pass() {
+        let code = "/// Adds.\nfn add() {}\r\n\n#[cfg(test)]\nmod tests {\n    // check\n    #[test]\n    fn t() {}\n}\n";
+        let parsed = ParsedSource::parse(code, Edition::CURRENT);
+
+        assert!(!parsed.content.contains('\r'));
+        assert!(parsed.parse.errors().is_empty());
+        assert_eq!(parsed.line_index.total_lines, 9);
+        assert_eq!(parsed.line_types, analyze_lines(code, Edition::CURRENT));
+        assert_eq!(parsed.is_test_line, classify_lines(code, Edition::CURRENT));
+        assert_eq!(
+            parsed.line_types[..3],
+            [LineType::Rustdoc, LineType::Code, LineType::Blank]
+        );
+        assert_eq!(parsed.line_types[5], LineType::Comment);
+        assert_eq!(
+            parsed.is_test_line,
+            [false, false, true, true, true, true, true, true, true]
+        );
+    }
+
+    /// Tests that the writer thread accumulates every file exactly once under parallelism.
+    #[test]
+    fn test_parallel_results_reach_accumulator_once() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_writer_thread");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        for i in 0..64 {
+            fs::write(temp_dir.join(format!("f{}.rs", i)), "fn f() {}\n").unwrap();
+        }
+
+        let options = AnalysisOptions {
+            jobs: NonZeroUsize::new(4),
+            ..Default::default()
+        };
+        let mut acc = InMemoryAccumulator::new();
+        let skipped = analyze_directory(&temp_dir, &options, &mut acc).unwrap();
+
+        assert!(skipped.is_empty());
+        let summary = acc.get_summary();
+        assert_eq!(summary.files, 64);
+        assert_eq!(summary.total.code_lines, 64);
+        let paths: HashSet<String> = acc.iter_files().unwrap().map(|file| file.path).collect();
+        assert_eq!(paths.len(), 64);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that a report streaming its files serializes exactly like a collected one.
+    #[test]
+    fn test_streamed_report_matches_report() {
+        let mut file = make_minimal_test_file_stats();
+        file.path = "src/lib.rs".to_string();
+        let report = Report {
+            summary: Summary {
+                files: 1,
+                ..Default::default()
+            },
+            files: vec![file.clone(), file],
+            revision: Some("abc123".to_string()),
+            labels: BTreeMap::from([("team".to_string(), "core".to_string())]),
+            orphans: vec!["src/orphan.rs".to_string()],
+            metadata: Some(RunMetadata::default()),
+            sample: Some(SampleEstimate::default()),
+            ..Default::default()
+        };
+        let streamed = Report {
+            summary: report.summary.clone(),
+            files: SerializeIter::new(report.files.clone().into_iter()),
+            revision: report.revision.clone(),
+            labels: report.labels.clone(),
+            orphans: report.orphans.clone(),
+            skipped: report.skipped.clone(),
+            documentation: report.documentation.clone(),
+            languages: report.languages.clone(),
+            metadata: report.metadata.clone(),
+            sample: report.sample.clone(),
+        };
+
+        let mut output = Vec::new();
+        write_json_report(&mut output, &streamed).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            format!("{}\n", serde_json::to_string_pretty(&report).unwrap())
+        );
+        assert!(serde_json::to_string(&streamed).is_err());
+    }
+
+    /// Tests that the spilling accumulator moves to disk past its threshold without loss.
+    #[test]
+    fn test_spilling_accumulator() {
+        let file = |name: &str| make_simple_file_stats(name, 3, 1, 0, 0, 2);
+        let mut acc = SpillingAccumulator {
+            threshold: Some(2),
+            ..SpillingAccumulator::new(AccumulatorKind::Memory).unwrap()
+        };
+
+        acc.add_file(&file("a.rs")).unwrap();
+        acc.add_file(&file("b.rs")).unwrap();
+        assert!(acc.disk.is_none());
+        acc.add_file(&file("c.rs")).unwrap();
+        assert!(acc.disk.is_some());
+        assert!(acc.memory.files.is_empty());
+        acc.add_file(&file("d.rs")).unwrap();
+        acc.flush().unwrap();
+
+        let summary = acc.get_summary();
+        assert_eq!(summary.files, 4);
+        assert_eq!(summary.total.code_lines, 8);
+        let paths: Vec<String> = acc.iter_files().unwrap().map(|f| f.path).collect();
+        assert_eq!(paths, ["a.rs", "b.rs", "c.rs", "d.rs"]);
+
+        assert!(
+            SpillingAccumulator::new(AccumulatorKind::Disk)
+                .unwrap()
+                .disk
+                .is_some()
+        );
+        let args = Args::try_parse_from(["ruloc", "--file", "a.rs"]).unwrap();
+        assert_eq!(args.accumulator, AccumulatorKind::Auto);
+        let args =
+            Args::try_parse_from(["ruloc", "--file", "a.rs", "--accumulator", "memory"]).unwrap();
+        assert_eq!(args.accumulator, AccumulatorKind::Memory);
+    }
+
+    /// Tests that errors map to their category's exit code and keep their source chain.
+    #[test]
+    fn test_ruloc_error_exit_codes() {
+        use std::error::Error;
+
+        let missing = Path::new("/nonexistent/ruloc/missing.rs");
+        let io = analyze_file(missing, &AnalysisOptions::default()).unwrap_err();
+        assert_eq!(io.exit_code(), 3);
+        assert!(io.to_string().starts_with("Failed to read file '"));
+        assert!(io.source().unwrap().is::<std::io::Error>());
+        assert_eq!(SkipCategory::of(&io), SkipCategory::Unreadable);
+
+        let wrapped = io.context("Analysis 'core' failed");
+        assert_eq!(wrapped.exit_code(), 3);
+        assert!(
+            wrapped
+                .to_string()
+                .starts_with("Analysis 'core' failed: Failed to read")
+        );
+        let source = wrapped.source().unwrap();
+        assert!(source.source().unwrap().is::<std::io::Error>());
+
+        let oversized = RulocError::FileTooLarge {
+            path: PathBuf::from("big.rs"),
+            size: 20,
+            limit: 10,
+        };
+        assert_eq!(oversized.exit_code(), 1);
+        assert!(
+            oversized
+                .to_string()
+                .contains("exceeds maximum size limit (20 bytes > 10 bytes)")
+        );
+        assert_eq!(SkipCategory::of(&oversized), SkipCategory::Oversized);
+
+        assert_eq!(RulocError::Config(String::new()).exit_code(), 2);
+        assert_eq!(RulocError::Parse(String::new()).exit_code(), 4);
+        assert_eq!(RulocError::Git(String::new()).exit_code(), 5);
+        assert_eq!(RulocError::Output(String::new()).exit_code(), 6);
+        assert_eq!(
+            SkipCategory::of(&RulocError::Parse(String::new())),
+            SkipCategory::Unreadable
+        );
+
+        let invalid = decode_source(Path::new("latin1.rs"), b"// caf\xe9\n", true).unwrap_err();
+        assert_eq!(invalid.exit_code(), 4);
+        assert!(invalid.to_string().contains("(1 invalid bytes)"));
+        assert_eq!(SkipCategory::of(&invalid), SkipCategory::InvalidUtf8);
+    }
+
+    /// Tests that an analysis sink receives every file, the skipped files and orphans, and
+    /// the final summary once.
+    #[test]
+    fn test_analyze_into_sink() {
+        /// Sink recording what it receives; `Cell` makes it `!Sync`, which sinks may be.
+        #[derive(Default)]
+        struct RecordingSink {
+            paths: Vec<String>,
+            skipped: Vec<SkippedFile>,
+            orphans: Vec<String>,
+            summaries: Vec<Summary>,
+            rejected: std::cell::Cell<usize>,
+        }
+
+        impl AnalysisSink for RecordingSink {
+            fn file(&mut self, file_stats: &FileStats) -> Result<(), RulocError> {
+                if file_stats.path.ends_with("rejected.rs") {
+                    self.rejected.set(self.rejected.get() + 1);
+                    return Err(RulocError::Output("rejected".to_string()));
+                }
+                self.paths.push(file_stats.path.clone());
+                Ok(())
+            }
+
+            fn skipped(&mut self, skipped: &[SkippedFile]) -> Result<(), RulocError> {
+                self.skipped.extend_from_slice(skipped);
+                Ok(())
+            }
+
+            fn orphans(&mut self, orphans: &[String]) -> Result<(), RulocError> {
+                self.orphans.extend_from_slice(orphans);
+                Ok(())
+            }
+
+            fn summary(&mut self, summary: &Summary) -> Result<(), RulocError> {
+                assert!(self.summaries.is_empty());
+                self.summaries.push(summary.clone());
+                Ok(())
+            }
+        }
+
+        let temp_dir = std::env::temp_dir().join("test_ruloc_analysis_sink");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        fs::write(temp_dir.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(temp_dir.join("b.rs"), "fn b() {}\n\nfn c() {}\n").unwrap();
+        fs::write(temp_dir.join("rejected.rs"), "fn d() {}\n").unwrap();
+
+        let mut sink = RecordingSink::default();
+        let summary = analyze_into(
+            &InputSource::Directory(temp_dir.clone()),
+            &AnalysisOptions::default(),
+            &mut sink,
+        )
+        .unwrap();
+
+        assert_eq!(sink.paths.len(), 2);
+        assert!(sink.paths[0].ends_with("a.rs") && sink.paths[1].ends_with("b.rs"));
+        assert_eq!(sink.skipped.len(), 1);
+        assert!(sink.skipped[0].path.ends_with("rejected.rs"));
+        assert_eq!(sink.skipped[0].category, SkipCategory::Accumulator);
+        assert!(sink.orphans.is_empty());
+        assert_eq!(sink.summaries.len(), 1);
+        assert_eq!(sink.summaries[0], summary);
+        assert_eq!((summary.files, summary.total.code_lines), (2, 3));
+
+        let mut sink = RecordingSink::default();
+        analyze_into(
+            &InputSource::Entry(temp_dir.join("a.rs")),
+            &AnalysisOptions::default(),
+            &mut sink,
+        )
+        .unwrap();
+        assert_eq!(sink.paths.len(), 1);
+        assert!(sink.skipped.is_empty());
+        assert_eq!(sink.orphans.len(), 2);
+        assert!(sink.orphans[0].ends_with("b.rs") && sink.orphans[1].ends_with("rejected.rs"));
+
+        let mut sink = RecordingSink::default();
+        let err = analyze_into(
+            &InputSource::File(temp_dir.join("rejected.rs")),
+            &AnalysisOptions::default(),
+            &mut sink,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "rejected");
+        assert_eq!(err.exit_code(), 6);
+        assert_eq!(sink.rejected.get(), 1);
+        assert!(sink.summaries.is_empty());
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that analyzing content in memory matches analyzing the same file.
+    #[test]
+    fn test_analyze_content_matches_file() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_analyze_content");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let content = "/// Adds.\npub fn add() {}\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn it() {}\n}\n";
+        let path = temp_dir.join("lib.rs");
+        fs::write(&path, content).unwrap();
+
+        let options = AnalysisOptions::default();
+        let stats = analyze_content(
+            &path,
+            Cow::Borrowed(content),
+            0,
+            options.editions.resolve(&path),
+            &options,
+        );
+        assert_eq!(stats, analyze_file(&path, &options).unwrap());
+        assert_eq!((stats.production.code_lines, stats.test.code_lines), (1, 5));
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests the C ABI round trip from analysis to JSON and release.
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_ffi_analyze_file() {
+        use ffi::{ruloc_analyze_file, ruloc_free, ruloc_report_to_json};
+        use std::ffi::{CStr, CString};
+
+        let temp_dir = std::env::temp_dir().join("test_ruloc_ffi");
+        let _ = fs::remove_dir_all(&temp_dir);
+        fs::create_dir_all(&temp_dir).unwrap();
+        let path = temp_dir.join("lib.rs");
+        fs::write(&path, "pub fn a() {}\n").unwrap();
+
+        let json_of = |path: &Path| {
+            let path = CString::new(path.to_str().unwrap()).unwrap();
+            // SAFETY: the path is NUL-terminated and the report is freed exactly once
+            unsafe {
+                let report = ruloc_analyze_file(path.as_ptr());
+                assert!(!report.is_null());
+                let json = CStr::from_ptr(ruloc_report_to_json(report))
+                    .to_str()
+                    .unwrap()
+                    .to_string();
+                assert_eq!(
+                    CStr::from_ptr(ruloc_report_to_json(report)).to_str(),
+                    Ok(json.as_str())
+                );
+                ruloc_free(report);
+                serde_json::from_str::<serde_json::Value>(&json).unwrap()
+            }
+        };
+
+        let stats = json_of(&path);
+        assert_eq!(stats["total"]["code-lines"], 1);
+        let missing = json_of(&temp_dir.join("missing.rs"));
+        assert!(missing["error"].as_str().unwrap().contains("missing.rs"));
+        assert_eq!(missing["exit-code"], 3);
+
+        // SAFETY: null pointers are accepted by every function
+        unsafe {
+            assert!(ruloc_analyze_file(std::ptr::null()).is_null());
+            assert!(ruloc_report_to_json(std::ptr::null_mut()).is_null());
+            ruloc_free(std::ptr::null_mut());
+        }
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests the GitHub job summary Markdown and warning annotations.
+    #[test]
+    fn test_github_summary() {
+        let mut summary = Summary::default();
+        summary.add_file(&make_simple_file_stats("src/a.rs", 10, 2, 1, 3, 4));
+        summary.long_functions = 2;
+
+        let markdown = format_github_summary(&summary, &TABLE_HEADERS, None, 1);
+        assert!(markdown.starts_with("## ruloc\n\n| Scope | Code |"));
+        assert!(markdown.contains("\n| Total | 4 | 1 | 3 | 2 | 10 |\n"));
+        assert!(!markdown.contains("| Bench |"));
+        assert!(markdown.contains("**Files:** 1 · **Long functions:** 2 · **Skipped files:** 1"));
+        assert!(!markdown.contains("<details>"));
+
+        let rows = [table_row(
+            "src/a|b.rs".to_string(),
+            &summary.total,
+            &summary.test,
+        )];
+        let markdown = format_github_summary(&summary, &TABLE_HEADERS, Some(&rows), 0);
+        assert!(markdown.contains("<details><summary>Files (1)</summary>"));
+        assert!(markdown.contains("| `src/a\\|b.rs` | 4 | 1 | 3 | 2 |"));
+        assert!(markdown.ends_with("</details>\n\n"));
+
+        let finding = Finding {
+            rule: FindingRule::LongFunction,
+            path: "src/a,b.rs".to_string(),
+            line: Some(7),
+            message: "run: 100%\nlines".to_string(),
+        };
+        assert_eq!(
+            github_warning(&finding),
+            "::warning file=src/a%2Cb.rs,line=7,title=Long function::run: 100%25%0Alines"
+        );
+        let finding = Finding {
+            line: None,
+            ..finding
+        };
+        assert!(github_warning(&finding).starts_with("::warning file=src/a%2Cb.rs,title="));
+        assert!(Args::try_parse_from(["ruloc", "--dir", "src", "--github-summary-files"]).is_err());
+    }
+
+    /// Tests shields.io badge generation for each metric.
+    #[test]
+    fn test_badges() {
+        let mut summary = Summary::default();
+        let mut file = make_simple_file_stats("src/lib.rs", 20_000, 0, 0, 600, 12_345);
+        file.test = make_line_stats(4_000, 0, 0, 0, 3_703);
+        file.production = make_line_stats(16_000, 0, 0, 600, 8_642);
+        summary.add_file(&file);
+
+        let badge = BadgeKind::CodeLines.badge(&summary);
+        assert_eq!(
+            serde_json::to_value(&badge).unwrap(),
+            serde_json::json!({
+                "schemaVersion": 1,
+                "label": "lines of code",
+                "message": "12.3k",
+                "color": "blue",
+            })
+        );
+        let test_ratio = BadgeKind::TestRatio.badge(&summary);
+        assert_eq!(
+            (test_ratio.message.as_str(), test_ratio.color),
+            ("30.0%", "green")
+        );
+        let doc_ratio = BadgeKind::DocRatio.badge(&summary);
+        assert_eq!(
+            (doc_ratio.message.as_str(), doc_ratio.color),
+            ("6.9%", "orange")
+        );
+        let empty = BadgeKind::TestRatio.badge(&Summary::default());
+        assert_eq!((empty.message.as_str(), empty.color), ("n/a", "lightgrey"));
+
+        assert_eq!(format_compact_count(999), "999");
+        assert_eq!(format_compact_count(2_500_000), "2.5M");
+        assert!(Args::try_parse_from(["ruloc", "--dir", "src", "--badge", "code-lines"]).is_err());
+    }
+
+    /// Tests deriving findings from file statistics and rendering them as SARIF.
+    #[test]
+    fn test_findings_and_sarif() {
+        let args = Args::try_parse_from([
+            "ruloc",
+            "--dir",
+            "./crate",
+            "--max-fn-lines",
+            "10",
+            "--min-doc-coverage",
+            "75%",
+        ])
+        .unwrap();
+        let mut file = make_simple_file_stats("src/lib.rs", 40, 0, 0, 0, 40);
+        file.long_functions = vec![LongFunction {
+            name: "run".to_string(),
+            start_line: 12,
+            end_line: 36,
+            lines: 25,
+        }];
+        file.doc_coverage = Some(DocCoverage {
+            public_items: 4,
+            documented_items: 2,
+        });
+
+        let findings = file_findings(&args, &file);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].rule, FindingRule::LongFunction);
+        assert_eq!(findings[0].path, "crate/src/lib.rs");
+        assert_eq!(findings[0].line, Some(12));
+        assert_eq!(findings[0].message, "run spans 25 lines (maximum 10)");
+        assert_eq!(findings[1].rule, FindingRule::LowDocCoverage);
+        assert_eq!(findings[1].line, None);
+        assert_eq!(
+            findings[1].message,
+            "2 of 4 public items documented (50.0%, minimum 75%)"
+        );
+
+        file.doc_coverage = Some(DocCoverage {
+            public_items: 4,
+            documented_items: 3,
+        });
+        assert_eq!(file_findings(&args, &file).len(), 1);
+
+        let skipped = [SkippedFile {
+            path: "./crate/big.rs".to_string(),
+            reason: "File too large".to_string(),
+            category: SkipCategory::Oversized,
+        }];
+        let mut findings: Vec<Finding> = skipped_findings(&skipped).collect();
+        assert_eq!(findings[0].path, "crate/big.rs");
+        findings.insert(0, file_findings(&args, &file).remove(0));
+
+        let log = sarif_log(&findings);
+        assert_eq!(log["version"], "2.1.0");
+        let run = &log["runs"][0];
+        assert_eq!(run["tool"]["driver"]["name"], "ruloc");
+        assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), 6);
+        assert_eq!(run["results"][0]["ruleId"], "long-function");
+        assert_eq!(run["results"][0]["ruleIndex"], 0);
+        assert_eq!(
+            run["results"][0]["locations"][0]["physicalLocation"],
+            serde_json::json!({
+                "artifactLocation": { "uri": "crate/src/lib.rs" },
+                "region": { "startLine": 12 },
+            })
+        );
+        assert_eq!(run["results"][1]["ruleId"], "oversized-file");
+        assert!(
+            run["results"][1]["locations"][0]["physicalLocation"]
+                .get("region")
+                .is_none()
+        );
+
+        assert_eq!(parse_percentage("62.5").unwrap(), 62.5);
+        assert!(parse_percentage("101").is_err());
+        assert!(parse_percentage("lots").is_err());
+    }
+
+    /// Tests sampling of history revisions by tag and by week.
+    #[test]
+    fn test_select_history_revisions() {
+        let day = SECONDS_PER_WEEK / 7;
+        let commits: Vec<HistoryCommit> = [0, 2, 8, 9, 20]
+            .iter()
+            .enumerate()
+            .map(|(index, days)| HistoryCommit {
+                hash: format!("{:040}", index),
+                timestamp: 1_700_000_000 + days * day,
+            })
+            .collect();
+        let tags = HashMap::from([
+            (commits[1].hash.clone(), vec!["v0.1.0".to_string()]),
+            (
+                commits[3].hash.clone(),
+                vec!["v0.2.0".to_string(), "latest".to_string()],
+            ),
+        ]);
+
+        let tagged = select_history_revisions(&commits, &tags, HistoryStep::Tag);
+        let labels: Vec<&str> = tagged.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, ["v0.1.0", "v0.2.0,latest"]);
+
+        let weekly = select_history_revisions(&commits, &tags, HistoryStep::Weekly);
+        let picked: Vec<&HistoryCommit> = weekly.iter().map(|(_, commit)| *commit).collect();
+        assert_eq!(picked, [&commits[1], &commits[3], &commits[4]]);
+        assert_eq!(weekly[2].0, "000000000000");
+
+        assert!(select_history_revisions(&[], &tags, HistoryStep::Weekly).is_empty());
+    }
+
+    /// Tests the CSV encoding of the history time series and the subcommand defaults.
+    #[test]
+    fn test_format_history_csv() {
+        let mut summary = Summary::default();
+        let mut file = make_simple_file_stats("src/lib.rs", 10, 1, 1, 0, 8);
+        file.production = make_line_stats(6, 1, 1, 0, 6);
+        file.test = make_line_stats(4, 0, 0, 0, 2);
+        summary.add_file(&file);
+        let points = [
+            HistoryPoint {
+                revision: "v0.1.0,latest".to_string(),
+                commit: "abc".to_string(),
+                date: format_utc_timestamp(0),
+                summary,
+            },
+            HistoryPoint {
+                revision: "def".to_string(),
+                commit: "def".to_string(),
+                date: format_utc_timestamp(0),
+                summary: Summary::default(),
+            },
+        ];
+
+        let csv = format_history_csv(&points);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], HISTORY_CSV_HEADER);
+        assert_eq!(
+            lines[1],
+            "\"v0.1.0,latest\",abc,1970-01-01T00:00:00Z,1,10,1,1,0,8,6,2,25.0"
+        );
+        assert!(lines[2].ends_with(",0,0,0,"));
+
+        let args = Args::try_parse_from(["ruloc", "history", "--since", "v0.1.0"]).unwrap();
+        match args.command {
+            Some(Command::History {
+                since,
+                step,
+                dir,
+                format,
+            }) => {
+                assert_eq!(since.as_deref(), Some("v0.1.0"));
+                assert_eq!(step, HistoryStep::Tag);
+                assert_eq!(dir, PathBuf::from("."));
+                assert_eq!(format, HistoryFormat::Csv);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    /// Tests comparing the analyses of two directories and rendering the delta report.
+    #[test]
+    fn test_compare_directories() {
+        let file = |path: &str, code: usize| {
+            let mut stats = make_simple_file_stats(path, code + 2, 1, 1, 0, code);
+            stats.test = make_line_stats(0, 0, 0, 0, 0);
+            (path.to_string(), stats)
+        };
+        let files_a = BTreeMap::from([
+            file("src/lib.rs", 10),
+            file("src/old.rs", 4),
+            file("src/same.rs", 3),
+        ]);
+        let files_b = BTreeMap::from([
+            file("src/lib.rs", 15),
+            file("src/new.rs", 6),
+            file("src/same.rs", 3),
+        ]);
+
+        let comparison =
+            compare_directories(Path::new("upstream"), Path::new("fork"), &files_a, &files_b);
+        assert_eq!((comparison.files_a, comparison.files_b), (3, 3));
+        assert_eq!(comparison.summary.total.code_lines, 7);
+        assert_eq!(comparison.summary.total.all_lines, 7);
+        assert_eq!(comparison.summary.production.code_lines, 7);
+        assert_eq!(comparison.summary.test, LineStatsDelta::default());
+
+        let changes: Vec<(&str, FileChange, i64)> = comparison
+            .files
+            .iter()
+            .map(|file| (file.path.as_str(), file.change, file.delta.total.code_lines))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                ("src/lib.rs", FileChange::Modified, 5),
+                ("src/new.rs", FileChange::Added, 6),
+                ("src/old.rs", FileChange::Removed, -4),
+            ]
+        );
+
+        let text = format_comparison_text(&comparison);
+        assert!(text.starts_with("Comparing upstream -> fork\nFiles: 3 -> 3 (+0)\n"));
+        assert!(
+            text.contains("\nTotal               +7        +0        +0        +0        +7\n")
+        );
+        assert!(text.ends_with("  - src/old.rs: all -6, blank -1, comment -1, code -4"));
+
+        let markdown = format_comparison_markdown(&comparison);
+        assert!(markdown.contains("| Total | +7 | +0 | +0 | +0 | +7 |"));
+        assert!(markdown.contains("| + | `src/new.rs` | +8 | +6 | +6 | +0 |"));
+
+        let json = serde_json::to_value(&comparison).unwrap();
+        assert_eq!(json["files"][1]["change"], "added");
+        assert_eq!(json["files"][1]["total"]["code-lines"], 6);
+        assert_eq!(json["dir-b"], "fork");
+    }
+
+    /// Tests parsing crate specifications and crates.io responses for the crate subcommand.
+    #[test]
+    fn test_crate_spec() {
+        let pinned = CrateSpec::parse("serde@1.0.200").unwrap();
+        assert_eq!(pinned.name, "serde");
+        assert_eq!(pinned.version.as_deref(), Some("1.0.200"));
+        assert_eq!(
+            pinned.archive_url("1.0.200"),
+            "https://static.crates.io/crates/serde/serde-1.0.200.crate"
+        );
+        assert_eq!(CrateSpec::parse("ra_ap_syntax").unwrap().version, None);
+        assert!(CrateSpec::parse("").is_err());
+        assert!(CrateSpec::parse("serde@").is_err());
+        assert!(CrateSpec::parse("../serde@1").is_err());
+        assert!(CrateSpec::parse("serde@1.0/../x").is_err());
+
+        assert_eq!(
+            parse_latest_version(
+                r#"{"crate":{"max_version":"2.0.0-rc.1","max_stable_version":"1.4.0"}}"#
+            )
+            .unwrap(),
+            "1.4.0"
+        );
+        assert_eq!(
+            parse_latest_version(
+                r#"{"crate":{"max_version":"0.1.0-alpha","max_stable_version":null}}"#
+            )
+            .unwrap(),
+            "0.1.0-alpha"
+        );
+        assert!(parse_latest_version(r#"{"errors":[]}"#).is_err());
+
+        let args = Args::try_parse_from([
+            "ruloc",
+            "crate",
+            "serde@1.0.200",
+            "--out-json",
+            "--no-color",
+        ])
+        .unwrap();
+        match args.command {
+            Some(Command::Crate { spec, flags }) => {
+                assert_eq!(spec, pinned);
+                assert_eq!(flags, ["--out-json", "--no-color"]);
+            }
+            other => panic!("unexpected command: {:?}", other),
+        }
+    }
+
+    /// Tests analyzing a repository through --git and --rev from a local clone source.
+    #[test]
+    fn test_remote_git_analysis() {
+        let origin = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            run_git(
+                origin.path(),
+                &[
+                    &[
+                        "-c",
+                        "user.name=ruloc",
+                        "-c",
+                        "user.email=ruloc@example.com",
+                    ],
+                    args,
+                ]
+                .concat(),
+            )
+            .unwrap()
+        };
+        git(&["init", "--quiet"]);
+        fs::write(origin.path().join("lib.rs"), "fn one() {}\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "one"]);
+        git(&["tag", "v1"]);
+        fs::write(origin.path().join("lib.rs"), "fn one() {}\nfn two() {}\n").unwrap();
+        git(&["commit", "--quiet", "-am", "two"]);
+
+        let url = display_path(origin.path());
+        for (rev, lines) in [(None, 2), (Some("v1"), 1)] {
+            let checkout = tempfile::tempdir().unwrap();
+            shallow_clone(&url, rev.unwrap_or("HEAD"), checkout.path()).unwrap();
+            let content = fs::read_to_string(checkout.path().join("lib.rs")).unwrap();
+            assert_eq!(content.lines().count(), lines);
+        }
+
+        let checkout = tempfile::tempdir().unwrap();
+        assert!(shallow_clone(&url, "--upload-pack=evil", checkout.path()).is_err());
+        assert!(Args::try_parse_from(["ruloc", "--git", &url, "--dir", "."]).is_err());
+        assert!(Args::try_parse_from(["ruloc", "--dir", ".", "--rev", "v1"]).is_err());
+        assert!(Args::try_parse_from(["ruloc", "--git", &url, "--rev", "v1"]).is_ok());
+    }
+
+    /// Tests reading sources at a revision with their verbatim contents and crate editions.
+    #[test]
+    fn test_revision_sources() {
+        let repo = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            run_git(
+                repo.path(),
+                &[
+                    &[
+                        "-c",
+                        "user.name=ruloc",
+                        "-c",
+                        "user.email=ruloc@example.com",
+                    ],
+                    args,
+                ]
+                .concat(),
+            )
+            .unwrap()
+        };
+        git(&["init", "--quiet"]);
+        let write = |path: &str, content: &str| {
+            let path = repo.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\"]\n[workspace.package]\nedition = \"2018\"\n",
+        );
+        write("crates/legacy/Cargo.toml", "[package]\nname = \"legacy\"\n");
+        write(
+            "crates/inherited/Cargo.toml",
+            "[package]\nname = \"inherited\"\nedition.workspace = true\n",
+        );
+        write("crates/legacy/src/lib.rs", "\n\npub fn async() {}\n\n");
+        write("crates/inherited/src/ünï code.rs", "pub struct Shown;\n");
+        write("build.rs", "fn main() {}\n");
+        git(&["add", "."]);
+        git(&["commit", "--quiet", "-m", "init"]);
+
+        let toplevel = PathBuf::from(git(&["rev-parse", "--show-toplevel"]));
+        let read = |prefix: &str, forced: Option<Edition>| {
+            let mut files = Vec::new();
+            let sources = RevisionSources::list(&toplevel, prefix, "HEAD").unwrap();
+            sources
+                .read(forced, |path, content, edition| {
+                    files.push((path.to_string(), content, edition))
+                })
+                .unwrap();
+            (files, sources.crate_dirs)
+        };
+
+        let (files, crate_dirs) = read("crates/", None);
+        assert_eq!(crate_dirs, ["crates/inherited", "crates/legacy"]);
+        assert_eq!(
+            files,
+            [
+                (
+                    "crates/inherited/src/ünï code.rs".to_string(),
+                    "pub struct Shown;\n".to_string(),
+                    Edition::Edition2018
+                ),
+                (
+                    "crates/legacy/src/lib.rs".to_string(),
+                    "\n\npub fn async() {}\n\n".to_string(),
+                    Edition::Edition2015
+                ),
+            ]
+        );
+        let (files, _) = read("", Some(Edition::Edition2021));
+        assert_eq!(files.len(), 3);
+        assert!(
+            files
+                .iter()
+                .all(|(_, _, edition)| *edition == Edition::Edition2021)
+        );
+        assert_eq!(read("", None).0[0].2, Edition::CURRENT);
+
+        // `async` is an identifier in the 2015 crate, so the function is part of its API
+        let snapshot = api_snapshot(&toplevel, "", "HEAD").unwrap();
+        assert!(snapshot["crates/legacy"].contains_key("src/lib.rs: fn async"));
+        assert!(snapshot["crates/inherited"].contains_key("src/ünï code.rs: struct Shown"));
+        assert!(RevisionSources::list(&toplevel, "", "missing").is_err());
+    }
+
+    /// Tests counting commits per path and ranking files by churn × size.
+    #[test]
+    fn test_rank_hotspots() {
+        let log = "\ncrates/core/src/lib.rs\ncrates/core/src/parse.rs\n\ncrates/core/src/lib.rs\n\nREADME.md\n";
+        let commits = count_commits_per_path(log);
+        assert_eq!(commits["crates/core/src/lib.rs"], 2);
+        assert_eq!(commits["README.md"], 1);
+
+        let files = BTreeMap::from([
+            (
+                "src/lib.rs".to_string(),
+                make_simple_file_stats("src/lib.rs", 60, 0, 0, 0, 50),
+            ),
+            (
+                "src/parse.rs".to_string(),
+                make_simple_file_stats("src/parse.rs", 120, 0, 0, 0, 100),
+            ),
+            (
+                "src/new.rs".to_string(),
+                make_simple_file_stats("src/new.rs", 500, 0, 0, 0, 400),
+            ),
+        ]);
+        let hotspots = rank_hotspots(&files, &commits, "crates/core/", 10);
+        assert_eq!(
+            hotspots,
+            [
+                Hotspot {
+                    path: "src/lib.rs".to_string(),
+                    commits: 2,
+                    code_lines: 50,
+                    score: 100,
+                },
+                Hotspot {
+                    path: "src/parse.rs".to_string(),
+                    commits: 1,
+                    code_lines: 100,
+                    score: 100,
+                },
+            ]
+        );
+        assert_eq!(rank_hotspots(&files, &commits, "crates/core/", 1).len(), 1);
+        assert!(rank_hotspots(&files, &commits, "", 10).is_empty());
+
+        let text = format_hotspots_text(&hotspots);
+        assert!(text.starts_with("Rank       Score  Commits    Code  File\n"));
+        assert!(text.ends_with("   2         100        1     100  src/parse.rs"));
+        assert!(
+            format_hotspots_markdown(&hotspots).ends_with("| 2 | `src/parse.rs` | 100 | 1 | 100 |")
+        );
+    }
+
+    /// Tests measuring rustdoc volume per file and per public item with --doc-detail.
+    #[test]
+    fn test_doc_detail() {
+        let code = r#"//! Crate docs here.
+
+/// Parses   input
+/// quickly.
+pub fn parse() {}
+
+/** Block docs. */
+pub struct Parser;
+
+impl Parser {
+    /// Creates a parser.
+    pub fn new() -> Self { Parser }
+}
+
+// Not documentation.
+fn private() {}
+
+#[cfg(test)]
+mod tests {
+    /// Test helper.
+    pub fn helper() {}
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let detail = compute_doc_detail(&root, &LineIndex::new(code));
+
+        assert_eq!(
+            detail.volume,
+            DocVolume {
+                words: 13,
+                characters: 78,
+                ..Default::default()
+            }
+        );
+        let items: Vec<(&str, usize, usize)> = detail
+            .items
+            .iter()
+            .map(|item| (item.name.as_str(), item.line, item.volume.words))
+            .collect();
+        assert_eq!(
+            items,
+            [("parse", 5, 3), ("Parser", 8, 2), ("Parser::new", 12, 3)]
+        );
+        assert_eq!(detail.items[0].volume.characters, 22);
+        assert_eq!(
+            format_doc_volume(&detail.items[0].volume),
+            "3 words, 22 characters"
+        );
+
+        let mut summary = Summary::default();
+        summary.add_file(&make_simple_file_stats("a.rs", 1, 0, 0, 0, 1));
+        assert_eq!(summary.doc_volume, None);
+        let file = FileStats {
+            doc_detail: Some(detail),
+            ..make_simple_file_stats("b.rs", 1, 0, 0, 0, 1)
+        };
+        summary.add_file(&file);
+        summary.add_file(&file);
+        assert_eq!(summary.doc_volume.unwrap().words, 26);
+
+        let json = serde_json::to_value(&file).unwrap();
+        assert_eq!(json["doc-detail"]["words"], 13);
+        assert_eq!(json["doc-detail"]["items"][2]["name"], "Parser::new");
+        assert_eq!(json["doc-detail"]["items"][2]["characters"], 17);
+    }
+
+    /// Tests cyclomatic complexity estimation, thresholds, and aggregation.
+    #[test]
+    fn test_function_complexity() {
+        let code = r#"fn straight() {}
+
+fn branchy(x: Option<u8>, flag: bool) -> Option<u8> {
+    if flag && x.is_some() || !flag {
+        for _ in 0..3 {}
+    }
+    let value = x?;
+    match value {
+        0 => None,
+        1 | 2 => Some(1),
+        _ => {
+            let check = |v: u8| if v > 9 { 1 } else { 0 };
+            fn nested() { loop {} }
+            Some(check(value))
+        }
+    }
+}
+
+trait Shape {
+    fn area(&self) -> f64;
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let line_index = LineIndex::new(code);
+
+        let (complexity, complex) = compute_function_complexity(&root, &line_index, Some(3));
+        assert_eq!(
+            complexity,
+            FunctionComplexity {
+                functions: 3,
+                total: 1 + 9 + 2,
+                max: 9,
+            }
+        );
+        assert_eq!(
+            complex,
+            [ComplexFunction {
+                name: "branchy".to_string(),
+                start_line: 3,
+                complexity: 9,
+            }]
+        );
+        assert!(
+            compute_function_complexity(&root, &line_index, None)
+                .1
+                .is_empty()
+        );
+        assert_eq!(
+            format_function_complexity(&complexity),
+            "3 functions, mean 4.0, max 9"
+        );
+        assert_eq!(
+            format_function_complexity(&FunctionComplexity::default()),
+            "n/a (no functions)"
+        );
+
+        let file = FileStats {
+            complexity: Some(complexity),
+            complex_functions: complex,
+            ..make_simple_file_stats("src/lib.rs", 20, 0, 0, 0, 20)
+        };
+        let mut summary = Summary::default();
+        summary.add_file(&file);
+        summary.add_file(&file);
+        assert_eq!(summary.complexity.unwrap().functions, 6);
+        assert_eq!(summary.complexity.unwrap().max, 9);
+        assert_eq!(summary.complex_functions, 2);
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["complexity"]["mean"], 4.0);
+
+        let args = Args::try_parse_from(["ruloc", "--dir", ".", "--max-complexity", "3"]).unwrap();
+        let options = args.analysis_options().unwrap();
+        assert!(options.complexity);
+        let findings = file_findings(&args, &file);
+        assert_eq!(findings[0].rule, FindingRule::ComplexFunction);
+        assert_eq!(
+            findings[0].message,
+            "branchy has a cyclomatic complexity of 9 (maximum 3)"
+        );
+    }
+
+    /// Tests nesting depth measurement, the else-if chain rule, and the gate.
+    #[test]
+    fn test_nesting_depth() {
+        let code = r#"fn flat() -> u8 { 1 }
+
+fn chained(x: u8) -> u8 {
+    if x == 0 { 0 } else if x == 1 { 1 } else if x == 2 { 2 } else { 3 }
+}
+
+impl Grid {
+    fn scan(&self) {
+        for row in &self.rows {
+            if row.ok {
+                row.cells.iter().for_each(|cell| {
+                    match cell {
+                        _ => {}
+                    }
+                });
+            }
+        }
+        fn helper() { loop {} }
+    }
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let line_index = LineIndex::new(code);
+
+        let (depth, nested) = compute_nesting(&root, &line_index, Some(2));
+        assert_eq!(depth, 4);
+        assert_eq!(
+            nested,
+            [NestedFunction {
+                name: "Grid::scan".to_string(),
+                start_line: 8,
+                depth: 4,
+            }]
+        );
+        assert_eq!(compute_nesting(&root, &line_index, Some(1)).1.len(), 1);
+        assert!(compute_nesting(&root, &line_index, None).1.is_empty());
+
+        let args = Args::try_parse_from(["ruloc", "--dir", ".", "--max-nesting", "2"]).unwrap();
+        assert!(args.analysis_options().unwrap().nesting);
+        let file = FileStats {
+            max_nesting: Some(depth),
+            nested_functions: nested,
+            ..make_simple_file_stats("src/grid.rs", 20, 0, 0, 0, 20)
+        };
+        assert_eq!(
+            file_findings(&args, &file)[0].message,
+            "Grid::scan nests 4 levels deep (maximum 2)"
+        );
+
+        let mut summary = Summary::default();
+        summary.add_file(&file);
+        summary.add_file(&FileStats {
+            max_nesting: Some(1),
+            ..make_simple_file_stats("src/flat.rs", 5, 0, 0, 0, 5)
+        });
+        assert_eq!(summary.max_nesting, Some(4));
+        assert_eq!(summary.nested_functions, 1);
+        let error = check_gates(&args, &summary, &[]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "1 function(s) exceed the maximum nesting depth of 2"
+        );
+    }
+
+    /// Tests that compute_item_visibility splits items by category and visibility.
+    #[test]
+    fn test_compute_item_visibility() {
+        let code = r#"pub fn api() {}
+fn helper() {}
+pub struct Config;
+pub(crate) enum Mode { A }
+type Alias = u8;
+pub trait Shape { fn area(&self) -> f64; }
+const LIMIT: u8 = 3;
+pub static NAME: &str = "x";
+
+impl Config {
+    pub fn new() -> Self { Config }
+    fn check(&self) {}
+}
+
+impl Shape for Config {
+    fn area(&self) -> f64 { 0.0 }
+}
+
+pub mod inner {
+    pub fn nested() {}
+}
+
+#[cfg(test)]
+mod tests {
+    pub fn fixture() {}
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let items = compute_item_visibility(&root);
+
+        assert_eq!(
+            items.public,
+            ItemCounts {
+                functions: 3,
+                types: 1,
+                traits: 1,
+                consts: 1,
+            }
+        );
+        assert_eq!(
+            items.private,
+            ItemCounts {
+                functions: 2,
+                types: 2,
+                traits: 0,
+                consts: 1,
+            }
+        );
+        assert_eq!(
+            format_item_visibility(&items),
+            "6 public, 5 private (fns 3/2, types 1/2, traits 1/0, consts 1/1)"
+        );
+
+        let file = FileStats {
+            items: Some(items),
+            ..make_simple_file_stats("src/lib.rs", 10, 0, 0, 0, 10)
+        };
+        let mut summary = Summary::default();
+        summary.add_file(&file);
+        summary.add_file(&file);
+        assert_eq!(summary.items.unwrap().public.functions, 6);
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["items"]["private"]["types"], 4);
+
+        let args = Args::try_parse_from(["ruloc", "--dir", "src", "--item-visibility"]).unwrap();
+        assert!(args.analysis_options().unwrap().item_visibility);
+    }
+
+    /// Tests the item kind census and its opt-in aggregation.
+    #[test]
+    fn test_item_kind_counts() {
+        let code = r#"struct Point;
+pub enum Mode { A, B }
+trait Shape { fn area(&self) -> f64; }
+impl Point { fn new() -> Self { Point } }
+impl Shape for Point { fn area(&self) -> f64 { 0.0 } }
+macro_rules! square { ($x:expr) => { $x * $x }; }
+const LIMIT: u8 = 3;
+static NAME: &str = "x";
+fn main() { println!("{}", square!(2)); }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn works() {}
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let counts = compute_item_kind_counts(&root);
+        assert_eq!(
+            counts,
+            ItemKindCounts {
+                structs: 1,
+                enums: 1,
+                traits: 1,
+                impls: 2,
+                functions: 5,
+                macros: 1,
+                consts: 1,
+                statics: 1,
+            }
+        );
+        assert_eq!(
+            format_item_kind_counts(&counts),
+            "1 structs, 1 enums, 1 traits, 2 impls, 5 fns, 1 macros, 1 consts, 1 statics"
+        );
+
+        let args = Args::try_parse_from(["ruloc", "--dir", ".", "--item-counts"]).unwrap();
+        assert!(args.analysis_options().unwrap().item_counts);
+
+        let mut summary = Summary::default();
+        summary.add_file(&make_simple_file_stats("src/a.rs", 1, 0, 0, 0, 1));
+        assert_eq!(summary.item_counts, None);
+        summary.add_file(&FileStats {
+            item_counts: Some(counts),
+            ..make_simple_file_stats("src/b.rs", 1, 0, 0, 0, 1)
+        });
+        summary.add_file(&FileStats {
+            item_counts: Some(counts),
+            ..make_simple_file_stats("src/c.rs", 1, 0, 0, 0, 1)
+        });
+        assert_eq!(summary.item_counts.unwrap().impls, 4);
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["item-counts"]["functions"], 10);
+    }
+
+    /// Tests async function, await point, and async body line counting.
+    #[test]
+    fn test_compute_async_metrics() {
+        let code = r#"trait Fetch {
+    async fn fetch(&self) -> u8;
+}
+
+async fn load(source: &impl Fetch) -> u8 {
+    let first = source.fetch().await;
+    let block = async {
+        source.fetch().await
+    };
+    first + block.await
+}
+
+fn spawn() {
+    let task = async move {
+        // comment lines are not code
+        work().await;
+    };
+    let retry = async |n: u8| n;
+}
+"#;
+        let parse = SourceFile::parse(code, Edition::CURRENT);
+        let line_types = analyze_lines(code, Edition::CURRENT);
+        let metrics =
+            compute_async_metrics(&parse.syntax_node(), &LineIndex::new(code), &line_types);
+
+        assert_eq!(
+            metrics,
+            AsyncMetrics {
+                async_functions: 2,
+                await_points: 4,
+                async_lines: 11,
+            }
+        );
+        assert_eq!(
+            format_async_metrics(&metrics),
+            "2 async fns, 4 await points, 11 lines in async bodies"
+        );
+
+        let mut summary = Summary::default();
+        summary.add_file(&FileStats {
+            async_metrics: Some(metrics),
+            ..make_simple_file_stats("src/io.rs", 19, 0, 0, 0, 19)
+        });
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["async"]["await-points"], 4);
+    }
+
+    /// Tests the panic call site census, its production/test split, and the gate.
+    #[test]
+    fn test_panic_census() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("lib.rs");
+        fs::write(
+            &path,
+            r#"pub fn parse(input: &str) -> u8 {
+    let value = input.parse().unwrap();
+    let other: u8 = input.parse().expect("number");
+    if value > other {
+        panic!("too large");
+    }
+    std::todo!()
+}
+
+pub fn unwrap_or_zero(input: Option<u8>) -> u8 {
+    input.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn parses() {
+        assert_eq!(super::parse("1"), Some(1).unwrap());
+        unimplemented!();
+    }
+}
+"#,
+        )
+        .unwrap();
+
+        let args = Args::try_parse_from(["ruloc", "--dir", ".", "--list-panics"]).unwrap();
+        let options = args.analysis_options().unwrap();
+        assert!(options.panics);
+        let stats = analyze_file(&path, &options).unwrap();
+        let panics = stats.panics.unwrap();
+        assert_eq!(
+            panics.production,
+            PanicCounts {
+                unwrap: 1,
+                expect: 1,
+                panic: 1,
+                todo: 1,
+                unimplemented: 0,
+            }
+        );
+        assert_eq!(panics.test.total(), 2);
+        assert_eq!(panics.test.unimplemented, 1);
+        assert_eq!(
+            format_panic_counts(&panics.production),
+            "4 (unwrap 1, expect 1, panic! 1, todo! 1, unimplemented! 0)"
+        );
+
+        let lines: Vec<(usize, &str, bool)> = stats
+            .panic_sites
+            .iter()
+            .map(|site| (site.line, site.kind.as_str(), site.test))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (2, "unwrap", false),
+                (3, "expect", false),
+                (5, "panic!", false),
+                (7, "todo!", false),
+                (18, "unwrap", true),
+                (19, "unimplemented!", true),
+            ]
+        );
+        assert_eq!(
+            stats.panic_sites[1].text,
+            r#"let other: u8 = input.parse().expect("number");"#
+        );
+
+        let mut summary = Summary::default();
+        summary.add_file(&stats);
+        let gated = Args::try_parse_from(["ruloc", "--dir", ".", "--max-panics", "3"]).unwrap();
+        assert_eq!(
+            check_gates(&gated, &summary, &[]).unwrap_err().to_string(),
+            "production code contains 4 panic call site(s), exceeding the maximum of 3"
+        );
+        let lenient = Args::try_parse_from(["ruloc", "--dir", ".", "--max-panics", "4"]).unwrap();
+        assert!(check_gates(&lenient, &summary, &[]).is_ok());
+    }
+
+    /// Tests that compute_allowed_lints counts lints per allow attribute and ranks them.
+    #[test]
+    fn test_compute_allowed_lints() {
+        let code = r#"#![allow(dead_code)]
+
+#[allow(clippy::too_many_arguments, dead_code)]
+fn wide(a: u8, b: u8) {}
+
+#[allow(unused_variables, reason = "kept for symmetry")]
+fn narrow() {
+    #[allow(clippy :: needless_return)]
+    return;
+}
+
+#[deny(missing_docs)]
+#[cfg_attr(test, allow(unused))]
+struct Kept;
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let lints = compute_allowed_lints(&root);
+
+        assert_eq!(
+            lints,
+            BTreeMap::from([
+                ("clippy::needless_return".to_string(), 1),
+                ("clippy::too_many_arguments".to_string(), 1),
+                ("dead_code".to_string(), 2),
+                ("unused_variables".to_string(), 1),
+            ])
+        );
+        assert_eq!(
+            most_allowed_lints(&lints)[..2],
+            [("dead_code", 2), ("clippy::needless_return", 1)]
+        );
+
+        let args = Args::try_parse_from(["ruloc", "--dir", ".", "--allows"]).unwrap();
+        assert!(args.analysis_options().unwrap().allows);
+        let file = FileStats {
+            allowed_lints: lints,
+            ..make_simple_file_stats("src/lib.rs", 14, 0, 0, 0, 14)
+        };
+        let mut summary = Summary::default();
+        summary.add_file(&file);
+        summary.add_file(&file);
+        assert_eq!(summary.allowed_lints["dead_code"], 4);
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["allowed-lints"]["unused_variables"], 2);
+    }
+
+    /// Tests that compute_cfg_surface attributes lines to non-test predicate atoms.
+    #[test]
+    fn test_compute_cfg_surface() {
+        let code = r#"#[cfg(unix)]
+fn unix_only() {
+    println!("unix");
+}
+
+#[cfg(all(target_os = "linux", feature = "epoll"))]
+mod epoll {
+    pub fn poll() {}
+}
+
+#[cfg(not(windows))]
+const SEP: char = '/';
+
+fn shared() {}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn works() {}
+}
+"#;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let line_types = analyze_lines(code, Edition::CURRENT);
+        let surface = compute_cfg_surface(&root, &LineIndex::new(code), &line_types);
+
+        assert_eq!(surface.guarded.all_lines, 10);
+        assert_eq!(surface.guarded.code_lines, 10);
+        let atoms: Vec<(&str, usize)> = surface
+            .predicates
+            .iter()
+            .map(|(atom, stats)| (atom.as_str(), stats.all_lines))
+            .collect();
+        assert_eq!(
+            atoms,
+            [
+                ("feature = \"epoll\"", 4),
+                ("target_os = \"linux\"", 4),
+                ("unix", 4),
+                ("windows", 2),
+            ]
+        );
+
+        let args = Args::try_parse_from(["ruloc", "--dir", ".", "--cfg-surface"]).unwrap();
+        assert!(args.analysis_options().unwrap().cfg_surface);
+        let file = FileStats {
+            cfg_surface: Some(surface),
+            ..make_simple_file_stats("src/sys.rs", 20, 0, 0, 0, 20)
+        };
+        let mut summary = Summary::default();
+        summary.add_file(&file);
+        summary.add_file(&file);
+        let merged = summary.cfg_surface.unwrap();
+        assert_eq!(merged.guarded.code_lines, 20);
+        assert_eq!(merged.predicates["unix"].all_lines, 8);
+    }
+
+    /// Tests line length measurement, nearest-rank percentiles, and merging.
+    #[test]
+    fn test_line_lengths() {
+        let mut content = String::new();
+        for length in 1..=20 {
+            content.push_str(&"x".repeat(length * 5));
+            content.push_str("\n\n");
+        }
+        content.push_str("    \n");
+
+        let lengths = LineLengths::of(&content, 80);
+        assert_eq!(lengths.max, 100);
+        assert_eq!(lengths.over_width, 4);
+        assert_eq!(lengths.p50, 50);
+        assert_eq!(lengths.p95, 95);
+        assert_eq!(
+            format_line_lengths(&lengths),
+            "max 100, 4 over 80, p50 50, p95 95"
+        );
+        assert_eq!(LineLengths::of("", 80).p95, 0);
+        assert_eq!(LineLengths::of("héllo wörld\n", 10).over_width, 1);
+
+        let mut merged = lengths.clone();
+        merged.add(&LineLengths::of(&"y\n".repeat(60), 80));
+        assert_eq!(merged.max, 100);
+        assert_eq!(merged.over_width, 4);
+        assert_eq!(merged.p50, 1);
+        assert_eq!(merged.p95, 80);
+
+        let json = serde_json::to_value(&lengths).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"width": 80, "max": 100, "over-width": 4, "p50": 50, "p95": 95})
+        );
+
+        let args = Args::try_parse_from(["ruloc", "--dir", ".", "--line-length-stats"]).unwrap();
+        assert_eq!(
+            args.analysis_options().unwrap().line_width,
+            Some(DEFAULT_LINE_WIDTH)
+        );
+        let plain = Args::try_parse_from(["ruloc", "--dir", ".", "--line-width", "80"]).unwrap();
+        assert_eq!(plain.analysis_options().unwrap().line_width, None);
+    }
+
+    /// Tests that compute_string_lines counts code lines dominated by string literals.
+    #[test]
+    fn test_compute_string_lines() {
+        let code = r####"const QUERY: &str = r#"
+SELECT id, name
+FROM users
+WHERE active
+"#;
+
+fn greet(name: &str) {
+    println!("Hello there, {}!", name);
+    let total = compute(name.len(), 42) + offset();
+    let bytes = b"raw bytes payload";
+}
+"####;
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let line_types = analyze_lines(code, Edition::CURRENT);
+
+        assert_eq!(
+            compute_string_lines(&root, &LineIndex::new(code), &line_types),
+            5
+        );
+
+        let args = Args::try_parse_from(["ruloc", "--dir", ".", "--string-lines"]).unwrap();
+        assert!(args.analysis_options().unwrap().string_lines);
+        let mut summary = Summary::default();
+        summary.add_file(&make_simple_file_stats("src/a.rs", 1, 0, 0, 0, 1));
+        assert_eq!(summary.string_lines, None);
+        for _ in 0..2 {
+            summary.add_file(&FileStats {
+                string_lines: Some(6),
+                ..make_simple_file_stats("src/b.rs", 12, 1, 0, 0, 11)
+            });
+        }
+        assert_eq!(summary.string_lines, Some(12));
+    }
+
+    /// Tests duplicate grouping of identical and whitespace/comment-only variants.
+    #[test]
+    fn test_duplicate_detection() {
+        let original = "fn add(a: u8, b: u8) -> u8 {\n    a + b\n}\n";
+        let reformatted = "// copied from math.rs\nfn add(a: u8, b: u8) -> u8 { a + b }\n";
+        let different = "fn sub(a: u8, b: u8) -> u8 {\n    a - b\n}\n";
+        let args = Args::try_parse_from(["ruloc", "--dir", ".", "--duplicates"]).unwrap();
+        let options = args.analysis_options().unwrap();
+        let analyze = |path: &str, content: &str| {
+            analyze_content(
+                Path::new(path),
+                Cow::Borrowed(content),
+                0,
+                Edition::CURRENT,
+                &options,
+            )
+        };
+
+        let a = analyze("src/a.rs", original);
+        assert!(a.content_hashes.is_some());
+        let mut summary = Summary::default();
+        summary.add_file(&analyze("src/z.rs", original));
+        summary.add_file(&analyze("src/sub.rs", different));
+        summary.add_file(&a);
+        assert_eq!(summary.duplicates.len(), 1);
+        assert!(summary.duplicates[0].identical);
+        summary.add_file(&analyze("src/copy.rs", reformatted));
+        assert!(!summary.duplicates[0].identical);
+        let paths: Vec<&str> = summary.duplicates[0]
+            .files
+            .iter()
+            .map(|file| file.path.as_str())
+            .collect();
+        assert_eq!(paths, ["src/z.rs", "src/a.rs", "src/copy.rs"]);
+
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(
+            json["duplicates"][0]["files"][0],
+            serde_json::json!({"path": "src/z.rs", "lines": 3})
+        );
+        assert!(json.get("unique_contents").is_none());
+
+        let plain = Args::try_parse_from(["ruloc", "--dir", "."]).unwrap();
+        let stats = analyze_content(
+            Path::new("src/a.rs"),
+            Cow::Borrowed(original),
+            0,
+            Edition::CURRENT,
+            &plain.analysis_options().unwrap(),
+        );
+        assert_eq!(stats.content_hashes, None);
+    }
+
+    /// Tests the token and syntax node size measures selected with --count.
+    #[test]
+    fn test_count_tokens_and_nodes() {
+        let code = "// answer\nfn f() -> u8 { 42 }\n";
+        let args =
+            Args::try_parse_from(["ruloc", "--dir", ".", "--count", "tokens,nodes"]).unwrap();
+        let options = args.analysis_options().unwrap();
+        assert_eq!(
+            options.size_measures,
+            [SizeMeasure::Tokens, SizeMeasure::Nodes]
+        );
+
+        let stats = analyze_content(
+            Path::new("src/lib.rs"),
+            Cow::Borrowed(code),
+            0,
+            Edition::CURRENT,
+            &options,
+        );
+        assert_eq!(
+            stats.tokens,
+            Some(TokenCounts {
+                all: 18,
+                non_trivia: 9,
+            })
+        );
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        assert_eq!(stats.nodes, Some(root.descendants().count()));
+
+        let mut summary = Summary::default();
+        summary.add_file(&stats);
+        summary.add_file(&stats);
+        assert_eq!(summary.tokens.unwrap().non_trivia, 18);
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(json["tokens"]["non-trivia"], 18);
+
+        let only_nodes = Args::try_parse_from(["ruloc", "--dir", ".", "--count", "nodes"]).unwrap();
+        let stats = analyze_content(
+            Path::new("src/lib.rs"),
+            Cow::Borrowed(code),
+            0,
+            Edition::CURRENT,
+            &only_nodes.analysis_options().unwrap(),
+        );
+        assert_eq!(stats.tokens, None);
+        assert!(stats.nodes.is_some());
+        assert!(Args::try_parse_from(["ruloc", "--dir", ".", "--count", "words"]).is_err());
+    }
+
+    /// Tests that statement counts ignore how code is wrapped across lines.
+    #[test]
+    fn test_compute_statement_count() {
+        let compact = r#"use std::fmt;
+struct Point { x: u8 }
+impl Point { fn norm(&self) -> u8 { let x = self.x; println!("{}", x); x * x } }
+macro_rules! twice { ($e:expr) => { $e; $e }; }
+"#;
+        let wrapped = r#"use std::fmt;
+
+struct Point {
+    x: u8,
+}
+
+impl Point {
+    fn norm(&self) -> u8 {
+        let x =
+            self.x;
+        println!(
+            "{}",
+            x
+        );
+        x
+            * x
+    }
+}
+
+macro_rules! twice {
+    ($e:expr) => {
+        $e;
+        $e
+    };
+}
+"#;
+        let count = |code: &str| {
+            compute_statement_count(&SourceFile::parse(code, Edition::CURRENT).syntax_node())
+        };
+
+        // use, struct, impl, fn, let, println! statement, tail expression, macro_rules
+        assert_eq!(count(compact), 8);
+        assert_eq!(count(wrapped), 8);
+
+        let args = Args::try_parse_from(["ruloc", "--dir", ".", "--count", "statements"]).unwrap();
+        let stats = analyze_content(
+            Path::new("src/lib.rs"),
+            Cow::Borrowed(compact),
+            0,
+            Edition::CURRENT,
+            &args.analysis_options().unwrap(),
+        );
+        assert_eq!(stats.statements, Some(8));
+        let mut summary = Summary::default();
+        summary.add_file(&stats);
+        summary.add_file(&stats);
+        assert_eq!(summary.statements, Some(16));
+    }
+
+    /// Tests that `--group-by module` regroups files and inline modules by module path.
+    #[test]
+    fn test_group_by_module() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_group_by_module");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let src = temp_dir.join("src");
+        fs::create_dir_all(src.join("parser")).unwrap();
+        fs::create_dir_all(src.join("util")).unwrap();
+        fs::write(temp_dir.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        fs::write(
+            src.join("lib.rs"),
+            "mod parser;\nmod util;\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn t() {}\n}\n",
+        )
+        .unwrap();
+        fs::write(src.join("parser.rs"), "mod lexer;\npub fn parse() {}\n").unwrap();
+        fs::write(src.join("parser").join("lexer.rs"), "pub fn lex() {}\n").unwrap();
+        fs::write(src.join("util").join("mod.rs"), "pub fn helper() {}\n").unwrap();
+        fs::write(src.join("stray.rs"), "fn stray() {}\n").unwrap();
+
+        let source = InputSource::Directory(temp_dir.clone());
+        let options = AnalysisOptions {
+            inline_modules: true,
+            ..Default::default()
+        };
+        let mut accumulator = InMemoryAccumulator::new();
+        analyze_input(&source, &options, &mut accumulator, None).unwrap();
+
+        let modules = ModuleMap::resolve(&source, SymlinkPolicy::default());
+        let output_options = OutputOptions {
+            modules: Some(modules),
+            ..Default::default()
+        };
+        let (summary, files) = output_options.ordered_report(&accumulator).unwrap();
+        let entries: Vec<FileStats> = files.collect();
+        let paths: Vec<&str> = entries.iter().map(|entry| entry.path.as_str()).collect();
+
+        assert_eq!(summary.files, 5);
+        assert_eq!(
+            paths,
+            [
+                src.join("stray.rs").to_string_lossy().as_ref(),
+                "crate",
+                "crate::parser",
+                "crate::parser::lexer",
+                "crate::tests",
+                "crate::util",
+            ]
+        );
+        assert_eq!(entries[1].total.all_lines, 3);
+        assert_eq!(entries[1].production.code_lines, 2);
+        assert_eq!(entries[4].total.all_lines, 5);
+        assert_eq!(entries[4].test.code_lines, 5);
+        assert!(entries.iter().all(|entry| entry.inline_modules.is_empty()));
+        assert_eq!(output_options.table_headers()[0], "Module");
+        assert_eq!(OutputOptions::default().table_headers(), TABLE_HEADERS);
+
+        // Single-line inline modules take their lines out of the enclosing module
+        fs::write(
+            src.join("lib.rs"),
+            "//! Demo.\n\npub fn one() -> u32 {\n    1\n}\n\n#[cfg(test)]\nmod tests { #[test] fn t() {} }\nmod b { pub fn c() {} } pub fn d() {}\npub fn e() {}\n",
+        )
+        .unwrap();
+        let mut accumulator = InMemoryAccumulator::new();
+        analyze_input(&source, &options, &mut accumulator, None).unwrap();
+        let (summary, files) = output_options.ordered_report(&accumulator).unwrap();
+        let entries: Vec<FileStats> = files.collect();
+        let lines = |path: &str| {
+            let entry = entries.iter().find(|entry| entry.path == path).unwrap();
+            (entry.total.all_lines, entry.total.code_lines)
+        };
+        assert_eq!(lines("crate"), (7, 4));
+        assert_eq!(lines("crate::tests"), (2, 2));
+        assert_eq!(lines("crate::b"), (1, 1));
+        let code_lines: usize = entries.iter().map(|entry| entry.total.code_lines).sum();
+        let all_lines: usize = entries.iter().map(|entry| entry.total.all_lines).sum();
+        assert_eq!(
+            (all_lines, code_lines),
+            (summary.total.all_lines, summary.total.code_lines)
+        );
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests the --fail-on-empty, --min-files, and --min-code-lines scope gates.
+    #[test]
+    fn test_scope_gates() {
+        let mut summary = Summary::default();
+        summary.add_file(&FileStats {
+            path: "src/lib.rs".to_string(),
+            total: LineStats {
+                all_lines: 12,
+                code_lines: 10,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        let empty = Args {
+            fail_on_empty: true,
+            ..Default::default()
+        };
+        assert!(check_gates(&empty, &summary, &[]).is_ok());
+        let err = check_gates(&empty, &Summary::default(), &[])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("0 file(s) with no code were analyzed"));
+
+        let min_files = Args {
+            min_files: Some(2),
+            ..Default::default()
+        };
+        let err = check_gates(&min_files, &summary, &[])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("only 1 file(s) were analyzed, fewer than the minimum of 2"));
+
+        let min_code_lines = Args {
+            min_code_lines: Some(10),
+            ..Default::default()
+        };
+        assert!(check_gates(&min_code_lines, &summary, &[]).is_ok());
+        let err = check_gates(
+            &Args {
+                min_code_lines: Some(11),
+                ..Default::default()
+            },
+            &summary,
+            &[],
+        )
+        .unwrap_err();
+        assert_eq!(err.exit_code(), 1);
+        assert!(err.to_string().contains("only 10 code line(s)"));
+    }
+
+    /// Tests matching of single- and multi-dot source extensions from --ext.
+    #[test]
+    fn test_source_extensions() {
+        let default = SourceExtensions::default();
+        assert!(default.matches(Path::new("src/lib.rs")));
+        assert!(!default.matches(Path::new("src/lib.rs.in")));
+        assert!(!default.matches(Path::new("src/.rs")));
+
+        let extensions = SourceExtensions::new(&[".rs".to_string(), "rs.in".to_string()]);
+        assert!(extensions.matches(Path::new("src/lib.rs")));
+        assert!(extensions.matches(Path::new("src/gen/table.rs.in")));
+        assert!(!extensions.matches(Path::new("src/gen/table.in")));
+        assert_eq!(SourceExtensions::new(&[" ".to_string()]), default);
+
+        let args = Args::try_parse_from(["ruloc", "--dir", "src", "--ext", "rs,rs.in"]).unwrap();
+        assert_eq!(
+            args.analysis_options().unwrap().extensions,
+            SourceExtensions(vec!["rs".to_string(), "rs.in".to_string()])
+        );
+    }
+
+    /// Tests that build scripts and proc-macro crates are reported apart from production code.
+    #[test]
+    fn test_tooling_classification() {
+        let temp_dir = std::env::temp_dir().join("test_ruloc_tooling");
+        let _ = fs::remove_dir_all(&temp_dir);
+        let app = temp_dir.join("app");
+        let derive = temp_dir.join("derive");
+        fs::create_dir_all(app.join("src")).unwrap();
+        fs::create_dir_all(app.join("build")).unwrap();
+        fs::create_dir_all(derive.join("src")).unwrap();
+        fs::write(app.join("Cargo.toml"), "[package]\nname = \"app\"\n").unwrap();
+        fs::write(
+            app.join("build.rs"),
+            "#[path = \"build/codegen.rs\"]\nmod codegen;\nfn main() {}\n",
+        )
+        .unwrap();
+        fs::write(app.join("build").join("codegen.rs"), "pub fn emit() {}\n").unwrap();
+        fs::write(app.join("src").join("lib.rs"), "pub fn run() {}\n").unwrap();
+        fs::write(
+            derive.join("Cargo.toml"),
+            "[package]\nname = \"derive\"\n\n[lib]\nproc-macro = true\n",
+        )
+        .unwrap();
+        fs::write(
+            derive.join("src").join("lib.rs"),
+            "pub fn expand() {}\n\n#[cfg(test)]\nmod tests {\n    #[test]\n    fn t() {}\n}\n",
+        )
+        .unwrap();
+
+        let options = AnalysisOptions::default();
+        let build = analyze_file(&app.join("build.rs"), &options).unwrap();
+        assert_eq!(build.build_script.code_lines, 3);
+        assert_eq!(build.production, LineStats::default());
+        let codegen = analyze_file(&app.join("build").join("codegen.rs"), &options).unwrap();
+        assert_eq!(codegen.build_script.code_lines, 1);
+        let lib = analyze_file(&app.join("src").join("lib.rs"), &options).unwrap();
+        assert_eq!(lib.production.code_lines, 1);
+        assert_eq!(lib.build_script, LineStats::default());
+
+        let proc_macro = analyze_file(&derive.join("src").join("lib.rs"), &options).unwrap();
+        assert_eq!(proc_macro.proc_macro.code_lines, 1);
+        assert_eq!(proc_macro.test.code_lines, 5);
+        assert_eq!(proc_macro.production, LineStats::default());
+
+        let mut summary = Summary::default();
+        for stats in [&build, &codegen, &lib, &proc_macro] {
+            summary.add_file(stats);
+        }
+        assert_eq!(summary.build_script.code_lines, 4);
+        assert_eq!(summary.proc_macro.code_lines, 1);
+
+        fs::remove_dir_all(&temp_dir).ok();
+    }
+
+    /// Tests that --quiet and --no-progress hide the progress bar and --quiet also per-file errors.
+    #[test]
+    fn test_args_quiet_and_no_progress() {
+        let parse = |flags: &[&str]| {
+            let mut argv = vec!["ruloc", "--dir", "src"];
+            argv.extend_from_slice(flags);
+            Args::try_parse_from(argv)
+                .unwrap()
+                .analysis_options()
+                .unwrap()
+        };
+
+        let default = parse(&[]);
+        assert!(default.progress);
+        assert!(!default.quiet_errors);
+
+        let no_progress = parse(&["--no-progress"]);
+        assert!(!no_progress.progress);
+        assert!(!no_progress.quiet_errors);
+
+        let quiet = parse(&["-q"]);
+        assert!(!quiet.progress);
+        assert!(quiet.quiet_errors);
+
+        assert!(Args::try_parse_from(["ruloc", "--dir", "src", "--quiet", "--verbose"]).is_err());
+    }
+
+    /// Tests that hidden progress bars leave log output alone and the bar template parses.
+    #[test]
+    fn test_active_progress_registration() {
+        let hidden = ProgressBar::hidden();
+        {
+            let _guard = ActiveProgress::register(&hidden);
+            assert!(ACTIVE_PROGRESS.lock().unwrap().is_none());
+            assert_eq!(ProgressAwareStderr.write(b"").unwrap(), 0);
+        }
+        assert!(ACTIVE_PROGRESS.lock().unwrap().is_none());
+        assert!(
+            ProgressStyle::default_bar()
+                .template(PROGRESS_TEMPLATE)
+                .is_ok()
+        );
+    }
+
+    /// Tests that --log-format json writes each record as one flat JSON object and
+    /// filters records below the default level.
+    #[test]
+    fn test_log_subscriber_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("diagnostics.jsonl");
+        let args = Args::try_parse_from(["ruloc", "--dir", "src", "--log-format", "json"]).unwrap();
+        assert_eq!(args.log_format, LogFormat::Json);
+
+        let file = Arc::new(fs::File::create(&path).unwrap());
+        tracing::subscriber::with_default(log_subscriber(&args, file), || {
+            warn!("Skipping {} \"x\"", 2);
+            trace!("Analyzing file: x.rs");
+        });
+
+        let output = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 1);
+        let record: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert!(record["timestamp"].is_string());
+        assert_eq!(record["level"], "WARN");
+        assert_eq!(record["target"], module_path!());
+        assert_eq!(record["message"], "Skipping 2 \"x\"");
+    }
+
+    /// Tests that atomically written reports keep the permissions of the file they replace
+    /// and otherwise get those of a plainly created file.
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomically_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let write = |path: &Path| {
+            write_atomically(path, |writer| {
+                writer
+                    .write_all(b"report\n")
+                    .map_err(RulocError::io("Failed to write"))
+            })
+            .unwrap()
+        };
+
+        let created = dir.path().join("created.json");
+        fs::File::create(&created).unwrap();
+        let new = dir.path().join("new.json");
+        write(&new);
+        assert_eq!(mode(&new), mode(&created));
+
+        let existing = dir.path().join("existing.json");
+        fs::write(&existing, "old\n").unwrap();
+        fs::set_permissions(&existing, fs::Permissions::from_mode(0o640)).unwrap();
+        write(&existing);
+        assert_eq!(mode(&existing), 0o640);
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "report\n");
+    }
+
+    /// Tests that the summary table is never colored when written to a file.
+    #[test]
+    fn test_colors_table_only_on_stdout() {
+        let to_file = OutputOptions {
+            color: true,
+            output: Some(PathBuf::from("report.txt")),
+            ..Default::default()
+        };
+        assert!(!to_file.colors_table());
+
+        let no_color = OutputOptions::default();
+        assert!(!no_color.colors_table());
+    }
+
+    /// Tests that each requested format gets its own destination and that formats
+    /// cannot share one.
+    #[test]
+    fn test_report_targets() {
+        let targets = |flags: &[&str]| {
+            let mut argv = vec!["ruloc", "--dir", "."];
+            argv.extend(flags);
+            Args::parse_from(argv).report_targets()
+        };
+        let target = |format, path: Option<&str>| ReportTarget {
+            format,
+            path: path.map(PathBuf::from),
+        };
+
+        assert_eq!(targets(&[]).unwrap(), [target(ReportKind::Table, None)]);
+        assert_eq!(
+            targets(&[
+                "--out-json",
+                "report.json",
+                "--out-html",
+                "report.html",
+                "--out-text"
+            ])
+            .unwrap(),
+            [
+                target(ReportKind::Table, None),
+                target(ReportKind::Json, Some("report.json")),
+                target(ReportKind::Html, Some("report.html")),
+            ]
+        );
+        assert_eq!(
+            targets(&["--out-text", "--out-text-verbose"]).unwrap(),
+            [target(ReportKind::Text, None)]
+        );
+        assert_eq!(
+            targets(&["--out-json", "--output", "out.json", "--out-text", "-"]).unwrap(),
+            [
+                target(ReportKind::Table, None),
+                target(ReportKind::Json, Some("out.json")),
+            ]
+        );
+
+        assert!(matches!(
+            targets(&["--out-json", "--out-html"]),
+            Err(RulocError::Config(_))
+        ));
+        assert!(matches!(
+            targets(&["--out-json", "same", "--out-html", "same"]),
+            Err(RulocError::Config(_))
+        ));
+    }
+
+    /// Tests that the HTML report escapes paths and closes the document.
+    #[test]
+    fn test_write_html_report() {
+        let file = FileStats {
+            path: "src/<a&b>.rs".to_string(),
+            total: LineStats {
+                code_lines: 4,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let summary = Summary {
+            files: 1,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write_html_report(
+            &mut out,
+            &summary,
+            std::iter::once(file),
+            &OutputOptions::default(),
+        )
+        .unwrap();
+        let html = String::from_utf8(out).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<tr><td>src/&lt;a&amp;b&gt;.rs</td><td>4</td>"));
+        assert!(html.contains("<td>Total (1 files)</td>"));
+        assert!(html.ends_with("</html>\n"));
+    }
+
+    /// Tests per-file sparklines drawn from reports stored by a SQLite sink.
+    #[test]
+    fn test_html_report_trends() {
+        assert_eq!(
+            render_sparkline(&[Some(10), None, Some(20)]),
+            "<svg class=\"trend\" width=\"80\" height=\"16\" viewBox=\"0 0 80 16\">\
+             <title>Code lines: 10, -, 20</title>\
+             <polyline fill=\"none\" stroke=\"currentColor\" points=\"0.0,15.0 80.0,1.0\"/></svg>"
+        );
+        assert!(render_sparkline(&[Some(5)]).contains("points=\"0.0,8.0\""));
+
+        if std::process::Command::new("sqlite3")
+            .arg("-version")
+            .output()
+            .is_err()
+        {
+            return;
+        }
+        let temp_dir = tempfile::tempdir().unwrap();
+        let database = temp_dir.path().join("metrics.db");
+        let mut sink = SqliteSink {
+            path: database.clone(),
+        };
+        for report in [
+            r#"{"files": [{"path": "src/a.rs", "total": {"code-lines": 3}}]}"#,
+            r#"{"files": [{"path": "src/a.rs", "total": {"code-lines": 5}},
+                          {"path": "src/b.rs", "total": {"code-lines": 7}}]}"#,
+            r#"{"files": [{"path": "src/b.rs", "total": {"code-lines": 8}}]}"#,
+        ] {
+            sink.deliver(report, ReportKind::Json).unwrap();
+        }
+        sink.deliver("Total 1", ReportKind::Table).unwrap();
+
+        let trends = load_file_trends(&database, 2).unwrap();
+        assert_eq!(trends["src/a.rs"], [Some(5), None]);
+        assert_eq!(trends["src/b.rs"], [Some(7), Some(8)]);
+        assert!(load_file_trends(&temp_dir.path().join("missing.db"), 2).is_err());
+
+        let options = OutputOptions {
+            trends: Some(trends),
+            ..Default::default()
+        };
+        let file = FileStats {
+            path: "src/b.rs".to_string(),
+            total: LineStats {
+                code_lines: 9,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        write_html_report(
+            &mut out,
+            &Summary::default(),
+            std::iter::once(file),
+            &options,
+        )
+        .unwrap();
+        let html = String::from_utf8(out).unwrap();
+        assert!(html.contains("<th>Trend</th>"));
+        assert!(html.contains("<title>Code lines: 7, 8, 9</title>"));
+    }
+
+    /// Tests projecting when code lines reach a budget from stored runs.
+    #[test]
+    fn test_project_budget() {
+        assert_eq!(parse_line_count("500k").unwrap(), 500_000);
+        assert_eq!(parse_line_count("1.5M").unwrap(), 1_500_000);
+        assert_eq!(parse_line_count("800").unwrap(), 800);
+        assert!(parse_line_count("-1k").is_err());
+        assert!(parse_line_count("5g").is_err());
+
+        let day = SECONDS_PER_DAY as u64;
+        let start = 1_700_006_400; // 2023-11-15T00:00:00Z
+        let history = [
+            (start, 1_000),
+            (start + day, 1_100),
+            (start + 2 * day, 1_200),
+        ];
+        let projection = project_budget(&history, 2_000).unwrap();
+        assert_eq!(projection.current, 1_200);
+        assert!((projection.lines_per_day - 100.0).abs() < 1e-9);
+        assert_eq!(projection.reached_at, Some(start + 10 * day));
+        assert_eq!(
+            format_budget_projection(&projection),
+            "Code lines: 1200 of 2000 budget (60.0%)\n\
+             Growth: +100.0 lines/day over the last 3 runs\n\
+             Projected to reach the budget on 2023-11-25 (in 8 days)"
+        );
+
+        let shrinking = project_budget(&[(start, 1_200), (start + day, 1_000)], 2_000).unwrap();
+        assert_eq!(shrinking.reached_at, None);
+        assert!(format_budget_projection(&shrinking).ends_with("not projected to be reached"));
+        let exceeded = project_budget(&history, 1_000).unwrap();
+        assert!(format_budget_projection(&exceeded).ends_with("already exceeded"));
+        assert_eq!(project_budget(&history[..1], 2_000), None);
+        assert_eq!(project_budget(&[(start, 1), (start, 2)], 2_000), None);
+
+        let args =
+            Args::try_parse_from(["ruloc", "project", "--db", "m.db", "--budget", "500k"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Project {
+                budget: 500_000,
+                runs: 10,
+                ..
+            })
+        ));
+        assert!(
+            Args::try_parse_from([
+                "ruloc", "project", "--db", "m.db", "--budget", "1", "--runs", "1"
+            ])
+            .is_err()
+        );
+        assert!(Args::try_parse_from(["ruloc", "--dir", ".", "--budget", "1k"]).is_err());
+    }
+
+    /// Writes one report to stdout from an accumulator.
+    fn output_to_stdout<A: StatsAccumulator>(
+        format: ReportKind,
+        accumulator: &A,
+    ) -> Result<(), RulocError> {
+        let target = ReportTarget { format, path: None };
+        output_reports(accumulator, &[target], &OutputOptions::default())
+    }
+
+    /// Tests that ruloc:ignore and ruloc:test directives override classification.
+    #[test]
+    fn test_classification_directives() {
+        let code = "fn kept() {}
+
+// ruloc:ignore-start
+fn generated() {
+    let x = 1;
+}
+// ruloc:ignore-end
+
+// ruloc:test
+fn fixture() -> u32 {
+    42
+}
+fn helper() {} // ruloc:test
+/// Mentions // ruloc:test without being a directive.
+fn documented() {}
+";
+        let parsed = ParsedSource::parse(code, Edition::CURRENT);
+        let ignored: Vec<usize> = (0..parsed.is_ignored_line.len())
+            .filter(|&line| parsed.is_ignored_line[line])
+            .collect();
+        assert_eq!(ignored, [2, 3, 4, 5, 6]);
+        let test: Vec<usize> = (0..parsed.is_test_line.len())
+            .filter(|&line| parsed.is_test_line[line])
+            .collect();
+        assert_eq!(test, [8, 9, 10, 11, 12]);
+
+        let stats = analyze_content(
+            Path::new("src/lib.rs"),
+            Cow::Borrowed(code),
+            0,
+            Edition::CURRENT,
+            &AnalysisOptions::default(),
+        );
+        assert_eq!(stats.ignored_lines, 5);
+        assert_eq!(stats.total.all_lines, 10);
+        assert_eq!(stats.test.code_lines, 3);
+        assert_eq!(stats.test.comment_lines, 2);
+        assert_eq!(stats.production.code_lines, 2);
+
+        // An unterminated ignore block extends to the end of the file
+        let parsed = ParsedSource::parse(
+            "fn a() {}\n// ruloc:ignore-start\nfn b() {}\n",
+            Edition::CURRENT,
+        );
+        assert_eq!(parsed.is_ignored_line, [false, true, true]);
+    }
+
+    /// Tests that --max-files and --max-total-size stop the run once exceeded.
+    #[test]
+    fn test_discovery_limits() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.rs", "b.rs", "c.rs"] {
+            fs::write(dir.path().join(name), "fn f() {}\n").unwrap();
+        }
+        let analyze = |limits| {
+            let options = AnalysisOptions {
+                limits,
+                ..Default::default()
+            };
+            analyze_directory(dir.path(), &options, &mut InMemoryAccumulator::new())
+        };
+
+        assert!(
+            analyze(DiscoveryLimits {
+                max_files: Some(3),
+                max_total_size: Some(30),
+            })
+            .is_ok()
+        );
+        let too_many = analyze(DiscoveryLimits {
+            max_files: Some(2),
+            ..Default::default()
+        });
+        assert!(
+            matches!(&too_many, Err(RulocError::Analysis(message)) if message.contains("--max-files"))
+        );
+        let too_large = analyze(DiscoveryLimits {
+            max_total_size: Some(29),
+            ..Default::default()
+        });
+        assert!(
+            matches!(&too_large, Err(RulocError::Analysis(message)) if message.contains("--max-total-size"))
+        );
+
+        let args = Args::parse_from([
+            "ruloc",
+            "--dir",
+            ".",
+            "--max-files",
+            "10",
+            "--max-total-size",
+            "2KB",
+        ]);
+        let limits = args.analysis_options().unwrap().limits;
+        assert_eq!(
+            (limits.max_files, limits.max_total_size),
+            (Some(10), Some(2048))
+        );
+    }
+
+    /// Tests parsing `--sample` sizes and drawing reproducible samples.
+    #[test]
+    fn test_sample_selection() {
+        assert_eq!(parse_sample_size("10%").unwrap(), SampleSize::Percent(10.0));
+        assert_eq!(parse_sample_size(" 500 ").unwrap(), SampleSize::Files(500));
+        for invalid in ["0", "0%", "101%", "-5", "ten"] {
+            assert!(parse_sample_size(invalid).is_err(), "{}", invalid);
+        }
+        assert_eq!(SampleSize::Percent(10.0).files(25), 3);
+        assert_eq!(SampleSize::Percent(100.0).files(25), 25);
+        assert_eq!(SampleSize::Files(500).files(25), 25);
+        assert_eq!(SampleSize::Files(5).files(0), 0);
+
+        let paths: Vec<PathBuf> = (0..50)
+            .map(|i| PathBuf::from(format!("{}.rs", i)))
+            .collect();
+        let sampling = Sampling::new(SampleSize::Files(10), 42);
+        let first = sampling.select(paths.clone());
+        let mut shuffled = paths.clone();
+        shuffled.reverse();
+        let second = sampling.select(shuffled);
+        assert_eq!(first.len(), 10);
+        assert_eq!(first, second);
+        assert!(first.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_ne!(Sampling::new(SampleSize::Files(10), 7).select(paths), first);
+        assert_eq!(sampling.population.load(Ordering::Relaxed), 100);
+    }
+
+    /// Tests extrapolating sampled totals with confidence margins.
+    #[test]
+    fn test_sample_estimate() {
+        let file = |code_lines| FileStats {
+            total: LineStats {
+                all_lines: code_lines,
+                code_lines,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let estimate_of = |sample: &SampleEstimate, metric: &str| {
+            sample
+                .estimates
+                .iter()
+                .find(|estimate| estimate.metric == metric)
+                .cloned()
+                .unwrap()
+        };
+
+        let sampling = Sampling::new(SampleSize::Files(4), 1);
+        sampling.population.store(40, Ordering::Relaxed);
+        let sample = sampling.estimate([10, 20, 30, 40].into_iter().map(file));
+        assert_eq!((sample.population_files, sample.sampled_files), (40, 4));
+        assert_eq!(sample.confidence, 95);
+        let code = estimate_of(&sample, "code-lines");
+        assert_eq!(code.value, 1000);
+        // sd = 12.91, se = 40 * sqrt(0.9 * 166.67 / 4) = 244.9, margin = 1.96 * se
+        assert_eq!(code.margin, Some(480));
+        assert_eq!(estimate_of(&sample, "test-code-lines").value, 0);
+
+        let census = Sampling::new(SampleSize::Percent(100.0), 1);
+        census.population.store(2, Ordering::Relaxed);
+        let sample = census.estimate([5, 7].into_iter().map(file));
+        let code = estimate_of(&sample, "code-lines");
+        assert_eq!((code.value, code.margin), (12, Some(0)));
+
+        let single = Sampling::new(SampleSize::Files(1), 1);
+        single.population.store(10, Ordering::Relaxed);
+        let sample = single.estimate([5].into_iter().map(file));
+        let code = estimate_of(&sample, "code-lines");
+        assert_eq!((code.value, code.margin), (50, None));
+        assert!(format_sample_estimate(&sample).contains("code lines 50 ± ?"));
+    }
+
+    /// Tests that a dry run reports discovered, oversized, and excluded files.
+    #[test]
+    fn test_dry_run_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("lib.rs"), "fn a() {}\n").unwrap();
+        fs::write(dir.path().join("big.rs"), "x".repeat(100)).unwrap();
+        fs::write(dir.path().join("api.pb.rs"), "fn b() {}\n").unwrap();
+        fs::write(dir.path().join("notes.txt"), "not rust").unwrap();
+
+        let options = AnalysisOptions {
+            max_file_size: Some(50),
+            generated: Some(GeneratedRules::new(&[], &["*.pb.rs".to_string()]).unwrap()),
+            limits: DiscoveryLimits {
+                max_files: Some(2),
+                max_total_size: None,
+            },
+            sampling: Some(Arc::new(Sampling::new(SampleSize::Percent(50.0), 1))),
+            ..Default::default()
+        };
+        let dry_run = dry_run_directory(dir.path(), &options);
+        let names = |files: &[DiscoveredFile]| -> Vec<String> {
+            files
+                .iter()
+                .map(|file| file.path.rsplit('/').next().unwrap().to_string())
+                .collect()
+        };
+        assert_eq!(names(&dry_run.files), ["api.pb.rs", "lib.rs"]);
+        assert_eq!(dry_run.total_bytes, 20);
+        assert_eq!(names(&dry_run.oversized), ["big.rs"]);
+        assert_eq!(dry_run.excluded.len(), 1);
+        assert!(dry_run.excluded[0].path.ends_with("api.pb.rs"));
+        assert_eq!(dry_run.sampled_files, Some(2));
+        assert!(
+            dry_run
+                .limit_exceeded
+                .as_deref()
+                .unwrap()
+                .contains("--max-files")
+        );
+
+        let mut output = Vec::new();
+        write_dry_run(&mut output, Path::new("src"), &dry_run).unwrap();
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.starts_with("Dry run of src:\n  Files to analyze: 2 (20 bytes)\n"));
+        assert!(text.contains("  Skipped by --max-file-size:\n"));
+        assert!(text.contains("big.rs (100 bytes)\n"));
+
+        let root = dir.path().canonicalize().unwrap();
+        let mut dry_run = dry_run_directory(&root.join("."), &options);
+        dry_run.normalize_paths(&PathNormalizer {
+            style: PathStyle::Relative,
+            root: root.clone(),
+        });
+        assert_eq!(dry_run.files[1].path, "lib.rs");
+        assert_eq!(dry_run.oversized[0].path, "big.rs");
+        assert_eq!(dry_run.excluded[0].path, "api.pb.rs");
+    }
+
+    /// Tests that cfg predicates mark code as test code only when it requires `test`.
+    #[test]
+    fn test_cfg_predicate_test_semantics() {
+        let predicate = |attr: &str| {
+            let content = format!("#[cfg{}]\nfn f() {{}}\n", attr);
+            let root = SourceFile::parse(&content, Edition::CURRENT).syntax_node();
+            let attr = root.descendants().find_map(ast::Attr::cast).unwrap();
+            CfgPredicate::parse(&attr.token_tree().unwrap())
+        };
+        let requires_test = |attr: &str| predicate(attr).unwrap().requires_test();
+
+        assert!(requires_test("(test)"));
+        assert!(requires_test("(all(test, unix))"));
+        assert!(requires_test("(all(unix, not(any(not(test), windows)),))"));
+        assert!(!requires_test("(all(unix, any(test, miri)))"));
+        assert!(requires_test("(not(not(test)))"));
+        assert!(requires_test("(any(test, false))"));
+        assert!(!requires_test("(not(test))"));
+        assert!(!requires_test("(any(test, feature = \"testing\"))"));
+        assert!(!requires_test("(all(not(test), unix))"));
+        assert!(!requires_test("(feature = \"test\")"));
+        assert!(!requires_test("(test_utils)"));
+        assert!(!requires_test("(any(unix, windows))"));
+
+        assert_eq!(
+            predicate("(any(test, feature = \"x\"))"),
+            Some(CfgPredicate::Any(vec![
+                CfgPredicate::Option {
+                    name: "test".to_string(),
+                    value: None
+                },
+                CfgPredicate::Option {
+                    name: "feature".to_string(),
+                    value: Some("\"x\"".to_string())
+                },
+            ]))
+        );
+        for malformed in ["(not(test, unix))", "(test unix)", "(maybe(test))", "()"] {
+            assert_eq!(predicate(malformed), None, "{}", malformed);
+        }
+
+        let content = "#[cfg(not(test))]\nfn production() {}\n\n#[cfg(any(test, feature = \"testing\"))]\nmod helpers {}\n\n#[cfg(all(test, unix))]\nmod tests {}\n";
+        let is_test = classify_lines(content, Edition::CURRENT);
+        assert_eq!(is_test[..5], [false; 5]);
+        assert_eq!(is_test[6..], [true; 2]);
+    }
+
+    /// Tests test detection through `cfg_attr` and test attribute macros.
+    #[test]
+    fn test_test_attribute_detection() {
+        let is_test_fn = |attrs: &str| {
+            let content = format!("{}\nfn f() {{}}\n", attrs);
+            let root = SourceFile::parse(&content, Edition::CURRENT).syntax_node();
+            let func = root
+                .descendants()
+                .find(|node| ast::Fn::can_cast(node.kind()));
+            is_test_node(&func.unwrap())
+        };
+
+        for attrs in [
+            "#[test]",
+            "#[tokio::test(flavor = \"multi_thread\")]",
+            "#[test_case(1 ; \"one\")]\n#[test_case(2)]",
+            "#[test_case(1)]\n#[test]",
+            "#[test]\n#[test_case(1)]",
+            "#[rstest::rstest]",
+            "#[cfg_attr(feature = \"x\", test)]",
+            "#[cfg_attr(all(unix, feature = \"x\"), ignore, tokio::test)]",
+            "#[cfg_attr(unix, cfg_attr(feature = \"x\", test_case(1)))]",
+        ] {
+            assert!(is_test_fn(attrs), "{}", attrs);
+        }
+        for attrs in [
+            "#[inline]",
+            "#[testing]",
+            "#[cfg_attr(test, inline)]",
+            "#[cfg_attr(feature = \"test\", derive(Debug))]",
+            "#[my::test_helper]",
+        ] {
+            assert!(!is_test_fn(attrs), "{}", attrs);
+        }
+    }
+
+    /// Tests that test-only helpers and test file name conventions count as test code.
+    #[test]
+    fn test_test_helpers_and_file_names() {
+        let content = "pub fn prod() {}\n\n#[cfg(test)]\nfn make_fixture() -> u32 {\n    1\n}\n\n#[cfg(test)]\nimpl Default for Prod {\n    fn default() -> Self { Prod }\n}\n\n#[cfg(test)]\nuse std::collections::HashMap;\n\n#[cfg(not(test))]\nconst LIMIT: u32 = 1;\n";
+        let is_test = classify_lines(content, Edition::CURRENT);
+        assert!(!is_test[0]);
+        assert!(is_test[2..=5].iter().all(|&test| test));
+        assert!(is_test[7..=10].iter().all(|&test| test));
+        assert!(is_test[12..=13].iter().all(|&test| test));
+        assert_eq!(is_test[15..], [false, false]);
+
+        for name in ["parser_test.rs", "src/test_util.rs", "dir/test_gen.rs.in"] {
+            assert!(is_test_file_name(Path::new(name)), "{}", name);
+        }
+        for name in [
+            "tests.rs",
+            "src/testing.rs",
+            "latest.rs",
+            "contest_test/lib.rs",
+        ] {
+            assert!(!is_test_file_name(Path::new(name)), "{}", name);
+        }
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("parser_test.rs");
+        fs::write(&file, "fn helper() {}\n\nfn check() {\n    helper();\n}\n").unwrap();
+        let stats = analyze_file(&file, &AnalysisOptions::default()).unwrap();
+        assert_eq!((stats.production.code_lines, stats.test.code_lines), (4, 0));
+        let options = AnalysisOptions {
+            test_file_names: true,
+            ..Default::default()
+        };
+        let stats = analyze_file(&file, &options).unwrap();
+        assert_eq!((stats.production.code_lines, stats.test.code_lines), (0, 4));
+        assert_eq!(stats.test.blank_lines, 1);
+    }
+
+    /// Tests counting visible and hidden lines of doc examples with --doc-detail.
+    #[test]
+    fn test_doc_example_lines() {
+        let volume = DocVolume::of(
+            " Example:\n ```\n # use std::fmt;\n #\n let x = 1;\n ## not hidden\n #[derive(Debug)]\n ```\n ```text\n # plain text\n ```\n ~~~~no_run,edition2021\n # fn main() {\n run();\n ```\n # }\n ~~~~\n # Heading",
+        );
+        assert_eq!((volume.example_lines, volume.hidden_example_lines), (5, 4));
+        assert_eq!(
+            format_doc_volume(&volume),
+            format!(
+                "{} words, {} characters, 5 example lines (4 hidden)",
+                volume.words, volume.characters
+            )
+        );
+        assert_eq!(code_fence("```rust,ignore"), Some(("```", true)));
+        assert_eq!(code_fence("````toml"), Some(("````", false)));
+        assert_eq!(code_fence("``inline``"), None);
+
+        // Fences inside a block only close it when bare and at least as long as the opener
+        let nested = DocVolume::of(
+            " ````\n ```rust\n let x = 1;\n ```\n ````\n ```\n ```ignore\n # hidden\n ```",
+        );
+        assert_eq!((nested.example_lines, nested.hidden_example_lines), (4, 1));
+        assert!(closes_fence("```` ", "```"));
+        assert!(!closes_fence("```", "````"));
+        assert!(!closes_fence("~~~", "```"));
+
+        let code = "//! ```\n//! # fn hidden() {}\n//! ```\n\n/// ```\n/// # let x = 1;\n/// assert_eq!(x, 1);\n/// ```\npub fn checked() {}\n";
+        let root = SourceFile::parse(code, Edition::CURRENT).syntax_node();
+        let detail = compute_doc_detail(&root, &LineIndex::new(code));
+        assert_eq!(
+            (
+                detail.volume.example_lines,
+                detail.volume.hidden_example_lines
+            ),
+            (1, 2)
+        );
+        assert_eq!(
+            (
+                detail.items[0].volume.example_lines,
+                detail.items[0].volume.hidden_example_lines
+            ),
+            (1, 1)
+        );
+        let json = serde_json::to_value(detail.volume).unwrap();
+        assert_eq!(json["hidden-example-lines"], 2);
+    }
+
+    /// Tests counting non-Rust files into a repository-wide roll-up with --all-languages.
+    #[test]
+    fn test_all_languages() {
+        let toml = PlainLanguage::of(Path::new("Cargo.toml")).unwrap();
+        assert_eq!(
+            toml.count_lines("# Manifest\n[package]\n\nname = \"x\" # inline\n"),
+            LanguageLines {
+                files: 1,
+                all_lines: 4,
+                blank_lines: 1,
+                comment_lines: 1,
+                code_lines: 2,
+            }
+        );
+        let markdown = PlainLanguage::of(Path::new("docs/README.MD")).unwrap();
+        let lines = markdown.count_lines("# Title\n<!-- one -->\n<!--\nmore\n-->\nText\n");
+        assert_eq!((lines.comment_lines, lines.code_lines), (4, 2));
+        assert_eq!(PlainLanguage::of(Path::new("ci.yml")).unwrap().name, "YAML");
+        assert_eq!(
+            PlainLanguage::of(Path::new("build.sh")).unwrap().name,
+            "Shell"
+        );
+        assert!(PlainLanguage::of(Path::new("main.rs")).is_none());
+        assert!(PlainLanguage::of(Path::new("Makefile")).is_none());
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(temp_dir.path().join("target")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("src")).unwrap();
+        fs::write(
+            temp_dir.path().join("Cargo.toml"),
+            "[package]\nname = \"x\"\n",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("target").join("out.toml"), "a = 1\n").unwrap();
+        fs::write(temp_dir.path().join("run.sh"), "#!/bin/sh\n\necho hi\n").unwrap();
+        fs::write(
+            temp_dir.path().join("src").join("lib.rs"),
+            "/// Doc.\n// Note.\npub fn f() {}\n",
+        )
+        .unwrap();
+
+        let args = Args::try_parse_from([
+            "ruloc",
+            "--dir",
+            temp_dir.path().to_str().unwrap(),
+            "--all-languages",
+        ])
+        .unwrap();
+        let mut accumulator = InMemoryAccumulator::new();
+        let outcome = analyze_input(
+            &args.input_source(),
+            &args.analysis_options().unwrap(),
+            &mut accumulator,
+            None,
+        )
+        .unwrap();
+        let mut options = OutputOptions::default();
+        options.record(outcome);
+        let languages = options.languages(&accumulator.get_summary()).unwrap();
+        assert_eq!(
+            languages.languages.keys().collect::<Vec<_>>(),
+            ["Rust", "Shell", "TOML"]
+        );
+        assert_eq!(languages.languages["Rust"].comment_lines, 2);
+        assert_eq!(languages.languages["TOML"].files, 1);
+        assert_eq!(
+            languages.total,
+            LanguageLines {
+                files: 3,
+                all_lines: 8,
+                blank_lines: 1,
+                comment_lines: 3,
+                code_lines: 4,
+            }
+        );
+        assert_eq!(
+            format_language_lines(&languages.total),
+            "3 files, 8 lines (4 code, 3 comment, 1 blank)"
+        );
+
+        let json = serde_json::to_value(&languages).unwrap();
+        assert_eq!(json["total"]["code-lines"], 4);
+        assert!(
+            OutputOptions::default()
+                .languages(&Summary::default())
+                .is_none()
+        );
+        assert!(Args::try_parse_from(["ruloc", "--file", "x.rs", "--all-languages"]).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+use super::*;
+use std::ffi::{CStr, CString, c_char};
+use std::ptr;
+
+/// Outcome of analyzing one file, handed to C callers as an opaque pointer.
+pub struct RulocReport {
+    /// Statistics of the file, or the reason it could not be analyzed.
+    result: Result<FileStats, RulocError>,
+
+    /// JSON rendering of the outcome, created on first request.
+    json: Option<CString>,
+}
+
+impl RulocReport {
+    /// Renders the outcome as JSON.
+    ///
+    /// # Returns
+    ///
+    /// The file statistics as in `--out-json` reports, or an object with `error` and
+    /// `exit-code` fields when the analysis failed
+    fn to_json(&self) -> CString {
+        let json = match &self.result {
+            Ok(stats) => serde_json::to_string(stats),
+            Err(e) => serde_json::to_string(&serde_json::json!({
+                "error": e.to_string(),
+                "exit-code": e.exit_code(),
+            })),
+        }
+        .unwrap_or_else(|e| format!("{{\"error\":\"{}\"}}", e));
+        CString::new(json).expect("serde_json escapes NUL bytes")
+    }
+}
+
+/// Analyzes a single Rust file with the default options.
+///
+/// Analysis errors do not return null; they are reported by
+/// [`ruloc_report_to_json`].
+///
+/// # Arguments
+///
+/// * `path` - NUL-terminated UTF-8 path of the file
+///
+/// # Returns
+///
+/// A report to release with [`ruloc_free`], or null if `path` is null or not UTF-8
+///
+/// # Safety
+///
+/// `path` must be null or point to a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ruloc_analyze_file(path: *const c_char) -> *mut RulocReport {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    // SAFETY: the caller guarantees `path` is a NUL-terminated string
+    let Ok(path) = unsafe { CStr::from_ptr(path) }.to_str() else {
+        return ptr::null_mut();
+    };
+
+    let result = analyze_file(Path::new(path), &AnalysisOptions::default());
+    Box::into_raw(Box::new(RulocReport { result, json: None }))
+}
+
+/// Returns the JSON rendering of a report.
+///
+/// # Arguments
+///
+/// * `report` - Report returned by [`ruloc_analyze_file`]
+///
+/// # Returns
+///
+/// A NUL-terminated JSON string owned by the report and valid until it is freed, or
+/// null if `report` is null
+///
+/// # Safety
+///
+/// `report` must be null or a report from [`ruloc_analyze_file`] that has not been
+/// freed, and must not be used from another thread during the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ruloc_report_to_json(report: *mut RulocReport) -> *const c_char {
+    // SAFETY: the caller guarantees `report` is null or a live, unaliased report
+    let Some(report) = (unsafe { report.as_mut() }) else {
+        return ptr::null();
+    };
+    if report.json.is_none() {
+        report.json = Some(report.to_json());
+    }
+    report.json.as_deref().map_or(ptr::null(), CStr::as_ptr)
+}
+
+/// Releases a report and the JSON string obtained from it.
+///
+/// # Arguments
+///
+/// * `report` - Report returned by [`ruloc_analyze_file`]; null is ignored
+///
+/// # Safety
+///
+/// `report` must be null or a report from [`ruloc_analyze_file`] that has not been
+/// freed already.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ruloc_free(report: *mut RulocReport) {
+    if !report.is_null() {
+        // SAFETY: the caller guarantees `report` came from Box::into_raw and is live
+        drop(unsafe { Box::from_raw(report) });
+    }
+}
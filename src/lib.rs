@@ -0,0 +1,7942 @@
+//! # ruloc - Rust Lines of Code Counter
+//!
+//! A sophisticated yet minimalist command-line tool designed for precise analysis of Rust source code.
+//! Employs AST-based parsing to accurately distinguish between production and test code, providing
+//! granular metrics across blank lines, comments, rustdoc documentation, and executable code.
+//!
+//! ## Core Capabilities
+//!
+//! - **AST-Driven Analysis**: Leverages `ra_ap_syntax` for token-level parsing, ensuring accurate
+//!   classification of code elements even within complex constructs like raw strings and macros.
+//! - **Production/Test Separation**: Intelligently identifies test modules and functions via
+//!   `#[test]` and `#[cfg(test)]` attributes, segregating metrics accordingly.
+//! - **Memory-Efficient Architecture**: Implements streaming accumulators supporting both in-memory
+//!   and file-backed storage, enabling analysis of arbitrarily large codebases.
+//! - **Parallel Processing**: Utilizes Rayon for concurrent file analysis, maximizing throughput
+//!   on multi-core systems.
+//! - **Flexible Output**: Supports both human-readable text and machine-parseable JSON formats.
+//!
+//! ## Library
+//!
+//! The analyzer lives in this crate root and is built as the `ruloc_ffi` library. The
+//! command-line interface is the `cli` module, enabled by the default `cli` feature; the
+//! `ruloc` binary only calls `cli::run`.
+//!
+//! With the `ffi` feature, the shared and static libraries export a C ABI so that build
+//! systems written in C, C++, or Python can call the analyzer directly instead of running
+//! the `ruloc` binary and parsing its output. The exported functions are declared in
+//! `include/ruloc.h`:
+//!
+//! - `ruloc_analyze_file` analyzes one file and returns an opaque report
+//! - `ruloc_report_to_json` renders a report as JSON, owned by the report
+//! - `ruloc_free` releases a report
+//!
+//! Rust callers can analyze one file with [`analyze_file`], or stream results into their
+//! own consumer: implement [`AnalysisSink`] and pass it to `analyze_into` (with the `cli`
+//! feature) with an [`InputSource`] and [`AnalysisOptions`]. Files that could not be
+//! analyzed are delivered to the sink as [`SkippedFile`]s, while failures of the whole run
+//! are reported as [`RulocError`], whose variants identify the kind of failure.
+//!
+//! ```
+//! use ruloc_ffi::{AnalysisOptions, AnalysisSink, FileStats, InputSource, RulocError};
+//!
+//! /// Collects the path of every analyzed file.
+//! struct Paths(Vec<String>);
+//!
+//! impl AnalysisSink for Paths {
+//!     fn file(&mut self, file_stats: &FileStats) -> Result<(), RulocError> {
+//!         self.0.push(file_stats.path.clone());
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut paths = Paths(Vec::new());
+//! let source = InputSource::Directory("tests/fixtures/basic".into());
+//! let summary = ruloc_ffi::analyze_into(&source, &AnalysisOptions::default(), &mut paths)?;
+//! assert_eq!(summary.files, paths.0.len());
+//! # Ok::<(), RulocError>(())
+//! ```
+
+use clap::ValueEnum;
+#[cfg(feature = "cli")]
+use indicatif::{ProgressBar, ProgressStyle};
+use ra_ap_syntax::{
+    AstNode, AstToken, Direction, Edition, NodeOrToken, Parse, SourceFile, SyntaxKind, SyntaxNode,
+    SyntaxToken, ast, ast::HasAttrs, ast::HasName,
+};
+#[cfg(feature = "cli")]
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+#[cfg(feature = "cli")]
+use std::io::{IsTerminal, Write};
+#[cfg(feature = "cli")]
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "cli")]
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "cli")]
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+#[cfg(feature = "cli")]
+use tempfile::NamedTempFile;
+#[cfg(feature = "cli")]
+use tracing::error;
+use tracing::{debug, trace, warn};
+#[cfg(feature = "cli")]
+use walkdir::WalkDir;
+
+#[cfg(feature = "cli")]
+pub mod cli;
+
+/// C ABI for embedding the analyzer in build systems written in other languages.
+///
+/// Built with the `ffi` feature into the `ruloc_ffi` shared and static libraries, and
+/// declared in `include/ruloc.h`. Every report returned by [`ffi::ruloc_analyze_file`] is
+/// owned by the caller and must be released with [`ffi::ruloc_free`].
+#[cfg(feature = "ffi")]
+mod ffi;
+
+/// In-browser entry points, built with `--features wasm` for `wasm32-unknown-unknown`.
+///
+/// Only the filesystem-independent [`analyze_content`] core is exposed, so no directory
+/// walk, thread pool, or progress bar is involved.
+#[cfg(feature = "wasm")]
+mod wasm;
+
+/// Number of leading lines searched for generated-file markers.
+const GENERATED_HEADER_LINES: usize = 20;
+
+/// Prefix of the in-source comments that override line classification.
+const DIRECTIVE_PREFIX: &str = "ruloc:";
+
+/// Errors that abort a ruloc run, categorized by what went wrong.
+///
+/// Library consumers can match on the kind of failure, and the CLI maps each kind to its
+/// own exit code (see [`RulocError::exit_code`]). Failures caused by an underlying I/O
+/// error keep it as their [`source`](std::error::Error::source), and errors raised within
+/// a larger operation keep the original error behind [`RulocError::Context`].
+#[derive(Debug, thiserror::Error)]
+pub enum RulocError {
+    /// A file or directory could not be read or written.
+    #[error("{context}: {source}")]
+    Io {
+        /// What was being attempted, including the path involved.
+        context: String,
+
+        /// The underlying I/O failure.
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// Input could not be parsed: a malformed report or debug baseline, or unexpected
+    /// output from an external tool.
+    #[error("{0}")]
+    Parse(String),
+
+    /// A source file is not valid UTF-8 and `--strict-utf8` was given.
+    #[error(
+        "Failed to read file '{}': stream did not contain valid UTF-8 ({invalid_bytes} invalid bytes). Remove --strict-utf8 to decode it lossily.",
+        .path.display()
+    )]
+    InvalidUtf8 {
+        /// The undecodable file.
+        path: PathBuf,
+
+        /// Number of bytes that are not part of a valid UTF-8 sequence.
+        invalid_bytes: usize,
+    },
+
+    /// Flags, analysis plans, or rule files are invalid.
+    #[error("{0}")]
+    Config(String),
+
+    /// A git command failed or git is unavailable.
+    #[error("{0}")]
+    Git(String),
+
+    /// A report could not be accumulated, rendered, or delivered.
+    #[error("{0}")]
+    Output(String),
+
+    /// A file exceeded `--max-file-size`.
+    #[error(
+        "File '{}' exceeds maximum size limit ({size} bytes > {limit} bytes). Consider increasing --max-file-size or excluding this file.",
+        .path.display()
+    )]
+    FileTooLarge {
+        /// The oversized file.
+        path: PathBuf,
+
+        /// Size of the file in bytes.
+        size: u64,
+
+        /// The configured limit in bytes.
+        limit: u64,
+    },
+
+    /// The input held nothing that could be analyzed.
+    #[error("{0}")]
+    Analysis(String),
+
+    /// The analysis completed, but a `--fail-on-*` or `--strict` gate failed.
+    #[error("{0}")]
+    Gate(String),
+
+    /// A failure within one step of a larger operation, such as one analysis of a plan.
+    #[error("{context}: {source}")]
+    Context {
+        /// The step that failed.
+        context: String,
+
+        /// The original error.
+        #[source]
+        source: Box<RulocError>,
+    },
+}
+
+impl RulocError {
+    /// Returns the process exit code for this error.
+    ///
+    /// Analysis and gate failures exit with 1, invalid configuration with 2 (like
+    /// command-line usage errors), and I/O, parse, git, and output failures with 3, 4, 5,
+    /// and 6 respectively. Contextualized errors use the code of the original error.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            RulocError::Analysis(_) | RulocError::Gate(_) | RulocError::FileTooLarge { .. } => 1,
+            RulocError::Config(_) => 2,
+            RulocError::Io { .. } => 3,
+            RulocError::Parse(_) | RulocError::InvalidUtf8 { .. } => 4,
+            RulocError::Git(_) => 5,
+            RulocError::Output(_) => 6,
+            RulocError::Context { source, .. } => source.exit_code(),
+        }
+    }
+
+    /// Builds a converter from an I/O error, for use with `map_err`.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - What was being attempted, including the path involved
+    fn io(context: impl Into<String>) -> impl FnOnce(std::io::Error) -> Self {
+        move |source| RulocError::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    /// Wraps the error with the step of a larger operation it occurred in.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - Description of the step, e.g. `Analysis 'core' failed`
+    #[cfg(feature = "cli")]
+    fn context(self, context: impl Into<String>) -> Self {
+        RulocError::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+}
+
+/// Comprehensive line-level statistics for a defined scope of Rust source code.
+///
+/// Provides a complete breakdown of source code composition, categorizing every line
+/// into mutually exclusive classifications. Designed for serialization via serde with
+/// kebab-case field naming for enhanced interoperability with external tools.
+///
+/// # Classification Taxonomy
+///
+/// - **All Lines**: Aggregate count encompassing the entire scope
+/// - **Blank Lines**: Lines containing exclusively whitespace characters (spaces, tabs, newlines)
+/// - **Comment Lines**: Standard comments (`//` and `/* */`) excluding documentation
+/// - **Rustdoc Lines**: Documentation comments (`///`, `//!`, `/**`, `/*!`)
+/// - **Code Lines**: Executable Rust code including declarations, expressions, and statements
+///
+/// # Invariants
+///
+/// The sum of blank, comment, rustdoc, and code lines equals `all_lines` for valid statistics.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LineStats {
+    /// Aggregate count of all lines within the analyzed scope.
+    #[serde(rename = "all-lines")]
+    pub all_lines: usize,
+
+    /// Count of lines consisting solely of whitespace characters.
+    #[serde(rename = "blank-lines")]
+    pub blank_lines: usize,
+
+    /// Count of non-documentation comment lines.
+    #[serde(rename = "comment-lines")]
+    pub comment_lines: usize,
+
+    /// Count of rustdoc documentation comment lines.
+    #[serde(rename = "rustdoc-lines")]
+    pub rustdoc_lines: usize,
+
+    /// Count of executable code lines.
+    #[serde(rename = "code-lines")]
+    pub code_lines: usize,
+}
+
+impl LineStats {
+    /// Performs element-wise accumulation of metrics from another instance.
+    ///
+    /// Aggregates line counts across all categories, enabling hierarchical composition
+    /// of statistics from individual files to directory-level summaries.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The statistics instance to merge into this one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use ruloc_ffi::LineStats;
+    /// let mut total = LineStats { all_lines: 100, code_lines: 60, ..Default::default() };
+    /// let additional = LineStats { all_lines: 50, code_lines: 30, ..Default::default() };
+    /// total.add(&additional);
+    /// assert_eq!(total.all_lines, 150);
+    /// assert_eq!(total.code_lines, 90);
+    /// ```
+    pub fn add(&mut self, other: &LineStats) {
+        self.all_lines += other.all_lines;
+        self.blank_lines += other.blank_lines;
+        self.comment_lines += other.comment_lines;
+        self.rustdoc_lines += other.rustdoc_lines;
+        self.code_lines += other.code_lines;
+    }
+
+    /// Removes the metrics of another instance, the inverse of [`LineStats::add`].
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Statistics of a subset of the lines counted by this instance
+    pub fn subtract(&mut self, other: &LineStats) {
+        self.all_lines = self.all_lines.saturating_sub(other.all_lines);
+        self.blank_lines = self.blank_lines.saturating_sub(other.blank_lines);
+        self.comment_lines = self.comment_lines.saturating_sub(other.comment_lines);
+        self.rustdoc_lines = self.rustdoc_lines.saturating_sub(other.rustdoc_lines);
+        self.code_lines = self.code_lines.saturating_sub(other.code_lines);
+    }
+}
+
+/// Content hashes of a file, computed with `--duplicates`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContentHashes {
+    /// Hash of the file content as read.
+    pub exact: u64,
+
+    /// Hash of the file's non-trivia tokens, ignoring whitespace, comments, and rustdoc.
+    pub normalized: u64,
+}
+
+/// A file belonging to a [`DuplicateGroup`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DuplicateFile {
+    /// Path of the file.
+    pub path: String,
+
+    /// Total number of lines in the file.
+    pub lines: usize,
+
+    /// Content hash of the file, not serialized.
+    #[serde(skip)]
+    exact: u64,
+}
+
+/// Files with the same code once whitespace, comments, and rustdoc are ignored, as
+/// reported with `--duplicates`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DuplicateGroup {
+    /// Whether every file in the group is byte-for-byte identical.
+    pub identical: bool,
+
+    /// The duplicated files, at least two.
+    pub files: Vec<DuplicateFile>,
+
+    /// Normalized content hash shared by the group, not serialized.
+    #[serde(skip)]
+    fingerprint: u64,
+}
+
+/// Why a file was excluded from an analysis.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkipCategory {
+    /// The file exceeded `--max-file-size`.
+    Oversized,
+
+    /// The file is not valid UTF-8 and `--strict-utf8` was given.
+    InvalidUtf8,
+
+    /// The file or its metadata could not be read.
+    #[default]
+    Unreadable,
+
+    /// The file was analyzed but its statistics could not be recorded.
+    Accumulator,
+}
+
+impl SkipCategory {
+    /// Returns the kebab-case name used in JSON output.
+    #[cfg(feature = "cli")]
+    fn as_str(self) -> &'static str {
+        match self {
+            SkipCategory::Oversized => "oversized",
+            SkipCategory::InvalidUtf8 => "invalid-utf8",
+            SkipCategory::Unreadable => "unreadable",
+            SkipCategory::Accumulator => "accumulator",
+        }
+    }
+
+    /// Classifies a file analysis error.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - Error returned by [`analyze_file`]
+    ///
+    /// # Returns
+    ///
+    /// The category matching the error, [`SkipCategory::Unreadable`] when unrecognized
+    #[cfg(feature = "cli")]
+    fn of(error: &RulocError) -> Self {
+        match error {
+            RulocError::FileTooLarge { .. } => SkipCategory::Oversized,
+            RulocError::InvalidUtf8 { .. } => SkipCategory::InvalidUtf8,
+            _ => SkipCategory::Unreadable,
+        }
+    }
+}
+
+/// A file excluded from an analysis, with the reason it was skipped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SkippedFile {
+    /// Path of the skipped file.
+    pub path: String,
+
+    /// Human-readable explanation, as printed on stderr.
+    pub reason: String,
+
+    /// Machine-readable classification of the reason.
+    pub category: SkipCategory,
+}
+
+/// Documentation coverage of the public API surface within a defined scope.
+///
+/// Complements raw rustdoc line counts by measuring how thoroughly the exported interface
+/// is documented: every public function, struct, enum, and trait is counted, and those
+/// carrying rustdoc comments (or `#[doc = "..."]` attributes) are tallied as documented.
+/// Items residing within test code are excluded, as they never form part of a public API.
+///
+/// # Serialization
+///
+/// Serializes both raw counters alongside a derived `coverage-percent` field, which is
+/// `null` when the scope contains no public items. The derived field is ignored during
+/// deserialization and recomputed from the counters on demand.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+pub struct DocCoverage {
+    /// Count of public items eligible for documentation.
+    #[serde(rename = "public-items")]
+    pub public_items: usize,
+
+    /// Count of public items carrying at least one rustdoc comment.
+    #[serde(rename = "documented-items")]
+    pub documented_items: usize,
+}
+
+impl DocCoverage {
+    /// Performs element-wise accumulation of coverage counters from another instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The coverage instance to merge into this one
+    pub fn add(&mut self, other: &DocCoverage) {
+        self.public_items += other.public_items;
+        self.documented_items += other.documented_items;
+    }
+
+    /// Computes the share of documented public items as a percentage.
+    ///
+    /// # Returns
+    ///
+    /// `Some(percentage)` in the range `0.0..=100.0`, or `None` when the scope contains
+    /// no public items and coverage is therefore undefined
+    pub fn percentage(&self) -> Option<f64> {
+        if self.public_items == 0 {
+            return None;
+        }
+        Some(self.documented_items as f64 * 100.0 / self.public_items as f64)
+    }
+}
+
+impl Serialize for DocCoverage {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("DocCoverage", 3)?;
+        state.serialize_field("public-items", &self.public_items)?;
+        state.serialize_field("documented-items", &self.documented_items)?;
+        state.serialize_field("coverage-percent", &self.percentage())?;
+        state.end()
+    }
+}
+
+/// Line counts attributed to macro definitions and macro invocations.
+///
+/// Macros can hide substantial amounts of logic behind compact syntax, skewing the
+/// perception of a file's size. These counters surface such code separately from plain
+/// code: they are a subset of the code lines already reported, not an additional category.
+///
+/// # Counting Rules
+///
+/// - **Definition lines**: Code lines within `macro_rules!` and `macro` definitions
+/// - **Invocation lines**: Code lines within a macro invocation's token tree, excluding
+///   the line on which the invocation starts, so that one-line calls such as
+///   `println!("...")` are not counted while multi-line DSL invocations are
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MacroLines {
+    /// Count of code lines inside macro definitions.
+    #[serde(rename = "definition-lines")]
+    pub definition_lines: usize,
+
+    /// Count of code lines continuing a macro invocation beyond its first line.
+    #[serde(rename = "invocation-lines")]
+    pub invocation_lines: usize,
+}
+
+impl MacroLines {
+    /// Performs element-wise accumulation of macro line counts from another instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The macro line counts to merge into this one
+    pub fn add(&mut self, other: &MacroLines) {
+        self.definition_lines += other.definition_lines;
+        self.invocation_lines += other.invocation_lines;
+    }
+}
+
+/// Lines under conditional compilation, gathered with `--cfg-surface`.
+///
+/// A line counts as guarded when it lies within an item, statement, or expression carrying
+/// a `#[cfg(...)]` attribute, or within a file carrying `#![cfg(...)]`. Predicates that
+/// reference only `test` are ignored, as test code is reported separately.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CfgSurface {
+    /// Lines guarded by any non-test `cfg` predicate, each counted once.
+    pub guarded: LineStats,
+
+    /// Lines guarded by each predicate atom, such as `unix`, `target_os = "linux"`, or
+    /// `feature = "serde"`. A line guarded by several atoms counts toward each of them.
+    pub predicates: BTreeMap<String, LineStats>,
+}
+
+impl CfgSurface {
+    /// Performs element-wise accumulation of guarded line counts from another instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The surface to merge into this one
+    pub fn add(&mut self, other: &CfgSurface) {
+        self.guarded.add(&other.guarded);
+        for (predicate, stats) in &other.predicates {
+            self.predicates
+                .entry(predicate.clone())
+                .or_default()
+                .add(stats);
+        }
+    }
+}
+
+/// Line length distribution gathered with `--line-length-stats`.
+///
+/// Lengths are measured in characters, excluding the line terminator. Percentiles use the
+/// nearest-rank method over non-blank lines.
+///
+/// # Serialization
+///
+/// The per-length histogram backing the percentiles is not serialized; instances read
+/// back from JSON keep their percentiles but cannot be merged into exact aggregates.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LineLengths {
+    /// Width above which lines are counted as too long.
+    pub width: usize,
+
+    /// Length of the longest line.
+    pub max: usize,
+
+    /// Number of lines longer than `width`.
+    #[serde(rename = "over-width")]
+    pub over_width: usize,
+
+    /// Median length of non-blank lines.
+    pub p50: usize,
+
+    /// 95th percentile length of non-blank lines.
+    pub p95: usize,
+
+    /// Number of non-blank lines of each length.
+    #[serde(skip)]
+    histogram: BTreeMap<usize, usize>,
+}
+
+impl LineLengths {
+    /// Measures the line lengths of source content.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - Source content to measure
+    /// * `width` - Width above which lines are counted as too long
+    pub fn of(content: &str, width: usize) -> Self {
+        let mut lengths = LineLengths {
+            width,
+            ..Default::default()
+        };
+        for line in content.lines() {
+            let length = line.chars().count();
+            lengths.max = lengths.max.max(length);
+            lengths.over_width += usize::from(length > width);
+            if !line.trim().is_empty() {
+                *lengths.histogram.entry(length).or_default() += 1;
+            }
+        }
+        lengths.update_percentiles();
+        lengths
+    }
+
+    /// Merges the line lengths of another scope into this one.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The line lengths to merge
+    pub fn add(&mut self, other: &LineLengths) {
+        self.width = other.width;
+        self.max = self.max.max(other.max);
+        self.over_width += other.over_width;
+        for (&length, &count) in &other.histogram {
+            *self.histogram.entry(length).or_default() += count;
+        }
+        self.update_percentiles();
+    }
+
+    /// Recomputes `p50` and `p95` from the histogram, if it holds any lines.
+    fn update_percentiles(&mut self) {
+        let lines: usize = self.histogram.values().sum();
+        if lines == 0 {
+            return;
+        }
+        let percentile = |percent: usize| {
+            let rank = (lines * percent).div_ceil(100).max(1);
+            let mut seen = 0;
+            self.histogram
+                .iter()
+                .find(|&(_, &count)| {
+                    seen += count;
+                    seen >= rank
+                })
+                .map_or(0, |(&length, _)| length)
+        };
+        self.p50 = percentile(50);
+        self.p95 = percentile(95);
+    }
+}
+
+/// Token counts of a scope, gathered with `--count tokens`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TokenCounts {
+    /// Every lexical token, including whitespace and comments.
+    pub all: usize,
+
+    /// Tokens other than whitespace and comments.
+    #[serde(rename = "non-trivia")]
+    pub non_trivia: usize,
+}
+
+impl TokenCounts {
+    /// Performs element-wise accumulation of token counts from another instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The token counts to merge into this one
+    pub fn add(&mut self, other: &TokenCounts) {
+        self.all += other.all;
+        self.non_trivia += other.non_trivia;
+    }
+}
+
+/// Counts of declared items by category.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ItemCounts {
+    /// Free functions and inherent methods.
+    pub functions: usize,
+
+    /// Structs, enums, unions, and type aliases.
+    pub types: usize,
+
+    /// Trait definitions.
+    pub traits: usize,
+
+    /// Constants and statics.
+    pub consts: usize,
+}
+
+impl ItemCounts {
+    /// Performs element-wise accumulation of item counts from another instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The item counts to merge into this one
+    pub fn add(&mut self, other: &ItemCounts) {
+        self.functions += other.functions;
+        self.types += other.types;
+        self.traits += other.traits;
+        self.consts += other.consts;
+    }
+
+    /// Returns the number of items across all categories.
+    pub fn total(&self) -> usize {
+        self.functions + self.types + self.traits + self.consts
+    }
+}
+
+/// Declared items split by visibility, tracking the growth of a crate's API surface.
+///
+/// Only unrestricted `pub` items count as public; `pub(crate)`, `pub(super)`, and
+/// `pub(in path)` items are private to the crate, as for [`DocCoverage`]. Members of trait
+/// definitions and trait `impl` blocks are not counted, since their visibility follows the
+/// trait. Items residing within test code are excluded.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ItemVisibility {
+    /// Items declared `pub`.
+    pub public: ItemCounts,
+
+    /// Items with any narrower visibility.
+    pub private: ItemCounts,
+}
+
+impl ItemVisibility {
+    /// Performs element-wise accumulation of item counts from another instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The item counts to merge into this one
+    pub fn add(&mut self, other: &ItemVisibility) {
+        self.public.add(&other.public);
+        self.private.add(&other.private);
+    }
+}
+
+/// Census of declared item kinds gathered with `--item-counts`.
+///
+/// Every item in the file is counted regardless of visibility or nesting, including
+/// methods, trait members, and items within test code.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ItemKindCounts {
+    /// Struct definitions.
+    pub structs: usize,
+
+    /// Enum definitions.
+    pub enums: usize,
+
+    /// Trait definitions.
+    pub traits: usize,
+
+    /// `impl` blocks, inherent and trait alike.
+    pub impls: usize,
+
+    /// Functions and methods.
+    pub functions: usize,
+
+    /// `macro_rules!` and `macro` definitions.
+    pub macros: usize,
+
+    /// Constants.
+    pub consts: usize,
+
+    /// Statics.
+    pub statics: usize,
+}
+
+impl ItemKindCounts {
+    /// Performs element-wise accumulation of item kind counts from another instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The item kind counts to merge into this one
+    pub fn add(&mut self, other: &ItemKindCounts) {
+        self.structs += other.structs;
+        self.enums += other.enums;
+        self.traits += other.traits;
+        self.impls += other.impls;
+        self.functions += other.functions;
+        self.macros += other.macros;
+        self.consts += other.consts;
+        self.statics += other.statics;
+    }
+}
+
+/// Usage of `async`/`.await`, gathered with `--async-metrics`.
+///
+/// Async bodies are the bodies of `async fn` items, `async` blocks, and `async` closures;
+/// a line nested in several of them is counted once.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AsyncMetrics {
+    /// Functions declared `async`, including trait method declarations.
+    #[serde(rename = "async-functions")]
+    pub async_functions: usize,
+
+    /// `.await` expressions.
+    #[serde(rename = "await-points")]
+    pub await_points: usize,
+
+    /// Code lines inside async bodies.
+    #[serde(rename = "async-lines")]
+    pub async_lines: usize,
+}
+
+impl AsyncMetrics {
+    /// Performs element-wise accumulation of async metrics from another instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The async metrics to merge into this one
+    pub fn add(&mut self, other: &AsyncMetrics) {
+        self.async_functions += other.async_functions;
+        self.await_points += other.await_points;
+        self.async_lines += other.async_lines;
+    }
+}
+
+/// Type-level complexity indicators gathered with `--type-complexity`.
+///
+/// Line counts under-represent the maintenance cost of type-heavy code; these counters
+/// capture how much generic machinery a scope declares.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TypeComplexity {
+    /// Generic parameters (type, lifetime, and const) declared on items.
+    #[serde(rename = "generic-params")]
+    pub generic_params: usize,
+
+    /// `where` clauses attached to items.
+    #[serde(rename = "where-clauses")]
+    pub where_clauses: usize,
+
+    /// Higher-ranked trait bounds (`for<'a> ...`).
+    #[serde(rename = "higher-ranked-bounds")]
+    pub higher_ranked_bounds: usize,
+}
+
+impl TypeComplexity {
+    /// Performs element-wise accumulation of complexity counters from another instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The counters to merge into this one
+    pub fn add(&mut self, other: &TypeComplexity) {
+        self.generic_params += other.generic_params;
+        self.where_clauses += other.where_clauses;
+        self.higher_ranked_bounds += other.higher_ranked_bounds;
+    }
+}
+
+/// Volume of rustdoc prose, gathered with `--doc-detail`.
+///
+/// Line counts say little about how much documentation a scope carries; words and characters
+/// of the comment text (without the `///` or `//!` markers) quantify it. Lines of Rust code
+/// examples are counted apart, split into the visible ones and the ones hidden from the
+/// rendered docs with a `# ` prefix, which still compile as part of the doc test.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DocVolume {
+    /// Whitespace-separated words.
+    pub words: usize,
+
+    /// Characters, excluding leading and trailing whitespace of each line.
+    pub characters: usize,
+
+    /// Lines of Rust code examples shown in the rendered docs, excluding the fences.
+    #[serde(rename = "example-lines", default)]
+    pub example_lines: usize,
+
+    /// Lines of Rust code examples hidden from the rendered docs with a `# ` prefix.
+    #[serde(rename = "hidden-example-lines", default)]
+    pub hidden_example_lines: usize,
+}
+
+/// Code block attributes under which rustdoc still treats a fenced block as Rust.
+const RUST_CODE_BLOCK_ATTRIBUTES: [&str; 9] = [
+    "rust",
+    "ignore",
+    "should_panic",
+    "no_run",
+    "compile_fail",
+    "test_harness",
+    "standalone_crate",
+    "edition2015",
+    "edition2018",
+];
+
+impl DocVolume {
+    /// Measures the text of a doc comment block.
+    ///
+    /// Code fences are tracked across the lines of `text`, so the consecutive doc comments
+    /// of one item should be measured together.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - Comment text without its doc comment markers
+    pub fn of(text: &str) -> Self {
+        let mut volume = DocVolume {
+            words: text.split_whitespace().count(),
+            characters: text.lines().map(|line| line.trim().chars().count()).sum(),
+            ..Default::default()
+        };
+
+        // The open fence, with whether its block is Rust code
+        let mut fence: Option<(&str, bool)> = None;
+        for line in text.lines().map(str::trim_start) {
+            match fence {
+                Some((marker, _)) if closes_fence(line, marker) => fence = None,
+                Some((_, true)) => {
+                    if line.trim_end() == "#" || line.starts_with("# ") {
+                        volume.hidden_example_lines += 1;
+                    } else {
+                        volume.example_lines += 1;
+                    }
+                }
+                Some((_, false)) => {}
+                None => fence = code_fence(line),
+            }
+        }
+        volume
+    }
+
+    /// Performs element-wise accumulation of volumes from another instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The volume to merge into this one
+    pub fn add(&mut self, other: &DocVolume) {
+        self.words += other.words;
+        self.characters += other.characters;
+        self.example_lines += other.example_lines;
+        self.hidden_example_lines += other.hidden_example_lines;
+    }
+}
+
+/// Checks whether a line closes the fenced code block opened by `marker`.
+///
+/// As in CommonMark, a closing fence is a bare run of the opening fence's character, at
+/// least as long as the opening fence and without an info string.
+///
+/// # Arguments
+///
+/// * `line` - Doc comment line without its leading whitespace
+/// * `marker` - The fence that opened the block
+///
+/// # Returns
+///
+/// `true` if the line ends the code block
+fn closes_fence(line: &str, marker: &str) -> bool {
+    let line = line.trim_end();
+    let fence_char = marker.chars().next();
+    line.len() >= marker.len() && line.chars().all(|c| Some(c) == fence_char)
+}
+
+/// Recognizes a line opening a fenced code block in markdown.
+///
+/// # Arguments
+///
+/// * `line` - Doc comment line without its leading whitespace
+///
+/// # Returns
+///
+/// The fence marker (three or more backticks or tildes) and whether rustdoc treats the block
+/// as Rust code, which it does unless the info string names another language; `None` if
+/// the line opens no code block
+fn code_fence(line: &str) -> Option<(&str, bool)> {
+    let fence_char = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let length = line.len() - line.trim_start_matches(fence_char).len();
+    if length < 3 {
+        return None;
+    }
+    let (marker, info) = line.split_at(length);
+    let is_rust = info
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|attribute| !attribute.is_empty())
+        .all(|attribute| {
+            RUST_CODE_BLOCK_ATTRIBUTES.contains(&attribute) || attribute.starts_with("edition")
+        });
+    Some((marker, is_rust))
+}
+
+/// Rustdoc volume of one public item, reported with `--doc-detail`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ItemDocVolume {
+    /// Item name, qualified with enclosing inline modules and `impl` types.
+    pub name: String,
+
+    /// Item kind keyword (`fn`, `struct`, `enum`, ...).
+    pub kind: String,
+
+    /// One-based line on which the item starts.
+    pub line: usize,
+
+    /// Volume of the item's doc comments.
+    #[serde(flatten)]
+    pub volume: DocVolume,
+}
+
+/// Rustdoc volume of a file and of each of its public items, present with `--doc-detail`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DocDetail {
+    /// Volume of every doc comment in the file, including module-level `//!` docs.
+    #[serde(flatten)]
+    pub volume: DocVolume,
+
+    /// Public items outside test code, in source order.
+    pub items: Vec<ItemDocVolume>,
+}
+
+/// Location of a work-marker (such as `TODO` or `FIXME`) found within a comment.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MarkerLocation {
+    /// One-based line number on which the marker appears.
+    pub line: usize,
+
+    /// The marker that matched (e.g., `TODO`).
+    pub marker: String,
+
+    /// Trimmed text of the source line containing the marker.
+    pub text: String,
+}
+
+/// Call sites that may panic at runtime, counted with `--panics`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PanicCounts {
+    /// `.unwrap()` method calls.
+    pub unwrap: usize,
+
+    /// `.expect(..)` method calls.
+    pub expect: usize,
+
+    /// `panic!` invocations.
+    pub panic: usize,
+
+    /// `todo!` invocations.
+    pub todo: usize,
+
+    /// `unimplemented!` invocations.
+    pub unimplemented: usize,
+}
+
+impl PanicCounts {
+    /// Performs element-wise accumulation of panic site counts from another instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The counts to merge into this one
+    pub fn add(&mut self, other: &PanicCounts) {
+        self.unwrap += other.unwrap;
+        self.expect += other.expect;
+        self.panic += other.panic;
+        self.todo += other.todo;
+        self.unimplemented += other.unimplemented;
+    }
+
+    /// Returns the number of call sites across all kinds.
+    pub fn total(&self) -> usize {
+        self.unwrap + self.expect + self.panic + self.todo + self.unimplemented
+    }
+
+    /// Returns the counter for a panic site kind, as named by [`panic_site_kind`].
+    fn counter(&mut self, kind: &str) -> &mut usize {
+        match kind {
+            "unwrap" => &mut self.unwrap,
+            "expect" => &mut self.expect,
+            "panic!" => &mut self.panic,
+            "todo!" => &mut self.todo,
+            _ => &mut self.unimplemented,
+        }
+    }
+}
+
+/// Panic call sites split between production and test code.
+///
+/// Sites within benchmark code are not counted.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PanicCensus {
+    /// Call sites in production code.
+    pub production: PanicCounts,
+
+    /// Call sites in test code.
+    pub test: PanicCounts,
+}
+
+impl PanicCensus {
+    /// Performs element-wise accumulation of a census from another instance.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The census to merge into this one
+    pub fn add(&mut self, other: &PanicCensus) {
+        self.production.add(&other.production);
+        self.test.add(&other.test);
+    }
+}
+
+/// Location of a call site that may panic, as listed with `--list-panics`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PanicSite {
+    /// One-based line number of the call.
+    pub line: usize,
+
+    /// Kind of call (`unwrap`, `expect`, `panic!`, `todo!`, or `unimplemented!`).
+    pub kind: String,
+
+    /// Whether the call lies within test code.
+    pub test: bool,
+
+    /// Trimmed text of the source line containing the call.
+    pub text: String,
+}
+
+/// Location of a syntax error the parser recovered from, as listed with
+/// `--list-parse-errors`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ParseErrorSite {
+    /// One-based line number where the error starts.
+    pub line: usize,
+
+    /// Parser message describing the error.
+    pub message: String,
+}
+
+/// Returns `true` for zero counters, letting opt-in totals stay out of serialized output.
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+/// Returns `true` for values equal to their default, keeping empty aggregates out of output.
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// A function whose body exceeds the configured `--max-fn-lines` threshold.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LongFunction {
+    /// Function name, qualified with the implementing type for methods (e.g., `Parser::parse`).
+    pub name: String,
+
+    /// One-based line on which the function signature starts.
+    #[serde(rename = "start-line")]
+    pub start_line: usize,
+
+    /// One-based line on which the function ends.
+    #[serde(rename = "end-line")]
+    pub end_line: usize,
+
+    /// Number of lines spanned by the function, excluding attributes and doc comments.
+    pub lines: usize,
+}
+
+/// A function whose cyclomatic complexity exceeds the configured `--max-complexity`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ComplexFunction {
+    /// Function name, qualified with the implementing type for methods (e.g., `Parser::parse`).
+    pub name: String,
+
+    /// One-based line on which the function signature starts.
+    #[serde(rename = "start-line")]
+    pub start_line: usize,
+
+    /// Estimated cyclomatic complexity of the function.
+    pub complexity: usize,
+}
+
+/// A function whose block nesting exceeds the configured `--max-nesting` depth.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NestedFunction {
+    /// Function name, qualified with the implementing type for methods (e.g., `Parser::parse`).
+    pub name: String,
+
+    /// One-based line on which the function signature starts.
+    #[serde(rename = "start-line")]
+    pub start_line: usize,
+
+    /// Deepest nesting of control-flow blocks and closures within the function body.
+    pub depth: usize,
+}
+
+/// Cyclomatic complexity of the functions within a scope, gathered with `--complexity`.
+///
+/// Each function with a body starts at 1 and gains one point per branch point: `if`,
+/// `while`, `for`, and `loop` expressions, every `match` arm after the first, `&&`, `||`,
+/// and `?`. Closures count toward their enclosing function; nested functions are measured
+/// on their own.
+///
+/// # Serialization
+///
+/// Serializes the raw counters alongside a derived `mean` field, which is `null` when the
+/// scope contains no functions. The derived field is ignored during deserialization.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+pub struct FunctionComplexity {
+    /// Number of functions with a body.
+    pub functions: usize,
+
+    /// Sum of the complexity of every function.
+    pub total: usize,
+
+    /// Highest complexity of any single function.
+    pub max: usize,
+}
+
+impl FunctionComplexity {
+    /// Records the complexity of one function.
+    ///
+    /// # Arguments
+    ///
+    /// * `complexity` - The function's cyclomatic complexity
+    pub fn record(&mut self, complexity: usize) {
+        self.functions += 1;
+        self.total += complexity;
+        self.max = self.max.max(complexity);
+    }
+
+    /// Merges the complexity of another scope into this one.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The complexity to merge
+    pub fn add(&mut self, other: &FunctionComplexity) {
+        self.functions += other.functions;
+        self.total += other.total;
+        self.max = self.max.max(other.max);
+    }
+
+    /// Computes the mean complexity per function.
+    ///
+    /// # Returns
+    ///
+    /// `Some(mean)`, or `None` when the scope contains no functions
+    pub fn mean(&self) -> Option<f64> {
+        (self.functions > 0).then(|| self.total as f64 / self.functions as f64)
+    }
+}
+
+impl Serialize for FunctionComplexity {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("FunctionComplexity", 4)?;
+        state.serialize_field("functions", &self.functions)?;
+        state.serialize_field("total", &self.total)?;
+        state.serialize_field("max", &self.max)?;
+        state.serialize_field("mean", &self.mean())?;
+        state.end()
+    }
+}
+
+/// An `impl` block declared within a file, as listed with `--impls`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ImplBlock {
+    /// The implementing type as written in the source (e.g., `Parser<'a>`).
+    #[serde(rename = "self-type")]
+    pub self_type: String,
+
+    /// The implemented trait as written in the source; absent for inherent impls.
+    #[serde(rename = "trait", default, skip_serializing_if = "Option::is_none")]
+    pub trait_name: Option<String>,
+
+    /// One-based line on which the `impl` header starts.
+    #[serde(rename = "start-line")]
+    pub start_line: usize,
+
+    /// Number of lines spanned by the block, excluding attributes and doc comments.
+    pub lines: usize,
+}
+
+/// A file pulled in through `include!`, `include_str!`, or `include_bytes!`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IncludeTarget {
+    /// Name of the including macro (e.g., `include_str`).
+    #[serde(rename = "macro")]
+    pub macro_name: String,
+
+    /// Target path exactly as written in the macro invocation.
+    pub target: String,
+
+    /// Target path resolved relative to the including file; absent when it does not exist.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved: Option<String>,
+}
+
+/// A test function whose name does not match `--test-name-pattern`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TestNameViolation {
+    /// Name of the offending test function.
+    pub name: String,
+
+    /// One-based line on which the function signature starts.
+    pub line: usize,
+}
+
+/// How far a file's line classification can be trusted.
+///
+/// Classification is `exact` when the file was decoded and parsed without loss. It is
+/// `approximate` when invalid UTF-8 had to be replaced or the parser had to recover from
+/// syntax errors, so token boundaries (and therefore line types) nearby may be off.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Classification {
+    /// Every line was classified from a complete, error-free syntax tree.
+    #[default]
+    Exact,
+
+    /// Some lines were classified from a recovered or otherwise degraded input.
+    Approximate,
+}
+
+/// Line statistics of an inline module (`mod name { ... }`), recorded with `--group-by module`.
+///
+/// Lines of nested inline modules are attributed to the innermost module only.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct InlineModule {
+    /// Module path relative to the enclosing file's module, such as `tests` or `a::b`.
+    pub path: String,
+
+    /// Statistics of all lines of the module.
+    pub total: LineStats,
+
+    /// Statistics of the module's production lines.
+    pub production: LineStats,
+
+    /// Statistics of the module's test lines.
+    pub test: LineStats,
+
+    /// Statistics of the module's benchmark lines.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub bench: LineStats,
+
+    /// Statistics of the module's lines when it belongs to a build script.
+    #[serde(rename = "build-script", default, skip_serializing_if = "is_default")]
+    pub build_script: LineStats,
+
+    /// Statistics of the module's lines when it belongs to a procedural macro crate.
+    #[serde(rename = "proc-macro", default, skip_serializing_if = "is_default")]
+    pub proc_macro: LineStats,
+}
+
+/// Tripartite statistical analysis of a single Rust source file.
+///
+/// Segregates metrics into three orthogonal perspectives: aggregate totals, production code,
+/// and test code. This decomposition facilitates precise understanding of code distribution
+/// between implementation and verification concerns.
+///
+/// # Field Relationships
+///
+/// - `total` = `production` + `test` (component-wise)
+/// - All line counts within each `LineStats` instance maintain their individual invariants
+///
+/// # Use Cases
+///
+/// - Tracking test coverage ratios
+/// - Identifying files with disproportionate test/production ratios
+/// - Aggregating directory-level statistics
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FileStats {
+    /// Canonical path to the analyzed file, relative to the analysis root directory.
+    pub path: String,
+
+    /// Aggregate statistics encompassing all content within the file.
+    pub total: LineStats,
+
+    /// Statistics exclusively for production code, excluding test modules and functions.
+    pub production: LineStats,
+
+    /// Statistics exclusively for test code identified via `#[test]` and `#[cfg(test)]`.
+    pub test: LineStats,
+
+    /// Statistics for benchmark code: `#[bench]` functions, criterion harness macros, and
+    /// files under `benches/`. Excluded from `production` and `test`.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub bench: LineStats,
+
+    /// Statistics of build script code (`build.rs` and the modules it declares), which is
+    /// tooling rather than shipped production code.
+    #[serde(rename = "build-script", default, skip_serializing_if = "is_default")]
+    pub build_script: LineStats,
+
+    /// Statistics of procedural macro crate code (`[lib] proc-macro = true`), which is
+    /// tooling rather than shipped production code.
+    #[serde(rename = "proc-macro", default, skip_serializing_if = "is_default")]
+    pub proc_macro: LineStats,
+
+    /// Documentation coverage of the public items declared within the file, present with
+    /// `--doc-coverage`.
+    #[serde(
+        rename = "doc-coverage",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub doc_coverage: Option<DocCoverage>,
+
+    /// Code lines attributed to macro definitions and multi-line macro invocations,
+    /// present with `--macro-lines`.
+    #[serde(
+        rename = "macro-lines",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub macro_lines: Option<MacroLines>,
+
+    /// Public and private functions, types, traits, and consts declared within the file,
+    /// present with `--item-visibility`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items: Option<ItemVisibility>,
+
+    /// Line statistics per cargo feature gating the lines, present with `--by-feature`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub features: BTreeMap<String, LineStats>,
+
+    /// Lines under non-test conditional compilation, present with `--cfg-surface`.
+    #[serde(
+        rename = "cfg-surface",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cfg_surface: Option<CfgSurface>,
+
+    /// Longest line, lines over the width, and length percentiles, present with
+    /// `--line-length-stats`.
+    #[serde(
+        rename = "line-lengths",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub line_lengths: Option<LineLengths>,
+
+    /// Code lines dominated by string literal content, present with `--string-lines`.
+    #[serde(
+        rename = "string-lines",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub string_lines: Option<usize>,
+
+    /// Token counts, present with `--count tokens`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<TokenCounts>,
+
+    /// Syntax tree node count, present with `--count nodes`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nodes: Option<usize>,
+
+    /// Logical statement count, present with `--count statements`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statements: Option<usize>,
+
+    /// Occurrences of each work-marker (e.g., `TODO`, `FIXME`) found in comments.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub markers: BTreeMap<String, usize>,
+
+    /// Occurrences of each lint named in `#[allow(...)]` and `#![allow(...)]` attributes,
+    /// present with `--allows`.
+    #[serde(
+        rename = "allowed-lints",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub allowed_lints: BTreeMap<String, usize>,
+
+    /// Functions exceeding the `--max-fn-lines` threshold, in source order.
+    #[serde(
+        rename = "long-functions",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub long_functions: Vec<LongFunction>,
+
+    /// Function count and mean and maximum cyclomatic complexity, present with `--complexity`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<FunctionComplexity>,
+
+    /// Functions exceeding the `--max-complexity` threshold, in source order.
+    #[serde(
+        rename = "complex-functions",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub complex_functions: Vec<ComplexFunction>,
+
+    /// Deepest block nesting of any function in the file, present with `--nesting`.
+    #[serde(
+        rename = "max-nesting",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_nesting: Option<usize>,
+
+    /// Functions nested deeper than the `--max-nesting` threshold, in source order.
+    #[serde(
+        rename = "nested-functions",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub nested_functions: Vec<NestedFunction>,
+
+    /// Counts of structs, enums, traits, impls, functions, macros, consts, and statics,
+    /// present with `--item-counts`.
+    #[serde(
+        rename = "item-counts",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub item_counts: Option<ItemKindCounts>,
+
+    /// Async function, `.await`, and async body line counts, present with `--async-metrics`.
+    #[serde(rename = "async", default, skip_serializing_if = "Option::is_none")]
+    pub async_metrics: Option<AsyncMetrics>,
+
+    /// Generic, `where`-clause, and higher-ranked bound counts, present with `--type-complexity`.
+    #[serde(
+        rename = "type-complexity",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub type_complexity: Option<TypeComplexity>,
+
+    /// Rustdoc words and characters of the file and its public items, present with
+    /// `--doc-detail`.
+    #[serde(
+        rename = "doc-detail",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub doc_detail: Option<DocDetail>,
+
+    /// Bucket of the time since the file's last commit, present with `--age-buckets`.
+    #[serde(
+        rename = "age-bucket",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub age_bucket: Option<String>,
+
+    /// Test functions violating `--test-name-pattern`, in source order.
+    #[serde(
+        rename = "test-name-violations",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub test_name_violations: Vec<TestNameViolation>,
+
+    /// Files included through `include!`-family macros, present with `--follow-includes`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub includes: Vec<IncludeTarget>,
+
+    /// `impl` blocks declared in the file, in source order, present with `--impls`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub impls: Vec<ImplBlock>,
+
+    /// Individual work-marker locations, present with `--todos`.
+    #[serde(
+        rename = "marker-locations",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub marker_locations: Vec<MarkerLocation>,
+
+    /// `unwrap`/`expect`/`panic!`/`todo!`/`unimplemented!` call sites, present with `--panics`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub panics: Option<PanicCensus>,
+
+    /// Individual panic call site locations, present with `--list-panics`.
+    #[serde(rename = "panic-sites", default, skip_serializing_if = "Vec::is_empty")]
+    pub panic_sites: Vec<PanicSite>,
+
+    /// Whether the line counts are exact or derived from degraded input.
+    #[serde(default)]
+    pub classification: Classification,
+
+    /// Number of syntax errors the parser recovered from; lines near them may be
+    /// miscounted.
+    #[serde(rename = "parse-errors", default, skip_serializing_if = "is_zero")]
+    pub parse_errors: usize,
+
+    /// Individual syntax error locations, present with `--list-parse-errors`.
+    #[serde(
+        rename = "parse-error-sites",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub parse_error_sites: Vec<ParseErrorSite>,
+
+    /// Whether the file was detected as generated and is reported separately.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub generated: bool,
+
+    /// Number of invalid UTF-8 bytes replaced with U+FFFD when the file was decoded.
+    #[serde(rename = "replaced-bytes", default, skip_serializing_if = "is_zero")]
+    pub replaced_bytes: usize,
+
+    /// Number of lines excluded from all counts by `// ruloc:ignore-start` directives.
+    #[serde(rename = "ignored-lines", default, skip_serializing_if = "is_zero")]
+    pub ignored_lines: usize,
+
+    /// Review notes attached with `ruloc annotate`, in the order they were added.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+
+    /// Content hashes grouping duplicate files, present with `--duplicates`. Not serialized.
+    #[serde(skip)]
+    pub content_hashes: Option<ContentHashes>,
+
+    /// Inline modules declared in the file, present with `--group-by module`.
+    #[serde(
+        rename = "inline-modules",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub inline_modules: Vec<InlineModule>,
+
+    /// Rust edition the file was parsed with, resolved from the owning crate's `Cargo.toml`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edition: Option<String>,
+
+    /// Revision-pinned hyperlink to the file, present only when `--link-base` is supplied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Consolidated statistical summary aggregated across an entire analysis scope.
+///
+/// Represents the culmination of file-level metrics rolled up into a comprehensive
+/// project or directory-wide overview. Maintains the tripartite decomposition
+/// (total/production/test) while tracking the number of files contributing to the aggregate.
+///
+/// # Aggregation Semantics
+///
+/// - File count increments with each unique file added
+/// - Line statistics accumulate additively across all dimensions
+/// - Preserves production/test separation throughout the hierarchy
+///
+/// # Applications
+///
+/// - Project-wide code composition reports
+/// - Comparative analysis across multiple directories
+/// - Baseline metrics for CI/CD pipelines
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Summary {
+    /// Cardinal count of unique files incorporated into this summary.
+    pub files: usize,
+
+    /// Aggregate line statistics spanning all analyzed files.
+    pub total: LineStats,
+
+    /// Aggregate production code statistics across all files.
+    pub production: LineStats,
+
+    /// Aggregate test code statistics across all files.
+    pub test: LineStats,
+
+    /// Aggregate benchmark code statistics across all files.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub bench: LineStats,
+
+    /// Aggregate build script statistics across all files.
+    #[serde(rename = "build-script", default, skip_serializing_if = "is_default")]
+    pub build_script: LineStats,
+
+    /// Aggregate procedural macro crate statistics across all files.
+    #[serde(rename = "proc-macro", default, skip_serializing_if = "is_default")]
+    pub proc_macro: LineStats,
+
+    /// Aggregate documentation coverage of public items across all files, present with
+    /// `--doc-coverage`.
+    #[serde(
+        rename = "doc-coverage",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub doc_coverage: Option<DocCoverage>,
+
+    /// Aggregate macro definition and invocation line counts across all files, present
+    /// with `--macro-lines`.
+    #[serde(
+        rename = "macro-lines",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub macro_lines: Option<MacroLines>,
+
+    /// Aggregate public and private item counts across all files, present with
+    /// `--item-visibility`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub items: Option<ItemVisibility>,
+
+    /// Aggregate line statistics per cargo feature, present with `--by-feature`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub features: BTreeMap<String, LineStats>,
+
+    /// Lines under non-test conditional compilation across all files, present with
+    /// `--cfg-surface`.
+    #[serde(
+        rename = "cfg-surface",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cfg_surface: Option<CfgSurface>,
+
+    /// Longest line, lines over the width, and length percentiles, present with
+    /// `--line-length-stats`.
+    #[serde(
+        rename = "line-lengths",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub line_lengths: Option<LineLengths>,
+
+    /// Code lines dominated by string literal content, present with `--string-lines`.
+    #[serde(
+        rename = "string-lines",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub string_lines: Option<usize>,
+
+    /// Token counts, present with `--count tokens`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<TokenCounts>,
+
+    /// Syntax tree node count, present with `--count nodes`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nodes: Option<usize>,
+
+    /// Logical statement count, present with `--count statements`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub statements: Option<usize>,
+
+    /// Aggregate occurrences of each work-marker across all files.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub markers: BTreeMap<String, usize>,
+
+    /// Aggregate occurrences of each allowed lint across all files.
+    #[serde(
+        rename = "allowed-lints",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub allowed_lints: BTreeMap<String, usize>,
+
+    /// Total number of functions exceeding the `--max-fn-lines` threshold.
+    #[serde(rename = "long-functions", default, skip_serializing_if = "is_zero")]
+    pub long_functions: usize,
+
+    /// Aggregate cyclomatic complexity across all files, present with `--complexity`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<FunctionComplexity>,
+
+    /// Total number of functions exceeding the `--max-complexity` threshold.
+    #[serde(rename = "complex-functions", default, skip_serializing_if = "is_zero")]
+    pub complex_functions: usize,
+
+    /// Deepest block nesting of any function across all files, present with `--nesting`.
+    #[serde(
+        rename = "max-nesting",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub max_nesting: Option<usize>,
+
+    /// Total number of functions nested deeper than the `--max-nesting` threshold.
+    #[serde(rename = "nested-functions", default, skip_serializing_if = "is_zero")]
+    pub nested_functions: usize,
+
+    /// Number of files whose classification is approximate rather than exact.
+    #[serde(rename = "approximate-files", default, skip_serializing_if = "is_zero")]
+    pub approximate_files: usize,
+
+    /// Number of files containing syntax errors.
+    #[serde(rename = "parse-error-files", default, skip_serializing_if = "is_zero")]
+    pub parse_error_files: usize,
+
+    /// Line statistics of files detected as generated, which are excluded from `total`,
+    /// `production`, and `test` unless `--count-generated` is given.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub generated: LineStats,
+
+    /// Line statistics per last-modified age bucket, present with `--age-buckets`.
+    #[serde(
+        rename = "age-buckets",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub age_buckets: BTreeMap<String, LineStats>,
+
+    /// Total number of test functions violating `--test-name-pattern`.
+    #[serde(
+        rename = "test-name-violations",
+        default,
+        skip_serializing_if = "is_zero"
+    )]
+    pub test_name_violations: usize,
+
+    /// Aggregate type complexity across all files, present with `--type-complexity`.
+    #[serde(
+        rename = "type-complexity",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub type_complexity: Option<TypeComplexity>,
+
+    /// Item kind census across all files, present with `--item-counts`.
+    #[serde(
+        rename = "item-counts",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub item_counts: Option<ItemKindCounts>,
+
+    /// Panic call sites across all files, present with `--panics`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub panics: Option<PanicCensus>,
+
+    /// Async usage across all files, present with `--async-metrics`.
+    #[serde(rename = "async", default, skip_serializing_if = "Option::is_none")]
+    pub async_metrics: Option<AsyncMetrics>,
+
+    /// Rustdoc words and characters across all files, present with `--doc-detail`.
+    #[serde(
+        rename = "doc-volume",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub doc_volume: Option<DocVolume>,
+
+    /// Number of `impl` blocks implementing each trait across all files, present with `--impls`.
+    #[serde(
+        rename = "trait-impls",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub trait_impls: BTreeMap<String, usize>,
+
+    /// Groups of identical or near-identical files, present with `--duplicates`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub duplicates: Vec<DuplicateGroup>,
+
+    /// Files not yet known to have a duplicate, keyed by normalized content hash.
+    #[serde(skip)]
+    unique_contents: BTreeMap<u64, DuplicateFile>,
+}
+
+impl Summary {
+    /// Incorporates file-level statistics into this aggregate summary.
+    ///
+    /// Atomically increments the file counter and merges all three statistical dimensions
+    /// (total, production, test) into their respective accumulators.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_stats` - Complete statistical profile of a single file to integrate
+    ///
+    /// # Postconditions
+    ///
+    /// - `self.files` increases by exactly 1
+    /// - All line counts in `self.total`, `self.production`, and `self.test` increase
+    ///   by their corresponding values from `file_stats`
+    /// - Documentation coverage and macro line counters increase by those of `file_stats`
+    /// - Per-feature statistics and work-marker counts are merged key by key
+    /// - Generated files contribute to `self.generated` instead of the three line dimensions
+    pub fn add_file(&mut self, file_stats: &FileStats) {
+        self.files += 1;
+        if file_stats.generated {
+            self.generated.add(&file_stats.total);
+        } else {
+            self.total.add(&file_stats.total);
+            self.production.add(&file_stats.production);
+            self.test.add(&file_stats.test);
+            self.bench.add(&file_stats.bench);
+            self.build_script.add(&file_stats.build_script);
+            self.proc_macro.add(&file_stats.proc_macro);
+        }
+        if let Some(doc_coverage) = &file_stats.doc_coverage {
+            self.doc_coverage
+                .get_or_insert_with(DocCoverage::default)
+                .add(doc_coverage);
+        }
+        if let Some(macro_lines) = &file_stats.macro_lines {
+            self.macro_lines
+                .get_or_insert_with(MacroLines::default)
+                .add(macro_lines);
+        }
+        if let Some(items) = &file_stats.items {
+            self.items
+                .get_or_insert_with(ItemVisibility::default)
+                .add(items);
+        }
+        for (feature, stats) in &file_stats.features {
+            self.features.entry(feature.clone()).or_default().add(stats);
+        }
+        if let Some(string_lines) = file_stats.string_lines {
+            *self.string_lines.get_or_insert(0) += string_lines;
+        }
+        if let Some(tokens) = &file_stats.tokens {
+            self.tokens
+                .get_or_insert_with(TokenCounts::default)
+                .add(tokens);
+        }
+        if let Some(nodes) = file_stats.nodes {
+            *self.nodes.get_or_insert(0) += nodes;
+        }
+        if let Some(statements) = file_stats.statements {
+            *self.statements.get_or_insert(0) += statements;
+        }
+        if let Some(line_lengths) = &file_stats.line_lengths {
+            self.line_lengths
+                .get_or_insert_with(LineLengths::default)
+                .add(line_lengths);
+        }
+        if let Some(cfg_surface) = &file_stats.cfg_surface {
+            self.cfg_surface
+                .get_or_insert_with(CfgSurface::default)
+                .add(cfg_surface);
+        }
+        for (marker, count) in &file_stats.markers {
+            *self.markers.entry(marker.clone()).or_default() += count;
+        }
+        for (lint, count) in &file_stats.allowed_lints {
+            *self.allowed_lints.entry(lint.clone()).or_default() += count;
+        }
+        self.long_functions += file_stats.long_functions.len();
+        if let Some(complexity) = &file_stats.complexity {
+            self.complexity
+                .get_or_insert_with(FunctionComplexity::default)
+                .add(complexity);
+        }
+        self.complex_functions += file_stats.complex_functions.len();
+        if let Some(depth) = file_stats.max_nesting {
+            self.max_nesting = Some(self.max_nesting.map_or(depth, |max| max.max(depth)));
+        }
+        self.nested_functions += file_stats.nested_functions.len();
+        if file_stats.classification == Classification::Approximate {
+            self.approximate_files += 1;
+        }
+        if file_stats.parse_errors > 0 {
+            self.parse_error_files += 1;
+        }
+        self.test_name_violations += file_stats.test_name_violations.len();
+        if let Some(bucket) = &file_stats.age_bucket {
+            self.age_buckets
+                .entry(bucket.clone())
+                .or_default()
+                .add(&file_stats.total);
+        }
+        if let Some(type_complexity) = &file_stats.type_complexity {
+            self.type_complexity
+                .get_or_insert_with(TypeComplexity::default)
+                .add(type_complexity);
+        }
+        if let Some(item_counts) = &file_stats.item_counts {
+            self.item_counts
+                .get_or_insert_with(ItemKindCounts::default)
+                .add(item_counts);
+        }
+        if let Some(panics) = &file_stats.panics {
+            self.panics
+                .get_or_insert_with(PanicCensus::default)
+                .add(panics);
+        }
+        if let Some(async_metrics) = &file_stats.async_metrics {
+            self.async_metrics
+                .get_or_insert_with(AsyncMetrics::default)
+                .add(async_metrics);
+        }
+        if let Some(doc_detail) = &file_stats.doc_detail {
+            self.doc_volume
+                .get_or_insert_with(DocVolume::default)
+                .add(&doc_detail.volume);
+        }
+        for trait_name in file_stats
+            .impls
+            .iter()
+            .filter_map(|block| block.trait_name.as_ref())
+        {
+            *self.trait_impls.entry(trait_name.clone()).or_default() += 1;
+        }
+        if let Some(hashes) = file_stats.content_hashes {
+            self.add_content(&file_stats.path, file_stats.total.all_lines, hashes);
+        }
+    }
+
+    /// Records the content of a file, grouping it with earlier files of the same content.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file
+    /// * `lines` - Total number of lines in the file
+    /// * `hashes` - Content hashes of the file
+    fn add_content(&mut self, path: &str, lines: usize, hashes: ContentHashes) {
+        let file = DuplicateFile {
+            path: path.to_string(),
+            lines,
+            exact: hashes.exact,
+        };
+
+        if let Some(group) = self
+            .duplicates
+            .iter_mut()
+            .find(|group| group.fingerprint == hashes.normalized)
+        {
+            group.identical &= group.files[0].exact == file.exact;
+            group.files.push(file);
+        } else if let Some(first) = self.unique_contents.remove(&hashes.normalized) {
+            self.duplicates.push(DuplicateGroup {
+                identical: first.exact == file.exact,
+                files: vec![first, file],
+                fingerprint: hashes.normalized,
+            });
+        } else {
+            self.unique_contents.insert(hashes.normalized, file);
+        }
+    }
+}
+
+/// Totals of all discovered files, extrapolated from the random subset analyzed with
+/// `--sample`.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+struct SampleEstimate {
+    /// Number of files discovered.
+    pub population_files: usize,
+
+    /// Number of files analyzed.
+    pub sampled_files: usize,
+
+    /// Seed that selected the sample; passing it to `--sample-seed` repeats the selection.
+    pub seed: u64,
+
+    /// Confidence level of the intervals, in percent.
+    pub confidence: u8,
+
+    /// Estimated totals, one per metric.
+    pub estimates: Vec<EstimatedTotal>,
+}
+
+/// Extrapolated total of one metric with the half-width of its confidence interval.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct EstimatedTotal {
+    /// Metric name, e.g. `code-lines` or `test-code-lines`.
+    pub metric: String,
+
+    /// Estimated total across all discovered files.
+    pub value: u64,
+
+    /// Half-width of the confidence interval, absent when fewer than two files were
+    /// sampled from a larger population.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub margin: Option<u64>,
+}
+
+/// Line and word counts of the markdown documentation shipped with a crate.
+///
+/// Covers the crate's README (the manifest's `package.readme`, or `README.md`) and every
+/// `.md` file below its `doc/` and `docs/` directories.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+struct MarkdownDocs {
+    /// Number of markdown files counted.
+    pub files: usize,
+
+    /// Number of non-blank markdown lines.
+    pub lines: usize,
+
+    /// Number of whitespace-separated words.
+    pub words: usize,
+}
+
+#[cfg(feature = "cli")]
+impl MarkdownDocs {
+    /// Adds another set of markdown counts to this one.
+    fn add(&mut self, other: &MarkdownDocs) {
+        self.files += other.files;
+        self.lines += other.lines;
+        self.words += other.words;
+    }
+}
+
+/// Blank, comment, and code line counts of the files of one language.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+struct LanguageLines {
+    /// Number of files counted.
+    pub files: usize,
+
+    /// Aggregate count of all lines.
+    pub all_lines: usize,
+
+    /// Count of lines consisting solely of whitespace characters.
+    pub blank_lines: usize,
+
+    /// Count of comment lines, including documentation comments.
+    pub comment_lines: usize,
+
+    /// Count of all other lines.
+    pub code_lines: usize,
+}
+
+#[cfg(feature = "cli")]
+impl LanguageLines {
+    /// Adds another set of line counts to this one.
+    fn add(&mut self, other: &LanguageLines) {
+        self.files += other.files;
+        self.all_lines += other.all_lines;
+        self.blank_lines += other.blank_lines;
+        self.comment_lines += other.comment_lines;
+        self.code_lines += other.code_lines;
+    }
+}
+
+/// Strategy pattern for memory-efficient accumulation of file statistics.
+///
+/// Defines a polymorphic interface enabling distinct storage backends for statistical
+/// data. Implementations may optimize for different constraints: in-memory accumulation
+/// for speed with small codebases, or streaming to disk for large-scale analyses
+/// exceeding available RAM.
+///
+/// # Design Rationale
+///
+/// - **Scalability**: Prevents memory exhaustion when analyzing extensive codebases
+/// - **Flexibility**: Permits runtime selection of accumulation strategy based on context
+/// - **Thread Safety**: Requires `Send` so a writer thread can own the accumulator
+///
+/// # Implementations
+///
+/// - `InMemoryAccumulator`: Stores all data in `Vec`, optimized for small to medium projects
+/// - `FileBackedAccumulator`: Streams to temporary file, suitable for arbitrarily large codebases
+/// - `SpillingAccumulator`: Starts in memory and moves to disk past a file-count threshold
+#[cfg(feature = "cli")]
+trait StatsAccumulator: Send {
+    /// Incorporates a file's statistics into the accumulator.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_stats` - Complete statistical profile for a single analyzed file
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the underlying storage mechanism fails (e.g., disk I/O errors,
+    /// serialization failures, or out-of-disk-space conditions).
+    fn add_file(&mut self, file_stats: &FileStats) -> Result<(), RulocError>;
+
+    /// Retrieves a snapshot of the current aggregate summary.
+    ///
+    /// Provides O(1) access to consolidated statistics without requiring traversal
+    /// of all accumulated files. The summary reflects all files added up to this point.
+    ///
+    /// # Returns
+    ///
+    /// Current `Summary` instance representing the aggregate of all accumulated files
+    fn get_summary(&self) -> Summary;
+
+    /// Constructs an iterator yielding individual file statistics.
+    ///
+    /// Enables sequential traversal of all accumulated file data for detailed reporting
+    /// or analysis. For file-backed implementations, this typically involves reading
+    /// from persistent storage.
+    ///
+    /// # Returns
+    ///
+    /// Boxed iterator producing `FileStats` instances in insertion order
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the backing store cannot be read (e.g., file corruption,
+    /// permission issues, or deserialization failures).
+    fn iter_files(&self) -> Result<Box<dyn Iterator<Item = FileStats>>, RulocError>;
+}
+
+/// Consumer of analysis results, for streaming them into custom destinations.
+///
+/// Implement this trait to feed a database, UI, or any other consumer directly from the
+/// pipeline with [`analyze_into`], instead of implementing `StatsAccumulator` and
+/// reading the files back. Files are delivered one at a time from a single thread, in
+/// path order, and are not retained by ruloc, so sinks need to be `Send` but not `Sync`.
+pub trait AnalysisSink: Send {
+    /// Receives the statistics of one analyzed file once it and every file before it
+    /// have completed.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_stats` - Statistics of the analyzed file
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the file cannot be consumed; the file is then reported to
+    /// [`AnalysisSink::skipped`] (or fails the run when a single file is analyzed)
+    fn file(&mut self, file_stats: &FileStats) -> Result<(), RulocError>;
+
+    /// Receives the files excluded from the analysis, sorted by path, once every analyzed
+    /// file has been delivered.
+    ///
+    /// Called only when the analysis succeeds, before [`AnalysisSink::summary`]. The
+    /// default implementation ignores them.
+    ///
+    /// # Arguments
+    ///
+    /// * `skipped` - Files that were not analyzed, with the reason for each
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the skipped files cannot be consumed
+    fn skipped(&mut self, skipped: &[SkippedFile]) -> Result<(), RulocError> {
+        let _ = skipped;
+        Ok(())
+    }
+
+    /// Receives the Rust files that no crate root reaches, sorted by path.
+    ///
+    /// Called only when the analysis of an entry point succeeds, before
+    /// [`AnalysisSink::summary`]. The default implementation ignores them.
+    ///
+    /// # Arguments
+    ///
+    /// * `orphans` - Paths of the unreachable files
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the orphans cannot be consumed
+    fn orphans(&mut self, orphans: &[String]) -> Result<(), RulocError> {
+        let _ = orphans;
+        Ok(())
+    }
+
+    /// Receives the aggregate summary once every file has been delivered.
+    ///
+    /// Called only when the analysis succeeds. The default implementation ignores it.
+    ///
+    /// # Arguments
+    ///
+    /// * `summary` - Aggregate statistics across all delivered files
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err` if the summary cannot be consumed
+    fn summary(&mut self, summary: &Summary) -> Result<(), RulocError> {
+        let _ = summary;
+        Ok(())
+    }
+}
+
+/// Accumulator adapter that forwards each file to an [`AnalysisSink`].
+///
+/// Only the rolling summary is kept; file statistics go straight to the sink.
+#[cfg(feature = "cli")]
+struct SinkAccumulator<'a, S: AnalysisSink> {
+    /// Consumer receiving each file.
+    sink: &'a mut S,
+
+    /// In-memory rolling summary, incrementally updated with each file.
+    summary: Summary,
+}
+
+#[cfg(feature = "cli")]
+impl<S: AnalysisSink> StatsAccumulator for SinkAccumulator<'_, S> {
+    fn add_file(&mut self, file_stats: &FileStats) -> Result<(), RulocError> {
+        self.sink.file(file_stats)?;
+        self.summary.add_file(file_stats);
+        Ok(())
+    }
+
+    fn get_summary(&self) -> Summary {
+        self.summary.clone()
+    }
+
+    fn iter_files(&self) -> Result<Box<dyn Iterator<Item = FileStats>>, RulocError> {
+        Err(RulocError::Output(
+            "Analysis sinks receive file statistics without retaining them".to_string(),
+        ))
+    }
+}
+
+/// Renders a path for reports with `/` separators on every platform.
+///
+/// Only the platform's own separator is replaced, so a backslash that is part of a file
+/// name on Unix is kept.
+///
+/// # Arguments
+///
+/// * `path` - Path to render
+///
+/// # Returns
+///
+/// The lossily decoded path using `/` between components
+fn display_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .replace(std::path::MAIN_SEPARATOR, "/")
+}
+
+/// Expresses a reported file path relative to a root directory using `/` separators.
+///
+/// Resolves the path to its canonical form when it exists on disk before stripping the
+/// root prefix, so results are identical across platforms and invocation directories.
+///
+/// # Arguments
+///
+/// * `root` - Canonical root directory (e.g., the git working tree root)
+/// * `path` - File path as recorded in [`FileStats::path`]
+///
+/// # Returns
+///
+/// The `/`-joined path below `root`, or the normalized path itself if it lies outside
+fn root_relative_path(root: &Path, path: &str) -> String {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+    let relative = path.strip_prefix(root).unwrap_or(&path);
+    relative
+        .components()
+        .filter_map(|component| match component {
+            std::path::Component::Normal(part) => Some(part.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Seconds in a day, used to convert commit timestamps into ages.
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Last-commit timestamps of every file in a git working tree.
+///
+/// Built from a single `git log` traversal so that bucketing thousands of files does not
+/// spawn one git process per file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct FileAges {
+    /// Canonical root of the git working tree.
+    root: PathBuf,
+
+    /// Unix timestamp of the most recent commit touching each root-relative path.
+    last_modified: HashMap<String, i64>,
+
+    /// Unix timestamp that ages are measured against.
+    now: i64,
+}
+
+impl FileAges {
+    /// Reads last-modified commit times for the repository enclosing a directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if git is unavailable or the directory is not inside a repository
+    #[cfg(feature = "cli")]
+    fn load(analysis_root: &Path) -> Result<Self, RulocError> {
+        let root = PathBuf::from(run_git(analysis_root, &["rev-parse", "--show-toplevel"])?);
+        let root = fs::canonicalize(&root).unwrap_or(root);
+        let log = run_git(
+            &root,
+            &["log", "--format=@%ct", "--name-only", "--no-renames"],
+        )?;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs() as i64);
+
+        Ok(Self {
+            root,
+            last_modified: Self::parse_log(&log),
+            now,
+        })
+    }
+
+    /// Parses `git log --format=@%ct --name-only` output, newest commits first.
+    ///
+    /// # Returns
+    ///
+    /// The newest commit timestamp for every path mentioned in the log
+    #[cfg(feature = "cli")]
+    fn parse_log(log: &str) -> HashMap<String, i64> {
+        let mut last_modified = HashMap::new();
+        let mut timestamp = None;
+
+        for line in log.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            match line.strip_prefix('@') {
+                Some(time) => timestamp = time.parse::<i64>().ok(),
+                None => {
+                    if let Some(time) = timestamp {
+                        last_modified.entry(line.to_string()).or_insert(time);
+                    }
+                }
+            }
+        }
+
+        last_modified
+    }
+
+    /// Classifies a file into an age bucket by the time since its last commit.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path as recorded in [`FileStats::path`]
+    ///
+    /// # Returns
+    ///
+    /// One of the `--age-buckets` bucket names; files never committed are `untracked`
+    fn bucket_for(&self, path: &str) -> &'static str {
+        let Some(modified) = self
+            .last_modified
+            .get(&root_relative_path(&self.root, path))
+        else {
+            return "untracked";
+        };
+
+        match (self.now - modified) / SECONDS_PER_DAY {
+            days if days < 90 => "under-3-months",
+            days if days < 365 => "3-to-12-months",
+            _ => "over-1-year",
+        }
+    }
+}
+
+/// Runs a git command in a directory and captures its trimmed standard output.
+///
+/// # Arguments
+///
+/// * `dir` - Directory in which to run git
+/// * `args` - Arguments passed to git
+///
+/// # Returns
+///
+/// The command's standard output with surrounding whitespace removed
+///
+/// # Errors
+///
+/// Returns an error if git cannot be executed or exits unsuccessfully (e.g., the
+/// directory is not inside a git working tree)
+#[cfg(feature = "cli")]
+fn run_git(dir: &Path, args: &[&str]) -> Result<String, RulocError> {
+    run_git_raw(dir, args).map(|output| output.trim().to_string())
+}
+
+/// Runs a git command in a directory and captures its standard output verbatim.
+///
+/// Used where surrounding whitespace is significant, such as file contents read from the
+/// object database.
+///
+/// # Arguments
+///
+/// * `dir` - Directory in which to run git
+/// * `args` - Arguments passed to git
+///
+/// # Returns
+///
+/// The command's standard output, with invalid UTF-8 replaced
+///
+/// # Errors
+///
+/// Returns an error if git cannot be executed or exits unsuccessfully
+#[cfg(feature = "cli")]
+fn run_git_raw(dir: &Path, args: &[&str]) -> Result<String, RulocError> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| {
+            RulocError::Git(format!(
+                "Failed to run git: {}. Ensure git is installed and on PATH.",
+                e
+            ))
+        })?;
+
+    if !output.status.success() {
+        return Err(RulocError::Git(format!(
+            "git {} failed in '{}': {}",
+            args.join(" "),
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Resolves the Rust edition of source files from their crates' `Cargo.toml`.
+///
+/// The nearest manifest with a `[package]` table owns a file. Its `package.edition` is used,
+/// following `edition.workspace = true` to the enclosing workspace's
+/// `workspace.package.edition`; a package without an edition is 2015, matching Cargo.
+/// Files outside any package are parsed with [`Edition::CURRENT`]. Results are cached per
+/// directory since every file of a crate shares one answer. An edition given with
+/// `--edition` overrides the manifests.
+#[derive(Debug, Default)]
+struct EditionResolver {
+    /// Edition every file is parsed with, ignoring the manifests.
+    forced: Option<Edition>,
+
+    /// Edition resolved for each directory visited so far.
+    by_dir: Mutex<HashMap<PathBuf, Edition>>,
+}
+
+impl EditionResolver {
+    /// Creates a resolver, optionally parsing every file with one edition.
+    ///
+    /// # Arguments
+    ///
+    /// * `forced` - Edition overriding the manifests, as given with `--edition`
+    #[cfg(feature = "cli")]
+    fn new(forced: Option<Edition>) -> Self {
+        Self {
+            forced,
+            ..Default::default()
+        }
+    }
+
+    /// Determines the edition a file should be parsed with.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - Path to the Rust source file
+    ///
+    /// # Returns
+    ///
+    /// The `--edition` override, else the edition of the owning package, or
+    /// [`Edition::CURRENT`] if there is none
+    fn resolve(&self, file: &Path) -> Edition {
+        if let Some(edition) = self.forced {
+            return edition;
+        }
+        let dir = file
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+
+        if let Some(edition) = self.by_dir.lock().unwrap().get(&dir) {
+            return *edition;
+        }
+
+        let edition = dir
+            .ancestors()
+            .find_map(|ancestor| {
+                let manifest = read_manifest(&ancestor.join("Cargo.toml"))?;
+                let package = manifest.get("package")?;
+                Some(package_edition(ancestor, package, |dir| {
+                    read_manifest(&dir.join("Cargo.toml"))
+                }))
+            })
+            .unwrap_or(Edition::CURRENT);
+
+        self.by_dir.lock().unwrap().insert(dir, edition);
+        edition
+    }
+}
+
+/// Kinds of tooling code reported apart from production code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ToolingKind {
+    /// A build script and the modules it declares.
+    BuildScript,
+
+    /// The library of a procedural macro crate.
+    ProcMacro,
+}
+
+impl ToolingKind {
+    /// Routes the non-test, non-benchmark lines of a file to their category.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - Tooling kind of the file, `None` for ordinary code
+    /// * `shipped` - Statistics of the lines that are neither test nor benchmark code
+    ///
+    /// # Returns
+    ///
+    /// `(production, build_script, proc_macro)` statistics
+    fn split(kind: Option<Self>, shipped: LineStats) -> (LineStats, LineStats, LineStats) {
+        match kind {
+            None => (shipped, LineStats::default(), LineStats::default()),
+            Some(ToolingKind::BuildScript) => (LineStats::default(), shipped, LineStats::default()),
+            Some(ToolingKind::ProcMacro) => (LineStats::default(), LineStats::default(), shipped),
+        }
+    }
+}
+
+/// Tooling files of a package, keyed by canonical path.
+type PackageTooling = HashMap<PathBuf, ToolingKind>;
+
+/// Identifies build script and procedural macro files from their packages' `Cargo.toml`.
+///
+/// The nearest manifest with a `[package]` table owns a file. Its build script (`build.rs`
+/// or `package.build`) and, when `[lib] proc-macro = true`, its library are followed
+/// through their `mod` declarations. Results are cached per package and per directory.
+#[derive(Debug, Default)]
+struct ToolingResolver {
+    /// Tooling files of the package owning each directory visited so far.
+    by_dir: Mutex<HashMap<PathBuf, Arc<PackageTooling>>>,
+
+    /// Tooling files of each package visited so far, keyed by manifest directory.
+    by_package: Mutex<HashMap<PathBuf, Arc<PackageTooling>>>,
+}
+
+impl ToolingResolver {
+    /// Determines whether a file is tooling code.
+    ///
+    /// # Arguments
+    ///
+    /// * `file` - Path to the Rust source file
+    ///
+    /// # Returns
+    ///
+    /// The tooling kind of the file, or `None` for ordinary code
+    fn resolve(&self, file: &Path) -> Option<ToolingKind> {
+        let dir = file
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+
+        let cached = self.by_dir.lock().unwrap().get(&dir).cloned();
+        let tooling = cached.unwrap_or_else(|| {
+            let tooling = dir
+                .ancestors()
+                .find_map(|ancestor| {
+                    let manifest = read_manifest(&ancestor.join("Cargo.toml"))?;
+                    manifest.get("package")?;
+                    Some(self.package(ancestor, &manifest))
+                })
+                .unwrap_or_default();
+            self.by_dir
+                .lock()
+                .unwrap()
+                .insert(dir.clone(), Arc::clone(&tooling));
+            tooling
+        });
+
+        let file = fs::canonicalize(file).unwrap_or_else(|_| file.to_path_buf());
+        tooling.get(&file).copied()
+    }
+
+    /// Collects the tooling files of a package, once per package.
+    ///
+    /// # Arguments
+    ///
+    /// * `manifest_dir` - Directory of the package's `Cargo.toml`
+    /// * `manifest` - The parsed manifest
+    ///
+    /// # Returns
+    ///
+    /// The build script and procedural macro files of the package
+    fn package(&self, manifest_dir: &Path, manifest: &toml::Table) -> Arc<PackageTooling> {
+        if let Some(tooling) = self.by_package.lock().unwrap().get(manifest_dir) {
+            return Arc::clone(tooling);
+        }
+
+        let build = match manifest
+            .get("package")
+            .and_then(|package| package.get("build"))
+        {
+            Some(toml::Value::String(path)) => Some(manifest_dir.join(path)),
+            Some(toml::Value::Boolean(false)) => None,
+            _ => Some(manifest_dir.join("build.rs")),
+        };
+        let lib = manifest.get("lib");
+        let proc_macro = lib
+            .and_then(|lib| lib.get("proc-macro").or_else(|| lib.get("proc_macro")))
+            .and_then(toml::Value::as_bool)
+            .unwrap_or(false)
+            .then(|| {
+                lib.and_then(|lib| lib.get("path"))
+                    .and_then(toml::Value::as_str)
+                    .map(|path| manifest_dir.join(path))
+                    .unwrap_or_else(|| manifest_dir.join("src").join("lib.rs"))
+            });
+
+        let mut tooling = PackageTooling::new();
+        let roots = [
+            (build, ToolingKind::BuildScript),
+            (proc_macro, ToolingKind::ProcMacro),
+        ];
+        for (root, kind) in roots {
+            let Some(root) = root.filter(|root| root.is_file()) else {
+                continue;
+            };
+            match follow_module_tree(&root, &mut HashSet::new()) {
+                Ok(files) => tooling.extend(
+                    files
+                        .into_iter()
+                        .map(|file| (fs::canonicalize(&file).unwrap_or(file), kind)),
+                ),
+                Err(e) => warn!("{}", e),
+            }
+        }
+
+        let tooling = Arc::new(tooling);
+        self.by_package
+            .lock()
+            .unwrap()
+            .insert(manifest_dir.to_path_buf(), Arc::clone(&tooling));
+        tooling
+    }
+}
+
+/// Reads and parses a `Cargo.toml`, returning `None` if it is missing or malformed.
+fn read_manifest(path: &Path) -> Option<toml::Table> {
+    let content = fs::read_to_string(path).ok()?;
+    toml::from_str(&content)
+        .map_err(|e| warn!("Failed to parse {}: {}", path.display(), e))
+        .ok()
+}
+
+/// Extracts the edition declared by a `[package]` table.
+///
+/// # Arguments
+///
+/// * `manifest_dir` - Directory of the manifest declaring the package
+/// * `package` - The `[package]` table
+/// * `read_manifest` - Reads the manifest of a directory, used to find the workspace
+///
+/// # Returns
+///
+/// The declared or workspace-inherited edition, 2015 when unspecified
+fn package_edition(
+    manifest_dir: &Path,
+    package: &toml::Value,
+    read_manifest: impl Fn(&Path) -> Option<toml::Table>,
+) -> Edition {
+    let declared = match package.get("edition") {
+        Some(toml::Value::String(edition)) => Some(edition.clone()),
+        Some(inherited)
+            if inherited.get("workspace").and_then(toml::Value::as_bool) == Some(true) =>
+        {
+            manifest_dir
+                .ancestors()
+                .skip(1)
+                .find_map(|ancestor| {
+                    let manifest = read_manifest(ancestor)?;
+                    let workspace = manifest.get("workspace")?;
+                    Some(
+                        workspace
+                            .get("package")
+                            .and_then(|package| package.get("edition"))
+                            .and_then(toml::Value::as_str)
+                            .map(str::to_string),
+                    )
+                })
+                .flatten()
+        }
+        _ => None,
+    };
+
+    match declared {
+        Some(edition) => edition.parse().unwrap_or_else(|_| {
+            warn!(
+                "Unknown edition '{}' in {}, parsing with {}",
+                edition,
+                manifest_dir.join("Cargo.toml").display(),
+                Edition::CURRENT
+            );
+            Edition::CURRENT
+        }),
+        None => Edition::Edition2015,
+    }
+}
+
+/// Rules for symbolic links met while walking a directory.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SymlinkPolicy {
+    /// Whether symlinked files and directories are traversed at all.
+    follow: bool,
+
+    /// Whether followed links may resolve outside the directory being walked.
+    allow_external: bool,
+}
+
+#[cfg(feature = "cli")]
+impl Default for SymlinkPolicy {
+    fn default() -> Self {
+        Self {
+            follow: true,
+            allow_external: false,
+        }
+    }
+}
+
+#[cfg(feature = "cli")]
+impl SymlinkPolicy {
+    /// Decides whether a directory entry may be visited under this policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - Entry produced by the directory walk
+    /// * `root` - Canonical path of the directory being walked
+    ///
+    /// # Returns
+    ///
+    /// `true` unless the entry is a symlink that must not be followed or that escapes `root`
+    #[cfg(feature = "cli")]
+    fn admits(&self, entry: &walkdir::DirEntry, root: &Path) -> bool {
+        match self.refusal(entry, root) {
+            None => true,
+            Some(SymlinkRefusal::NotFollowed) => {
+                debug!("Skipping symlink {}", entry.path().display());
+                false
+            }
+            Some(SymlinkRefusal::External(target)) => {
+                warn!(
+                    "Skipping symlink {} pointing outside the analysis root to {} (pass --allow-external to follow it)",
+                    entry.path().display(),
+                    target.display()
+                );
+                false
+            }
+            Some(SymlinkRefusal::Broken(e)) => {
+                warn!("Skipping broken symlink {}: {}", entry.path().display(), e);
+                false
+            }
+        }
+    }
+
+    /// Determines why a directory entry must not be visited under this policy, without
+    /// logging it.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - Entry produced by the directory walk
+    /// * `root` - Canonical path of the directory being walked
+    ///
+    /// # Returns
+    ///
+    /// The reason the entry is refused, or `None` if it may be visited
+    #[cfg(feature = "cli")]
+    fn refusal(&self, entry: &walkdir::DirEntry, root: &Path) -> Option<SymlinkRefusal> {
+        if entry.depth() == 0 || !entry.path_is_symlink() {
+            return None;
+        }
+        if !self.follow {
+            return Some(SymlinkRefusal::NotFollowed);
+        }
+        if self.allow_external {
+            return None;
+        }
+
+        match fs::canonicalize(entry.path()) {
+            Ok(target) if target.starts_with(root) => None,
+            Ok(target) => Some(SymlinkRefusal::External(target)),
+            Err(e) => Some(SymlinkRefusal::Broken(e)),
+        }
+    }
+}
+
+/// Why a [`SymlinkPolicy`] refuses to visit a symbolic link.
+#[cfg(feature = "cli")]
+#[derive(Debug)]
+enum SymlinkRefusal {
+    /// Symlinks are not followed at all.
+    NotFollowed,
+
+    /// The link resolves outside the directory being walked, to the given target.
+    External(PathBuf),
+
+    /// The link target cannot be resolved.
+    Broken(std::io::Error),
+}
+
+#[cfg(feature = "cli")]
+impl SymlinkRefusal {
+    /// Describes the refusal for reports.
+    fn describe(&self) -> String {
+        match self {
+            SymlinkRefusal::NotFollowed => {
+                "symlink not followed (--no-follow-symlinks)".to_string()
+            }
+            SymlinkRefusal::External(target) => format!(
+                "symlink pointing outside the analysis root to {} (--allow-external follows it)",
+                target.display()
+            ),
+            SymlinkRefusal::Broken(e) => format!("broken symlink: {}", e),
+        }
+    }
+}
+
+/// File name extensions that directory walks treat as Rust sources, set with `--ext`.
+///
+/// Extensions may span several dots, such as `rs.in` for templates spliced with
+/// `include!`, so they are matched against the end of the file name rather than
+/// [`Path::extension`].
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SourceExtensions(Vec<String>);
+
+#[cfg(feature = "cli")]
+impl Default for SourceExtensions {
+    fn default() -> Self {
+        SourceExtensions(vec!["rs".to_string()])
+    }
+}
+
+#[cfg(feature = "cli")]
+impl SourceExtensions {
+    /// Builds the extension set from `--ext` values.
+    ///
+    /// # Arguments
+    ///
+    /// * `extensions` - Extensions with or without a leading dot
+    ///
+    /// # Returns
+    ///
+    /// The given extensions, or `rs` alone if none remain after trimming
+    fn new(extensions: &[String]) -> Self {
+        let extensions: Vec<String> = extensions
+            .iter()
+            .map(|ext| ext.trim().trim_start_matches('.').to_string())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        if extensions.is_empty() {
+            Self::default()
+        } else {
+            SourceExtensions(extensions)
+        }
+    }
+
+    /// Checks whether a file is a Rust source by its name.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File to check
+    ///
+    /// # Returns
+    ///
+    /// `true` if the file name is a non-empty stem followed by one of the extensions
+    fn matches(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+        self.0.iter().any(|ext| {
+            name.strip_suffix(ext.as_str())
+                .and_then(|stem| stem.strip_suffix('.'))
+                .is_some_and(|stem| !stem.is_empty())
+        })
+    }
+}
+
+/// Orders two paths by their bytes, the order in which reports list files.
+///
+/// Unlike [`Path`]'s component-wise ordering, `a-b/c.rs` sorts before `a/b.rs` here, just
+/// as their reported strings do.
+///
+/// # Arguments
+///
+/// * `a` - First path
+/// * `b` - Second path
+///
+/// # Returns
+///
+/// The byte-wise ordering of the two paths
+#[cfg(feature = "cli")]
+fn compare_paths(a: &Path, b: &Path) -> std::cmp::Ordering {
+    a.as_os_str()
+        .as_encoded_bytes()
+        .cmp(b.as_os_str().as_encoded_bytes())
+}
+
+/// Orders two siblings of a directory walk so that the walk yields paths in the order of
+/// [`compare_paths`].
+///
+/// A directory is compared as its name followed by a separator, since that is how its
+/// name continues in the paths of the files below it.
+///
+/// # Arguments
+///
+/// * `a` - First entry
+/// * `b` - Second entry, in the same directory as `a`
+///
+/// # Returns
+///
+/// The ordering of the two entries
+#[cfg(feature = "cli")]
+fn compare_walk_entries(a: &walkdir::DirEntry, b: &walkdir::DirEntry) -> std::cmp::Ordering {
+    let separator = |entry: &walkdir::DirEntry| entry.file_type().is_dir().then_some(b'/');
+    let (a_separator, b_separator) = (separator(a), separator(b));
+    let a_key = a.file_name().as_encoded_bytes().iter().chain(&a_separator);
+    let b_key = b.file_name().as_encoded_bytes().iter().chain(&b_separator);
+    a_key.cmp(b_key)
+}
+
+/// Starts a directory walk that honors a symlink policy.
+///
+/// Entries are visited in path order (see [`compare_walk_entries`]), so everything
+/// discovered by the walk is already sorted the way reports list files.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to walk
+/// * `policy` - Whether to follow symlinks and whether they may leave `dir`
+///
+/// # Returns
+///
+/// The walk, ready for further `filter_entry` pruning; pair with [`walk_entry_ok`]
+#[cfg(feature = "cli")]
+fn walk_dir(
+    dir: &Path,
+    policy: SymlinkPolicy,
+) -> walkdir::FilterEntry<walkdir::IntoIter, impl FnMut(&walkdir::DirEntry) -> bool> {
+    let root = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    WalkDir::new(dir)
+        .follow_links(policy.follow)
+        .sort_by(compare_walk_entries)
+        .into_iter()
+        .filter_entry(move |entry| policy.admits(entry, &root))
+}
+
+/// Starts a directory walk like [`walk_dir`] that also prunes `target` directories.
+///
+/// `filter_entry` cannot be chained onto [`walk_dir`], so workspace-wide scans use this
+/// walk to skip build output below `dir`.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to walk
+/// * `policy` - Whether to follow symlinks and whether they may leave `dir`
+///
+/// # Returns
+///
+/// The walk; pair with [`walk_entry_ok`]
+#[cfg(feature = "cli")]
+fn walk_workspace(
+    dir: &Path,
+    policy: SymlinkPolicy,
+) -> walkdir::FilterEntry<walkdir::IntoIter, impl FnMut(&walkdir::DirEntry) -> bool> {
+    let root = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    WalkDir::new(dir)
+        .follow_links(policy.follow)
+        .into_iter()
+        .filter_entry(move |entry| {
+            (entry.depth() == 0 || entry.file_name() != "target") && policy.admits(entry, &root)
+        })
+}
+
+/// Unwraps a directory walk result, reporting symlink cycles instead of looping on them.
+///
+/// # Returns
+///
+/// The entry, or `None` if it could not be read
+#[cfg(feature = "cli")]
+fn walk_entry_ok(entry: walkdir::Result<walkdir::DirEntry>) -> Option<walkdir::DirEntry> {
+    entry
+        .map_err(|e| match (e.loop_ancestor(), e.path()) {
+            (Some(ancestor), Some(path)) => warn!(
+                "Skipping symlink cycle at {} (points back to {})",
+                path.display(),
+                ancestor.display()
+            ),
+            _ => debug!("Skipping unreadable entry: {}", e),
+        })
+        .ok()
+}
+
+/// Heuristics that identify generated source files.
+#[derive(Debug, Clone, Default)]
+struct GeneratedRules {
+    /// Markers searched for in the first [`GENERATED_HEADER_LINES`] lines of a file.
+    markers: Vec<String>,
+
+    /// Path globs, compiled to regular expressions matched against `/`-separated paths.
+    globs: Vec<Regex>,
+}
+
+impl GeneratedRules {
+    /// Builds rules from marker strings and path globs, ignoring blank entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a glob cannot be compiled
+    #[cfg(feature = "cli")]
+    fn new(markers: &[String], globs: &[String]) -> Result<Self, RulocError> {
+        let non_blank = |values: &[String]| -> Vec<String> {
+            values
+                .iter()
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+                .collect()
+        };
+
+        Ok(Self {
+            markers: non_blank(markers),
+            globs: non_blank(globs)
+                .iter()
+                .map(|glob| glob_to_regex(glob))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Decides whether a file is generated.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file
+    /// * `content` - Content of the file
+    ///
+    /// # Returns
+    ///
+    /// `true` if the path matches a glob or the header contains a marker
+    fn matches(&self, path: &Path, content: &str) -> bool {
+        let path = path.to_string_lossy().replace('\\', "/");
+        if self.globs.iter().any(|glob| glob.is_match(&path)) {
+            return true;
+        }
+
+        content.lines().take(GENERATED_HEADER_LINES).any(|line| {
+            self.markers
+                .iter()
+                .any(|marker| line.contains(marker.as_str()))
+        })
+    }
+}
+
+/// Category assigned to items matched by a user-supplied classification rule.
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum RuleCategory {
+    /// Lines are counted as test code.
+    Test,
+
+    /// Lines are counted as benchmark code.
+    Bench,
+
+    /// The whole file is reported as generated.
+    Generated,
+}
+
+/// A rule mapping an attribute path to a classification category.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+struct ClassificationRule {
+    /// Attribute path to match, such as `wasm_bindgen_test` or `tokio::test`.
+    attribute: String,
+
+    /// Category given to items carrying the attribute.
+    category: RuleCategory,
+}
+
+/// Attribute-driven classification rules loaded from a `--rules` file.
+///
+/// Extends the built-in `#[test]`, `#[cfg(test)]`, and `#[bench]` detection with
+/// project-specific attributes.
+///
+/// # Example
+///
+/// ```toml
+/// [[rule]]
+/// attribute = "wasm_bindgen_test"
+/// category = "test"
+///
+/// [[rule]]
+/// attribute = "builder"
+/// category = "generated"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+struct ClassificationRules {
+    /// Rules in declaration order; the first matching rule wins.
+    #[serde(rename = "rule", default)]
+    rules: Vec<ClassificationRule>,
+}
+
+impl ClassificationRules {
+    /// Reads and parses a rules file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a valid rules document
+    #[cfg(feature = "cli")]
+    fn load(path: &Path) -> Result<Self, RulocError> {
+        let content = fs::read_to_string(path).map_err(RulocError::io(format!(
+            "Failed to read rules '{}'",
+            path.display()
+        )))?;
+        toml::from_str(&content)
+            .map_err(|e| RulocError::Config(format!("Invalid rules '{}': {}", path.display(), e)))
+    }
+
+    /// Checks whether any rule assigns items to a category, so that files can skip
+    /// walking their syntax tree for categories no rule uses.
+    ///
+    /// # Arguments
+    ///
+    /// * `category` - Category to look for
+    ///
+    /// # Returns
+    ///
+    /// `true` if at least one rule has the category
+    fn assigns(&self, category: RuleCategory) -> bool {
+        self.rules.iter().any(|rule| rule.category == category)
+    }
+
+    /// Determines the category a rule assigns to a syntax node.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The syntax tree node to examine
+    ///
+    /// # Returns
+    ///
+    /// The category of the first rule matching one of the node's attributes, if any
+    fn category_of(&self, node: &SyntaxNode) -> Option<RuleCategory> {
+        if self.rules.is_empty() {
+            return None;
+        }
+
+        let owner = ast::AnyHasAttrs::cast(node.clone())?;
+        let paths: Vec<String> = owner
+            .attrs()
+            .filter_map(|attr| attr.path())
+            .map(|path| path.to_string())
+            .collect();
+        self.rules
+            .iter()
+            .find(|rule| paths.contains(&rule.attribute))
+            .map(|rule| rule.category)
+    }
+
+    /// Marks the lines covered by items that a rule assigns to a category.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - Root of the file's syntax tree
+    /// * `content` - The complete source file content
+    /// * `category` - Category to mark
+    ///
+    /// # Returns
+    ///
+    /// One flag per line, `true` for lines of matched items
+    fn classify_lines(
+        &self,
+        root: &SyntaxNode,
+        content: &str,
+        category: RuleCategory,
+    ) -> Vec<bool> {
+        if !self.assigns(category) {
+            return vec![false; content.lines().count()];
+        }
+        mark_node_lines(
+            root.descendants()
+                .filter(|node| self.category_of(node) == Some(category)),
+            content,
+        )
+    }
+
+    /// Decides whether any item of a file is assigned to a category.
+    fn matches_any(&self, root: &SyntaxNode, category: RuleCategory) -> bool {
+        self.assigns(category)
+            && root
+                .descendants()
+                .any(|node| self.category_of(&node) == Some(category))
+    }
+}
+
+/// Compiles a path glob into a regular expression matching whole path components.
+///
+/// `**` matches across directories, `*` and `?` stay within one component, and a pattern
+/// matches any path ending in it (so `*.pb.rs` matches `src/proto/a.pb.rs`).
+///
+/// # Errors
+///
+/// Returns an error if the resulting expression is invalid
+#[cfg(feature = "cli")]
+fn glob_to_regex(glob: &str) -> Result<Regex, RulocError> {
+    let mut pattern = String::from("(?:^|/)");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                pattern.push_str(".*");
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+
+    Regex::new(&pattern).map_err(|e| RulocError::Config(format!("Invalid glob '{}': {}", glob, e)))
+}
+
+/// Phases of a run whose time is reported by `--timings`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TimingPhase {
+    /// Discovering the files to analyze.
+    #[cfg(feature = "cli")]
+    Walk,
+
+    /// Reading source files and the single parse that types their lines.
+    Parse,
+
+    /// Applying classification rules and computing per-file metrics.
+    Classify,
+
+    /// Adding file statistics to the accumulator.
+    #[cfg(feature = "cli")]
+    Aggregate,
+
+    /// Rendering and delivering the report.
+    #[cfg(feature = "cli")]
+    Output,
+}
+
+impl TimingPhase {
+    /// Every phase, in pipeline order.
+    #[cfg(feature = "cli")]
+    const ALL: [TimingPhase; 5] = [
+        TimingPhase::Walk,
+        TimingPhase::Parse,
+        TimingPhase::Classify,
+        TimingPhase::Aggregate,
+        TimingPhase::Output,
+    ];
+
+    /// Returns the name printed for the phase.
+    #[cfg(feature = "cli")]
+    fn name(self) -> &'static str {
+        match self {
+            TimingPhase::Walk => "walk",
+            TimingPhase::Parse => "parse",
+            TimingPhase::Classify => "classify",
+            TimingPhase::Aggregate => "aggregate",
+            TimingPhase::Output => "output",
+        }
+    }
+}
+
+/// Time spent per phase and per file, collected with `--timings`.
+///
+/// Worker threads add to the phase totals concurrently, so the walk, parse, and
+/// classify phases are summed across threads and may exceed the wall time of the run.
+#[derive(Debug, Default)]
+struct Timings {
+    /// Nanoseconds spent per phase, indexed in `TimingPhase::ALL` order.
+    phases: [AtomicU64; 5],
+
+    /// Analysis time of every file.
+    #[cfg(feature = "cli")]
+    files: Mutex<Vec<(String, Duration)>>,
+}
+
+impl Timings {
+    /// Adds elapsed time to a phase.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - Phase the time was spent in
+    /// * `elapsed` - Time spent
+    fn add(&self, phase: TimingPhase, elapsed: Duration) {
+        self.phases[phase as usize].fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    /// Returns the total time spent in a phase.
+    #[cfg(feature = "cli")]
+    fn phase(&self, phase: TimingPhase) -> Duration {
+        Duration::from_nanos(self.phases[phase as usize].load(Ordering::Relaxed))
+    }
+
+    /// Records how long a file took to analyze.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The analyzed file
+    /// * `elapsed` - Time spent analyzing it
+    #[cfg(feature = "cli")]
+    fn record_file(&self, path: &Path, elapsed: Duration) {
+        self.files
+            .lock()
+            .unwrap()
+            .push((display_path(path), elapsed));
+    }
+
+    /// Returns the slowest files, slowest first and ties ordered by path.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - Maximum number of files to return
+    #[cfg(feature = "cli")]
+    fn slowest_files(&self, count: usize) -> Vec<(String, Duration)> {
+        let mut files = self.files.lock().unwrap().clone();
+        files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        files.truncate(count);
+        files
+    }
+}
+
+/// Settings governing how individual files are analyzed.
+///
+/// Bundles the limits and opt-in analyses applied uniformly to every file, so that the
+/// single-file, directory, and plan-driven pipelines share one configuration surface.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisOptions {
+    /// Maximum file size in bytes; larger files are skipped.
+    max_file_size: Option<u64>,
+
+    /// Whether to attribute lines to the cargo features gating them.
+    feature_breakdown: bool,
+
+    /// Whether to measure the lines under non-test conditional compilation.
+    cfg_surface: bool,
+
+    /// Width above which lines are counted as too long, when measuring line lengths.
+    line_width: Option<usize>,
+
+    /// Whether to count code lines dominated by string literals.
+    string_lines: bool,
+
+    /// Whether to hash file contents to find duplicate files.
+    duplicates: bool,
+
+    /// Size measures to report alongside line counts.
+    size_measures: Vec<SizeMeasure>,
+
+    /// Whether to record the line statistics of inline modules for module grouping.
+    inline_modules: bool,
+
+    /// Work-markers counted within comments; an empty list disables the census.
+    markers: Vec<String>,
+
+    /// Whether to record the location of every work-marker occurrence.
+    list_markers: bool,
+
+    /// Whether to count the lints suppressed with `allow` attributes.
+    allows: bool,
+
+    /// Whether to count call sites that may panic.
+    panics: bool,
+
+    /// Whether to record the location of every panic call site.
+    list_panics: bool,
+
+    /// Whether to record and log the location of every syntax error.
+    list_parse_errors: bool,
+
+    /// Whether files named `*_test.rs` or `test_*.rs` are counted as test code.
+    test_file_names: bool,
+
+    /// Line threshold above which functions are reported as too long.
+    max_fn_lines: Option<usize>,
+
+    /// Whether to estimate the cyclomatic complexity of every function.
+    complexity: bool,
+
+    /// Complexity threshold above which functions are reported as too complex.
+    max_complexity: Option<usize>,
+
+    /// Whether to measure the block nesting depth of every function.
+    nesting: bool,
+
+    /// Depth above which functions are reported as too deeply nested.
+    max_nesting: Option<usize>,
+
+    /// Whether to inventory `impl` blocks and the traits they implement.
+    list_impls: bool,
+
+    /// Whether to count generic parameters, `where` clauses, and higher-ranked bounds.
+    type_complexity: bool,
+
+    /// Whether to count declared items by kind.
+    item_counts: bool,
+
+    /// Whether to measure the share of public items carrying rustdoc.
+    doc_coverage: bool,
+
+    /// Whether to count the code lines inside macro definitions and invocations.
+    macro_lines: bool,
+
+    /// Whether to count declared items by visibility.
+    item_visibility: bool,
+
+    /// Whether to measure `async`/`.await` usage.
+    async_metrics: bool,
+
+    /// Whether to measure the words and characters of rustdoc comments.
+    doc_detail: bool,
+
+    /// Number of worker threads for directory analysis; `None` uses rayon's global pool.
+    #[cfg(feature = "cli")]
+    jobs: Option<NonZeroUsize>,
+
+    /// Whether analyzed files are accumulated as they finish rather than in path order.
+    #[cfg(feature = "cli")]
+    completion_order: bool,
+
+    /// Whether to resolve `include!`-family targets and count included Rust sources.
+    follow_includes: bool,
+
+    /// Pattern every test function name must match; `None` disables the audit.
+    test_name_pattern: Option<Regex>,
+
+    /// Whether to report files unreachable from every crate root in the analyzed directory.
+    #[cfg(feature = "cli")]
+    dead_files: bool,
+
+    /// Whether to count each crate's README and `doc/` markdown in a documentation roll-up.
+    #[cfg(feature = "cli")]
+    include_docs: bool,
+
+    /// Whether to count TOML, Markdown, YAML, and shell files in a language roll-up.
+    #[cfg(feature = "cli")]
+    all_languages: bool,
+
+    /// How directory traversal treats symbolic links.
+    #[cfg(feature = "cli")]
+    symlinks: SymlinkPolicy,
+
+    /// File name extensions that directory traversal analyzes as Rust sources.
+    #[cfg(feature = "cli")]
+    extensions: SourceExtensions,
+
+    /// Limits on the number and total size of discovered files.
+    #[cfg(feature = "cli")]
+    limits: DiscoveryLimits,
+
+    /// Random subset of the discovered files to analyze; `None` analyzes every file.
+    #[cfg(feature = "cli")]
+    sampling: Option<Arc<Sampling>>,
+
+    /// Whether files with invalid UTF-8 fail instead of being decoded lossily.
+    strict_utf8: bool,
+
+    /// Whether large files are memory-mapped instead of read into memory.
+    mmap: bool,
+
+    /// Whether per-file errors are kept off stderr.
+    #[cfg(feature = "cli")]
+    quiet_errors: bool,
+
+    /// Whether the progress bar may be shown when stdout is a terminal.
+    #[cfg(feature = "cli")]
+    progress: bool,
+
+    /// Heuristics identifying generated files; `None` counts them as ordinary code.
+    generated: Option<GeneratedRules>,
+
+    /// User-supplied attribute rules extending test, bench, and generated detection.
+    rules: Arc<ClassificationRules>,
+
+    /// Last-commit times used to bucket files by age; `None` disables bucketing.
+    file_ages: Option<Arc<FileAges>>,
+
+    /// Per-crate edition lookup shared by all worker threads.
+    editions: Arc<EditionResolver>,
+
+    /// Build script and procedural macro lookup shared by all worker threads.
+    tooling: Arc<ToolingResolver>,
+
+    /// Phase and per-file timings collected with `--timings`; `None` skips timing.
+    timings: Option<Arc<Timings>>,
+}
+
+impl AnalysisOptions {
+    /// Runs a closure, adding its duration to a phase when timings are collected.
+    ///
+    /// # Arguments
+    ///
+    /// * `phase` - Phase the closure belongs to
+    /// * `f` - The work to time
+    ///
+    /// # Returns
+    ///
+    /// The closure's result
+    fn timed<T>(&self, phase: TimingPhase, f: impl FnOnce() -> T) -> T {
+        let Some(timings) = &self.timings else {
+            return f();
+        };
+        let started = Instant::now();
+        let result = f();
+        timings.add(phase, started.elapsed());
+        result
+    }
+}
+
+/// Represents the input source for analysis (file or directory).
+///
+/// This enum encodes the invariant that exactly one input type is provided,
+/// which is enforced by clap's ArgGroup but represented as a proper type.
+#[derive(Debug, Clone)]
+pub enum InputSource {
+    /// Analyze a single Rust file.
+    File(PathBuf),
+
+    /// Analyze all Rust files in a directory recursively.
+    Directory(PathBuf),
+
+    /// Analyze the Rust files reachable through `mod` declarations from an entry point.
+    Entry(PathBuf),
+}
+
+/// Size measures offered by `--count` as alternatives to line counting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SizeMeasure {
+    /// Lexical tokens, with and without trivia (whitespace and comments).
+    Tokens,
+
+    /// Syntax tree nodes.
+    Nodes,
+
+    /// Logical statements: items, `let` bindings, expression statements, and block tail
+    /// expressions, independent of formatting.
+    Statements,
+}
+
+/// A public item of a crate's API surface, as compared by `api-diff` and measured by
+/// `--doc-detail`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct PublicItem {
+    /// Item name, qualified with enclosing inline modules and `impl` types (e.g., `parser::Parser::new`).
+    name: String,
+
+    /// Item kind keyword (`fn`, `struct`, `enum`, ...).
+    kind: &'static str,
+
+    /// Number of lines spanned by the item, excluding attributes and doc comments.
+    lines: usize,
+
+    /// One-based line on which the item starts, excluding attributes and doc comments.
+    line: usize,
+
+    /// Volume of the item's doc comments.
+    docs: DocVolume,
+}
+
+/// Returns the keyword describing a public API item, or `None` for other nodes.
+fn public_item_kind(node: &SyntaxNode) -> Option<&'static str> {
+    let kind = match ast::Item::cast(node.clone())? {
+        ast::Item::Fn(_) => "fn",
+        ast::Item::Struct(_) => "struct",
+        ast::Item::Enum(_) => "enum",
+        ast::Item::Union(_) => "union",
+        ast::Item::Trait(_) => "trait",
+        ast::Item::TypeAlias(_) => "type",
+        ast::Item::Const(_) => "const",
+        ast::Item::Static(_) => "static",
+        _ => return None,
+    };
+
+    let visibility = node.children().find_map(ast::Visibility::cast)?;
+    (visibility.syntax().text() == "pub").then_some(kind)
+}
+
+/// Collects the public items declared in a source file, skipping test sections.
+///
+/// Descends into inline modules and `impl` blocks, qualifying names with the module
+/// and implementing type so that methods remain distinguishable.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `line_index` - Line index over the same source content
+///
+/// # Returns
+///
+/// Public items in source order
+fn collect_public_items(root: &SyntaxNode, line_index: &LineIndex) -> Vec<PublicItem> {
+    fn visit(
+        node: &SyntaxNode,
+        scope: &[String],
+        line_index: &LineIndex,
+        items: &mut Vec<PublicItem>,
+    ) {
+        for child in node.children() {
+            if is_test_node(&child) {
+                continue;
+            }
+
+            let name = child
+                .children()
+                .find_map(ast::Name::cast)
+                .map(|name| name.text().to_string());
+
+            if let (Some(kind), Some(name)) = (public_item_kind(&child), &name)
+                && let Some((start, end)) = item_line_span(&child, line_index)
+            {
+                let mut qualified = scope.to_vec();
+                qualified.push(name.clone());
+                items.push(PublicItem {
+                    name: qualified.join("::"),
+                    kind,
+                    lines: end - start + 1,
+                    line: start + 1,
+                    docs: doc_comment_volume(&child),
+                });
+            }
+
+            let nested_scope = if let Some(imp) = ast::Impl::cast(child.clone()) {
+                imp.self_ty().map(|ty| ty.syntax().text().to_string())
+            } else if ast::Module::can_cast(child.kind()) {
+                name
+            } else {
+                None
+            };
+
+            match nested_scope {
+                Some(segment) => {
+                    let mut nested = scope.to_vec();
+                    nested.push(segment);
+                    visit(&child, &nested, line_index, items);
+                }
+                None if ast::Trait::can_cast(child.kind()) => {}
+                None => visit(&child, scope, line_index, items),
+            }
+        }
+    }
+
+    let mut items = Vec::new();
+    visit(root, &[], line_index, &mut items);
+    items
+}
+
+/// Mutually exclusive taxonomy for source code line classification.
+///
+/// Represents the fundamental categorization scheme applied during line-level analysis.
+/// Each line in a source file maps to exactly one variant, forming a complete partition
+/// of the source code space.
+///
+/// # Classification Priority
+///
+/// When lines contain multiple token types, classification follows this precedence:
+/// 1. Rustdoc (highest priority)
+/// 2. Comment
+/// 3. Code
+/// 4. Blank (lowest priority - default assumption)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum LineType {
+    /// Lines consisting exclusively of whitespace characters (spaces, tabs, newlines).
+    ///
+    /// Examples: empty lines, lines with only indentation
+    Blank,
+
+    /// Standard non-documentation comment lines.
+    ///
+    /// Includes `//` line comments and `/* */` block comments, excluding
+    /// documentation variants recognized by rustdoc.
+    Comment,
+
+    /// Documentation comment lines recognized by rustdoc.
+    ///
+    /// Comprises `///`, `//!`, `/**`, and `/*!` comment forms that generate
+    /// API documentation when processed by rustdoc.
+    Rustdoc,
+
+    /// Executable code lines containing declarations, expressions, or statements.
+    ///
+    /// Encompasses all Rust syntax elements beyond comments and whitespace,
+    /// including keywords, identifiers, operators, literals, and punctuation.
+    Code,
+}
+
+/// Runs the analysis pipeline against an [`AnalysisSink`].
+///
+/// Each analyzed file is handed to [`AnalysisSink::file`] in path order as soon as the
+/// files before it are done. Once the analysis succeeds, the sink receives the skipped
+/// files through [`AnalysisSink::skipped`], the orphans of an entry point through
+/// [`AnalysisSink::orphans`], and finally the summary through [`AnalysisSink::summary`].
+///
+/// # Arguments
+///
+/// * `source` - The file, directory, or entry point to analyze
+/// * `options` - Per-file analysis settings
+/// * `sink` - Consumer of the results
+///
+/// # Returns
+///
+/// The summary that was delivered to the sink
+///
+/// # Errors
+///
+/// Returns an error if the analysis fails or the sink rejects the skipped files, the
+/// orphans, or the summary
+#[cfg(feature = "cli")]
+pub fn analyze_into<S: AnalysisSink>(
+    source: &InputSource,
+    options: &AnalysisOptions,
+    sink: &mut S,
+) -> Result<Summary, RulocError> {
+    let mut accumulator = SinkAccumulator {
+        sink,
+        summary: Summary::default(),
+    };
+    let outcome = analyze_source(source, options, &mut accumulator)?;
+
+    let SinkAccumulator { sink, summary } = accumulator;
+    if !outcome.skipped.is_empty() {
+        sink.skipped(&outcome.skipped)?;
+    }
+    if !outcome.orphans.is_empty() {
+        sink.orphans(&outcome.orphans)?;
+    }
+    sink.summary(&summary)?;
+    Ok(summary)
+}
+
+/// Files discovered alongside the statistics while analyzing an input.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct InputOutcome {
+    /// Files unreachable from the analyzed crate roots, sorted by path.
+    orphans: Vec<String>,
+
+    /// Files excluded from the analysis, sorted by path.
+    skipped: Vec<SkippedFile>,
+
+    /// Markdown documentation per crate directory, collected with `--include-docs`.
+    markdown_docs: Option<BTreeMap<String, MarkdownDocs>>,
+
+    /// Line counts of non-Rust files per language, collected with `--all-languages`.
+    other_languages: Option<BTreeMap<String, LanguageLines>>,
+}
+
+/// Dispatches analysis of a file, directory, or entry point into an accumulator.
+///
+/// # Returns
+///
+/// The orphaned files discovered for entry-point and `--dead-files` inputs, the files
+/// skipped during directory or entry-point analysis, the per-crate markdown
+/// documentation of `--include-docs` directory inputs, and the non-Rust line counts of
+/// `--all-languages` directory inputs
+///
+/// # Errors
+///
+/// Returns an error if discovery, analysis, or accumulation fails
+#[cfg(feature = "cli")]
+fn analyze_source<A: StatsAccumulator>(
+    source: &InputSource,
+    options: &AnalysisOptions,
+    accumulator: &mut A,
+) -> Result<InputOutcome, RulocError> {
+    match source {
+        InputSource::File(file_path) => {
+            let started = Instant::now();
+            let stats = analyze_file(file_path, options)?;
+            if let Some(timings) = &options.timings {
+                timings.record_file(file_path, started.elapsed());
+            }
+            options.timed(TimingPhase::Aggregate, || accumulator.add_file(&stats))?;
+            Ok(InputOutcome::default())
+        }
+        InputSource::Directory(dir_path) => {
+            let skipped = analyze_directory(dir_path, options, accumulator)?;
+            let orphans = if options.dead_files {
+                find_dead_files(dir_path, options.symlinks)
+            } else {
+                Vec::new()
+            };
+            let markdown_docs = options
+                .include_docs
+                .then(|| count_markdown_docs(dir_path, options.symlinks));
+            let other_languages = options
+                .all_languages
+                .then(|| count_other_languages(dir_path, options.symlinks));
+            Ok(InputOutcome {
+                orphans,
+                skipped,
+                markdown_docs,
+                other_languages,
+            })
+        }
+        InputSource::Entry(entry) => {
+            let graph = options.timed(TimingPhase::Walk, || {
+                discover_module_graph(entry, options.symlinks)
+            })?;
+            let orphans = graph.orphan_paths();
+            let mut files = graph.files;
+            files.sort_by(|a, b| compare_paths(a, b));
+            let skipped = analyze_paths(files.into_iter(), entry, options, accumulator)?;
+            Ok(InputOutcome {
+                orphans,
+                skipped,
+                ..Default::default()
+            })
+        }
+    }
+}
+
+/// Performs AST-driven line-by-line classification of Rust source code.
+///
+/// Leverages the `ra_ap_syntax` parser to tokenize source content with full semantic awareness,
+/// correctly disambiguating comment-like patterns within string literals, raw strings, and
+/// character constants. Each line receives a deterministic classification based on its
+/// predominant token type.
+///
+/// # Algorithm
+///
+/// 1. Parse source into syntax tree via [`ParsedSource::parse`]
+/// 2. Build byte-offset-to-line-number mapping for O(log n) lookups
+/// 3. Traverse all tokens, classifying covered lines according to token kinds
+/// 4. Resolve conflicts (e.g., code + comment on same line) via precedence rules
+///
+/// # Classification Rules
+///
+/// - Lines with only whitespace tokens → `LineType::Blank`
+/// - Lines with `COMMENT` tokens matching `///|//!|/**|/*!` → `LineType::Rustdoc`
+/// - Lines with other `COMMENT` tokens → `LineType::Comment`
+/// - Lines with any non-whitespace, non-comment tokens → `LineType::Code`
+/// - Mixed lines prioritize Comment/Rustdoc over Code
+///
+/// # Arguments
+///
+/// * `content` - Complete source file content as UTF-8 string
+/// * `edition` - Rust edition the file is parsed with
+///
+/// # Returns
+///
+/// Vector of [`LineType`] classifications, indexed by zero-based line number
+///
+/// # Examples
+///
+/// ```ignore
+/// let code = "// comment\nfn main() {}\n";
+/// let types = analyze_lines(code, Edition::CURRENT);
+/// assert_eq!(types[0], LineType::Comment);
+/// assert_eq!(types[1], LineType::Code);
+/// ```
+#[cfg(all(test, feature = "cli"))]
+fn analyze_lines(content: &str, edition: Edition) -> Vec<LineType> {
+    ParsedSource::parse(content, edition).line_types
+}
+
+/// A source file parsed once, with the line classifications derived from its syntax tree.
+///
+/// Line typing, test-section detection, and the per-file metrics all share this single
+/// parse; parsing dominates the cost of analyzing a file, so it is never repeated.
+struct ParsedSource<'a> {
+    /// LF-normalized content the tree was parsed from.
+    content: Cow<'a, str>,
+
+    /// Syntax tree of the content, along with any syntax errors.
+    parse: Parse<SourceFile>,
+
+    /// Offset-to-line lookup for `content`.
+    line_index: LineIndex,
+
+    /// Blank, comment, rustdoc, or code classification of every line.
+    line_types: Vec<LineType>,
+
+    /// Comment tokens in source order, gathered while typing the lines so that directives
+    /// and markers are found without walking the tree again.
+    comments: Vec<SyntaxToken>,
+
+    /// Whether each line belongs to a test section.
+    is_test_line: Vec<bool>,
+
+    /// Whether each line is excluded from line counts by `// ruloc:ignore-start`.
+    is_ignored_line: Vec<bool>,
+}
+
+impl<'a> ParsedSource<'a> {
+    /// Parses source content and classifies its lines in a single pass.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - Source content with any line endings
+    /// * `edition` - Rust edition the file is parsed with
+    ///
+    /// # Returns
+    ///
+    /// The syntax tree together with the line types, test lines, and ignored lines derived
+    /// from it, with `// ruloc:` directives applied
+    fn parse(content: &'a str, edition: Edition) -> Self {
+        let content = normalize_line_endings(content);
+        let parse = SourceFile::parse(&content, edition);
+        let root = parse.syntax_node();
+        let line_index = LineIndex::new(&content);
+        let (line_types, comments) = type_lines(&root, &line_index);
+        let mut is_test_line = mark_test_lines(&root, &content);
+        let is_ignored_line = apply_directives(&comments, &line_index, &mut is_test_line);
+
+        Self {
+            content,
+            parse,
+            line_index,
+            line_types,
+            comments,
+            is_test_line,
+            is_ignored_line,
+        }
+    }
+}
+
+/// Classifies every line by the tokens of a parsed file (see [`ParsedSource::parse`]).
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed file
+/// * `line_index` - Offset-to-line lookup for the file's content
+///
+/// # Returns
+///
+/// Vector of [`LineType`] classifications, indexed by zero-based line number, and the
+/// comment tokens of the file in source order
+fn type_lines(root: &SyntaxNode, line_index: &LineIndex) -> (Vec<LineType>, Vec<SyntaxToken>) {
+    let total_lines = line_index.total_lines;
+    if total_lines == 0 {
+        return (Vec::new(), Vec::new());
+    }
+
+    // Initialize all lines as blank
+    let mut line_types = vec![LineType::Blank; total_lines];
+    let mut comments = Vec::new();
+
+    // Collect all tokens and classify lines based on them
+    for token in root
+        .descendants_with_tokens()
+        .filter_map(|e| e.into_token())
+    {
+        let (start_line, end_line) = line_index.line_span(token.text_range());
+
+        // Classify based on token kind
+        match token.kind() {
+            SyntaxKind::COMMENT => {
+                // Check if this is a rustdoc comment
+                let text = token.text();
+                let is_rustdoc = text.starts_with("///")
+                    || text.starts_with("//!")
+                    || text.starts_with("/**")
+                    || text.starts_with("/*!");
+
+                let line_type = if is_rustdoc {
+                    LineType::Rustdoc
+                } else {
+                    LineType::Comment
+                };
+
+                // Mark all lines covered by this comment token
+                line_types[start_line..=end_line.min(total_lines - 1)]
+                    .iter_mut()
+                    .for_each(|t| *t = line_type);
+                comments.push(token);
+            }
+            SyntaxKind::WHITESPACE => {
+                // Whitespace doesn't change classification
+            }
+            _ => {
+                // Any other token (keywords, identifiers, literals, etc.) is Code
+                // But only override if the line isn't already marked as Comment or Rustdoc
+                line_types[start_line..=end_line.min(total_lines - 1)]
+                    .iter_mut()
+                    .filter(|t| **t != LineType::Comment && **t != LineType::Rustdoc)
+                    .for_each(|t| *t = LineType::Code);
+            }
+        }
+    }
+
+    (line_types, comments)
+}
+
+/// Computes line statistics from classified line types by counting occurrences.
+///
+/// # Arguments
+///
+/// * `line_types` - Slice of classified line types to count
+/// * `total_lines` - Total number of lines (used for the `all_lines` field)
+///
+/// # Returns
+///
+/// A `LineStats` instance with counts for each line type
+fn compute_line_stats(line_types: &[LineType], total_lines: usize) -> LineStats {
+    let blank_lines = line_types.iter().filter(|&&t| t == LineType::Blank).count();
+    let comment_lines = line_types
+        .iter()
+        .filter(|&&t| t == LineType::Comment)
+        .count();
+    let rustdoc_lines = line_types
+        .iter()
+        .filter(|&&t| t == LineType::Rustdoc)
+        .count();
+    let code_lines = line_types.iter().filter(|&&t| t == LineType::Code).count();
+
+    LineStats {
+        all_lines: total_lines,
+        blank_lines,
+        comment_lines,
+        rustdoc_lines,
+        code_lines,
+    }
+}
+
+/// Represents a code section with its classification and line range.
+#[derive(Debug, Clone)]
+struct CodeSection {
+    /// Starting line number (0-indexed).
+    start_line: usize,
+    /// Ending line number (0-indexed, inclusive).
+    end_line: usize,
+}
+
+/// Determines if a syntax node represents a test item by checking for test attributes.
+///
+/// Identifies functions carrying a test attribute (see [`is_test_attribute`]), and items
+/// whose `#[cfg(...)]` predicate only holds in test builds, such as `cfg(test)` or
+/// `cfg(all(test, unix))`. Any item counts, so test helpers guarded by `#[cfg(test)]` in
+/// production modules (functions, impl blocks, types, constants, imports) are test code
+/// just like a `#[cfg(test)]` module. Predicates that also hold without `test`, such as
+/// `cfg(not(test))` or `cfg(any(test, feature = "testing"))`, gate production code.
+///
+/// # Arguments
+///
+/// * `node` - The syntax tree node to examine
+///
+/// # Returns
+///
+/// `true` if the node represents a test function or test-only item, `false` otherwise
+fn is_test_node(node: &SyntaxNode) -> bool {
+    // Check if this is a function with #[test] or a similar test attribute
+    if let Some(func) = ast::Fn::cast(node.clone())
+        && func.attrs().any(|attr| is_test_attribute(&attr))
+    {
+        return true;
+    }
+
+    // Check if this is any item with a test-only #[cfg(...)] attribute
+    ast::Item::cast(node.clone()).is_some_and(|item| has_test_cfg(item.attrs()))
+}
+
+/// Checks whether a file name follows a test file convention, `*_test.rs` or `test_*.rs`.
+///
+/// Used with `--test-file-names`, which counts such files as test code entirely.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file
+///
+/// # Returns
+///
+/// `true` if the file name, up to its first `.`, ends with `_test` or starts with `test_`
+fn is_test_file_name(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let stem = name.split('.').next().unwrap_or(name);
+    stem.ends_with("_test") || stem.starts_with("test_")
+}
+
+/// Attributes that make a function a test, matched against the last segment of the
+/// attribute path: `#[test]` and runtime wrappers such as `#[tokio::test]`, and attribute
+/// macros that expand to tests such as `#[test_case(...)]` or `#[rstest::rstest]`.
+const TEST_ATTRIBUTES: [&str; 4] = ["test", "test_case", "test_matrix", "rstest"];
+
+/// Checks whether an attribute marks a function as a test.
+///
+/// Resolution policy: an attribute is a test attribute when the last segment of its path
+/// is one of [`TEST_ATTRIBUTES`], wherever it appears among the function's attributes.
+/// A `#[cfg_attr(predicate, ...)]` counts when any attribute it applies (including
+/// through nested `cfg_attr`s) is a test attribute, whatever the predicate: such a
+/// function exists to be a test even in builds where it is not one. Other attributes
+/// can be mapped to test code with `--rules`.
+///
+/// # Arguments
+///
+/// * `attr` - The attribute to examine
+///
+/// # Returns
+///
+/// `true` if the attribute, possibly conditionally, makes the function a test
+fn is_test_attribute(attr: &ast::Attr) -> bool {
+    let Some(path) = attr.path() else {
+        return false;
+    };
+    let path = path.to_string();
+    if path == "cfg_attr" {
+        return attr
+            .token_tree()
+            .is_some_and(|token_tree| cfg_attr_applies_test(&token_tree));
+    }
+    is_test_attribute_path(&path)
+}
+
+/// Checks whether an attribute path names a test attribute.
+///
+/// # Arguments
+///
+/// * `path` - Attribute path, such as `tokio::test`
+///
+/// # Returns
+///
+/// `true` if the last segment of the path is one of [`TEST_ATTRIBUTES`]
+fn is_test_attribute_path(path: &str) -> bool {
+    let name = path.rsplit("::").next().unwrap_or(path).trim();
+    TEST_ATTRIBUTES.contains(&name)
+}
+
+/// Checks whether a `cfg_attr` applies a test attribute when its predicate holds.
+///
+/// # Arguments
+///
+/// * `token_tree` - The parenthesized arguments of a `#[cfg_attr(...)]` attribute
+///
+/// # Returns
+///
+/// `true` if one of the attributes following the predicate is a test attribute
+fn cfg_attr_applies_test(token_tree: &ast::TokenTree) -> bool {
+    // Split the arguments at top-level commas; nested token trees are single elements
+    let mut arguments = vec![Vec::new()];
+    for element in token_tree.syntax().children_with_tokens() {
+        match element.kind() {
+            SyntaxKind::L_PAREN | SyntaxKind::R_PAREN => {}
+            SyntaxKind::COMMA => arguments.push(Vec::new()),
+            kind if kind.is_trivia() => {}
+            _ => arguments.last_mut().unwrap().push(element),
+        }
+    }
+
+    arguments.iter().skip(1).any(|attribute| {
+        let path: String = attribute
+            .iter()
+            .map_while(|element| {
+                element
+                    .as_token()
+                    .filter(|token| {
+                        matches!(
+                            token.kind(),
+                            SyntaxKind::IDENT | SyntaxKind::COLON | SyntaxKind::COLON2
+                        )
+                    })
+                    .map(|token| token.text().to_string())
+            })
+            .collect();
+        if path == "cfg_attr" {
+            return attribute
+                .iter()
+                .find_map(|element| element.as_node().cloned().and_then(ast::TokenTree::cast))
+                .is_some_and(|nested| cfg_attr_applies_test(&nested));
+        }
+        !path.is_empty() && is_test_attribute_path(&path)
+    })
+}
+
+/// Checks whether any `#[cfg(...)]` attribute restricts an item to test builds.
+///
+/// # Arguments
+///
+/// * `attrs` - Attributes of the item
+///
+/// # Returns
+///
+/// `true` if a `cfg` predicate cannot hold unless `test` is set
+fn has_test_cfg(mut attrs: impl Iterator<Item = ast::Attr>) -> bool {
+    attrs.any(|attr| {
+        attr.path().is_some_and(|path| path.to_string() == "cfg")
+            && attr
+                .token_tree()
+                .and_then(|token_tree| CfgPredicate::parse(&token_tree))
+                .is_some_and(|predicate| predicate.requires_test())
+    })
+}
+
+/// A configuration predicate, as written in `#[cfg(...)]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CfgPredicate {
+    /// A bare option such as `test` or `unix`, or a key-value option such as
+    /// `feature = "serde"`.
+    Option {
+        /// Name of the option.
+        name: String,
+
+        /// Quoted value of a key-value option.
+        value: Option<String>,
+    },
+
+    /// `all(...)`: holds when every predicate holds; `all()` and `true` always hold.
+    All(Vec<CfgPredicate>),
+
+    /// `any(...)`: holds when some predicate holds; `any()` and `false` never hold.
+    Any(Vec<CfgPredicate>),
+
+    /// `not(...)`: holds when the predicate does not.
+    Not(Box<CfgPredicate>),
+}
+
+impl CfgPredicate {
+    /// Parses the predicate of a `cfg` attribute.
+    ///
+    /// # Arguments
+    ///
+    /// * `token_tree` - The parenthesized predicate of a `#[cfg(...)]` attribute
+    ///
+    /// # Returns
+    ///
+    /// The predicate, or `None` if it is malformed
+    fn parse(token_tree: &ast::TokenTree) -> Option<Self> {
+        let tokens: Vec<SyntaxToken> = token_tree
+            .syntax()
+            .descendants_with_tokens()
+            .filter_map(|element| element.into_token())
+            .filter(|token| !token.kind().is_trivia())
+            .collect();
+        let [SyntaxKind::L_PAREN, .., SyntaxKind::R_PAREN] = tokens
+            .iter()
+            .map(SyntaxToken::kind)
+            .collect::<Vec<_>>()
+            .as_slice()
+        else {
+            return None;
+        };
+
+        let inner = &tokens[1..tokens.len() - 1];
+        let mut position = 0;
+        let predicate = Self::parse_at(inner, &mut position)?;
+        if inner.get(position).map(SyntaxToken::kind) == Some(SyntaxKind::COMMA) {
+            position += 1;
+        }
+        (position == inner.len()).then_some(predicate)
+    }
+
+    /// Parses one predicate starting at a position, advancing past it.
+    ///
+    /// # Arguments
+    ///
+    /// * `tokens` - Non-trivia tokens of the predicate
+    /// * `position` - Index of the first token, updated to the token after the predicate
+    ///
+    /// # Returns
+    ///
+    /// The predicate, or `None` if it is malformed
+    fn parse_at(tokens: &[SyntaxToken], position: &mut usize) -> Option<Self> {
+        let token = tokens.get(*position)?;
+        *position += 1;
+        match token.kind() {
+            SyntaxKind::TRUE_KW => return Some(CfgPredicate::All(Vec::new())),
+            SyntaxKind::FALSE_KW => return Some(CfgPredicate::Any(Vec::new())),
+            SyntaxKind::IDENT => {}
+            _ => return None,
+        }
+        let name = token.text().to_string();
+
+        match tokens.get(*position).map(SyntaxToken::kind) {
+            Some(SyntaxKind::EQ) => {
+                let value = tokens.get(*position + 1)?;
+                if value.kind() != SyntaxKind::STRING {
+                    return None;
+                }
+                *position += 2;
+                Some(CfgPredicate::Option {
+                    name,
+                    value: Some(value.text().to_string()),
+                })
+            }
+            Some(SyntaxKind::L_PAREN) => {
+                *position += 1;
+                let mut predicates = Vec::new();
+                let next = |position: usize| tokens.get(position).map(SyntaxToken::kind);
+                while next(*position) != Some(SyntaxKind::R_PAREN) {
+                    if !predicates.is_empty() {
+                        // Predicates are separated by commas, with an optional trailing one
+                        if next(*position) != Some(SyntaxKind::COMMA) {
+                            return None;
+                        }
+                        *position += 1;
+                        if next(*position) == Some(SyntaxKind::R_PAREN) {
+                            break;
+                        }
+                    }
+                    predicates.push(Self::parse_at(tokens, position)?);
+                }
+                *position += 1;
+
+                match name.as_str() {
+                    "all" => Some(CfgPredicate::All(predicates)),
+                    "any" => Some(CfgPredicate::Any(predicates)),
+                    "not" if predicates.len() == 1 => {
+                        Some(CfgPredicate::Not(Box::new(predicates.pop()?)))
+                    }
+                    _ => None,
+                }
+            }
+            _ => Some(CfgPredicate::Option { name, value: None }),
+        }
+    }
+
+    /// Whether the predicate can only hold in test builds.
+    ///
+    /// The predicate is evaluated with `test` unset and every other option unknown, using
+    /// three-valued logic; code it gates is test code when the result is definitely false.
+    fn requires_test(&self) -> bool {
+        self.evaluate_without_test() == Some(false)
+    }
+
+    /// Evaluates the predicate with `test` unset and every other option unknown.
+    ///
+    /// # Returns
+    ///
+    /// `Some` with the value when it does not depend on unknown options, `None` otherwise
+    fn evaluate_without_test(&self) -> Option<bool> {
+        match self {
+            CfgPredicate::Option { name, value: None } if name == "test" => Some(false),
+            CfgPredicate::Option { .. } => None,
+            CfgPredicate::Not(predicate) => predicate.evaluate_without_test().map(|value| !value),
+            CfgPredicate::All(predicates) => {
+                let values: Vec<_> = predicates.iter().map(Self::evaluate_without_test).collect();
+                if values.contains(&Some(false)) {
+                    Some(false)
+                } else if values.iter().all(|value| *value == Some(true)) {
+                    Some(true)
+                } else {
+                    None
+                }
+            }
+            CfgPredicate::Any(predicates) => {
+                let values: Vec<_> = predicates.iter().map(Self::evaluate_without_test).collect();
+                if values.contains(&Some(true)) {
+                    Some(true)
+                } else if values.iter().all(|value| *value == Some(false)) {
+                    Some(false)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Determines if a syntax node is benchmark code.
+///
+/// Matches functions annotated with `#[bench]` and criterion's `criterion_group!` and
+/// `criterion_main!` harness invocations.
+///
+/// # Arguments
+///
+/// * `node` - The syntax tree node to examine
+///
+/// # Returns
+///
+/// `true` if the node is a benchmark function or harness macro call
+fn is_bench_node(node: &SyntaxNode) -> bool {
+    if let Some(func) = ast::Fn::cast(node.clone()) {
+        return func
+            .attrs()
+            .filter_map(|attr| attr.path())
+            .any(|path| path.to_string() == "bench");
+    }
+
+    ast::MacroCall::cast(node.clone())
+        .and_then(|call| call.path())
+        .and_then(|path| path.segment())
+        .and_then(|segment| segment.name_ref())
+        .is_some_and(|name| matches!(name.text().as_str(), "criterion_group" | "criterion_main"))
+}
+
+/// Marks the lines of a file that belong to benchmark code.
+///
+/// Every line of a file below a `benches` directory is benchmark code; elsewhere only the
+/// nodes recognized by [`is_bench_node`] are, wherever they are nested.
+///
+/// # Arguments
+///
+/// * `path` - Path of the file
+/// * `root` - Root of the file's syntax tree
+/// * `content` - The complete source file content
+///
+/// # Returns
+///
+/// One flag per line, `true` for benchmark lines
+fn classify_bench_lines(path: &Path, root: &SyntaxNode, content: &str) -> Vec<bool> {
+    let total_lines = content.lines().count();
+    let in_benches_dir = path
+        .parent()
+        .is_some_and(|dir| dir.components().any(|c| c.as_os_str() == "benches"));
+    if in_benches_dir {
+        return vec![true; total_lines];
+    }
+
+    mark_node_lines(root.descendants().filter(is_bench_node), content)
+}
+
+/// Marks the lines spanned by a set of syntax nodes.
+///
+/// # Arguments
+///
+/// * `nodes` - Nodes whose lines are marked
+/// * `content` - The complete source file content
+///
+/// # Returns
+///
+/// One flag per line, `true` for lines overlapping any of the nodes
+fn mark_node_lines(nodes: impl Iterator<Item = SyntaxNode>, content: &str) -> Vec<bool> {
+    let total_lines = content.lines().count();
+    let mut marked = vec![false; total_lines];
+    for node in nodes {
+        let start_offset: usize = node.text_range().start().into();
+        let end_offset: usize = node.text_range().end().into();
+        let start_line = content[..start_offset].lines().count().saturating_sub(1);
+        let end_line = content[..end_offset].lines().count().saturating_sub(1);
+        if total_lines > 0 {
+            marked[start_line.min(total_lines - 1)..=end_line.min(total_lines - 1)].fill(true);
+        }
+    }
+
+    marked
+}
+
+/// Recursively finds test sections in the syntax tree by traversing AST nodes.
+///
+/// When a test node is found, adds its line range to the sections vector and
+/// stops recursing into that subtree (since nested tests are part of the parent).
+///
+/// # Arguments
+///
+/// * `node` - The current syntax tree node being examined
+/// * `sections` - Mutable vector to collect discovered test sections
+/// * `content` - The complete source file content (used for line offset calculation)
+fn find_test_sections(node: &SyntaxNode, sections: &mut Vec<CodeSection>, content: &str) {
+    if is_test_node(node) {
+        let text_range = node.text_range();
+        let start_offset = text_range.start().into();
+        let end_offset = text_range.end().into();
+
+        let start_line = content[..start_offset].lines().count().saturating_sub(1);
+        let end_line = content[..end_offset].lines().count().saturating_sub(1);
+
+        trace!("Found test section: lines {}-{}", start_line, end_line);
+
+        sections.push(CodeSection {
+            start_line,
+            end_line,
+        });
+        return; // Don't recurse into test sections
+    }
+
+    for child in node.children() {
+        find_test_sections(&child, sections, content);
+    }
+}
+
+/// Determines which lines belong to production vs test code using AST analysis.
+///
+/// Parses the source code to build a syntax tree, identifies all test sections,
+/// and marks their corresponding line ranges as test code.
+///
+/// # Arguments
+///
+/// * `content` - The source code content to classify
+/// * `edition` - Rust edition the file is parsed with
+///
+/// # Returns
+///
+/// A vector of boolean values, one per line, where `true` indicates test code
+/// and `false` indicates production code
+#[cfg(all(test, feature = "cli"))]
+fn classify_lines(content: &str, edition: Edition) -> Vec<bool> {
+    ParsedSource::parse(content, edition).is_test_line
+}
+
+/// Marks the lines of a parsed file that belong to test sections.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed file
+/// * `content` - LF-normalized content the file was parsed from
+///
+/// # Returns
+///
+/// A vector of boolean values, one per line, where `true` indicates test code
+fn mark_test_lines(root: &SyntaxNode, content: &str) -> Vec<bool> {
+    let mut test_sections = Vec::new();
+    find_test_sections(root, &mut test_sections, content);
+
+    let total_lines = content.lines().count();
+    let mut is_test_line = vec![false; total_lines];
+
+    for section in test_sections {
+        let end = section.end_line.min(total_lines - 1);
+        is_test_line[section.start_line..=end].fill(true);
+    }
+
+    debug!(
+        "Classified {} lines: {} test, {} production",
+        total_lines,
+        is_test_line.iter().filter(|&&x| x).count(),
+        is_test_line.iter().filter(|&&x| !x).count()
+    );
+
+    is_test_line
+}
+
+/// Applies `// ruloc:` override comments to the classification of a parsed file.
+///
+/// `// ruloc:ignore-start` and `// ruloc:ignore-end` exclude the lines between them,
+/// including the directives themselves, from every line count; a start without an end
+/// extends to the end of the file. `// ruloc:test` marks the item that follows it as test
+/// code, or only its own line when it trails code. Only plain `//` comments are
+/// directives, so documentation that mentions them is unaffected.
+///
+/// # Arguments
+///
+/// * `comments` - Comment tokens of the parsed file, in source order
+/// * `line_index` - Offset-to-line lookup for the file's content
+/// * `is_test_line` - Test lines detected from the syntax, updated in place
+///
+/// # Returns
+///
+/// A vector of boolean values, one per line, where `true` indicates an ignored line
+fn apply_directives(
+    comments: &[SyntaxToken],
+    line_index: &LineIndex,
+    is_test_line: &mut [bool],
+) -> Vec<bool> {
+    let total_lines = is_test_line.len();
+    let mut is_ignored_line = vec![false; total_lines];
+    let mut ignore_start = None;
+
+    for token in comments {
+        let Some(directive) = token
+            .text()
+            .strip_prefix("//")
+            .filter(|rest| !rest.starts_with(['/', '!']))
+            .and_then(|rest| rest.trim().strip_prefix(DIRECTIVE_PREFIX))
+        else {
+            continue;
+        };
+        let (line, _) = line_index.line_span(token.text_range());
+        if line >= total_lines {
+            continue;
+        }
+
+        match directive.trim() {
+            "ignore-start" => {
+                ignore_start.get_or_insert(line);
+            }
+            "ignore-end" => {
+                if let Some(start) = ignore_start.take() {
+                    is_ignored_line[start..=line].fill(true);
+                }
+            }
+            "test" => {
+                let end = directive_target_end(token, line_index).min(total_lines - 1);
+                is_test_line[line..=end].fill(true);
+            }
+            other => debug!(
+                "Ignoring unknown directive '{}{}' on line {}",
+                DIRECTIVE_PREFIX,
+                other,
+                line + 1
+            ),
+        }
+    }
+    if let Some(start) = ignore_start {
+        is_ignored_line[start..].fill(true);
+    }
+
+    is_ignored_line
+}
+
+/// Finds the last line that a `// ruloc:test` directive applies to.
+///
+/// # Arguments
+///
+/// * `comment` - The directive's comment token
+/// * `line_index` - Offset-to-line lookup for the file's content
+///
+/// # Returns
+///
+/// The zero-based last line of the item following the directive, or the directive's own
+/// line when it trails code or nothing follows it
+fn directive_target_end(comment: &SyntaxToken, line_index: &LineIndex) -> usize {
+    let (line, _) = line_index.line_span(comment.text_range());
+    let trails_code = std::iter::successors(comment.prev_token(), |prev| prev.prev_token())
+        .take_while(|prev| !(prev.kind() == SyntaxKind::WHITESPACE && prev.text().contains('\n')))
+        .any(|prev| !prev.kind().is_trivia());
+    if trails_code {
+        return line;
+    }
+
+    // Comments directly above an item are part of its node; otherwise the item follows
+    let parent = comment.parent();
+    let target = match parent {
+        Some(parent)
+            if parent.kind() != SyntaxKind::SOURCE_FILE
+                && parent.text_range().start() == comment.text_range().start() =>
+        {
+            Some(parent)
+        }
+        _ => comment
+            .siblings_with_tokens(Direction::Next)
+            .skip(1)
+            .find_map(NodeOrToken::into_node),
+    };
+    target.map_or(line, |node| line_index.line_span(node.text_range()).1)
+}
+
+/// Determines whether an item is public and, if so, whether it carries documentation.
+///
+/// Only unrestricted `pub` visibility qualifies; `pub(crate)`, `pub(super)`, and
+/// `pub(in path)` items are not part of the exported API and are ignored. Documentation
+/// may take the form of rustdoc comments or explicit `#[doc = "..."]` attributes.
+///
+/// # Arguments
+///
+/// * `item` - The AST item to inspect
+///
+/// # Returns
+///
+/// `Some(true)` for documented public items, `Some(false)` for undocumented public items,
+/// and `None` for items that are not public
+fn public_item_documentation<N: ast::HasVisibility + ast::HasDocComments>(
+    item: &N,
+) -> Option<bool> {
+    let visibility = item.visibility()?;
+    if visibility.syntax().text() != "pub" {
+        return None;
+    }
+
+    let has_doc_comment = item.doc_comments().next().is_some();
+    let has_doc_attr = item
+        .attrs()
+        .any(|attr| attr.path().is_some_and(|path| path.to_string() == "doc"));
+
+    Some(has_doc_comment || has_doc_attr)
+}
+
+/// Recursively tallies declared items by category and visibility.
+///
+/// Descends into inline modules and inherent `impl` blocks; trait definitions, trait
+/// `impl` blocks, and test sections are skipped entirely.
+///
+/// # Arguments
+///
+/// * `node` - The current syntax tree node being examined
+/// * `items` - Mutable item counters updated in place
+fn collect_item_visibility(node: &SyntaxNode, items: &mut ItemVisibility) {
+    for child in node.children() {
+        if is_test_node(&child) {
+            continue;
+        }
+
+        let Some(item) = ast::Item::cast(child.clone()) else {
+            continue;
+        };
+        let public = child
+            .children()
+            .find_map(ast::Visibility::cast)
+            .is_some_and(|visibility| visibility.syntax().text() == "pub");
+        let counts = if public {
+            &mut items.public
+        } else {
+            &mut items.private
+        };
+
+        match item {
+            ast::Item::Fn(_) => counts.functions += 1,
+            ast::Item::Struct(_)
+            | ast::Item::Enum(_)
+            | ast::Item::Union(_)
+            | ast::Item::TypeAlias(_) => counts.types += 1,
+            ast::Item::Trait(_) => counts.traits += 1,
+            ast::Item::Const(_) | ast::Item::Static(_) => counts.consts += 1,
+            ast::Item::Module(module) => {
+                if let Some(item_list) = module.item_list() {
+                    collect_item_visibility(item_list.syntax(), items);
+                }
+            }
+            ast::Item::Impl(imp) if imp.trait_().is_none() => {
+                if let Some(assoc_items) = imp.assoc_item_list() {
+                    collect_item_visibility(assoc_items.syntax(), items);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Computes the public and private item counts of a parsed source file.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+///
+/// # Returns
+///
+/// Item counts split by visibility
+fn compute_item_visibility(root: &SyntaxNode) -> ItemVisibility {
+    let mut items = ItemVisibility::default();
+    collect_item_visibility(root, &mut items);
+    items
+}
+
+/// Recursively tallies public items and their documentation status.
+///
+/// Inspects functions, structs, enums, and traits, descending into nested modules and
+/// `impl` blocks so that public methods are included. Test sections are skipped entirely.
+///
+/// # Arguments
+///
+/// * `node` - The current syntax tree node being examined
+/// * `coverage` - Mutable coverage counters updated in place
+fn collect_doc_coverage(node: &SyntaxNode, coverage: &mut DocCoverage) {
+    if is_test_node(node) {
+        return;
+    }
+
+    let documented = ast::Fn::cast(node.clone())
+        .and_then(|item| public_item_documentation(&item))
+        .or_else(|| {
+            ast::Struct::cast(node.clone()).and_then(|item| public_item_documentation(&item))
+        })
+        .or_else(|| ast::Enum::cast(node.clone()).and_then(|item| public_item_documentation(&item)))
+        .or_else(|| {
+            ast::Trait::cast(node.clone()).and_then(|item| public_item_documentation(&item))
+        });
+
+    if let Some(documented) = documented {
+        coverage.public_items += 1;
+        if documented {
+            coverage.documented_items += 1;
+        }
+    }
+
+    for child in node.children() {
+        collect_doc_coverage(&child, coverage);
+    }
+}
+
+/// Measures the doc comments attached directly to a node, such as an item's `///` lines.
+fn doc_comment_volume(node: &SyntaxNode) -> DocVolume {
+    let text: Vec<String> = node
+        .children_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter_map(ast::Comment::cast)
+        .filter_map(|comment| comment.doc_comment().map(str::to_owned))
+        .collect();
+    DocVolume::of(&text.join("\n"))
+}
+
+/// Measures the rustdoc comments of a file and of each of its public items.
+///
+/// # Arguments
+///
+/// * `root` - The root node of a parsed source file
+/// * `line_index` - Line index over the same source content
+///
+/// # Returns
+///
+/// The volume of every doc comment in the file, including module-level `//!` docs, and
+/// the volume of each public item outside test code
+fn compute_doc_detail(root: &SyntaxNode, line_index: &LineIndex) -> DocDetail {
+    // Doc comments sharing a parent node document the same item and are measured together
+    let mut volume = DocVolume::default();
+    let mut block: Option<(SyntaxNode, Vec<String>)> = None;
+    for comment in root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter_map(ast::Comment::cast)
+    {
+        let Some(text) = comment.doc_comment() else {
+            continue;
+        };
+        let parent = comment.syntax().parent();
+        match (&mut block, parent) {
+            (Some((node, lines)), Some(parent)) if *node == parent => lines.push(text.to_owned()),
+            (_, parent) => {
+                if let Some((_, lines)) = block.take() {
+                    volume.add(&DocVolume::of(&lines.join("\n")));
+                }
+                block = parent.map(|parent| (parent, vec![text.to_owned()]));
+            }
+        }
+    }
+    if let Some((_, lines)) = block {
+        volume.add(&DocVolume::of(&lines.join("\n")));
+    }
+
+    let items = collect_public_items(root, line_index)
+        .into_iter()
+        .map(|item| ItemDocVolume {
+            name: item.name,
+            kind: item.kind.to_string(),
+            line: item.line,
+            volume: item.docs,
+        })
+        .collect();
+    DocDetail { volume, items }
+}
+
+/// Computes documentation coverage for all public items in a syntax tree.
+///
+/// # Arguments
+///
+/// * `root` - The root node of a parsed source file
+///
+/// # Returns
+///
+/// A [`DocCoverage`] instance tallying public and documented items outside test code
+fn compute_doc_coverage(root: &SyntaxNode) -> DocCoverage {
+    let mut coverage = DocCoverage::default();
+    collect_doc_coverage(root, &mut coverage);
+    coverage
+}
+
+/// Byte-offset-to-line-number index over a source file.
+///
+/// Records the starting offset of every line once, after which any offset can be mapped
+/// to its zero-based line number in O(log n) via binary search.
+struct LineIndex {
+    /// Byte offsets at which each line begins, in ascending order.
+    line_starts: Vec<usize>,
+
+    /// Number of lines in the indexed content, as reported by `str::lines`.
+    total_lines: usize,
+}
+
+impl LineIndex {
+    /// Builds the index for the given content.
+    ///
+    /// Lines start after every `\n`, so both LF and CRLF terminators are handled: the
+    /// `\r` of a CRLF pair belongs to the line it terminates, matching `str::lines`.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - Complete source file content
+    ///
+    /// # Returns
+    ///
+    /// A `LineIndex` covering every line of `content`
+    fn new(content: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(content.match_indices('\n').map(|(pos, _)| pos + 1))
+            .collect();
+
+        Self {
+            line_starts,
+            total_lines: content.lines().count(),
+        }
+    }
+
+    /// Maps a byte offset to its zero-based line number.
+    ///
+    /// Offsets beyond the last line are clamped to the last line.
+    ///
+    /// # Arguments
+    ///
+    /// * `offset` - Byte offset within the indexed content
+    ///
+    /// # Returns
+    ///
+    /// The zero-based line containing `offset`
+    fn line_of(&self, offset: usize) -> usize {
+        self.line_starts
+            .binary_search(&offset)
+            .unwrap_or_else(|insert_pos| insert_pos.saturating_sub(1))
+            .min(self.total_lines.saturating_sub(1))
+    }
+
+    /// Maps a syntax element's text range to the inclusive range of lines it spans.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - Text range of a syntax node or token
+    ///
+    /// # Returns
+    ///
+    /// Inclusive `(start_line, end_line)` pair of zero-based line numbers
+    fn line_span(&self, range: ra_ap_syntax::TextRange) -> (usize, usize) {
+        let start: usize = range.start().into();
+        let end: usize = range.end().into();
+        (
+            self.line_of(start),
+            self.line_of(end.saturating_sub(1).max(start)),
+        )
+    }
+}
+
+/// Computes code line counts attributed to macro definitions and invocations.
+///
+/// Walks the syntax tree marking the lines covered by `macro_rules!`/`macro` definitions
+/// and by the continuation lines of macro invocation token trees, then counts the marked
+/// lines that are classified as code. Each line is counted at most once per category.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `line_index` - Line index over the same source content
+/// * `line_types` - Per-line classifications produced by [`type_lines`]
+///
+/// # Returns
+///
+/// A [`MacroLines`] instance with definition and invocation line counts
+fn compute_macro_lines(
+    root: &SyntaxNode,
+    line_index: &LineIndex,
+    line_types: &[LineType],
+) -> MacroLines {
+    let mut in_definition = vec![false; line_types.len()];
+    let mut in_invocation = vec![false; line_types.len()];
+
+    for node in root.descendants() {
+        match node.kind() {
+            SyntaxKind::MACRO_RULES | SyntaxKind::MACRO_DEF => {
+                let (start, end) = line_index.line_span(node.text_range());
+                if let Some(lines) = in_definition.get_mut(start..=end) {
+                    lines.fill(true);
+                }
+            }
+            SyntaxKind::MACRO_CALL => {
+                let Some(token_tree) =
+                    ast::MacroCall::cast(node.clone()).and_then(|call| call.token_tree())
+                else {
+                    continue;
+                };
+                let (first, _) = line_index.line_span(node.text_range());
+                let (_, end) = line_index.line_span(token_tree.syntax().text_range());
+                if let Some(lines) = in_invocation.get_mut(first + 1..=end) {
+                    lines.fill(true);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let count_code = |mask: &[bool]| {
+        mask.iter()
+            .zip(line_types)
+            .filter(|&(&marked, &line_type)| marked && line_type == LineType::Code)
+            .count()
+    };
+
+    MacroLines {
+        definition_lines: count_code(&in_definition),
+        invocation_lines: count_code(&in_invocation),
+    }
+}
+
+/// Counts the lexical tokens of a parsed file.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+///
+/// # Returns
+///
+/// The total and non-trivia token counts
+fn compute_token_counts(root: &SyntaxNode) -> TokenCounts {
+    let mut counts = TokenCounts::default();
+    for token in root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+    {
+        counts.all += 1;
+        counts.non_trivia += usize::from(!token.kind().is_trivia());
+    }
+    counts
+}
+
+/// Counts the logical statements of a parsed file.
+///
+/// Every item (including `use` declarations, `impl` blocks, and associated items), `let`
+/// binding, expression statement, and block tail expression counts once, however it is
+/// wrapped across lines. Macro invocations in expression position count as the statement
+/// containing them, not as items.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+///
+/// # Returns
+///
+/// The number of logical statements
+fn compute_statement_count(root: &SyntaxNode) -> usize {
+    root.descendants()
+        .filter(|node| match node.kind() {
+            SyntaxKind::LET_STMT | SyntaxKind::EXPR_STMT => true,
+            SyntaxKind::MACRO_CALL => node
+                .parent()
+                .is_none_or(|parent| parent.kind() != SyntaxKind::MACRO_EXPR),
+            kind if ast::Item::can_cast(kind) => true,
+            _ => ast::StmtList::cast(node.clone())
+                .and_then(|list| list.tail_expr())
+                .is_some(),
+        })
+        .count()
+}
+
+/// Hashes the content of a file, exactly and ignoring trivia.
+///
+/// # Arguments
+///
+/// * `content` - Source content of the file
+/// * `root` - Root node of the same content, parsed
+///
+/// # Returns
+///
+/// The exact and normalized content hashes
+fn compute_content_hashes(content: &str, root: &SyntaxNode) -> ContentHashes {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    let mut exact = DefaultHasher::new();
+    content.hash(&mut exact);
+
+    let mut normalized = DefaultHasher::new();
+    for token in root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+    {
+        token.text().hash(&mut normalized);
+    }
+
+    ContentHashes {
+        exact: exact.finish(),
+        normalized: normalized.finish(),
+    }
+}
+
+/// Counts the code lines dominated by string literal content.
+///
+/// A line is dominated when string, byte string, and C string literal tokens (raw or not)
+/// account for more than half of its non-whitespace characters, so the interior lines of a
+/// multi-line literal always count while a `println!("...")` call may or may not.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `line_index` - Line index over the same source content
+/// * `line_types` - Per-line classifications produced by [`type_lines`]
+///
+/// # Returns
+///
+/// The number of code lines dominated by string literals
+fn compute_string_lines(
+    root: &SyntaxNode,
+    line_index: &LineIndex,
+    line_types: &[LineType],
+) -> usize {
+    let mut visible = vec![0usize; line_types.len()];
+    let mut literal = vec![0usize; line_types.len()];
+
+    for token in root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+    {
+        let is_literal = matches!(
+            token.kind(),
+            SyntaxKind::STRING | SyntaxKind::BYTE_STRING | SyntaxKind::C_STRING
+        );
+        let first_line = line_index.line_of(token.text_range().start().into());
+        for (line, segment) in (first_line..).zip(token.text().split('\n')) {
+            let Some(count) = visible.get_mut(line) else {
+                break;
+            };
+            let characters = segment.chars().filter(|c| !c.is_whitespace()).count();
+            *count += characters;
+            if is_literal {
+                literal[line] += characters;
+            }
+        }
+    }
+
+    line_types
+        .iter()
+        .zip(visible.iter().zip(&literal))
+        .filter(|&(&line_type, (&visible, &literal))| {
+            line_type == LineType::Code && literal * 2 > visible
+        })
+        .count()
+}
+
+/// Computes `async`/`.await` usage of a file.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `line_index` - Line index over the same source content
+/// * `line_types` - Per-line classifications produced by [`type_lines`]
+///
+/// # Returns
+///
+/// The async metrics for the file
+fn compute_async_metrics(
+    root: &SyntaxNode,
+    line_index: &LineIndex,
+    line_types: &[LineType],
+) -> AsyncMetrics {
+    let mut metrics = AsyncMetrics::default();
+    let mut in_async = vec![false; line_types.len()];
+
+    for node in root.descendants() {
+        let body = match node.kind() {
+            SyntaxKind::FN => ast::Fn::cast(node.clone())
+                .filter(|func| func.async_token().is_some())
+                .map(|func| {
+                    metrics.async_functions += 1;
+                    func.body().map(|body| body.syntax().text_range())
+                })
+                .unwrap_or_default(),
+            SyntaxKind::BLOCK_EXPR => ast::BlockExpr::cast(node.clone())
+                .filter(|block| block.async_token().is_some())
+                .map(|_| node.text_range()),
+            SyntaxKind::CLOSURE_EXPR => ast::ClosureExpr::cast(node.clone())
+                .filter(|closure| closure.async_token().is_some())
+                .and_then(|closure| closure.body())
+                .map(|body| body.syntax().text_range()),
+            SyntaxKind::AWAIT_EXPR => {
+                metrics.await_points += 1;
+                None
+            }
+            _ => None,
+        };
+
+        if let Some(range) = body {
+            let (start, end) = line_index.line_span(range);
+            if let Some(lines) = in_async.get_mut(start..=end) {
+                lines.fill(true);
+            }
+        }
+    }
+
+    metrics.async_lines = in_async
+        .iter()
+        .zip(line_types)
+        .filter(|&(&marked, &line_type)| marked && line_type == LineType::Code)
+        .count();
+    metrics
+}
+
+/// Macros whose single string-literal argument names a file to include.
+const INCLUDE_MACROS: [&str; 3] = ["include", "include_str", "include_bytes"];
+
+/// Finds `include!`-family invocations with literal paths and resolves their targets.
+///
+/// Invocations whose argument is computed (e.g., `concat!(env!("OUT_DIR"), ...)`) cannot
+/// be resolved statically and are ignored.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `path` - Path of the including file, used to resolve relative targets
+///
+/// # Returns
+///
+/// Include targets in source order
+fn find_include_targets(root: &SyntaxNode, path: &Path) -> Vec<IncludeTarget> {
+    let base = path.parent().unwrap_or(Path::new("."));
+
+    root.descendants()
+        .filter_map(ast::MacroCall::cast)
+        .filter_map(|call| {
+            let macro_name = call.path()?.segment()?.name_ref()?.text().to_string();
+            if !INCLUDE_MACROS.contains(&macro_name.as_str()) {
+                return None;
+            }
+
+            let mut literals = call
+                .token_tree()?
+                .syntax()
+                .children_with_tokens()
+                .filter_map(|element| element.into_token())
+                .filter(|token| !token.kind().is_trivia() && !token.kind().is_punct());
+            let literal = literals.next()?;
+            if literal.kind() != SyntaxKind::STRING || literals.next().is_some() {
+                return None;
+            }
+
+            let target = literal
+                .text()
+                .strip_prefix('"')?
+                .strip_suffix('"')?
+                .to_string();
+            let candidate = base.join(&target);
+            let resolved = candidate.is_file().then(|| display_path(&candidate));
+
+            Some(IncludeTarget {
+                macro_name,
+                target,
+                resolved,
+            })
+        })
+        .collect()
+}
+
+/// Selects the included Rust sources that a directory walk would not analyze itself.
+///
+/// Only `include!` splices Rust code; `include_str!` and `include_bytes!` pull in data.
+/// Targets with a source extension (see `--ext`) are already counted by the walk when
+/// they live inside the analyzed tree, so only other extensions are returned to avoid
+/// double counting.
+///
+/// # Arguments
+///
+/// * `stats` - Statistics of the including file
+/// * `extensions` - Extensions the directory walk analyzes
+///
+/// # Returns
+///
+/// Resolved paths of included Rust sources without a source extension
+#[cfg(feature = "cli")]
+fn uncounted_rust_includes(stats: &FileStats, extensions: &SourceExtensions) -> Vec<PathBuf> {
+    stats
+        .includes
+        .iter()
+        .filter(|include| include.macro_name == "include")
+        .filter_map(|include| include.resolved.as_ref().map(PathBuf::from))
+        .filter(|resolved| !extensions.matches(resolved))
+        .collect()
+}
+
+/// Extracts the cargo feature names referenced by a `cfg` predicate.
+///
+/// Scans the predicate for `feature = "name"` pairs at any nesting depth, so features
+/// combined through `all(...)`, `any(...)`, or `not(...)` are all reported.
+///
+/// # Arguments
+///
+/// * `token_tree` - The parenthesized predicate of a `#[cfg(...)]` attribute
+///
+/// # Returns
+///
+/// Feature names in order of appearance
+fn cfg_features(token_tree: &ast::TokenTree) -> Vec<String> {
+    let tokens: Vec<_> = token_tree
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+        .collect();
+
+    tokens
+        .windows(3)
+        .filter(|window| {
+            window[0].kind() == SyntaxKind::IDENT
+                && window[0].text() == "feature"
+                && window[1].kind() == SyntaxKind::EQ
+                && window[2].kind() == SyntaxKind::STRING
+        })
+        .map(|window| window[2].text().trim_matches('"').to_string())
+        .collect()
+}
+
+/// Collects the cargo features gating a syntax node through its `#[cfg(...)]` attributes.
+///
+/// # Arguments
+///
+/// * `node` - The syntax tree node to examine
+///
+/// # Returns
+///
+/// Names of the features referenced by the node's own `cfg` attributes
+fn feature_gates(node: &SyntaxNode) -> Vec<String> {
+    let Some(owner) = ast::AnyHasAttrs::cast(node.clone()) else {
+        return Vec::new();
+    };
+
+    owner
+        .attrs()
+        .filter(|attr| attr.path().is_some_and(|path| path.to_string() == "cfg"))
+        .filter_map(|attr| attr.token_tree())
+        .flat_map(|token_tree| cfg_features(&token_tree))
+        .collect()
+}
+
+/// Attributes lines to the cargo features gating them.
+///
+/// Every node carrying `#[cfg(...)]` attributes that reference `feature = "..."` marks
+/// the lines it spans as belonging to each referenced feature. A line nested within
+/// several gates, or guarded by a predicate naming several features, is attributed to
+/// each of them, so the breakdown answers "how much code does feature X guard" rather
+/// than partitioning the file.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `line_index` - Line index over the same source content
+/// * `line_types` - Per-line classifications produced by [`type_lines`]
+///
+/// # Returns
+///
+/// Line statistics keyed by feature name, empty when no feature gates are present
+fn compute_feature_breakdown(
+    root: &SyntaxNode,
+    line_index: &LineIndex,
+    line_types: &[LineType],
+) -> BTreeMap<String, LineStats> {
+    let mut gated_lines: BTreeMap<String, Vec<bool>> = BTreeMap::new();
+
+    for node in root.descendants() {
+        let features = feature_gates(&node);
+        if features.is_empty() {
+            continue;
+        }
+
+        let (start, end) = line_index.line_span(node.text_range());
+        for feature in features {
+            let mask = gated_lines
+                .entry(feature)
+                .or_insert_with(|| vec![false; line_types.len()]);
+            if let Some(lines) = mask.get_mut(start..=end) {
+                lines.fill(true);
+            }
+        }
+    }
+
+    gated_lines
+        .into_iter()
+        .map(|(feature, mask)| (feature, masked_line_stats(line_types, &mask)))
+        .collect()
+}
+
+/// Computes the line statistics of the inline modules declared in a file.
+///
+/// Every line belongs to exactly one module: an inline module owns the lines of its whole
+/// item, including its attributes and doc comments, and a line shared with the enclosing
+/// module goes to the inline module. Modules declared inside
+/// function bodies are not addressable by path and stay with their enclosing module.
+///
+/// # Arguments
+///
+/// * `root` - Root syntax node of the file
+/// * `line_index` - Line index over the source content
+/// * `line_types` - Per-line classifications produced by [`type_lines`]
+/// * `is_test_line` - Per-line flags marking test code
+/// * `is_bench_line` - Per-line flags marking benchmark code
+/// * `tooling` - Tooling kind of the file, `None` for ordinary code
+///
+/// # Returns
+///
+/// One entry per inline module in source order, each counting only the lines not
+/// claimed by a nested inline module
+fn compute_inline_modules(
+    root: &SyntaxNode,
+    line_index: &LineIndex,
+    line_types: &[LineType],
+    is_test_line: &[bool],
+    is_bench_line: &[bool],
+    tooling: Option<ToolingKind>,
+) -> Vec<InlineModule> {
+    let mut owner: Vec<Option<usize>> = vec![None; line_types.len()];
+    let mut paths = Vec::new();
+
+    for module in root.descendants().filter_map(ast::Module::cast) {
+        if module.item_list().is_none()
+            || module
+                .syntax()
+                .ancestors()
+                .any(|node| node.kind() == SyntaxKind::BLOCK_EXPR)
+        {
+            continue;
+        }
+        let mut segments: Vec<String> = module
+            .syntax()
+            .ancestors()
+            .filter_map(ast::Module::cast)
+            .filter_map(|module| module.name().map(|name| name.text().to_string()))
+            .collect();
+        segments.reverse();
+        // The whole item, attributes and doc comments included, belongs to the module
+        let (start, end) = line_index.line_span(module.syntax().text_range());
+
+        // Descendants are visited in preorder, so nested modules overwrite their parents
+        let end = end.min(line_types.len().saturating_sub(1));
+        for line in owner.iter_mut().take(end + 1).skip(start) {
+            *line = Some(paths.len());
+        }
+        paths.push(segments.join("::"));
+    }
+
+    paths
+        .into_iter()
+        .enumerate()
+        .map(|(index, path)| {
+            let scoped = |in_scope: &dyn Fn(usize) -> bool| {
+                let mask: Vec<bool> = (0..line_types.len())
+                    .map(|line| owner[line] == Some(index) && in_scope(line))
+                    .collect();
+                masked_line_stats(line_types, &mask)
+            };
+            let (production, build_script, proc_macro) = ToolingKind::split(
+                tooling,
+                scoped(&|line| !is_bench_line[line] && !is_test_line[line]),
+            );
+            InlineModule {
+                path,
+                total: scoped(&|_| true),
+                production,
+                test: scoped(&|line| !is_bench_line[line] && is_test_line[line]),
+                bench: scoped(&|line| is_bench_line[line]),
+                build_script,
+                proc_macro,
+            }
+        })
+        .collect()
+}
+
+/// Computes line statistics over the lines selected by a mask.
+///
+/// # Arguments
+///
+/// * `line_types` - Per-line classifications produced by [`type_lines`]
+/// * `mask` - Per-line flags selecting the lines to count
+///
+/// # Returns
+///
+/// Statistics of the selected lines
+fn masked_line_stats(line_types: &[LineType], mask: &[bool]) -> LineStats {
+    let selected: Vec<LineType> = line_types
+        .iter()
+        .zip(mask)
+        .filter(|&(_, &selected)| selected)
+        .map(|(&line_type, _)| line_type)
+        .collect();
+    compute_line_stats(&selected, selected.len())
+}
+
+/// Extracts the predicate atoms of a `cfg` predicate, ignoring `test`.
+///
+/// Atoms are the leaves of the predicate once `all(...)`, `any(...)`, and `not(...)` are
+/// looked through: bare options such as `unix` and key-value options such as
+/// `target_os = "linux"`, rendered as written with normalized spacing.
+///
+/// # Arguments
+///
+/// * `token_tree` - The parenthesized predicate of a `#[cfg(...)]` attribute
+///
+/// # Returns
+///
+/// Predicate atoms in order of appearance
+fn cfg_atoms(token_tree: &ast::TokenTree) -> Vec<String> {
+    let tokens: Vec<_> = token_tree
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| !token.kind().is_trivia())
+        .collect();
+
+    let mut atoms = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if token.kind() != SyntaxKind::IDENT {
+            continue;
+        }
+        let next = tokens.get(index + 1).map(|next| next.kind());
+        let value = tokens.get(index + 2);
+        match (next, value) {
+            (Some(SyntaxKind::L_PAREN), _) => {}
+            (Some(SyntaxKind::EQ), Some(value)) => {
+                atoms.push(format!("{} = {}", token.text(), value.text()));
+            }
+            _ if token.text() == "test" => {}
+            _ => atoms.push(token.text().to_string()),
+        }
+    }
+    atoms
+}
+
+/// Attributes lines to the non-test `cfg` predicates guarding them.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `line_index` - Line index over the same source content
+/// * `line_types` - Per-line classifications produced by [`type_lines`]
+///
+/// # Returns
+///
+/// The guarded line statistics, overall and per predicate atom
+fn compute_cfg_surface(
+    root: &SyntaxNode,
+    line_index: &LineIndex,
+    line_types: &[LineType],
+) -> CfgSurface {
+    let mut guarded = vec![false; line_types.len()];
+    let mut predicates: BTreeMap<String, Vec<bool>> = BTreeMap::new();
+
+    for node in root.descendants() {
+        let Some(owner) = ast::AnyHasAttrs::cast(node.clone()) else {
+            continue;
+        };
+        let atoms: Vec<String> = owner
+            .attrs()
+            .filter(|attr| attr.path().is_some_and(|path| path.to_string() == "cfg"))
+            .filter_map(|attr| attr.token_tree())
+            .flat_map(|token_tree| cfg_atoms(&token_tree))
+            .collect();
+        if atoms.is_empty() {
+            continue;
+        }
+
+        let (start, end) = line_index.line_span(node.text_range());
+        let fill = |mask: &mut [bool]| {
+            if let Some(lines) = mask.get_mut(start..=end) {
+                lines.fill(true);
+            }
+        };
+        fill(&mut guarded);
+        for atom in atoms {
+            fill(
+                predicates
+                    .entry(atom)
+                    .or_insert_with(|| vec![false; line_types.len()]),
+            );
+        }
+    }
+
+    CfgSurface {
+        guarded: masked_line_stats(line_types, &guarded),
+        predicates: predicates
+            .into_iter()
+            .map(|(atom, mask)| (atom, masked_line_stats(line_types, &mask)))
+            .collect(),
+    }
+}
+
+/// Finds whole-word occurrences of a marker within a piece of text.
+///
+/// A match counts only when it is not embedded in a longer identifier, so `TODO`
+/// matches `// TODO: fix` and `TODO(alice)` but not `TODOS` or `MY_TODO`.
+///
+/// # Arguments
+///
+/// * `text` - Text to search, typically a comment token
+/// * `marker` - Marker to look for
+///
+/// # Returns
+///
+/// Byte offsets of each occurrence within `text`
+fn marker_occurrences(text: &str, marker: &str) -> Vec<usize> {
+    let is_word_byte = |byte: u8| byte.is_ascii_alphanumeric() || byte == b'_';
+    let bytes = text.as_bytes();
+
+    text.match_indices(marker)
+        .map(|(offset, _)| offset)
+        .filter(|&offset| {
+            let before_ok = offset == 0 || !is_word_byte(bytes[offset - 1]);
+            let end = offset + marker.len();
+            let after_ok = end >= bytes.len() || !is_word_byte(bytes[end]);
+            before_ok && after_ok
+        })
+        .collect()
+}
+
+/// Scans comment tokens for work-markers such as `TODO`, `FIXME`, `HACK`, and `XXX`.
+///
+/// Only comments are scanned, so markers appearing in identifiers or string literals
+/// are ignored.
+///
+/// # Arguments
+///
+/// * `comments` - Comment tokens of the parsed source file, in source order
+/// * `content` - Complete source file content, used to extract location text
+/// * `line_index` - Line index over the same source content
+/// * `markers` - Markers to search for
+/// * `list_locations` - Whether to record each occurrence's location
+///
+/// # Returns
+///
+/// Occurrence counts keyed by marker (markers without occurrences are omitted) and,
+/// when requested, the location of every occurrence in source order
+fn compute_marker_census(
+    comments: &[SyntaxToken],
+    content: &str,
+    line_index: &LineIndex,
+    markers: &[String],
+    list_locations: bool,
+) -> (BTreeMap<String, usize>, Vec<MarkerLocation>) {
+    let mut counts = BTreeMap::new();
+    let mut locations = Vec::new();
+    let lines: Vec<&str> = if list_locations {
+        content.lines().collect()
+    } else {
+        Vec::new()
+    };
+
+    for token in comments {
+        let token_start: usize = token.text_range().start().into();
+        for marker in markers {
+            for offset in marker_occurrences(token.text(), marker) {
+                *counts.entry(marker.clone()).or_insert(0) += 1;
+                if list_locations {
+                    let line = line_index.line_of(token_start + offset);
+                    locations.push(MarkerLocation {
+                        line: line + 1,
+                        marker: marker.clone(),
+                        text: lines.get(line).map_or("", |text| text.trim()).to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    locations.sort_by_key(|location| location.line);
+    (counts, locations)
+}
+
+/// Counts the lints named in the `allow` attributes of a file.
+///
+/// Both outer `#[allow(...)]` and inner `#![allow(...)]` attributes are counted, once per
+/// lint they name; `reason = "..."` arguments are ignored.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+///
+/// # Returns
+///
+/// Occurrence counts keyed by lint name (e.g., `dead_code`, `clippy::too_many_arguments`)
+fn compute_allowed_lints(root: &SyntaxNode) -> BTreeMap<String, usize> {
+    let mut lints = BTreeMap::new();
+
+    let allow_arguments = root
+        .descendants()
+        .filter_map(ast::Attr::cast)
+        .filter(|attr| attr.path().is_some_and(|path| path.to_string() == "allow"))
+        .filter_map(|attr| attr.token_tree());
+    for token_tree in allow_arguments {
+        let text = token_tree.syntax().text().to_string();
+        let arguments = text.trim_start_matches('(').trim_end_matches(')');
+        for lint in arguments
+            .split(',')
+            .map(|lint| lint.split_whitespace().collect::<String>())
+        {
+            if !lint.is_empty() && !lint.contains('=') {
+                *lints.entry(lint).or_insert(0) += 1;
+            }
+        }
+    }
+
+    lints
+}
+
+/// Names the kind of panic call site a method or macro name denotes.
+///
+/// # Arguments
+///
+/// * `name` - Method or macro name, without the `!`
+/// * `is_macro` - Whether the name is invoked as a macro
+///
+/// # Returns
+///
+/// The kind (`unwrap`, `expect`, `panic!`, `todo!`, or `unimplemented!`), or `None` for
+/// calls that do not panic
+fn panic_site_kind(name: &str, is_macro: bool) -> Option<&'static str> {
+    match (name, is_macro) {
+        ("unwrap", false) => Some("unwrap"),
+        ("expect", false) => Some("expect"),
+        ("panic", true) => Some("panic!"),
+        ("todo", true) => Some("todo!"),
+        ("unimplemented", true) => Some("unimplemented!"),
+        _ => None,
+    }
+}
+
+/// Finds the call sites of a file that may panic.
+///
+/// Matches `.unwrap()` and `.expect(..)` method calls regardless of the receiver type, and
+/// `panic!`, `todo!`, and `unimplemented!` invocations by the last segment of their path.
+/// Macro arguments (such as those of `assert_eq!`) are not parsed into expressions, so
+/// their tokens are scanned for `.unwrap`, `.expect`, and `panic!`-style sequences instead.
+/// Macro definitions are skipped.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+///
+/// # Returns
+///
+/// The offset and kind of every call site, in source order
+fn find_panic_sites(root: &SyntaxNode) -> Vec<(usize, &'static str)> {
+    let mut sites = Vec::new();
+
+    for element in root.descendants_with_tokens() {
+        match element {
+            NodeOrToken::Node(node) => {
+                let site = if let Some(call) = ast::MethodCallExpr::cast(node.clone()) {
+                    call.name_ref().and_then(|name| {
+                        panic_site_kind(name.text().as_str(), false)
+                            .map(|kind| (name.syntax().text_range().start(), kind))
+                    })
+                } else if let Some(call) = ast::MacroCall::cast(node.clone()) {
+                    call.path()
+                        .and_then(|path| path.segment())
+                        .and_then(|segment| segment.name_ref())
+                        .and_then(|name| panic_site_kind(name.text().as_str(), true))
+                        .map(|kind| (node.text_range().start(), kind))
+                } else {
+                    None
+                };
+                sites.extend(site.map(|(offset, kind)| (offset.into(), kind)));
+            }
+            NodeOrToken::Token(token) => {
+                let in_macro_arguments = token
+                    .parent()
+                    .is_some_and(|parent| parent.kind() == SyntaxKind::TOKEN_TREE)
+                    && !token.parent_ancestors().any(|ancestor| {
+                        matches!(
+                            ancestor.kind(),
+                            SyntaxKind::MACRO_RULES | SyntaxKind::MACRO_DEF
+                        )
+                    });
+                if !in_macro_arguments || token.kind() != SyntaxKind::IDENT {
+                    continue;
+                }
+
+                let previous = std::iter::successors(token.prev_token(), |prev| prev.prev_token())
+                    .find(|prev| !prev.kind().is_trivia());
+                let next = std::iter::successors(token.next_token(), |next| next.next_token())
+                    .find(|next| !next.kind().is_trivia());
+                let kind = if previous.is_some_and(|prev| prev.kind() == SyntaxKind::DOT) {
+                    panic_site_kind(token.text(), false)
+                } else if next.is_some_and(|next| next.kind() == SyntaxKind::BANG) {
+                    panic_site_kind(token.text(), true)
+                } else {
+                    None
+                };
+                sites.extend(kind.map(|kind| (token.text_range().start().into(), kind)));
+            }
+        }
+    }
+
+    sites
+}
+
+/// Counts the call sites of a file that may panic, split between production and test code.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `content` - Complete source file content, used to extract location text
+/// * `line_index` - Line index over the same source content
+/// * `is_test_line` - Per-line flags marking test code
+/// * `is_bench_line` - Per-line flags marking benchmark code, which is skipped
+/// * `list_sites` - Whether to record each call site's location
+///
+/// # Returns
+///
+/// The census for the file and, when requested, the location of every call site in source
+/// order
+fn compute_panic_census(
+    root: &SyntaxNode,
+    content: &str,
+    line_index: &LineIndex,
+    is_test_line: &[bool],
+    is_bench_line: &[bool],
+    list_sites: bool,
+) -> (PanicCensus, Vec<PanicSite>) {
+    let mut census = PanicCensus::default();
+    let mut sites = Vec::new();
+    let lines: Vec<&str> = content.lines().collect();
+
+    for (offset, kind) in find_panic_sites(root) {
+        let line = line_index.line_of(offset);
+        if is_bench_line.get(line).copied().unwrap_or(false) {
+            continue;
+        }
+
+        let test = is_test_line.get(line).copied().unwrap_or(false);
+        let counts = if test {
+            &mut census.test
+        } else {
+            &mut census.production
+        };
+        *counts.counter(kind) += 1;
+
+        if list_sites {
+            sites.push(PanicSite {
+                line: line + 1,
+                kind: kind.to_string(),
+                test,
+                text: lines.get(line).map_or("", |text| text.trim()).to_string(),
+            });
+        }
+    }
+
+    (census, sites)
+}
+
+/// Computes the zero-based line span of an item, excluding leading attributes and doc comments.
+///
+/// # Arguments
+///
+/// * `node` - The item node (function, `impl` block, ...)
+/// * `line_index` - Line index over the source content
+///
+/// # Returns
+///
+/// `(start, end)` zero-based lines from the first signature token through the end of
+/// the item, or `None` if the item consists solely of attributes and comments
+fn item_line_span(node: &SyntaxNode, line_index: &LineIndex) -> Option<(usize, usize)> {
+    let signature_start = node
+        .children_with_tokens()
+        .find(|element| {
+            !matches!(
+                element.kind(),
+                SyntaxKind::ATTR | SyntaxKind::COMMENT | SyntaxKind::WHITESPACE
+            )
+        })?
+        .text_range()
+        .start();
+
+    Some(line_index.line_span(ra_ap_syntax::TextRange::new(
+        signature_start,
+        node.text_range().end(),
+    )))
+}
+
+/// Finds test functions whose names do not match the required pattern.
+///
+/// Test functions are those carrying a `#[test]` attribute, including namespaced
+/// variants such as `#[tokio::test]`.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `line_index` - Line index over the same source content
+/// * `pattern` - Pattern every test function name must match
+///
+/// # Returns
+///
+/// Violations in source order
+fn audit_test_names(
+    root: &SyntaxNode,
+    line_index: &LineIndex,
+    pattern: &Regex,
+) -> Vec<TestNameViolation> {
+    root.descendants()
+        .filter_map(ast::Fn::cast)
+        .filter(|func| func.attrs().any(|attr| is_test_attribute(&attr)))
+        .filter_map(|func| {
+            let name = func.name()?.text().to_string();
+            if pattern.is_match(&name) {
+                return None;
+            }
+            let (start, _) = item_line_span(func.syntax(), line_index)?;
+            Some(TestNameViolation {
+                name,
+                line: start + 1,
+            })
+        })
+        .collect()
+}
+
+/// Derives a display name for a function, qualifying methods with their `impl` type.
+///
+/// # Arguments
+///
+/// * `func` - The function node
+///
+/// # Returns
+///
+/// `Type::name` for functions inside `impl` blocks, otherwise the bare function name
+fn function_display_name(func: &ast::Fn) -> String {
+    let name = func
+        .name()
+        .map_or_else(|| "<anonymous>".to_string(), |name| name.text().to_string());
+
+    let self_ty = func
+        .syntax()
+        .ancestors()
+        .skip(1)
+        .find_map(ast::Impl::cast)
+        .and_then(|imp| imp.self_ty());
+
+    match self_ty {
+        Some(self_ty) => format!("{}::{}", self_ty.syntax().text(), name),
+        None => name,
+    }
+}
+
+/// Finds functions whose bodies span more than the given number of lines.
+///
+/// Function length is measured from the first line of the signature (after any
+/// attributes and doc comments) through the closing brace, so documentation never
+/// counts against a function.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `line_index` - Line index over the same source content
+/// * `max_lines` - Maximum number of lines a function may span
+///
+/// # Returns
+///
+/// Functions exceeding the threshold, in source order
+fn find_long_functions(
+    root: &SyntaxNode,
+    line_index: &LineIndex,
+    max_lines: usize,
+) -> Vec<LongFunction> {
+    root.descendants()
+        .filter_map(ast::Fn::cast)
+        .filter_map(|func| {
+            let (start, end) = item_line_span(func.syntax(), line_index)?;
+            let lines = end - start + 1;
+
+            (lines > max_lines).then(|| LongFunction {
+                name: function_display_name(&func),
+                start_line: start + 1,
+                end_line: end + 1,
+                lines,
+            })
+        })
+        .collect()
+}
+
+/// Counts the branch points below a node, stopping at nested functions.
+///
+/// See [`FunctionComplexity`] for the constructs that count as branch points.
+fn count_branch_points(node: &SyntaxNode) -> usize {
+    node.children()
+        .filter(|child| !ast::Fn::can_cast(child.kind()))
+        .map(|child| {
+            let own = match child.kind() {
+                SyntaxKind::IF_EXPR
+                | SyntaxKind::WHILE_EXPR
+                | SyntaxKind::FOR_EXPR
+                | SyntaxKind::LOOP_EXPR
+                | SyntaxKind::TRY_EXPR => 1,
+                SyntaxKind::MATCH_EXPR => ast::MatchExpr::cast(child.clone())
+                    .and_then(|expr| expr.match_arm_list())
+                    .map_or(0, |arms| arms.arms().count().saturating_sub(1)),
+                SyntaxKind::BIN_EXPR => ast::BinExpr::cast(child.clone())
+                    .and_then(|expr| expr.op_kind())
+                    .map_or(0, |op| usize::from(matches!(op, ast::BinaryOp::LogicOp(_)))),
+                _ => 0,
+            };
+            own + count_branch_points(&child)
+        })
+        .sum()
+}
+
+/// Computes the deepest nesting of control-flow blocks and closures below a node.
+///
+/// `if`, `while`, `for`, `loop`, and `match` expressions and closures each open one level;
+/// an `else if` continues its chain rather than nesting. Nested functions are skipped, as
+/// they are measured on their own.
+fn nesting_depth(node: &SyntaxNode) -> usize {
+    node.children()
+        .filter(|child| !ast::Fn::can_cast(child.kind()))
+        .map(|child| {
+            let opens = match child.kind() {
+                SyntaxKind::IF_EXPR => node.kind() != SyntaxKind::IF_EXPR,
+                SyntaxKind::WHILE_EXPR
+                | SyntaxKind::FOR_EXPR
+                | SyntaxKind::LOOP_EXPR
+                | SyntaxKind::MATCH_EXPR
+                | SyntaxKind::CLOSURE_EXPR => true,
+                _ => false,
+            };
+            usize::from(opens) + nesting_depth(&child)
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Measures the block nesting depth of every function with a body.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `line_index` - Line index over the same source content
+/// * `max_nesting` - Depth above which functions are reported, if any
+///
+/// # Returns
+///
+/// The deepest nesting in the file (0 when it has no functions) and the functions exceeding
+/// the threshold, in source order
+fn compute_nesting(
+    root: &SyntaxNode,
+    line_index: &LineIndex,
+    max_nesting: Option<usize>,
+) -> (usize, Vec<NestedFunction>) {
+    let mut file_depth = 0;
+    let mut nested_functions = Vec::new();
+
+    for func in root.descendants().filter_map(ast::Fn::cast) {
+        let Some(body) = func.body() else {
+            continue;
+        };
+        let depth = nesting_depth(body.syntax());
+        file_depth = file_depth.max(depth);
+
+        if max_nesting.is_some_and(|max| depth > max)
+            && let Some((start, _)) = item_line_span(func.syntax(), line_index)
+        {
+            nested_functions.push(NestedFunction {
+                name: function_display_name(&func),
+                start_line: start + 1,
+                depth,
+            });
+        }
+    }
+
+    (file_depth, nested_functions)
+}
+
+/// Estimates the cyclomatic complexity of every function with a body.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `line_index` - Line index over the same source content
+/// * `max_complexity` - Threshold above which functions are reported, if any
+///
+/// # Returns
+///
+/// The complexity counters for the file and the functions exceeding the threshold, in
+/// source order
+fn compute_function_complexity(
+    root: &SyntaxNode,
+    line_index: &LineIndex,
+    max_complexity: Option<usize>,
+) -> (FunctionComplexity, Vec<ComplexFunction>) {
+    let mut complexity = FunctionComplexity::default();
+    let mut complex_functions = Vec::new();
+
+    for func in root.descendants().filter_map(ast::Fn::cast) {
+        let Some(body) = func.body() else {
+            continue;
+        };
+        let function_complexity = 1 + count_branch_points(body.syntax());
+        complexity.record(function_complexity);
+
+        if max_complexity.is_some_and(|max| function_complexity > max)
+            && let Some((start, _)) = item_line_span(func.syntax(), line_index)
+        {
+            complex_functions.push(ComplexFunction {
+                name: function_display_name(&func),
+                start_line: start + 1,
+                complexity: function_complexity,
+            });
+        }
+    }
+
+    (complexity, complex_functions)
+}
+
+/// Counts generic parameters, `where` clauses, and higher-ranked trait bounds.
+///
+/// Generic parameters are only counted on item declarations, so the parameters of a
+/// `for<'a>` binder contribute to the higher-ranked bound count instead. Higher-ranked
+/// bounds are recognized as a `for` keyword immediately followed by `<`, excluding
+/// `impl ... for` headers and `for` loops.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+///
+/// # Returns
+///
+/// The type complexity counters for the file
+fn compute_type_complexity(root: &SyntaxNode) -> TypeComplexity {
+    let mut complexity = TypeComplexity::default();
+
+    for node in root.descendants() {
+        if let Some(params) = ast::GenericParamList::cast(node.clone()) {
+            let on_item = node
+                .parent()
+                .is_some_and(|parent| ast::Item::can_cast(parent.kind()));
+            if on_item {
+                complexity.generic_params += params.generic_params().count();
+            }
+        } else if ast::WhereClause::can_cast(node.kind()) {
+            complexity.where_clauses += 1;
+        }
+    }
+
+    complexity.higher_ranked_bounds = root
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .filter(|token| token.kind() == SyntaxKind::FOR_KW)
+        .filter(|token| {
+            !token.parent().is_some_and(|parent| {
+                matches!(parent.kind(), SyntaxKind::IMPL | SyntaxKind::FOR_EXPR)
+            })
+        })
+        .filter(|token| {
+            std::iter::successors(token.next_token(), |next| next.next_token())
+                .find(|next| !next.kind().is_trivia())
+                .is_some_and(|next| next.kind() == SyntaxKind::L_ANGLE)
+        })
+        .count();
+
+    complexity
+}
+
+/// Counts the items of a file by kind.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+///
+/// # Returns
+///
+/// The item kind census for the file
+fn compute_item_kind_counts(root: &SyntaxNode) -> ItemKindCounts {
+    let mut counts = ItemKindCounts::default();
+
+    for node in root.descendants() {
+        match node.kind() {
+            SyntaxKind::STRUCT => counts.structs += 1,
+            SyntaxKind::ENUM => counts.enums += 1,
+            SyntaxKind::TRAIT => counts.traits += 1,
+            SyntaxKind::IMPL => counts.impls += 1,
+            SyntaxKind::FN => counts.functions += 1,
+            SyntaxKind::MACRO_RULES | SyntaxKind::MACRO_DEF => counts.macros += 1,
+            SyntaxKind::CONST => counts.consts += 1,
+            SyntaxKind::STATIC => counts.statics += 1,
+            _ => {}
+        }
+    }
+
+    counts
+}
+
+/// Inventories the `impl` blocks of a file with their types, traits, and sizes.
+///
+/// Trait names are reduced to their final path segment without generic arguments, so
+/// that `std::fmt::Display` and `Display` aggregate together in the crate-level inventory.
+///
+/// # Arguments
+///
+/// * `root` - Root node of the parsed source file
+/// * `line_index` - Line index over the same source content
+///
+/// # Returns
+///
+/// `impl` blocks in source order
+fn collect_impl_blocks(root: &SyntaxNode, line_index: &LineIndex) -> Vec<ImplBlock> {
+    root.descendants()
+        .filter_map(ast::Impl::cast)
+        .filter_map(|imp| {
+            let (start, end) = item_line_span(imp.syntax(), line_index)?;
+            let self_type = imp.self_ty()?.syntax().text().to_string();
+            let trait_name = imp.trait_().map(|trait_ty| match &trait_ty {
+                ast::Type::PathType(path_type) => path_type
+                    .path()
+                    .and_then(|path| path.segment())
+                    .and_then(|segment| segment.name_ref())
+                    .map_or_else(
+                        || trait_ty.syntax().text().to_string(),
+                        |name| name.text().to_string(),
+                    ),
+                _ => trait_ty.syntax().text().to_string(),
+            });
+
+            Some(ImplBlock {
+                self_type,
+                trait_name,
+                start_line: start + 1,
+                lines: end - start + 1,
+            })
+        })
+        .collect()
+}
+
+/// Files at least this large are memory-mapped with `--mmap`; smaller files are cheaper
+/// to read than to map.
+#[cfg(any(feature = "mmap", all(test, feature = "cli")))]
+const MMAP_MIN_BYTES: u64 = 64 * 1024;
+
+/// Raw bytes of a source file, either read into memory or memory-mapped.
+enum SourceBytes {
+    /// Bytes copied into a heap buffer.
+    Read(Vec<u8>),
+
+    /// Bytes mapped from the file, paged in by the kernel on demand.
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl std::ops::Deref for SourceBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            SourceBytes::Read(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            SourceBytes::Mapped(map) => map,
+        }
+    }
+}
+
+impl SourceBytes {
+    /// Loads the bytes of a source file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File to load
+    /// * `mmap` - Whether files of at least [`MMAP_MIN_BYTES`] are memory-mapped
+    ///
+    /// # Returns
+    ///
+    /// The file's bytes, mapped when requested and the file is large enough
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened, read, or mapped
+    fn load(path: &Path, mmap: bool) -> Result<Self, RulocError> {
+        let read_error = |source| RulocError::Io {
+            context: format!("Failed to read file '{}'", path.display()),
+            source,
+        };
+
+        #[cfg(feature = "mmap")]
+        if mmap {
+            let file = fs::File::open(path).map_err(read_error)?;
+            if file.metadata().map_err(read_error)?.len() >= MMAP_MIN_BYTES {
+                // SAFETY: the map is read-only and dropped once the file is analyzed. A file
+                // truncated by another process while mapped can fault the read; --mmap is
+                // opt-in for trees that are not being modified during the run.
+                let map = unsafe { memmap2::Mmap::map(&file) }.map_err(read_error)?;
+                return Ok(SourceBytes::Mapped(map));
+            }
+        }
+        #[cfg(not(feature = "mmap"))]
+        let _ = mmap;
+
+        fs::read(path).map(SourceBytes::Read).map_err(read_error)
+    }
+}
+
+/// Decodes the bytes of a source file, replacing invalid UTF-8 unless strict decoding is
+/// requested.
+///
+/// A single vendored file with a stray Latin-1 byte should not fail a whole run, so by
+/// default each invalid sequence becomes U+FFFD and a warning is logged. CRLF line endings
+/// are normalized to LF (see [`normalize_line_endings`]). Valid LF-only sources are
+/// borrowed, so memory-mapped files reach the parser without being copied.
+///
+/// # Arguments
+///
+/// * `path` - File the bytes were loaded from, used in messages
+/// * `bytes` - Raw file content
+/// * `strict_utf8` - Whether invalid UTF-8 is an error rather than being replaced
+///
+/// # Returns
+///
+/// The decoded, LF-normalized content and the number of invalid bytes that were replaced
+///
+/// # Errors
+///
+/// Returns [`RulocError::InvalidUtf8`] if the bytes are not valid UTF-8 and `strict_utf8` is set
+fn decode_source<'a>(
+    path: &Path,
+    bytes: &'a [u8],
+    strict_utf8: bool,
+) -> Result<(Cow<'a, str>, usize), RulocError> {
+    if let Ok(content) = std::str::from_utf8(bytes) {
+        return Ok((normalize_line_endings(content), 0));
+    }
+
+    let replaced_bytes: usize = bytes.utf8_chunks().map(|chunk| chunk.invalid().len()).sum();
+    if strict_utf8 {
+        return Err(RulocError::InvalidUtf8 {
+            path: path.to_path_buf(),
+            invalid_bytes: replaced_bytes,
+        });
+    }
+
+    warn!(
+        "File {} is not valid UTF-8; replaced {} invalid bytes",
+        path.display(),
+        replaced_bytes
+    );
+    let content = String::from_utf8_lossy(bytes);
+    Ok((
+        Cow::Owned(normalize_line_endings(&content).into_owned()),
+        replaced_bytes,
+    ))
+}
+
+/// Converts CRLF line endings to LF.
+///
+/// The Rust lexer expects the CRLF normalization that rustc applies before lexing; given
+/// raw CRLF input, every multi-line string literal and block doc comment reports a bare
+/// carriage return error, which would mark Windows checkouts as approximate. Line numbers
+/// are unaffected, so counts match between LF and CRLF checkouts of the same file.
+///
+/// # Arguments
+///
+/// * `content` - Source content with any line endings
+///
+/// # Returns
+///
+/// The content with `\r\n` replaced by `\n`, borrowed when there is nothing to replace
+fn normalize_line_endings(content: &str) -> Cow<'_, str> {
+    if content.contains("\r\n") {
+        Cow::Owned(content.replace("\r\n", "\n"))
+    } else {
+        Cow::Borrowed(content)
+    }
+}
+
+/// Analyzes a single Rust source file to compute line statistics.
+///
+/// Reads the file and resolves its crate's edition, then classifies its content.
+///
+/// # Arguments
+///
+/// * `path` - Path to the Rust source file to analyze
+/// * `options` - Size limit and opt-in analyses to apply
+///
+/// # Returns
+///
+/// `Ok(FileStats)` with the analysis results, or a [`RulocError`] if file reading fails
+/// or the file exceeds the size limit
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or exceeds the maximum size
+///
+/// # Examples
+///
+/// ```
+/// use ruloc_ffi::{AnalysisOptions, analyze_file};
+/// use std::path::Path;
+///
+/// let path = Path::new("tests/fixtures/basic/src/lib.rs");
+/// let stats = analyze_file(path, &AnalysisOptions::default())?;
+/// assert!(stats.production.code_lines > 0);
+/// # Ok::<(), ruloc_ffi::RulocError>(())
+/// ```
+pub fn analyze_file(path: &Path, options: &AnalysisOptions) -> Result<FileStats, RulocError> {
+    trace!("Analyzing file: {}", path.display());
+
+    // Check file size if limit is specified
+    if let Some(max_size) = options.max_file_size {
+        let metadata = fs::metadata(path).map_err(RulocError::io(format!(
+            "Failed to get metadata for '{}'",
+            path.display()
+        )))?;
+        let file_size = metadata.len();
+
+        if file_size > max_size {
+            debug!(
+                "Skipping file {} (size: {} bytes exceeds limit: {} bytes)",
+                path.display(),
+                file_size,
+                max_size
+            );
+            return Err(RulocError::FileTooLarge {
+                path: path.to_path_buf(),
+                size: file_size,
+                limit: max_size,
+            });
+        }
+    }
+
+    let bytes = options.timed(TimingPhase::Parse, || SourceBytes::load(path, options.mmap))?;
+    let (content, replaced_bytes) = options.timed(TimingPhase::Parse, || {
+        decode_source(path, &bytes, options.strict_utf8)
+    })?;
+
+    let edition = options.editions.resolve(path);
+    Ok(analyze_content(
+        path,
+        content,
+        replaced_bytes,
+        edition,
+        options,
+    ))
+}
+
+/// Classifies Rust source held in memory and computes its line statistics.
+///
+/// This is the filesystem-independent core of [`analyze_file`]: it classifies lines as
+/// blank/comment/code, identifies test sections, and computes separate statistics for
+/// total, production, and test code. `path` only labels the result and feeds path-based
+/// rules such as benchmark directories and generated-file globs.
+///
+/// # Arguments
+///
+/// * `path` - Path the content was read from
+/// * `content` - Decoded source content
+/// * `replaced_bytes` - Number of invalid UTF-8 bytes replaced while decoding
+/// * `edition` - Edition to parse the content with
+/// * `options` - Opt-in analyses to apply
+///
+/// # Returns
+///
+/// The statistics of the content
+fn analyze_content(
+    path: &Path,
+    content: Cow<'_, str>,
+    replaced_bytes: usize,
+    edition: Edition,
+    options: &AnalysisOptions,
+) -> FileStats {
+    let total_lines = content.lines().count();
+    if total_lines == 0 {
+        debug!("Empty file: {}", path.display());
+        return FileStats {
+            path: display_path(path),
+            total: LineStats {
+                all_lines: 0,
+                ..Default::default()
+            },
+            replaced_bytes,
+            edition: Some(edition.to_string()),
+            ..Default::default()
+        };
+    }
+
+    let parsed = options.timed(TimingPhase::Parse, || {
+        ParsedSource::parse(&content, edition)
+    });
+    // Instant::now is only called when timing, as it is unavailable on some targets
+    let classify_started = options.timings.as_ref().map(|_| Instant::now());
+    let ParsedSource {
+        content,
+        parse,
+        line_index,
+        line_types,
+        comments,
+        mut is_test_line,
+        is_ignored_line,
+    } = parsed;
+    let syntax_errors = parse.errors();
+    let classification = if replaced_bytes > 0 {
+        Classification::Approximate
+    } else if syntax_errors.is_empty() {
+        Classification::Exact
+    } else {
+        debug!(
+            "File {} has syntax errors; classification is approximate",
+            path.display()
+        );
+        Classification::Approximate
+    };
+    let parse_error_sites = if options.list_parse_errors {
+        syntax_errors
+            .iter()
+            .map(|error| {
+                let line = line_index.line_of(usize::from(error.range().start())) + 1;
+                warn!("{}:{}: syntax error: {}", path.display(), line, error);
+                ParseErrorSite {
+                    line,
+                    message: error.to_string(),
+                }
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let root = parse.syntax_node();
+    let doc_coverage = options.doc_coverage.then(|| compute_doc_coverage(&root));
+    let macro_lines = options
+        .macro_lines
+        .then(|| compute_macro_lines(&root, &line_index, &line_types));
+    let items = options
+        .item_visibility
+        .then(|| compute_item_visibility(&root));
+    let features = if options.feature_breakdown {
+        compute_feature_breakdown(&root, &line_index, &line_types)
+    } else {
+        BTreeMap::new()
+    };
+    let cfg_surface = options
+        .cfg_surface
+        .then(|| compute_cfg_surface(&root, &line_index, &line_types));
+    let line_lengths = options
+        .line_width
+        .map(|width| LineLengths::of(&content, width));
+    let string_lines = options
+        .string_lines
+        .then(|| compute_string_lines(&root, &line_index, &line_types));
+    let content_hashes = options
+        .duplicates
+        .then(|| compute_content_hashes(&content, &root));
+    let tokens = options
+        .size_measures
+        .contains(&SizeMeasure::Tokens)
+        .then(|| compute_token_counts(&root));
+    let nodes = options
+        .size_measures
+        .contains(&SizeMeasure::Nodes)
+        .then(|| root.descendants().count());
+    let statements = options
+        .size_measures
+        .contains(&SizeMeasure::Statements)
+        .then(|| compute_statement_count(&root));
+    let (complexity, complex_functions) = if options.complexity {
+        let (complexity, complex_functions) =
+            compute_function_complexity(&root, &line_index, options.max_complexity);
+        (Some(complexity), complex_functions)
+    } else {
+        (None, Vec::new())
+    };
+    let (max_nesting, nested_functions) = if options.nesting {
+        let (depth, nested_functions) = compute_nesting(&root, &line_index, options.max_nesting);
+        (Some(depth), nested_functions)
+    } else {
+        (None, Vec::new())
+    };
+    let type_complexity = options
+        .type_complexity
+        .then(|| compute_type_complexity(&root));
+    let item_counts = options.item_counts.then(|| compute_item_kind_counts(&root));
+    let allowed_lints = if options.allows {
+        compute_allowed_lints(&root)
+    } else {
+        BTreeMap::new()
+    };
+    let async_metrics = options
+        .async_metrics
+        .then(|| compute_async_metrics(&root, &line_index, &line_types));
+    let doc_detail = options
+        .doc_detail
+        .then(|| compute_doc_detail(&root, &line_index));
+    let age_bucket = options
+        .file_ages
+        .as_ref()
+        .map(|ages| ages.bucket_for(&path.to_string_lossy()).to_string());
+    let test_name_violations = options
+        .test_name_pattern
+        .as_ref()
+        .map(|pattern| audit_test_names(&root, &line_index, pattern))
+        .unwrap_or_default();
+    let includes = if options.follow_includes {
+        find_include_targets(&root, path)
+    } else {
+        Vec::new()
+    };
+    let impls = if options.list_impls {
+        collect_impl_blocks(&root, &line_index)
+    } else {
+        Vec::new()
+    };
+    let long_functions = options
+        .max_fn_lines
+        .map(|max_lines| find_long_functions(&root, &line_index, max_lines))
+        .unwrap_or_default();
+    let (markers, marker_locations) = compute_marker_census(
+        &comments,
+        &content,
+        &line_index,
+        &options.markers,
+        options.list_markers,
+    );
+
+    // Compute production, test, and benchmark stats; benchmark lines take precedence
+    let mut is_bench_line = classify_bench_lines(path, &root, &content);
+    let rule_lines = |category| options.rules.classify_lines(&root, &content, category);
+    for (line, ruled) in rule_lines(RuleCategory::Test).into_iter().enumerate() {
+        is_test_line[line] |= ruled;
+    }
+    if options.test_file_names && is_test_file_name(path) {
+        is_test_line.fill(true);
+    }
+    for (line, ruled) in rule_lines(RuleCategory::Bench).into_iter().enumerate() {
+        is_bench_line[line] |= ruled;
+    }
+    // Lines excluded by `// ruloc:ignore-start` are left out of every count
+    let scoped_stats = |in_scope: &dyn Fn(usize) -> bool| {
+        let scoped_types: Vec<_> = line_types
+            .iter()
+            .enumerate()
+            .filter(|&(line, _)| !is_ignored_line[line] && in_scope(line))
+            .map(|(_, lt)| *lt)
+            .collect();
+        compute_line_stats(&scoped_types, scoped_types.len())
+    };
+    let ignored_lines = is_ignored_line.iter().filter(|&&ignored| ignored).count();
+    let total = if ignored_lines == 0 {
+        compute_line_stats(&line_types, total_lines)
+    } else {
+        scoped_stats(&|_| true)
+    };
+    let (panics, panic_sites) = if options.panics {
+        let (census, sites) = compute_panic_census(
+            &root,
+            &content,
+            &line_index,
+            &is_test_line,
+            &is_bench_line,
+            options.list_panics,
+        );
+        (Some(census), sites)
+    } else {
+        (None, Vec::new())
+    };
+    // Build scripts and proc-macro crates are tooling, so they take the production lines
+    let tooling = options.tooling.resolve(path);
+    let (production, build_script, proc_macro) = ToolingKind::split(
+        tooling,
+        scoped_stats(&|line| !is_bench_line[line] && !is_test_line[line]),
+    );
+    let test = scoped_stats(&|line| !is_bench_line[line] && is_test_line[line]);
+    let bench = scoped_stats(&|line| is_bench_line[line]);
+    let inline_modules = if options.inline_modules {
+        compute_inline_modules(
+            &root,
+            &line_index,
+            &line_types,
+            &is_test_line,
+            &is_bench_line,
+            tooling,
+        )
+    } else {
+        Vec::new()
+    };
+
+    debug!(
+        "File {}: total={}, prod={}, test={}",
+        path.display(),
+        total.all_lines,
+        production.all_lines,
+        test.all_lines
+    );
+
+    let generated = options.generated.as_ref().is_some_and(|rules| {
+        rules.matches(path, &content) || options.rules.matches_any(&root, RuleCategory::Generated)
+    });
+
+    if let (Some(timings), Some(started)) = (&options.timings, classify_started) {
+        timings.add(TimingPhase::Classify, started.elapsed());
+    }
+
+    FileStats {
+        path: display_path(path),
+        total,
+        production,
+        test,
+        bench,
+        build_script,
+        proc_macro,
+        doc_coverage,
+        macro_lines,
+        items,
+        features,
+        cfg_surface,
+        line_lengths,
+        string_lines,
+        tokens,
+        nodes,
+        statements,
+        markers,
+        allowed_lints,
+        long_functions,
+        complexity,
+        complex_functions,
+        max_nesting,
+        nested_functions,
+        item_counts,
+        async_metrics,
+        type_complexity,
+        doc_detail,
+        age_bucket,
+        test_name_violations,
+        includes,
+        impls,
+        marker_locations,
+        panics,
+        panic_sites,
+        classification,
+        parse_errors: syntax_errors.len(),
+        parse_error_sites,
+        generated,
+        replaced_bytes,
+        ignored_lines,
+        edition: Some(edition.to_string()),
+        url: None,
+        notes: Vec::new(),
+        content_hashes,
+        inline_modules,
+    }
+}
+
+/// Rust files reachable from an entry point through `mod` declarations.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct ModuleGraph {
+    /// Reachable files in discovery order, starting with the entry point.
+    files: Vec<PathBuf>,
+
+    /// Rust files below the entry point's directory that no `mod` declaration reaches.
+    orphans: Vec<PathBuf>,
+}
+
+#[cfg(feature = "cli")]
+impl ModuleGraph {
+    /// Returns the orphaned files as display strings, sorted by path.
+    fn orphan_paths(&self) -> Vec<String> {
+        let mut orphans: Vec<String> = self.orphans.iter().map(|path| display_path(path)).collect();
+        orphans.sort();
+        orphans
+    }
+}
+
+/// Extracts the literal value of a `#[path = "..."]` attribute on a module, if present.
+fn module_path_attribute(module: &ast::Module) -> Option<String> {
+    module
+        .attrs()
+        .find(|attr| attr.path().is_some_and(|path| path.to_string() == "path"))?
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+        .find(|token| token.kind() == SyntaxKind::STRING)
+        .and_then(|token| {
+            token
+                .text()
+                .strip_prefix('"')?
+                .strip_suffix('"')
+                .map(str::to_string)
+        })
+}
+
+/// Collects the files declared by out-of-line `mod` items below a syntax node.
+///
+/// Follows rustc's lookup rules: `mod name;` resolves to `name.rs` or `name/mod.rs`
+/// within the module directory, inline modules extend the module directory, and
+/// `#[path]` attributes override the lookup relative to the declaring file's directory
+/// (or the inline module's directory).
+///
+/// # Arguments
+///
+/// * `node` - Source file or inline module item list to scan
+/// * `module_dir` - Directory in which child modules of `node` are looked up
+/// * `file_dir` - Directory containing the declaring file
+/// * `inline` - Whether `node` is nested inside an inline module
+/// * `scope` - Module path of `node` relative to the crate root
+/// * `declared` - Receives `(path, is_mod_rs, module path)` triples for every resolved
+///   module file
+fn collect_module_declarations(
+    node: &SyntaxNode,
+    module_dir: &Path,
+    file_dir: &Path,
+    inline: bool,
+    scope: &[String],
+    declared: &mut Vec<(PathBuf, bool, Vec<String>)>,
+) {
+    for module in node.children().filter_map(ast::Module::cast) {
+        let Some(name) = module.name().map(|name| name.text().to_string()) else {
+            continue;
+        };
+        let path_attribute = module_path_attribute(&module);
+        let module_path = [scope, std::slice::from_ref(&name)].concat();
+
+        if let Some(item_list) = module.item_list() {
+            let nested_dir = module_dir.join(path_attribute.as_deref().unwrap_or(&name));
+            collect_module_declarations(
+                item_list.syntax(),
+                &nested_dir,
+                file_dir,
+                true,
+                &module_path,
+                declared,
+            );
+            continue;
+        }
+
+        if let Some(path_attribute) = path_attribute {
+            let base = if inline { module_dir } else { file_dir };
+            declared.push((base.join(path_attribute), true, module_path));
+            continue;
+        }
+
+        let flat = module_dir.join(format!("{}.rs", name));
+        let nested = module_dir.join(&name).join("mod.rs");
+        if flat.is_file() {
+            declared.push((flat, false, module_path));
+        } else if nested.is_file() {
+            declared.push((nested, true, module_path));
+        } else {
+            warn!(
+                "Module '{}' declared in {} has no source file",
+                name,
+                file_dir.display()
+            );
+        }
+    }
+}
+
+/// Follows `mod` declarations from a crate root, collecting every reachable file.
+///
+/// # Arguments
+///
+/// * `entry` - Crate root such as `src/lib.rs` or `src/main.rs`
+/// * `seen` - Canonical paths already reached; updated in place so that several roots
+///   can share one traversal state
+///
+/// # Returns
+///
+/// The newly reached files in discovery order, starting with the entry point
+///
+/// # Errors
+///
+/// Returns an error if the entry point cannot be read
+fn follow_module_tree(
+    entry: &Path,
+    seen: &mut HashSet<PathBuf>,
+) -> Result<Vec<PathBuf>, RulocError> {
+    Ok(walk_module_tree(entry, seen)?
+        .into_iter()
+        .map(|(file, _)| file)
+        .collect())
+}
+
+/// Follows `mod` declarations from a crate root, recording each file's module path.
+///
+/// # Arguments
+///
+/// * `entry` - Crate root such as `src/lib.rs` or `src/main.rs`
+/// * `seen` - Canonical paths already reached; updated in place
+///
+/// # Returns
+///
+/// The newly reached files in discovery order, each with its module path segments
+/// relative to the crate root (empty for the entry point)
+///
+/// # Errors
+///
+/// Returns an error if the entry point cannot be read
+fn walk_module_tree(
+    entry: &Path,
+    seen: &mut HashSet<PathBuf>,
+) -> Result<Vec<(PathBuf, Vec<String>)>, RulocError> {
+    let mut files = Vec::new();
+    let mut queue = std::collections::VecDeque::from([(entry.to_path_buf(), true, Vec::new())]);
+    let mut is_entry = true;
+
+    while let Some((file, is_mod_rs, module_path)) = queue.pop_front() {
+        let first = std::mem::replace(&mut is_entry, false);
+        if !seen.insert(fs::canonicalize(&file).unwrap_or_else(|_| file.clone())) {
+            continue;
+        }
+
+        let content = match fs::read_to_string(&file) {
+            Ok(content) => content,
+            Err(e) if first => {
+                return Err(RulocError::io(format!(
+                    "Failed to read entry point '{}'",
+                    file.display()
+                ))(e));
+            }
+            Err(e) => {
+                warn!("Skipping module file {}: {}", file.display(), e);
+                continue;
+            }
+        };
+
+        let file_dir = file.parent().unwrap_or(Path::new(".")).to_path_buf();
+        let module_dir = match (is_mod_rs, file.file_stem()) {
+            (false, Some(stem)) => file_dir.join(stem),
+            _ => file_dir.clone(),
+        };
+        let root = SourceFile::parse(&content, Edition::CURRENT).syntax_node();
+        let mut declared = Vec::new();
+        collect_module_declarations(
+            &root,
+            &module_dir,
+            &file_dir,
+            false,
+            &module_path,
+            &mut declared,
+        );
+
+        queue.extend(declared);
+        files.push((file, module_path));
+    }
+
+    Ok(files)
+}
+
+/// Lists the Rust files below a directory that are not in a reachable set.
+///
+/// Cargo `target` directories are skipped since they only hold build artifacts.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to scan
+/// * `reachable` - Canonical paths of reachable files
+/// * `symlinks` - Traversal policy for symbolic links
+///
+/// # Returns
+///
+/// Unreachable Rust files in traversal order
+#[cfg(feature = "cli")]
+fn unreachable_rust_files(
+    dir: &Path,
+    reachable: &HashSet<PathBuf>,
+    symlinks: SymlinkPolicy,
+) -> Vec<PathBuf> {
+    walk_workspace(dir, symlinks)
+        .filter_map(walk_entry_ok)
+        .filter(|e| e.path().is_file())
+        .filter(|e| e.path().extension().and_then(|s| s.to_str()) == Some("rs"))
+        .map(walkdir::DirEntry::into_path)
+        .filter(|path| {
+            !reachable.contains(&fs::canonicalize(path).unwrap_or_else(|_| path.clone()))
+        })
+        .collect()
+}
+
+/// Discovers the files of a crate by following `mod` declarations from an entry point.
+///
+/// Rust files below the entry point's directory that are never reached are reported as
+/// orphans rather than analyzed, so stale or unused sources do not pollute the statistics.
+///
+/// # Arguments
+///
+/// * `entry` - Crate root such as `src/lib.rs` or `src/main.rs`
+/// * `symlinks` - Traversal policy used while searching for orphans
+///
+/// # Returns
+///
+/// The reachable files and the orphaned ones
+///
+/// # Errors
+///
+/// Returns an error if the entry point cannot be read
+#[cfg(feature = "cli")]
+fn discover_module_graph(entry: &Path, symlinks: SymlinkPolicy) -> Result<ModuleGraph, RulocError> {
+    let mut seen = HashSet::new();
+    let files = follow_module_tree(entry, &mut seen)?;
+    let orphans = unreachable_rust_files(entry.parent().unwrap_or(Path::new(".")), &seen, symlinks);
+
+    Ok(ModuleGraph { files, orphans })
+}
+
+/// Lists the crate roots (targets) declared by a Cargo manifest.
+///
+/// Combines Cargo's target auto-discovery conventions (`src/lib.rs`, `src/main.rs`,
+/// `src/bin`, `examples`, `tests`, `benches`, and `build.rs`) with explicit `path`
+/// entries from `[lib]`, `[[bin]]`, `[[example]]`, `[[test]]`, `[[bench]]`, and
+/// `package.build`.
+///
+/// # Arguments
+///
+/// * `manifest_dir` - Directory containing the manifest
+/// * `manifest` - Content of `Cargo.toml`
+///
+/// # Returns
+///
+/// Existing crate root files
+#[cfg(feature = "cli")]
+fn manifest_crate_roots(manifest_dir: &Path, manifest: &str) -> Vec<PathBuf> {
+    let table: toml::Table = toml::from_str(manifest).unwrap_or_else(|e| {
+        warn!(
+            "Failed to parse {}: {}",
+            manifest_dir.join("Cargo.toml").display(),
+            e
+        );
+        toml::Table::new()
+    });
+
+    let mut roots = vec![
+        manifest_dir.join("src").join("lib.rs"),
+        manifest_dir.join("src").join("main.rs"),
+        manifest_dir.join("build.rs"),
+    ];
+
+    for target_dir in ["src/bin", "examples", "tests", "benches"] {
+        let Ok(entries) = fs::read_dir(manifest_dir.join(target_dir)) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+            if entry.is_dir() {
+                roots.push(entry.join("main.rs"));
+            } else if entry.extension().and_then(|ext| ext.to_str()) == Some("rs") {
+                roots.push(entry);
+            }
+        }
+    }
+
+    let explicit_path = |value: &toml::Value| {
+        value
+            .get("path")
+            .and_then(toml::Value::as_str)
+            .map(|path| manifest_dir.join(path))
+    };
+    roots.extend(table.get("lib").and_then(explicit_path));
+    for section in ["bin", "example", "test", "bench"] {
+        if let Some(targets) = table.get(section).and_then(toml::Value::as_array) {
+            roots.extend(targets.iter().filter_map(explicit_path));
+        }
+    }
+    if let Some(build) = table
+        .get("package")
+        .and_then(|package| package.get("build"))
+        .and_then(toml::Value::as_str)
+    {
+        roots.push(manifest_dir.join(build));
+    }
+
+    roots.retain(|root| root.is_file());
+    roots
+}
+
+/// Finds Rust files that no crate root in a workspace reaches through `mod` declarations.
+///
+/// Every `Cargo.toml` below the directory contributes its crate roots; files not
+/// reachable from any of them are dead, typically abandoned modules.
+///
+/// # Arguments
+///
+/// * `dir` - Workspace directory to scan
+/// * `symlinks` - Traversal policy for symbolic links
+///
+/// # Returns
+///
+/// Dead files as display strings, sorted by path
+#[cfg(feature = "cli")]
+fn find_dead_files(dir: &Path, symlinks: SymlinkPolicy) -> Vec<String> {
+    let mut reachable = HashSet::new();
+
+    let manifests = walk_workspace(dir, symlinks)
+        .filter_map(walk_entry_ok)
+        .filter(|e| e.file_name() == "Cargo.toml" && e.path().is_file());
+
+    for manifest in manifests {
+        let manifest_dir = manifest.path().parent().unwrap_or(Path::new("."));
+        let content = match fs::read_to_string(manifest.path()) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Failed to read {}: {}", manifest.path().display(), e);
+                continue;
+            }
+        };
+
+        for root in manifest_crate_roots(manifest_dir, &content) {
+            if let Err(e) = follow_module_tree(&root, &mut reachable) {
+                warn!("{}", e);
+            }
+        }
+    }
+
+    ModuleGraph {
+        files: Vec::new(),
+        orphans: unreachable_rust_files(dir, &reachable, symlinks),
+    }
+    .orphan_paths()
+}
+
+/// Counts the non-blank lines and words of a markdown document.
+///
+/// # Arguments
+///
+/// * `content` - Markdown source
+///
+/// # Returns
+///
+/// Counts for a single file
+#[cfg(feature = "cli")]
+fn markdown_counts(content: &str) -> MarkdownDocs {
+    MarkdownDocs {
+        files: 1,
+        lines: content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .count(),
+        words: content.split_whitespace().count(),
+    }
+}
+
+/// Lists the markdown documentation files belonging to a crate.
+///
+/// # Arguments
+///
+/// * `crate_dir` - Directory containing the crate's `Cargo.toml`
+/// * `manifest` - Content of `Cargo.toml`
+/// * `symlinks` - How symbolic links below `doc/` and `docs/` are treated
+///
+/// # Returns
+///
+/// The README, if present, followed by the `.md` files under `doc/` and `docs/`
+#[cfg(feature = "cli")]
+fn crate_markdown_files(crate_dir: &Path, manifest: &str, symlinks: SymlinkPolicy) -> Vec<PathBuf> {
+    let readme = toml::from_str::<toml::Table>(manifest)
+        .ok()
+        .and_then(|table| {
+            table
+                .get("package")?
+                .get("readme")?
+                .as_str()
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| "README.md".to_string());
+
+    let mut files: Vec<PathBuf> = Some(crate_dir.join(readme))
+        .filter(|path| path.is_file())
+        .into_iter()
+        .collect();
+
+    for docs_dir in ["doc", "docs"] {
+        let docs_dir = crate_dir.join(docs_dir);
+        if !docs_dir.is_dir() {
+            continue;
+        }
+        let mut markdown: Vec<PathBuf> = walk_dir(&docs_dir, symlinks)
+            .filter_map(walk_entry_ok)
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.path().extension().and_then(|ext| ext.to_str()) == Some("md"))
+            .map(|e| e.path().to_path_buf())
+            .collect();
+        markdown.sort();
+        files.extend(markdown);
+    }
+
+    files
+}
+
+/// Counts the markdown documentation of every crate below a directory.
+///
+/// Each `Cargo.toml` found outside `target/` directories marks a crate whose README and
+/// `doc/`/`docs/` markdown is counted. Unreadable documents are skipped with a warning.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to search for crates
+/// * `symlinks` - How symbolic links are treated during the search
+///
+/// # Returns
+///
+/// Markdown counts keyed by crate directory
+#[cfg(feature = "cli")]
+fn count_markdown_docs(dir: &Path, symlinks: SymlinkPolicy) -> BTreeMap<String, MarkdownDocs> {
+    let manifests = walk_workspace(dir, symlinks)
+        .filter_map(walk_entry_ok)
+        .filter(|e| e.file_name() == "Cargo.toml" && e.path().is_file());
+
+    let mut crates = BTreeMap::new();
+    for manifest in manifests {
+        let crate_dir = manifest.path().parent().unwrap_or(Path::new("."));
+        let content = match fs::read_to_string(manifest.path()) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("Failed to read {}: {}", manifest.path().display(), e);
+                continue;
+            }
+        };
+
+        let mut docs = MarkdownDocs::default();
+        for path in crate_markdown_files(crate_dir, &content, symlinks) {
+            match fs::read_to_string(&path) {
+                Ok(markdown) => docs.add(&markdown_counts(&markdown)),
+                Err(e) => warn!("Failed to read {}: {}", path.display(), e),
+            }
+        }
+        crates.insert(crate_dir.display().to_string(), docs);
+    }
+
+    crates
+}
+
+/// Comment syntax of a language counted with `--all-languages`.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PlainLanguage {
+    /// Name the language is reported under.
+    name: &'static str,
+
+    /// File extensions identifying the language, without the leading `.`.
+    extensions: &'static [&'static str],
+
+    /// Prefix starting a comment that runs to the end of the line.
+    line_comment: Option<&'static str>,
+
+    /// Delimiters of comments that may span several lines.
+    block_comment: Option<(&'static str, &'static str)>,
+}
+
+/// Languages besides Rust counted with `--all-languages`.
+#[cfg(feature = "cli")]
+const PLAIN_LANGUAGES: [PlainLanguage; 4] = [
+    PlainLanguage {
+        name: "Markdown",
+        extensions: &["md", "markdown"],
+        line_comment: None,
+        block_comment: Some(("<!--", "-->")),
+    },
+    PlainLanguage {
+        name: "Shell",
+        extensions: &["sh", "bash", "zsh"],
+        line_comment: Some("#"),
+        block_comment: None,
+    },
+    PlainLanguage {
+        name: "TOML",
+        extensions: &["toml"],
+        line_comment: Some("#"),
+        block_comment: None,
+    },
+    PlainLanguage {
+        name: "YAML",
+        extensions: &["yml", "yaml"],
+        line_comment: Some("#"),
+        block_comment: None,
+    },
+];
+
+#[cfg(feature = "cli")]
+impl PlainLanguage {
+    /// Finds the language a file belongs to by its extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path of the file
+    ///
+    /// # Returns
+    ///
+    /// The language, or `None` if the file is not of a counted language
+    fn of(path: &Path) -> Option<&'static PlainLanguage> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        PLAIN_LANGUAGES
+            .iter()
+            .find(|language| language.extensions.contains(&extension.as_str()))
+    }
+
+    /// Counts the blank, comment, and code lines of a file in this language.
+    ///
+    /// A line is a comment when it starts with a line comment prefix or lies within a block
+    /// comment; comment markers inside strings are not recognized.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - File content
+    ///
+    /// # Returns
+    ///
+    /// Counts for a single file
+    fn count_lines(&self, content: &str) -> LanguageLines {
+        let mut lines = LanguageLines {
+            files: 1,
+            ..Default::default()
+        };
+        let mut in_block = false;
+        for line in content.lines().map(str::trim) {
+            lines.all_lines += 1;
+            if line.is_empty() {
+                lines.blank_lines += 1;
+                continue;
+            }
+            match self.block_comment {
+                Some((_, end)) if in_block => in_block = !line.contains(end),
+                Some((start, end)) if line.starts_with(start) => {
+                    in_block = !line[start.len()..].contains(end);
+                }
+                _ if self
+                    .line_comment
+                    .is_some_and(|prefix| line.starts_with(prefix)) => {}
+                _ => {
+                    lines.code_lines += 1;
+                    continue;
+                }
+            }
+            lines.comment_lines += 1;
+        }
+        lines
+    }
+}
+
+/// Counts the lines of every TOML, Markdown, YAML, and shell file below a directory.
+///
+/// `target/` directories are skipped. Unreadable files are skipped with a warning.
+///
+/// # Arguments
+///
+/// * `dir` - Directory to search
+/// * `symlinks` - How symbolic links are treated during the search
+///
+/// # Returns
+///
+/// Line counts keyed by language name, for the languages with at least one file
+#[cfg(feature = "cli")]
+fn count_other_languages(dir: &Path, symlinks: SymlinkPolicy) -> BTreeMap<String, LanguageLines> {
+    let mut languages: BTreeMap<String, LanguageLines> = BTreeMap::new();
+    for entry in walk_workspace(dir, symlinks)
+        .filter_map(walk_entry_ok)
+        .filter(|e| e.file_type().is_file())
+    {
+        let Some(language) = PlainLanguage::of(entry.path()) else {
+            continue;
+        };
+        match fs::read(entry.path()) {
+            Ok(bytes) => languages
+                .entry(language.name.to_string())
+                .or_default()
+                .add(&language.count_lines(&String::from_utf8_lossy(&bytes))),
+            Err(e) => warn!("Failed to read {}: {}", entry.path().display(), e),
+        }
+    }
+    languages
+}
+
+/// Analyzes all Rust files in a directory recursively using parallel directory traversal.
+///
+/// Walks the directory tree, identifies all `.rs` files, and analyzes each one in parallel
+/// using rayon. Symbolic links are followed per the options' [`SymlinkPolicy`], with
+/// cycles skipped and links leaving `dir` refused by default. Files exceeding the size limit
+/// are skipped. Shows a progress bar during processing. Results are added to the provided
+/// accumulator, enabling memory-efficient processing of large codebases.
+///
+/// # Arguments
+///
+/// * `dir` - Path to the directory to analyze
+/// * `options` - Size limit, thread count, and opt-in analyses applied to every file
+/// * `accumulator` - Accumulator to collect file statistics
+///
+/// # Returns
+///
+/// The files that were skipped, sorted by path, or a [`RulocError`] if no Rust files are
+/// found or analysis fails
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - A thread pool with the requested number of jobs cannot be created
+/// - No Rust files are found in the directory
+/// - Accumulator operations fail
+#[cfg(feature = "cli")]
+fn analyze_directory<A: StatsAccumulator>(
+    dir: &Path,
+    options: &AnalysisOptions,
+    accumulator: &mut A,
+) -> Result<Vec<SkippedFile>, RulocError> {
+    let mut paths = walk_dir(dir, options.symlinks)
+        .filter_map(walk_entry_ok)
+        .filter(|e| e.path().is_file())
+        .filter(|e| options.extensions.matches(e.path()))
+        .map(walkdir::DirEntry::into_path);
+    let paths = std::iter::from_fn(|| options.timed(TimingPhase::Walk, || paths.next()));
+
+    analyze_paths(paths, dir, options, accumulator)
+}
+
+/// Layout of the directory analysis progress bar: elapsed time, bar, files done out of
+/// the total, throughput, estimated time remaining, and the file most recently started.
+#[cfg(feature = "cli")]
+const PROGRESS_TEMPLATE: &str =
+    "[{elapsed_precise}] {bar:30.cyan/blue} {pos}/{len} files ({per_sec}, ETA {eta}) {wide_msg}";
+
+/// Progress bar currently drawn on the terminal, cleared while log records are written.
+#[cfg(feature = "cli")]
+static ACTIVE_PROGRESS: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+/// Registration of a progress bar in [`ACTIVE_PROGRESS`], removed again on drop.
+#[cfg(feature = "cli")]
+struct ActiveProgress;
+
+#[cfg(feature = "cli")]
+impl ActiveProgress {
+    /// Makes log output suspend a progress bar while it is drawn.
+    ///
+    /// # Arguments
+    ///
+    /// * `progress` - The bar; hidden bars are not registered
+    ///
+    /// # Returns
+    ///
+    /// A guard unregistering the bar when dropped
+    fn register(progress: &ProgressBar) -> Self {
+        if !progress.is_hidden() {
+            *ACTIVE_PROGRESS.lock().unwrap() = Some(progress.clone());
+        }
+        ActiveProgress
+    }
+}
+
+#[cfg(feature = "cli")]
+impl Drop for ActiveProgress {
+    fn drop(&mut self) {
+        ACTIVE_PROGRESS.lock().unwrap().take();
+    }
+}
+
+/// Safety limits on the files a run discovers, set with `--max-files` and
+/// `--max-total-size`.
+///
+/// Unlike `--max-file-size`, which skips individual files, exceeding one of these limits
+/// stops discovery and fails the run, since it usually means ruloc was pointed at the
+/// wrong directory.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, Default)]
+struct DiscoveryLimits {
+    /// Maximum number of files.
+    max_files: Option<usize>,
+
+    /// Maximum total size of the files in bytes.
+    max_total_size: Option<u64>,
+}
+
+#[cfg(feature = "cli")]
+impl DiscoveryLimits {
+    /// Checks the files discovered so far against the limits.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - Number of files discovered so far
+    /// * `bytes` - Total size of those files
+    /// * `scope` - Directory or entry point the files are discovered from
+    ///
+    /// # Returns
+    ///
+    /// `Ok(())` while within the limits
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RulocError::Analysis`] naming the exceeded limit
+    fn check(&self, files: usize, bytes: u64, scope: &Path) -> Result<(), RulocError> {
+        if let Some(max_files) = self.max_files.filter(|max_files| files > *max_files) {
+            return Err(RulocError::Analysis(format!(
+                "More than {} files found in {}; stopped by --max-files",
+                max_files,
+                scope.display()
+            )));
+        }
+        if let Some(max_size) = self.max_total_size.filter(|max_size| bytes > *max_size) {
+            return Err(RulocError::Analysis(format!(
+                "Files in {} exceed {} bytes in total; stopped by --max-total-size",
+                scope.display(),
+                max_size
+            )));
+        }
+        Ok(())
+    }
+
+    /// Whether the total size of the files has to be measured.
+    fn measures_size(&self) -> bool {
+        self.max_total_size.is_some()
+    }
+}
+
+/// Size of the random subset analyzed with `--sample`.
+#[cfg(feature = "cli")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SampleSize {
+    /// Percentage of the discovered files.
+    Percent(f64),
+
+    /// Number of files.
+    Files(usize),
+}
+
+#[cfg(feature = "cli")]
+impl SampleSize {
+    /// Resolves the number of files to sample.
+    ///
+    /// # Arguments
+    ///
+    /// * `population` - Number of discovered files
+    ///
+    /// # Returns
+    ///
+    /// The number of files to analyze, at least one unless nothing was discovered
+    fn files(self, population: usize) -> usize {
+        match self {
+            SampleSize::Percent(percent) => {
+                ((population as f64 * percent / 100.0).ceil() as usize).min(population)
+            }
+            SampleSize::Files(files) => files.min(population),
+        }
+    }
+}
+
+/// Confidence level of the intervals reported with `--sample`, in percent.
+#[cfg(feature = "cli")]
+const SAMPLE_CONFIDENCE: u8 = 95;
+
+/// Standard normal quantile matching [`SAMPLE_CONFIDENCE`].
+#[cfg(feature = "cli")]
+const SAMPLE_Z_SCORE: f64 = 1.96;
+
+/// Metric extrapolated with `--sample`: its name and the per-file value it sums.
+#[cfg(feature = "cli")]
+type SampleMetric = (&'static str, fn(&FileStats) -> usize);
+
+/// Metrics extrapolated with `--sample`.
+#[cfg(feature = "cli")]
+const SAMPLE_METRICS: [SampleMetric; 7] = [
+    ("all-lines", |file| file.total.all_lines),
+    ("code-lines", |file| file.total.code_lines),
+    ("comment-lines", |file| file.total.comment_lines),
+    ("rustdoc-lines", |file| file.total.rustdoc_lines),
+    ("blank-lines", |file| file.total.blank_lines),
+    ("production-code-lines", |file| file.production.code_lines),
+    ("test-code-lines", |file| file.test.code_lines),
+];
+
+/// Selects the random subset of `--sample` and extrapolates its totals.
+///
+/// Shared by all worker threads; the discovered files of every input are counted, so
+/// several inputs extrapolate to their combined population.
+#[cfg(feature = "cli")]
+#[derive(Debug)]
+struct Sampling {
+    /// Size of the subset drawn from each input.
+    size: SampleSize,
+
+    /// Seed of the random selection.
+    seed: u64,
+
+    /// Number of files discovered so far.
+    population: AtomicUsize,
+}
+
+#[cfg(feature = "cli")]
+impl Sampling {
+    /// Creates a sampler drawing subsets of a given size.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - Size of the subset drawn from each input
+    /// * `seed` - Seed of the random selection
+    fn new(size: SampleSize, seed: u64) -> Self {
+        Self {
+            size,
+            seed,
+            population: AtomicUsize::new(0),
+        }
+    }
+
+    /// Draws the random subset of the discovered files.
+    ///
+    /// The files are sorted first, so the same seed selects the same files regardless
+    /// of the order the file system lists them in.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - All discovered files
+    ///
+    /// # Returns
+    ///
+    /// The sampled files, sorted by path
+    fn select(&self, mut paths: Vec<PathBuf>) -> Vec<PathBuf> {
+        self.population.fetch_add(paths.len(), Ordering::Relaxed);
+        paths.sort();
+
+        // Partial Fisher-Yates shuffle: the first `count` slots end up uniformly sampled
+        let count = self.size.files(paths.len());
+        let mut state = self.seed;
+        for index in 0..count {
+            let remaining = (paths.len() - index) as u64;
+            let pick = index + (split_mix64(&mut state) % remaining) as usize;
+            paths.swap(index, pick);
+        }
+        paths.truncate(count);
+        paths.sort_by(|a, b| compare_paths(a, b));
+        paths
+    }
+
+    /// Extrapolates the totals of all discovered files from the analyzed ones.
+    ///
+    /// Each total is the sample mean scaled to the population, and its margin is the
+    /// normal-approximation interval with the finite population correction, so a sample
+    /// covering every file has a margin of zero.
+    ///
+    /// # Arguments
+    ///
+    /// * `files` - Statistics of the analyzed files
+    ///
+    /// # Returns
+    ///
+    /// The estimated totals
+    fn estimate(&self, files: impl Iterator<Item = FileStats>) -> SampleEstimate {
+        let mut sums = [(0.0f64, 0.0f64); SAMPLE_METRICS.len()];
+        let mut sampled = 0;
+        for file in files {
+            sampled += 1;
+            for ((_, value), (sum, squares)) in SAMPLE_METRICS.iter().zip(&mut sums) {
+                let value = value(&file) as f64;
+                *sum += value;
+                *squares += value * value;
+            }
+        }
+
+        let population = self.population.load(Ordering::Relaxed).max(sampled);
+        let (n, big_n) = (sampled as f64, population as f64);
+        let estimates = SAMPLE_METRICS
+            .iter()
+            .zip(sums)
+            .map(|((metric, _), (sum, squares))| {
+                let mean = if sampled == 0 { 0.0 } else { sum / n };
+                let margin = if sampled == population {
+                    Some(0)
+                } else if sampled < 2 {
+                    None
+                } else {
+                    let variance = ((squares - n * mean * mean) / (n - 1.0)).max(0.0);
+                    let error = big_n * ((1.0 - n / big_n) * variance / n).sqrt();
+                    Some((SAMPLE_Z_SCORE * error).round() as u64)
+                };
+                EstimatedTotal {
+                    metric: metric.to_string(),
+                    value: (mean * big_n).round() as u64,
+                    margin,
+                }
+            })
+            .collect();
+
+        SampleEstimate {
+            population_files: population,
+            sampled_files: sampled,
+            seed: self.seed,
+            confidence: SAMPLE_CONFIDENCE,
+            estimates,
+        }
+    }
+}
+
+/// Advances a SplitMix64 generator and returns its next output.
+///
+/// # Arguments
+///
+/// * `state` - Generator state, updated in place
+///
+/// # Returns
+///
+/// The next pseudo-random number
+#[cfg(feature = "cli")]
+fn split_mix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Analyzed files buffered between the analysis workers and the accumulator writer.
+///
+/// Bounds memory when the accumulator (e.g., a file-backed one on slow storage) falls
+/// behind: workers block once this many results are waiting.
+#[cfg(feature = "cli")]
+const RESULT_QUEUE_DEPTH: usize = 1024;
+
+/// Analyzes a stream of Rust files in parallel, adding their statistics to an accumulator.
+///
+/// Shared by directory traversal and module-graph discovery. Files exceeding the size
+/// limit are skipped, and a progress bar is shown when attached to a terminal.
+/// Results are added by one dedicated writer thread, so the accumulator needs no lock.
+/// The writer adds files in the order of `paths`, each followed by the sources it
+/// includes, unless [`AnalysisOptions::completion_order`] asks for them as they finish;
+/// only results finishing ahead of a slower earlier file are held back meanwhile.
+///
+/// # Arguments
+///
+/// * `paths` - Files to analyze
+/// * `scope` - Directory or entry point the files were discovered from, used in messages
+/// * `options` - Size limit, sampling, thread count, and opt-in analyses applied to every
+///   file
+/// * `accumulator` - Accumulator to collect file statistics
+///
+/// # Returns
+///
+/// The files that were skipped, sorted by path
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - A thread pool with the requested number of jobs cannot be created
+/// - More files are provided than `--max-files` or `--max-total-size` allow
+/// - No Rust files are provided
+/// - No file could be analyzed
+#[cfg(feature = "cli")]
+fn analyze_paths<A: StatsAccumulator, I: Iterator<Item = PathBuf> + Send>(
+    paths: I,
+    scope: &Path,
+    options: &AnalysisOptions,
+    accumulator: &mut A,
+) -> Result<Vec<SkippedFile>, RulocError> {
+    // Run inside a dedicated pool when the thread count is capped
+    if let Some(jobs) = options.jobs {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs.get())
+            .build()
+            .map_err(|e| {
+                RulocError::Config(format!(
+                    "Failed to create thread pool with {} jobs: {}",
+                    jobs, e
+                ))
+            })?;
+        let options = AnalysisOptions {
+            jobs: None,
+            ..options.clone()
+        };
+        return pool.install(|| analyze_paths(paths, scope, &options, accumulator));
+    }
+
+    // Stop discovering files once --max-files or --max-total-size is exceeded
+    let limit_exceeded = Mutex::new(None);
+    let (mut discovered_files, mut discovered_bytes) = (0, 0);
+    let paths = paths.take_while(|path| {
+        discovered_files += 1;
+        if options.limits.measures_size() {
+            discovered_bytes += fs::metadata(path).map_or(0, |metadata| metadata.len());
+        }
+        match options
+            .limits
+            .check(discovered_files, discovered_bytes, scope)
+        {
+            Ok(()) => true,
+            Err(error) => {
+                *limit_exceeded.lock().unwrap() = Some(error);
+                false
+            }
+        }
+    });
+
+    // Draw the random subset of --sample from the discovered files
+    let paths: Box<dyn Iterator<Item = PathBuf> + Send + '_> = match &options.sampling {
+        Some(sampling) => Box::new(sampling.select(paths.collect()).into_iter()),
+        None => Box::new(paths),
+    };
+
+    // Setup the progress bar only if we're in a terminal and it was not turned off. The
+    // paths are listed up front then, so the bar knows its length and can estimate the ETA
+    let show_progress = options.progress && std::io::stdout().is_terminal();
+    let (paths, progress): (Box<dyn Iterator<Item = PathBuf> + Send + '_>, _) = if show_progress {
+        let paths: Vec<PathBuf> = paths.collect();
+        let bar = ProgressBar::new(paths.len() as u64);
+        bar.set_style(
+            ProgressStyle::default_bar()
+                .template(PROGRESS_TEMPLATE)
+                .unwrap()
+                .progress_chars("=> "),
+        );
+        bar.enable_steady_tick(std::time::Duration::from_millis(100));
+        (Box::new(paths.into_iter()), bar)
+    } else {
+        (Box::new(paths), ProgressBar::hidden())
+    };
+    let _progress_guard = ActiveProgress::register(&progress);
+
+    // Atomic counters
+    let skipped_count = Arc::new(AtomicUsize::new(0));
+    let analyzed_count = Arc::new(AtomicUsize::new(0));
+    let total_files_found = Arc::new(AtomicUsize::new(0));
+
+    // Files excluded from the analysis, reported alongside the statistics
+    let skipped = Mutex::new(Vec::new());
+    let skip = |path: &Path, reason: String, category: SkipCategory| {
+        if category == SkipCategory::Oversized {
+            debug!("Skipped: {}", reason);
+        } else if !options.quiet_errors {
+            // Printed while the bar is cleared, and also when the bar is hidden
+            progress.suspend(|| error!("{}", reason));
+        }
+        skipped.lock().unwrap().push(SkippedFile {
+            path: display_path(path),
+            reason,
+            category,
+        });
+    };
+
+    // Workers hand results to a single writer thread through a bounded channel, so they
+    // never contend on the accumulator and at most RESULT_QUEUE_DEPTH results wait in memory.
+    // Every discovered file sends exactly one message tagged with its position, `None` if
+    // it failed, so the writer can add the files in discovery order
+    let (sender, receiver) = mpsc::sync_channel::<(usize, Option<FileStats>)>(RESULT_QUEUE_DEPTH);
+    let write_results = || {
+        let add = |accumulator: &mut A, stats: &FileStats| {
+            if let Err(e) = options.timed(TimingPhase::Aggregate, || accumulator.add_file(stats)) {
+                let reason = format!("Failed to add file stats: {}", e);
+                skip(Path::new(&stats.path), reason, SkipCategory::Accumulator);
+            } else {
+                analyzed_count.fetch_add(1, Ordering::Relaxed);
+            }
+        };
+
+        // Included Rust sources the walk would miss are analyzed right after their first
+        // includer, once each
+        let mut followed_includes = HashSet::new();
+        let mut write = |stats: FileStats| {
+            add(accumulator, &stats);
+            for include in uncounted_rust_includes(&stats, &options.extensions) {
+                let key = fs::canonicalize(&include).unwrap_or_else(|_| include.clone());
+                if !followed_includes.insert(key) {
+                    continue;
+                }
+                match analyze_file(&include, options) {
+                    Ok(included) => add(accumulator, &included),
+                    Err(e) => skip(&include, e.to_string(), SkipCategory::of(&e)),
+                }
+            }
+        };
+
+        // Results finishing ahead of an earlier file wait here until it arrives
+        let mut pending = BTreeMap::new();
+        let mut next = 0;
+        for (position, analyzed) in receiver {
+            if options.completion_order {
+                if let Some(stats) = analyzed {
+                    write(stats);
+                }
+                continue;
+            }
+            pending.insert(position, analyzed);
+            while let Some(analyzed) = pending.remove(&next) {
+                next += 1;
+                if let Some(stats) = analyzed {
+                    write(stats);
+                }
+            }
+        }
+    };
+
+    // Stream and analyze files in parallel without collecting
+    std::thread::scope(|threads| {
+        threads.spawn(write_results);
+        let paths = paths.enumerate().par_bridge();
+        paths.for_each_with(sender, |sender, (position, path)| {
+            let path = path.as_path();
+            total_files_found.fetch_add(1, Ordering::Relaxed);
+            if show_progress {
+                progress.set_message(display_path(path));
+            }
+
+            let started = Instant::now();
+            let result = analyze_file(path, options);
+            if let Some(timings) = &options.timings {
+                timings.record_file(path, started.elapsed());
+            }
+            progress.inc(1);
+
+            let analyzed = match result {
+                Ok(stats) => Some(stats),
+                Err(e) => {
+                    let category = SkipCategory::of(&e);
+                    if category == SkipCategory::Oversized {
+                        skipped_count.fetch_add(1, Ordering::Relaxed);
+                    }
+                    skip(path, e.to_string(), category);
+                    None
+                }
+            };
+
+            // Sending only fails once the writer has panicked, which the scope re-raises
+            let _ = sender.send((position, analyzed));
+        });
+    });
+
+    progress.finish_with_message("analysis complete");
+    if let Some(error) = limit_exceeded.into_inner().unwrap() {
+        return Err(error);
+    }
+
+    let final_analyzed = analyzed_count.load(Ordering::Relaxed);
+    let final_skipped = skipped_count.load(Ordering::Relaxed);
+    let final_total = total_files_found.load(Ordering::Relaxed);
+
+    if final_total == 0 {
+        return Err(RulocError::Analysis(format!(
+            "No Rust files found in {}",
+            scope.display()
+        )));
+    }
+
+    debug!(
+        "Analyzed {} files in {} (skipped {} files exceeding size limit)",
+        final_analyzed,
+        scope.display(),
+        final_skipped
+    );
+
+    if final_analyzed == 0 {
+        return Err(RulocError::Analysis(format!(
+            "No Rust files could be analyzed in {}",
+            scope.display()
+        )));
+    }
+
+    let mut skipped = skipped.into_inner().unwrap();
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(skipped)
+}
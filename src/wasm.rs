@@ -0,0 +1,34 @@
+use super::*;
+use wasm_bindgen::prelude::*;
+
+/// Classifies pasted Rust source and returns its statistics as JSON.
+///
+/// # Arguments
+///
+/// * `content` - Rust source code
+/// * `edition` - Edition to parse with, such as `"2021"`; the latest when omitted
+///
+/// # Returns
+///
+/// The [`FileStats`] of the source, serialized as in `--out-json` reports
+///
+/// # Errors
+///
+/// Returns an error if the edition is unknown
+#[wasm_bindgen(js_name = analyzeSource)]
+pub fn analyze_source(content: &str, edition: Option<String>) -> Result<String, JsError> {
+    let edition = match edition {
+        Some(edition) => edition
+            .parse()
+            .map_err(|_| JsError::new(&format!("Unknown edition '{}'", edition)))?,
+        None => Edition::CURRENT,
+    };
+    let stats = analyze_content(
+        Path::new("input.rs"),
+        Cow::Borrowed(content),
+        0,
+        edition,
+        &AnalysisOptions::default(),
+    );
+    serde_json::to_string(&stats).map_err(|e| JsError::new(&e.to_string()))
+}
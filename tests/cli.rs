@@ -0,0 +1,488 @@
+//! End-to-end tests of the `ruloc` binary.
+//!
+//! Each test runs a real invocation against the fixture tree in `tests/fixtures` and checks
+//! stdout, stderr, and the exit code, so that flag renames and output format changes are
+//! caught here rather than by downstream scripts. Expected statistics live in
+//! `tests/golden`; update them deliberately when a classification change is intended.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use serde_json::Value;
+
+/// Fixture crate with production code, a test module, and one long function.
+const FIXTURE_DIR: &str = "tests/fixtures/basic";
+
+/// Single fixture file mixing production and test code.
+const FIXTURE_FILE: &str = "tests/fixtures/basic/src/lib.rs";
+
+/// Fixture file declaring tests through `cfg_attr`, `test_case`, and `tokio::test`.
+const ATTRIBUTES_FIXTURE_FILE: &str = "tests/fixtures/attributes/src/lib.rs";
+
+/// Builds a `ruloc` command running from the crate root, so fixture paths are stable.
+fn ruloc() -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_ruloc"));
+    command.current_dir(env!("CARGO_MANIFEST_DIR"));
+    command
+}
+
+/// Loads the golden statistics for the fixture directory.
+fn golden() -> Value {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/basic.json");
+    serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+/// Runs a JSON analysis of the fixture directory and parses the report.
+fn json_report() -> Value {
+    let output = ruloc()
+        .args(["--dir", FIXTURE_DIR, "--out-json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    serde_json::from_slice(&output).unwrap()
+}
+
+#[test]
+fn json_directory_report_matches_golden() {
+    let report = json_report();
+    let golden = golden();
+
+    for section in ["total", "production", "test"] {
+        assert_eq!(
+            report["summary"][section], golden[section],
+            "summary.{} differs from tests/golden/basic.json",
+            section
+        );
+    }
+
+    let paths: Vec<&Value> = report["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|file| &file["path"])
+        .collect();
+    let expected: Vec<&Value> = golden["files"].as_array().unwrap().iter().collect();
+    assert_eq!(paths, expected);
+}
+
+#[test]
+fn text_file_report_prints_table() {
+    ruloc()
+        .args(["--file", FIXTURE_FILE])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("File "))
+        .stdout(predicate::str::contains("\nlib.rs "))
+        .stdout(predicate::str::contains("72.7%"))
+        .stdout(predicate::str::contains("Total (1 files)"))
+        .stdout(predicate::str::contains("\nSummary:").not())
+        .stdout(predicate::str::contains("\x1b[").not());
+}
+
+#[test]
+fn text_table_lists_requested_analyses() {
+    ruloc()
+        .args([
+            "--file",
+            FIXTURE_FILE,
+            "--max-fn-lines",
+            "2",
+            "--complexity",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("File "))
+        .stdout(predicate::str::contains(
+            "\nSummary:\n  Complexity: 2 functions, mean 1.0, max 1\n  Long functions: 2\n",
+        ))
+        .stdout(predicate::str::contains(
+            "\nFiles:\n  lib.rs:\n    Long functions:\n      add (lines 2-5, 4 lines)\n",
+        ));
+}
+
+#[test]
+fn verbose_text_file_report_prints_summary() {
+    ruloc()
+        .args(["--file", FIXTURE_FILE, "--out-text-verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("Summary:\n  Files: 1\n"))
+        .stdout(predicate::str::contains("\n  lib.rs:\n"))
+        .stdout(predicate::str::contains("Code lines: 8"));
+}
+
+#[test]
+fn absolute_paths_are_reported_on_request() {
+    let output = ruloc()
+        .args(["--dir", FIXTURE_DIR, "--out-json", "--absolute-paths"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: Value = serde_json::from_slice(&output).unwrap();
+    let expected = format!(
+        "{}/{}",
+        env!("CARGO_MANIFEST_DIR").replace('\\', "/"),
+        FIXTURE_FILE
+    );
+    assert_eq!(report["files"][0]["path"], expected.as_str());
+}
+
+#[test]
+fn metadata_is_embedded_only_on_request() {
+    assert!(json_report().get("metadata").is_none());
+
+    let output = ruloc()
+        .args(["--dir", FIXTURE_DIR, "--out-json", "--with-metadata"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: Value = serde_json::from_slice(&output).unwrap();
+    let metadata = &report["metadata"];
+    assert_eq!(metadata["tool-version"], env!("CARGO_PKG_VERSION"));
+    assert!(metadata["timestamp"].as_str().unwrap().ends_with('Z'));
+    assert!(metadata["duration-ms"].is_u64());
+    assert_eq!(
+        metadata["args"],
+        serde_json::json!(["--dir", FIXTURE_DIR, "--out-json", "--with-metadata"])
+    );
+}
+
+#[test]
+fn timings_are_printed_to_stderr() {
+    ruloc()
+        .args(["--dir", FIXTURE_DIR, "--out-json", "--timings"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("{"))
+        .stderr(predicate::str::contains("Timings (wall "))
+        .stderr(predicate::str::contains("  parse "))
+        .stderr(predicate::str::contains("Slowest files:"));
+}
+
+#[test]
+fn debug_mode_marks_every_line() {
+    let output = ruloc()
+        .args(["--file", FIXTURE_FILE, "--debug", "--no-color"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+
+    let markers: Vec<&str> = stdout
+        .lines()
+        .filter_map(|line| line.get(..3))
+        .filter(|prefix| prefix.starts_with('P') || prefix.starts_with('T'))
+        .collect();
+    assert_eq!(
+        markers,
+        [
+            "PDC", "PCO", "PCM", "PCO", "PCO", "TBL", "TCO", "TCO", "TCO", "TBL", "TCO", "TCO",
+            "TCO", "TCO", "TCO",
+        ]
+    );
+}
+
+#[test]
+fn debug_mode_prints_json_records() {
+    let output = ruloc()
+        .args(["--dir", FIXTURE_DIR, "--debug", "--out-json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let stdout = String::from_utf8(output).unwrap();
+
+    let records: Vec<Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 2);
+    let lib = records
+        .iter()
+        .find(|record| record["path"] == FIXTURE_FILE)
+        .unwrap();
+    assert_eq!(
+        lib["lines"][0],
+        serde_json::json!({"line": 1, "type": "rustdoc", "is-test": false})
+    );
+    assert_eq!(lib["lines"][6]["is-test"], true);
+}
+
+#[test]
+fn failing_threshold_exits_with_error() {
+    ruloc()
+        .args([
+            "--dir",
+            FIXTURE_DIR,
+            "--max-fn-lines",
+            "3",
+            "--fail-on-long-fns",
+        ])
+        .assert()
+        .failure()
+        .code(1)
+        .stderr(predicate::str::contains("exceed the maximum of 3 lines"));
+}
+
+#[test]
+fn passing_threshold_exits_successfully() {
+    ruloc()
+        .args([
+            "--dir",
+            FIXTURE_DIR,
+            "--max-fn-lines",
+            "100",
+            "--fail-on-long-fns",
+        ])
+        .assert()
+        .success();
+}
+
+#[test]
+fn conflicting_flags_are_rejected() {
+    ruloc()
+        .args(["--file", FIXTURE_FILE, "--debug", "--out-text-verbose"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("cannot be used with"));
+}
+
+#[test]
+fn missing_input_is_reported() {
+    ruloc()
+        .args(["--file", "tests/fixtures/missing.rs"])
+        .assert()
+        .failure()
+        .code(3)
+        .stderr(predicate::str::starts_with("Error: Failed to read file"))
+        .stderr(predicate::str::contains("tests/fixtures/missing.rs"));
+}
+
+#[test]
+fn github_summary_is_appended_with_annotations() {
+    let summary = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(summary.path(), "## Earlier step\n").unwrap();
+
+    ruloc()
+        .args([
+            "--dir",
+            FIXTURE_DIR,
+            "--max-fn-lines",
+            "3",
+            "--out-github-summary",
+            "--github-summary-files",
+        ])
+        .env("GITHUB_STEP_SUMMARY", summary.path())
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(
+            "::warning file=tests/fixtures/basic/src/lib.rs,line=",
+        ));
+
+    let markdown = std::fs::read_to_string(summary.path()).unwrap();
+    assert!(markdown.starts_with("## Earlier step\n## ruloc\n"));
+    assert!(markdown.contains("<details><summary>Files (2)</summary>"));
+}
+
+#[test]
+fn sarif_log_reports_long_functions() {
+    let sarif = tempfile::NamedTempFile::new().unwrap();
+
+    ruloc()
+        .args(["--dir", FIXTURE_DIR, "--max-fn-lines", "3", "--out-sarif"])
+        .arg(sarif.path())
+        .assert()
+        .success();
+
+    let log: Value = serde_json::from_slice(&std::fs::read(sarif.path()).unwrap()).unwrap();
+    assert_eq!(log["version"], "2.1.0");
+    let result = &log["runs"][0]["results"][0];
+    assert_eq!(result["ruleId"], "long-function");
+    assert_eq!(
+        result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+        FIXTURE_FILE
+    );
+}
+
+#[test]
+fn text_table_lists_panic_sites() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("lib.rs");
+    std::fs::write(&path, "fn main() {\n    run().unwrap();\n}\n").unwrap();
+
+    ruloc()
+        .arg("--file")
+        .arg(&path)
+        .arg("--list-panics")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "\nSummary:\n  Panic sites: production 1 (unwrap 1,",
+        ))
+        .stdout(predicate::str::contains(
+            "lib.rs:\n    Panic sites:\n      2: run().unwrap();\n",
+        ));
+}
+
+#[test]
+fn text_table_lists_duplicate_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = "fn main() {\n    let total = 1 + 2;\n}\n";
+    std::fs::write(dir.path().join("a.rs"), source).unwrap();
+    std::fs::write(dir.path().join("b.rs"), source).unwrap();
+
+    ruloc()
+        .arg("--dir")
+        .arg(dir.path())
+        .arg("--duplicates")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "\nSummary:\n  Duplicates:\n    2 files, identical:\n      a.rs (3 lines)\n      b.rs (3 lines)\n",
+        ));
+}
+
+#[test]
+fn text_table_lists_other_languages() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+    std::fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\n# comment\nname = \"x\"\n",
+    )
+    .unwrap();
+
+    ruloc()
+        .arg("--dir")
+        .arg(dir.path())
+        .arg("--all-languages")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "\nSummary:\n  Languages:\n    Rust: 1 files, 1 lines (1 code, 0 comment, 0 blank)\n    TOML: 1 files, 3 lines (2 code, 1 comment, 0 blank)\n    Total: 2 files, 4 lines (3 code, 1 comment, 0 blank)\n",
+        ));
+}
+
+#[test]
+fn output_flag_writes_report_to_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("report.json");
+    std::fs::write(&path, "stale").unwrap();
+
+    ruloc()
+        .args(["--dir", FIXTURE_DIR, "--out-json", "--output"])
+        .arg(&path)
+        .assert()
+        .success()
+        .stdout(predicate::str::is_empty());
+
+    let report: Value = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    assert_eq!(report["summary"]["total"], golden()["total"]);
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    ruloc()
+        .args(["--file", FIXTURE_FILE, "--output", "-"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("File "));
+}
+
+#[test]
+fn several_formats_are_written_in_one_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let json = dir.path().join("report.json");
+    let html = dir.path().join("report.html");
+
+    ruloc()
+        .args(["--dir", FIXTURE_DIR, "--out-text", "--out-json"])
+        .arg(&json)
+        .arg("--out-html")
+        .arg(&html)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("File "));
+
+    let report: Value = serde_json::from_slice(&std::fs::read(&json).unwrap()).unwrap();
+    assert_eq!(report["summary"]["total"], golden()["total"]);
+    let page = std::fs::read_to_string(&html).unwrap();
+    assert!(page.contains("<tr><td>src/lib.rs</td>"));
+
+    ruloc()
+        .args(["--dir", FIXTURE_DIR, "--out-json", "--out-html"])
+        .assert()
+        .failure()
+        .code(2)
+        .stderr(predicate::str::contains("Several output formats"));
+}
+
+#[test]
+fn full_sample_extrapolates_exact_totals() {
+    let output = ruloc()
+        .args(["--dir", FIXTURE_DIR, "--out-json", "--sample", "100%"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: Value = serde_json::from_slice(&output).unwrap();
+    let sample = &report["sample"];
+    assert_eq!(sample["population-files"], 2);
+    assert_eq!(sample["sampled-files"], 2);
+    let code = &sample["estimates"][1];
+    assert_eq!(code["metric"], "code-lines");
+    assert_eq!(code["value"], golden()["total"]["code-lines"]);
+    assert_eq!(code["margin"], 0);
+
+    ruloc()
+        .args(["--dir", FIXTURE_DIR, "--sample", "1", "--sample-seed", "7"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Total (1 files)"))
+        .stdout(predicate::str::contains("Sampled 1 of 2 files (seed 7)"));
+}
+
+#[test]
+fn dry_run_reports_discovered_files() {
+    ruloc()
+        .args(["--dir", FIXTURE_DIR, "--dry-run", "--max-file-size", "150"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "Dry run of tests/fixtures/basic:\n  Files to analyze: 1 (",
+        ))
+        .stdout(predicate::str::contains(
+            "Skipped by --max-file-size:\n    src/lib.rs (",
+        ));
+
+    ruloc()
+        .args(["--dir", FIXTURE_DIR, "--dry-run", "--max-files", "1"])
+        .assert()
+        .failure()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "Limit exceeded: More than 1 files",
+        ));
+}
+
+#[test]
+fn test_attribute_macros_are_counted_as_test_code() {
+    let output = ruloc()
+        .args(["--file", ATTRIBUTES_FIXTURE_FILE, "--out-json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(report["summary"]["production"]["code-lines"], 5);
+    assert_eq!(report["summary"]["test"]["code-lines"], 13);
+}
@@ -0,0 +1,24 @@
+//! Tests declared through attributes other than a plain `#[test]`.
+
+pub fn double(x: u32) -> u32 {
+    x * 2
+}
+
+#[cfg_attr(feature = "slow-tests", test)]
+fn doubles_slowly() {
+    assert_eq!(double(2), 4);
+}
+
+#[test_case::test_case(1, 2)]
+#[test_case::test_case(2, 4)]
+fn doubles(x: u32, expected: u32) {
+    assert_eq!(double(x), expected);
+}
+
+#[tokio::test]
+async fn doubles_async() {
+    assert_eq!(double(3), 6);
+}
+
+#[cfg(not(test))]
+pub fn release_only() {}
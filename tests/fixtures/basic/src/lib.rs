@@ -0,0 +1,15 @@
+/// Adds two numbers.
+pub fn add(a: i32, b: i32) -> i32 {
+    // plain sum
+    a + b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds() {
+        assert_eq!(add(1, 2), 3);
+    }
+}
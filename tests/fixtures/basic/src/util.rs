@@ -0,0 +1,6 @@
+pub fn long_function() {
+    let a = 1;
+    let b = 2;
+    let c = a + b;
+    println!("{}", c);
+}